@@ -0,0 +1,441 @@
+//! Typed clients for nylon-ring plugins, generated at build time.
+//!
+//! A [`PluginDescriptor`] lists a plugin's entries with the Rust types of
+//! their requests and responses. [`generate`] turns it into a module with
+//! one struct holding a [`PluginHandle`], and one async method per entry
+//! that calls it through `call_typed`, or `call_typed_stream` for streaming
+//! entries. The handle is looked up once, when the client is made, instead
+//! of on every call.
+//!
+//! From a build script, [`generate_file`] reads the descriptor from a JSON
+//! file and writes the module, usually into `OUT_DIR`, to be `include!`d:
+//!
+//! ```no_run
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("users.rs");
+//! println!("cargo:rerun-if-changed=users.json");
+//! nylon_ring_codegen::generate_file("users.json", out).unwrap();
+//! ```
+//!
+//! The types are written as given, so paths in the descriptor must resolve
+//! where the module is included. The host needs its `serde` feature.
+//!
+//! The output depends only on the descriptor: entries are sorted by method
+//! name, so regenerating gives the same file byte for byte, and
+//! [`generate_file`] leaves the file alone if it would not change.
+//!
+//! [`PluginHandle`]: https://docs.rs/nylon-ring-host/latest/nylon_ring_host/struct.PluginHandle.html
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors from reading a descriptor or generating a client.
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error("failed to read descriptor {path}: {source}")]
+    ReadDescriptor {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("invalid descriptor: {0}")]
+    InvalidDescriptor(#[source] serde_json::Error),
+
+    #[error("failed to write generated client {path}: {source}")]
+    WriteClient {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{0:?} is not a valid Rust type name for the client")]
+    InvalidClientName(String),
+
+    #[error("entry {entry:?}: {reason}")]
+    InvalidEntry { entry: String, reason: String },
+
+    /// Two entries, or an entry and one of the client's own methods, map to
+    /// the same method name.
+    #[error("entries {first:?} and {second:?} both map to method `{method}`")]
+    MethodClash {
+        first: String,
+        second: String,
+        method: String,
+    },
+}
+
+/// A plugin's entries and the types they take and return.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginDescriptor {
+    /// The plugin's name, used in docs and for the client's name.
+    pub plugin: String,
+    /// The client struct's name. Defaults to the plugin's name in
+    /// `PascalCase` followed by `Plugin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub entries: Vec<EntryDescriptor>,
+}
+
+/// One entry of a [`PluginDescriptor`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntryDescriptor {
+    /// The entry's name, as the plugin registers it.
+    pub name: String,
+    pub kind: EntryKind,
+    /// The request type, as a Rust path.
+    pub request: String,
+    /// The response type, or the type of every frame of a stream.
+    pub response: String,
+    /// Doc comment for the generated method.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+}
+
+/// How an entry answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// One response, read with `call_typed`. The plugin decodes the request
+    /// with `nr_decode_request`.
+    Unary,
+    /// A stream of frames, read with `call_typed_stream`. The request is
+    /// plain JSON.
+    Stream,
+}
+
+impl PluginDescriptor {
+    /// Parse a descriptor from JSON.
+    pub fn from_json(json: &str) -> Result<Self, CodegenError> {
+        serde_json::from_str(json).map_err(CodegenError::InvalidDescriptor)
+    }
+}
+
+/// Methods every client has, which entries may not map to.
+const CLIENT_METHODS: [&str; 3] = ["new", "from_host", "handle"];
+
+/// Generate the client module for `descriptor`.
+pub fn generate(descriptor: &PluginDescriptor) -> Result<String, CodegenError> {
+    let client = match &descriptor.client {
+        Some(client) => client.clone(),
+        None => format!("{}Plugin", pascal_case(&descriptor.plugin)),
+    };
+    if !is_type_name(&client) {
+        return Err(CodegenError::InvalidClientName(client));
+    }
+
+    let mut methods = Vec::with_capacity(descriptor.entries.len());
+    for entry in &descriptor.entries {
+        for ty in [&entry.request, &entry.response] {
+            if !is_type(ty) {
+                return Err(CodegenError::InvalidEntry {
+                    entry: entry.name.clone(),
+                    reason: format!("{ty:?} is not a Rust type"),
+                });
+            }
+        }
+        let method = method_name(&entry.name).ok_or_else(|| CodegenError::InvalidEntry {
+            entry: entry.name.clone(),
+            reason: "no method name can be made from it".to_string(),
+        })?;
+        if let Some(taken) = CLIENT_METHODS.iter().find(|taken| **taken == method) {
+            return Err(CodegenError::MethodClash {
+                first: entry.name.clone(),
+                second: format!("{client}::{taken}"),
+                method,
+            });
+        }
+        methods.push((method, entry));
+    }
+    methods.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| x.name.cmp(&y.name)));
+    if let Some(pair) = methods.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(CodegenError::MethodClash {
+            first: pair[0].1.name.clone(),
+            second: pair[1].1.name.clone(),
+            method: pair[0].0.clone(),
+        });
+    }
+
+    let plugin = &descriptor.plugin;
+    let mut out = format!(
+        r#"// @generated by nylon-ring-codegen from the descriptor of the `{plugin}` plugin.
+// Do not edit; regenerate from the descriptor instead.
+
+/// Typed calls to the `{plugin}` plugin.
+#[derive(Clone)]
+pub struct {client} {{
+    handle: ::nylon_ring_host::PluginHandle,
+}}
+
+// Callers use only some of the entries.
+#[allow(dead_code)]
+impl {client} {{
+    /// Wrap the handle of a loaded `{plugin}` plugin.
+    pub fn new(handle: ::nylon_ring_host::PluginHandle) -> Self {{
+        Self {{ handle }}
+    }}
+
+    /// The plugin loaded into `host` as `name`, if any.
+    pub fn from_host(
+        host: &::nylon_ring_host::NylonRingHost,
+        name: &str,
+    ) -> ::std::option::Option<Self> {{
+        host.plugin(name).map(Self::new)
+    }}
+
+    /// The handle calls go through.
+    pub fn handle(&self) -> &::nylon_ring_host::PluginHandle {{
+        &self.handle
+    }}
+"#
+    );
+    for (method, entry) in &methods {
+        let _ = writeln!(out);
+        match &entry.doc {
+            Some(doc) => {
+                for line in doc.lines() {
+                    let _ = writeln!(
+                        out,
+                        "    ///{}{line}",
+                        if line.is_empty() { "" } else { " " }
+                    );
+                }
+            }
+            None => {
+                let _ = writeln!(out, "    /// The `{}` entry.", entry.name);
+            }
+        }
+        let (response, call) = match entry.kind {
+            EntryKind::Unary => (entry.response.clone(), "call_typed"),
+            EntryKind::Stream => (
+                format!("::nylon_ring_host::TypedFrameStream<{}>", entry.response),
+                "call_typed_stream",
+            ),
+        };
+        let _ = writeln!(out, "    pub async fn {method}(");
+        let _ = writeln!(out, "        &self,");
+        let _ = writeln!(out, "        request: &{},", entry.request);
+        let _ = writeln!(
+            out,
+            "    ) -> ::std::result::Result<{response}, ::nylon_ring_host::NylonRingHostError> {{"
+        );
+        let _ = writeln!(
+            out,
+            "        self.handle.{call}({:?}, request).await",
+            entry.name
+        );
+        let _ = writeln!(out, "    }}");
+    }
+    let _ = writeln!(out, "}}");
+    Ok(out)
+}
+
+/// Read the descriptor at `descriptor`, generate its client and write it to
+/// `out`, unless `out` already holds exactly that. For build scripts.
+pub fn generate_file(
+    descriptor: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+) -> Result<(), CodegenError> {
+    let (descriptor, out) = (descriptor.as_ref(), out.as_ref());
+    let json =
+        std::fs::read_to_string(descriptor).map_err(|source| CodegenError::ReadDescriptor {
+            path: descriptor.display().to_string(),
+            source,
+        })?;
+    let code = generate(&PluginDescriptor::from_json(&json)?)?;
+    if std::fs::read_to_string(out).is_ok_and(|existing| existing == code) {
+        return Ok(());
+    }
+    std::fs::write(out, code).map_err(|source| CodegenError::WriteClient {
+        path: out.display().to_string(),
+        source,
+    })
+}
+
+/// Keywords that cannot be raw identifiers.
+const NOT_RAW: [&str; 5] = ["crate", "self", "Self", "super", "_"];
+
+const KEYWORDS: [&str; 50] = [
+    "abstract",
+    "as",
+    "async",
+    "await",
+    "become",
+    "box",
+    "break",
+    "const",
+    "continue",
+    "do",
+    "dyn",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "final",
+    "fn",
+    "for",
+    "gen",
+    "if",
+    "impl",
+    "in",
+    "let",
+    "loop",
+    "macro",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "override",
+    "priv",
+    "pub",
+    "ref",
+    "return",
+    "static",
+    "struct",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "union",
+    "unsafe",
+    "unsized",
+    "use",
+    "virtual",
+    "where",
+    "while",
+    "yield",
+    "macro_rules",
+];
+
+/// The method for the entry `name`: `snake_case`, with anything that cannot
+/// be in an identifier replaced by `_`.
+fn method_name(name: &str) -> Option<String> {
+    let mut method = String::with_capacity(name.len() + 2);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            method.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        method.push(if c.is_ascii_alphanumeric() {
+            c.to_ascii_lowercase()
+        } else {
+            '_'
+        });
+    }
+    if method.chars().all(|c| c == '_') {
+        return None;
+    }
+    if method.starts_with(|c: char| c.is_ascii_digit()) {
+        method.insert(0, '_');
+    }
+    if NOT_RAW.contains(&method.as_str()) {
+        method.push('_');
+    } else if KEYWORDS.contains(&method.as_str()) {
+        method.insert_str(0, "r#");
+    }
+    Some(method)
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+        && !NOT_RAW.contains(&name)
+}
+
+/// Whether `ty` looks like a type: it is written into the generated code
+/// as is, so anything that could end the signature is refused.
+fn is_type(ty: &str) -> bool {
+    !ty.trim().is_empty()
+        && ty.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(
+                    c,
+                    '_' | ':' | '<' | '>' | ',' | ' ' | '&' | '\'' | '[' | ']' | '(' | ')'
+                )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> EntryDescriptor {
+        EntryDescriptor {
+            name: name.to_string(),
+            kind: EntryKind::Unary,
+            request: "u32".to_string(),
+            response: "String".to_string(),
+            doc: None,
+        }
+    }
+
+    fn descriptor(entries: Vec<EntryDescriptor>) -> PluginDescriptor {
+        PluginDescriptor {
+            plugin: "user-store".to_string(),
+            client: None,
+            entries,
+        }
+    }
+
+    #[test]
+    fn test_method_names() {
+        assert_eq!(method_name("getUser").as_deref(), Some("get_user"));
+        assert_eq!(method_name("users.get").as_deref(), Some("users_get"));
+        assert_eq!(method_name("2fa").as_deref(), Some("_2fa"));
+        assert_eq!(method_name("type").as_deref(), Some("r#type"));
+        assert_eq!(method_name("self").as_deref(), Some("self_"));
+        assert_eq!(method_name("--"), None);
+    }
+
+    #[test]
+    fn test_output_does_not_depend_on_entry_order() {
+        let forward = descriptor(vec![entry("b"), entry("a")]);
+        let backward = descriptor(vec![entry("a"), entry("b")]);
+        let code = generate(&forward).unwrap();
+        assert_eq!(code, generate(&backward).unwrap());
+        assert!(code.contains("pub struct UserStorePlugin {"));
+        assert!(code.find("fn a(").unwrap() < code.find("fn b(").unwrap());
+    }
+
+    #[test]
+    fn test_clashing_entries_are_refused() {
+        let clash = descriptor(vec![entry("get-user"), entry("getUser")]);
+        assert!(matches!(
+            generate(&clash),
+            Err(CodegenError::MethodClash { method, .. }) if method == "get_user"
+        ));
+        let clash = descriptor(vec![entry("handle")]);
+        assert!(matches!(
+            generate(&clash),
+            Err(CodegenError::MethodClash { .. })
+        ));
+    }
+
+    #[test]
+    fn test_types_cannot_escape_the_signature() {
+        let mut bad = entry("get");
+        bad.response = "String { loop {} } fn x() -> u8".to_string();
+        assert!(matches!(
+            generate(&descriptor(vec![bad])),
+            Err(CodegenError::InvalidEntry { .. })
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+//! The client generated for the example plugin, from the descriptor the
+//! example host builds it from.
+
+use nylon_ring_codegen::{generate, generate_file, PluginDescriptor};
+use nylon_ring_host::{plugin_artifact_in, NylonRingHost, Profile};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+mod orders {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    pub struct Order {
+        pub id: u32,
+        pub items: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    pub struct Receipt {
+        pub id: u32,
+        pub count: usize,
+        pub items: Vec<String>,
+    }
+}
+
+mod example_plugin {
+    include!("generated/example_plugin.rs");
+}
+
+use example_plugin::ExamplePlugin;
+use orders::{Order, Receipt};
+
+// The example plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const DESCRIPTOR: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../examples/ex-nyring-host/plugin.json"
+);
+const GENERATED: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/generated/example_plugin.rs"
+);
+
+/// The example plugin, built into a target directory of its own so the
+/// build does not wait on the lock held by the running `cargo test`.
+fn example_plugin() -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| {
+        let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("example-plugin");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet", "-p", "ex-nyring-plugin", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "failed to build the example plugin");
+        plugin_artifact_in(&target_dir, "ex-nyring-plugin", Profile::Debug).unwrap()
+    })
+}
+
+fn client(host: &mut NylonRingHost) -> ExamplePlugin {
+    host.load("example", example_plugin().to_str().unwrap())
+        .unwrap();
+    ExamplePlugin::from_host(host, "example").unwrap()
+}
+
+fn order() -> Order {
+    Order {
+        id: 7,
+        items: vec!["tea".into(), "bread".into()],
+    }
+}
+
+#[test]
+fn test_checked_in_client_matches_the_descriptor() {
+    let json = std::fs::read_to_string(DESCRIPTOR).unwrap();
+    let code = generate(&PluginDescriptor::from_json(&json).unwrap()).unwrap();
+    if std::env::var_os("NYLON_RING_CODEGEN_BLESS").is_some() {
+        std::fs::write(GENERATED, &code).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(GENERATED).unwrap();
+    assert!(
+        code == checked_in,
+        "tests/generated/example_plugin.rs is stale; rerun with NYLON_RING_CODEGEN_BLESS=1"
+    );
+}
+
+#[test]
+fn test_generate_file_leaves_an_up_to_date_file_alone() {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("example_plugin.rs");
+    let _ = std::fs::remove_file(&out);
+    generate_file(DESCRIPTOR, &out).unwrap();
+    let written = std::fs::metadata(&out).unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    generate_file(DESCRIPTOR, &out).unwrap();
+    assert_eq!(
+        std::fs::metadata(&out).unwrap().modified().unwrap(),
+        written
+    );
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap(),
+        std::fs::read_to_string(GENERATED).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_unary_method_calls_the_entry() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    let plugin = client(&mut host);
+
+    let receipt = plugin.order(&order()).await.unwrap();
+    assert_eq!(
+        receipt,
+        Receipt {
+            id: 7,
+            count: 2,
+            items: vec!["bread".into(), "tea".into()],
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_stream_method_yields_typed_frames() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    let plugin = client(&mut host);
+
+    let mut items = plugin.order_items(&order()).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(item) = items.next().await {
+        received.push(item.unwrap());
+    }
+    assert_eq!(received, ["bread", "tea"]);
+}
+
+#[tokio::test]
+async fn test_client_entries_are_provided_by_the_plugin() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    let plugin = client(&mut host);
+
+    let json = std::fs::read_to_string(DESCRIPTOR).unwrap();
+    let descriptor = PluginDescriptor::from_json(&json).unwrap();
+    let provided = plugin.handle().provided_entries().unwrap();
+    for entry in &descriptor.entries {
+        assert!(provided.contains(&entry.name), "{} is missing", entry.name);
+    }
+}
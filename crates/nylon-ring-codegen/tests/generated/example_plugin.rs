@@ -0,0 +1,46 @@
+// @generated by nylon-ring-codegen from the descriptor of the `example` plugin.
+// Do not edit; regenerate from the descriptor instead.
+
+/// Typed calls to the `example` plugin.
+#[derive(Clone)]
+pub struct ExamplePlugin {
+    handle: ::nylon_ring_host::PluginHandle,
+}
+
+// Callers use only some of the entries.
+#[allow(dead_code)]
+impl ExamplePlugin {
+    /// Wrap the handle of a loaded `example` plugin.
+    pub fn new(handle: ::nylon_ring_host::PluginHandle) -> Self {
+        Self { handle }
+    }
+
+    /// The plugin loaded into `host` as `name`, if any.
+    pub fn from_host(
+        host: &::nylon_ring_host::NylonRingHost,
+        name: &str,
+    ) -> ::std::option::Option<Self> {
+        host.plugin(name).map(Self::new)
+    }
+
+    /// The handle calls go through.
+    pub fn handle(&self) -> &::nylon_ring_host::PluginHandle {
+        &self.handle
+    }
+
+    /// Answer an order with a receipt listing its items sorted.
+    pub async fn order(
+        &self,
+        request: &crate::orders::Order,
+    ) -> ::std::result::Result<crate::orders::Receipt, ::nylon_ring_host::NylonRingHostError> {
+        self.handle.call_typed("order", request).await
+    }
+
+    /// Stream the items of an order, sorted, one per frame.
+    pub async fn order_items(
+        &self,
+        request: &crate::orders::Order,
+    ) -> ::std::result::Result<::nylon_ring_host::TypedFrameStream<String>, ::nylon_ring_host::NylonRingHostError> {
+        self.handle.call_typed_stream("order_items", request).await
+    }
+}
@@ -1,18 +1,9 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use nylon_ring_host::{NylonRingHost, PluginHandle};
+use nylon_ring_host::{
+    plugin_artifact_path, NotifierOptions, NylonRingHost, PluginHandle, Profile,
+};
 use std::hint::black_box;
 
-fn get_plugin_path() -> String {
-    #[cfg(target_os = "macos")]
-    let path = "target/release/libex_nyring_plugin.dylib";
-    #[cfg(target_os = "windows")]
-    let path = "target/release/ex_nyring_plugin.dll";
-    #[cfg(target_os = "linux")]
-    let path = "target/release/libex_nyring_plugin.so";
-
-    path.to_string()
-}
-
 fn setup_host() -> (NylonRingHost, PluginHandle) {
     // Get the workspace root directory
     let workspace_root = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -34,7 +25,8 @@ fn setup_host() -> (NylonRingHost, PluginHandle) {
         .status()
         .expect("Failed to build plugin");
 
-    let plugin_path = workspace_root.join(get_plugin_path());
+    let plugin_path = plugin_artifact_path("ex-nyring-plugin", Profile::Release)
+        .expect("Plugin library not found");
     let mut host = NylonRingHost::new();
     host.load("default", plugin_path.to_str().unwrap())
         .expect("Failed to load plugin");
@@ -86,6 +78,41 @@ fn bench_call_response_with_payload(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_large_payload(c: &mut Criterion) {
+    const SIZE: usize = 8 << 20;
+    let (_host, plugin) = setup_host();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let payload = vec![42u8; SIZE];
+
+    let mut group = c.benchmark_group("large_payload_8mb");
+    group.throughput(criterion::Throughput::Bytes(SIZE as u64));
+
+    // The plugin reverses the body into a new vector, which the caller frees.
+    group.bench_function("call_response", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let result = plugin.call_response("mirror", black_box(&payload)).await;
+                black_box(result).unwrap();
+            })
+        })
+    });
+
+    // The plugin reverses the body into a pooled host buffer, which the
+    // caller hands back for the next call.
+    group.bench_function("call_response_shared", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let result = plugin
+                    .call_response_shared("mirror", black_box(&payload))
+                    .await;
+                black_box(result).unwrap();
+            })
+        })
+    });
+
+    group.finish();
+}
+
 fn bench_call_response_fast(c: &mut Criterion) {
     let (_host, plugin) = setup_host();
     let runtime = tokio::runtime::Runtime::new().unwrap();
@@ -130,11 +157,88 @@ fn bench_call_without_response(c: &mut Criterion) {
     group.finish();
 }
 
+/// Concurrent unary calls, which insert and remove pending entries from
+/// several threads at once. Run once with and once without the
+/// `slab-pending` feature to compare the two stores in one report.
+fn bench_pending_store(c: &mut Criterion) {
+    const CALLS: usize = 64;
+    let (_host, plugin) = setup_host();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let store = if cfg!(feature = "slab-pending") {
+        "slab"
+    } else {
+        "dashmap"
+    };
+
+    let mut group = c.benchmark_group("pending_store");
+    group.throughput(criterion::Throughput::Elements(CALLS as u64));
+
+    group.bench_function(store, |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let calls: Vec<_> = (0..CALLS)
+                    .map(|_| {
+                        let plugin = plugin.clone();
+                        tokio::spawn(async move { plugin.call_response("echo", b"").await })
+                    })
+                    .collect();
+                for call in calls {
+                    black_box(call.await.unwrap()).unwrap();
+                }
+            })
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_notifier(c: &mut Criterion) {
+    const EVENTS: usize = 1000;
+    let (_host, plugin) = setup_host();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("fire_and_forget_events");
+    group.throughput(criterion::Throughput::Elements(EVENTS as u64));
+
+    group.bench_function("per_event", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for _ in 0..EVENTS {
+                    let result = plugin.call("benchmark_batch", black_box(b"event")).await;
+                    black_box(result).unwrap();
+                }
+            })
+        })
+    });
+
+    let notifier = runtime.block_on(async {
+        plugin.notifier(
+            "benchmark_batch",
+            NotifierOptions {
+                max_events: EVENTS,
+                ..NotifierOptions::default()
+            },
+        )
+    });
+    group.bench_function("batched", |b| {
+        b.iter(|| {
+            for _ in 0..EVENTS {
+                notifier.push(black_box(b"event")).unwrap();
+            }
+        })
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_call_response,
     bench_call_response_with_payload,
+    bench_large_payload,
     bench_call_response_fast,
-    bench_call_without_response
+    bench_call_without_response,
+    bench_pending_store,
+    bench_notifier
 );
 criterion_main!(benches);
@@ -0,0 +1,167 @@
+//! What a loaded plugin provides, and the ABI it was loaded at.
+//!
+//! Captured once at load time, so a host can tell why a call such as
+//! `send_stream_data` is refused without reading the plugin's source.
+
+use nylon_ring::NR_ABI_VERSION;
+use std::fmt;
+
+/// The slots of a plugin's function table, in table order. WebAssembly
+/// plugins export them with an `nr_` prefix.
+pub(crate) const PLUGIN_FUNCTIONS: [&str; 7] = [
+    "init",
+    "handle",
+    "shutdown",
+    "stream_data",
+    "stream_close",
+    "health",
+    "handle_req",
+];
+
+/// Host callbacks, with the ABI version that added each.
+const HOST_VTABLE: &[(&str, u32)] = &[
+    ("send_result", 1),
+    ("get_host_ext", 2),
+    ("send_result_channel", 2),
+];
+
+const HOST_EXT: &[(&str, u32)] = &[
+    ("set_state", 1),
+    ("get_state", 1),
+    ("report_panic", 2),
+    ("dispatch_spawn", 2),
+    ("take_dispatch_result", 2),
+    ("get_state_into", 2),
+    ("is_revoked", 2),
+    ("complete_later", 2),
+    ("log", 3),
+    ("buf_acquire", 4),
+    ("buf_commit", 4),
+    ("set_state_scoped", 5),
+    ("get_state_scoped_into", 5),
+    ("send_result_seq", 7),
+];
+
+/// The ABI a plugin was loaded at and which of its optional functions it
+/// provides, as returned by
+/// [`PluginHandle::abi_details`](crate::PluginHandle::abi_details).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiDetails {
+    /// The ABI version the plugin was built against. WebAssembly plugins
+    /// are served as version 1.
+    pub abi_version: u32,
+    /// The newest ABI version this host supports.
+    pub host_abi_version: u32,
+    pub wasm: bool,
+    /// Every slot of the plugin's function table, in table order, with
+    /// whether the plugin filled it.
+    pub plugin_functions: Vec<(&'static str, bool)>,
+    /// The host tables the plugin can call into, with the callbacks in each
+    /// that exist at the plugin's ABI version.
+    pub host_tables: Vec<HostTable>,
+}
+
+/// Callbacks of one host table a plugin can rely on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostTable {
+    /// `NrHostVTable`, `NrHostExt`, or `env` for WebAssembly imports.
+    pub name: &'static str,
+    pub functions: Vec<&'static str>,
+}
+
+impl AbiDetails {
+    pub(crate) fn native(abi_version: u32, provided: [bool; 7]) -> Self {
+        let at_version = |table: &[(&'static str, u32)]| {
+            table
+                .iter()
+                .filter(|(_, since)| *since <= abi_version)
+                .map(|(name, _)| *name)
+                .collect()
+        };
+        Self {
+            abi_version,
+            host_abi_version: NR_ABI_VERSION,
+            wasm: false,
+            plugin_functions: PLUGIN_FUNCTIONS.into_iter().zip(provided).collect(),
+            host_tables: vec![
+                HostTable {
+                    name: "NrHostVTable",
+                    functions: at_version(HOST_VTABLE),
+                },
+                HostTable {
+                    name: "NrHostExt",
+                    functions: at_version(HOST_EXT),
+                },
+            ],
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    pub(crate) fn wasm(provided: [bool; 7]) -> Self {
+        Self {
+            abi_version: 1,
+            host_abi_version: NR_ABI_VERSION,
+            wasm: true,
+            plugin_functions: PLUGIN_FUNCTIONS.into_iter().zip(provided).collect(),
+            host_tables: vec![HostTable {
+                name: "env",
+                functions: vec!["nr_send_result"],
+            }],
+        }
+    }
+
+    /// Whether the plugin filled the slot `function`, such as
+    /// `"stream_data"`.
+    pub fn provides(&self, function: &str) -> bool {
+        self.plugin_functions
+            .iter()
+            .any(|(name, provided)| *name == function && *provided)
+    }
+}
+
+impl fmt::Display for AbiDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.wasm { "wasm" } else { "native" };
+        writeln!(
+            f,
+            "abi: v{} ({kind}, host supports up to v{})",
+            self.abi_version, self.host_abi_version
+        )?;
+        let list = |provided: bool| {
+            self.plugin_functions
+                .iter()
+                .filter(|(_, p)| *p == provided)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "provides: {}", list(true))?;
+        writeln!(f, "missing: {}", list(false))?;
+        for (i, table) in self.host_tables.iter().enumerate() {
+            write!(f, "{}: {}", table.name, table.functions.join(", "))?;
+            if i + 1 < self.host_tables.len() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_tables_follow_abi_version() {
+        let v1 = AbiDetails::native(1, [true, true, false, false, false, false, false]);
+        assert_eq!(v1.host_tables[0].functions, ["send_result"]);
+        assert_eq!(v1.host_tables[1].functions, ["set_state", "get_state"]);
+
+        let current = AbiDetails::native(NR_ABI_VERSION, [true; 7]);
+        assert_eq!(current.host_tables[0].functions.len(), HOST_VTABLE.len());
+        assert_eq!(current.host_tables[1].functions.len(), HOST_EXT.len());
+        assert!(current.provides("stream_close"));
+        assert!(!v1.provides("stream_close"));
+        assert!(!v1.provides("no_such_slot"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Finding plugin libraries Cargo built, for tests, benches and examples.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+
+/// A Cargo build profile, which names the directory artifacts end up in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Debug,
+    Release,
+}
+
+impl Profile {
+    /// The profile the running code was built with, as far as
+    /// `debug_assertions` tell: `Release` for benches and `--release`.
+    pub fn current() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Debug
+        } else {
+            Self::Release
+        }
+    }
+
+    fn dir(self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Release => "release",
+        }
+    }
+}
+
+/// The file name of the library Cargo builds for the cdylib crate
+/// `crate_name` on this platform: `libfoo_bar.so`, `libfoo_bar.dylib` or
+/// `foo_bar.dll` for `foo-bar`.
+pub fn plugin_file_name(crate_name: &str) -> String {
+    format!("{DLL_PREFIX}{}{DLL_SUFFIX}", crate_name.replace('-', "_"))
+}
+
+/// The library Cargo built for the cdylib crate `crate_name` under
+/// `profile`.
+///
+/// The target directory is `CARGO_TARGET_DIR` when set. Otherwise it is the
+/// `target` directory of the package or the workspace: every ancestor of
+/// `CARGO_MANIFEST_DIR` (set by `cargo run`, `cargo test` and
+/// `cargo bench`) and of the current directory is tried, nearest first.
+/// Under `CARGO_BUILD_TARGET`, the target triple's directory is tried
+/// before the host's. Fails with
+/// [`NylonRingHostError::PluginArtifactNotFound`], listing every path
+/// tried, if none exists.
+///
+/// ```no_run
+/// use nylon_ring_host::{plugin_artifact_path, NylonRingHost, Profile};
+///
+/// # fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+/// let path = plugin_artifact_path("ex-nyring-plugin", Profile::Release)?;
+/// let mut host = NylonRingHost::new();
+/// host.load("example", path.to_str().unwrap())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn plugin_artifact_path(crate_name: &str, profile: Profile) -> Result<PathBuf> {
+    let target_dirs = match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) => vec![PathBuf::from(dir)],
+        None => {
+            let starts = std::env::var_os("CARGO_MANIFEST_DIR")
+                .map(PathBuf::from)
+                .into_iter()
+                .chain(std::env::current_dir().ok());
+            let mut dirs = Vec::new();
+            for start in starts {
+                for dir in start.ancestors().map(|dir| dir.join("target")) {
+                    if !dirs.contains(&dir) {
+                        dirs.push(dir);
+                    }
+                }
+            }
+            dirs
+        }
+    };
+    let triple = std::env::var("CARGO_BUILD_TARGET").ok();
+    find(crate_name, &target_dirs, triple.as_deref(), profile)
+}
+
+/// Like [`plugin_artifact_path`], in the target directory `target_dir`.
+pub fn plugin_artifact_in(
+    target_dir: &Path,
+    crate_name: &str,
+    profile: Profile,
+) -> Result<PathBuf> {
+    find(crate_name, &[target_dir.to_path_buf()], None, profile)
+}
+
+fn find(
+    crate_name: &str,
+    target_dirs: &[PathBuf],
+    triple: Option<&str>,
+    profile: Profile,
+) -> Result<PathBuf> {
+    let file_name = plugin_file_name(crate_name);
+    let mut tried = Vec::new();
+    for target_dir in target_dirs {
+        let profile_dirs = triple
+            .map(|triple| target_dir.join(triple).join(profile.dir()))
+            .into_iter()
+            .chain([target_dir.join(profile.dir())]);
+        for profile_dir in profile_dirs {
+            let path = profile_dir.join(&file_name);
+            if path.is_file() {
+                return Ok(path);
+            }
+            tried.push(path);
+        }
+    }
+    Err(NylonRingHostError::PluginArtifactNotFound {
+        crate_name: crate_name.to_string(),
+        tried,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_follows_the_platform() {
+        let name = plugin_file_name("ex-nyring-plugin");
+        assert!(name.starts_with(DLL_PREFIX));
+        assert!(name.ends_with(DLL_SUFFIX));
+        assert!(name.contains("ex_nyring_plugin"));
+    }
+
+    #[test]
+    fn test_nearest_existing_artifact_wins() {
+        let root = tempfile::tempdir().unwrap();
+        let (package, workspace) = (root.path().join("pkg/target"), root.path().join("target"));
+        let built = workspace.join("release").join(plugin_file_name("demo"));
+        std::fs::create_dir_all(built.parent().unwrap()).unwrap();
+        std::fs::write(&built, b"").unwrap();
+
+        let dirs = [package.clone(), workspace];
+        assert_eq!(find("demo", &dirs, None, Profile::Release).unwrap(), built);
+
+        let cross = package
+            .join("x86_64-pc-windows-gnu/release")
+            .join(plugin_file_name("demo"));
+        std::fs::create_dir_all(cross.parent().unwrap()).unwrap();
+        std::fs::write(&cross, b"").unwrap();
+        let triple = Some("x86_64-pc-windows-gnu");
+        assert_eq!(
+            find("demo", &dirs, triple, Profile::Release).unwrap(),
+            cross
+        );
+    }
+
+    #[test]
+    fn test_missing_artifact_lists_the_candidates() {
+        let root = tempfile::tempdir().unwrap();
+        let error = plugin_artifact_in(root.path(), "demo", Profile::Debug).unwrap_err();
+        let NylonRingHostError::PluginArtifactNotFound { crate_name, tried } = &error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!(crate_name, "demo");
+        assert_eq!(
+            tried,
+            &[root.path().join("debug").join(plugin_file_name("demo"))]
+        );
+        assert!(error.to_string().contains(&tried[0].display().to_string()));
+    }
+}
@@ -0,0 +1,128 @@
+//! How calls reach a loaded plugin.
+//!
+//! Native plugins are called through their vtable. WebAssembly plugins
+//! (behind the `wasm` feature) are adapted to the same calls, so everything
+//! above this module treats both alike.
+
+#[cfg(feature = "wasm")]
+use crate::wasm::WasmPlugin;
+use crate::HighLevelRequest;
+use nylon_ring::{NrBytes, NrKV, NrPluginVTable, NrRequest, NrStatus, NrStr};
+
+type HandleReqFn = unsafe extern "C" fn(
+    entry: NrStr,
+    sid: u64,
+    req: *const NrRequest,
+    payload: NrBytes,
+) -> NrStatus;
+
+pub(crate) enum Backend {
+    /// A shared library, or a plugin linked into the host binary.
+    Native {
+        vtable: &'static NrPluginVTable,
+        /// `vtable.health` for plugins of ABI version 6 or later, whose
+        /// vtables have it; `None` for older ones.
+        health: Option<unsafe extern "C" fn() -> NrStatus>,
+        /// `vtable.handle_req` for plugins of ABI version 8 or later.
+        handle_req: Option<HandleReqFn>,
+    },
+    #[cfg(feature = "wasm")]
+    Wasm(Box<WasmPlugin>),
+}
+
+impl Backend {
+    /// Start a call. A plugin without `handle` is rejected at load time, so
+    /// `Unsupported` is only a fallback.
+    pub(crate) fn handle(&self, entry: &str, sid: u64, payload: &[u8]) -> NrStatus {
+        match self {
+            Backend::Native { vtable, .. } => match vtable.handle {
+                Some(handle) => unsafe {
+                    handle(NrStr::new(entry), sid, NrBytes::from_slice(payload))
+                },
+                None => NrStatus::Unsupported,
+            },
+            #[cfg(feature = "wasm")]
+            Backend::Wasm(plugin) => plugin.handle(entry, sid, payload),
+        }
+    }
+
+    /// Call `entry` with `req`, through `handle_req`. WebAssembly plugins
+    /// and plugins without it get `Unsupported`.
+    pub(crate) fn handle_req(&self, entry: &str, sid: u64, req: &HighLevelRequest) -> NrStatus {
+        let Backend::Native {
+            handle_req: Some(handle_req),
+            ..
+        } = self
+        else {
+            return NrStatus::Unsupported;
+        };
+        let headers: Vec<NrKV> = req
+            .headers
+            .iter()
+            .map(|(name, value)| NrKV {
+                key: NrStr::new(name),
+                value: NrStr::new(value),
+            })
+            .collect();
+        let nr_req = NrRequest {
+            method: NrStr::new(&req.method),
+            path: NrStr::new(&req.path),
+            query: NrStr::new(&req.query),
+            headers: headers.as_ptr(),
+            headers_len: headers.len() as u32,
+        };
+        unsafe {
+            handle_req(
+                NrStr::new(entry),
+                sid,
+                &nr_req,
+                NrBytes::from_slice(&req.body),
+            )
+        }
+    }
+
+    /// Send data into an active stream. `None` if the plugin does not accept
+    /// stream data.
+    pub(crate) fn stream_data(&self, sid: u64, data: &[u8]) -> Option<NrStatus> {
+        match self {
+            Backend::Native { vtable, .. } => vtable
+                .stream_data
+                .map(|stream_data| unsafe { stream_data(sid, NrBytes::from_slice(data)) }),
+            #[cfg(feature = "wasm")]
+            Backend::Wasm(plugin) => plugin.stream_data(sid, data),
+        }
+    }
+
+    /// Close an active stream. `None` if the plugin does not support it.
+    pub(crate) fn stream_close(&self, sid: u64) -> Option<NrStatus> {
+        match self {
+            Backend::Native { vtable, .. } => vtable
+                .stream_close
+                .map(|stream_close| unsafe { stream_close(sid) }),
+            #[cfg(feature = "wasm")]
+            Backend::Wasm(plugin) => plugin.stream_close(sid),
+        }
+    }
+
+    /// Ask the plugin whether it is healthy. `None` if it has no health
+    /// check.
+    pub(crate) fn health(&self) -> Option<NrStatus> {
+        match self {
+            Backend::Native { health, .. } => health.map(|health| unsafe { health() }),
+            #[cfg(feature = "wasm")]
+            Backend::Wasm(plugin) => plugin.health(),
+        }
+    }
+
+    pub(crate) fn shutdown(&self) {
+        match self {
+            Backend::Native { vtable, .. } => {
+                if let Some(shutdown) = vtable.shutdown {
+                    unsafe { shutdown() }
+                }
+            }
+            #[cfg(feature = "wasm")]
+            Backend::Wasm(plugin) => plugin.shutdown(),
+        }
+    }
+}
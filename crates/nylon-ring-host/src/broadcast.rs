@@ -0,0 +1,111 @@
+//! Host-initiated broadcasts to every loaded plugin.
+//!
+//! Each plugin is called on its own task. A plugin whose `handle`
+//! returns `Invalid` does not implement the entry; that is reported as its
+//! status rather than as an error.
+
+use crate::error::NylonRingHostError;
+use crate::rt::{self, Instant};
+use crate::types::Result;
+use crate::{NylonRingHost, PluginHandle};
+use nylon_ring::NrStatus;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+impl NylonRingHost {
+    /// Fire-and-forget `entry` on every loaded plugin concurrently.
+    ///
+    /// Returns `(plugin, result)` per plugin, sorted by name.
+    pub async fn broadcast(&self, entry: &str, payload: &[u8]) -> Vec<(String, Result<NrStatus>)> {
+        let results = self
+            .each_plugin(entry, payload, |plugin, entry, payload| async move {
+                plugin.call(&entry, &payload).await
+            })
+            .await;
+        results
+            .into_iter()
+            .map(|(name, result)| match result {
+                Err(NylonRingHostError::PluginHandleFailed {
+                    status: NrStatus::Invalid,
+                    ..
+                }) => (name, Ok(NrStatus::Invalid)),
+                other => (name, other),
+            })
+            .collect()
+    }
+
+    /// Call `entry` on every loaded plugin concurrently and collect the
+    /// responses. All calls share one `timeout`; plugins that have not
+    /// answered by then get [`NylonRingHostError::Timeout`].
+    ///
+    /// Returns `(plugin, result)` per plugin, sorted by name. Plugins that do
+    /// not implement the entry report `(Invalid, [])`.
+    pub async fn broadcast_response(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Vec<(String, Result<(NrStatus, Vec<u8>)>)> {
+        let deadline = Instant::now() + timeout;
+        let results = self
+            .each_plugin(entry, payload, move |plugin, entry, payload| async move {
+                rt::timeout_at(deadline, plugin.call_response(&entry, &payload))
+                    .await
+                    .unwrap_or(Err(NylonRingHostError::Timeout(timeout)))
+            })
+            .await;
+        results
+            .into_iter()
+            .map(|(name, result)| match result {
+                Err(NylonRingHostError::PluginHandleFailed {
+                    status: NrStatus::Invalid,
+                    ..
+                }) => (name, Ok((NrStatus::Invalid, Vec::new()))),
+                other => (name, other),
+            })
+            .collect()
+    }
+
+    /// Spawn `f` for every plugin and wait for all of them.
+    async fn each_plugin<T, F, Fut>(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        f: F,
+    ) -> Vec<(String, Result<T>)>
+    where
+        T: Send + 'static,
+        F: Fn(PluginHandle, Arc<str>, Arc<[u8]>) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let entry: Arc<str> = entry.into();
+        let payload: Arc<[u8]> = payload.into();
+
+        let tasks: Vec<_> = self
+            .plugins
+            .iter()
+            .map(|(name, plugin)| {
+                let handle = PluginHandle {
+                    plugin: plugin.clone(),
+                };
+                let task =
+                    self.host_ctx
+                        .config
+                        .spawner
+                        .spawn(f(handle, entry.clone(), payload.clone()));
+                (name.clone(), task)
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for (name, task) in tasks {
+            let result = task
+                .await
+                .unwrap_or_else(|e| Err(NylonRingHostError::ReceiveResponseFailed(e.to_string())));
+            results.push((name, result));
+        }
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+}
@@ -1,9 +1,66 @@
 //! FFI callback handlers for the plugin interface.
 
-use crate::context::{HostContext, CURRENT_UNARY_RESULT, CURRENT_UNARY_TX};
-use crate::types::{StreamFrame, UnaryResultSlot, UnarySender};
-use nylon_ring::{NrBytes, NrStatus, NrStr};
+use crate::context::{
+    defer_fast, insert_pending, remove_pending, Dispatched, HostContext, InFlight, PluginContext,
+    CURRENT_UNARY_RESULT, CURRENT_UNARY_TX,
+};
+use crate::dispatch_cache::Lookup;
+use crate::misdelivery;
+use crate::sid::{is_fire_and_forget, next_sid, sid_epoch, sid_mode, SidMode};
+use crate::strict::{self, StrictViolation, STRICT_ERROR_CODE};
+use crate::trace::{self, CallSpan, TraceEvent};
+use crate::types::{PanicReport, Pending, StreamFrame, UnaryResultSlot, UnarySender};
+use nylon_ring::{
+    NrBytes, NrHostExt, NrLogLevel, NrStatus, NrStr, NrTuple, NrVec, NR_SCOPE_GLOBAL,
+    NR_SCOPE_PLUGIN, NR_SCOPE_SID, NR_STATE_ABSENT,
+};
+use parking_lot::RwLock;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::c_void;
+use std::sync::Weak;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+/// Error returned by `set_state` when the key is not valid UTF-8.
+const INVALID_KEY_ERROR: &[u8] = b"state key is not valid UTF-8";
+
+/// Error returned by `set_state` when `host_ctx` was not issued by a host.
+const INVALID_CONTEXT_ERROR: &[u8] = b"host_ctx is not a context issued by the host";
+
+/// Error returned by `set_state` once the plugin's callbacks were revoked.
+const REVOKED_ERROR: &[u8] = b"host_ctx was revoked for shutdown";
+
+/// Error returned by `set_state_scoped` for a scope the host does not know.
+const UNKNOWN_SCOPE_ERROR: &[u8] = b"unknown state scope";
+
+/// Error returned by `set_state` under strict mode for a SID the host never
+/// issued.
+const UNKNOWN_SID_ERROR: &[u8] = b"state written under a SID the host never issued";
+
+/// Whether `sid` is `INIT_SID` or could have been issued by `ctx`.
+fn issued_by(ctx: &HostContext, sid: u64) -> bool {
+    sid == nylon_ring::INIT_SID || (sid_mode(sid).is_some() && sid_epoch(sid) == ctx.epoch)
+}
+
+/// Resolve the shared host context from a plugin's `host_ctx` pointer.
+///
+/// # Safety
+///
+/// `host_ctx` must be a non-null pointer to a live `PluginContext`.
+#[inline(always)]
+unsafe fn host_context<'a>(host_ctx: *mut c_void) -> &'a HostContext {
+    &plugin_context(host_ctx).host
+}
+
+/// Resolve the calling plugin's context from the `host_ctx` pointer.
+///
+/// # Safety
+///
+/// `host_ctx` must be a non-null pointer to a live `PluginContext`.
+#[inline(always)]
+unsafe fn plugin_context<'a>(host_ctx: *mut c_void) -> &'a PluginContext {
+    &*(host_ctx as *const PluginContext)
+}
 
 /// Callback invoked by the plugin to send results back to the host.
 ///
@@ -14,30 +71,102 @@ use std::ffi::c_void;
 ///
 /// # Safety
 ///
-/// Must be called with a valid `host_ctx` pointer created by this host.
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
 pub(crate) unsafe extern "C" fn send_result_vec_callback(
     host_ctx: *mut c_void,
     sid: u64,
     status: NrStatus,
     payload: nylon_ring::NrVec<u8>,
 ) {
-    if host_ctx.is_null() {
+    deliver(host_ctx, sid, status, None, None, payload);
+}
+
+/// Callback sending a stream frame on a named sub-channel. Invalid UTF-8 in
+/// the channel name is replaced.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn send_result_channel_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+    status: NrStatus,
+    channel: NrStr,
+    payload: nylon_ring::NrVec<u8>,
+) {
+    let channel = channel.as_str_lossy().into_owned();
+    deliver(host_ctx, sid, status, Some(channel), None, payload);
+}
+
+/// Callback sending a stream frame numbered `seq`, for
+/// [`PluginHandle::call_stream_ordered`](crate::PluginHandle::call_stream_ordered).
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn send_result_seq_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+    seq: u64,
+    status: NrStatus,
+    payload: nylon_ring::NrVec<u8>,
+) {
+    deliver(host_ctx, sid, status, None, Some(seq), payload);
+}
+
+/// Route a result to whoever waits on `sid`.
+unsafe fn deliver(
+    host_ctx: *mut c_void,
+    sid: u64,
+    status: NrStatus,
+    channel: Option<String>,
+    seq: Option<u64>,
+    payload: nylon_ring::NrVec<u8>,
+) {
+    // Taking or freeing a buffer whose parts disagree is undefined; leak
+    // it and fail the call instead.
+    let (status, payload) = if payload.is_well_formed() {
+        (status, payload)
+    } else {
+        std::mem::forget(payload);
+        (NrStatus::Invalid, nylon_ring::NrVec::default())
+    };
+    if !PluginContext::is_live(host_ctx) {
         return;
     }
-    let ctx = &*(host_ctx as *const HostContext);
+    let plugin = plugin_context(host_ctx);
+    plugin.metrics.record_response(status);
+    trace::result(sid, status, payload.len);
+    let ctx = &*plugin.host;
+    ctx.metrics.record_received(payload.len);
+    let strict = ctx.config.strict_mode;
 
-    // Convert NrVec to Vec<u8>
-    let mut data_vec = Some(payload.into_vec());
+    // Free: the plugin allocated the payload with our allocator, as the
+    // ABI requires (see `NrVec`).
+    let mut data = payload.into_vec();
+    if !misdelivery::check(plugin, sid, &mut data) {
+        return;
+    }
+    let mut data_vec = Some(data);
 
     // ── ULTRA FAST DIRECT SLOT (call_response_fast) ──
     let mut handled_fast = false;
 
     CURRENT_UNARY_RESULT.with(|cell| {
-        let ptr = cell.get();
-        if !ptr.is_null() {
+        if let Some(ptr) = cell.get().get(ctx, sid) {
             let slot: &mut UnaryResultSlot = unsafe { &mut *ptr };
 
-            if let Some(data) = data_vec.take() {
+            if strict && slot.is_some() {
+                // Fail the call rather than keep either result.
+                strict::report(plugin, sid, StrictViolation::DuplicateResult);
+                let message = StrictViolation::DuplicateResult.to_string();
+                let frame = nylon_ring::encode_error(STRICT_ERROR_CODE, &message);
+                *slot = Some((NrStatus::Err, frame.into_vec()));
+                data_vec = None;
+            } else if let Some(data) = data_vec.take() {
+                if strict {
+                    ctx.answered.insert(sid);
+                }
                 *slot = Some((status, data));
             }
             // For Slab architecture, if we allocated a slot, we might need to clear it?
@@ -54,8 +183,7 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
 
     // ── FAST PATH: oneshot sender (Legacy / Thread Local Fast Path) ──
     CURRENT_UNARY_TX.with(|cell| {
-        let ptr = cell.get();
-        if !ptr.is_null() {
+        if let Some(ptr) = cell.get().get(ctx, sid) {
             let slot: &mut UnarySender = unsafe { &mut *ptr };
 
             if let Some(tx) = slot.take() {
@@ -72,6 +200,11 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
     }
 
     // ── SHARDED MAP / CHANNEL PATH ──
+    // Nobody waits on fire-and-forget results; skip the map lookup.
+    if is_fire_and_forget(sid) {
+        return;
+    }
+
     let data_vec = match data_vec.take() {
         Some(v) => v,
         None => return, // Already consumed
@@ -79,10 +212,18 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
 
     // Optimization: Try to get stream sender with Read Lock first (99% case for streams)
     if let Some(tx) = crate::context::get_pending_stream(ctx, sid) {
-        let _ = tx.send(StreamFrame {
+        ctx.tracer.emit(TraceEvent::StreamFrame { sid, status });
+        let lag = tx.send(StreamFrame {
             status,
             data: data_vec,
+            channel,
+            seq,
         });
+        match lag {
+            Some(lag) => plugin.metrics.record_stream_lag(lag),
+            None if strict => strict::report(plugin, sid, StrictViolation::StreamClosed),
+            None => {}
+        }
 
         let is_finished = matches!(
             status,
@@ -101,16 +242,37 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
     if let Some(entry) = crate::context::remove_pending(ctx, sid) {
         match entry {
             crate::types::Pending::Unary(tx) => {
+                misdelivery::note_foreign(plugin, sid);
+                if strict {
+                    ctx.answered.insert(sid);
+                }
                 // Oneshot: just send result
                 let _ = tx.send((status, data_vec));
             }
+            crate::types::Pending::Dispatched(tx, in_flight) => {
+                misdelivery::note_foreign(plugin, sid);
+                if strict {
+                    ctx.answered.insert(sid);
+                }
+                // The call is answered even if the result is never taken.
+                let _ = tx.send((status, data_vec));
+                drop(in_flight);
+            }
             crate::types::Pending::Stream(tx) => {
+                ctx.tracer.emit(TraceEvent::StreamFrame { sid, status });
                 // Should technically be caught by optimization above, but handle race conditions or edge cases
                 // Stream: send frame
-                let _ = tx.send(StreamFrame {
+                let lag = tx.send(StreamFrame {
                     status,
                     data: data_vec,
+                    channel,
+                    seq,
                 });
+                match lag {
+                    Some(lag) => plugin.metrics.record_stream_lag(lag),
+                    None if strict => strict::report(plugin, sid, StrictViolation::StreamClosed),
+                    None => {}
+                }
 
                 // If stream is NOT finished, we must PUT IT BACK so next callback finds it.
                 let is_finished = matches!(
@@ -123,6 +285,14 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
                 }
             }
         }
+    } else if strict && (sid_mode(sid) != Some(SidMode::Fast) || ctx.answered.contains(sid)) {
+        strict::report(plugin, sid, ctx.answered.classify(sid));
+    } else if sid_mode(sid) == Some(SidMode::Fast) {
+        // Answered from another thread before `handle` returned `Accepted`
+        if strict {
+            ctx.answered.insert(sid);
+        }
+        crate::context::park_fast(ctx, sid, status, data_vec);
     }
 }
 
@@ -130,19 +300,30 @@ pub(crate) unsafe extern "C" fn send_result_vec_callback(
 ///
 /// # Safety
 ///
-/// Must be called with a valid `host_ctx` pointer created by this host.
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
 pub(crate) unsafe extern "C" fn set_state_callback(
     host_ctx: *mut c_void,
     sid: u64,
     key: NrStr,
     value: NrBytes,
 ) -> NrBytes {
-    if host_ctx.is_null() {
-        return NrBytes::from_slice(&[]);
+    if !PluginContext::is_valid(host_ctx) {
+        return NrBytes::from_slice(INVALID_CONTEXT_ERROR);
+    }
+    let plugin = plugin_context(host_ctx);
+    if plugin.is_revoked() {
+        return NrBytes::from_slice(REVOKED_ERROR);
+    }
+    let ctx = host_context(host_ctx);
+    if ctx.config.strict_mode && !issued_by(ctx, sid) {
+        strict::report(plugin, sid, StrictViolation::StateForUnknownSid);
+        return NrBytes::from_slice(UNKNOWN_SID_ERROR);
     }
-    let ctx = &*(host_ctx as *const HostContext);
 
-    let key_str = key.as_str().to_string();
+    let key_str = match key.try_as_str() {
+        Ok(k) => k.to_string(),
+        Err(_) => return NrBytes::from_slice(INVALID_KEY_ERROR),
+    };
 
     // Copy data from NrBytes to owned Vec<u8>
     let value_vec = value.as_slice().to_vec();
@@ -153,33 +334,659 @@ pub(crate) unsafe extern "C" fn set_state_callback(
         .insert(key_str, value_vec);
 
     // Return empty bytes on success
-    NrBytes::from_slice(&[])
+    NrBytes::empty()
+}
+
+thread_local! {
+    /// Backing storage for the views `get_state` returns.
+    static STATE_VIEW: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
 }
 
 /// Callback for getting per-SID state from the host.
 ///
+/// The value is copied into a per-thread buffer, so concurrent `set_state`
+/// calls do not change a view. The buffer is reused: the next `get_state` on
+/// the same thread overwrites it and may move it, after which the previous
+/// view must not be read. Kept for plugins that predate
+/// [`get_state_into_callback`].
+///
 /// # Safety
 ///
-/// Must be called with a valid `host_ctx` pointer created by this host.
-/// The returned `NrBytes` is only valid as long as the `DashMap` entry exists.
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
 pub(crate) unsafe extern "C" fn get_state_callback(
     host_ctx: *mut c_void,
     sid: u64,
     key: NrStr,
 ) -> NrBytes {
-    if host_ctx.is_null() {
-        return NrBytes::from_slice(&[]);
+    if !PluginContext::is_live(host_ctx) {
+        return NrBytes::empty();
+    }
+    let ctx = host_context(host_ctx);
+
+    let key_str = match key.try_as_str() {
+        Ok(k) => k,
+        Err(_) => return NrBytes::empty(),
+    };
+    let Some(sid_state) = ctx.state_per_sid.get(&sid) else {
+        return NrBytes::empty();
+    };
+    let Some(value) = sid_state.get(key_str) else {
+        return NrBytes::empty();
+    };
+
+    STATE_VIEW.with(|view| {
+        let mut view = view.borrow_mut();
+        view.clear();
+        view.extend_from_slice(value);
+        NrBytes::from_slice(&view)
+    })
+}
+
+/// Callback copying per-SID state into a plugin-provided buffer. Returns the
+/// value length, or [`NR_STATE_ABSENT`] if there is none; the value is only
+/// copied if it fits.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+/// `out_buf` must be writable for `out_cap` bytes.
+pub(crate) unsafe extern "C" fn get_state_into_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+    key: NrStr,
+    out_buf: *mut u8,
+    out_cap: u64,
+) -> u64 {
+    if !PluginContext::is_live(host_ctx) {
+        return NR_STATE_ABSENT;
+    }
+    let ctx = host_context(host_ctx);
+
+    let Ok(key_str) = key.try_as_str() else {
+        return NR_STATE_ABSENT;
+    };
+    let Some(sid_state) = ctx.state_per_sid.get(&sid) else {
+        return NR_STATE_ABSENT;
+    };
+    // Copied while the shard's read lock keeps `set_state` out.
+    copy_value(sid_state.get(key_str), out_buf, out_cap)
+}
+
+/// Copy `value` into `out_buf` if it fits in `out_cap` bytes. Returns its
+/// length, or [`NR_STATE_ABSENT`] if there is none.
+///
+/// # Safety
+///
+/// `out_buf` must be valid for `out_cap` bytes of writes.
+unsafe fn copy_value(value: Option<&Vec<u8>>, out_buf: *mut u8, out_cap: u64) -> u64 {
+    let Some(value) = value else {
+        return NR_STATE_ABSENT;
+    };
+    if !value.is_empty() && value.len() as u64 <= out_cap {
+        std::ptr::copy_nonoverlapping(value.as_ptr(), out_buf, value.len());
+    }
+    value.len() as u64
+}
+
+/// The map behind a plugin or global state `scope`.
+fn scoped_map(plugin: &PluginContext, scope: u32) -> Option<&RwLock<HashMap<String, Vec<u8>>>> {
+    match scope {
+        NR_SCOPE_PLUGIN => Some(&plugin.state),
+        NR_SCOPE_GLOBAL => Some(&plugin.host.global_state),
+        _ => None,
+    }
+}
+
+/// Callback for setting state in a scope; see
+/// [`NrHostExt::set_state_scoped`].
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn set_state_scoped_callback(
+    host_ctx: *mut c_void,
+    scope: u32,
+    owner: u64,
+    key: NrStr,
+    value: NrBytes,
+) -> NrBytes {
+    if scope == NR_SCOPE_SID {
+        return set_state_callback(host_ctx, owner, key, value);
+    }
+    if !PluginContext::is_valid(host_ctx) {
+        return NrBytes::from_slice(INVALID_CONTEXT_ERROR);
+    }
+    let plugin = plugin_context(host_ctx);
+    if plugin.is_revoked() {
+        return NrBytes::from_slice(REVOKED_ERROR);
+    }
+    let Some(map) = scoped_map(plugin, scope) else {
+        return NrBytes::from_slice(UNKNOWN_SCOPE_ERROR);
+    };
+    let Ok(key) = key.try_as_str() else {
+        return NrBytes::from_slice(INVALID_KEY_ERROR);
+    };
+    map.write()
+        .insert(key.to_string(), value.as_slice().to_vec());
+    NrBytes::empty()
+}
+
+/// Callback copying state in a scope into a plugin-provided buffer; see
+/// [`NrHostExt::get_state_scoped_into`].
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn get_state_scoped_into_callback(
+    host_ctx: *mut c_void,
+    scope: u32,
+    owner: u64,
+    key: NrStr,
+    out_buf: *mut u8,
+    out_cap: u64,
+) -> u64 {
+    if scope == NR_SCOPE_SID {
+        return get_state_into_callback(host_ctx, owner, key, out_buf, out_cap);
+    }
+    if !PluginContext::is_live(host_ctx) {
+        return NR_STATE_ABSENT;
+    }
+    let plugin = plugin_context(host_ctx);
+    let (Some(map), Ok(key)) = (scoped_map(plugin, scope), key.try_as_str()) else {
+        return NR_STATE_ABSENT;
+    };
+    copy_value(map.read().get(key), out_buf, out_cap)
+}
+
+/// Callback returning the host extension table for a plugin.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn get_host_ext_callback(host_ctx: *mut c_void) -> *const NrHostExt {
+    if !PluginContext::is_valid(host_ctx) {
+        return std::ptr::null();
+    }
+    &host_context(host_ctx).host_ext
+}
+
+/// Callback telling a plugin whether its callbacks were revoked. Contexts
+/// that were not issued by a host count as revoked.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn is_revoked_callback(host_ctx: *mut c_void) -> bool {
+    !PluginContext::is_live(host_ctx)
+}
+
+/// Callback announcing that `sid` is answered after `handle` returns.
+///
+/// Only fast calls need it: their result slot lives on the caller's stack
+/// for the duration of `handle`, so the call is moved to the pending map
+/// for the late answer. Every other call already waits there.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn complete_later_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+) -> NrStatus {
+    if !PluginContext::is_valid(host_ctx) {
+        return NrStatus::Invalid;
+    }
+    if plugin_context(host_ctx).is_revoked() {
+        return NrStatus::Revoked;
+    }
+    let ctx = host_context(host_ctx);
+    if CURRENT_UNARY_RESULT.with(|cell| cell.get().get(ctx, sid).is_some()) {
+        defer_fast(ctx, sid);
+    }
+    NrStatus::Ok
+}
+
+/// Callback for recording a panic caught inside a plugin entry point. The
+/// panic is logged, and its message ends up in the call's error.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn report_panic_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+    entry: NrStr,
+    message: NrStr,
+    backtrace: NrStr,
+) {
+    if !PluginContext::is_live(host_ctx) {
+        return;
+    }
+    let ctx = plugin_context(host_ctx);
+    let report = PanicReport {
+        sid,
+        entry: entry.as_str_lossy().into_owned(),
+        message: message.as_str_lossy().into_owned(),
+        backtrace: backtrace.as_str_lossy().into_owned(),
+    };
+    log::warn!(
+        "plugin {:?} panicked in {:?} (sid {sid}): {}",
+        ctx.name,
+        report.entry,
+        report.message
+    );
+    ctx.push_panic_report(report);
+}
+
+/// Callback logging a plugin's record through the `log` facade, with the
+/// plugin's name under the `plugin` key. Served to revoked plugins too, so
+/// `shutdown` can log.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn log_callback(
+    host_ctx: *mut c_void,
+    level: u32,
+    target: NrStr,
+    message: NrStr,
+) {
+    if !PluginContext::is_valid(host_ctx) {
+        return;
+    }
+    let level = match NrLogLevel::from_u32(level) {
+        NrLogLevel::Error => log::Level::Error,
+        NrLogLevel::Warn => log::Level::Warn,
+        NrLogLevel::Info => log::Level::Info,
+        NrLogLevel::Debug => log::Level::Debug,
+        NrLogLevel::Trace => log::Level::Trace,
+    };
+    let target = target.as_str_lossy();
+    if !log::log_enabled!(target: &target, level) {
+        return;
+    }
+    let plugin: &str = &plugin_context(host_ctx).name;
+    log::log!(target: &target, level, plugin = plugin; "{}", message.as_str_lossy());
+}
+
+/// Callback handing a plugin a buffer of the shared arena to write a
+/// response into.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn buf_acquire_callback(
+    host_ctx: *mut c_void,
+    size: u64,
+) -> NrTuple<u64, *mut u8> {
+    let none = NrTuple::from((0, std::ptr::null_mut()));
+    if !PluginContext::is_live(host_ctx) {
+        return none;
     }
-    let ctx = &*(host_ctx as *const HostContext);
+    let Ok(size) = usize::try_from(size) else {
+        return none;
+    };
+    host_context(host_ctx).shared.acquire(size).into()
+}
 
-    let key_str = key.as_str();
-    if let Some(sid_state) = ctx.state_per_sid.get(&sid) {
-        if let Some(value) = sid_state.get(key_str) {
-            // Return NrBytes pointing to the Vec<u8> data
-            return NrBytes::from_slice(value.as_slice());
+/// Callback marking a shared buffer as written.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn buf_commit_callback(
+    host_ctx: *mut c_void,
+    handle: u64,
+    len: u64,
+) -> NrStatus {
+    if !PluginContext::is_valid(host_ctx) {
+        return NrStatus::Invalid;
+    }
+    if plugin_context(host_ctx).is_revoked() {
+        return NrStatus::Revoked;
+    }
+    let Ok(len) = usize::try_from(len) else {
+        return NrStatus::Invalid;
+    };
+    host_context(host_ctx).shared.commit(handle, len)
+}
+
+/// Callback starting a call from one plugin to another.
+///
+/// The target's `handle` runs on the calling thread; its response is parked
+/// in a `Pending::Unary` until the caller takes it. A call whose response is
+/// in the dispatch cache gets it parked at once, without running `handle`.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+pub(crate) unsafe extern "C" fn dispatch_spawn_callback(
+    host_ctx: *mut c_void,
+    target: NrStr,
+    entry: NrStr,
+    payload: NrBytes,
+) -> NrTuple<NrStatus, u64> {
+    let rejected = |status| NrTuple::from((status, 0));
+    if !PluginContext::is_valid(host_ctx) {
+        return rejected(NrStatus::Invalid);
+    }
+    if plugin_context(host_ctx).is_revoked() {
+        return rejected(NrStatus::Revoked);
+    }
+    let ctx = host_context(host_ctx);
+
+    let (Ok(target), Ok(entry_str)) = (target.try_as_str(), entry.try_as_str()) else {
+        return rejected(NrStatus::Invalid);
+    };
+    let plugin = ctx
+        .dispatch_targets
+        .read()
+        .get(target)
+        .and_then(Weak::upgrade);
+    let Some(plugin) = plugin else {
+        return rejected(NrStatus::Invalid);
+    };
+
+    let payload = payload.as_slice();
+    let caller = &plugin_context(host_ctx).name;
+    let slot = match ctx
+        .dispatch_cache
+        .lookup(caller, target, entry_str, payload)
+    {
+        Lookup::Uncached => None,
+        Lookup::Miss(slot) => Some(slot),
+        Lookup::Hit(data) => {
+            let Ok(sid) = next_sid(ctx, SidMode::Unary) else {
+                return rejected(NrStatus::Err);
+            };
+            let (tx, rx) = oneshot::channel();
+            let _ = tx.send((NrStatus::Ok, data));
+            let dispatched = Dispatched { rx, cache: None };
+            ctx.dispatched.insert(sid, dispatched);
+            ctx.metrics.record_dispatch_cache_hit();
+            return (NrStatus::Ok, sid).into();
         }
+    };
+
+    let _call = plugin.ctx.metrics.start_call(entry_str);
+    let Ok(sid) = next_sid(ctx, SidMode::Unary) else {
+        return rejected(NrStatus::Err);
+    };
+    let (tx, rx) = oneshot::channel();
+    let in_flight = InFlight::new(&plugin.ctx);
+    insert_pending(ctx, sid, Pending::Dispatched(tx, in_flight));
+    let dispatched = Dispatched { rx, cache: slot };
+    ctx.dispatched.insert(sid, dispatched);
+
+    let span = CallSpan::new("dispatch", target, entry_str, sid, payload.len());
+    let status = span.in_scope(|| plugin.handle(entry_str, sid, payload));
+    span.finish(status, 0);
+    if !status.is_success() {
+        remove_pending(ctx, sid);
+        ctx.dispatched.remove(&sid);
+        plugin.ctx.metrics.record_error();
+        return rejected(status);
     }
+    if !plugin.answers_later(status) {
+        // Answered during `handle`, or not at all.
+        remove_pending(ctx, sid);
+    }
+    (NrStatus::Ok, sid).into()
+}
+
+/// Callback taking the response to a dispatched call. SIDs that were never
+/// dispatched, or were already taken, report `Invalid`.
+///
+/// # Safety
+///
+/// `host_ctx` must be null or readable; see [`PluginContext::is_valid`].
+/// `status` and `payload` must be null or valid for writes.
+pub(crate) unsafe extern "C" fn take_dispatch_result_callback(
+    host_ctx: *mut c_void,
+    sid: u64,
+    status: *mut NrStatus,
+    payload: *mut NrVec<u8>,
+) -> bool {
+    if !PluginContext::is_valid(host_ctx) || status.is_null() || payload.is_null() {
+        return false;
+    }
+    if plugin_context(host_ctx).is_revoked() {
+        // Answer at once, so a plugin polling for a response stops waiting.
+        status.write(NrStatus::Revoked);
+        payload.write(NrVec::default());
+        return true;
+    }
+    let ctx = host_context(host_ctx);
+
+    let (result_status, data) = match ctx.dispatched.remove(&sid) {
+        None => (NrStatus::Invalid, Vec::new()),
+        Some((_, mut dispatched)) => match dispatched.rx.try_recv() {
+            Ok((status, data)) => {
+                if let (NrStatus::Ok, Some(slot)) = (status, dispatched.cache) {
+                    slot.fill(&data);
+                }
+                (status, data)
+            }
+            Err(TryRecvError::Empty) => {
+                ctx.dispatched.insert(sid, dispatched);
+                return false;
+            }
+            // The pending entry went away without a response.
+            Err(TryRecvError::Closed) => (NrStatus::Err, Vec::new()),
+        },
+    };
+    status.write(result_status);
+    payload.write(NrVec::from_vec(data));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn new_ctx() -> PluginContext {
+        PluginContext::new(
+            "test",
+            Arc::new(HostContext::new(
+                NrHostExt {
+                    set_state: set_state_callback,
+                    get_state: get_state_callback,
+                    report_panic: report_panic_callback,
+                    dispatch_spawn: dispatch_spawn_callback,
+                    take_dispatch_result: take_dispatch_result_callback,
+                    get_state_into: get_state_into_callback,
+                    is_revoked: is_revoked_callback,
+                    complete_later: complete_later_callback,
+                    log: log_callback,
+                    buf_acquire: buf_acquire_callback,
+                    buf_commit: buf_commit_callback,
+                    set_state_scoped: set_state_scoped_callback,
+                    get_state_scoped_into: get_state_scoped_into_callback,
+                    send_result_seq: send_result_seq_callback,
+                },
+                Default::default(),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_state_rejects_invalid_utf8_key() {
+        let plugin_ctx = new_ctx();
+        let ctx_ptr = &plugin_ctx as *const PluginContext as *mut c_void;
+        let ctx = &plugin_ctx.host;
+
+        let bad_key = [b'k', 0xc3, 0x28];
+        let key = NrStr {
+            ptr: bad_key.as_ptr(),
+            len: bad_key.len() as u32,
+        };
+
+        let err = unsafe { set_state_callback(ctx_ptr, 7, key, NrBytes::from_slice(b"v")) };
+        assert_eq!(err.as_slice(), INVALID_KEY_ERROR);
+        assert!(ctx.state_per_sid.get(&7).is_none());
+
+        let value = unsafe { get_state_callback(ctx_ptr, 7, key) };
+        assert!(value.as_slice().is_empty());
 
-    // Return empty bytes if not found
-    NrBytes::from_slice(&[])
+        let ok =
+            unsafe { set_state_callback(ctx_ptr, 7, NrStr::new("k"), NrBytes::from_slice(b"v")) };
+        assert!(ok.as_slice().is_empty());
+        let value = unsafe { get_state_callback(ctx_ptr, 7, NrStr::new("k")) };
+        assert_eq!(value.as_slice(), b"v");
+    }
+
+    #[test]
+    fn test_concurrent_state_reads_are_consistent() {
+        let plugin_ctx = new_ctx();
+        let ctx_ptr = &plugin_ctx as *const PluginContext as usize;
+        let ext = plugin_ctx.host.host_ext;
+        let key = NrStr::new("shared");
+
+        // Value `n` is `n` repeated over a length derived from `n`, so a torn
+        // or stale read shows up as mixed bytes or a wrong length.
+        let value = |n: u8| vec![n; 1 + (n as usize * 61) % 4096];
+        let check = |bytes: &[u8]| {
+            if let Some(&n) = bytes.first() {
+                assert_eq!(bytes, value(n).as_slice());
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for writer in 0..4u8 {
+                scope.spawn(move || {
+                    for i in 0..2000u32 {
+                        let n = (i as u8).wrapping_mul(4).wrapping_add(writer);
+                        unsafe {
+                            set_state_callback(
+                                ctx_ptr as *mut c_void,
+                                1,
+                                key,
+                                NrBytes::from_slice(&value(n)),
+                            );
+                        }
+                    }
+                });
+            }
+            for _ in 0..4 {
+                scope.spawn(move || {
+                    for _ in 0..2000 {
+                        let ctx = ctx_ptr as *mut c_void;
+                        check(&unsafe { ext.read_state(ctx, 1, "shared") });
+                        let legacy = unsafe { get_state_callback(ctx, 1, key) };
+                        check(legacy.as_slice());
+                    }
+                });
+            }
+        });
+
+        // A buffer that is too small is left untouched.
+        let ctx = ctx_ptr as *mut c_void;
+        let mut small = [0u8; 1];
+        let len = unsafe { get_state_into_callback(ctx, 1, key, small.as_mut_ptr(), 1) };
+        assert!(len > 1);
+        assert_eq!(small, [0]);
+        assert_eq!(
+            unsafe {
+                get_state_into_callback(ctx, 1, NrStr::new("missing"), small.as_mut_ptr(), 1)
+            },
+            NR_STATE_ABSENT
+        );
+    }
+
+    #[test]
+    fn test_state_views_are_replaced_by_the_next_read() {
+        let plugin_ctx = new_ctx();
+        let ctx_ptr = &plugin_ctx as *const PluginContext as *mut c_void;
+        let set = |key: &str, value: &[u8]| unsafe {
+            set_state_callback(ctx_ptr, 1, NrStr::new(key), NrBytes::from_slice(value));
+        };
+        set("short", b"ab");
+        set("long", &[7u8; 4096]);
+        set("empty", b"");
+
+        // One view at a time: each read is copied out before the next, which
+        // may reuse or move the buffer.
+        for key in ["short", "long", "short", "empty", "long"] {
+            let view = unsafe { get_state_callback(ctx_ptr, 1, NrStr::new(key)) };
+            let expected: &[u8] = match key {
+                "short" => b"ab",
+                "long" => &[7u8; 4096],
+                _ => b"",
+            };
+            assert_eq!(view.as_slice(), expected);
+        }
+
+        // `get_state_into` tells an empty value from a missing one.
+        let mut buf = [0u8; 1];
+        let into = |key: &str, buf: &mut [u8]| unsafe {
+            get_state_into_callback(ctx_ptr, 1, NrStr::new(key), buf.as_mut_ptr(), 1)
+        };
+        assert_eq!(into("empty", &mut buf), 0);
+        assert_eq!(into("missing", &mut buf), NR_STATE_ABSENT);
+        let ext = plugin_ctx.host.host_ext;
+        assert_eq!(unsafe { ext.state(ctx_ptr, 1, "empty") }, Some(Vec::new()));
+        assert_eq!(unsafe { ext.state(ctx_ptr, 1, "missing") }, None);
+    }
+
+    #[test]
+    fn test_foreign_context_is_rejected() {
+        // A plugin passing its own struct where `host_ctx` is expected.
+        let foreign = vec![0x5au64; std::mem::size_of::<PluginContext>() / 8 + 1];
+        let ptr = foreign.as_ptr() as *mut c_void;
+        unsafe {
+            assert!(!PluginContext::is_valid(ptr));
+            assert!(get_host_ext_callback(ptr).is_null());
+            let err = set_state_callback(ptr, 1, NrStr::new("k"), NrBytes::from_slice(b"v"));
+            assert_eq!(err.as_slice(), INVALID_CONTEXT_ERROR);
+            assert!(get_state_callback(ptr, 1, NrStr::new("k"))
+                .as_slice()
+                .is_empty());
+            send_result_vec_callback(ptr, 1, NrStatus::Ok, nylon_ring::NrVec::from_slice(b"x"));
+        }
+
+        let plugin_ctx = new_ctx();
+        let ctx_ptr = &plugin_ctx as *const PluginContext as *mut c_void;
+        assert!(unsafe { PluginContext::is_valid(ctx_ptr) });
+        assert!(!unsafe { get_host_ext_callback(ctx_ptr) }.is_null());
+    }
+
+    #[test]
+    fn test_revoked_context_is_refused() {
+        let plugin_ctx = new_ctx();
+        let ctx_ptr = &plugin_ctx as *const PluginContext as *mut c_void;
+        let key = NrStr::new("k");
+        unsafe {
+            set_state_callback(ctx_ptr, 3, key, NrBytes::from_slice(b"v"));
+            assert!(!is_revoked_callback(ctx_ptr));
+
+            plugin_ctx.revoke();
+            assert!(is_revoked_callback(ctx_ptr));
+            // The extension table stays reachable, to ask `is_revoked`.
+            assert!(!get_host_ext_callback(ctx_ptr).is_null());
+
+            let err = set_state_callback(ctx_ptr, 3, key, NrBytes::from_slice(b"w"));
+            assert_eq!(err.as_slice(), REVOKED_ERROR);
+            assert!(get_state_callback(ctx_ptr, 3, key).as_slice().is_empty());
+
+            let spawned =
+                dispatch_spawn_callback(ctx_ptr, NrStr::new("any"), key, NrBytes::empty());
+            assert_eq!((spawned.a, spawned.b), (NrStatus::Revoked, 0));
+
+            let mut status = NrStatus::Ok;
+            let mut payload = std::mem::MaybeUninit::<NrVec<u8>>::uninit();
+            assert!(take_dispatch_result_callback(
+                ctx_ptr,
+                9,
+                &mut status,
+                payload.as_mut_ptr()
+            ));
+            assert_eq!(status, NrStatus::Revoked);
+            assert!(payload.assume_init().as_slice().is_empty());
+        }
+        assert_eq!(
+            plugin_ctx.host.state_per_sid.get(&3).unwrap().get("k"),
+            Some(&b"v".to_vec())
+        );
+    }
 }
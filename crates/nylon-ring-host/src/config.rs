@@ -0,0 +1,175 @@
+//! Host configuration fixed at construction, through [`NylonRingHostBuilder`].
+
+use crate::error::NylonRingHostError;
+use crate::rt;
+use crate::types::Result;
+use crate::NylonRingHost;
+use std::time::Duration;
+
+/// Settings a host is built with. Settings that can change later, such as
+/// the drain timeout, have setters on [`NylonRingHost`] instead.
+#[derive(Debug, Clone)]
+pub(crate) struct HostConfig {
+    /// Shards of the pending-request map. A power of two.
+    pub(crate) pending_shards: usize,
+    /// Calls each plugin may have in flight before new ones are rejected.
+    pub(crate) max_in_flight: Option<u64>,
+    /// How long a unary call waits for its response.
+    pub(crate) call_timeout: Option<Duration>,
+    /// Frames a stream buffers for its receiver before it is closed.
+    pub(crate) stream_capacity: Option<usize>,
+    /// Whether `call_response_fast` may answer through the thread-local slot.
+    pub(crate) fast_path: bool,
+    /// Whether plugin mistakes are reported instead of ignored.
+    pub(crate) strict_mode: bool,
+    /// Payload size from which `call_response_shared` passes requests in a
+    /// shared buffer.
+    pub(crate) shared_threshold: usize,
+    /// Where background tasks run.
+    pub(crate) spawner: rt::Spawner,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            pending_shards: 64,
+            max_in_flight: None,
+            call_timeout: None,
+            stream_capacity: None,
+            fast_path: true,
+            strict_mode: false,
+            shared_threshold: 1 << 20,
+            spawner: rt::Spawner::default(),
+        }
+    }
+}
+
+/// Builds a [`NylonRingHost`] with settings other than the defaults of
+/// [`NylonRingHost::new`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+/// use nylon_ring_host::{testing, NylonRingHost};
+/// use std::time::Duration;
+///
+/// let mut host = NylonRingHost::builder()
+///     .max_in_flight(1024)
+///     .call_timeout(Duration::from_secs(5))
+///     .build()?;
+/// host.load_static("mock", testing::mock_plugin())?;
+/// let (_, data) = host.plugin("mock").unwrap().call_response("echo", b"hi").await?;
+/// assert_eq!(data, b"hi");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NylonRingHostBuilder {
+    config: HostConfig,
+}
+
+impl NylonRingHostBuilder {
+    /// Start from the defaults of [`NylonRingHost::new`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shards of the map holding calls that wait for a response. Must be a
+    /// power of two. Defaults to 64.
+    pub fn pending_shards(mut self, shards: usize) -> Self {
+        self.config.pending_shards = shards;
+        self
+    }
+
+    /// Calls each plugin may have in flight (see
+    /// [`PluginHandle::in_flight`](crate::PluginHandle::in_flight)) before
+    /// further calls from the host fail with
+    /// [`NylonRingHostError::Overloaded`]. Must be at least 1. Unlimited by
+    /// default.
+    ///
+    /// Calls plugins dispatch to each other are counted but never rejected.
+    pub fn max_in_flight(mut self, limit: usize) -> Self {
+        self.config.max_in_flight = Some(limit as u64);
+        self
+    }
+
+    /// How long unary calls wait for their response before failing with
+    /// [`NylonRingHostError::Timeout`]. A response that arrives later is
+    /// dropped. Streams are not affected. No timeout by default.
+    pub fn call_timeout(mut self, timeout: Duration) -> Self {
+        self.config.call_timeout = Some(timeout);
+        self
+    }
+
+    /// Frames a stream buffers for a receiver that does not keep up. One
+    /// more closes the stream, although the final frame is always let
+    /// through: the receiver ends after the buffered frames and reports
+    /// [`StreamReceiver::overflowed`](crate::StreamReceiver::overflowed).
+    /// Must be at least 1. Unbounded by default.
+    pub fn stream_capacity(mut self, frames: usize) -> Self {
+        self.config.stream_capacity = Some(frames);
+        self
+    }
+
+    /// Whether [`PluginHandle::call_response_fast`](crate::PluginHandle::call_response_fast)
+    /// takes responses sent during `handle` from a thread-local slot. When
+    /// disabled it is [`call_response`](crate::PluginHandle::call_response).
+    /// Enabled by default.
+    pub fn fast_path(mut self, enabled: bool) -> Self {
+        self.config.fast_path = enabled;
+        self
+    }
+
+    /// Report plugin mistakes the host otherwise ignores, for development:
+    /// results for calls nobody waits on, second results for a call, frames
+    /// for streams whose receiver is gone and state written under SIDs the
+    /// host never issued. See [`StrictViolation`](crate::StrictViolation).
+    /// Off by default.
+    pub fn strict_mode(mut self, enabled: bool) -> Self {
+        self.config.strict_mode = enabled;
+        self
+    }
+
+    /// Payload size in bytes from which
+    /// [`PluginHandle::call_response_shared`](crate::PluginHandle::call_response_shared)
+    /// passes the request in a host-owned shared buffer instead of as a
+    /// plain view. Defaults to 1 MiB.
+    pub fn shared_threshold(mut self, bytes: usize) -> Self {
+        self.config.shared_threshold = bytes;
+        self
+    }
+
+    /// Run the host's background tasks, such as those of
+    /// [`call_stream_mux`](crate::PluginHandle::call_stream_mux),
+    /// [`broadcast`](crate::NylonRingHost::broadcast) and health checks, on
+    /// `runtime` instead of the runtime of whoever starts them. See
+    /// [`NylonRingHost::new_with_runtime`].
+    #[cfg(feature = "tokio-rt")]
+    pub fn runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.config.spawner = rt::Spawner(Some(runtime));
+        self
+    }
+
+    /// Build the host, or fail with [`NylonRingHostError::InvalidConfig`]
+    /// if a setting is out of range.
+    pub fn build(self) -> Result<NylonRingHost> {
+        let config = self.config;
+        if !config.pending_shards.is_power_of_two() {
+            return Err(NylonRingHostError::InvalidConfig(format!(
+                "pending shard count must be a power of two, got {}",
+                config.pending_shards
+            )));
+        }
+        if config.max_in_flight == Some(0) {
+            return Err(NylonRingHostError::InvalidConfig(
+                "in-flight limit must be at least 1".to_string(),
+            ));
+        }
+        if config.stream_capacity == Some(0) {
+            return Err(NylonRingHostError::InvalidConfig(
+                "stream capacity must be at least 1".to_string(),
+            ));
+        }
+        Ok(NylonRingHost::with_config(config))
+    }
+}
@@ -1,80 +1,651 @@
-use crate::types::{FastPendingMap, FastStateMap, Pending, UnaryResultSlot, UnarySender};
-use nylon_ring::NrHostExt;
-use rustc_hash::FxBuildHasher;
-use std::cell::Cell;
+use crate::config::HostConfig;
+use crate::dispatch_cache::{CacheSlot, DispatchCache};
+use crate::error::NylonRingHostError;
+use crate::metrics::{HostMetrics, Metrics};
+use crate::rt;
+use crate::schema::Schemas;
+use crate::shared::SharedArena;
+use crate::sid::{sid_key, SidExhaustion};
+use crate::stream::{ResumeHandle, ResumeToken, StreamLagAlert, StreamLagHook, StreamSender};
+use crate::strict::Answered;
+use crate::tombstone::DEFAULT_TOMBSTONE_GRACE;
+use crate::trace::Tracer;
+use crate::types::{
+    FastPendingMap, FastStateMap, PanicReport, Pending, UnaryReceiver, UnaryResultSlot, UnarySender,
+};
+use crate::LoadedPlugin;
+use dashmap::DashMap;
+use nylon_ring::{NrHostExt, NrStatus};
+use parking_lot::{Condvar, Mutex, RwLock};
+use rustc_hash::{FxBuildHasher, FxHashMap};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::LocalKey;
+use std::time::{Duration, Instant};
 
-/// Number of shards for the pending requests.
-const SHARD_COUNT: usize = 64;
-const SHARD_MASK: usize = SHARD_COUNT - 1;
+/// How long a plugin's `shutdown` may take before it is detached, unless
+/// configured otherwise.
+pub(crate) const DEFAULT_SHUTDOWN_WATCHDOG: Duration = Duration::from_secs(5);
 
-/// Host context shared with the plugin.
-#[repr(C)]
+/// How long a plugin's calls in flight may take to finish before it is shut
+/// down, unless configured otherwise.
+pub(crate) const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers to `Accepted` fast calls kept for their callers at most.
+const MAX_EARLY_FAST: usize = 1024;
+
+/// State shared by all plugins of one host.
+///
+/// Plugins never see this type. Their `host_ctx` points at a
+/// [`PluginContext`] and they reach the host only through the vtable and
+/// extension callbacks, so the layout here is free to change.
 pub(crate) struct HostContext {
     /// Sharded Pending Map Storage
     pub(crate) pending_shards: Box<[FastPendingMap]>,
+    /// `pending_shards.len() - 1`; the length is a power of two.
+    shard_mask: usize,
+    pub(crate) config: HostConfig,
 
     pub(crate) state_per_sid: FastStateMap,
+    /// State plugins stored in `NR_SCOPE_GLOBAL`.
+    pub(crate) global_state: RwLock<HashMap<String, Vec<u8>>>,
     pub(crate) host_ext: NrHostExt,
+    pub(crate) tracer: Tracer,
+    /// Tag carried in every SID this host issues.
+    pub(crate) epoch: u16,
+    pub(crate) stream_lag_alert: Mutex<Option<(StreamLagAlert, StreamLagHook)>>,
+    /// Loaded plugins by name, for `dispatch_spawn`.
+    pub(crate) dispatch_targets: RwLock<FxHashMap<String, Weak<LoadedPlugin>>>,
+    /// Responses to dispatched calls, until the caller takes them.
+    pub(crate) dispatched: DashMap<u64, Dispatched, FxBuildHasher>,
+    pub(crate) dispatch_cache: DispatchCache,
+    /// Resumable streams by token. Expired ones are dropped lazily.
+    pub(crate) resumable: DashMap<ResumeToken, ResumeHandle, FxBuildHasher>,
+    /// How long an unloaded plugin's `shutdown` may take before it is
+    /// detached.
+    pub(crate) shutdown_watchdog: Mutex<Duration>,
+    /// How long a plugin's calls in flight may take to finish before it is
+    /// shut down anyway.
+    pub(crate) drain_timeout: Mutex<Duration>,
+    /// How long calls to an entry a reload removed fail with
+    /// `EntryRemovedInVersion`.
+    pub(crate) tombstone_grace: Mutex<Duration>,
+    /// Answers to fast calls that came from another thread before `handle`
+    /// returned `Accepted`, until the caller picks them up.
+    pub(crate) early_fast: Mutex<FxHashMap<u64, (NrStatus, Vec<u8>)>>,
+    /// What `next_sid` does once the sequence space is used up.
+    pub(crate) sid_exhaustion: Mutex<SidExhaustion>,
+    pub(crate) metrics: HostMetrics,
+    /// Unary calls answered lately, under strict mode.
+    pub(crate) answered: Answered,
+    /// Buffers for payloads above the shared threshold.
+    pub(crate) shared: SharedArena,
 }
 
 impl HostContext {
-    pub(crate) fn new(host_ext: NrHostExt) -> Self {
-        let mut shards = Vec::with_capacity(SHARD_COUNT);
-        for _ in 0..SHARD_COUNT {
+    pub(crate) fn new(host_ext: NrHostExt, config: HostConfig) -> Self {
+        let mut shards = Vec::with_capacity(config.pending_shards);
+        for _ in 0..config.pending_shards {
+            #[cfg(not(feature = "slab-pending"))]
             shards.push(FastPendingMap::with_hasher(FxBuildHasher));
+            #[cfg(feature = "slab-pending")]
+            shards.push(FastPendingMap::new(config.pending_shards));
         }
 
         Self {
             pending_shards: shards.into_boxed_slice(),
+            shard_mask: config.pending_shards - 1,
+            config,
             state_per_sid: FastStateMap::with_hasher(FxBuildHasher),
+            global_state: RwLock::new(HashMap::new()),
             host_ext,
+            tracer: Tracer::new(),
+            epoch: crate::sid::next_epoch(),
+            stream_lag_alert: Mutex::new(None),
+            dispatch_targets: RwLock::new(FxHashMap::default()),
+            dispatched: DashMap::with_hasher(FxBuildHasher),
+            dispatch_cache: DispatchCache::default(),
+            resumable: DashMap::with_hasher(FxBuildHasher),
+            shutdown_watchdog: Mutex::new(DEFAULT_SHUTDOWN_WATCHDOG),
+            drain_timeout: Mutex::new(DEFAULT_DRAIN_TIMEOUT),
+            tombstone_grace: Mutex::new(DEFAULT_TOMBSTONE_GRACE),
+            early_fast: Mutex::new(FxHashMap::default()),
+            sid_exhaustion: Mutex::new(SidExhaustion::default()),
+            metrics: HostMetrics::new(),
+            answered: Answered::default(),
+            shared: SharedArena::default(),
         }
     }
 }
 
+/// A call started with `dispatch_spawn` whose response was not taken yet.
+pub(crate) struct Dispatched {
+    pub(crate) rx: UnaryReceiver,
+    /// Where the response goes if the route is cached.
+    pub(crate) cache: Option<CacheSlot>,
+}
+
 // Safety: OK
 unsafe impl Send for HostContext {}
 unsafe impl Sync for HostContext {}
 
+/// Number of panic reports kept per plugin.
+const MAX_PANIC_REPORTS: usize = 16;
+
+/// Marker identifying a live [`PluginContext`] ("nylonctx").
+const CONTEXT_CANARY: u64 = u64::from_be_bytes(*b"nylonctx");
+
+/// Per-plugin view of the host.
+///
+/// A pointer to this is what the plugin receives as `host_ctx`, so callbacks
+/// can tell which plugin they were invoked by.
+pub(crate) struct PluginContext {
+    /// Always [`CONTEXT_CANARY`] while the context is live.
+    canary: u64,
+    pub(crate) name: String,
+    pub(crate) host: Arc<HostContext>,
+    pub(crate) metrics: Metrics,
+    pub(crate) panic_reports: Mutex<VecDeque<PanicReport>>,
+    pub(crate) schemas: RwLock<Schemas>,
+    /// State the plugin stored in `NR_SCOPE_PLUGIN`. A reload gets a new
+    /// context, and with it empty state.
+    pub(crate) state: RwLock<HashMap<String, Vec<u8>>>,
+    /// Calls whose response has not arrived yet. See [`InFlight`].
+    in_flight: AtomicU64,
+    /// Signalled when `in_flight` drops to zero, under `drain_lock`.
+    drained: Condvar,
+    drain_lock: Mutex<()>,
+    /// Set once the plugin is being shut down; callbacks then return at once.
+    revoked: AtomicBool,
+    /// Whether the plugin declared `ECHOES_NONCE_KEY` during `init`.
+    echoes_nonce: AtomicBool,
+}
+
+impl PluginContext {
+    pub(crate) fn new(name: &str, host: Arc<HostContext>) -> Self {
+        Self {
+            canary: CONTEXT_CANARY,
+            name: name.to_string(),
+            host,
+            metrics: Metrics::new(),
+            panic_reports: Mutex::new(VecDeque::with_capacity(MAX_PANIC_REPORTS)),
+            schemas: RwLock::new(Schemas::default()),
+            state: RwLock::new(HashMap::new()),
+            in_flight: AtomicU64::new(0),
+            drained: Condvar::new(),
+            drain_lock: Mutex::new(()),
+            revoked: AtomicBool::new(false),
+            echoes_nonce: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether results for unary calls carry the call's nonce.
+    pub(crate) fn echoes_nonce(&self) -> bool {
+        self.echoes_nonce.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_echoes_nonce(&self) {
+        self.echoes_nonce.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `host_ctx` looks like a context issued by a host: non-null
+    /// and carrying the canary. Catches plugins that pass a pointer of their
+    /// own or have scribbled over the context, before any of it is used.
+    ///
+    /// This is a sanity check, not a liveness check. A context is freed once
+    /// its plugin is unloaded, and reading a freed context is undefined
+    /// behavior whatever the canary says; plugins must not call back after
+    /// `shutdown`.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be null or readable for `size_of::<PluginContext>()`
+    /// bytes, which rules out a context that has been dropped.
+    #[inline(always)]
+    pub(crate) unsafe fn is_valid(host_ctx: *const c_void) -> bool {
+        !host_ctx.is_null()
+            && std::ptr::addr_of!((*(host_ctx as *const Self)).canary).read_unaligned()
+                == CONTEXT_CANARY
+    }
+
+    /// Whether `host_ctx` is a valid context (see [`is_valid`](Self::is_valid))
+    /// whose callbacks have not been revoked.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`is_valid`](Self::is_valid).
+    #[inline(always)]
+    pub(crate) unsafe fn is_live(host_ctx: *const c_void) -> bool {
+        Self::is_valid(host_ctx) && !(*(host_ctx as *const Self)).is_revoked()
+    }
+
+    /// Stop serving the plugin's callbacks, ahead of its `shutdown`.
+    pub(crate) fn revoke(&self) {
+        self.revoked.store(true, Ordering::Release);
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_revoked(&self) -> bool {
+        self.revoked.load(Ordering::Acquire)
+    }
+
+    /// Calls to the plugin whose response has not arrived yet.
+    pub(crate) fn in_flight(&self) -> u64 {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Wait until no calls are in flight or `deadline` passes. Returns
+    /// whether the plugin drained.
+    pub(crate) fn wait_drained(&self, deadline: Instant) -> bool {
+        let mut guard = self.drain_lock.lock();
+        while self.in_flight() > 0 {
+            if self.drained.wait_until(&mut guard, deadline).timed_out() {
+                return self.in_flight() == 0;
+            }
+        }
+        true
+    }
+
+    /// The message of the latest panic reported for `sid`.
+    pub(crate) fn panic_message(&self, sid: u64) -> Option<String> {
+        let reports = self.panic_reports.lock();
+        let report = reports.iter().rev().find(|report| report.sid == sid)?;
+        Some(report.message.clone())
+    }
+
+    /// Record a panic report, evicting the oldest one when full.
+    pub(crate) fn push_panic_report(&self, report: PanicReport) {
+        let mut reports = self.panic_reports.lock();
+        if reports.len() == MAX_PANIC_REPORTS {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+}
+
+/// Counts a call as in flight on its plugin until dropped. Held by the
+/// caller until the response arrives, by a stream until its last frame or
+/// until its receiver is dropped, and by the pending entry of a dispatched
+/// call until its response arrives. The plugin is not shut down while any
+/// are held, up to the host's drain timeout.
+pub(crate) struct InFlight(Arc<PluginContext>);
+
+impl std::fmt::Debug for InFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("InFlight").field(&self.0.name).finish()
+    }
+}
+
+impl InFlight {
+    pub(crate) fn new(ctx: &Arc<PluginContext>) -> Self {
+        ctx.in_flight.fetch_add(1, Ordering::AcqRel);
+        Self(ctx.clone())
+    }
+
+    /// Count a call from the host, or fail with `Overloaded` if the plugin
+    /// is at the host's in-flight limit.
+    pub(crate) fn acquire(ctx: &Arc<PluginContext>) -> Result<Self, NylonRingHostError> {
+        let in_flight = Self::new(ctx);
+        match ctx.host.config.max_in_flight {
+            Some(limit) if ctx.in_flight() > limit => Err(NylonRingHostError::Overloaded {
+                plugin: ctx.name.clone(),
+                limit,
+            }),
+            _ => Ok(in_flight),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Taking the lock orders this after a drainer's check, so the
+            // wakeup is not lost.
+            let _guard = self.0.drain_lock.lock();
+            self.0.drained.notify_all();
+        }
+    }
+}
+
+impl Drop for PluginContext {
+    fn drop(&mut self) {
+        // Helps a stale `host_ctx` stand out in a debugger. It does not make
+        // using one sound: the memory is freed right after this.
+        self.canary = 0;
+    }
+}
+
+/// The shard holding `key`, a SID without its mode byte.
 #[inline(always)]
-fn get_shard(ctx: &HostContext, sid: u64) -> &FastPendingMap {
+fn get_shard(ctx: &HostContext, key: u64) -> &FastPendingMap {
     unsafe {
         ctx.pending_shards
-            .get_unchecked((sid as usize) & SHARD_MASK)
+            .get_unchecked((key as usize) & ctx.shard_mask)
     }
 }
 
-/// Insert a pending request.
+/// Insert a pending request. Streams count as active until removed.
 pub(crate) fn insert_pending(ctx: &HostContext, sid: u64, pending: Pending) {
-    get_shard(ctx, sid).insert(sid, pending);
+    let key = sid_key(sid);
+    if matches!(pending, Pending::Stream(_)) {
+        ctx.metrics.stream_opened();
+    }
+    get_shard(ctx, key).insert(key, (pending, rt::Instant::now()));
 }
 
 /// Remove and return a pending request.
 pub(crate) fn remove_pending(ctx: &HostContext, sid: u64) -> Option<Pending> {
-    get_shard(ctx, sid).remove(&sid).map(|(_, v)| v)
+    let key = sid_key(sid);
+    #[cfg(not(feature = "slab-pending"))]
+    let removed = get_shard(ctx, key).remove(&key).map(|(_, (v, _))| v);
+    #[cfg(feature = "slab-pending")]
+    let removed = get_shard(ctx, key).remove(key).map(|(v, _)| v);
+    if matches!(removed, Some(Pending::Stream(_))) {
+        ctx.metrics.stream_closed();
+    }
+    removed
 }
 
 /// Reinsert a pending request (used for streaming continuations).
 pub(crate) fn reinsert_pending(ctx: &HostContext, sid: u64, pending: Pending) {
     // Always insert into Global Shard for continuations to support cross-thread access
-    get_shard(ctx, sid).insert(sid, pending);
+    insert_pending(ctx, sid, pending);
 }
 
 /// Get a pending stream sender without removing it (Read Lock).
-pub(crate) fn get_pending_stream(
-    ctx: &HostContext,
-    sid: u64,
-) -> Option<tokio::sync::mpsc::UnboundedSender<crate::types::StreamFrame>> {
-    if let Some(entry) = get_shard(ctx, sid).get(&sid) {
-        if let crate::types::Pending::Stream(tx) = entry.value() {
+#[cfg(not(feature = "slab-pending"))]
+pub(crate) fn get_pending_stream(ctx: &HostContext, sid: u64) -> Option<StreamSender> {
+    let key = sid_key(sid);
+    if let Some(entry) = get_shard(ctx, key).get(&key) {
+        if let (Pending::Stream(tx), _) = entry.value() {
             return Some(tx.clone());
         }
     }
     None
 }
 
+/// Get a pending stream sender without removing it.
+#[cfg(feature = "slab-pending")]
+pub(crate) fn get_pending_stream(ctx: &HostContext, sid: u64) -> Option<StreamSender> {
+    let key = sid_key(sid);
+    get_shard(ctx, key).stream(key)
+}
+
+/// Whether `sid` is a stream that has not ended yet.
+#[cfg(not(feature = "slab-pending"))]
+pub(crate) fn has_pending_stream(ctx: &HostContext, sid: u64) -> bool {
+    let key = sid_key(sid);
+    get_shard(ctx, key)
+        .get(&key)
+        .is_some_and(|entry| matches!(entry.value(), (Pending::Stream(_), _)))
+}
+
+/// Whether `sid` is a stream that has not ended yet.
+#[cfg(feature = "slab-pending")]
+pub(crate) fn has_pending_stream(ctx: &HostContext, sid: u64) -> bool {
+    get_pending_stream(ctx, sid).is_some()
+}
+
+/// Visit the pending requests of each shard, in shard order. `f` gets the
+/// shard index, the request and when it was registered.
+pub(crate) fn for_each_pending(ctx: &HostContext, mut f: impl FnMut(usize, &Pending, rt::Instant)) {
+    for (index, shard) in ctx.pending_shards.iter().enumerate() {
+        #[cfg(not(feature = "slab-pending"))]
+        for entry in shard.iter() {
+            let (pending, since) = entry.value();
+            f(index, pending, *since);
+        }
+        #[cfg(feature = "slab-pending")]
+        shard.for_each(|pending, since| f(index, pending, since));
+    }
+}
+
+/// Keep the pending requests `keep` returns `true` for, given each one and
+/// when it was registered, and drop the others.
+pub(crate) fn retain_pending(
+    ctx: &HostContext,
+    mut keep: impl FnMut(&Pending, rt::Instant) -> bool,
+) {
+    for shard in ctx.pending_shards.iter() {
+        #[cfg(not(feature = "slab-pending"))]
+        shard.retain(|_, (pending, since)| keep(pending, *since));
+        #[cfg(feature = "slab-pending")]
+        shard.retain(&mut keep);
+    }
+}
+
 // --- Thread Local Optimization for Unary Results ---
+
+/// A thread-local result slot, bound by one host for one SID. Callbacks only
+/// use it when both match, so other hosts (or other calls) replying on this
+/// thread go through the pending map instead.
+pub(crate) struct BoundSlot<T> {
+    pub(crate) host: *const HostContext,
+    pub(crate) sid: u64,
+    pub(crate) slot: *mut T,
+}
+
+/// State the caller stored for one call before `handle`, removed with
+/// whatever the plugin added once the call is over, or on drop if it failed.
+pub(crate) struct CallState<'a> {
+    ctx: &'a HostContext,
+    sid: u64,
+}
+
+impl<'a> CallState<'a> {
+    pub(crate) fn seed(ctx: &'a HostContext, sid: u64, seed: &[(&str, &[u8])]) -> Self {
+        let state: HashMap<_, _> = seed
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.to_vec()))
+            .collect();
+        ctx.state_per_sid.insert(sid, state.into());
+        Self { ctx, sid }
+    }
+
+    /// Remove the call's state and return it.
+    pub(crate) fn take(self) -> HashMap<String, Vec<u8>> {
+        self.ctx
+            .state_per_sid
+            .remove(&self.sid)
+            .map(|(_, state)| state.values)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for CallState<'_> {
+    fn drop(&mut self) {
+        self.ctx.state_per_sid.remove(&self.sid);
+    }
+}
+
+// Manual impls: the derives would require `T: Copy`.
+impl<T> Clone for BoundSlot<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BoundSlot<T> {}
+
+impl<T> BoundSlot<T> {
+    pub(crate) const EMPTY: Self = Self {
+        host: std::ptr::null(),
+        sid: 0,
+        slot: std::ptr::null_mut(),
+    };
+
+    /// The slot, if it was bound by `host` for `sid`.
+    #[inline(always)]
+    pub(crate) fn get(&self, host: &HostContext, sid: u64) -> Option<*mut T> {
+        (!self.slot.is_null() && std::ptr::eq(self.host, host) && self.sid == sid)
+            .then_some(self.slot)
+    }
+
+    fn is(&self, other: &Self) -> bool {
+        std::ptr::eq(self.host, other.host) && self.sid == other.sid && self.slot == other.slot
+    }
+}
+
+/// A binding of a thread-local result slot, undone on drop, even when
+/// unwinding, so the cell never keeps a pointer to a slot that is gone.
+///
+/// Bindings nest: a fast call made during another one's `handle` binds over
+/// it and restores it. They must be undone in reverse order, which a call
+/// that awaited while bound (and so interleaved with another call on the
+/// thread, or moved to another thread) would break. Debug builds panic on
+/// that instead of leaving the cell pointing at a dead slot.
+pub(crate) struct TlsSlotGuard<T: 'static> {
+    cell: &'static LocalKey<Cell<BoundSlot<T>>>,
+    bound: BoundSlot<T>,
+    previous: BoundSlot<T>,
+    /// Bound on, and so only undone on, this thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T: 'static> TlsSlotGuard<T> {
+    pub(crate) fn bind(cell: &'static LocalKey<Cell<BoundSlot<T>>>, bound: BoundSlot<T>) -> Self {
+        let previous = cell.with(|cell| cell.replace(bound));
+        Self {
+            cell,
+            bound,
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> Drop for TlsSlotGuard<T> {
+    fn drop(&mut self) {
+        let current = self.cell.with(|cell| cell.replace(self.previous));
+        if cfg!(debug_assertions) && !current.is(&self.bound) && !std::thread::panicking() {
+            panic!(
+                "thread-local result slot for sid {} was rebound by sid {} and not restored",
+                self.bound.sid, current.sid
+            );
+        }
+    }
+}
+
 thread_local! {
-    pub(crate) static CURRENT_UNARY_RESULT: Cell<*mut UnaryResultSlot> = const { Cell::new(std::ptr::null_mut()) };
-    pub(crate) static CURRENT_UNARY_TX: Cell<*mut UnarySender> = const { Cell::new(std::ptr::null_mut()) };
+    pub(crate) static CURRENT_UNARY_RESULT: Cell<BoundSlot<UnaryResultSlot>> = const { Cell::new(BoundSlot::EMPTY) };
+    pub(crate) static CURRENT_UNARY_TX: Cell<BoundSlot<UnarySender>> = const { Cell::new(BoundSlot::EMPTY) };
+    /// Fast calls the plugin will answer after `handle` returns, moved to
+    /// the pending map. Taken by the caller once `handle` is back.
+    static DEFERRED_FAST: RefCell<Vec<(u64, UnaryReceiver)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Move the fast call `sid` to the pending map, for an answer that arrives
+/// after `handle` returns. Its caller on this thread picks up the receiver
+/// with [`take_deferred_fast`].
+pub(crate) fn defer_fast(ctx: &HostContext, sid: u64) {
+    DEFERRED_FAST.with(|deferred| {
+        let mut deferred = deferred.borrow_mut();
+        if deferred.iter().any(|(s, _)| *s == sid) {
+            return;
+        }
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        insert_pending(ctx, sid, Pending::Unary(tx));
+        deferred.push((sid, rx));
+    });
+}
+
+/// The receiver for `sid`, if the plugin deferred it during `handle`.
+pub(crate) fn take_deferred_fast(sid: u64) -> Option<UnaryReceiver> {
+    DEFERRED_FAST.with(|deferred| {
+        let mut deferred = deferred.borrow_mut();
+        let at = deferred.iter().position(|(s, _)| *s == sid)?;
+        Some(deferred.swap_remove(at).1)
+    })
+}
+
+/// Keep the answer to the fast call `sid` for its caller, who has not
+/// picked it up with [`late_fast`] yet.
+pub(crate) fn park_fast(ctx: &HostContext, sid: u64, status: NrStatus, data: Vec<u8>) {
+    let mut early = ctx.early_fast.lock();
+    // The caller may have started waiting since the map was checked.
+    if let Some(Pending::Unary(tx)) = remove_pending(ctx, sid) {
+        let _ = tx.send((status, data));
+        return;
+    }
+    if early.len() < MAX_EARLY_FAST {
+        early.insert(sid_key(sid), (status, data));
+    }
+}
+
+/// The receiver for the fast call `sid`, which `handle` returned
+/// `Accepted` for without calling `complete_later`. Its answer may already
+/// be parked.
+pub(crate) fn late_fast(ctx: &HostContext, sid: u64) -> UnaryReceiver {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let mut early = ctx.early_fast.lock();
+    match early.remove(&sid_key(sid)) {
+        Some(answer) => {
+            let _ = tx.send(answer);
+        }
+        None => insert_pending(ctx, sid, Pending::Unary(tx)),
+    }
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static SLOT: Cell<BoundSlot<u8>> = const { Cell::new(BoundSlot::EMPTY) };
+    }
+
+    fn bound(sid: u64, slot: &mut u8) -> BoundSlot<u8> {
+        BoundSlot {
+            host: std::ptr::null(),
+            sid,
+            slot,
+        }
+    }
+
+    fn current() -> BoundSlot<u8> {
+        SLOT.with(Cell::get)
+    }
+
+    #[test]
+    fn test_nested_bindings_restore_in_order() {
+        let (mut outer_slot, mut inner_slot) = (0, 0);
+        let outer = bound(1, &mut outer_slot);
+        let inner = bound(2, &mut inner_slot);
+        {
+            let _outer = TlsSlotGuard::bind(&SLOT, outer);
+            {
+                let _inner = TlsSlotGuard::bind(&SLOT, inner);
+                assert!(current().is(&inner));
+            }
+            assert!(current().is(&outer));
+        }
+        assert!(current().is(&BoundSlot::EMPTY));
+    }
+
+    #[test]
+    fn test_unwinding_clears_the_binding() {
+        let mut slot = 0;
+        let binding = bound(1, &mut slot);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _bound = TlsSlotGuard::bind(&SLOT, binding);
+            panic!("handle panicked");
+        }));
+        assert!(result.is_err());
+        assert!(current().is(&BoundSlot::EMPTY));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "slot for sid 1 was rebound by sid 2")]
+    fn test_out_of_order_unbind_panics() {
+        let (mut first_slot, mut second_slot) = (0, 0);
+        let first = TlsSlotGuard::bind(&SLOT, bound(1, &mut first_slot));
+        let _second = TlsSlotGuard::bind(&SLOT, bound(2, &mut second_slot));
+        drop(first);
+    }
 }
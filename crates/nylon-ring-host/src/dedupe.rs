@@ -0,0 +1,163 @@
+//! Duplicate frame suppression for streams.
+//!
+//! A stream opened with [`StreamOptions::dedupe`](crate::StreamOptions::dedupe)
+//! strips the header off frames the plugin numbered with
+//! [`nylon_ring::encode_seq_frame`] and delivers each sequence number once:
+//! a number delivered before is a duplicate, and one more than
+//! [`DedupeOptions::window`] below the highest delivered is a replay. Both
+//! are dropped. Frames without a number pass through, unless
+//! [`DedupeOptions::hash_window`] is set, in which case a frame equal to one
+//! of the last few unnumbered frames is dropped as a duplicate too. Only
+//! `Ok` frames are checked; the final frame is always delivered.
+
+use nylon_ring::decode_seq_frame;
+use std::collections::{BTreeSet, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+
+/// How a stream drops repeated frames.
+#[derive(Debug, Clone, Copy)]
+pub struct DedupeOptions {
+    /// How far below the highest delivered sequence number a frame can be
+    /// and still be delivered, if it was not already.
+    pub window: u64,
+    /// Unnumbered frames to remember, to drop repeats of; 0 turns hashing
+    /// off. Equal frames sent on purpose within the window are lost too, so
+    /// only use it for streams whose frames are unique.
+    pub hash_window: usize,
+}
+
+impl Default for DedupeOptions {
+    fn default() -> Self {
+        Self {
+            window: 64,
+            hash_window: 0,
+        }
+    }
+}
+
+/// Frames a deduplicating stream dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupeStats {
+    /// Frames whose sequence number, or contents, were delivered already.
+    pub duplicates: u64,
+    /// Numbered frames too far behind the highest delivered one.
+    pub replays: u64,
+}
+
+pub(crate) struct Dedupe {
+    options: DedupeOptions,
+    /// Sequence numbers delivered within the window of the highest one.
+    delivered: BTreeSet<u64>,
+    hasher: RandomState,
+    /// Hashes of the last unnumbered frames, oldest first.
+    recent: VecDeque<u64>,
+    pub(crate) stats: DedupeStats,
+}
+
+impl Dedupe {
+    pub(crate) fn new(options: DedupeOptions) -> Self {
+        Self {
+            options,
+            delivered: BTreeSet::new(),
+            hasher: RandomState::new(),
+            recent: VecDeque::new(),
+            stats: DedupeStats::default(),
+        }
+    }
+
+    /// Whether an `Ok` frame with `data`, on `channel`, is to be delivered.
+    /// Strips the sequence header off `data` if it has one.
+    pub(crate) fn admit(&mut self, data: &mut Vec<u8>, channel: Option<&str>) -> bool {
+        let Some((seq, rest)) = decode_seq_frame(data) else {
+            return self.admit_unnumbered(data, channel);
+        };
+        let header = data.len() - rest.len();
+        if let Some(&highest) = self.delivered.last() {
+            if seq < highest.saturating_sub(self.options.window) {
+                self.stats.replays += 1;
+                return false;
+            }
+        }
+        if !self.delivered.insert(seq) {
+            self.stats.duplicates += 1;
+            return false;
+        }
+        let highest = *self.delivered.last().unwrap();
+        let oldest = highest.saturating_sub(self.options.window);
+        self.delivered = self.delivered.split_off(&oldest);
+        data.drain(..header);
+        true
+    }
+
+    fn admit_unnumbered(&mut self, data: &[u8], channel: Option<&str>) -> bool {
+        if self.options.hash_window == 0 {
+            return true;
+        }
+        let hash = self.hasher.hash_one((channel, data));
+        if self.recent.contains(&hash) {
+            self.stats.duplicates += 1;
+            return false;
+        }
+        if self.recent.len() == self.options.hash_window {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(hash);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nylon_ring::encode_seq_frame;
+
+    fn admit(dedupe: &mut Dedupe, seq: u64) -> bool {
+        let mut data = encode_seq_frame(seq, b"x").as_slice().to_vec();
+        let admitted = dedupe.admit(&mut data, None);
+        assert!(!admitted || data == b"x");
+        admitted
+    }
+
+    #[test]
+    fn test_sequence_window() {
+        let mut dedupe = Dedupe::new(DedupeOptions {
+            window: 2,
+            hash_window: 0,
+        });
+        assert!(admit(&mut dedupe, 5));
+        assert!(!admit(&mut dedupe, 5));
+        // Late, but within the window.
+        assert!(admit(&mut dedupe, 3));
+        assert!(!admit(&mut dedupe, 3));
+        assert!(admit(&mut dedupe, 9));
+        // 3 and 5 fell out of the window.
+        assert!(!admit(&mut dedupe, 5));
+        assert!(admit(&mut dedupe, 7));
+        assert_eq!(
+            dedupe.stats,
+            DedupeStats {
+                duplicates: 2,
+                replays: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unnumbered_frames_are_hashed_only_on_request() {
+        let mut off = Dedupe::new(DedupeOptions::default());
+        assert!(off.admit(&mut b"a".to_vec(), None));
+        assert!(off.admit(&mut b"a".to_vec(), None));
+
+        let mut on = Dedupe::new(DedupeOptions {
+            window: 0,
+            hash_window: 2,
+        });
+        assert!(on.admit(&mut b"a".to_vec(), None));
+        assert!(!on.admit(&mut b"a".to_vec(), None));
+        assert!(on.admit(&mut b"a".to_vec(), Some("other")));
+        assert!(on.admit(&mut b"b".to_vec(), None));
+        // "a" on the default channel was pushed out by the last two.
+        assert!(on.admit(&mut b"a".to_vec(), None));
+        assert_eq!(on.stats.duplicates, 1);
+    }
+}
@@ -0,0 +1,91 @@
+//! What a host holds on to, to find leaks.
+//!
+//! Calls a plugin never answers stay in the pending map, and state stored
+//! under a SID nobody removes stays in the state map. [`HostDiagnostics`]
+//! counts both; [`purge_stale`] drops what has been there too long.
+
+use crate::context::{self, HostContext};
+use crate::rt::Instant;
+use crate::types::Pending;
+use std::time::Duration;
+
+/// A snapshot of the pending and state maps of a host, as returned by
+/// [`NylonRingHost::diagnostics`](crate::NylonRingHost::diagnostics).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostDiagnostics {
+    /// Requests waiting on a result, per shard of the pending map.
+    pub shards: Vec<ShardDiagnostics>,
+    /// SIDs with state stored under them.
+    pub sids_with_state: usize,
+    /// Key and value bytes of that state.
+    pub state_bytes: usize,
+    /// How long the oldest pending request has been waiting.
+    pub oldest_pending: Option<Duration>,
+}
+
+impl HostDiagnostics {
+    /// Unary and dispatched calls waiting on a result, over all shards.
+    pub fn pending_unary(&self) -> usize {
+        self.shards.iter().map(|shard| shard.unary).sum()
+    }
+
+    /// Streams not yet ended, over all shards.
+    pub fn pending_streams(&self) -> usize {
+        self.shards.iter().map(|shard| shard.streams).sum()
+    }
+}
+
+/// Requests in one shard of the pending map.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardDiagnostics {
+    /// Unary calls, including calls made with `dispatch_spawn`.
+    pub unary: usize,
+    pub streams: usize,
+}
+
+pub(crate) fn collect(ctx: &HostContext) -> HostDiagnostics {
+    let now = Instant::now();
+    let mut shards = vec![ShardDiagnostics::default(); ctx.pending_shards.len()];
+    let mut oldest = None::<Instant>;
+    context::for_each_pending(ctx, |shard, pending, since| {
+        match pending {
+            Pending::Unary(_) | Pending::Dispatched(..) => shards[shard].unary += 1,
+            Pending::Stream(_) => shards[shard].streams += 1,
+        }
+        oldest = Some(oldest.map_or(since, |oldest| oldest.min(since)));
+    });
+
+    let mut sids_with_state = 0;
+    let mut state_bytes = 0;
+    for entry in ctx.state_per_sid.iter() {
+        sids_with_state += 1;
+        state_bytes += entry
+            .values
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum::<usize>();
+    }
+
+    HostDiagnostics {
+        shards,
+        sids_with_state,
+        state_bytes,
+        oldest_pending: oldest.map(|oldest| now.saturating_duration_since(oldest)),
+    }
+}
+
+/// Drop unary calls pending for longer than `older_than`, and state stored
+/// under a SID longer than that. Returns how many calls were dropped.
+pub(crate) fn purge_stale(ctx: &HostContext, older_than: Duration) -> usize {
+    let now = Instant::now();
+    let stale = |since: Instant| now.saturating_duration_since(since) > older_than;
+    let mut purged = 0;
+    // Dropping a call's sender fails its caller with `OneshotClosed`.
+    context::retain_pending(ctx, |pending, since| {
+        let keep = matches!(pending, Pending::Stream(_)) || !stale(since);
+        purged += usize::from(!keep);
+        keep
+    });
+    ctx.state_per_sid.retain(|_, state| !stale(state.since));
+    purged
+}
@@ -0,0 +1,281 @@
+//! Memoized responses to calls between plugins.
+//!
+//! A [`DispatchCacheRule`] set with
+//! [`NylonRingHost::cache_dispatch`](crate::NylonRingHost::cache_dispatch)
+//! covers the calls one plugin makes to an entry of another through
+//! `dispatch_spawn`. While a response to the same payload is cached, the call
+//! is answered from the cache and the target's `handle` is not run. Only
+//! `Ok` responses are cached, once the caller has taken them, and not if the
+//! route was invalidated (or the target reloaded) since the call was made.
+
+use parking_lot::{Mutex, RwLock};
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Entries a rule keeps unless configured otherwise.
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+/// How long and how many responses to keep for one route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchCacheRule {
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl DispatchCacheRule {
+    /// Keep each response for `ttl`, and at most 1024 of them.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Keep at most `max` responses. When a new one does not fit, the one
+    /// closest to expiring is dropped. 0 disables the rule.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+}
+
+/// `(caller, target, entry)`.
+type Route = (String, String, String);
+
+/// Hash of a route, so lookups need not build a [`Route`].
+fn route_hash(caller: &str, target: &str, entry: &str) -> u64 {
+    let mut hasher = FxHasher::default();
+    (caller, target, entry).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A route and its responses. Routes with the same hash share a bucket.
+struct RouteTable {
+    route: Route,
+    table: Arc<Mutex<Table>>,
+}
+
+impl RouteTable {
+    fn is(&self, caller: &str, target: &str, entry: &str) -> bool {
+        let (c, t, e) = &self.route;
+        c == caller && t == target && e == entry
+    }
+}
+
+struct Cached {
+    expires: Instant,
+    data: Vec<u8>,
+}
+
+/// The responses cached for one route, by payload.
+struct Table {
+    rule: DispatchCacheRule,
+    entries: FxHashMap<Vec<u8>, Cached>,
+    /// Bumped whenever the table is invalidated, so responses to calls
+    /// made before that are not cached.
+    generation: u64,
+}
+
+impl Table {
+    fn insert(&mut self, payload: Vec<u8>, data: Vec<u8>) {
+        if self.rule.max_entries == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self.entries.len() >= self.rule.max_entries && !self.entries.contains_key(&payload) {
+            self.entries.retain(|_, cached| cached.expires > now);
+            if self.entries.len() >= self.rule.max_entries {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.expires)
+                    .map(|(payload, _)| payload.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        let expires = now + self.rule.ttl;
+        self.entries.insert(payload, Cached { expires, data });
+    }
+}
+
+/// Where a response to a cacheable call goes once it is taken.
+pub(crate) struct CacheSlot {
+    table: Arc<Mutex<Table>>,
+    payload: Vec<u8>,
+    /// The table's generation when the call was made.
+    generation: u64,
+}
+
+impl CacheSlot {
+    /// Cache `data`, unless the table was invalidated since the call was
+    /// made: the response may come from the plugin before a reload.
+    pub(crate) fn fill(self, data: &[u8]) {
+        let mut table = self.table.lock();
+        if table.generation == self.generation {
+            table.insert(self.payload, data.to_vec());
+        }
+    }
+}
+
+/// What the cache knows about a call.
+pub(crate) enum Lookup {
+    /// No rule covers the route.
+    Uncached,
+    /// A live response to the same payload.
+    Hit(Vec<u8>),
+    /// Covered, but nothing live is cached; fill the slot with the response.
+    Miss(CacheSlot),
+}
+
+/// The dispatch caches of one host.
+#[derive(Default)]
+pub(crate) struct DispatchCache {
+    tables: RwLock<FxHashMap<u64, Vec<RouteTable>>>,
+}
+
+impl DispatchCache {
+    /// Cache calls from `caller` to `entry` of `target` under `rule`,
+    /// dropping whatever the route had cached. `None` removes the rule.
+    pub(crate) fn set_rule(
+        &self,
+        caller: &str,
+        target: &str,
+        entry: &str,
+        rule: Option<DispatchCacheRule>,
+    ) {
+        let hash = route_hash(caller, target, entry);
+        let mut tables = self.tables.write();
+        let bucket = tables.entry(hash).or_default();
+        bucket.retain(|route| !route.is(caller, target, entry));
+        if let Some(rule) = rule {
+            let table = Table {
+                rule,
+                entries: FxHashMap::default(),
+                generation: 0,
+            };
+            bucket.push(RouteTable {
+                route: (caller.to_string(), target.to_string(), entry.to_string()),
+                table: Arc::new(Mutex::new(table)),
+            });
+        }
+        if bucket.is_empty() {
+            tables.remove(&hash);
+        }
+    }
+
+    pub(crate) fn lookup(&self, caller: &str, target: &str, entry: &str, payload: &[u8]) -> Lookup {
+        let table = {
+            let tables = self.tables.read();
+            if tables.is_empty() {
+                return Lookup::Uncached;
+            }
+            let route = tables
+                .get(&route_hash(caller, target, entry))
+                .and_then(|bucket| bucket.iter().find(|route| route.is(caller, target, entry)));
+            match route {
+                Some(route) => route.table.clone(),
+                None => return Lookup::Uncached,
+            }
+        };
+        let generation = {
+            let mut guard = table.lock();
+            if let Some(cached) = guard.entries.get(payload) {
+                if cached.expires > Instant::now() {
+                    return Lookup::Hit(cached.data.clone());
+                }
+                guard.entries.remove(payload);
+            }
+            guard.generation
+        };
+        Lookup::Miss(CacheSlot {
+            table,
+            payload: payload.to_vec(),
+            generation,
+        })
+    }
+
+    /// Drop the responses cached from `target`, for every caller; only
+    /// those of `entry` if given. Rules stay in place.
+    pub(crate) fn invalidate(&self, target: &str, entry: Option<&str>) {
+        for route in self.tables.read().values().flatten() {
+            let (_, route_target, route_entry) = &route.route;
+            if route_target == target && entry.is_none_or(|entry| entry == route_entry) {
+                let mut table = route.table.lock();
+                table.entries.clear();
+                table.generation += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(cache: &DispatchCache, payload: &[u8]) -> Option<Vec<u8>> {
+        match cache.lookup("a", "b", "get", payload) {
+            Lookup::Hit(data) => Some(data),
+            Lookup::Miss(slot) => {
+                slot.fill(&[payload, b"!"].concat());
+                None
+            }
+            Lookup::Uncached => panic!("route not covered"),
+        }
+    }
+
+    #[test]
+    fn test_ttl_and_capacity() {
+        let cache = DispatchCache::default();
+        assert!(matches!(
+            cache.lookup("a", "b", "get", b"x"),
+            Lookup::Uncached
+        ));
+
+        let rule = DispatchCacheRule::new(Duration::from_secs(60)).max_entries(2);
+        cache.set_rule("a", "b", "get", Some(rule));
+        assert!(matches!(
+            cache.lookup("c", "b", "get", b"x"),
+            Lookup::Uncached
+        ));
+        assert_eq!(hit(&cache, b"x"), None);
+        assert_eq!(hit(&cache, b"x"), Some(b"x!".to_vec()));
+        assert_eq!(hit(&cache, b"y"), None);
+        // A third payload pushes out the first.
+        assert_eq!(hit(&cache, b"z"), None);
+        assert_eq!(hit(&cache, b"x"), None);
+        assert_eq!(hit(&cache, b"z"), Some(b"z!".to_vec()));
+
+        cache.invalidate("b", Some("other"));
+        assert_eq!(hit(&cache, b"z"), Some(b"z!".to_vec()));
+        cache.invalidate("b", None);
+        assert_eq!(hit(&cache, b"z"), None);
+
+        // A response to a call made before an invalidation is dropped.
+        let Lookup::Miss(stale) = cache.lookup("a", "b", "get", b"s") else {
+            panic!("expected a miss");
+        };
+        cache.invalidate("b", None);
+        stale.fill(b"old");
+        assert_eq!(hit(&cache, b"s"), None);
+        assert_eq!(hit(&cache, b"s"), Some(b"s!".to_vec()));
+
+        cache.set_rule(
+            "a",
+            "b",
+            "get",
+            Some(DispatchCacheRule::new(Duration::ZERO)),
+        );
+        assert_eq!(hit(&cache, b"x"), None);
+        assert_eq!(hit(&cache, b"x"), None);
+
+        cache.set_rule("a", "b", "get", None);
+        assert!(matches!(
+            cache.lookup("a", "b", "get", b"x"),
+            Lookup::Uncached
+        ));
+    }
+}
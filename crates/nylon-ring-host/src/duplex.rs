@@ -0,0 +1,133 @@
+//! Full-duplex streams.
+//!
+//! [`DuplexStream`] ties the receiving half of a stream to the
+//! [`PluginHandle::send_stream_data`] and [`PluginHandle::close_stream`]
+//! calls that feed it, so the SID cannot outlive the stream or be used
+//! after it was closed.
+
+use crate::stream::StreamReceiver;
+use crate::types::{Result, StreamFrame};
+use crate::{NylonRingHostError, PluginHandle};
+use nylon_ring::NrStatus;
+
+/// A stream the host both sends to and receives from, as returned by
+/// [`PluginHandle::open_duplex`].
+///
+/// Once either side closes the stream, [`send`](Self::send) fails with
+/// [`NylonRingHostError::UnknownStream`]. [`next`](Self::next) yields the
+/// frames the plugin sends, its final frame last, then `None`. Dropping the
+/// stream before it ended cancels it, which calls the plugin's
+/// `stream_close`.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+/// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+/// # let mut host = NylonRingHost::new();
+/// # host.load_static("mock", testing::mock_plugin())?;
+/// let plugin = host.plugin("mock").unwrap();
+/// let mut duplex = plugin.open_duplex("open", b"").await?;
+///
+/// duplex.send(b"ping")?;
+/// assert_eq!(duplex.next().await.unwrap().data, b"ping");
+///
+/// duplex.close()?;
+/// assert!(duplex.send(b"late").is_err());
+/// assert_eq!(duplex.next().await.unwrap().status, NrStatus::StreamEnd);
+/// assert!(duplex.next().await.is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub struct DuplexStream {
+    plugin: PluginHandle,
+    sid: u64,
+    rx: StreamReceiver,
+    /// The host closed the stream.
+    closed: bool,
+    /// The plugin's final frame was received.
+    ended: bool,
+}
+
+impl DuplexStream {
+    /// The stream's SID.
+    pub fn sid(&self) -> u64 {
+        self.sid
+    }
+
+    /// Send `data` to the plugin's `stream_data`.
+    pub fn send(&self, data: &[u8]) -> Result<NrStatus> {
+        if self.closed || self.ended {
+            return Err(NylonRingHostError::UnknownStream(self.sid));
+        }
+        self.plugin.send_stream_data(self.sid, data)
+    }
+
+    /// Receive the next frame, or `None` once the plugin's final frame was
+    /// received.
+    pub async fn next(&mut self) -> Option<StreamFrame> {
+        if self.ended {
+            return None;
+        }
+        let frame = self.rx.recv().await;
+        if frame
+            .as_ref()
+            .is_none_or(|frame| frame.status != NrStatus::Ok)
+        {
+            self.ended = true;
+        }
+        frame
+    }
+
+    /// Close the stream from the host side. Frames the plugin sends until
+    /// it ends the stream are still received. Closing a stream that was
+    /// already closed, or that the plugin ended, does nothing.
+    pub fn close(&mut self) -> Result<()> {
+        if self.closed || self.ended {
+            return Ok(());
+        }
+        self.closed = true;
+        match self.plugin.close_stream(self.sid) {
+            // The plugin ended the stream first.
+            Ok(_) | Err(NylonRingHostError::UnknownStream(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        // After `close` the plugin has been told; it ends the stream itself.
+        if !self.closed && !self.ended {
+            let _ = self.plugin.cancel_stream(self.sid);
+        }
+    }
+}
+
+impl std::fmt::Debug for DuplexStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuplexStream")
+            .field("sid", &self.sid)
+            .field("closed", &self.closed)
+            .field("ended", &self.ended)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginHandle {
+    /// Open a stream on `entry` for sending as well as receiving.
+    ///
+    /// Fails with [`NylonRingHostError::MissingFunction`] before calling the
+    /// plugin unless it provides both `stream_data` and `stream_close`.
+    pub async fn open_duplex(&self, entry: &str, payload: &[u8]) -> Result<DuplexStream> {
+        self.require("stream_data")?;
+        self.require("stream_close")?;
+        let (sid, rx) = self.call_stream(entry, payload).await?;
+        Ok(DuplexStream {
+            plugin: self.clone(),
+            sid,
+            rx,
+            closed: false,
+            ended: false,
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Per-entry concurrency limits.
+//!
+//! An entry with a limit runs at most `max_concurrent` unary calls at once.
+//! Up to `queue_depth` more wait for a turn, in order; calls beyond that
+//! fail at once with [`NylonRingHostError::EntryOverloaded`]. A waiting call
+//! that is dropped, by a caller's timeout for instance, or that outwaits the
+//! host's call timeout gives up its place in the queue.
+
+use crate::error::NylonRingHostError;
+use crate::rt;
+use crate::types::Result;
+use parking_lot::RwLock;
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How many calls to one entry may run and wait at once. See
+/// [`PluginHandle::set_entry_limit`](crate::PluginHandle::set_entry_limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryLimit {
+    pub max_concurrent: usize,
+    pub queue_depth: usize,
+}
+
+struct Limiter {
+    limit: EntryLimit,
+    running: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+/// Gives a call's place in the queue back when it stops waiting.
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// The limits of one plugin's entries.
+#[derive(Default)]
+pub(crate) struct EntryLimits {
+    limiters: RwLock<FxHashMap<String, Arc<Limiter>>>,
+}
+
+impl EntryLimits {
+    /// Replace the limit on `entry`. Calls admitted under the old limit
+    /// finish under it.
+    pub(crate) fn set(&self, entry: &str, limit: EntryLimit) {
+        let limiter = Limiter {
+            limit,
+            running: Arc::new(Semaphore::new(limit.max_concurrent)),
+            queued: AtomicUsize::new(0),
+        };
+        self.limiters
+            .write()
+            .insert(entry.to_string(), Arc::new(limiter));
+    }
+
+    pub(crate) fn remove(&self, entry: &str) -> Option<EntryLimit> {
+        self.limiters
+            .write()
+            .remove(entry)
+            .map(|limiter| limiter.limit)
+    }
+
+    /// The limits set, sorted by entry.
+    pub(crate) fn list(&self) -> Vec<(String, EntryLimit)> {
+        let mut limits: Vec<_> = self
+            .limiters
+            .read()
+            .iter()
+            .map(|(entry, limiter)| (entry.clone(), limiter.limit))
+            .collect();
+        limits.sort_by(|a, b| a.0.cmp(&b.0));
+        limits
+    }
+
+    /// Fresh limiters with the same limits, for a reloaded instance.
+    pub(crate) fn copy(&self) -> Self {
+        let copy = Self::default();
+        for (entry, limit) in self.list() {
+            copy.set(&entry, limit);
+        }
+        copy
+    }
+
+    /// Wait for a turn to call `entry`, up to `timeout`. `None` if it has
+    /// no limit. Holding the permit holds the turn.
+    pub(crate) async fn acquire(
+        &self,
+        plugin: &str,
+        entry: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Option<OwnedSemaphorePermit>> {
+        let Some(limiter) = self.limiters.read().get(entry).cloned() else {
+            return Ok(None);
+        };
+        if let Ok(permit) = limiter.running.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        let queued = limiter.queued.fetch_add(1, Ordering::AcqRel);
+        let _queued = Queued(&limiter.queued);
+        if queued >= limiter.limit.queue_depth {
+            return Err(NylonRingHostError::EntryOverloaded {
+                plugin: plugin.to_string(),
+                entry: entry.to_string(),
+                max_concurrent: limiter.limit.max_concurrent,
+                queue_depth: limiter.limit.queue_depth,
+            });
+        }
+        let turn = limiter.running.clone().acquire_owned();
+        let permit = match timeout {
+            Some(timeout) => rt::timeout(timeout, turn)
+                .await
+                .ok_or(NylonRingHostError::Timeout(timeout))?,
+            None => turn.await,
+        };
+        // The semaphore is never closed.
+        Ok(Some(permit.expect("entry limiter closed")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_concurrent: usize, queue_depth: usize) -> EntryLimits {
+        let limits = EntryLimits::default();
+        limits.set(
+            "heavy",
+            EntryLimit {
+                max_concurrent,
+                queue_depth,
+            },
+        );
+        limits
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_fails_fast() {
+        let limits = limits(1, 0);
+        let running = limits.acquire("p", "heavy", None).await.unwrap();
+        assert!(running.is_some());
+        assert!(matches!(
+            limits.acquire("p", "heavy", None).await,
+            Err(NylonRingHostError::EntryOverloaded { .. })
+        ));
+
+        drop(running);
+        assert!(limits.acquire("p", "heavy", None).await.unwrap().is_some());
+        assert!(limits.acquire("p", "other", None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_call_leaves_the_queue() {
+        let limits = limits(1, 1);
+        let _running = limits.acquire("p", "heavy", None).await.unwrap();
+        let timeout = Some(Duration::from_millis(10));
+        assert!(matches!(
+            limits.acquire("p", "heavy", timeout).await,
+            Err(NylonRingHostError::Timeout(_))
+        ));
+        // Its place is free again.
+        assert!(matches!(
+            limits.acquire("p", "heavy", timeout).await,
+            Err(NylonRingHostError::Timeout(_))
+        ));
+    }
+}
@@ -6,9 +6,61 @@ pub enum NylonRingHostError {
     #[error("failed to load plugin library: {0}")]
     FailedToLoadLibrary(#[source] libloading::Error),
 
+    #[error("failed to materialize in-memory plugin library: {0}")]
+    FailedToMaterializeLibrary(#[source] std::io::Error),
+
+    #[error("loading isolated plugin instances is not supported on this platform: {0}")]
+    DuplicateInstanceUnsupported(String),
+
+    #[cfg(feature = "wasm")]
+    #[error("failed to load WebAssembly plugin: {0:#}")]
+    Wasm(wasmtime::Error),
+
+    #[cfg(feature = "serde")]
+    #[error("failed to encode request: {0}")]
+    Encode(#[source] nylon_ring::codec::CodecError),
+
+    /// The plugin answered, but the response did not decode. `bytes` is the
+    /// response as sent.
+    #[cfg(feature = "serde")]
+    #[error("failed to decode response: {source}")]
+    Decode {
+        bytes: Vec<u8>,
+        #[source]
+        source: nylon_ring::codec::CodecError,
+    },
+
+    #[error("invalid schema: {0}")]
+    InvalidSchema(String),
+
+    #[error("payload violates schema at {path:?}: {message}")]
+    SchemaViolation { path: String, message: String },
+
     #[error("invalid plugin path: {0}")]
     InvalidPluginPath(String),
 
+    #[error(
+        "no library built for plugin crate {crate_name:?}; tried: {}",
+        tried.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    PluginArtifactNotFound {
+        crate_name: String,
+        tried: Vec<std::path::PathBuf>,
+    },
+
+    #[error("invalid plugin manifest {path}: {reason}")]
+    InvalidManifest { path: String, reason: String },
+
+    #[error("failed to read plugin directory {path}: {source}")]
+    ReadPluginDir {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("a plugin named {0:?} is already loaded")]
+    PluginAlreadyLoaded(String),
+
     #[error("missing required symbol: {0}")]
     MissingSymbol(String),
 
@@ -18,21 +70,136 @@ pub enum NylonRingHostError {
     #[error("incompatible ABI version: expected {expected}, got {actual}")]
     IncompatibleAbiVersion { expected: u32, actual: u32 },
 
+    #[error("plugin info struct_size mismatch: expected {expected}, got {actual}")]
+    StructSizeMismatch { expected: u32, actual: u32 },
+
+    #[error("failed to read plugin file {path:?}: {source}")]
+    ReadPluginFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot inspect plugin file {path:?}: {reason}")]
+    InspectPluginFailed { path: String, reason: String },
+
+    #[error("plugin file {path:?} exports {symbol:?}, which the load policy does not allow")]
+    UnexpectedExport { path: String, symbol: String },
+
+    #[error("checksum mismatch for {path:?}: expected sha256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+
     #[error("plugin vtable is null")]
     NullPluginVTable,
 
     #[error("plugin vtable missing required functions")]
     MissingRequiredFunctions,
 
-    #[error("plugin init failed with status: {0:?}")]
-    PluginInitFailed(nylon_ring::NrStatus),
+    #[error("plugin {plugin:?} does not provide {function}; see abi_details")]
+    MissingFunction {
+        plugin: String,
+        function: &'static str,
+    },
+
+    #[error(
+        "plugin init failed with status: {status:?}{}",
+        message.as_deref().map(|m| format!(": {m}")).unwrap_or_default()
+    )]
+    PluginInitFailed {
+        status: nylon_ring::NrStatus,
+        /// Reason the plugin stored under `nylon_ring::INIT_ERROR_KEY`.
+        message: Option<String>,
+    },
+
+    #[error("failed to start plugin thread pool: {0}")]
+    SpawnPoolFailed(#[source] std::io::Error),
 
-    #[error("plugin handle failed immediately with status: {0:?}")]
-    PluginHandleFailed(nylon_ring::NrStatus),
+    #[error("stream {0} is closed")]
+    StreamClosed(u64),
+
+    /// The SID is not a stream of this plugin that is still open: it ended,
+    /// was never a stream, or was opened on another plugin.
+    #[error("stream {0} is not an open stream of this plugin")]
+    UnknownStream(u64),
+
+    #[error("stream can no longer be resumed")]
+    StreamExpired,
+
+    #[error("plugin failed with error {code}: {message}")]
+    PluginError { code: u32, message: String },
+
+    #[error(
+        "plugin handle failed immediately with status: {status:?}{}",
+        message.as_deref().map(|m| format!(": {m}")).unwrap_or_default()
+    )]
+    PluginHandleFailed {
+        status: nylon_ring::NrStatus,
+        /// Message of the panic the plugin reported for the call, if any.
+        message: Option<String>,
+    },
 
     #[error("failed to receive response from plugin: {0}")]
     ReceiveResponseFailed(String),
 
+    #[error("plugin dependency cycle between: {0:?}")]
+    DependencyCycle(Vec<String>),
+
+    #[error("plugin not found: {0}")]
+    PluginNotFound(String),
+
+    #[error("invalid route pattern: {0}")]
+    InvalidRoute(String),
+
+    #[error("route {pattern:?} already maps to plugin {existing:?}, cannot map it to {plugin:?}")]
+    AmbiguousRoute {
+        pattern: String,
+        existing: String,
+        plugin: String,
+    },
+
+    #[error("no route for entry: {0}")]
+    NoRoute(String),
+
+    #[error("plugin pool not found: {0}")]
+    PoolNotFound(String),
+
+    #[error("plugin pool {0:?} has no loaded members")]
+    EmptyPool(String),
+
+    #[error("entry {entry:?} was removed when the plugin was reloaded from version {old_version:?} to {new_version:?}")]
+    EntryRemovedInVersion {
+        entry: String,
+        old_version: String,
+        new_version: String,
+    },
+
+    #[error("plugin {plugin:?} already has {limit} calls in flight")]
+    Overloaded { plugin: String, limit: u64 },
+
+    #[error("entry {entry:?} of plugin {plugin:?} already has {max_concurrent} calls running and {queue_depth} queued")]
+    EntryOverloaded {
+        plugin: String,
+        entry: String,
+        max_concurrent: usize,
+        queue_depth: usize,
+    },
+
+    #[error("plugin {plugin:?} is quarantined after failing repeatedly")]
+    Quarantined { plugin: String },
+
+    #[error("invalid host configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("call timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("session ID space exhausted")]
+    SidSpaceExhausted,
+
     #[error("oneshot channel closed")]
     OneshotClosed,
 }
@@ -0,0 +1,45 @@
+//! Liveness probes through the reserved entry
+//! [`HEALTH_ENTRY`](nylon_ring::HEALTH_ENTRY).
+//!
+//! A plugin that serves `__health` answers `Ok` while it is healthy and any
+//! other status when it is not. Plugins that do not serve it reject the
+//! entry like any unknown one, which [`HealthStatus::Unknown`] reports, so
+//! an orchestrator can probe every plugin alike.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use nylon_ring::NrStatus;
+
+/// The outcome of [`PluginHandle::health_check`](crate::PluginHandle::health_check).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The plugin answered `Ok`.
+    Healthy,
+    /// The plugin answered, with another status.
+    Degraded(NrStatus),
+    /// The plugin does not serve the health entry: it rejected the call
+    /// with `Invalid` or `Unsupported`.
+    Unknown,
+    /// No answer within the timeout, or the call could not be made.
+    Unreachable,
+}
+
+impl HealthStatus {
+    pub(crate) fn from_response(response: Option<Result<(NrStatus, Vec<u8>)>>) -> Self {
+        match response {
+            Some(Ok((NrStatus::Ok, _))) => HealthStatus::Healthy,
+            Some(Ok((status, _))) => HealthStatus::Degraded(status),
+            Some(Err(NylonRingHostError::PluginHandleFailed {
+                status: NrStatus::Invalid | NrStatus::Unsupported,
+                ..
+            })) => HealthStatus::Unknown,
+            Some(Err(NylonRingHostError::PluginHandleFailed { status, .. })) => {
+                HealthStatus::Degraded(status)
+            }
+            Some(Err(NylonRingHostError::PluginError { .. })) => {
+                HealthStatus::Degraded(NrStatus::Err)
+            }
+            Some(Err(_)) | None => HealthStatus::Unreachable,
+        }
+    }
+}
@@ -5,51 +5,358 @@
 //! modes including fire-and-forget calls, request-response patterns, and
 //! bidirectional streaming.
 
+mod abi;
+mod artifact;
+mod backend;
+mod broadcast;
 mod callbacks;
+mod config;
 mod context;
+mod dedupe;
+mod diagnostics;
+mod dispatch_cache;
+mod duplex;
+mod entry_limit;
 mod error;
 mod extensions;
+mod health;
+mod load;
+mod load_policy;
+mod long_poll;
+pub mod metrics;
+mod misdelivery;
+mod mux;
+mod notifier;
+pub mod oneshot;
+mod ordered;
+#[cfg(feature = "slab-pending")]
+mod pending_slab;
+mod plugin_pool;
+mod pool;
+mod quarantine;
+mod request;
+mod routing;
+mod rt;
+mod schema;
+mod shared;
 mod sid;
+mod source;
+mod stream;
+mod strict;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tombstone;
+mod trace;
+#[cfg(feature = "serde")]
+mod typed;
 mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-use callbacks::{get_state_callback, send_result_vec_callback, set_state_callback};
-use context::{HostContext, CURRENT_UNARY_RESULT};
+use backend::Backend;
+use callbacks::{
+    buf_acquire_callback, buf_commit_callback, complete_later_callback, dispatch_spawn_callback,
+    get_host_ext_callback, get_state_callback, get_state_into_callback,
+    get_state_scoped_into_callback, is_revoked_callback, log_callback, report_panic_callback,
+    send_result_channel_callback, send_result_seq_callback, send_result_vec_callback,
+    set_state_callback, set_state_scoped_callback, take_dispatch_result_callback,
+};
+use config::HostConfig;
+use context::{
+    BoundSlot, CallState, HostContext, InFlight, PluginContext, TlsSlotGuard, CURRENT_UNARY_RESULT,
+};
+use entry_limit::EntryLimits;
 use libloading::{Library, Symbol};
-use nylon_ring::{NrBytes, NrHostExt, NrHostVTable, NrPluginInfo, NrPluginVTable, NrStr};
+use misdelivery::HandlingGuard;
+use nylon_ring::{
+    NrBytes, NrHostExt, NrHostVTable, NrPluginInfo, NrStr, NrTuple, NrVec, ENTRIES_KEY,
+    INIT_ERROR_KEY, INIT_SID, NR_ABI_VERSION,
+};
+use parking_lot::{Mutex, RwLock};
+use plugin_pool::PluginPool;
+use pool::BlockingPool;
+use quarantine::HealthMonitor;
+use routing::Router;
+use rustc_hash::FxHashSet;
+use shared::SharedRequest;
 use sid::next_sid;
+use source::PluginSource;
 use std::collections::HashMap;
 use std::ffi::c_void;
-use std::sync::Arc;
-use types::{Result, StreamFrame, StreamReceiver};
+use std::mem::ManuallyDrop;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Once};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use tokio::sync::OwnedSemaphorePermit;
+use tombstone::Tombstones;
+use trace::CallSpan;
+use types::Result;
 
+pub use abi::{AbiDetails, HostTable};
+pub use artifact::{plugin_artifact_in, plugin_artifact_path, plugin_file_name, Profile};
+pub use config::NylonRingHostBuilder;
+pub use dedupe::{DedupeOptions, DedupeStats};
+pub use diagnostics::{HostDiagnostics, ShardDiagnostics};
+pub use dispatch_cache::DispatchCacheRule;
+pub use duplex::DuplexStream;
+pub use entry_limit::EntryLimit;
 pub use error::NylonRingHostError;
 pub use extensions::Extensions;
+pub use health::HealthStatus;
+pub use load::{LoadDirOptions, LoadOutcome, LoadReport, LoadStrategy, PluginSpec};
+pub use load_policy::LoadPolicy;
+pub use long_poll::{LongPollOptions, LongPollOutcome};
+pub use metrics::{HostMetricsSnapshot, MetricsSnapshot};
+pub use mux::MuxStream;
+pub use notifier::{Notifier, NotifierOptions};
+#[cfg(feature = "serde")]
+pub use nylon_ring::codec::PayloadCodec;
 pub use nylon_ring::NrStatus;
+pub use ordered::{OrderOptions, STREAM_GAP};
+pub use pool::ExecutionPolicy;
+pub use quarantine::{HealthPolicy, HealthState, PluginHealth};
+pub use request::HighLevelRequest;
+#[cfg(feature = "json-schema")]
+pub use schema::JsonSchema;
+pub use schema::{BytesSchema, Schema, SchemaRule, Violation};
+pub use shared::{SharedBufferStats, SharedPayload};
+pub use sid::{is_fire_and_forget, sid_epoch, sid_generation, sid_mode, SidExhaustion, SidMode};
+pub use stream::{
+    ResumeOptions, ResumeToken, StreamHandle, StreamLag, StreamLagAlert, StreamLagHook,
+    StreamLimit, StreamOptions, StreamReceiver, TryRecvError,
+};
+pub use strict::StrictViolation;
+pub use trace::{TraceEvent, TraceHook};
+#[cfg(feature = "serde")]
+pub use typed::{TypedFrameStream, TypedStreamError};
+pub use types::PanicReport;
 pub use types::StreamFrame as PublicStreamFrame;
 
+/// The callback table every plugin receives. Static, so a plugin detached
+/// by the shutdown watchdog can never outlive it.
+static HOST_VTABLE: NrHostVTable = NrHostVTable {
+    send_result: send_result_vec_callback,
+    get_host_ext: get_host_ext_callback,
+    send_result_channel: send_result_channel_callback,
+};
+
 /// A loaded plugin instance.
 pub struct LoadedPlugin {
-    _lib: Library,
-    vtable: &'static NrPluginVTable,
+    _lib: Option<Library>,
+    /// Taken by `drop`.
+    backend: ManuallyDrop<Backend>,
     #[allow(dead_code)]
     plugin_ctx: *mut c_void,
     host_ctx: Arc<HostContext>,
-    path: String,
+    ctx: Arc<PluginContext>,
+    source: PluginSource,
+    /// Entries the host may call, or `None` for all of them.
+    entries: Option<Arc<FxHashSet<String>>>,
+    /// Concurrency limits of single entries.
+    entry_limits: EntryLimits,
+    /// The ABI version the plugin was built against and the functions it
+    /// provides.
+    abi: AbiDetails,
+    /// The version in the plugin's info, empty for WASM plugins.
+    version: String,
+    /// Entries the plugin published under `ENTRIES_KEY`, or `None` if it
+    /// published none.
+    provided: Option<FxHashSet<String>>,
+    /// Entries earlier instances served and a reload removed.
+    tombstones: Tombstones,
+    /// Failures under the plugin's [`HealthPolicy`], and its quarantine.
+    health: HealthMonitor,
+    /// Threads unary calls run `handle` on, under
+    /// [`ExecutionPolicy::DedicatedPool`].
+    pool: RwLock<Option<Arc<BlockingPool>>>,
+    /// Threads `call_response_blocking` falls back to without a dedicated
+    /// pool. Not part of the execution policy, so other calls stay inline.
+    blocking_pool: Mutex<Option<Arc<BlockingPool>>>,
+    shutdown: Once,
+    // Declared after `_lib` so the backing file outlives the mapping.
+    _temp_file: Option<NamedTempFile>,
 }
 
 unsafe impl Send for LoadedPlugin {}
 unsafe impl Sync for LoadedPlugin {}
 
+impl LoadedPlugin {
+    /// Call `entry`, unless it is outside the plugin's allowed entries or
+    /// the plugin is shutting down.
+    fn handle(&self, entry: &str, sid: u64, payload: &[u8]) -> NrStatus {
+        self.invoke(entry, sid, payload.len(), || {
+            self.backend.handle(entry, sid, payload)
+        })
+    }
+
+    /// Like [`handle`](Self::handle), passing `req` through `handle_req`.
+    fn handle_req(&self, entry: &str, sid: u64, req: &HighLevelRequest) -> NrStatus {
+        self.invoke(entry, sid, req.body.len(), || {
+            self.backend.handle_req(entry, sid, req)
+        })
+    }
+
+    /// Run `call` for `entry` under the checks and bookkeeping every call
+    /// gets.
+    fn invoke(
+        &self,
+        entry: &str,
+        sid: u64,
+        len: usize,
+        call: impl FnOnce() -> NrStatus,
+    ) -> NrStatus {
+        let metrics = &self.host_ctx.metrics;
+        metrics.record_call(len);
+        let status = if self.ctx.is_revoked() {
+            NrStatus::Revoked
+        } else if self
+            .entries
+            .as_ref()
+            .is_some_and(|entries| !entries.contains(entry))
+        {
+            NrStatus::Unsupported
+        } else {
+            let _handling = HandlingGuard::enter(&self.ctx, sid);
+            if self.ctx.echoes_nonce() {
+                misdelivery::seed_nonce(&self.host_ctx, sid);
+            }
+            call()
+        };
+        if !status.is_success() {
+            metrics.record_error();
+        }
+        self.health.record(status);
+        status
+    }
+
+    /// Whether `status` from `handle` leaves the answer for later. Only
+    /// `Accepted` does since ABI v2; before, `Ok` could mean either.
+    fn answers_later(&self, status: NrStatus) -> bool {
+        status == NrStatus::Accepted || (status == NrStatus::Ok && self.abi.abi_version < 2)
+    }
+
+    fn pool(&self) -> Option<Arc<BlockingPool>> {
+        self.pool.read().clone()
+    }
+
+    fn execution_policy(&self) -> ExecutionPolicy {
+        match &*self.pool.read() {
+            Some(pool) => ExecutionPolicy::DedicatedPool {
+                threads: pool.threads(),
+            },
+            None => ExecutionPolicy::Inline,
+        }
+    }
+
+    fn set_execution_policy(&self, policy: ExecutionPolicy) -> Result<()> {
+        let pool = match policy {
+            ExecutionPolicy::Inline => None,
+            ExecutionPolicy::DedicatedPool { threads } => Some(Arc::new(
+                BlockingPool::new(&self.ctx.name, threads)
+                    .map_err(NylonRingHostError::SpawnPoolFailed)?,
+            )),
+        };
+        *self.pool.write() = pool;
+        Ok(())
+    }
+
+    /// Wait until no calls are in flight, for at most the host's drain
+    /// timeout.
+    fn drain(&self) {
+        let deadline = Instant::now() + *self.host_ctx.drain_timeout.lock();
+        if !self.ctx.wait_drained(deadline) {
+            log::warn!(
+                "plugin {:?} still has {} calls in flight; shutting it down anyway",
+                self.ctx.name,
+                self.ctx.in_flight()
+            );
+        }
+    }
+
+    /// Let calls in flight finish, then revoke the plugin's callbacks and run
+    /// its `shutdown`, at most once.
+    fn shutdown(&self) {
+        self.shutdown.call_once(|| {
+            self.drain();
+            self.ctx.revoke();
+            self.backend.shutdown();
+        });
+    }
+}
+
+/// Tears a plugin down: waits for its calls in flight, revokes its
+/// callbacks, so a plugin thread calling the host cannot hold up its own
+/// `shutdown`, then runs `shutdown` under the host's watchdog, and only then
+/// unloads the library and invalidates the context.
 impl Drop for LoadedPlugin {
     fn drop(&mut self) {
-        if let Some(shutdown_fn) = self.vtable.shutdown {
-            unsafe {
-                shutdown_fn();
-            }
+        let teardown = Teardown {
+            // Safety: `backend` is not used again.
+            backend: unsafe { ManuallyDrop::take(&mut self.backend) },
+            _lib: self._lib.take(),
+            _temp_file: self._temp_file.take(),
+            _ctx: self.ctx.clone(),
+        };
+        if self.shutdown.is_completed() {
+            return;
         }
+        self.drain();
+        self.ctx.revoke();
+        let watchdog = *self.host_ctx.shutdown_watchdog.lock();
+        teardown.run(&self.ctx.name, watchdog);
     }
 }
 
+/// What a plugin's `shutdown` needs, in the order it is released afterwards.
+struct Teardown {
+    backend: Backend,
+    _lib: Option<Library>,
+    // Declared after `_lib` so the backing file outlives the mapping.
+    _temp_file: Option<NamedTempFile>,
+    _ctx: Arc<PluginContext>,
+}
+
+// Safety: the plugin's `shutdown` may run on any thread.
+unsafe impl Send for Teardown {}
+
+impl Teardown {
+    /// Run `shutdown` on a thread of its own and wait for it for at most
+    /// `watchdog`. A plugin that overruns is detached: the thread keeps its
+    /// library loaded and its context alive until `shutdown` returns.
+    fn run(self, name: &str, watchdog: Duration) {
+        let (done_tx, done_rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name(format!("nylon-ring-shutdown-{name}"))
+            .spawn(move || {
+                self.backend.shutdown();
+                // Released first, so the library is unloaded and its copy
+                // removed by the time the caller stops waiting.
+                drop(self);
+                let _ = done_tx.send(());
+            });
+        if let Err(e) = spawned {
+            log::warn!("could not shut down plugin {name:?}: {e}");
+        } else if done_rx.recv_timeout(watchdog).is_err() {
+            log::warn!("plugin {name:?} did not shut down within {watchdog:?}; detaching it");
+        }
+    }
+}
+
+/// Turn an `Err` response carrying an error frame into
+/// [`NylonRingHostError::PluginError`].
+fn surface_error(response: (NrStatus, Vec<u8>)) -> Result<(NrStatus, Vec<u8>)> {
+    if response.0 == NrStatus::Err {
+        if let Some(error) = nylon_ring::decode_error(&response.1) {
+            return Err(NylonRingHostError::PluginError {
+                code: error.code,
+                message: error.message,
+            });
+        }
+    }
+    Ok(response)
+}
+
 /// A handle to a specific plugin for making calls.
 #[derive(Clone)]
 pub struct PluginHandle {
@@ -58,156 +365,966 @@ pub struct PluginHandle {
 
 impl PluginHandle {
     /// Call a plugin entry point with a request-response pattern.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let (status, data) = plugin.call_response("echo", b"hello").await?;
+    /// assert_eq!(status, NrStatus::Ok);
+    /// assert_eq!(data, b"hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// A status other than `Ok` or `Accepted` returned by `handle` fails the
+    /// call with [`NylonRingHostError::PluginHandleFailed`], without a body.
+    /// A plugin that wants the caller to see error bytes sends them as the
+    /// result instead, during `handle` or later: the call then returns
+    /// `Ok((NrStatus::Err, bytes))`. An `Err` response whose payload is an
+    /// error frame (see [`nylon_ring::encode_error`]) fails with
+    /// [`NylonRingHostError::PluginError`].
+    ///
+    /// Under [`ExecutionPolicy::DedicatedPool`], `handle` runs on the
+    /// plugin's own threads as in
+    /// [`call_response_blocking`](Self::call_response_blocking).
     pub async fn call_response(&self, entry: &str, payload: &[u8]) -> Result<(NrStatus, Vec<u8>)> {
+        surface_error(self.call_response_raw_error(entry, payload).await?)
+    }
+
+    /// Like [`call_response`](Self::call_response), with the response in
+    /// the `NrVec` the plugin sent it in, for handing on across the ABI.
+    /// The buffer is neither copied nor reallocated on the way: the host
+    /// takes it over as it is, which the ABI allows because plugins
+    /// allocate payloads with the host's allocator (see
+    /// [`NrVec`](nylon_ring::NrVec#allocation)).
+    pub async fn call_response_vec(
+        &self,
+        entry: &str,
+        payload: &[u8],
+    ) -> Result<(NrStatus, NrVec<u8>)> {
+        let (status, data) = self.call_response(entry, payload).await?;
+        Ok((status, NrVec::from_vec(data)))
+    }
+
+    /// Like [`call_response`](Self::call_response), but error frames are
+    /// returned as the raw `(Err, payload)` response.
+    pub async fn call_response_raw_error(
+        &self,
+        entry: &str,
+        payload: &[u8],
+    ) -> Result<(NrStatus, Vec<u8>)> {
+        let (response, _) = self.call_unary(entry, payload, None).await?;
+        Ok(response)
+    }
+
+    /// [`call_response`](Self::call_response) once per payload, with the
+    /// calls awaited concurrently on the current task. Each call has its own
+    /// SID; the results are in the order of `payloads`, whatever order the
+    /// plugin answers in.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let results = plugin.call_response_many("echo", &[b"a", b"b"]).await;
+    /// assert_eq!(results[1].as_ref().unwrap().1, b"b");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_response_many(
+        &self,
+        entry: &str,
+        payloads: &[&[u8]],
+    ) -> Vec<Result<(NrStatus, Vec<u8>)>> {
+        rt::join_all(
+            payloads
+                .iter()
+                .map(|payload| self.call_response(entry, payload)),
+        )
+        .await
+    }
+
+    /// Like [`call_response`](Self::call_response), with per-call state
+    /// passed both ways: the `seed` pairs are stored under the call's SID
+    /// before `handle` runs, where the plugin reads them with `get_state`,
+    /// and the state as the plugin left it is returned with the response.
+    /// The state is removed from the host once the call is over, whether it
+    /// succeeded or not.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let seed: &[(&str, &[u8])] = &[("tenant", b"acme")];
+    /// let (_, data, state) = plugin
+    ///     .call_response_with_state("remember", b"kept", seed)
+    ///     .await?;
+    /// assert_eq!(data, b"kept");
+    /// assert_eq!(state["tenant"], b"acme");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_response_with_state(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        seed: &[(&str, &[u8])],
+    ) -> Result<(NrStatus, Vec<u8>, HashMap<String, Vec<u8>>)> {
+        let (response, state) = self.call_unary(entry, payload, Some(seed)).await?;
+        let (status, data) = surface_error(response)?;
+        Ok((status, data, state.unwrap_or_default()))
+    }
+
+    /// The body of [`call_response_raw_error`](Self::call_response_raw_error).
+    /// With a `seed`, the call's state is seeded and returned.
+    async fn call_unary(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        seed: Option<&[(&str, &[u8])]>,
+    ) -> Result<((NrStatus, Vec<u8>), Option<HashMap<String, Vec<u8>>>)> {
+        self.admit(entry)?;
+        let _turn = self.entry_turn(entry).await?;
+        if let Some(pool) = self.plugin.pool() {
+            return self.call_response_pooled(&pool, entry, payload, seed).await;
+        }
+        self.call_inline(entry, payload, seed, |sid| {
+            self.plugin.handle(entry, sid, payload)
+        })
+        .await
+    }
+
+    /// The inline part of [`call_unary`](Self::call_unary), with `invoke`
+    /// calling into the plugin under the call's SID.
+    async fn call_inline(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        seed: Option<&[(&str, &[u8])]>,
+        invoke: impl FnOnce(u64) -> NrStatus,
+    ) -> Result<((NrStatus, Vec<u8>), Option<HashMap<String, Vec<u8>>>)> {
+        let schema = self.plugin.ctx.schemas.read().get(entry);
+        if let Some(schema) = &schema {
+            schema.check_request(payload)?;
+        }
+        let _in_flight = InFlight::acquire(&self.plugin.ctx)?;
+        let call = self.plugin.ctx.metrics.start_call(entry);
+
         // Create Oneshot Channel
         let (tx, rx) = tokio::sync::oneshot::channel();
 
         // Generate SID
-        let sid = next_sid();
+        let sid = next_sid(&self.plugin.host_ctx, SidMode::Unary)?;
 
         // Insert into Map (Async Path)
         context::insert_pending(&self.plugin.host_ctx, sid, types::Pending::Unary(tx));
+        let state = seed.map(|seed| CallState::seed(&self.plugin.host_ctx, sid, seed));
 
-        let payload_bytes = NrBytes::from_slice(payload);
-        let handle_raw_fn = match self.plugin.vtable.handle {
-            Some(f) => f,
+        let span = self.trace_start("call_response", sid, entry, payload);
+        let status = span.in_scope(|| invoke(sid));
+
+        if !status.is_success() {
+            context::remove_pending(&self.plugin.host_ctx, sid);
+            self.plugin.ctx.metrics.record_error();
+            self.trace_end(&span, sid, status, 0);
+            return Err(self.handle_failed(sid, status));
+        }
+        if !self.plugin.answers_later(status) {
+            // Answered during `handle`, or not at all
+            context::remove_pending(&self.plugin.host_ctx, sid);
+        }
+
+        // Wait for response (Allocation here for oneshot state)
+        let response = self.response(sid, rx).await?;
+        call.finish();
+        self.trace_end(&span, sid, response.0, response.1.len());
+        if let (Some(schema), NrStatus::Ok) = (&schema, response.0) {
+            schema.check_response(&response.1)?;
+        }
+        Ok((response, state.map(CallState::take)))
+    }
+
+    /// Like [`call_response`](Self::call_response), for multi-megabyte
+    /// payloads (see [`nylon_ring::shared`]).
+    ///
+    /// A payload of at least the host's
+    /// [`shared_threshold`](NylonRingHostBuilder::shared_threshold) is
+    /// passed as a shared request: by reference, with a host-owned response
+    /// buffer of the same size. That is, unless `entry` has a request
+    /// schema, or the plugin runs on a dedicated pool, which gets a copy of
+    /// the payload. A plugin that answers with a shared reply hands over the
+    /// buffer it wrote the response into: the returned [`SharedPayload`] is
+    /// that buffer, and goes back to the host's pool for reuse when dropped.
+    /// Plain responses are returned as they are.
+    pub async fn call_response_shared(
+        &self,
+        entry: &str,
+        payload: &[u8],
+    ) -> Result<(NrStatus, SharedPayload)> {
+        let host = &self.plugin.host_ctx;
+        // Releases the response buffer on return, unless the reply took it.
+        let request = (payload.len() >= host.config.shared_threshold
+            && self.plugin.pool().is_none()
+            && self.plugin.ctx.schemas.read().get(entry).is_none())
+        .then(|| SharedRequest::new(host, payload));
+        let (status, data) = match &request {
+            Some(request) => self.call_response(entry, request.frame()).await?,
+            None => self.call_response(entry, payload).await?,
+        };
+        let shared = nylon_ring::shared::decode_shared_reply(&data)
+            .and_then(|handle| host.shared.take(handle));
+        Ok(match shared {
+            Some(buf) => (status, SharedPayload::shared(buf, host)),
+            None => (status, SharedPayload::owned(data)),
+        })
+    }
+
+    /// Like [`call_response`](Self::call_response), but `handle` runs on
+    /// the plugin's dedicated threads, so a plugin that blocks in `handle`
+    /// (file IO, FFI) does not stall the caller's executor.
+    ///
+    /// A plugin without [`ExecutionPolicy::DedicatedPool`] gets a pool of
+    /// four threads on first use, for these calls only: its policy stays
+    /// [`ExecutionPolicy::Inline`].
+    pub async fn call_response_blocking(
+        &self,
+        entry: &str,
+        payload: &[u8],
+    ) -> Result<(NrStatus, Vec<u8>)> {
+        self.admit(entry)?;
+        let pool = match self.plugin.pool() {
+            Some(pool) => pool,
             None => {
-                context::remove_pending(&self.plugin.host_ctx, sid);
-                return Err(NylonRingHostError::MissingRequiredFunctions);
+                let mut slot = self.plugin.blocking_pool.lock();
+                match &*slot {
+                    Some(pool) => pool.clone(),
+                    None => slot
+                        .insert(Arc::new(
+                            BlockingPool::new(&self.plugin.ctx.name, pool::DEFAULT_POOL_THREADS)
+                                .map_err(NylonRingHostError::SpawnPoolFailed)?,
+                        ))
+                        .clone(),
+                }
             }
         };
+        let (response, _) = self
+            .call_response_pooled(&pool, entry, payload, None)
+            .await?;
+        surface_error(response)
+    }
+
+    /// Run `handle` on `pool` and await the response through the pending
+    /// map. The thread-local fast path never applies: the plugin responds
+    /// on a pool thread.
+    async fn call_response_pooled(
+        &self,
+        pool: &BlockingPool,
+        entry: &str,
+        payload: &[u8],
+        seed: Option<&[(&str, &[u8])]>,
+    ) -> Result<((NrStatus, Vec<u8>), Option<HashMap<String, Vec<u8>>>)> {
+        let schema = self.plugin.ctx.schemas.read().get(entry);
+        if let Some(schema) = &schema {
+            schema.check_request(payload)?;
+        }
+        let _in_flight = InFlight::acquire(&self.plugin.ctx)?;
+        let call = self.plugin.ctx.metrics.start_call(entry);
 
-        let status = unsafe { handle_raw_fn(NrStr::new(entry), sid, payload_bytes) };
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let sid = next_sid(&self.plugin.host_ctx, SidMode::Unary)?;
+        context::insert_pending(&self.plugin.host_ctx, sid, types::Pending::Unary(tx));
+        let state = seed.map(|seed| CallState::seed(&self.plugin.host_ctx, sid, seed));
 
-        if status != NrStatus::Ok {
+        let span = self.trace_start("call_response", sid, entry, payload);
+        let (status_tx, status_rx) = tokio::sync::oneshot::channel();
+        let plugin = self.plugin.clone();
+        let (entry_owned, payload) = (entry.to_string(), payload.to_vec());
+        let pool_span = span.clone();
+        pool.execute(move || {
+            let status = pool_span.in_scope(|| plugin.handle(&entry_owned, sid, &payload));
+            if !plugin.answers_later(status) {
+                // Cleaned up here, in case the caller has gone away.
+                context::remove_pending(&plugin.host_ctx, sid);
+            }
+            let _ = status_tx.send(status);
+        });
+
+        // A job the pool dropped unrun never reached the plugin.
+        let status = status_rx.await.unwrap_or_else(|_| {
             context::remove_pending(&self.plugin.host_ctx, sid);
-            return Err(NylonRingHostError::PluginHandleFailed(status));
+            NrStatus::Err
+        });
+        if !status.is_success() {
+            self.plugin.ctx.metrics.record_error();
+            self.trace_end(&span, sid, status, 0);
+            return Err(self.handle_failed(sid, status));
         }
 
-        // Wait for response (Allocation here for oneshot state)
-        rx.await.map_err(|_| NylonRingHostError::OneshotClosed)
+        let response = self.response(sid, rx).await?;
+        call.finish();
+        self.trace_end(&span, sid, response.0, response.1.len());
+        if let (Some(schema), NrStatus::Ok) = (&schema, response.0) {
+            schema.check_response(&response.1)?;
+        }
+        Ok((response, state.map(CallState::take)))
     }
 
-    /// Ultra-fast unary call for synchronous plugins.
+    /// Ultra-fast unary call for synchronous plugins. Error frames are
+    /// surfaced as in [`call_response`](Self::call_response).
+    ///
+    /// A plugin that calls `complete_later` from `handle` (as
+    /// `nylon_ring::nr_async_reply` does), or returns `Accepted`, is waited
+    /// on through the pending map instead.
+    ///
+    /// Under [`ExecutionPolicy::DedicatedPool`] this is
+    /// [`call_response_blocking`](Self::call_response_blocking).
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// // `echo` responds before returning from `handle`, so it can skip the
+    /// // pending map.
+    /// let (status, data) = plugin.call_response_fast("echo", b"hello").await?;
+    /// assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"hello"[..]));
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn call_response_fast(
         &self,
         entry: &str,
         payload: &[u8],
     ) -> Result<(NrStatus, Vec<u8>)> {
-        // Use a "Fast SID" that bypasses the Map (High bit set)
-        let sid = next_sid();
+        if !self.plugin.host_ctx.config.fast_path {
+            return self.call_response(entry, payload).await;
+        }
+        self.admit(entry)?;
+        let _turn = self.entry_turn(entry).await?;
+        if let Some(pool) = self.plugin.pool() {
+            let (response, _) = self
+                .call_response_pooled(&pool, entry, payload, None)
+                .await?;
+            return surface_error(response);
+        }
+        let schema = self.plugin.ctx.schemas.read().get(entry);
+        if let Some(schema) = &schema {
+            schema.check_request(payload)?;
+        }
+        let _in_flight = InFlight::acquire(&self.plugin.ctx)?;
+        let call = self.plugin.ctx.metrics.start_call(entry);
+
+        // Results go straight to the TLS slot, never through the map
+        let sid = next_sid(&self.plugin.host_ctx, SidMode::Fast)?;
 
         let mut slot: types::UnaryResultSlot = None;
 
-        // bind TLS slot
-        CURRENT_UNARY_RESULT.with(|cell| {
-            debug_assert!(
-                cell.get().is_null(),
-                "CURRENT_UNARY_RESULT already in use on this thread"
+        let span = self.trace_start("call_response_fast", sid, entry, payload);
+        let status = {
+            // bind TLS slot to this host and SID; the guard restores the
+            // previous binding so nested fast calls (even across hosts) stay
+            // separate
+            let _bound = TlsSlotGuard::bind(
+                &CURRENT_UNARY_RESULT,
+                BoundSlot {
+                    host: Arc::as_ptr(&self.plugin.host_ctx),
+                    sid,
+                    slot: &mut slot as *mut _,
+                },
             );
-            cell.set(&mut slot as *mut _);
-        });
+            span.in_scope(|| self.plugin.handle(entry, sid, payload))
+        };
+        // A plugin that called `complete_later` answers through the map
+        let deferred = context::take_deferred_fast(sid);
 
-        let payload_bytes = NrBytes::from_slice(payload);
+        if !status.is_success() {
+            if deferred.is_some() {
+                context::remove_pending(&self.plugin.host_ctx, sid);
+            }
+            self.plugin.ctx.metrics.record_error();
+            self.trace_end(&span, sid, status, 0);
+            return Err(self.handle_failed(sid, status));
+        }
 
-        let handle_raw_fn = match self.plugin.vtable.handle {
-            Some(f) => f,
-            None => {
-                CURRENT_UNARY_RESULT.with(|cell| cell.set(std::ptr::null_mut()));
-                return Err(NylonRingHostError::MissingRequiredFunctions);
+        let (st, data) = match (slot, deferred) {
+            (Some(result), deferred) => {
+                // Answered during `handle` after all
+                if deferred.is_some() {
+                    context::remove_pending(&self.plugin.host_ctx, sid);
+                }
+                result
+            }
+            (None, Some(rx)) => self.response(sid, rx).await?,
+            // Answered from another thread, possibly already
+            (None, None) if status == NrStatus::Accepted => {
+                let rx = context::late_fast(&self.plugin.host_ctx, sid);
+                self.response(sid, rx).await?
             }
+            (None, None) => return Err(self.closed()),
         };
-
-        let status = unsafe { handle_raw_fn(NrStr::new(entry), sid, payload_bytes) };
-
-        // unbind TLS slot
-        CURRENT_UNARY_RESULT.with(|cell| cell.set(std::ptr::null_mut()));
-
-        if status != NrStatus::Ok {
-            return Err(NylonRingHostError::PluginHandleFailed(status));
+        call.finish();
+        self.trace_end(&span, sid, st, data.len());
+        if let (Some(schema), NrStatus::Ok) = (&schema, st) {
+            schema.check_response(&data)?;
         }
+        surface_error((st, data))
+    }
 
-        match slot {
-            Some((st, data)) => Ok((st, data)),
-            None => Err(NylonRingHostError::OneshotClosed),
-        }
+    /// Ask the plugin whether it is healthy, through the reserved entry
+    /// [`HEALTH_ENTRY`](nylon_ring::HEALTH_ENTRY) on the fast path.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, HealthStatus, NylonRingHost};
+    /// # use std::time::Duration;
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// // The mock plugin has no health entry.
+    /// let health = plugin.health_check(Duration::from_secs(1)).await;
+    /// assert_eq!(health, HealthStatus::Unknown);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// `timeout` bounds the wait for an answer sent after `handle` returns;
+    /// a `handle` that never returns still blocks, as in any fast call.
+    pub async fn health_check(&self, timeout: Duration) -> HealthStatus {
+        let response = rt::timeout(
+            timeout,
+            self.call_response_fast(nylon_ring::HEALTH_ENTRY, &[]),
+        )
+        .await;
+        HealthStatus::from_response(response)
     }
 
     /// Fire-and-forget call to a plugin entry point.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// // Only whether the plugin accepted the call is reported.
+    /// assert_eq!(plugin.call("echo", b"ignored").await?, NrStatus::Ok);
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn call(&self, entry: &str, payload: &[u8]) -> Result<NrStatus> {
-        // Use Fast SID
-        let sid = next_sid();
+        self.call_now(entry, payload)
+    }
 
-        let payload_bytes = NrBytes::from_slice(payload);
-        let handle_raw_fn = match self.plugin.vtable.handle {
-            Some(f) => f,
-            None => {
-                return Err(NylonRingHostError::MissingRequiredFunctions);
-            }
-        };
+    /// The body of [`call`](Self::call), which never waits.
+    fn call_now(&self, entry: &str, payload: &[u8]) -> Result<NrStatus> {
+        self.admit(entry)?;
+        let _in_flight = InFlight::acquire(&self.plugin.ctx)?;
+        let call = self.plugin.ctx.metrics.start_call(entry);
 
-        let status = unsafe { handle_raw_fn(NrStr::new(entry), sid, payload_bytes) };
+        // The mode byte tells callbacks that nobody waits on the results
+        let sid = next_sid(&self.plugin.host_ctx, SidMode::FireAndForget)?;
 
-        if status != NrStatus::Ok {
-            return Err(NylonRingHostError::PluginHandleFailed(status));
+        let span = self.trace_start("call", sid, entry, payload);
+        let status = span.in_scope(|| self.plugin.handle(entry, sid, payload));
+        self.trace_end(&span, sid, status, 0);
+
+        if !status.is_success() {
+            self.plugin.ctx.metrics.record_error();
+            return Err(self.handle_failed(sid, status));
         }
+        call.finish();
         Ok(status)
     }
 
     /// Call a plugin entry point with a streaming response pattern.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let (_sid, mut rx) = plugin.call_stream("words", b"one two").await?;
+    ///
+    /// let mut words = Vec::new();
+    /// while let Some(frame) = rx.recv().await {
+    ///     if frame.status != NrStatus::Ok {
+    ///         break;
+    ///     }
+    ///     words.push(String::from_utf8(frame.data).unwrap());
+    /// }
+    /// assert_eq!(words, ["one", "two"]);
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn call_stream(&self, entry: &str, payload: &[u8]) -> Result<(u64, StreamReceiver)> {
-        let sid = next_sid();
+        self.open_stream(entry, payload, None, StreamOptions::default())
+    }
 
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<StreamFrame>();
+    /// Like [`call_stream`](Self::call_stream), but the stream ends with an
+    /// `Err` frame (see [`StreamLimit`]) when the plugin stays quiet longer
+    /// than `options.idle_timeout`, or sends more frames or bytes than
+    /// allowed, so a plugin that never sends its final frame cannot leave
+    /// the receiver waiting forever. With `options.dedupe`, frames the
+    /// plugin repeats are delivered once.
+    pub async fn call_stream_with_options(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        options: StreamOptions,
+    ) -> Result<(u64, StreamReceiver)> {
+        self.open_stream(entry, payload, None, options)
+    }
 
-        // Register the stream channel (Map)
-        context::insert_pending(&self.plugin.host_ctx, sid, types::Pending::Stream(tx));
+    /// Like [`call_stream`](PluginHandle::call_stream), but the stream
+    /// survives its receiver being dropped: frames are buffered per `options`
+    /// and [`resume_stream`](PluginHandle::resume_stream) attaches a new
+    /// receiver.
+    pub async fn call_stream_resumable(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        options: ResumeOptions,
+    ) -> Result<StreamHandle> {
+        let (sid, receiver) =
+            self.open_stream(entry, payload, Some(options), StreamOptions::default())?;
+
+        let resumable = &self.plugin.host_ctx.resumable;
+        let now = rt::Instant::now();
+        resumable.retain(|_, stream| !stream.expired(now));
+        let token = ResumeToken::new(sid);
+        resumable.insert(token, receiver.resume_handle());
 
-        let payload_bytes = NrBytes::from_slice(payload);
+        Ok(StreamHandle {
+            sid,
+            token,
+            receiver,
+            plugin: self.clone(),
+        })
+    }
 
-        let handle_raw_fn = match self.plugin.vtable.handle {
-            Some(f) => f,
+    /// Attach a new receiver to a resumable stream. Frames that arrived while
+    /// detached are replayed first. An attached receiver is replaced and sees
+    /// the end of its stream.
+    ///
+    /// Fails with [`NylonRingHostError::StreamExpired`] once the stream has
+    /// ended and everything was delivered, more than `buffer` frames arrived
+    /// while detached, or it stayed detached longer than `ttl`.
+    pub fn resume_stream(&self, token: ResumeToken) -> Result<StreamReceiver> {
+        let resumable = &self.plugin.host_ctx.resumable;
+        let receiver = resumable.get(&token).and_then(|stream| stream.resume());
+        match receiver {
+            Some(receiver) => Ok(receiver),
             None => {
-                context::remove_pending(&self.plugin.host_ctx, sid);
-                return Err(NylonRingHostError::MissingRequiredFunctions);
+                resumable.remove(&token);
+                Err(NylonRingHostError::StreamExpired)
             }
-        };
+        }
+    }
+
+    fn open_stream(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        resume: Option<ResumeOptions>,
+        limits: StreamOptions,
+    ) -> Result<(u64, StreamReceiver)> {
+        self.admit(entry)?;
+        self.open_stream_with(entry, payload, resume, limits, |sid| {
+            self.plugin.handle(entry, sid, payload)
+        })
+    }
 
-        let status = unsafe { handle_raw_fn(NrStr::new(entry), sid, payload_bytes) };
+    /// The body of [`open_stream`](Self::open_stream), with `invoke`
+    /// calling into the plugin under the stream's SID.
+    fn open_stream_with(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        resume: Option<ResumeOptions>,
+        limits: StreamOptions,
+        invoke: impl FnOnce(u64) -> NrStatus,
+    ) -> Result<(u64, StreamReceiver)> {
+        // In flight, in the metrics and for draining, until the stream ends.
+        let in_flight = InFlight::acquire(&self.plugin.ctx)?;
+        let call = self.plugin.ctx.metrics.start_call(entry);
 
-        if status != NrStatus::Ok {
+        let sid = next_sid(&self.plugin.host_ctx, SidMode::Stream)?;
+
+        let watch = self
+            .plugin
+            .host_ctx
+            .stream_lag_alert
+            .lock()
+            .as_ref()
+            .map(|(alert, hook)| stream::LagWatch {
+                plugin: self.plugin.ctx.name.clone(),
+                alert: *alert,
+                hook: hook.clone(),
+            });
+        let capacity = self.plugin.host_ctx.config.stream_capacity;
+        let dedupe = limits.dedupe;
+        let limits = stream::Limits::new(limits, &self.plugin);
+        let (tx, rx) = stream::channel(
+            sid,
+            Arc::downgrade(&self.plugin.ctx),
+            watch,
+            resume,
+            capacity,
+            limits,
+            dedupe,
+        );
+        tx.track(in_flight, call.detach(&self.plugin.ctx));
+
+        // Register the stream channel (Map)
+        context::insert_pending(&self.plugin.host_ctx, sid, types::Pending::Stream(tx));
+
+        let span = self.trace_start("call_stream", sid, entry, payload);
+        let status = span.in_scope(|| invoke(sid));
+
+        if !status.is_success() {
             context::remove_pending(&self.plugin.host_ctx, sid);
-            return Err(NylonRingHostError::PluginHandleFailed(status));
+            self.plugin.ctx.metrics.record_error();
+            self.trace_end(&span, sid, status, 0);
+            return Err(self.handle_failed(sid, status));
         }
+        // Frames are traced as they arrive.
+        span.finish(status, 0);
 
         Ok((sid, rx))
     }
 
+    /// Wait for a turn to call `entry` under its limit, if it has one, for
+    /// up to the host's call timeout.
+    async fn entry_turn(&self, entry: &str) -> Result<Option<OwnedSemaphorePermit>> {
+        let timeout = self.plugin.host_ctx.config.call_timeout;
+        self.plugin
+            .entry_limits
+            .acquire(&self.plugin.ctx.name, entry, timeout)
+            .await
+    }
+
+    /// Run at most `max_concurrent` unary calls to `entry` at once
+    /// ([`call_response`](Self::call_response) and the calls built on it,
+    /// and [`call_response_fast`](Self::call_response_fast)), replacing any
+    /// limit it had. Up to `queue_depth` more calls wait for a turn; calls
+    /// beyond that fail with [`NylonRingHostError::EntryOverloaded`]. A
+    /// waiting call gives up its place when it is dropped, or fails with
+    /// [`NylonRingHostError::Timeout`] once it has waited for the host's
+    /// call timeout.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, EntryLimit, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// plugin.set_entry_limit("echo", 4, 16)?;
+    /// let limit = EntryLimit {
+    ///     max_concurrent: 4,
+    ///     queue_depth: 16,
+    /// };
+    /// assert_eq!(plugin.entry_limits(), [("echo".to_string(), limit)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Calls admitted under the old limit finish under it. Limits apply to
+    /// this instance of the plugin and are kept across
+    /// [`reload`](NylonRingHost::reload).
+    pub fn set_entry_limit(
+        &self,
+        entry: &str,
+        max_concurrent: usize,
+        queue_depth: usize,
+    ) -> Result<()> {
+        if max_concurrent == 0 {
+            return Err(NylonRingHostError::InvalidConfig(
+                "an entry limit must allow at least one call".to_string(),
+            ));
+        }
+        let limit = EntryLimit {
+            max_concurrent,
+            queue_depth,
+        };
+        self.plugin.entry_limits.set(entry, limit);
+        Ok(())
+    }
+
+    /// Lift the limit on `entry`, returning it. Calls already admitted or
+    /// waiting finish under it.
+    pub fn remove_entry_limit(&self, entry: &str) -> Option<EntryLimit> {
+        self.plugin.entry_limits.remove(entry)
+    }
+
+    /// The entries with a limit, sorted.
+    pub fn entry_limits(&self) -> Vec<(String, EntryLimit)> {
+        self.plugin.entry_limits.list()
+    }
+
+    /// Wait for the response to the unary call `sid`, up to the host's call
+    /// timeout.
+    async fn response(&self, sid: u64, rx: types::UnaryReceiver) -> Result<(NrStatus, Vec<u8>)> {
+        let Some(timeout) = self.plugin.host_ctx.config.call_timeout else {
+            return rx.await.map_err(|_| self.closed());
+        };
+        match rt::timeout(timeout, rx).await {
+            Some(response) => response.map_err(|_| self.closed()),
+            None => {
+                // A late response finds nobody waiting and is dropped.
+                context::remove_pending(&self.plugin.host_ctx, sid);
+                self.plugin.ctx.metrics.record_error();
+                Err(NylonRingHostError::Timeout(timeout))
+            }
+        }
+    }
+
+    /// The error for a call the plugin rejected with `status`, with the
+    /// message of the panic behind it, if any.
+    fn handle_failed(&self, sid: u64, status: NrStatus) -> NylonRingHostError {
+        NylonRingHostError::PluginHandleFailed {
+            status,
+            message: self.plugin.ctx.panic_message(sid),
+        }
+    }
+
+    /// The error for a call whose response will never come, counted as an
+    /// error of the plugin and of the host.
+    fn closed(&self) -> NylonRingHostError {
+        self.plugin.ctx.metrics.record_error();
+        self.plugin.host_ctx.metrics.record_error();
+        NylonRingHostError::OneshotClosed
+    }
+
+    #[inline(always)]
+    fn trace_start(&self, call: &'static str, sid: u64, entry: &str, payload: &[u8]) -> CallSpan {
+        self.plugin.host_ctx.tracer.emit(TraceEvent::CallStart {
+            sid,
+            plugin: &self.plugin.ctx.name,
+            entry,
+        });
+        CallSpan::new(call, &self.plugin.ctx.name, entry, sid, payload.len())
+    }
+
+    #[inline(always)]
+    fn trace_end(&self, span: &CallSpan, sid: u64, status: NrStatus, bytes: usize) {
+        self.plugin
+            .host_ctx
+            .tracer
+            .emit(TraceEvent::CallEnd { sid, status, bytes });
+        span.finish(status, bytes);
+    }
+
     /// Send data to an active stream.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let (sid, mut rx) = plugin.call_stream("open", b"").await?;
+    ///
+    /// plugin.send_stream_data(sid, b"ping")?;
+    /// assert_eq!(rx.recv().await.unwrap().data, b"ping");
+    ///
+    /// plugin.close_stream(sid)?;
+    /// assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Fails with [`NylonRingHostError::UnknownStream`], without reaching
+    /// the plugin, unless `sid` is an open stream of this plugin.
     pub fn send_stream_data(&self, sid: u64, data: &[u8]) -> Result<NrStatus> {
-        let stream_data_fn = match self.plugin.vtable.stream_data {
-            Some(f) => f,
-            None => return Err(NylonRingHostError::MissingRequiredFunctions),
-        };
-        let payload = NrBytes::from_slice(data);
-        Ok(unsafe { stream_data_fn(sid, payload) })
+        self.require("stream_data")?;
+        self.check_stream_owner(sid)?;
+        self.plugin
+            .backend
+            .stream_data(sid, data)
+            .ok_or_else(|| self.missing("stream_data"))
+    }
+
+    /// The ABI the plugin was loaded at and which optional functions it
+    /// provides, such as `stream_data`, which
+    /// [`send_stream_data`](Self::send_stream_data) needs.
+    pub fn abi_details(&self) -> &AbiDetails {
+        &self.plugin.abi
+    }
+
+    /// Fail with [`NylonRingHostError::MissingFunction`] unless the plugin
+    /// provides `function`.
+    fn require(&self, function: &'static str) -> Result<()> {
+        if self.plugin.abi.provides(function) {
+            Ok(())
+        } else {
+            Err(self.missing(function))
+        }
+    }
+
+    /// Refuse calls to entries a reload removed, and every call while the
+    /// plugin is quarantined.
+    fn admit(&self, entry: &str) -> Result<()> {
+        self.plugin.tombstones.check(entry)?;
+        self.plugin
+            .health
+            .admit(&self.plugin.ctx.name, self.plugin.abi.provides("health"))
+    }
+
+    fn missing(&self, function: &'static str) -> NylonRingHostError {
+        NylonRingHostError::MissingFunction {
+            plugin: self.plugin.ctx.name.clone(),
+            function,
+        }
+    }
+
+    /// Fail with [`NylonRingHostError::UnknownStream`] unless `sid` is a
+    /// stream opened on this plugin that has not ended.
+    fn check_stream_owner(&self, sid: u64) -> Result<()> {
+        match context::get_pending_stream(&self.plugin.host_ctx, sid) {
+            Some(tx) if tx.is_owned_by(&self.plugin.ctx) => Ok(()),
+            _ => Err(NylonRingHostError::UnknownStream(sid)),
+        }
+    }
+
+    /// Like [`send_stream_data`](Self::send_stream_data), but fails with
+    /// [`NylonRingHostError::StreamClosed`] once the stream has ended, was
+    /// cancelled, or `sid` is not a stream.
+    ///
+    /// Writers can use this to stop producing instead of having their data
+    /// dropped by a plugin that already finished the stream.
+    pub fn send_stream_data_checked(&self, sid: u64, data: &[u8]) -> Result<NrStatus> {
+        if !context::has_pending_stream(&self.plugin.host_ctx, sid) {
+            return Err(NylonRingHostError::StreamClosed(sid));
+        }
+        self.send_stream_data(sid, data)
+    }
+
+    /// A snapshot of this plugin's call metrics.
+    ///
+    /// Latencies cover `call_response`, `call_response_fast` and `call`.
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.plugin.ctx.metrics.snapshot()
+    }
+
+    /// Calls to this plugin whose response has not arrived yet: unary calls
+    /// still awaited, streams that have not ended and still have a receiver,
+    /// and dispatched calls that have not been answered. The plugin is not
+    /// shut down while this is above zero, up to the host's drain timeout
+    /// (see [`NylonRingHost::set_drain_timeout`]).
+    pub fn in_flight(&self) -> u64 {
+        self.plugin.ctx.in_flight()
+    }
+
+    /// The entries the plugin listed when it was initialized, sorted, or
+    /// `None` if it listed none (plugins not built with `define_plugin!`).
+    pub fn provided_entries(&self) -> Option<Vec<String>> {
+        let mut entries: Vec<String> = self.plugin.provided.as_ref()?.iter().cloned().collect();
+        entries.sort();
+        Some(entries)
+    }
+
+    /// Panics caught inside this plugin's entry points, oldest first.
+    ///
+    /// Only the most recent reports are retained.
+    pub fn panic_reports(&self) -> Vec<PanicReport> {
+        self.plugin
+            .ctx
+            .panic_reports
+            .lock()
+            .iter()
+            .cloned()
+            .collect()
     }
 
     /// Close an active stream from the host side.
+    ///
+    /// Fails with [`NylonRingHostError::UnknownStream`], without reaching
+    /// the plugin, unless `sid` is an open stream of this plugin.
     pub fn close_stream(&self, sid: u64) -> Result<NrStatus> {
-        let stream_close_fn = match self.plugin.vtable.stream_close {
-            Some(f) => f,
-            None => return Err(NylonRingHostError::MissingRequiredFunctions),
-        };
-        Ok(unsafe { stream_close_fn(sid) })
+        self.require("stream_close")?;
+        self.check_stream_owner(sid)?;
+        self.plugin
+            .backend
+            .stream_close(sid)
+            .ok_or_else(|| self.missing("stream_close"))
+    }
+
+    /// Tear down a stream that went over a [`StreamOptions`] limit: remove
+    /// it and its state, and close it in the plugin.
+    pub(crate) fn end_stream(&self, sid: u64) {
+        let _ = self.cancel_stream(sid);
+        self.plugin.host_ctx.state_per_sid.remove(&sid);
+    }
+
+    /// Cancel a stream without waiting for the plugin to end it.
+    ///
+    /// The receiver ends once it has drained the frames already delivered,
+    /// and the plugin's `stream_close` is called so it can stop producing.
+    /// Cancelling a stream that already ended, or was cancelled, is a no-op,
+    /// as is cancelling a stream of another plugin.
+    ///
+    /// The host side is torn down even when `stream_close` returns an error
+    /// status, which is reported as [`NylonRingHostError::PluginHandleFailed`].
+    pub fn cancel_stream(&self, sid: u64) -> Result<()> {
+        let host_ctx = &self.plugin.host_ctx;
+        match context::remove_pending(host_ctx, sid) {
+            Some(types::Pending::Stream(tx)) if tx.is_owned_by(&self.plugin.ctx) => tx.close(),
+            Some(other) => {
+                context::reinsert_pending(host_ctx, sid, other);
+                return Ok(());
+            }
+            None => return Ok(()),
+        }
+        match self.plugin.backend.stream_close(sid) {
+            Some(status) if status != NrStatus::Ok => Err(self.handle_failed(sid, status)),
+            _ => Ok(()),
+        }
     }
 }
 
 /// The main host for loading and managing nylon-ring plugins.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+/// use nylon_ring_host::{testing, NylonRingHost};
+///
+/// let mut host = NylonRingHost::new();
+/// // Shared libraries are loaded with `load`; the mock is linked in.
+/// host.load_static("mock", testing::mock_plugin())?;
+///
+/// host.route("echo", "mock")?;
+/// let (_, data) = host.call_routed("echo", b"routed").await?;
+/// assert_eq!(data, b"routed");
+///
+/// host.unload("mock")?;
+/// assert!(host.plugin("mock").is_none());
+/// # Ok(())
+/// # }
+/// ```
 pub struct NylonRingHost {
     plugins: HashMap<String, Arc<LoadedPlugin>>,
+    routes: Router,
+    pools: HashMap<String, PluginPool>,
     host_ctx: Arc<HostContext>,
-    host_vtable: Box<NrHostVTable>,
 }
 
 unsafe impl Send for NylonRingHost {}
@@ -220,43 +1337,622 @@ impl Default for NylonRingHost {
 }
 
 impl NylonRingHost {
-    /// Create a new empty host.
+    /// Create a new empty host with the default settings. Use
+    /// [`builder`](Self::builder) to change them.
     pub fn new() -> Self {
-        let host_ctx = Arc::new(HostContext::new(NrHostExt {
-            set_state: set_state_callback,
-            get_state: get_state_callback,
-        }));
+        Self::with_config(HostConfig::default())
+    }
 
-        let host_vtable = Box::new(NrHostVTable {
-            send_result: send_result_vec_callback,
-        });
+    /// A host whose background tasks run on `runtime` rather than on the
+    /// runtime of whoever starts them: the tasks behind
+    /// [`call_stream_mux`](PluginHandle::call_stream_mux),
+    /// [`call_stream_ordered`](PluginHandle::call_stream_ordered),
+    /// [`broadcast`](Self::broadcast), notifiers with a `max_delay`, and
+    /// health checks. This keeps plugin work off an application's own
+    /// runtime, and lets a host be driven from a `current_thread` runtime
+    /// while its tasks run on a multi-thread one. The runtime needs its
+    /// timer enabled.
+    ///
+    /// Calls themselves run where they are awaited, and plugins dispatch to
+    /// each other synchronously, without a runtime.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new_with_runtime(runtime: tokio::runtime::Handle) -> Self {
+        Self::with_config(HostConfig {
+            spawner: rt::Spawner(Some(runtime)),
+            ..HostConfig::default()
+        })
+    }
+
+    /// A builder for a host with settings other than the defaults.
+    pub fn builder() -> NylonRingHostBuilder {
+        NylonRingHostBuilder::new()
+    }
+
+    pub(crate) fn with_config(config: HostConfig) -> Self {
+        let host_ctx = Arc::new(HostContext::new(
+            NrHostExt {
+                set_state: set_state_callback,
+                get_state: get_state_callback,
+                report_panic: report_panic_callback,
+                dispatch_spawn: dispatch_spawn_callback,
+                take_dispatch_result: take_dispatch_result_callback,
+                get_state_into: get_state_into_callback,
+                is_revoked: is_revoked_callback,
+                complete_later: complete_later_callback,
+                log: log_callback,
+                buf_acquire: buf_acquire_callback,
+                buf_commit: buf_commit_callback,
+                set_state_scoped: set_state_scoped_callback,
+                get_state_scoped_into: get_state_scoped_into_callback,
+                send_result_seq: send_result_seq_callback,
+            },
+            config,
+        ));
 
         Self {
             plugins: HashMap::new(),
+            routes: Router::default(),
+            pools: HashMap::new(),
             host_ctx,
-            host_vtable,
         }
     }
 
+    /// The tag this host puts in every SID it issues, so SIDs from different
+    /// hosts in one process can be told apart (see [`sid_epoch`]). SIDs are
+    /// unique process-wide regardless.
+    pub fn epoch(&self) -> u16 {
+        self.host_ctx.epoch
+    }
+
+    /// A copy of the state plugins stored for `sid` with `set_state`, or
+    /// `None` if there is none. State of calls made with
+    /// [`PluginHandle::call_response_with_state`] is returned by the call
+    /// instead, and removed.
+    pub fn state_for(&self, sid: u64) -> Option<HashMap<String, Vec<u8>>> {
+        self.host_ctx
+            .state_per_sid
+            .get(&sid)
+            .map(|state| state.values.clone())
+    }
+
+    /// A copy of the state the plugin loaded as `name` stored in
+    /// `NR_SCOPE_PLUGIN`, or `None` if no such plugin is loaded.
+    pub fn plugin_state(&self, name: &str) -> Option<HashMap<String, Vec<u8>>> {
+        self.plugins
+            .get(name)
+            .map(|plugin| plugin.ctx.state.read().clone())
+    }
+
+    /// A copy of the state plugins stored in `NR_SCOPE_GLOBAL`.
+    pub fn global_state(&self) -> HashMap<String, Vec<u8>> {
+        self.host_ctx.global_state.read().clone()
+    }
+
+    /// Buffers of the arena behind
+    /// [`PluginHandle::call_response_shared`], in use and pooled.
+    pub fn shared_buffer_stats(&self) -> SharedBufferStats {
+        self.host_ctx.shared.stats()
+    }
+
+    /// Counters summed over every plugin of this host, for scraping. Each
+    /// plugin's own metrics are in [`PluginHandle::metrics_snapshot`].
+    pub fn metrics(&self) -> HostMetricsSnapshot {
+        self.host_ctx.metrics.snapshot()
+    }
+
+    /// What the host holds on to: requests waiting on a result, per shard,
+    /// and the state stored under SIDs. Walks both maps, so it is meant for
+    /// debugging leaks rather than for scraping.
+    pub fn diagnostics(&self) -> HostDiagnostics {
+        diagnostics::collect(&self.host_ctx)
+    }
+
+    /// Quarantine `plugin` when it keeps failing, as [`HealthPolicy`]
+    /// describes, or stop watching it with `None`. Setting a policy clears
+    /// the plugin's record and ends any quarantine.
+    ///
+    /// Plugins whose table has a `health` function, such as those built
+    /// with `define_plugin!` and a `health:` handler, are checked every
+    /// `check_interval` on a background task: a Tokio task with the default
+    /// `tokio-rt` feature, otherwise a thread. The policy is kept across
+    /// [`reload_one`](Self::reload_one).
+    pub fn set_health_policy(&mut self, plugin: &str, policy: Option<HealthPolicy>) -> Result<()> {
+        let loaded = self
+            .plugins
+            .get(plugin)
+            .ok_or_else(|| NylonRingHostError::PluginNotFound(plugin.to_string()))?;
+        if let Some(policy) = &policy {
+            if policy.failure_threshold == 0 || policy.check_interval.is_zero() {
+                return Err(NylonRingHostError::InvalidConfig(
+                    "a health policy needs a failure threshold and a check interval".to_string(),
+                ));
+            }
+        }
+        let epoch = loaded.health.set_policy(policy);
+        if policy.is_some() && loaded.abi.provides("health") {
+            self.host_ctx
+                .config
+                .spawner
+                .spawn(quarantine::watch(Arc::downgrade(loaded), epoch));
+        }
+        Ok(())
+    }
+
+    /// The health of every loaded plugin, sorted by name. Plugins without a
+    /// [`HealthPolicy`] are always [`HealthState::Healthy`].
+    pub fn health_report(&self) -> Vec<(String, PluginHealth)> {
+        let mut report: Vec<_> = self
+            .plugins
+            .iter()
+            .map(|(name, plugin)| (name.clone(), plugin.health.report()))
+            .collect();
+        report.sort_by(|a, b| a.0.cmp(&b.0));
+        report
+    }
+
+    /// A readable summary of the loaded plugins, sorted by name: each
+    /// plugin's version, the entries it published, and its
+    /// [`AbiDetails`].
+    pub fn describe(&self) -> String {
+        let mut names: Vec<&String> = self.plugins.keys().collect();
+        names.sort();
+        let mut out = String::new();
+        for name in names {
+            let plugin = &self.plugins[name];
+            let version = if plugin.version.is_empty() {
+                "unversioned"
+            } else {
+                &plugin.version
+            };
+            out.push_str(&format!("{name} ({version})\n"));
+            if let Some(provided) = &plugin.provided {
+                let mut entries: Vec<&str> = provided.iter().map(String::as_str).collect();
+                entries.sort();
+                out.push_str(&format!("  entries: {}\n", entries.join(", ")));
+            }
+            for line in plugin.abi.to_string().lines() {
+                out.push_str(&format!("  {line}\n"));
+            }
+        }
+        out
+    }
+
+    /// Drop unary and dispatched calls that have waited on a result for
+    /// longer than `older_than`, and state stored under a SID for longer
+    /// than that. Callers of a dropped call fail with
+    /// [`NylonRingHostError::OneshotClosed`]. Streams are left alone; bound
+    /// them with [`StreamOptions`] instead. Returns how many calls were
+    /// dropped.
+    pub fn purge_stale(&self, older_than: Duration) -> usize {
+        diagnostics::purge_stale(&self.host_ctx, older_than)
+    }
+
     /// Load a plugin from the specified path with a given name.
     pub fn load(&mut self, name: &str, path: &str) -> Result<()> {
-        unsafe {
-            let lib = Library::new(path).map_err(NylonRingHostError::FailedToLoadLibrary)?;
+        self.load_source(name, PluginSource::Path(path.to_string()))
+    }
+
+    /// Load a plugin from an in-memory shared library image.
+    ///
+    /// The image is written to a private temporary file (`0600` on Unix) that
+    /// is removed again when the plugin is unloaded.
+    pub fn load_from_bytes(&mut self, name: &str, bytes: &[u8]) -> Result<()> {
+        self.load_source(name, PluginSource::Bytes(Arc::from(bytes)))
+    }
+
+    /// Load the plugin at `path` under `name` if it meets `policy`, for
+    /// plugins from sources the host does not trust.
+    ///
+    /// The file is read once and the checks run on what was read; the
+    /// plugin is then loaded from a private copy of those bytes, as with
+    /// [`load_from_bytes`](Self::load_from_bytes), so the file cannot be
+    /// replaced in between. See [`LoadPolicy`] for the checks.
+    pub fn load_checked(&mut self, name: &str, path: &str, policy: LoadPolicy) -> Result<()> {
+        let bytes = std::fs::read(path).map_err(|source| NylonRingHostError::ReadPluginFile {
+            path: path.to_string(),
+            source,
+        })?;
+        policy.check_file(path, &bytes)?;
+        let bytes: Arc<[u8]> = Arc::from(bytes);
+        let file = source::materialize(&*bytes)?;
+        let lib = unsafe { Library::new(file.path()) }
+            .map_err(NylonRingHostError::FailedToLoadLibrary)?;
+        let info = plugin_info(&lib)?;
+        if let Some(info) = unsafe { info.as_ref() } {
+            policy.check_info(info)?;
+        }
+        let plugin = self.init_plugin(
+            name,
+            Some(lib),
+            info,
+            PluginSource::Bytes(bytes),
+            Some(file),
+        )?;
+        self.register(name, plugin);
+        Ok(())
+    }
+
+    /// Load an isolated instance of the plugin at `path` under `name`.
+    ///
+    /// Dynamic loaders hand out the already-loaded library when the same
+    /// path is opened twice, so two plugins loaded with [`load`](Self::load)
+    /// share their statics. Each instance loaded here comes from its own
+    /// private copy of the library instead, and `reload` copies it again.
+    ///
+    /// Returns [`NylonRingHostError::DuplicateInstanceUnsupported`] on
+    /// platforms where copies cannot be isolated.
+    pub fn load_instanced(&mut self, name: &str, path: &str) -> Result<()> {
+        self.load_source(name, PluginSource::Instanced(path.to_string()))
+    }
+
+    /// Load a WebAssembly plugin from a `.wasm` (or `.wat`) file.
+    ///
+    /// The module is called through the same [`PluginHandle`] API as a
+    /// native plugin. It must export `memory`, `nr_alloc(len) -> ptr` and
+    /// `nr_handle(entry_ptr, entry_len, sid, payload_ptr, payload_len) ->
+    /// status`, and sends results through the imported
+    /// `env.nr_send_result(sid, status, ptr, len)`. `nr_init`,
+    /// `nr_shutdown`, `nr_stream_data` and `nr_stream_close` are optional.
+    ///
+    /// Results must be sent before the export that produced them returns,
+    /// and calls into one module are serialized. Host extensions such as
+    /// per-call state are not available to WebAssembly plugins.
+    #[cfg(feature = "wasm")]
+    pub fn load_wasm(&mut self, name: &str, path: &str) -> Result<()> {
+        self.load_source(name, PluginSource::Wasm(path.to_string()))
+    }
+
+    /// Register a plugin that is linked into the host binary.
+    ///
+    /// `info` is what the plugin's `nylon_ring_get_plugin_v1` returns.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn load_static(&mut self, name: &str, info: &'static NrPluginInfo) -> Result<()> {
+        self.load_source(name, PluginSource::Static(info))
+    }
+
+    /// Load a batch of plugins.
+    ///
+    /// Plugins are loaded in dependency order (see [`PluginSpec::depends_on`]),
+    /// otherwise in the order given. The report lists one outcome per spec in
+    /// input order. With [`LoadStrategy::AllOrNothing`] nothing is registered
+    /// unless every plugin loads, and plugins that were already initialized
+    /// are shut down again.
+    pub fn load_many(&mut self, specs: &[PluginSpec], strategy: LoadStrategy) -> LoadReport {
+        let mut outcomes: Vec<Option<LoadOutcome>> = specs.iter().map(|_| None).collect();
+
+        let (order, cycle) = load::load_order(specs);
+        if !cycle.is_empty() {
+            let names: Vec<String> = cycle.iter().map(|&i| specs[i].name.clone()).collect();
+            for &i in &cycle {
+                outcomes[i] = Some(LoadOutcome::Failed(NylonRingHostError::DependencyCycle(
+                    names.clone(),
+                )));
+            }
+        }
+
+        let mut staged: Vec<(usize, LoadedPlugin)> = Vec::new();
+        for i in order {
+            let spec = &specs[i];
+
+            let missing = spec.depends_on.iter().find(|dep| {
+                let staged_ok = staged.iter().any(|(j, _)| specs[*j].name == **dep);
+                let batch_ok = specs.iter().zip(&outcomes).any(|(s, o)| {
+                    s.name == **dep && o.as_ref().is_some_and(LoadOutcome::is_loaded)
+                });
+                !(staged_ok || batch_ok || self.plugins.contains_key(dep.as_str()))
+            });
+            if let Some(dep) = missing {
+                outcomes[i] = Some(LoadOutcome::Skipped {
+                    dependency: dep.clone(),
+                });
+                continue;
+            }
+
+            match self.instantiate(&spec.name, spec.source.clone()) {
+                Ok(mut plugin) => {
+                    plugin.entries = spec
+                        .entries
+                        .as_ref()
+                        .map(|entries| Arc::new(entries.iter().cloned().collect()));
+                    match strategy {
+                        LoadStrategy::BestEffort => {
+                            self.register(&spec.name, plugin);
+                            outcomes[i] = Some(LoadOutcome::Loaded);
+                        }
+                        LoadStrategy::AllOrNothing => staged.push((i, plugin)),
+                    }
+                }
+                Err(e) => outcomes[i] = Some(LoadOutcome::Failed(e)),
+            }
+        }
+
+        if staged.len() == specs.len() {
+            for (i, plugin) in staged {
+                self.register(&specs[i].name, plugin);
+                outcomes[i] = Some(LoadOutcome::Loaded);
+            }
+        } else {
+            // Dropping a staged plugin runs its shutdown; undo in reverse
+            // load order so dependents go first.
+            for (i, plugin) in staged.into_iter().rev() {
+                drop(plugin);
+                outcomes[i] = Some(LoadOutcome::RolledBack);
+            }
+        }
+
+        LoadReport {
+            outcomes: specs
+                .iter()
+                .zip(outcomes)
+                .map(|(spec, outcome)| {
+                    (
+                        spec.name.clone(),
+                        outcome.unwrap_or(LoadOutcome::RolledBack),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Validate payloads of `plugin`'s `entry` on the unary call paths
+    /// ([`PluginHandle::call_response`] and
+    /// [`call_response_fast`](PluginHandle::call_response_fast)), replacing
+    /// any rule registered before or published by the plugin for the entry.
+    ///
+    /// A request that violates the rule fails with
+    /// [`NylonRingHostError::SchemaViolation`] without reaching the plugin.
+    /// The rule is kept across [`reload`](Self::reload).
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// use nylon_ring_host::{testing, BytesSchema, NylonRingHost, NylonRingHostError};
+    ///
+    /// let mut host = NylonRingHost::new();
+    /// host.load_static("mock", testing::mock_plugin())?;
+    /// host.register_schema("mock", "echo", BytesSchema::new().max_len(4))?;
+    ///
+    /// let plugin = host.plugin("mock").unwrap();
+    /// assert!(plugin.call_response("echo", b"ping").await.is_ok());
+    /// assert!(matches!(
+    ///     plugin.call_response("echo", b"too long").await,
+    ///     Err(NylonRingHostError::SchemaViolation { .. })
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_schema(
+        &mut self,
+        plugin: &str,
+        entry: &str,
+        rule: impl Into<SchemaRule>,
+    ) -> Result<()> {
+        let loaded = self
+            .plugins
+            .get(plugin)
+            .ok_or_else(|| NylonRingHostError::PluginNotFound(plugin.to_string()))?;
+        loaded
+            .ctx
+            .schemas
+            .write()
+            .registered
+            .insert(entry.to_string(), Arc::new(rule.into()));
+        Ok(())
+    }
+
+    /// Choose where `plugin`'s `handle` runs for unary calls
+    /// ([`PluginHandle::call_response`] and
+    /// [`call_response_fast`](PluginHandle::call_response_fast)).
+    ///
+    /// [`ExecutionPolicy::DedicatedPool`] starts threads owned by the
+    /// plugin, replacing any pool it had; calls already queued on the old
+    /// pool still run. Fire-and-forget calls and streams are not affected.
+    /// The policy is kept across [`reload`](Self::reload).
+    pub fn set_execution_policy(&mut self, plugin: &str, policy: ExecutionPolicy) -> Result<()> {
+        self.plugins
+            .get(plugin)
+            .ok_or_else(|| NylonRingHostError::PluginNotFound(plugin.to_string()))?
+            .set_execution_policy(policy)
+    }
 
-            let get_plugin: Symbol<extern "C" fn() -> *const NrPluginInfo> =
-                lib.get(b"nylon_ring_get_plugin_v1\0").map_err(|_| {
-                    NylonRingHostError::MissingSymbol("nylon_ring_get_plugin_v1".to_string())
-                })?;
+    /// Answer calls that `caller` dispatches to `entry` of `target` from a
+    /// cache, keyed by payload, replacing any rule the route had. `target`'s
+    /// `handle` then runs once per distinct payload and TTL rather than once
+    /// per call. Only `Ok` responses are cached; hits are counted in
+    /// [`HostMetricsSnapshot::dispatch_cache_hits`].
+    ///
+    /// Responses from `target` are dropped when it is reloaded or unloaded,
+    /// or on [`invalidate_dispatch_cache`](Self::invalidate_dispatch_cache).
+    pub fn cache_dispatch(
+        &mut self,
+        caller: &str,
+        target: &str,
+        entry: &str,
+        rule: DispatchCacheRule,
+    ) {
+        self.host_ctx
+            .dispatch_cache
+            .set_rule(caller, target, entry, Some(rule));
+    }
+
+    /// Stop caching calls from `caller` to `entry` of `target`.
+    pub fn uncache_dispatch(&mut self, caller: &str, target: &str, entry: &str) {
+        self.host_ctx
+            .dispatch_cache
+            .set_rule(caller, target, entry, None);
+    }
+
+    /// Drop the cached responses from `target`, for all callers; only those
+    /// of `entry` if given. Cache rules stay in place.
+    pub fn invalidate_dispatch_cache(&self, target: &str, entry: Option<&str>) {
+        self.host_ctx.dispatch_cache.invalidate(target, entry);
+    }
+
+    /// Load every plugin library in `dir`.
+    ///
+    /// Libraries are recognized by the platform's extension (`.so`,
+    /// `.dylib` or `.dll`) and registered under their file name without
+    /// the `lib` prefix. A sidecar `<library>.plugin.toml` can override the
+    /// name, disable the library with `enabled = false`, or restrict the
+    /// callable entries with `entries = [...]`.
+    ///
+    /// One bad library or manifest does not stop the scan: the report lists
+    /// an outcome per enabled library, in file name order. Only failing to
+    /// read `dir` itself is an error.
+    pub fn load_dir(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: LoadDirOptions,
+    ) -> Result<LoadReport> {
+        let dir = dir.as_ref();
+        let libraries =
+            load::scan_dir(dir).map_err(|source| NylonRingHostError::ReadPluginDir {
+                path: dir.display().to_string(),
+                source,
+            })?;
+
+        let mut names = FxHashSet::default();
+        let mut found = Vec::new();
+        for library in &libraries {
+            let spec = match load::discover(library) {
+                Ok(Some(spec)) => spec,
+                Ok(None) => continue,
+                Err(e) => {
+                    found.push(Err((load::default_name(library), e)));
+                    continue;
+                }
+            };
+            let taken = !names.insert(spec.name.clone()) || self.plugins.contains_key(&spec.name);
+            if taken && !options.overwrite {
+                let e = NylonRingHostError::PluginAlreadyLoaded(spec.name.clone());
+                found.push(Err((spec.name, e)));
+                continue;
+            }
+            found.push(Ok(spec));
+        }
+
+        let specs: Vec<PluginSpec> = found
+            .iter()
+            .filter_map(|f| f.as_ref().ok())
+            .cloned()
+            .collect();
+        let mut loaded = self
+            .load_many(&specs, LoadStrategy::BestEffort)
+            .outcomes
+            .into_iter();
+        let outcomes = found
+            .into_iter()
+            .filter_map(|f| match f {
+                Ok(_) => loaded.next(),
+                Err((name, e)) => Some((name, LoadOutcome::Failed(e))),
+            })
+            .collect();
+        Ok(LoadReport { outcomes })
+    }
+
+    /// Make a plugin callable, replacing any plugin of the same name.
+    fn register(&mut self, name: &str, plugin: LoadedPlugin) {
+        let plugin = Arc::new(plugin);
+        self.host_ctx
+            .dispatch_targets
+            .write()
+            .insert(name.to_string(), Arc::downgrade(&plugin));
+        self.host_ctx.dispatch_cache.invalidate(name, None);
+        self.plugins.insert(name.to_string(), plugin);
+    }
+
+    fn load_source(&mut self, name: &str, source: PluginSource) -> Result<()> {
+        let plugin = self.instantiate(name, source)?;
+        self.register(name, plugin);
+        Ok(())
+    }
 
-            let info_ptr = get_plugin();
+    /// Open and initialize a plugin without registering it.
+    fn instantiate(&self, name: &str, source: PluginSource) -> Result<LoadedPlugin> {
+        match source {
+            PluginSource::Path(path) => {
+                let lib = unsafe { Library::new(&path) }
+                    .map_err(NylonRingHostError::FailedToLoadLibrary)?;
+                let info = plugin_info(&lib)?;
+                self.init_plugin(name, Some(lib), info, PluginSource::Path(path), None)
+            }
+            PluginSource::Bytes(bytes) => {
+                let file = source::materialize(&*bytes)?;
+                let lib = unsafe { Library::new(file.path()) }
+                    .map_err(NylonRingHostError::FailedToLoadLibrary)?;
+                let info = plugin_info(&lib)?;
+                self.init_plugin(
+                    name,
+                    Some(lib),
+                    info,
+                    PluginSource::Bytes(bytes),
+                    Some(file),
+                )
+            }
+            PluginSource::Instanced(path) => {
+                let file = source::instance_copy(&path)?;
+                let lib = unsafe { Library::new(file.path()) }
+                    .map_err(NylonRingHostError::FailedToLoadLibrary)?;
+                let info = plugin_info(&lib)?;
+                self.init_plugin(
+                    name,
+                    Some(lib),
+                    info,
+                    PluginSource::Instanced(path),
+                    Some(file),
+                )
+            }
+            #[cfg(feature = "wasm")]
+            PluginSource::Wasm(path) => {
+                let ctx = Arc::new(PluginContext::new(name, self.host_ctx.clone()));
+                let plugin = wasm::WasmPlugin::load(&path, ctx.clone())?;
+                let abi = AbiDetails::wasm(plugin.provided());
+                Ok(LoadedPlugin {
+                    _lib: None,
+                    backend: ManuallyDrop::new(Backend::Wasm(Box::new(plugin))),
+                    plugin_ctx: std::ptr::null_mut(),
+                    host_ctx: self.host_ctx.clone(),
+                    ctx,
+                    source: PluginSource::Wasm(path),
+                    entries: None,
+                    entry_limits: EntryLimits::default(),
+                    // `nr_handle` returns `Ok` for answers sent later too,
+                    // so it is served as ABI v1.
+                    abi,
+                    version: String::new(),
+                    provided: None,
+                    tombstones: Tombstones::default(),
+                    health: HealthMonitor::default(),
+                    pool: RwLock::new(None),
+                    blocking_pool: Mutex::new(None),
+                    shutdown: Once::new(),
+                    _temp_file: None,
+                })
+            }
+            #[cfg(any(test, feature = "testing"))]
+            PluginSource::Static(info) => {
+                self.init_plugin(name, None, info, PluginSource::Static(info), None)
+            }
+        }
+    }
+
+    fn init_plugin(
+        &self,
+        name: &str,
+        lib: Option<Library>,
+        info_ptr: *const NrPluginInfo,
+        source: PluginSource,
+        temp_file: Option<NamedTempFile>,
+    ) -> Result<LoadedPlugin> {
+        unsafe {
             if info_ptr.is_null() {
                 return Err(NylonRingHostError::NullPluginInfo);
             }
             let info = &*info_ptr;
 
-            if !info.compatible(1) {
+            if !(1..=NR_ABI_VERSION).contains(&info.abi_version) {
                 return Err(NylonRingHostError::IncompatibleAbiVersion {
-                    expected: 1,
+                    expected: NR_ABI_VERSION,
                     actual: info.abi_version,
                 });
             }
@@ -269,53 +1965,357 @@ impl NylonRingHost {
             if plugin_vtable.init.is_none() || plugin_vtable.handle.is_none() {
                 return Err(NylonRingHostError::MissingRequiredFunctions);
             }
+            // Older vtables end before `health`, or before `handle_req`.
+            let health = if info.abi_version >= 6 {
+                plugin_vtable.health
+            } else {
+                None
+            };
+            let handle_req = if info.abi_version >= 8 {
+                plugin_vtable.handle_req
+            } else {
+                None
+            };
 
             // Plugin context from info
             let plugin_ctx = info.plugin_ctx;
 
+            // Each plugin gets its own context so callbacks can identify it
+            let ctx = Arc::new(PluginContext::new(name, self.host_ctx.clone()));
+            let mut provided = None;
+
             // Initialize plugin
             if let Some(init_fn) = plugin_vtable.init {
-                init_fn(
-                    Arc::as_ptr(&self.host_ctx) as *mut c_void,
-                    &*self.host_vtable,
-                );
+                let status = init_fn(Arc::as_ptr(&ctx) as *mut c_void, &HOST_VTABLE);
+                let mut state = self.take_init_state();
+                if status != NrStatus::Ok {
+                    let message = state
+                        .remove(INIT_ERROR_KEY)
+                        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+                    return Err(NylonRingHostError::PluginInitFailed { status, message });
+                }
+                provided = state
+                    .remove(ENTRIES_KEY)
+                    .map(|list| tombstone::parse(&list));
+                if misdelivery::declares_echo(&mut state) {
+                    ctx.set_echoes_nonce();
+                }
+                ctx.schemas.write().publish(name, &state);
             }
 
             let loaded = LoadedPlugin {
                 _lib: lib,
-                vtable: plugin_vtable,
+                backend: ManuallyDrop::new(Backend::Native {
+                    vtable: plugin_vtable,
+                    health,
+                    handle_req,
+                }),
                 plugin_ctx,
                 host_ctx: self.host_ctx.clone(),
-                path: path.to_string(),
+                ctx,
+                source,
+                entries: None,
+                entry_limits: EntryLimits::default(),
+                abi: AbiDetails::native(
+                    info.abi_version,
+                    [
+                        true,
+                        true,
+                        plugin_vtable.shutdown.is_some(),
+                        plugin_vtable.stream_data.is_some(),
+                        plugin_vtable.stream_close.is_some(),
+                        health.is_some(),
+                        handle_req.is_some(),
+                    ],
+                ),
+                version: info.version.as_str_lossy().into_owned(),
+                provided,
+                tombstones: Tombstones::default(),
+                health: HealthMonitor::default(),
+                pool: RwLock::new(None),
+                blocking_pool: Mutex::new(None),
+                shutdown: Once::new(),
+                _temp_file: temp_file,
             };
 
-            self.plugins.insert(name.to_string(), Arc::new(loaded));
-            Ok(())
+            Ok(loaded)
         }
     }
 
-    /// Unload a plugin by name.
+    /// Remove the state a plugin stored under `INIT_SID` during `init`.
+    fn take_init_state(&self) -> HashMap<String, Vec<u8>> {
+        self.host_ctx
+            .state_per_sid
+            .remove(&INIT_SID)
+            .map(|(_, state)| state.values)
+            .unwrap_or_default()
+    }
+
+    /// Unload a plugin by name. Routes to the plugin are removed.
+    ///
+    /// Once the last handle to the plugin is gone, streams and dispatched
+    /// calls still in flight get up to the drain timeout set with
+    /// [`set_drain_timeout`](Self::set_drain_timeout) to finish. Then its
+    /// callbacks are revoked (they return at once, with
+    /// [`NrStatus::Revoked`] where they report a status) and its `shutdown`
+    /// runs under the watchdog set with
+    /// [`set_shutdown_watchdog`](Self::set_shutdown_watchdog). A plugin
+    /// still in `shutdown` when the watchdog fires is detached and keeps its
+    /// library loaded until `shutdown` returns.
     pub fn unload(&mut self, name: &str) -> Result<()> {
         self.plugins.remove(name);
+        self.host_ctx.dispatch_targets.write().remove(name);
+        self.host_ctx.dispatch_cache.invalidate(name, None);
+        self.routes.remove_plugin(name);
         Ok(())
     }
 
+    /// How long a plugin's `shutdown` may take on unload, reload or drop of
+    /// the host before it is detached. Defaults to 5 seconds.
+    pub fn set_shutdown_watchdog(&mut self, budget: Duration) {
+        *self.host_ctx.shutdown_watchdog.lock() = budget;
+    }
+
+    /// How long unload, reload or drop of the host waits for a plugin's
+    /// calls in flight (see [`PluginHandle::in_flight`]) before revoking its
+    /// callbacks and running its `shutdown`. Defaults to 5 seconds.
+    pub fn set_drain_timeout(&mut self, timeout: Duration) {
+        *self.host_ctx.drain_timeout.lock() = timeout;
+    }
+
+    /// How long calls to an entry a reload removed fail with
+    /// [`NylonRingHostError::EntryRemovedInVersion`] before they reach the
+    /// plugin like any unknown entry. Defaults to 60 seconds.
+    pub fn set_entry_tombstone_grace(&mut self, grace: Duration) {
+        *self.host_ctx.tombstone_grace.lock() = grace;
+    }
+
+    /// What this host does once the process has used up its session IDs.
+    /// Defaults to [`SidExhaustion::Recycle`].
+    pub fn set_sid_exhaustion(&mut self, policy: SidExhaustion) {
+        *self.host_ctx.sid_exhaustion.lock() = policy;
+    }
+
+    /// Shut down every plugin, waiting at most `timeout` in total.
+    ///
+    /// Each plugin's `shutdown` runs on its own thread. Returns
+    /// `(name, completed)` per plugin, sorted by name. A plugin that is still
+    /// in `shutdown` at the deadline is detached: its thread keeps the library
+    /// loaded until it returns. As on [`unload`](Self::unload), callbacks are
+    /// revoked before `shutdown` runs.
+    ///
+    /// Outstanding [`PluginHandle`]s keep their library loaded, but the
+    /// plugin behind them has been shut down and refuses calls with
+    /// [`NrStatus::Revoked`].
+    pub fn shutdown_all(self, timeout: Duration) -> Vec<(String, bool)> {
+        let Self { plugins, .. } = self;
+        let deadline = Instant::now() + timeout;
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let mut results = Vec::with_capacity(plugins.len());
+        for (index, (name, plugin)) in plugins.into_iter().enumerate() {
+            let done_tx = done_tx.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("nylon-ring-shutdown-{name}"))
+                .spawn(move || {
+                    plugin.shutdown();
+                    let _ = done_tx.send(index);
+                    // The library is unloaded here, once shutdown has returned.
+                    drop(plugin);
+                });
+            results.push((name, spawned.is_ok()));
+        }
+        drop(done_tx);
+
+        let mut completed = vec![false; results.len()];
+        let mut remaining = results.iter().filter(|(_, spawned)| *spawned).count();
+        while remaining > 0 {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match done_rx.recv_timeout(wait) {
+                Ok(index) => {
+                    completed[index] = true;
+                    remaining -= 1;
+                }
+                Err(_) => break,
+            }
+        }
+
+        let mut results: Vec<_> = results
+            .into_iter()
+            .zip(completed)
+            .map(|((name, _), completed)| (name, completed))
+            .collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
     /// Reload all plugins.
     pub fn reload(&mut self) -> Result<()> {
-        let mut plugins_to_reload = Vec::new();
-        for (name, plugin) in &self.plugins {
-            plugins_to_reload.push((name.clone(), plugin.path.clone()));
+        let names: Vec<String> = self.plugins.keys().cloned().collect();
+        for name in names {
+            self.reload_one(&name)?;
         }
+        Ok(())
+    }
 
-        // Load new versions - insert() will atomically replace old ones
-        // This ensures zero downtime (plugin() always returns a value)
-        for (name, path) in plugins_to_reload {
-            self.load(&name, &path)?;
+    /// Reload the plugin `name` from where it was loaded, keeping its entry
+    /// allowlist, entry limits, registered schemas and execution policy.
+    /// Other plugins are left running.
+    ///
+    /// The new instance replaces the old one only once it has initialized,
+    /// so [`plugin`](Self::plugin) always finds one. Handles obtained before
+    /// the reload keep calling the old instance; it is shut down when the
+    /// last of them is dropped.
+    ///
+    /// For plugins built with `define_plugin!`, which list their entries,
+    /// entries the new instance no longer serves are reported with
+    /// [`TraceEvent::EntriesChanged`], routes to them are flagged (see
+    /// [`stale_routes`](Self::stale_routes)), and calls to them fail with
+    /// [`NylonRingHostError::EntryRemovedInVersion`] for the grace period
+    /// set with [`set_entry_tombstone_grace`](Self::set_entry_tombstone_grace).
+    pub fn reload_one(&mut self, name: &str) -> Result<()> {
+        let old = self
+            .plugins
+            .get(name)
+            .ok_or_else(|| NylonRingHostError::PluginNotFound(name.to_string()))?;
+        let source = old.source.clone();
+        let entries = old.entries.clone();
+        let entry_limits = old.entry_limits.copy();
+        let health_policy = old.health.policy();
+        let schemas = old.ctx.schemas.read().registered.clone();
+        let policy = old.execution_policy();
+
+        let mut plugin = self.instantiate(name, source)?;
+        plugin.entries = entries;
+        plugin.entry_limits = entry_limits;
+        plugin.ctx.schemas.write().registered = schemas;
+        plugin.set_execution_policy(policy)?;
+
+        // Only plugins that list their entries on both sides can be compared.
+        let (added, removed) = match (&old.provided, &plugin.provided) {
+            (Some(old_entries), Some(new_entries)) => tombstone::diff(old_entries, new_entries),
+            _ => Default::default(),
+        };
+        let empty = FxHashSet::default();
+        let grace = *self.host_ctx.tombstone_grace.lock();
+        plugin.tombstones = old.tombstones.after_reload(
+            &removed,
+            plugin.provided.as_ref().unwrap_or(&empty),
+            (&old.version, &plugin.version),
+            grace,
+        );
+        if !added.is_empty() || !removed.is_empty() {
+            self.host_ctx.tracer.emit(TraceEvent::EntriesChanged {
+                plugin: name,
+                added: &added,
+                removed: &removed,
+            });
+            self.routes.entries_changed(name, &added, &removed);
+        }
+
+        self.register(name, plugin);
+        // The new instance starts with a clean record.
+        if health_policy.is_some() {
+            self.set_health_policy(name, health_policy)?;
         }
+        Ok(())
+    }
+
+    /// Install a hook that observes every call and stream frame, replacing
+    /// any previous hook. Applies to plugins that are already loaded.
+    ///
+    /// The hook runs inline on the calling thread (or the plugin's thread for
+    /// stream frames), so it should be cheap.
+    pub fn set_trace_hook(&mut self, hook: TraceHook) {
+        self.host_ctx.tracer.set(Some(hook));
+    }
+
+    /// Remove the trace hook.
+    pub fn clear_trace_hook(&mut self) {
+        self.host_ctx.tracer.set(None);
+    }
 
+    /// Call `hook` when a stream consumer stays behind its plugin for longer
+    /// than `alert.window`. The hook fires once per episode; the episode
+    /// ends when the lag drops back under the threshold.
+    ///
+    /// Streams pick up the alert configuration when they are opened. Only
+    /// those streams, and resumable ones, measure their lag; the others skip
+    /// the bookkeeping and report none.
+    pub fn on_stream_lag(&mut self, alert: StreamLagAlert, hook: StreamLagHook) {
+        *self.host_ctx.stream_lag_alert.lock() = Some((alert, hook));
+    }
+
+    /// Route entries matching `pattern` to the loaded plugin `plugin`.
+    ///
+    /// `pattern` is an exact entry name or a prefix ending in `*`
+    /// (`"image.*"`). Exact routes take precedence over prefixes, and longer
+    /// prefixes over shorter ones. Mapping a pattern that is already routed
+    /// to a different plugin fails with
+    /// [`NylonRingHostError::AmbiguousRoute`].
+    pub fn route(&mut self, pattern: &str, plugin: &str) -> Result<()> {
+        if !self.plugins.contains_key(plugin) {
+            return Err(NylonRingHostError::PluginNotFound(plugin.to_string()));
+        }
+        self.routes.add(pattern, plugin)
+    }
+
+    /// Exact routes whose entry was removed by a reload of their plugin,
+    /// sorted. The flag clears when a later reload brings the entry back or
+    /// the plugin is unloaded.
+    pub fn stale_routes(&self) -> Vec<String> {
+        self.routes.stale()
+    }
+
+    /// The plugin `entry` is routed to.
+    pub fn resolve(&self, entry: &str) -> Option<PluginHandle> {
+        self.routes
+            .resolve(entry)
+            .and_then(|plugin| self.plugin(plugin))
+    }
+
+    /// Call `entry` on the plugin it is routed to (see [`route`](Self::route)),
+    /// as [`PluginHandle::call_response`].
+    pub async fn call_routed(&self, entry: &str, payload: &[u8]) -> Result<(NrStatus, Vec<u8>)> {
+        let plugin = self
+            .resolve(entry)
+            .ok_or_else(|| NylonRingHostError::NoRoute(entry.to_string()))?;
+        plugin.call_response(entry, payload).await
+    }
+
+    /// Group the loaded plugins `members` under `pool_name`, for
+    /// [`call_pool_response`](Self::call_pool_response). Registering a pool
+    /// again replaces its members.
+    pub fn register_pool(&mut self, pool_name: &str, members: &[&str]) -> Result<()> {
+        if let Some(missing) = members.iter().find(|m| !self.plugins.contains_key(**m)) {
+            return Err(NylonRingHostError::PluginNotFound(missing.to_string()));
+        }
+        self.pools
+            .insert(pool_name.to_string(), PluginPool::new(members));
         Ok(())
     }
 
+    /// Call `entry` on the next member of the pool `pool`, in round-robin
+    /// order, as [`PluginHandle::call_response`]. Members unloaded since the
+    /// pool was registered are skipped; with none left, the call fails with
+    /// [`NylonRingHostError::EmptyPool`].
+    pub async fn call_pool_response(
+        &self,
+        pool: &str,
+        entry: &str,
+        payload: &[u8],
+    ) -> Result<(NrStatus, Vec<u8>)> {
+        let members = self
+            .pools
+            .get(pool)
+            .ok_or_else(|| NylonRingHostError::PoolNotFound(pool.to_string()))?;
+        let plugin = members
+            .rotation()
+            .find_map(|member| self.plugin(member))
+            .ok_or_else(|| NylonRingHostError::EmptyPool(pool.to_string()))?;
+        plugin.call_response(entry, payload).await
+    }
+
     /// Get a handle to a loaded plugin by name.
     pub fn plugin(&self, name: &str) -> Option<PluginHandle> {
         self.plugins
@@ -325,15 +2325,95 @@ impl NylonRingHost {
 
     /// Get host extension pointer from host_ctx.
     ///
+    /// The extension table gives plugins per-call state, among others. The
+    /// mock plugin's `remember` entry stores its payload with `set_state` and
+    /// reads it back with `get_state`:
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// # use nylon_ring_host::{testing, NylonRingHost};
+    /// # let mut host = NylonRingHost::new();
+    /// # host.load_static("mock", testing::mock_plugin())?;
+    /// let plugin = host.plugin("mock").unwrap();
+    /// let (_, data) = plugin.call_response("remember", b"kept").await?;
+    /// assert_eq!(data, b"kept");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
     /// # Safety
     ///
-    /// The caller must ensure that `host_ctx` is a valid pointer to a `HostContext`
-    /// instance that was created by this host, or a null pointer.
+    /// The caller must ensure that `host_ctx` is a `host_ctx` pointer handed to
+    /// a plugin by this host, or a null pointer.
     pub unsafe fn get_host_ext(host_ctx: *mut c_void) -> *const NrHostExt {
-        if host_ctx.is_null() {
-            return std::ptr::null();
-        }
-        let ctx = &*(host_ctx as *const HostContext);
-        &ctx.host_ext as *const NrHostExt
+        get_host_ext_callback(host_ctx)
+    }
+
+    /// Call `entry` on the plugin loaded as `target` without waiting for the
+    /// response. Same as the `dispatch_spawn` extension callback.
+    ///
+    /// The mock plugin's `forward` entry dispatches to another plugin this
+    /// way from inside its `handle`:
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+    /// use nylon_ring_host::{testing, NylonRingHost};
+    ///
+    /// let mut host = NylonRingHost::new();
+    /// host.load_static("front", testing::mock_plugin())?;
+    /// host.load_static("back", testing::mock_plugin())?;
+    ///
+    /// let front = host.plugin("front").unwrap();
+    /// let (_, data) = front.call_response("forward", b"back:hi").await?;
+    /// assert_eq!(data, b"hi");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as [`NylonRingHost::get_host_ext`].
+    pub unsafe fn dispatch_spawn(
+        host_ctx: *mut c_void,
+        target: &str,
+        entry: &str,
+        payload: &[u8],
+    ) -> NrTuple<NrStatus, u64> {
+        dispatch_spawn_callback(
+            host_ctx,
+            NrStr::new(target),
+            NrStr::new(entry),
+            NrBytes::from_slice(payload),
+        )
+    }
+
+    /// Take the response to a call started with
+    /// [`dispatch_spawn`](NylonRingHost::dispatch_spawn), or `None` if it has
+    /// not arrived yet.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`NylonRingHost::get_host_ext`].
+    pub unsafe fn try_take_dispatch_result(
+        host_ctx: *mut c_void,
+        sid: u64,
+    ) -> Option<(NrStatus, Vec<u8>)> {
+        let mut status = NrStatus::Ok;
+        let mut payload = std::mem::MaybeUninit::<NrVec<u8>>::uninit();
+        take_dispatch_result_callback(host_ctx, sid, &mut status, payload.as_mut_ptr())
+            .then(|| (status, payload.assume_init().into_vec()))
+    }
+}
+
+/// Resolve the plugin info exported by a loaded library.
+fn plugin_info(lib: &Library) -> Result<*const NrPluginInfo> {
+    unsafe {
+        let get_plugin: Symbol<extern "C" fn() -> *const NrPluginInfo> =
+            lib.get(b"nylon_ring_get_plugin_v1\0").map_err(|_| {
+                NylonRingHostError::MissingSymbol("nylon_ring_get_plugin_v1".to_string())
+            })?;
+        Ok(get_plugin())
     }
 }
@@ -0,0 +1,329 @@
+//! Batch plugin loading.
+//!
+//! `NylonRingHost::load_many` loads a set of plugins in dependency order and
+//! reports a per-plugin outcome, either committing every success
+//! (`BestEffort`) or nothing at all (`AllOrNothing`).
+//!
+//! `NylonRingHost::load_dir` builds such a batch from the shared libraries
+//! in a directory, each optionally described by a sidecar manifest.
+
+use crate::error::NylonRingHostError;
+use crate::source::PluginSource;
+use std::collections::HashMap;
+use std::env::consts::{DLL_EXTENSION, DLL_PREFIX};
+use std::path::{Path, PathBuf};
+
+/// Policy for a batch load when some plugins fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadStrategy {
+    /// Commit every plugin that loads successfully and report the failures.
+    #[default]
+    BestEffort,
+    /// Initialize every plugin into a staging set first and only commit if
+    /// all of them succeed. Staged plugins are shut down otherwise.
+    AllOrNothing,
+}
+
+/// A plugin to load as part of a batch.
+#[derive(Clone)]
+pub struct PluginSpec {
+    pub(crate) name: String,
+    pub(crate) source: PluginSource,
+    pub(crate) depends_on: Vec<String>,
+    pub(crate) entries: Option<Vec<String>>,
+}
+
+impl PluginSpec {
+    /// A plugin loaded from a shared library path.
+    pub fn new(name: &str, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source: PluginSource::Path(path.to_string()),
+            depends_on: Vec::new(),
+            entries: None,
+        }
+    }
+
+    /// A plugin linked into the host binary.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn from_static(name: &str, info: &'static nylon_ring::NrPluginInfo) -> Self {
+        Self {
+            name: name.to_string(),
+            source: PluginSource::Static(info),
+            depends_on: Vec::new(),
+            entries: None,
+        }
+    }
+
+    /// Declare that this plugin must be loaded after `name`.
+    ///
+    /// `name` must either be part of the same batch or already loaded.
+    pub fn depends_on(mut self, name: &str) -> Self {
+        self.depends_on.push(name.to_string());
+        self
+    }
+
+    /// Only let the host call these entries. Calls to any other entry fail
+    /// with `Unsupported` without reaching the plugin.
+    pub fn allow_entries<I, S>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.entries = Some(entries.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// The name the plugin will be registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Outcome of loading a single plugin in a batch.
+#[derive(Debug)]
+pub enum LoadOutcome {
+    /// The plugin was loaded and registered.
+    Loaded,
+    /// The plugin failed to load.
+    Failed(NylonRingHostError),
+    /// The plugin was not attempted because a dependency did not load.
+    Skipped {
+        /// The dependency that is missing.
+        dependency: String,
+    },
+    /// The plugin loaded but was shut down again because another plugin in
+    /// an `AllOrNothing` batch failed.
+    RolledBack,
+}
+
+impl LoadOutcome {
+    /// Whether the plugin ended up registered.
+    pub fn is_loaded(&self) -> bool {
+        matches!(self, LoadOutcome::Loaded)
+    }
+}
+
+/// Per-plugin results of a batch load, in the order the specs were given.
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub outcomes: Vec<(String, LoadOutcome)>,
+}
+
+impl LoadReport {
+    /// Whether every plugin in the batch was loaded.
+    pub fn is_success(&self) -> bool {
+        self.outcomes.iter().all(|(_, o)| o.is_loaded())
+    }
+
+    /// Names of the plugins that were loaded.
+    pub fn loaded(&self) -> impl Iterator<Item = &str> {
+        self.outcomes
+            .iter()
+            .filter(|(_, o)| o.is_loaded())
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Plugins that were not loaded, with their outcome.
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &LoadOutcome)> {
+        self.outcomes
+            .iter()
+            .filter(|(_, o)| !o.is_loaded())
+            .map(|(name, o)| (name.as_str(), o))
+    }
+}
+
+/// Options for [`NylonRingHost::load_dir`](crate::NylonRingHost::load_dir).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadDirOptions {
+    /// Replace plugins that are already loaded under the same name, and let
+    /// later libraries in the directory replace earlier ones. Name
+    /// collisions are reported as failures otherwise.
+    pub overwrite: bool,
+}
+
+/// The sidecar manifest of a plugin library: `libfoo.plugin.toml` next to
+/// `libfoo.so`.
+///
+/// ```toml
+/// name = "foo"              # registered name, defaults to the file name
+/// enabled = true            # skip the library when false
+/// entries = ["get", "put"]  # entries the host may call, defaults to all
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Manifest {
+    name: Option<String>,
+    disabled: bool,
+    entries: Option<Vec<String>>,
+}
+
+impl Manifest {
+    pub(crate) fn parse(text: &str) -> std::result::Result<Self, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut manifest = Manifest::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("name", toml::Value::String(name)) => manifest.name = Some(name),
+                ("enabled", toml::Value::Boolean(enabled)) => manifest.disabled = !enabled,
+                ("entries", toml::Value::Array(entries)) => {
+                    let entries = entries
+                        .into_iter()
+                        .map(|entry| match entry {
+                            toml::Value::String(entry) => Ok(entry),
+                            other => Err(format!("entries must be strings, found {other}")),
+                        })
+                        .collect::<std::result::Result<_, _>>()?;
+                    manifest.entries = Some(entries);
+                }
+                ("name" | "enabled" | "entries", value) => {
+                    return Err(format!("unexpected {} for `{key}`", value.type_str()))
+                }
+                _ => return Err(format!("unknown key `{key}`")),
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// The plugin libraries in `dir`, sorted by file name.
+pub(crate) fn scan_dir(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut libraries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == DLL_EXTENSION) {
+            libraries.push(path);
+        }
+    }
+    libraries.sort();
+    Ok(libraries)
+}
+
+/// The name a library is registered under without a manifest: its file
+/// name without the platform's prefix and extension (`libfoo.so` is `foo`).
+pub(crate) fn default_name(library: &Path) -> String {
+    let stem = library
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    match stem.strip_prefix(DLL_PREFIX) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => stem,
+    }
+}
+
+/// The spec for `library`, or `None` if its manifest disables it.
+pub(crate) fn discover(library: &Path) -> Result<Option<PluginSpec>, NylonRingHostError> {
+    let path = library
+        .to_str()
+        .ok_or_else(|| NylonRingHostError::InvalidPluginPath(library.display().to_string()))?;
+
+    let manifest_path = library.with_extension("plugin.toml");
+    let manifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(text) => {
+            Manifest::parse(&text).map_err(|reason| NylonRingHostError::InvalidManifest {
+                path: manifest_path.display().to_string(),
+                reason,
+            })?
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+        Err(e) => {
+            return Err(NylonRingHostError::InvalidManifest {
+                path: manifest_path.display().to_string(),
+                reason: e.to_string(),
+            })
+        }
+    };
+    if manifest.disabled {
+        return Ok(None);
+    }
+
+    let name = manifest.name.unwrap_or_else(|| default_name(library));
+    let mut spec = PluginSpec::new(&name, path);
+    spec.entries = manifest.entries;
+    Ok(Some(spec))
+}
+
+/// Order specs so that dependencies come first.
+///
+/// Independent specs keep their input order. Returns the indices into
+/// `specs` in load order, followed by the indices that could not be ordered
+/// because they are part of (or depend on) a dependency cycle.
+pub(crate) fn load_order(specs: &[PluginSpec]) -> (Vec<usize>, Vec<usize>) {
+    let by_name: HashMap<&str, usize> = specs
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.name.as_str(), i))
+        .collect();
+
+    let mut order = Vec::with_capacity(specs.len());
+    let mut placed = vec![false; specs.len()];
+
+    // Repeatedly take the first spec whose in-batch dependencies are placed.
+    // Batches are small, so the quadratic scan keeps the order obvious.
+    while let Some(i) = (0..specs.len()).find(|&i| {
+        !placed[i]
+            && specs[i].depends_on.iter().all(|dep| {
+                by_name
+                    .get(dep.as_str())
+                    .is_none_or(|&d| placed[d] || d == i)
+            })
+    }) {
+        placed[i] = true;
+        order.push(i);
+    }
+
+    let unresolved = (0..specs.len()).filter(|&i| !placed[i]).collect();
+    (order, unresolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_order() {
+        let specs = vec![
+            PluginSpec::new("api", "api.so").depends_on("db"),
+            PluginSpec::new("metrics", "metrics.so"),
+            PluginSpec::new("db", "db.so").depends_on("config"),
+            PluginSpec::new("config", "config.so"),
+        ];
+        let (order, unresolved) = load_order(&specs);
+        let order: Vec<&str> = order.into_iter().map(|i| specs[i].name()).collect();
+        assert_eq!(order, ["metrics", "config", "db", "api"]);
+        assert!(unresolved.is_empty());
+
+        let cyclic = vec![
+            PluginSpec::new("a", "a.so").depends_on("b"),
+            PluginSpec::new("b", "b.so").depends_on("a"),
+            PluginSpec::new("c", "c.so"),
+        ];
+        assert_eq!(load_order(&cyclic), (vec![2], vec![0, 1]));
+    }
+
+    #[test]
+    fn test_manifest() {
+        assert_eq!(Manifest::parse("").unwrap(), Manifest::default());
+        assert_eq!(
+            Manifest::parse("name = \"kv\"\nenabled = false\nentries = [\"get\", \"put\"]")
+                .unwrap(),
+            Manifest {
+                name: Some("kv".to_string()),
+                disabled: true,
+                entries: Some(vec!["get".to_string(), "put".to_string()]),
+            }
+        );
+        assert!(Manifest::parse("enabled = \"yes\"").is_err());
+        assert!(Manifest::parse("entries = [1]").is_err());
+        assert!(Manifest::parse("version = 2").is_err());
+        assert!(Manifest::parse("name =").is_err());
+    }
+
+    #[test]
+    fn test_default_name() {
+        let library = |stem: &str| PathBuf::from(format!("/plugins/{stem}.{DLL_EXTENSION}"));
+        assert_eq!(default_name(&library(&format!("{DLL_PREFIX}auth"))), "auth");
+        assert_eq!(default_name(&library("auth")), "auth");
+    }
+}
@@ -0,0 +1,147 @@
+//! Checks on a plugin file before the host trusts it.
+//!
+//! [`NylonRingHost::load_checked`](crate::NylonRingHost::load_checked)
+//! reads the file once and checks the bytes it read: their SHA-256 digest,
+//! and the symbols the library exports. A library exporting `malloc`, say,
+//! would replace the allocator `NrVec` buffers are freed with, so it is
+//! refused before the loader runs any of its code. The same bytes are then
+//! loaded from a private copy, as with `load_from_bytes`, so the file cannot
+//! change between the checks and the load. The ABI version and info size
+//! the plugin reports are checked before its `init` runs.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use nylon_ring::{NrPluginInfo, NR_ABI_VERSION};
+use object::{Object, ObjectKind};
+use sha2::{Digest, Sha256};
+
+/// The symbol every plugin exports, allowed under any policy.
+const ENTRY_SYMBOL: &str = "nylon_ring_get_plugin_v1";
+
+/// What [`NylonRingHost::load_checked`](crate::NylonRingHost::load_checked)
+/// requires of a plugin file. Checks left at `None` are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadPolicy {
+    /// The ABI version the plugin must report, exactly.
+    pub abi_version: Option<u32>,
+    /// The `struct_size` the plugin's `NrPluginInfo` must report, exactly.
+    pub struct_size: Option<u32>,
+    /// The SHA-256 digest of the file.
+    pub sha256: Option<[u8; 32]>,
+    /// Symbols the library may export besides `nylon_ring_get_plugin_v1`.
+    pub allowed_exports: Option<Vec<String>>,
+}
+
+impl LoadPolicy {
+    /// Require this host's ABI version and info size, and no exports but
+    /// `nylon_ring_get_plugin_v1`. Set [`sha256`](Self::sha256) to pin the
+    /// file as well.
+    pub fn strict() -> Self {
+        Self {
+            abi_version: Some(NR_ABI_VERSION),
+            struct_size: Some(std::mem::size_of::<NrPluginInfo>() as u32),
+            sha256: None,
+            allowed_exports: Some(Vec::new()),
+        }
+    }
+
+    /// Check the file read from `path`, before it is loaded.
+    pub(crate) fn check_file(&self, path: &str, bytes: &[u8]) -> Result<()> {
+        if let Some(expected) = &self.sha256 {
+            let actual: [u8; 32] = Sha256::digest(bytes).into();
+            if actual != *expected {
+                return Err(NylonRingHostError::ChecksumMismatch {
+                    path: path.to_string(),
+                    expected: hex(expected),
+                    actual: hex(&actual),
+                });
+            }
+        }
+        if let Some(allowed) = &self.allowed_exports {
+            let inspect_failed = |reason: String| NylonRingHostError::InspectPluginFailed {
+                path: path.to_string(),
+                reason,
+            };
+            let file = object::File::parse(bytes).map_err(|e| inspect_failed(e.to_string()))?;
+            if file.kind() != ObjectKind::Dynamic {
+                return Err(inspect_failed("not a shared library".to_string()));
+            }
+            let exports = file.exports().map_err(|e| inspect_failed(e.to_string()))?;
+            for export in exports {
+                let name = String::from_utf8_lossy(export.name());
+                // Mach-O prefixes C symbols with an underscore.
+                let symbol = match file.format() {
+                    object::BinaryFormat::MachO => name.strip_prefix('_').unwrap_or(&name),
+                    _ => &name,
+                };
+                if symbol != ENTRY_SYMBOL && !allowed.iter().any(|allowed| allowed == symbol) {
+                    return Err(NylonRingHostError::UnexpectedExport {
+                        path: path.to_string(),
+                        symbol: symbol.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the info the plugin returned, before its `init` runs.
+    pub(crate) fn check_info(&self, info: &NrPluginInfo) -> Result<()> {
+        if let Some(expected) = self.abi_version {
+            if info.abi_version != expected {
+                return Err(NylonRingHostError::IncompatibleAbiVersion {
+                    expected,
+                    actual: info.abi_version,
+                });
+            }
+        }
+        if let Some(expected) = self.struct_size {
+            if info.struct_size != expected {
+                return Err(NylonRingHostError::StructSizeMismatch {
+                    expected,
+                    actual: info.struct_size,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_that_are_not_libraries_are_refused() {
+        let policy = LoadPolicy::strict();
+        assert!(matches!(
+            policy.check_file("plugin", b"not a library"),
+            Err(NylonRingHostError::InspectPluginFailed { .. })
+        ));
+        // Nothing to inspect without an export rule.
+        assert!(LoadPolicy::default()
+            .check_file("plugin", b"not a library")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_checksum_is_compared() {
+        let policy = LoadPolicy {
+            sha256: Some(Sha256::digest(b"image").into()),
+            ..LoadPolicy::default()
+        };
+        assert!(policy.check_file("plugin", b"image").is_ok());
+        let Err(NylonRingHostError::ChecksumMismatch {
+            expected, actual, ..
+        }) = policy.check_file("plugin", b"other")
+        else {
+            panic!("checksum not compared");
+        };
+        assert_eq!(expected, hex(&Sha256::digest(b"image")));
+        assert_eq!(actual.len(), 64);
+    }
+}
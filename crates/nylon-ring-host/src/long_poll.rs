@@ -0,0 +1,104 @@
+//! Long-poll calls.
+//!
+//! A long-poll entry holds the call open until an event arrives or its wait
+//! runs out, then answers with the event or with nothing. The host bounds the
+//! wait with its own deadline, which progress frames can renew. See
+//! [`nylon_ring::long_poll`] for the frame encoding and the plugin-side helper.
+
+use crate::rt::{self, Instant};
+use crate::types::{self, Result};
+use crate::{context, stream, PluginHandle};
+use nylon_ring::NrStatus;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Options for [`PluginHandle::call_long_poll`].
+#[derive(Debug, Clone, Copy)]
+pub struct LongPollOptions {
+    /// How long to wait for an answer before treating the call as empty.
+    pub max_wait: Duration,
+    /// Whether a progress frame restarts the `max_wait` deadline.
+    pub renew_via_progress: bool,
+}
+
+/// How a long-poll call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LongPollOutcome {
+    /// The plugin answered with a result.
+    Fulfilled(NrStatus, Vec<u8>),
+    /// The plugin answered empty, or the deadline passed first.
+    Empty,
+}
+
+impl PluginHandle {
+    /// Call a long-poll entry.
+    ///
+    /// Long polls are counted as fulfilled or empty in the metrics and kept
+    /// out of the latency histogram, since their duration is mostly waiting.
+    pub async fn call_long_poll(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        options: LongPollOptions,
+    ) -> Result<LongPollOutcome> {
+        let _in_flight = context::InFlight::acquire(&self.plugin.ctx)?;
+        let _call = self.plugin.ctx.metrics.start_call(entry);
+
+        let sid = crate::next_sid(&self.plugin.host_ctx, crate::SidMode::Stream)?;
+        let (tx, mut rx) = stream::channel(
+            sid,
+            Arc::downgrade(&self.plugin.ctx),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        context::insert_pending(&self.plugin.host_ctx, sid, types::Pending::Stream(tx));
+
+        let span = self.trace_start("call_long_poll", sid, entry, payload);
+        let status = span.in_scope(|| self.plugin.handle(entry, sid, payload));
+
+        if !status.is_success() {
+            context::remove_pending(&self.plugin.host_ctx, sid);
+            self.plugin.ctx.metrics.record_error();
+            self.trace_end(&span, sid, status, 0);
+            return Err(self.handle_failed(sid, status));
+        }
+
+        let mut deadline = Instant::now() + options.max_wait;
+        let outcome = loop {
+            let frame = match rt::timeout_at(deadline, rx.recv()).await {
+                Some(Some(frame)) => frame,
+                Some(None) => {
+                    context::remove_pending(&self.plugin.host_ctx, sid);
+                    return Err(self.closed());
+                }
+                None => break LongPollOutcome::Empty,
+            };
+            match (frame.status, frame.data.is_empty()) {
+                (NrStatus::Ok, true) => {
+                    if options.renew_via_progress {
+                        deadline = Instant::now() + options.max_wait;
+                    }
+                }
+                (NrStatus::StreamEnd, true) => break LongPollOutcome::Empty,
+                _ => break LongPollOutcome::Fulfilled(frame.status, frame.data),
+            }
+        };
+
+        // A late answer after the deadline is dropped.
+        context::remove_pending(&self.plugin.host_ctx, sid);
+
+        let (status, bytes) = match &outcome {
+            LongPollOutcome::Fulfilled(status, data) => (*status, data.len()),
+            LongPollOutcome::Empty => (NrStatus::StreamEnd, 0),
+        };
+        self.plugin
+            .ctx
+            .metrics
+            .record_long_poll(matches!(outcome, LongPollOutcome::Fulfilled(..)));
+        self.trace_end(&span, sid, status, bytes);
+        Ok(outcome)
+    }
+}
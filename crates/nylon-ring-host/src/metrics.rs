@@ -0,0 +1,410 @@
+//! Per-plugin and host-wide call metrics.
+//!
+//! Counters are plain atomics updated with `Relaxed` ordering on the call
+//! paths; latencies go into a log2 histogram so recording never allocates or
+//! locks. [`MetricsSnapshot`] and [`HostMetricsSnapshot`] are owned copies
+//! suitable for exporting. With the `metrics-export` feature,
+//! [`render_prometheus`] renders a whole host in the OpenMetrics text format.
+
+use crate::context::PluginContext;
+use crate::stream::StreamLag;
+use dashmap::DashMap;
+use nylon_ring::NrStatus;
+use rustc_hash::FxBuildHasher;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics-export")]
+mod prometheus;
+#[cfg(feature = "metrics-export")]
+pub use prometheus::render_prometheus;
+
+/// Number of latency buckets. Bucket `i` holds samples in `[2^i, 2^(i+1))` ns.
+const LATENCY_BUCKETS: usize = 64;
+
+/// Statuses tracked in `responses_by_status`, indexed by their ABI value.
+const STATUSES: [NrStatus; 5] = [
+    NrStatus::Ok,
+    NrStatus::Err,
+    NrStatus::Invalid,
+    NrStatus::Unsupported,
+    NrStatus::StreamEnd,
+];
+
+/// Live metrics for one plugin.
+pub(crate) struct Metrics {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicU64,
+    calls_by_entry: DashMap<Box<str>, AtomicU64, FxBuildHasher>,
+    responses_by_status: [AtomicU64; STATUSES.len()],
+    latency: [AtomicU64; LATENCY_BUCKETS],
+    /// Sum of the recorded latencies, in nanoseconds.
+    latency_sum: AtomicU64,
+    max_stream_lag_frames: AtomicU64,
+    max_stream_lag_nanos: AtomicU64,
+    long_poll_fulfilled: AtomicU64,
+    long_poll_empty: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            calls_by_entry: DashMap::with_hasher(FxBuildHasher),
+            responses_by_status: std::array::from_fn(|_| AtomicU64::new(0)),
+            latency: std::array::from_fn(|_| AtomicU64::new(0)),
+            latency_sum: AtomicU64::new(0),
+            max_stream_lag_frames: AtomicU64::new(0),
+            max_stream_lag_nanos: AtomicU64::new(0),
+            long_poll_fulfilled: AtomicU64::new(0),
+            long_poll_empty: AtomicU64::new(0),
+        }
+    }
+
+    /// Record the start of a call. The returned guard tracks it as in flight
+    /// until dropped.
+    #[inline]
+    pub(crate) fn start_call(&self, entry: &str) -> CallGuard<'_> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match self.calls_by_entry.get(entry) {
+            Some(count) => {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+            None => {
+                self.calls_by_entry
+                    .entry(entry.into())
+                    .or_insert_with(|| AtomicU64::new(0))
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        CallGuard {
+            metrics: self,
+            started: Instant::now(),
+        }
+    }
+
+    /// Record a call that the plugin rejected immediately.
+    #[inline]
+    pub(crate) fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a result frame delivered by the plugin.
+    #[inline]
+    pub(crate) fn record_response(&self, status: NrStatus) {
+        if let Some(count) = self.responses_by_status.get(status as usize) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Fold a stream's current consumer lag into the high-water marks.
+    #[inline]
+    pub(crate) fn record_stream_lag(&self, lag: StreamLag) {
+        self.max_stream_lag_frames
+            .fetch_max(lag.frames as u64, Ordering::Relaxed);
+        self.max_stream_lag_nanos
+            .fetch_max(lag.oldest.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Record how a long-poll call ended.
+    #[inline]
+    pub(crate) fn record_long_poll(&self, fulfilled: bool) {
+        let counter = if fulfilled {
+            &self.long_poll_fulfilled
+        } else {
+            &self.long_poll_empty
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let nanos = (elapsed.as_nanos() as u64).max(1);
+        let bucket = (63 - nanos.leading_zeros()) as usize;
+        self.latency[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// The latency histogram, bucket `i` holding samples in
+    /// `[2^i, 2^(i+1))` ns, and the sum of the samples in nanoseconds.
+    #[cfg(feature = "metrics-export")]
+    pub(crate) fn latency(&self) -> ([u64; LATENCY_BUCKETS], u64) {
+        (
+            std::array::from_fn(|i| self.latency[i].load(Ordering::Relaxed)),
+            self.latency_sum.load(Ordering::Relaxed),
+        )
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        let buckets: Vec<u64> = self
+            .latency
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect();
+        let samples = buckets.iter().sum();
+
+        MetricsSnapshot {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            calls_by_entry: self
+                .calls_by_entry
+                .iter()
+                .map(|e| (e.key().to_string(), e.value().load(Ordering::Relaxed)))
+                .collect(),
+            responses_by_status: STATUSES
+                .iter()
+                .zip(&self.responses_by_status)
+                .map(|(status, count)| (*status, count.load(Ordering::Relaxed)))
+                .collect(),
+            latency_samples: samples,
+            latency_p50: quantile(&buckets, samples, 0.50),
+            latency_p99: quantile(&buckets, samples, 0.99),
+            max_stream_lag: StreamLag {
+                frames: self.max_stream_lag_frames.load(Ordering::Relaxed) as usize,
+                oldest: Duration::from_nanos(self.max_stream_lag_nanos.load(Ordering::Relaxed)),
+            },
+            long_poll_fulfilled: self.long_poll_fulfilled.load(Ordering::Relaxed),
+            long_poll_empty: self.long_poll_empty.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Upper bound of the bucket containing quantile `q`.
+fn quantile(buckets: &[u64], samples: u64, q: f64) -> Duration {
+    if samples == 0 {
+        return Duration::ZERO;
+    }
+    let rank = ((samples as f64) * q).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (i, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Duration::from_nanos(1u64.checked_shl(i as u32 + 1).unwrap_or(u64::MAX));
+        }
+    }
+    Duration::from_nanos(u64::MAX)
+}
+
+/// Tracks one call as in flight and records its latency when finished.
+pub(crate) struct CallGuard<'a> {
+    metrics: &'a Metrics,
+    started: Instant,
+}
+
+impl CallGuard<'_> {
+    /// Record the call latency. Calls dropped without finishing (for example
+    /// a cancelled future) only leave the in-flight count.
+    #[inline]
+    pub(crate) fn finish(self) {
+        self.metrics.record_latency(self.started.elapsed());
+    }
+
+    /// Hand the call over to whatever outlives the caller, such as a
+    /// stream, which finishes it with its last frame.
+    pub(crate) fn detach(self, ctx: &Arc<PluginContext>) -> DetachedCall {
+        let started = self.started;
+        std::mem::forget(self);
+        DetachedCall {
+            ctx: ctx.clone(),
+            started,
+        }
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A [`CallGuard`] that owns its plugin context.
+pub(crate) struct DetachedCall {
+    ctx: Arc<PluginContext>,
+    started: Instant,
+}
+
+impl DetachedCall {
+    pub(crate) fn finish(self) {
+        self.ctx.metrics.record_latency(self.started.elapsed());
+    }
+}
+
+impl Drop for DetachedCall {
+    fn drop(&mut self) {
+        self.ctx.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// An owned copy of a plugin's metrics.
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    /// Total calls issued to the plugin.
+    pub calls: u64,
+    /// Calls the plugin's `handle` rejected, and calls it never answered.
+    pub errors: u64,
+    /// Calls currently waiting for the plugin.
+    pub in_flight: u64,
+    /// Total calls per entry name.
+    pub calls_by_entry: HashMap<String, u64>,
+    /// Result frames delivered by the plugin, per status.
+    pub responses_by_status: Vec<(NrStatus, u64)>,
+    /// Number of completed calls with a recorded latency.
+    pub latency_samples: u64,
+    /// Median call latency (bucket upper bound).
+    pub latency_p50: Duration,
+    /// 99th percentile call latency (bucket upper bound).
+    pub latency_p99: Duration,
+    /// Largest consumer lag seen on any of the plugin's lag-tracked streams.
+    /// Frames and age are tracked independently.
+    pub max_stream_lag: StreamLag,
+    /// Long-poll calls answered with a result.
+    pub long_poll_fulfilled: u64,
+    /// Long-poll calls that ended without one.
+    pub long_poll_empty: u64,
+}
+
+impl MetricsSnapshot {
+    /// Fraction of calls rejected by the plugin.
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+
+    /// Result frames delivered with `status`.
+    pub fn responses(&self, status: NrStatus) -> u64 {
+        self.responses_by_status
+            .iter()
+            .find(|(s, _)| *s == status)
+            .map_or(0, |(_, count)| *count)
+    }
+}
+
+/// Live counters for all plugins of one host.
+pub(crate) struct HostMetrics {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    active_streams: AtomicU64,
+    dispatch_cache_hits: AtomicU64,
+    strict_violations: AtomicU64,
+}
+
+impl HostMetrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            active_streams: AtomicU64::new(0),
+            dispatch_cache_hits: AtomicU64::new(0),
+            strict_violations: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a call handing `bytes` of payload to a plugin.
+    #[inline]
+    pub(crate) fn record_call(&self, bytes: usize) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record a call that was rejected, or whose response never came.
+    #[inline]
+    pub(crate) fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a result frame of `bytes` from a plugin.
+    #[inline]
+    pub(crate) fn record_received(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn stream_opened(&self) {
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn stream_closed(&self) {
+        self.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_dispatch_cache_hit(&self) {
+        self.dispatch_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_strict_violation(&self) {
+        self.strict_violations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HostMetricsSnapshot {
+        HostMetricsSnapshot {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            dispatch_cache_hits: self.dispatch_cache_hits.load(Ordering::Relaxed),
+            strict_violations: self.strict_violations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// An owned copy of a host's metrics, summed over its plugins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostMetricsSnapshot {
+    /// Total calls issued to plugins, including dispatched ones.
+    pub calls: u64,
+    /// Calls rejected with a non-`Ok` status, plus calls whose response
+    /// channel closed without an answer.
+    pub errors: u64,
+    /// Payload bytes handed to plugins.
+    pub bytes_sent: u64,
+    /// Payload bytes plugins sent back, over all result frames.
+    pub bytes_received: u64,
+    /// Streams registered and not yet finished or cancelled. Long polls
+    /// in progress count as streams.
+    pub active_streams: u64,
+    /// Dispatched calls answered from a dispatch cache. These never reach
+    /// the target and are not counted in `calls`.
+    pub dispatch_cache_hits: u64,
+    /// Plugin mistakes caught under strict mode. Always zero otherwise.
+    pub strict_violations: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_quantiles() {
+        let metrics = Metrics::new();
+        for _ in 0..98 {
+            metrics.record_latency(Duration::from_nanos(100));
+        }
+        metrics.record_latency(Duration::from_micros(50));
+        metrics.record_latency(Duration::from_micros(50));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.latency_samples, 100);
+        // 100ns lands in [64, 128)
+        assert_eq!(snapshot.latency_p50, Duration::from_nanos(128));
+        // 50us lands in [32768, 65536)
+        assert_eq!(snapshot.latency_p99, Duration::from_nanos(65536));
+    }
+}
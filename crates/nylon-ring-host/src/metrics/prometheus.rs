@@ -0,0 +1,265 @@
+//! OpenMetrics text exposition of a host's metrics.
+//!
+//! The families below are part of the API: their names, types and labels
+//! only change with a breaking release.
+//!
+//! | Family | Type | Labels |
+//! |---|---|---|
+//! | `nylon_ring_plugins_loaded` | gauge | |
+//! | `nylon_ring_calls` | counter | |
+//! | `nylon_ring_errors` | counter | |
+//! | `nylon_ring_sent_bytes` | counter | |
+//! | `nylon_ring_received_bytes` | counter | |
+//! | `nylon_ring_active_streams` | gauge | |
+//! | `nylon_ring_dispatch_cache_hits` | counter | |
+//! | `nylon_ring_strict_violations` | counter | |
+//! | `nylon_ring_pending` | gauge | `shard`, `kind` (`unary` or `stream`) |
+//! | `nylon_ring_plugin_calls` | counter | `plugin` |
+//! | `nylon_ring_plugin_errors` | counter | `plugin` |
+//! | `nylon_ring_plugin_in_flight` | gauge | `plugin` |
+//! | `nylon_ring_plugin_responses` | counter | `plugin`, `status` |
+//! | `nylon_ring_entry_calls` | counter | `plugin`, `entry` |
+//! | `nylon_ring_plugin_call_latency_seconds` | histogram | `plugin` |
+//!
+//! `status` is the `NrStatus` variant name, such as `Ok`. Latency buckets
+//! are powers of two nanoseconds, from 2^10 (about 1µs) to 2^34 (about 17s)
+//! in steps of four. Plugins and entries are listed in name order.
+
+use crate::{diagnostics, NylonRingHost};
+use std::fmt::Write;
+
+/// Exponents of the latency bucket bounds, in nanoseconds.
+const LATENCY_BOUNDS: [u32; 13] = [10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 32, 34];
+
+/// Render the metrics of `host` and of each of its plugins in the
+/// OpenMetrics text format, ending with `# EOF`. Walks the pending map, as
+/// [`NylonRingHost::diagnostics`] does.
+pub fn render_prometheus(host: &NylonRingHost) -> String {
+    let mut names: Vec<&String> = host.plugins.keys().collect();
+    names.sort();
+    let plugins: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let metrics = &host.plugins[name].ctx.metrics;
+            (name.as_str(), metrics.snapshot(), metrics.latency())
+        })
+        .collect();
+    let totals = host.host_ctx.metrics.snapshot();
+    let pending = diagnostics::collect(&host.host_ctx);
+
+    let mut out = Exposition::default();
+    out.family("nylon_ring_plugins_loaded", "gauge", "Plugins loaded.");
+    out.sample("nylon_ring_plugins_loaded", &[], plugins.len());
+    for (name, help, value) in [
+        ("calls", "Calls made to any plugin.", totals.calls),
+        ("errors", "Calls any plugin failed.", totals.errors),
+        (
+            "sent_bytes",
+            "Payload bytes sent to plugins.",
+            totals.bytes_sent,
+        ),
+        (
+            "received_bytes",
+            "Payload bytes received from plugins.",
+            totals.bytes_received,
+        ),
+    ] {
+        out.counter(name, help, value);
+    }
+    out.family(
+        "nylon_ring_active_streams",
+        "gauge",
+        "Streams opened and not yet ended.",
+    );
+    out.sample("nylon_ring_active_streams", &[], totals.active_streams);
+    out.counter(
+        "dispatch_cache_hits",
+        "Dispatched calls answered from the dispatch cache.",
+        totals.dispatch_cache_hits,
+    );
+    out.counter(
+        "strict_violations",
+        "Callbacks refused in strict mode.",
+        totals.strict_violations,
+    );
+
+    out.family(
+        "nylon_ring_pending",
+        "gauge",
+        "Requests waiting on a result, per shard of the pending map.",
+    );
+    for (shard, counts) in pending.shards.iter().enumerate() {
+        let shard = shard.to_string();
+        for (kind, count) in [("unary", counts.unary), ("stream", counts.streams)] {
+            out.sample(
+                "nylon_ring_pending",
+                &[("shard", &shard), ("kind", kind)],
+                count,
+            );
+        }
+    }
+
+    out.family(
+        "nylon_ring_plugin_calls",
+        "counter",
+        "Calls made to the plugin.",
+    );
+    for (plugin, snapshot, _) in &plugins {
+        out.sample(
+            "nylon_ring_plugin_calls_total",
+            &[("plugin", plugin)],
+            snapshot.calls,
+        );
+    }
+    out.family(
+        "nylon_ring_plugin_errors",
+        "counter",
+        "Calls the plugin failed.",
+    );
+    for (plugin, snapshot, _) in &plugins {
+        out.sample(
+            "nylon_ring_plugin_errors_total",
+            &[("plugin", plugin)],
+            snapshot.errors,
+        );
+    }
+    out.family(
+        "nylon_ring_plugin_in_flight",
+        "gauge",
+        "Calls to the plugin not yet finished.",
+    );
+    for (plugin, snapshot, _) in &plugins {
+        out.sample(
+            "nylon_ring_plugin_in_flight",
+            &[("plugin", plugin)],
+            snapshot.in_flight,
+        );
+    }
+    out.family(
+        "nylon_ring_plugin_responses",
+        "counter",
+        "Results the plugin sent, by status.",
+    );
+    for (plugin, snapshot, _) in &plugins {
+        for (status, count) in &snapshot.responses_by_status {
+            let status = format!("{status:?}");
+            out.sample(
+                "nylon_ring_plugin_responses_total",
+                &[("plugin", plugin), ("status", &status)],
+                count,
+            );
+        }
+    }
+    out.family(
+        "nylon_ring_entry_calls",
+        "counter",
+        "Calls made to the entry.",
+    );
+    for (plugin, snapshot, _) in &plugins {
+        let mut entries: Vec<_> = snapshot.calls_by_entry.iter().collect();
+        entries.sort();
+        for (entry, count) in entries {
+            out.sample(
+                "nylon_ring_entry_calls_total",
+                &[("plugin", plugin), ("entry", entry)],
+                count,
+            );
+        }
+    }
+
+    let latency = "nylon_ring_plugin_call_latency_seconds";
+    out.family(latency, "histogram", "Latency of finished calls.");
+    for (plugin, _, (buckets, sum)) in &plugins {
+        for exponent in LATENCY_BOUNDS {
+            // Bucket `i` ends at 2^(i + 1) ns, so those below `exponent`
+            // hold only samples up to 2^exponent ns.
+            let below: u64 = buckets[..exponent as usize].iter().sum();
+            let le = ((1u64 << exponent) as f64 / 1e9).to_string();
+            out.sample(
+                &format!("{latency}_bucket"),
+                &[("plugin", plugin), ("le", &le)],
+                below,
+            );
+        }
+        let count: u64 = buckets.iter().sum();
+        out.sample(
+            &format!("{latency}_bucket"),
+            &[("plugin", plugin), ("le", "+Inf")],
+            count,
+        );
+        out.sample(&format!("{latency}_count"), &[("plugin", plugin)], count);
+        out.sample(
+            &format!("{latency}_sum"),
+            &[("plugin", plugin)],
+            *sum as f64 / 1e9,
+        );
+    }
+
+    out.text.push_str("# EOF\n");
+    out.text
+}
+
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+    }
+
+    /// A host-wide counter, `nylon_ring_{name}_total`.
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        let family = format!("nylon_ring_{name}");
+        self.family(&family, "counter", help);
+        self.sample(&format!("{family}_total"), &[], value);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            self.text.push('{');
+            for (i, (label, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.text.push(',');
+                }
+                let _ = write!(self.text, "{label}=\"{}\"", escape(value));
+            }
+            self.text.push('}');
+        }
+        let _ = writeln!(self.text, " {value}");
+    }
+}
+
+/// Escape a label value: backslashes, quotes and line feeds as the format
+/// requires, and any other control character as U+FFFD, so arbitrary entry
+/// names cannot break a line.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape("plain/entry"), "plain/entry");
+        assert_eq!(
+            escape("a\"b\\c\nd\re\u{0}"),
+            "a\\\"b\\\\c\\nd\u{fffd}e\u{fffd}"
+        );
+    }
+}
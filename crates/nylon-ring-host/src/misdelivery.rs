@@ -0,0 +1,142 @@
+//! Results sent under the wrong SID.
+//!
+//! A plugin that answers call A with the SID of call B hands B's caller
+//! A's data, and nothing in the SID tells the two apart. Plugins that
+//! declare [`ECHOES_NONCE_KEY`] get a random nonce per unary call, stored
+//! under [`CALL_NONCE_KEY`] before `handle`, and prefix the result with
+//! it; a result whose prefix is not the nonce of the call it names is
+//! dropped and reported as [`StrictViolation::NonceMismatch`], strict mode
+//! or not. For other plugins the host can only notice a result for a
+//! waiting unary call sent while the same plugin is handling a different
+//! SID on the same thread, which it logs, and reports as
+//! [`StrictViolation::ForeignSid`] under strict mode. Such a result is
+//! still delivered.
+
+use crate::context::{HostContext, PluginContext};
+use crate::sid::{sid_mode, SidMode};
+use crate::strict::{self, StrictViolation};
+use nylon_ring::{CALL_NONCE_KEY, ECHOES_NONCE_KEY};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::marker::PhantomData;
+use std::ptr;
+
+#[derive(Clone, Copy)]
+struct Handling {
+    plugin: *const PluginContext,
+    sid: u64,
+}
+
+thread_local! {
+    /// The innermost `handle` running on this thread.
+    static HANDLING: Cell<Handling> = const {
+        Cell::new(Handling {
+            plugin: ptr::null(),
+            sid: 0,
+        })
+    };
+}
+
+/// Marks this thread as running `handle` for a SID until dropped.
+pub(crate) struct HandlingGuard {
+    previous: Handling,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl HandlingGuard {
+    pub(crate) fn enter(plugin: &PluginContext, sid: u64) -> Self {
+        let previous = HANDLING.replace(Handling {
+            plugin: plugin as *const _,
+            sid,
+        });
+        Self {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for HandlingGuard {
+    fn drop(&mut self) {
+        HANDLING.set(self.previous);
+    }
+}
+
+/// Whether results for `sid` are the single answer of a call.
+fn is_call(sid: u64) -> bool {
+    matches!(sid_mode(sid), Some(SidMode::Unary | SidMode::Fast))
+}
+
+/// Whether the state a plugin published during `init` declares that it
+/// echoes nonces.
+pub(crate) fn declares_echo(init_state: &mut HashMap<String, Vec<u8>>) -> bool {
+    init_state.remove(ECHOES_NONCE_KEY).is_some()
+}
+
+/// Store a fresh nonce for the call `sid`, before `handle`.
+pub(crate) fn seed_nonce(host: &HostContext, sid: u64) {
+    if !is_call(sid) {
+        return;
+    }
+    let nonce = RandomState::new().hash_one(sid).to_le_bytes();
+    host.state_per_sid
+        .entry(sid)
+        .or_default()
+        .insert(CALL_NONCE_KEY.to_string(), nonce.to_vec());
+}
+
+/// Check a result `plugin` sent for `sid` before it is delivered, and strip
+/// the nonce off `data`. Returns `false` if the result must be dropped.
+pub(crate) fn check(plugin: &PluginContext, sid: u64, data: &mut Vec<u8>) -> bool {
+    if !plugin.echoes_nonce() || !is_call(sid) {
+        return true;
+    }
+
+    let host = &plugin.host;
+    let Some(mut state) = host.state_per_sid.get_mut(&sid) else {
+        return true;
+    };
+    // No nonce: the call was answered already, or never made.
+    let Some(nonce) = state.get(CALL_NONCE_KEY) else {
+        return true;
+    };
+    if !data.starts_with(nonce) {
+        drop(state);
+        strict::report(plugin, sid, StrictViolation::NonceMismatch);
+        return false;
+    }
+    data.drain(..nonce.len());
+    state.remove(CALL_NONCE_KEY);
+    if state.is_empty() {
+        drop(state);
+        host.state_per_sid
+            .remove_if(&sid, |_, state| state.is_empty());
+    }
+    true
+}
+
+/// Note a result `plugin` sent for the unary call `sid`, which someone
+/// waits on, if it comes while the plugin handles another SID on this
+/// thread. Plugins that echo nonces are checked by [`check`] instead.
+pub(crate) fn note_foreign(plugin: &PluginContext, sid: u64) {
+    if plugin.echoes_nonce() {
+        return;
+    }
+    let handling = HANDLING.get();
+    if ptr::eq(handling.plugin, plugin) && handling.sid != sid {
+        foreign(plugin, sid, handling.sid);
+    }
+}
+
+#[cold]
+fn foreign(plugin: &PluginContext, sid: u64, handling: u64) {
+    if plugin.host.config.strict_mode {
+        strict::report(plugin, sid, StrictViolation::ForeignSid);
+    } else {
+        log::warn!(
+            "plugin {:?} sent the result for sid {sid} while handling sid {handling}",
+            plugin.name
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Multiplexed streams.
+//!
+//! A plugin can tag the frames of a single stream with named sub-channels
+//! through `send_result_channel`. [`MuxStream`] splits such a stream into
+//! one receiver per channel; untagged frames go to the default channel.
+
+use crate::stream::{self, StreamOptions, StreamReceiver, StreamSender};
+use crate::types::{Result, StreamFrame};
+use crate::PluginHandle;
+use nylon_ring::NrStatus;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Weak};
+
+/// A stream split into named sub-channels, as returned by
+/// [`PluginHandle::call_stream_mux`].
+///
+/// Frames are buffered per channel until the channel is subscribed. The
+/// stream's final frame is delivered to every channel, subscribed or not,
+/// which ends them all. Dropping the `MuxStream` ends every receiver
+/// subscribed from it.
+pub struct MuxStream {
+    sid: u64,
+    demux: Arc<Mutex<Demux>>,
+}
+
+#[derive(Default)]
+struct Demux {
+    /// Keyed by channel name; `None` is the default channel.
+    channels: FxHashMap<Option<String>, Channel>,
+    /// The final frame, once the plugin sent it.
+    finished: Option<StreamFrame>,
+}
+
+struct Channel {
+    tx: StreamSender,
+    /// The receiver, until `subscribe` hands it out.
+    unclaimed: Option<StreamReceiver>,
+}
+
+impl Demux {
+    fn route(&mut self, sid: u64, frame: StreamFrame) {
+        if frame.status == NrStatus::Ok {
+            self.channel(sid, frame.channel.clone()).tx.send(frame);
+            return;
+        }
+        for channel in self.channels.values() {
+            channel.tx.send(frame.clone());
+        }
+        self.finished = Some(frame);
+    }
+
+    fn channel(&mut self, sid: u64, name: Option<String>) -> &mut Channel {
+        let finished = &self.finished;
+        self.channels.entry(name).or_insert_with(|| {
+            let (tx, rx) = open(sid, finished);
+            Channel {
+                tx,
+                unclaimed: Some(rx),
+            }
+        })
+    }
+}
+
+/// A channel that has already ended if the stream has.
+fn open(sid: u64, finished: &Option<StreamFrame>) -> (StreamSender, StreamReceiver) {
+    let (tx, rx) = stream::channel(sid, Weak::new(), None, None, None, None, None);
+    if let Some(frame) = finished {
+        tx.send(frame.clone());
+    }
+    (tx, rx)
+}
+
+impl MuxStream {
+    /// The stream's SID, for [`PluginHandle::send_stream_data`] and
+    /// [`PluginHandle::close_stream`].
+    pub fn sid(&self) -> u64 {
+        self.sid
+    }
+
+    /// Receive the frames sent on `channel`, starting with any that were
+    /// buffered. Subscribing to a channel again ends the previous receiver.
+    pub fn subscribe(&self, channel: &str) -> StreamReceiver {
+        self.take(Some(channel.to_string()))
+    }
+
+    /// Receive the frames sent without a channel.
+    pub fn subscribe_default(&self) -> StreamReceiver {
+        self.take(None)
+    }
+
+    fn take(&self, name: Option<String>) -> StreamReceiver {
+        let mut demux = self.demux.lock();
+        let finished = demux.finished.clone();
+        let channel = demux.channel(self.sid, name);
+        if let Some(rx) = channel.unclaimed.take() {
+            return rx;
+        }
+        channel.tx.close();
+        let (tx, rx) = open(self.sid, &finished);
+        channel.tx = tx;
+        rx
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        for channel in self.demux.lock().channels.values() {
+            channel.tx.close();
+        }
+    }
+}
+
+impl std::fmt::Debug for MuxStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MuxStream")
+            .field("sid", &self.sid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginHandle {
+    /// Call a streaming entry whose frames are tagged with sub-channels.
+    ///
+    /// Frames are routed on a background task: a Tokio task with the default
+    /// `tokio-rt` feature, otherwise a thread.
+    pub async fn call_stream_mux(&self, entry: &str, payload: &[u8]) -> Result<MuxStream> {
+        let (sid, mut rx) = self.open_stream(entry, payload, None, StreamOptions::default())?;
+
+        let demux = Arc::new(Mutex::new(Demux::default()));
+        let weak = Arc::downgrade(&demux);
+        self.plugin.host_ctx.config.spawner.spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                // Stop routing once the `MuxStream` is gone.
+                let Some(demux) = weak.upgrade() else {
+                    break;
+                };
+                demux.lock().route(sid, frame);
+            }
+        });
+
+        Ok(MuxStream { sid, demux })
+    }
+}
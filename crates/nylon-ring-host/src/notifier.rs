@@ -0,0 +1,234 @@
+//! Batched fire-and-forget calls.
+//!
+//! A [`Notifier`] buffers events for one entry and delivers them as a
+//! single fire-and-forget call per batch (see [`nylon_ring::batch`] for the
+//! encoding), so high-volume telemetry pays for one FFI crossing per batch
+//! instead of one per event.
+
+use crate::rt::{self, Instant};
+use crate::types::Result;
+use crate::PluginHandle;
+use nylon_ring::batch::{batch_item_len, push_batch_item};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// When a [`Notifier`] flushes, and how much it keeps when it cannot.
+#[derive(Debug, Clone, Copy)]
+pub struct NotifierOptions {
+    /// Flush once this many events are buffered.
+    pub max_events: usize,
+    /// Flush once the encoded batch reaches this many bytes.
+    pub max_bytes: usize,
+    /// Flush events that have waited this long, from a background task.
+    /// `None` leaves flushing to the other triggers.
+    pub max_delay: Option<Duration>,
+    /// Events kept while the plugin rejects batches. Beyond this, the
+    /// oldest events are dropped and counted in [`Notifier::lost`].
+    pub capacity: usize,
+}
+
+impl Default for NotifierOptions {
+    fn default() -> Self {
+        Self {
+            max_events: 256,
+            max_bytes: 64 * 1024,
+            max_delay: Some(Duration::from_millis(10)),
+            capacity: 4096,
+        }
+    }
+}
+
+/// Buffers fire-and-forget events for one entry and sends them in batches.
+///
+/// Created with [`PluginHandle::notifier`]. The entry receives each batch as
+/// its payload and reads the events with [`nylon_ring::batch_items`].
+/// Buffered events are flushed when the notifier is dropped.
+pub struct Notifier {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    plugin: PluginHandle,
+    entry: String,
+    options: NotifierOptions,
+    buffer: Mutex<Buffer>,
+    /// Held for the whole of a flush, so batches go out in order.
+    flushing: Mutex<()>,
+    lost: AtomicU64,
+}
+
+#[derive(Default)]
+struct Buffer {
+    events: VecDeque<Vec<u8>>,
+    /// Encoded size of `events`.
+    bytes: usize,
+    /// When the oldest buffered event arrived.
+    since: Option<Instant>,
+}
+
+impl Buffer {
+    fn push_front(&mut self, event: Vec<u8>) {
+        self.bytes += batch_item_len(event.len());
+        self.events.push_front(event);
+    }
+
+    fn push_back(&mut self, event: Vec<u8>) {
+        self.bytes += batch_item_len(event.len());
+        self.events.push_back(event);
+    }
+
+    /// Drop the oldest events beyond `capacity`. Returns how many.
+    fn truncate_front(&mut self, capacity: usize) -> u64 {
+        let mut dropped = 0;
+        while self.events.len() > capacity {
+            if let Some(event) = self.events.pop_front() {
+                self.bytes -= batch_item_len(event.len());
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+}
+
+impl PluginHandle {
+    /// A [`Notifier`] batching fire-and-forget calls to `entry`.
+    ///
+    /// With `max_delay` set, a background task is started, so this must be
+    /// called from within the runtime, unless the host was given one (see
+    /// [`NylonRingHost::new_with_runtime`](crate::NylonRingHost::new_with_runtime)).
+    pub fn notifier(&self, entry: &str, options: NotifierOptions) -> Notifier {
+        let shared = Arc::new(Shared {
+            plugin: self.clone(),
+            entry: entry.to_string(),
+            options,
+            buffer: Mutex::new(Buffer::default()),
+            flushing: Mutex::new(()),
+            lost: AtomicU64::new(0),
+        });
+        if let Some(max_delay) = options.max_delay {
+            self.plugin
+                .host_ctx
+                .config
+                .spawner
+                .spawn(flush_overdue(Arc::downgrade(&shared), max_delay));
+        }
+        Notifier { shared }
+    }
+}
+
+impl Notifier {
+    /// Buffer `event`, flushing if that reaches `max_events` or
+    /// `max_bytes`.
+    ///
+    /// A failed flush is returned as its error; the events stay buffered
+    /// for the next one.
+    pub fn push(&self, event: &[u8]) -> Result<()> {
+        let options = &self.shared.options;
+        let full = {
+            let mut buffer = self.shared.buffer.lock();
+            buffer.since.get_or_insert_with(Instant::now);
+            buffer.push_back(event.to_vec());
+            let dropped = buffer.truncate_front(options.capacity.max(1));
+            self.shared.lost.fetch_add(dropped, Ordering::Relaxed);
+            buffer.events.len() >= options.max_events || buffer.bytes >= options.max_bytes
+        };
+        if full {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Send everything buffered as one call. Returns the number of events
+    /// sent, 0 if there were none.
+    ///
+    /// If the plugin rejects the batch, its events are put back in front of
+    /// anything buffered since, and the oldest are dropped beyond
+    /// `capacity`.
+    pub fn flush(&self) -> Result<usize> {
+        self.shared.flush()
+    }
+
+    /// Events buffered and not yet sent.
+    pub fn buffered(&self) -> usize {
+        self.shared.buffer.lock().events.len()
+    }
+
+    /// Events dropped because the buffer was over `capacity`.
+    pub fn lost(&self) -> u64 {
+        self.shared.lost.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        if let Err(e) = self.shared.flush() {
+            log::warn!(
+                "notifier for {:?} lost {} events on drop: {e}",
+                self.shared.entry,
+                self.buffered()
+            );
+        }
+    }
+}
+
+impl Shared {
+    fn flush(&self) -> Result<usize> {
+        let _flushing = self.flushing.lock();
+        let Buffer { events, bytes, .. } = std::mem::take(&mut *self.buffer.lock());
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let mut batch = Vec::with_capacity(bytes);
+        for event in &events {
+            push_batch_item(&mut batch, event);
+        }
+        match self.plugin.call_now(&self.entry, &batch) {
+            Ok(_) => Ok(events.len()),
+            Err(e) => {
+                let mut buffer = self.buffer.lock();
+                for event in events.into_iter().rev() {
+                    buffer.push_front(event);
+                }
+                buffer.since = Some(Instant::now());
+                let dropped = buffer.truncate_front(self.options.capacity.max(1));
+                self.lost.fetch_add(dropped, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    /// When the oldest buffered event is due.
+    fn due(&self, max_delay: Duration) -> Option<Instant> {
+        self.buffer.lock().since.map(|since| since + max_delay)
+    }
+}
+
+/// Flush events older than `max_delay` until the notifier is dropped.
+async fn flush_overdue(shared: Weak<Shared>, max_delay: Duration) {
+    loop {
+        let wait = match shared.upgrade() {
+            Some(shared) => match shared.due(max_delay) {
+                Some(due) => due.saturating_duration_since(Instant::now()),
+                None => max_delay,
+            },
+            None => return,
+        };
+        rt::sleep(wait).await;
+
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        if shared
+            .due(max_delay)
+            .is_some_and(|due| due <= Instant::now())
+        {
+            if let Err(e) = shared.flush() {
+                log::debug!("timed flush of {:?} failed: {e}", shared.entry);
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Load a plugin, make a single call, and unload it again.
+//!
+//! Meant for build tooling and scripts that invoke one entry and exit. The
+//! host and plugin are torn down before returning, on error paths too.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), nylon_ring_host::NylonRingHostError> {
+//! use nylon_ring_host::oneshot::{self, OneshotOptions};
+//!
+//! let (status, output) =
+//!     oneshot::call("libs/plugin.so", "render", b"{}", OneshotOptions::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::NylonRingHostError;
+use crate::types::{Result, StreamFrame};
+use crate::{NylonRingHost, PluginHandle, PluginSpec};
+use nylon_ring::NrStatus;
+use std::future::Future;
+use std::time::Duration;
+
+/// Options for a one-shot call.
+#[derive(Debug, Clone, Copy)]
+pub struct OneshotOptions {
+    /// Upper bound for the call, or for collecting the whole stream.
+    /// `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for OneshotOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// Load the plugin at `path`, call `entry` as
+/// [`PluginHandle::call_response`](crate::PluginHandle::call_response), and
+/// unload it.
+pub async fn call(
+    path: &str,
+    entry: &str,
+    payload: &[u8],
+    options: OneshotOptions,
+) -> Result<(NrStatus, Vec<u8>)> {
+    call_spec(&PluginSpec::new("oneshot", path), entry, payload, options).await
+}
+
+/// Like [`call`], for any plugin source.
+pub async fn call_spec(
+    spec: &PluginSpec,
+    entry: &str,
+    payload: &[u8],
+    options: OneshotOptions,
+) -> Result<(NrStatus, Vec<u8>)> {
+    with_plugin(spec, options, |plugin| async move {
+        plugin.call_response(entry, payload).await
+    })
+    .await
+}
+
+/// Load the plugin at `path`, call `entry` as
+/// [`PluginHandle::call_stream`](crate::PluginHandle::call_stream), collect
+/// every frame up to and including the final one, and unload it.
+pub async fn call_stream(
+    path: &str,
+    entry: &str,
+    payload: &[u8],
+    options: OneshotOptions,
+) -> Result<Vec<StreamFrame>> {
+    call_stream_spec(&PluginSpec::new("oneshot", path), entry, payload, options).await
+}
+
+/// Like [`call_stream`], for any plugin source.
+pub async fn call_stream_spec(
+    spec: &PluginSpec,
+    entry: &str,
+    payload: &[u8],
+    options: OneshotOptions,
+) -> Result<Vec<StreamFrame>> {
+    with_plugin(spec, options, |plugin| async move {
+        let (_sid, mut rx) = plugin.call_stream(entry, payload).await?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = rx.recv().await {
+            let last = frame.status != NrStatus::Ok;
+            frames.push(frame);
+            if last {
+                break;
+            }
+        }
+        Ok(frames)
+    })
+    .await
+}
+
+/// Run `f` against a host with only `spec` loaded, then tear the host down.
+async fn with_plugin<F, Fut, T>(spec: &PluginSpec, options: OneshotOptions, f: F) -> Result<T>
+where
+    F: FnOnce(PluginHandle) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut host = NylonRingHost::new();
+    host.load_source(&spec.name, spec.source.clone())?;
+    let plugin = host
+        .plugin(&spec.name)
+        .ok_or_else(|| NylonRingHostError::PluginNotFound(spec.name.clone()))?;
+
+    // The handle is consumed by the call, so the plugin is unloaded below
+    // even if the call timed out.
+    let call = f(plugin);
+    let result = match options.timeout {
+        Some(timeout) => crate::rt::timeout(timeout, call)
+            .await
+            .unwrap_or(Err(NylonRingHostError::Timeout(timeout))),
+        None => call.await,
+    };
+
+    host.unload(&spec.name)?;
+    result
+}
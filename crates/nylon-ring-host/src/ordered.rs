@@ -0,0 +1,230 @@
+//! Streams put back in order by sequence number.
+//!
+//! A plugin answering one stream from several threads can number its frames
+//! with `send_result_seq`, from 1. [`PluginHandle::call_stream_ordered`]
+//! holds frames that arrive early and releases them in order. Frames sent
+//! without a number pass straight through.
+//!
+//! A missing frame is waited for up to [`OrderOptions::gap_timeout`]; then
+//! the stream ends with an `Err` frame whose data starts with
+//! [`STREAM_GAP`] and names the missing numbers. The frames held behind the
+//! gap are dropped. At most [`OrderOptions::max_buffered`] frames are held:
+//! one more ends the stream the same way at once, without waiting out the
+//! timeout. A final frame without a number also ends the stream with the
+//! gap error if frames are still held.
+//!
+//! The host stops listening to a stream once its final frame arrives, so a
+//! plugin sends that frame after all the others.
+
+use crate::rt;
+use crate::stream::{self, StreamOptions, StreamReceiver, StreamSender};
+use crate::types::{Result, StreamFrame};
+use crate::PluginHandle;
+use nylon_ring::NrStatus;
+use std::collections::BTreeMap;
+use std::sync::Weak;
+use std::time::Duration;
+
+/// The start of the data of the `Err` frame an ordered stream ends with
+/// when a frame is missing.
+pub const STREAM_GAP: &[u8] = b"stream gap";
+
+/// How long [`PluginHandle::call_stream_ordered`] waits for a missing frame,
+/// and how many frames it holds meanwhile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderOptions {
+    pub gap_timeout: Duration,
+    pub max_buffered: usize,
+}
+
+impl Default for OrderOptions {
+    fn default() -> Self {
+        Self {
+            gap_timeout: Duration::from_millis(500),
+            max_buffered: 256,
+        }
+    }
+}
+
+/// The frames of one stream that arrived ahead of their turn.
+struct Reorder {
+    next: u64,
+    held: BTreeMap<u64, StreamFrame>,
+    max_buffered: usize,
+}
+
+impl Reorder {
+    fn new(max_buffered: usize) -> Self {
+        Self {
+            next: 1,
+            held: BTreeMap::new(),
+            max_buffered,
+        }
+    }
+
+    /// Take in `frame`, returning the frames now due, in order, or the gap
+    /// error the stream ends with.
+    fn push(&mut self, frame: StreamFrame) -> std::result::Result<Vec<StreamFrame>, StreamFrame> {
+        let Some(seq) = frame.seq else {
+            if frame.status != NrStatus::Ok && !self.held.is_empty() {
+                return Err(self.gap());
+            }
+            return Ok(vec![frame]);
+        };
+        if seq < self.next || self.held.contains_key(&seq) {
+            // Sent twice.
+            return Ok(Vec::new());
+        }
+        if seq > self.next {
+            if self.held.len() >= self.max_buffered {
+                return Err(self.gap());
+            }
+            self.held.insert(seq, frame);
+            return Ok(Vec::new());
+        }
+        let mut due = vec![frame];
+        self.next += 1;
+        while let Some(frame) = self.held.remove(&self.next) {
+            due.push(frame);
+            self.next += 1;
+        }
+        Ok(due)
+    }
+
+    /// Whether a frame is missing.
+    fn waiting(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    fn gap(&self) -> StreamFrame {
+        let last = self.held.keys().next().map_or(self.next, |first| first - 1);
+        let missing = if last == self.next {
+            format!("frame {} missing", self.next)
+        } else {
+            format!("frames {} to {last} missing", self.next)
+        };
+        let mut data = STREAM_GAP.to_vec();
+        data.extend_from_slice(format!(": {missing}").as_bytes());
+        StreamFrame {
+            status: NrStatus::Err,
+            data,
+            channel: None,
+            seq: None,
+        }
+    }
+}
+
+impl PluginHandle {
+    /// Call a streaming entry whose frames are numbered with
+    /// `send_result_seq`, receiving them in order. See [`OrderOptions`] for
+    /// what happens when a frame goes missing.
+    ///
+    /// Frames are reordered on a background task: a Tokio task with the
+    /// default `tokio-rt` feature, otherwise a thread.
+    pub async fn call_stream_ordered(
+        &self,
+        entry: &str,
+        payload: &[u8],
+        options: OrderOptions,
+    ) -> Result<(u64, StreamReceiver)> {
+        let (sid, rx) = self.open_stream(entry, payload, None, StreamOptions::default())?;
+        let (tx, ordered) = stream::channel(sid, Weak::new(), None, None, None, None, None);
+        self.plugin
+            .host_ctx
+            .config
+            .spawner
+            .spawn(reorder(rx, tx, options));
+        Ok((sid, ordered))
+    }
+}
+
+/// Forward the frames of `rx` to `tx` in order. Dropping `rx` when done
+/// closes the stream in the plugin if it has not ended.
+async fn reorder(mut rx: StreamReceiver, tx: StreamSender, options: OrderOptions) {
+    let mut order = Reorder::new(options.max_buffered);
+    // When to give up on the frame `order` waits for, and its number.
+    let mut deadline: Option<(rt::Instant, u64)> = None;
+    loop {
+        let frame = match deadline {
+            Some((at, _)) => match rt::timeout_at(at, rx.recv()).await {
+                Some(frame) => frame,
+                None => {
+                    tx.send(order.gap());
+                    return;
+                }
+            },
+            None => rx.recv().await,
+        };
+        let Some(frame) = frame else {
+            tx.close();
+            return;
+        };
+        match order.push(frame) {
+            Ok(due) => {
+                for frame in due {
+                    let last = frame.status != NrStatus::Ok;
+                    if tx.send(frame).is_none() || last {
+                        // The receiver is gone, or the stream is over.
+                        return;
+                    }
+                }
+            }
+            Err(gap) => {
+                tx.send(gap);
+                return;
+            }
+        }
+        deadline = match deadline {
+            _ if !order.waiting() => None,
+            Some((at, next)) if next == order.next => Some((at, next)),
+            _ => Some((rt::Instant::now() + options.gap_timeout, order.next)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(seq: Option<u64>, status: NrStatus) -> StreamFrame {
+        StreamFrame {
+            status,
+            data: seq.map_or_else(Vec::new, |seq| seq.to_string().into_bytes()),
+            channel: None,
+            seq,
+        }
+    }
+
+    fn seqs(frames: Vec<StreamFrame>) -> Vec<u64> {
+        frames.into_iter().map(|frame| frame.seq.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_frames_are_released_in_order() {
+        let mut order = Reorder::new(8);
+        let mut released = Vec::new();
+        for seq in [3, 1, 2, 2, 5, 4] {
+            released.extend(seqs(order.push(frame(Some(seq), NrStatus::Ok)).unwrap()));
+        }
+        assert_eq!(released, [1, 2, 3, 4, 5]);
+        assert!(!order.waiting());
+        // Unnumbered frames are not held.
+        let passed = order.push(frame(None, NrStatus::Ok)).unwrap();
+        assert_eq!(passed.len(), 1);
+    }
+
+    #[test]
+    fn test_full_buffer_ends_with_the_gap() {
+        let mut order = Reorder::new(2);
+        order.push(frame(Some(3), NrStatus::Ok)).unwrap();
+        order.push(frame(Some(4), NrStatus::Ok)).unwrap();
+        let gap = order.push(frame(Some(5), NrStatus::Ok)).unwrap_err();
+        assert_eq!(gap.status, NrStatus::Err);
+        assert_eq!(gap.data, b"stream gap: frames 1 to 2 missing");
+
+        let mut order = Reorder::new(2);
+        order.push(frame(Some(2), NrStatus::Ok)).unwrap();
+        let gap = order.push(frame(None, NrStatus::StreamEnd)).unwrap_err();
+        assert_eq!(gap.data, b"stream gap: frame 1 missing");
+    }
+}
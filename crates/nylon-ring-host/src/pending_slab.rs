@@ -0,0 +1,177 @@
+//! Pending calls in slots indexed by SID, behind the `slab-pending` feature.
+//!
+//! Each shard of the pending map is a fixed array of slots. A SID's slot is
+//! taken from the bits of its key just above the shard bits, so the SIDs a
+//! thread issues in a row land in consecutive slots and a lookup hashes
+//! nothing. The slot keeps the whole key next to the call, which acts as
+//! its generation: a stale SID whose slot was reused finds nothing. A call
+//! whose slot is held by another call in flight goes to the shard's
+//! overflow map instead.
+
+use crate::rt::Instant;
+use crate::stream::StreamSender;
+use crate::types::{Pending, TimedPending};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use rustc_hash::FxBuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Slots in each shard. A power of two.
+const SLOTS_PER_SHARD: usize = 256;
+
+/// A call and the key it was inserted under.
+type Slot = Mutex<Option<(u64, TimedPending)>>;
+
+/// One shard of the pending map.
+pub(crate) struct PendingSlab {
+    slots: Box<[Slot]>,
+    /// Calls whose slot was taken when they were inserted.
+    overflow: DashMap<u64, TimedPending, FxBuildHasher>,
+    /// Entries in `overflow`, so lookups skip it while it is empty.
+    overflowed: AtomicUsize,
+    /// Bits of the key that pick the shard, skipped to pick the slot.
+    shard_bits: u32,
+}
+
+impl PendingSlab {
+    /// A shard of a map with `shard_count` shards, a power of two.
+    pub(crate) fn new(shard_count: usize) -> Self {
+        Self {
+            slots: (0..SLOTS_PER_SHARD).map(|_| Mutex::new(None)).collect(),
+            overflow: DashMap::with_hasher(FxBuildHasher),
+            overflowed: AtomicUsize::new(0),
+            shard_bits: shard_count.trailing_zeros(),
+        }
+    }
+
+    #[inline(always)]
+    fn slot(&self, key: u64) -> &Slot {
+        let index = (key >> self.shard_bits) as usize & (SLOTS_PER_SHARD - 1);
+        // Safety: `index` is masked to the slot count.
+        unsafe { self.slots.get_unchecked(index) }
+    }
+
+    pub(crate) fn insert(&self, key: u64, pending: TimedPending) {
+        let mut slot = self.slot(key).lock();
+        match &*slot {
+            Some((other, _)) if *other != key => {
+                drop(slot);
+                if self.overflow.insert(key, pending).is_none() {
+                    self.overflowed.fetch_add(1, Ordering::AcqRel);
+                }
+            }
+            _ => *slot = Some((key, pending)),
+        }
+    }
+
+    pub(crate) fn remove(&self, key: u64) -> Option<TimedPending> {
+        {
+            let mut slot = self.slot(key).lock();
+            if slot.as_ref().is_some_and(|(held, _)| *held == key) {
+                return slot.take().map(|(_, pending)| pending);
+            }
+        }
+        if self.overflowed.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        let (_, pending) = self.overflow.remove(&key)?;
+        self.overflowed.fetch_sub(1, Ordering::AcqRel);
+        Some(pending)
+    }
+
+    /// The sender of the stream `key`, if it is one.
+    pub(crate) fn stream(&self, key: u64) -> Option<StreamSender> {
+        {
+            let slot = self.slot(key).lock();
+            if let Some((held, (pending, _))) = &*slot {
+                if *held == key {
+                    return match pending {
+                        Pending::Stream(tx) => Some(tx.clone()),
+                        _ => None,
+                    };
+                }
+            }
+        }
+        if self.overflowed.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        match self.overflow.get(&key)?.value() {
+            (Pending::Stream(tx), _) => Some(tx.clone()),
+            _ => None,
+        }
+    }
+
+    /// Visit every request with when it was registered.
+    pub(crate) fn for_each(&self, mut f: impl FnMut(&Pending, Instant)) {
+        for slot in self.slots.iter() {
+            if let Some((_, (pending, since))) = &*slot.lock() {
+                f(pending, *since);
+            }
+        }
+        for entry in self.overflow.iter() {
+            let (pending, since) = entry.value();
+            f(pending, *since);
+        }
+    }
+
+    /// Drop the requests `keep` returns `false` for.
+    pub(crate) fn retain(&self, mut keep: impl FnMut(&Pending, Instant) -> bool) {
+        for slot in self.slots.iter() {
+            let mut slot = slot.lock();
+            if let Some((_, (pending, since))) = &*slot {
+                if !keep(pending, *since) {
+                    *slot = None;
+                }
+            }
+        }
+        self.overflow.retain(|_, (pending, since)| {
+            let kept = keep(pending, *since);
+            if !kept {
+                self.overflowed.fetch_sub(1, Ordering::AcqRel);
+            }
+            kept
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    fn unary() -> TimedPending {
+        (Pending::Unary(oneshot::channel().0), Instant::now())
+    }
+
+    fn is_unary(pending: Option<TimedPending>) -> bool {
+        matches!(pending, Some((Pending::Unary(_), _)))
+    }
+
+    #[test]
+    fn test_keys_sharing_a_slot_overflow() {
+        let slab = PendingSlab::new(4);
+        // Both pick slot 1 of a shard: the bits above the two shard bits
+        // wrap at the slot count.
+        let first = 1 << 2;
+        let second = first + ((SLOTS_PER_SHARD as u64) << 2);
+        slab.insert(first, unary());
+        slab.insert(second, unary());
+        assert_eq!(slab.overflow.len(), 1);
+
+        assert!(is_unary(slab.remove(second)));
+        assert!(slab.remove(second).is_none());
+        assert!(is_unary(slab.remove(first)));
+        assert!(slab.remove(first).is_none());
+    }
+
+    #[test]
+    fn test_stale_keys_find_nothing() {
+        let slab = PendingSlab::new(1);
+        slab.insert(7, unary());
+        let stale = 7 + SLOTS_PER_SHARD as u64;
+        assert!(slab.remove(stale).is_none());
+        assert!(slab.stream(stale).is_none());
+        assert!(slab.stream(7).is_none());
+        assert!(is_unary(slab.remove(7)));
+    }
+}
@@ -0,0 +1,29 @@
+//! Named pools of interchangeable plugins.
+//!
+//! Several instances of one plugin, loaded under names of their own, can be
+//! grouped under a pool name; each call through the pool goes to the next
+//! member in turn. Members unloaded since are skipped.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) struct PluginPool {
+    members: Vec<String>,
+    /// Round-robin position of the next call.
+    next: AtomicUsize,
+}
+
+impl PluginPool {
+    pub(crate) fn new(members: &[&str]) -> Self {
+        Self {
+            members: members.iter().map(|member| member.to_string()).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The members in the order the next call tries them.
+    pub(crate) fn rotation(&self) -> impl Iterator<Item = &str> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let len = self.members.len();
+        (0..len).map(move |i| self.members[(start + i) % len].as_str())
+    }
+}
@@ -0,0 +1,64 @@
+//! Dedicated threads for plugins whose `handle` blocks.
+//!
+//! A plugin with [`ExecutionPolicy::DedicatedPool`] gets a [`BlockingPool`]
+//! of its own. Unary calls hand `handle` to one of its threads and the
+//! caller awaits the response as usual, so file IO or FFI inside the plugin
+//! never stalls an async worker.
+
+use parking_lot::Mutex;
+use std::sync::{mpsc, Arc};
+
+/// Where a plugin's `handle` runs for unary calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionPolicy {
+    /// On the thread that makes the call.
+    #[default]
+    Inline,
+    /// On one of `threads` threads owned by the plugin.
+    DedicatedPool { threads: usize },
+}
+
+/// Threads used by `call_response_blocking` on a plugin without a pool.
+pub(crate) const DEFAULT_POOL_THREADS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads running jobs in submission order.
+///
+/// Dropping the pool lets every thread exit once the queue is drained. The
+/// threads are not joined, since the last job may be what drops the pool.
+pub(crate) struct BlockingPool {
+    jobs: mpsc::Sender<Job>,
+    threads: usize,
+}
+
+impl BlockingPool {
+    /// Start `threads` threads (at least one) named after `plugin`.
+    pub(crate) fn new(plugin: &str, threads: usize) -> std::io::Result<Self> {
+        let threads = threads.max(1);
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..threads {
+            let queue = queue.clone();
+            std::thread::Builder::new()
+                .name(format!("nylon-ring-pool-{plugin}-{i}"))
+                .spawn(move || loop {
+                    let job = queue.lock().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })?;
+        }
+        Ok(Self { jobs, threads })
+    }
+
+    pub(crate) fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Queue `job`. It is dropped unrun if every thread has exited.
+    pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
@@ -0,0 +1,294 @@
+//! Automatic quarantine of plugins that keep failing.
+//!
+//! Under a [`HealthPolicy`], every call's status from `handle` is counted:
+//! `Err`, which is also what `define_plugin!` answers after a panic, adds a
+//! failure and a success clears the count. Plugins with a `health` function
+//! are also asked on a timer, and a failed check counts like a failed call.
+//! Once `failure_threshold` failures follow each other, the plugin is
+//! quarantined: calls fail at once with
+//! [`NylonRingHostError::Quarantined`] until `quarantine_duration` has
+//! passed and a health check succeeds. Plugins without a `health` function
+//! come back when the duration is over.
+
+use crate::error::NylonRingHostError;
+use crate::rt;
+use crate::types::Result;
+use crate::LoadedPlugin;
+use nylon_ring::NrStatus;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Weak;
+use std::time::Duration;
+
+/// When to quarantine a plugin, and for how long. See
+/// [`NylonRingHost::set_health_policy`](crate::NylonRingHost::set_health_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthPolicy {
+    /// How often the plugin's `health` function is called.
+    pub check_interval: Duration,
+    /// Failures in a row that quarantine the plugin.
+    pub failure_threshold: u32,
+    /// How long calls are refused before the plugin may come back.
+    pub quarantine_duration: Duration,
+}
+
+/// Whether a plugin takes calls, as reported by
+/// [`NylonRingHost::health_report`](crate::NylonRingHost::health_report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthState {
+    Healthy,
+    /// Calls are refused for at least `remaining`.
+    Quarantined {
+        remaining: Duration,
+    },
+    /// The quarantine is over, but calls are refused until a health check
+    /// succeeds.
+    AwaitingCheck,
+}
+
+/// One plugin's entry in
+/// [`NylonRingHost::health_report`](crate::NylonRingHost::health_report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginHealth {
+    pub state: HealthState,
+    pub consecutive_failures: u32,
+    /// The status of the latest health check, if there was one.
+    pub last_check: Option<NrStatus>,
+    pub policy: Option<HealthPolicy>,
+}
+
+#[derive(Default)]
+struct State {
+    policy: Option<HealthPolicy>,
+    failures: u32,
+    quarantined_until: Option<rt::Instant>,
+    last_check: Option<NrStatus>,
+}
+
+/// The health of one plugin instance.
+#[derive(Default)]
+pub(crate) struct HealthMonitor {
+    state: Mutex<State>,
+    /// Whether a policy is set, so calls skip the lock without one.
+    enabled: AtomicBool,
+    /// Whether calls are refused, so healthy calls skip the lock.
+    quarantined: AtomicBool,
+    /// Bumped by every `set_policy`, to retire the previous checker.
+    epoch: AtomicU64,
+}
+
+impl HealthMonitor {
+    /// Replace the policy and clear the record, returning the new epoch.
+    pub(crate) fn set_policy(&self, policy: Option<HealthPolicy>) -> u64 {
+        let mut state = self.state.lock();
+        *state = State {
+            policy,
+            ..State::default()
+        };
+        self.enabled.store(policy.is_some(), Ordering::Release);
+        self.quarantined.store(false, Ordering::Release);
+        self.epoch.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    pub(crate) fn policy(&self) -> Option<HealthPolicy> {
+        self.state.lock().policy
+    }
+
+    /// Refuse a call while `plugin` is quarantined. Without a health check
+    /// to wait for, the quarantine ends with its duration.
+    pub(crate) fn admit(&self, plugin: &str, has_check: bool) -> Result<()> {
+        if !self.quarantined.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut state = self.state.lock();
+        match state.quarantined_until {
+            Some(until) if has_check || rt::Instant::now() < until => {
+                Err(NylonRingHostError::Quarantined {
+                    plugin: plugin.to_string(),
+                })
+            }
+            _ => {
+                self.release(&mut state);
+                Ok(())
+            }
+        }
+    }
+
+    /// Count the status `handle` returned for a call.
+    pub(crate) fn record(&self, status: NrStatus) {
+        if !self.enabled.load(Ordering::Acquire) {
+            return;
+        }
+        match status {
+            NrStatus::Ok | NrStatus::Accepted => {
+                let mut state = self.state.lock();
+                if self.quarantined.load(Ordering::Acquire) {
+                    // Admitted before the quarantine began.
+                    return;
+                }
+                state.failures = 0;
+            }
+            NrStatus::Err => self.fail(&mut self.state.lock()),
+            // Rejected calls and closed streams say nothing about health.
+            _ => {}
+        }
+    }
+
+    /// Record the status of a health check.
+    pub(crate) fn record_check(&self, status: NrStatus) {
+        let mut state = self.state.lock();
+        state.last_check = Some(status);
+        if status != NrStatus::Ok {
+            self.fail(&mut state);
+        } else if state
+            .quarantined_until
+            .is_some_and(|until| rt::Instant::now() >= until)
+        {
+            self.release(&mut state);
+        }
+    }
+
+    /// How long the checker should wait before the next check.
+    pub(crate) fn next_check(&self) -> Option<Duration> {
+        let state = self.state.lock();
+        let interval = state.policy?.check_interval;
+        Some(match state.quarantined_until {
+            Some(until) => interval.min(until.saturating_duration_since(rt::Instant::now())),
+            None => interval,
+        })
+    }
+
+    pub(crate) fn is_current(&self, epoch: u64) -> bool {
+        self.epoch.load(Ordering::Acquire) == epoch
+    }
+
+    pub(crate) fn report(&self) -> PluginHealth {
+        let state = self.state.lock();
+        let health = match state.quarantined_until {
+            None => HealthState::Healthy,
+            Some(until) => match until.checked_duration_since(rt::Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => HealthState::Quarantined { remaining },
+                _ => HealthState::AwaitingCheck,
+            },
+        };
+        PluginHealth {
+            state: health,
+            consecutive_failures: state.failures,
+            last_check: state.last_check,
+            policy: state.policy,
+        }
+    }
+
+    fn fail(&self, state: &mut State) {
+        let Some(policy) = state.policy else {
+            return;
+        };
+        if state.quarantined_until.is_some() {
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= policy.failure_threshold {
+            state.quarantined_until = Some(rt::Instant::now() + policy.quarantine_duration);
+            self.quarantined.store(true, Ordering::Release);
+        }
+    }
+
+    fn release(&self, state: &mut State) {
+        state.quarantined_until = None;
+        state.failures = 0;
+        self.quarantined.store(false, Ordering::Release);
+    }
+}
+
+/// Call the plugin's `health` function on the policy's schedule, until the
+/// plugin is dropped or revoked or the policy changes.
+pub(crate) async fn watch(plugin: Weak<LoadedPlugin>, epoch: u64) {
+    loop {
+        let wait = {
+            let Some(plugin) = plugin.upgrade() else {
+                return;
+            };
+            if !plugin.health.is_current(epoch) || plugin.ctx.is_revoked() {
+                return;
+            }
+            match plugin.health.next_check() {
+                Some(wait) => wait,
+                None => return,
+            }
+        };
+        rt::sleep(wait).await;
+        let Some(plugin) = plugin.upgrade() else {
+            return;
+        };
+        if !plugin.health.is_current(epoch) || plugin.ctx.is_revoked() {
+            return;
+        }
+        if let Some(status) = plugin.backend.health() {
+            plugin.health.record_check(status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(quarantine_duration: Duration) -> HealthMonitor {
+        let monitor = HealthMonitor::default();
+        monitor.set_policy(Some(HealthPolicy {
+            check_interval: Duration::from_secs(60),
+            failure_threshold: 2,
+            quarantine_duration,
+        }));
+        monitor
+    }
+
+    #[test]
+    fn test_failures_in_a_row_quarantine() {
+        let monitor = monitor(Duration::from_secs(60));
+        monitor.record(NrStatus::Err);
+        monitor.record(NrStatus::Ok);
+        monitor.record(NrStatus::Err);
+        monitor.record(NrStatus::Unsupported);
+        assert!(monitor.admit("p", true).is_ok());
+        assert_eq!(monitor.report().consecutive_failures, 1);
+
+        monitor.record(NrStatus::Err);
+        assert!(matches!(
+            monitor.admit("p", true),
+            Err(NylonRingHostError::Quarantined { .. })
+        ));
+        assert!(matches!(
+            monitor.report().state,
+            HealthState::Quarantined { .. }
+        ));
+        // A passing check does not end the quarantine early.
+        monitor.record_check(NrStatus::Ok);
+        assert!(monitor.admit("p", true).is_err());
+    }
+
+    #[test]
+    fn test_quarantine_ends_with_a_passing_check() {
+        let monitor = monitor(Duration::ZERO);
+        monitor.record(NrStatus::Err);
+        monitor.record(NrStatus::Err);
+        assert_eq!(monitor.report().state, HealthState::AwaitingCheck);
+        assert!(monitor.admit("p", true).is_err());
+
+        monitor.record_check(NrStatus::Err);
+        assert!(monitor.admit("p", true).is_err());
+        monitor.record_check(NrStatus::Ok);
+        assert!(monitor.admit("p", true).is_ok());
+        assert_eq!(monitor.report().state, HealthState::Healthy);
+        assert_eq!(monitor.report().consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_without_a_check_the_duration_suffices() {
+        let monitor = monitor(Duration::ZERO);
+        monitor.record(NrStatus::Err);
+        monitor.record(NrStatus::Err);
+        assert!(monitor.admit("p", false).is_ok());
+        assert_eq!(monitor.report().state, HealthState::Healthy);
+    }
+}
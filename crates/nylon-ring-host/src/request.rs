@@ -0,0 +1,77 @@
+//! Calls that pass a whole request, for plugins that route on it.
+//!
+//! [`PluginHandle::call_request`] hands the plugin the method, path, query
+//! and headers of a [`HighLevelRequest`] as an `NrRequest`, with the body as
+//! the payload, through the `handle_req` function of its vtable. Plugins
+//! built with `define_plugin!` provide it for the entries listed under
+//! `req_entries`. The request's [`Extensions`] stay in the host.
+
+use crate::stream::{StreamOptions, StreamReceiver};
+use crate::types::Result;
+use crate::{surface_error, Extensions, PluginHandle};
+use nylon_ring::NrStatus;
+
+/// A request as the host's protocol layer sees it.
+#[derive(Clone, Default)]
+pub struct HighLevelRequest {
+    pub method: String,
+    pub path: String,
+    /// The query string, without the `?`.
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Data for the host's own use, never passed to the plugin.
+    pub extensions: Extensions,
+}
+
+impl HighLevelRequest {
+    /// A request without query, headers or body.
+    pub fn new(method: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl PluginHandle {
+    /// Like [`call_response`](Self::call_response), passing `req` to the
+    /// plugin's `handle_req` with its body as the payload.
+    ///
+    /// Fails with [`NylonRingHostError::MissingFunction`] for plugins
+    /// without `handle_req`, which include WebAssembly plugins. The call
+    /// runs inline, even under [`ExecutionPolicy::DedicatedPool`].
+    ///
+    /// [`NylonRingHostError::MissingFunction`]: crate::NylonRingHostError::MissingFunction
+    /// [`ExecutionPolicy::DedicatedPool`]: crate::ExecutionPolicy::DedicatedPool
+    pub async fn call_request(
+        &self,
+        entry: &str,
+        req: &HighLevelRequest,
+    ) -> Result<(NrStatus, Vec<u8>)> {
+        self.admit(entry)?;
+        self.require("handle_req")?;
+        let _turn = self.entry_turn(entry).await?;
+        let (response, _) = self
+            .call_inline(entry, &req.body, None, |sid| {
+                self.plugin.handle_req(entry, sid, req)
+            })
+            .await?;
+        surface_error(response)
+    }
+
+    /// Like [`call_stream`](Self::call_stream), passing `req` as
+    /// [`call_request`](Self::call_request) does.
+    pub async fn call_request_stream(
+        &self,
+        entry: &str,
+        req: &HighLevelRequest,
+    ) -> Result<(u64, StreamReceiver)> {
+        self.admit(entry)?;
+        self.require("handle_req")?;
+        self.open_stream_with(entry, &req.body, None, StreamOptions::default(), |sid| {
+            self.plugin.handle_req(entry, sid, req)
+        })
+    }
+}
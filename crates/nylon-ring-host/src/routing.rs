@@ -0,0 +1,177 @@
+//! Entry-level routing.
+//!
+//! Maps entry names to plugin names so callers can dispatch on the entry
+//! alone. A pattern is either an exact entry (`"image.resize"`) or a prefix
+//! with a trailing wildcard (`"image.*"`, or `"*"` for everything).
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+#[derive(Default)]
+pub(crate) struct Router {
+    exact: FxHashMap<String, String>,
+    /// `(prefix, plugin)`, longest prefix first.
+    prefixes: Vec<(String, String)>,
+    /// Exact patterns whose entry a reload of their plugin removed.
+    stale: FxHashSet<String>,
+}
+
+impl Router {
+    /// Register `pattern` for `plugin`.
+    ///
+    /// Registering the same pattern for a different plugin is ambiguous and
+    /// rejected. Overlapping prefixes are fine: the longest one wins.
+    pub(crate) fn add(&mut self, pattern: &str, plugin: &str) -> Result<()> {
+        let (key, table) = match pattern.strip_suffix('*') {
+            Some(prefix) => (prefix, Pattern::Prefix),
+            None => (pattern, Pattern::Exact),
+        };
+        if key.contains('*') {
+            return Err(NylonRingHostError::InvalidRoute(pattern.to_string()));
+        }
+
+        let existing = match table {
+            Pattern::Exact => self.exact.get(key),
+            Pattern::Prefix => self
+                .prefixes
+                .iter()
+                .find(|(p, _)| p == key)
+                .map(|(_, plugin)| plugin),
+        };
+        match existing {
+            Some(existing) if existing == plugin => return Ok(()),
+            Some(existing) => {
+                return Err(NylonRingHostError::AmbiguousRoute {
+                    pattern: pattern.to_string(),
+                    existing: existing.clone(),
+                    plugin: plugin.to_string(),
+                })
+            }
+            None => {}
+        }
+
+        match table {
+            Pattern::Exact => {
+                self.exact.insert(key.to_string(), plugin.to_string());
+            }
+            Pattern::Prefix => {
+                let at = self.prefixes.partition_point(|(p, _)| p.len() >= key.len());
+                self.prefixes
+                    .insert(at, (key.to_string(), plugin.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The plugin an entry routes to. Exact matches win over prefixes.
+    pub(crate) fn resolve(&self, entry: &str) -> Option<&str> {
+        if let Some(plugin) = self.exact.get(entry) {
+            return Some(plugin);
+        }
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| entry.starts_with(prefix.as_str()))
+            .map(|(_, plugin)| plugin.as_str())
+    }
+
+    /// Drop every route to `plugin`.
+    pub(crate) fn remove_plugin(&mut self, plugin: &str) {
+        self.exact.retain(|_, p| p != plugin);
+        self.prefixes.retain(|(_, p)| p != plugin);
+        let exact = &self.exact;
+        self.stale.retain(|pattern| exact.contains_key(pattern));
+    }
+
+    /// Flag the exact routes to `plugin` for entries its reload removed,
+    /// and clear the flag on the ones it added back.
+    pub(crate) fn entries_changed(&mut self, plugin: &str, added: &[String], removed: &[String]) {
+        let routed = |entry: &String| self.exact.get(entry).is_some_and(|p| p == plugin);
+        let (added, removed): (Vec<_>, Vec<_>) = (
+            added.iter().filter(|e| routed(e)).collect(),
+            removed.iter().filter(|e| routed(e)).cloned().collect(),
+        );
+        for entry in added {
+            self.stale.remove(entry);
+        }
+        self.stale.extend(removed);
+    }
+
+    /// The flagged routes, sorted.
+    pub(crate) fn stale(&self) -> Vec<String> {
+        let mut stale: Vec<String> = self.stale.iter().cloned().collect();
+        stale.sort();
+        stale
+    }
+}
+
+enum Pattern {
+    Exact,
+    Prefix,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_precedence() {
+        let mut router = Router::default();
+        router.add("*", "fallback").unwrap();
+        router.add("image.*", "images").unwrap();
+        router.add("image.thumb.*", "thumbs").unwrap();
+        router.add("image.resize", "resizer").unwrap();
+
+        assert_eq!(router.resolve("image.resize"), Some("resizer"));
+        assert_eq!(router.resolve("image.crop"), Some("images"));
+        assert_eq!(router.resolve("image.thumb.small"), Some("thumbs"));
+        assert_eq!(router.resolve("text.upper"), Some("fallback"));
+
+        router.remove_plugin("fallback");
+        assert_eq!(router.resolve("text.upper"), None);
+    }
+
+    #[test]
+    fn test_route_conflicts() {
+        let mut router = Router::default();
+        router.add("image.*", "images").unwrap();
+        router.add("image.resize", "resizer").unwrap();
+
+        // Re-registering for the same plugin is a no-op.
+        router.add("image.*", "images").unwrap();
+        assert!(matches!(
+            router.add("image.*", "other"),
+            Err(NylonRingHostError::AmbiguousRoute { existing, .. }) if existing == "images"
+        ));
+        assert!(matches!(
+            router.add("image.resize", "other"),
+            Err(NylonRingHostError::AmbiguousRoute { .. })
+        ));
+        assert!(matches!(
+            router.add("image.*.small", "other"),
+            Err(NylonRingHostError::InvalidRoute(_))
+        ));
+    }
+
+    #[test]
+    fn test_routes_to_removed_entries_are_flagged() {
+        let mut router = Router::default();
+        router.add("users", "api").unwrap();
+        router.add("orders", "api").unwrap();
+        router.add("carts", "shop").unwrap();
+        router.add("api.*", "api").unwrap();
+
+        let removed = ["users".to_string(), "carts".to_string()];
+        router.entries_changed("api", &[], &removed);
+        // `carts` routes to another plugin, which still serves it.
+        assert_eq!(router.stale(), ["users"]);
+        assert_eq!(router.resolve("users"), Some("api"));
+
+        router.entries_changed("api", &["users".to_string()], &[]);
+        assert!(router.stale().is_empty());
+
+        router.entries_changed("api", &[], &removed);
+        router.remove_plugin("api");
+        assert!(router.stale().is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+//! The runtime services the host needs beyond channels: timers and tasks.
+//!
+//! Channels come from `tokio::sync`, which does not depend on the Tokio
+//! runtime and works under any executor. Timers and background tasks come
+//! from Tokio with the default `tokio-rt` feature. With only `async-io`
+//! enabled, timers come from `async-io` and each background task runs on its
+//! own thread, so the host can be driven by smol, async-std or a plain
+//! `block_on`.
+//!
+//! Background tasks are started through the host's [`Spawner`], which with
+//! Tokio may hold a runtime handle given to
+//! [`NylonRingHost::new_with_runtime`](crate::NylonRingHost::new_with_runtime).
+
+#[cfg(not(any(feature = "tokio-rt", feature = "async-io")))]
+compile_error!("nylon-ring-host needs either the `tokio-rt` or the `async-io` feature");
+
+use std::future::Future;
+use std::task::Poll;
+use std::time::Duration;
+
+pub(crate) use imp::{timeout_at, Instant, Spawner};
+
+/// Run `future` with a time limit. `None` if it did not finish in time.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    timeout_at(Instant::now() + duration, future).await
+}
+
+/// Wait for `duration`.
+pub(crate) async fn sleep(duration: Duration) {
+    timeout(duration, std::future::pending::<()>()).await;
+}
+
+/// Run `futures` concurrently on the current task and collect their outputs
+/// in order.
+pub(crate) async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures.into_iter().map(|f| Some(Box::pin(f))).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut done = true;
+        for (slot, output) in futures.iter_mut().zip(&mut outputs) {
+            let Some(future) = slot else {
+                continue;
+            };
+            match future.as_mut().poll(cx) {
+                Poll::Ready(value) => {
+                    *output = Some(value);
+                    *slot = None;
+                }
+                Poll::Pending => done = false,
+            }
+        }
+        if done {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+#[cfg(feature = "tokio-rt")]
+mod imp {
+    use std::future::Future;
+
+    pub(crate) use tokio::task::JoinHandle;
+    // Tokio's clock, so paused test time applies to deadlines.
+    pub(crate) use tokio::time::Instant;
+
+    /// Starts background tasks on the given runtime, or on the runtime of
+    /// the caller without one.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Spawner(pub(crate) Option<tokio::runtime::Handle>);
+
+    impl Spawner {
+        pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+        where
+            F: Future + Send + 'static,
+            F::Output: Send + 'static,
+        {
+            match &self.0 {
+                Some(runtime) => runtime.spawn(future),
+                None => tokio::spawn(future),
+            }
+        }
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+        tokio::time::timeout_at(deadline, future).await.ok()
+    }
+}
+
+#[cfg(all(feature = "async-io", not(feature = "tokio-rt")))]
+mod imp {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::sync::oneshot;
+
+    pub(crate) use std::time::Instant;
+
+    /// The output of a task started with [`spawn`]. Fails if the task
+    /// panicked.
+    pub(crate) struct JoinHandle<T>(oneshot::Receiver<T>);
+
+    impl<T> Future for JoinHandle<T> {
+        type Output = Result<T, oneshot::error::RecvError>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            Pin::new(&mut self.0).poll(cx)
+        }
+    }
+
+    /// Starts each background task on a thread of its own.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Spawner;
+
+    impl Spawner {
+        pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+        where
+            F: Future + Send + 'static,
+            F::Output: Send + 'static,
+        {
+            let (tx, rx) = oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(async_io::block_on(future));
+            });
+            JoinHandle(rx)
+        }
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+        let expire = async {
+            async_io::Timer::at(deadline).await;
+            None
+        };
+        futures_lite::future::or(async { Some(future.await) }, expire).await
+    }
+}
@@ -0,0 +1,290 @@
+//! Per-entry payload validation.
+//!
+//! A [`SchemaRule`] registered with
+//! [`NylonRingHost::register_schema`](crate::NylonRingHost::register_schema)
+//! is checked on the unary call paths: a request that does not conform is
+//! rejected without calling the plugin, and a response is checked if the
+//! rule has a response schema. Plugins can publish JSON Schemas of their own
+//! during `init` under [`nylon_ring::REQUEST_SCHEMA_KEY_PREFIX`] and
+//! [`nylon_ring::RESPONSE_SCHEMA_KEY_PREFIX`].
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Where and why a payload does not conform to a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Location in the payload, e.g. a JSON pointer. Empty for the payload
+    /// as a whole.
+    pub path: String,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// A check applied to the payloads of an entry.
+pub trait Schema: Send + Sync {
+    fn validate(&self, payload: &[u8]) -> std::result::Result<(), Violation>;
+}
+
+/// Length and prefix checks on raw bytes.
+#[derive(Debug, Clone, Default)]
+pub struct BytesSchema {
+    min_len: usize,
+    max_len: Option<usize>,
+    prefix: Vec<u8>,
+}
+
+impl BytesSchema {
+    /// A schema that accepts every payload.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_len(mut self, len: usize) -> Self {
+        self.min_len = len;
+        self
+    }
+
+    pub fn max_len(mut self, len: usize) -> Self {
+        self.max_len = Some(len);
+        self
+    }
+
+    /// Require payloads to start with `prefix`.
+    pub fn prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+}
+
+impl Schema for BytesSchema {
+    fn validate(&self, payload: &[u8]) -> std::result::Result<(), Violation> {
+        let len = payload.len();
+        if len < self.min_len {
+            let message = format!("{len} bytes, expected at least {}", self.min_len);
+            return Err(Violation::new("", message));
+        }
+        if let Some(max) = self.max_len.filter(|&max| len > max) {
+            return Err(Violation::new(
+                "",
+                format!("{len} bytes, expected at most {max}"),
+            ));
+        }
+        if !payload.starts_with(&self.prefix) {
+            let message = format!("does not start with \"{}\"", self.prefix.escape_ascii());
+            return Err(Violation::new("", message));
+        }
+        Ok(())
+    }
+}
+
+/// A JSON Schema. Payloads must be JSON documents that conform to it.
+#[cfg(feature = "json-schema")]
+pub struct JsonSchema {
+    validator: jsonschema::Validator,
+}
+
+#[cfg(feature = "json-schema")]
+impl JsonSchema {
+    pub fn new(schema: &serde_json::Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| NylonRingHostError::InvalidSchema(e.to_string()))?;
+        Ok(Self { validator })
+    }
+
+    /// Parse and compile a schema document.
+    pub fn from_slice(schema: &[u8]) -> Result<Self> {
+        let schema = serde_json::from_slice(schema)
+            .map_err(|e| NylonRingHostError::InvalidSchema(e.to_string()))?;
+        Self::new(&schema)
+    }
+}
+
+#[cfg(feature = "json-schema")]
+impl Schema for JsonSchema {
+    fn validate(&self, payload: &[u8]) -> std::result::Result<(), Violation> {
+        let document: serde_json::Value = serde_json::from_slice(payload)
+            .map_err(|e| Violation::new("", format!("invalid JSON: {e}")))?;
+        self.validator
+            .validate(&document)
+            .map_err(|e| Violation::new(e.instance_path().to_string(), e.to_string()))
+    }
+}
+
+#[cfg(feature = "json-schema")]
+impl std::fmt::Debug for JsonSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonSchema").finish_non_exhaustive()
+    }
+}
+
+/// The schemas checked for one entry.
+///
+/// Any [`Schema`] converts into a rule that only checks requests.
+#[derive(Clone, Default)]
+pub struct SchemaRule {
+    request: Option<Arc<dyn Schema>>,
+    response: Option<Arc<dyn Schema>>,
+}
+
+impl SchemaRule {
+    /// A rule that checks nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject requests that do not conform to `schema`.
+    pub fn request(mut self, schema: impl Schema + 'static) -> Self {
+        self.request = Some(Arc::new(schema));
+        self
+    }
+
+    /// Fail calls whose `Ok` response does not conform to `schema`. Other
+    /// statuses are not checked, since their payload is usually a message.
+    pub fn response(mut self, schema: impl Schema + 'static) -> Self {
+        self.response = Some(Arc::new(schema));
+        self
+    }
+
+    pub(crate) fn check_request(&self, payload: &[u8]) -> Result<()> {
+        check(self.request.as_deref(), payload)
+    }
+
+    pub(crate) fn check_response(&self, payload: &[u8]) -> Result<()> {
+        check(self.response.as_deref(), payload)
+    }
+}
+
+impl<S: Schema + 'static> From<S> for SchemaRule {
+    fn from(schema: S) -> Self {
+        Self::new().request(schema)
+    }
+}
+
+impl std::fmt::Debug for SchemaRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchemaRule")
+            .field("request", &self.request.is_some())
+            .field("response", &self.response.is_some())
+            .finish()
+    }
+}
+
+fn check(schema: Option<&dyn Schema>, payload: &[u8]) -> Result<()> {
+    match schema.map(|schema| schema.validate(payload)) {
+        Some(Err(Violation { path, message })) => {
+            Err(NylonRingHostError::SchemaViolation { path, message })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The rules of one plugin. Rules registered by the host take precedence
+/// over those the plugin published, and survive a reload.
+#[derive(Default)]
+pub(crate) struct Schemas {
+    pub(crate) registered: FxHashMap<String, Arc<SchemaRule>>,
+    pub(crate) published: FxHashMap<String, Arc<SchemaRule>>,
+}
+
+impl Schemas {
+    pub(crate) fn get(&self, entry: &str) -> Option<Arc<SchemaRule>> {
+        if self.registered.is_empty() && self.published.is_empty() {
+            return None;
+        }
+        self.registered
+            .get(entry)
+            .or_else(|| self.published.get(entry))
+            .cloned()
+    }
+
+    /// Compile the schemas a plugin stored under the schema key prefixes
+    /// during `init`. Schemas that fail to compile are skipped with a
+    /// warning.
+    pub(crate) fn publish(&mut self, plugin: &str, state: &HashMap<String, Vec<u8>>) {
+        use nylon_ring::{REQUEST_SCHEMA_KEY_PREFIX, RESPONSE_SCHEMA_KEY_PREFIX};
+
+        for (key, document) in state {
+            let (entry, is_request) =
+                if let Some(entry) = key.strip_prefix(REQUEST_SCHEMA_KEY_PREFIX) {
+                    (entry, true)
+                } else if let Some(entry) = key.strip_prefix(RESPONSE_SCHEMA_KEY_PREFIX) {
+                    (entry, false)
+                } else {
+                    continue;
+                };
+
+            #[cfg(feature = "json-schema")]
+            match JsonSchema::from_slice(document) {
+                Ok(schema) => {
+                    let rule = self.published.entry(entry.to_string()).or_default();
+                    let rule = Arc::make_mut(rule);
+                    if is_request {
+                        rule.request = Some(Arc::new(schema));
+                    } else {
+                        rule.response = Some(Arc::new(schema));
+                    }
+                }
+                Err(e) => log::warn!("plugin {plugin:?} published a bad schema for {entry:?}: {e}"),
+            }
+
+            #[cfg(not(feature = "json-schema"))]
+            {
+                let _ = (document, is_request);
+                log::warn!(
+                    "plugin {plugin:?} published a schema for {entry:?}, but the host was built without `json-schema`"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_schema() {
+        let schema = BytesSchema::new().min_len(3).max_len(8).prefix(b"v1:");
+        assert!(schema.validate(b"v1:").is_ok());
+        assert!(schema.validate(b"v1:hello").is_ok());
+        assert_eq!(
+            schema.validate(b"v1"),
+            Err(Violation::new("", "2 bytes, expected at least 3"))
+        );
+        assert_eq!(
+            schema.validate(b"v1:hello!"),
+            Err(Violation::new("", "9 bytes, expected at most 8"))
+        );
+        assert_eq!(
+            schema.validate(b"v2:x"),
+            Err(Violation::new("", "does not start with \"v1:\""))
+        );
+        assert!(BytesSchema::new().validate(b"").is_ok());
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test]
+    fn test_json_schema() {
+        let schema = JsonSchema::from_slice(
+            br#"{"type": "object", "properties": {"id": {"type": "integer"}}, "required": ["id"]}"#,
+        )
+        .unwrap();
+        assert!(schema.validate(br#"{"id": 7}"#).is_ok());
+        assert_eq!(schema.validate(br#"{"id": "7"}"#).unwrap_err().path, "/id");
+        assert!(schema.validate(b"{}").is_err());
+        assert!(schema.validate(b"not json").is_err());
+        assert!(JsonSchema::from_slice(br#"{"type": 5}"#).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Host-owned buffers for large payloads.
+//!
+//! Plugins write responses into buffers of the arena and reply with their
+//! handle, so a multi-megabyte response is neither copied into an `NrVec`
+//! by the plugin nor allocated anew. A shared request comes with one such
+//! buffer, and plugins take others through the `buf_acquire` callback.
+//! Buffers go back to a small pool when the [`SharedPayload`] holding them
+//! is dropped, and are reused by the next call.
+
+use crate::context::HostContext;
+use nylon_ring::shared::{encode_shared_request, NR_SHARED_REQUEST_LEN};
+use nylon_ring::NrStatus;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Weak};
+
+/// Free buffers kept for reuse. More are freed.
+const POOLED_BUFFERS: usize = 8;
+
+/// A buffer handed out under a handle.
+struct Slot {
+    buf: Vec<u8>,
+    /// Bytes the plugin may write.
+    size: usize,
+    /// Whether the plugin is done writing.
+    committed: bool,
+}
+
+/// Buffers handed out by handle, and free ones to reuse.
+#[derive(Default)]
+pub(crate) struct SharedArena {
+    inner: Mutex<ArenaInner>,
+}
+
+#[derive(Default)]
+struct ArenaInner {
+    /// The last handle handed out. Handle 0 is never used.
+    last_handle: u64,
+    live: FxHashMap<u64, Slot>,
+    free: Vec<Vec<u8>>,
+}
+
+impl ArenaInner {
+    /// The smallest free buffer with room for `size` bytes, or a new one.
+    /// Every byte of its capacity is initialized, so plugins can be handed
+    /// it as a slice.
+    fn buffer(&mut self, size: usize) -> Vec<u8> {
+        let fit = self
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, buf)| buf.capacity() >= size)
+            .min_by_key(|(_, buf)| buf.capacity())
+            .map(|(i, _)| i);
+        match fit {
+            Some(i) => self.free.swap_remove(i),
+            None => {
+                let mut buf = vec![0; size];
+                buf.clear();
+                buf
+            }
+        }
+    }
+
+    fn insert(&mut self, slot: Slot) -> u64 {
+        self.last_handle += 1;
+        self.live.insert(self.last_handle, slot);
+        self.last_handle
+    }
+
+    fn recycle(&mut self, mut buf: Vec<u8>) {
+        if self.free.len() < POOLED_BUFFERS {
+            buf.clear();
+            self.free.push(buf);
+        }
+    }
+}
+
+impl SharedArena {
+    /// Hand out a buffer with room for `size` bytes, for the plugin to
+    /// write to.
+    pub(crate) fn acquire(&self, size: usize) -> (u64, *mut u8) {
+        let mut inner = self.inner.lock();
+        let mut buf = inner.buffer(size);
+        let ptr = buf.as_mut_ptr();
+        let handle = inner.insert(Slot {
+            buf,
+            size,
+            committed: false,
+        });
+        (handle, ptr)
+    }
+
+    /// Record that the first `len` bytes of `handle` are written. A commit
+    /// past the buffer's size releases it.
+    pub(crate) fn commit(&self, handle: u64, len: usize) -> NrStatus {
+        let mut inner = self.inner.lock();
+        let Some(slot) = inner.live.get_mut(&handle) else {
+            return NrStatus::Invalid;
+        };
+        if slot.committed {
+            return NrStatus::Invalid;
+        }
+        if len > slot.size {
+            let slot = inner.live.remove(&handle).expect("slot was just found");
+            inner.recycle(slot.buf);
+            return NrStatus::Invalid;
+        }
+        // Safety: the plugin wrote `len` bytes, within the capacity.
+        unsafe { slot.buf.set_len(len) };
+        slot.committed = true;
+        NrStatus::Ok
+    }
+
+    /// Take the committed buffer `handle` out of the arena.
+    pub(crate) fn take(&self, handle: u64) -> Option<Vec<u8>> {
+        let mut inner = self.inner.lock();
+        let slot = inner.live.get(&handle)?;
+        if !slot.committed {
+            return None;
+        }
+        inner.live.remove(&handle).map(|slot| slot.buf)
+    }
+
+    /// Put the buffer `handle` back in the pool, whether or not it was
+    /// committed.
+    pub(crate) fn release(&self, handle: u64) {
+        let mut inner = self.inner.lock();
+        if let Some(slot) = inner.live.remove(&handle) {
+            inner.recycle(slot.buf);
+        }
+    }
+
+    fn recycle(&self, buf: Vec<u8>) {
+        self.inner.lock().recycle(buf);
+    }
+
+    pub(crate) fn stats(&self) -> SharedBufferStats {
+        let inner = self.inner.lock();
+        SharedBufferStats {
+            live: inner.live.len(),
+            pooled: inner.free.len(),
+        }
+    }
+}
+
+/// The response buffer of a shared request, released back to the pool on
+/// drop unless the plugin replied with it.
+pub(crate) struct SharedRequest<'a> {
+    arena: &'a SharedArena,
+    handle: u64,
+    frame: [u8; NR_SHARED_REQUEST_LEN],
+}
+
+impl<'a> SharedRequest<'a> {
+    pub(crate) fn new(host: &'a HostContext, body: &[u8]) -> Self {
+        let (handle, response) = host.shared.acquire(body.len());
+        Self {
+            arena: &host.shared,
+            handle,
+            frame: encode_shared_request(handle, body, response),
+        }
+    }
+
+    /// The payload to call with.
+    pub(crate) fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+}
+
+impl Drop for SharedRequest<'_> {
+    fn drop(&mut self) {
+        self.arena.release(self.handle);
+    }
+}
+
+/// Buffers of a host's shared payload arena, from
+/// [`NylonRingHost::shared_buffer_stats`](crate::NylonRingHost::shared_buffer_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharedBufferStats {
+    /// Buffers handed to plugins and not returned yet. Buffers a plugin
+    /// acquired but never sent stay live until the host is dropped.
+    pub live: usize,
+    /// Free buffers kept for reuse.
+    pub pooled: usize,
+}
+
+/// A response from
+/// [`PluginHandle::call_response_shared`](crate::PluginHandle::call_response_shared).
+///
+/// When the plugin answered with a shared buffer, this is that buffer,
+/// which goes back to the host's pool on drop.
+pub struct SharedPayload {
+    buf: Vec<u8>,
+    /// The arena to return `buf` to, for a shared buffer.
+    arena: Option<Weak<HostContext>>,
+}
+
+impl SharedPayload {
+    pub(crate) fn owned(buf: Vec<u8>) -> Self {
+        Self { buf, arena: None }
+    }
+
+    pub(crate) fn shared(buf: Vec<u8>, host: &Arc<HostContext>) -> Self {
+        Self {
+            buf,
+            arena: Some(Arc::downgrade(host)),
+        }
+    }
+
+    /// Whether the plugin answered with a shared buffer.
+    pub fn is_shared(&self) -> bool {
+        self.arena.is_some()
+    }
+
+    /// The bytes as a vector, keeping a shared buffer out of the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.arena = None;
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for SharedPayload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsRef<[u8]> for SharedPayload {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl fmt::Debug for SharedPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedPayload")
+            .field("len", &self.buf.len())
+            .field("shared", &self.is_shared())
+            .finish()
+    }
+}
+
+impl Drop for SharedPayload {
+    fn drop(&mut self) {
+        if let Some(host) = self.arena.take().and_then(|host| host.upgrade()) {
+            host.shared.recycle(std::mem::take(&mut self.buf));
+        }
+    }
+}
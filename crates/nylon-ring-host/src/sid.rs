@@ -3,16 +3,96 @@
 //! This module provides thread-local SID (Session ID) generation to minimize
 //! contention across threads. Each thread allocates SIDs from a local block,
 //! only synchronizing with other threads when the block is exhausted.
+//!
+//! A SID is laid out as:
+//!
+//! ```text
+//! | 63 ..= 56 | 55 ..= 44  | 43 ..= 0 |
+//! | mode      | host epoch | sequence |
+//! ```
+//!
+//! The mode byte says which kind of call the SID was issued for (see
+//! [`SidMode`]); the sequence never carries into it. The sequence counter is
+//! shared by every host in the process, so SIDs are unique process-wide
+//! until the sequence space runs out (see [`SidExhaustion`]); the epoch tags
+//! which host issued them.
+//!
+//! The sequence space holds 2^44 (about 1.76 × 10^13) SIDs, handed to
+//! threads in blocks of a million. A thread takes a whole block the first
+//! time it calls a plugin, so a process that spawns short-lived calling
+//! threads spends a million SIDs per thread: 17.6 million of them use up
+//! the space even if each makes a single call. At a steady 10 million
+//! calls per second on long-lived threads it lasts about 20 days. Hosts
+//! therefore recycle the space by default.
 
+use crate::context::HostContext;
+use crate::error::NylonRingHostError;
+use crate::types::Result;
 use std::cell::Cell;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Number of SIDs allocated per block.
 const SID_BLOCK_SIZE: u64 = 1_000_000;
 
+const MODE_SHIFT: u32 = 56;
+
+const EPOCH_SHIFT: u32 = 44;
+const EPOCH_MASK: u64 = 0xFFF;
+
+/// Exclusive upper bound of the sequence part.
+const SEQUENCE_LIMIT: u64 = 1 << EPOCH_SHIFT;
+
 /// Global counter for allocating SID blocks.
 static GLOBAL_SID: AtomicU64 = AtomicU64::new(1);
 
+/// Times the sequence space was recycled.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Counter for host epochs. Wraps after 4096 hosts, which only affects the
+/// tag, not uniqueness.
+static NEXT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// The kind of call a SID was issued for, kept in its top byte.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidMode {
+    /// A response delivered through the pending map.
+    Unary = 0x01,
+    /// A response delivered to the caller's thread-local slot.
+    Fast = 0x02,
+    /// A stream of frames.
+    Stream = 0x03,
+    /// A call whose results have no receiver. Keeps the top bit, which
+    /// marked these calls before the mode byte existed.
+    FireAndForget = 0x80,
+}
+
+impl SidMode {
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0x01 => Some(Self::Unary),
+            0x02 => Some(Self::Fast),
+            0x03 => Some(Self::Stream),
+            0x80 => Some(Self::FireAndForget),
+            _ => None,
+        }
+    }
+}
+
+/// What a host does once the process has used up the sequence space
+/// (2^44 SIDs; see the [module docs](self) for how fast that goes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SidExhaustion {
+    /// Fail new calls with [`NylonRingHostError::SidSpaceExhausted`], for
+    /// hosts that would rather stop than risk reusing a SID.
+    Error,
+    /// Start the sequence over and count a new generation (see
+    /// [`sid_generation`]). A SID is only reused if a call from the
+    /// previous generation with the same sequence is still outstanding.
+    #[default]
+    Recycle,
+}
+
 /// A block of SIDs allocated to a thread.
 #[derive(Copy, Clone)]
 struct SidBlock {
@@ -27,21 +107,177 @@ thread_local! {
     }) };
 }
 
-/// Generate the next unique session ID.
+/// Allocate the epoch for a new host.
+pub(crate) fn next_epoch() -> u16 {
+    (NEXT_EPOCH.fetch_add(1, Ordering::Relaxed) & EPOCH_MASK) as u16
+}
+
+/// Generate the next unique session ID for a `mode` call on `host`.
 ///
 /// This function uses thread-local storage to minimize contention.
 /// Each thread maintains a local block of SIDs and only synchronizes
 /// with the global counter when the block is exhausted.
-pub(crate) fn next_sid() -> u64 {
+pub(crate) fn next_sid(host: &HostContext, mode: SidMode) -> Result<u64> {
     THREAD_LOCAL_SID_BLOCK.with(|cell| {
         let mut block = cell.get();
         if block.offset >= SID_BLOCK_SIZE {
-            let base = GLOBAL_SID.fetch_add(SID_BLOCK_SIZE, Ordering::Relaxed);
+            let policy = *host.sid_exhaustion.lock();
+            let base = allocate_block(&GLOBAL_SID, &GENERATION, SEQUENCE_LIMIT, policy)
+                .ok_or(NylonRingHostError::SidSpaceExhausted)?;
             block = SidBlock { base, offset: 0 };
         }
-        let sid = block.base + block.offset;
+        let sequence = block.base + block.offset;
         block.offset += 1;
         cell.set(block);
-        sid
+        Ok(sequence | ((host.epoch as u64) << EPOCH_SHIFT) | ((mode as u64) << MODE_SHIFT))
     })
 }
+
+/// The part of `sid` that identifies the call, without the mode byte. Keys
+/// the pending map.
+#[inline(always)]
+pub(crate) fn sid_key(sid: u64) -> u64 {
+    sid & !(0xFF << MODE_SHIFT)
+}
+
+/// The mode `sid` was issued for, or `None` if it carries no known mode.
+#[inline(always)]
+pub fn sid_mode(sid: u64) -> Option<SidMode> {
+    SidMode::from_tag((sid >> MODE_SHIFT) as u8)
+}
+
+/// Whether `sid` belongs to a fire-and-forget call, whose results have no
+/// receiver on the host.
+#[inline(always)]
+pub fn is_fire_and_forget(sid: u64) -> bool {
+    (sid >> MODE_SHIFT) as u8 == SidMode::FireAndForget as u8
+}
+
+/// The epoch of the host that issued `sid`. See
+/// [`NylonRingHost::epoch`](crate::NylonRingHost::epoch).
+#[inline(always)]
+pub fn sid_epoch(sid: u64) -> u16 {
+    ((sid >> EPOCH_SHIFT) & EPOCH_MASK) as u16
+}
+
+/// How many times the process has recycled the sequence space under
+/// [`SidExhaustion::Recycle`].
+pub fn sid_generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Reserve a block of sequence numbers below `limit`. `None` once they are
+/// used up, unless `policy` recycles them.
+fn allocate_block(
+    counter: &AtomicU64,
+    generation: &AtomicU64,
+    limit: u64,
+    policy: SidExhaustion,
+) -> Option<u64> {
+    loop {
+        let base = counter.fetch_add(SID_BLOCK_SIZE, Ordering::Relaxed);
+        if base.saturating_add(SID_BLOCK_SIZE) <= limit {
+            return Some(base);
+        }
+        if policy == SidExhaustion::Error {
+            return None;
+        }
+        // One of the threads that ran out restarts the sequence; the others
+        // retry on the restarted counter.
+        let restarted = counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                (current.saturating_add(SID_BLOCK_SIZE) > limit).then_some(1)
+            })
+            .is_ok();
+        if restarted {
+            generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sid_layout() {
+        let host = crate::NylonRingHost::new();
+        let ctx = &*host.host_ctx;
+        let epoch = ctx.epoch;
+
+        for mode in [
+            SidMode::Unary,
+            SidMode::Fast,
+            SidMode::Stream,
+            SidMode::FireAndForget,
+        ] {
+            let sid = next_sid(ctx, mode).unwrap();
+            assert_eq!(sid_mode(sid), Some(mode));
+            assert_eq!(sid_epoch(sid), epoch);
+            assert_eq!(is_fire_and_forget(sid), mode == SidMode::FireAndForget);
+            assert_eq!(sid_key(sid) >> MODE_SHIFT, 0);
+            assert_eq!(
+                sid_key(sid) & (SEQUENCE_LIMIT - 1),
+                sid & (SEQUENCE_LIMIT - 1)
+            );
+        }
+        assert_eq!(sid_mode(0), None);
+        assert_eq!(sid_mode(0x7F << MODE_SHIFT), None);
+
+        // A full epoch and the largest sequence stay out of the mode byte.
+        let sid = (SEQUENCE_LIMIT - 1) | (EPOCH_MASK << EPOCH_SHIFT);
+        assert_eq!(sid_mode(sid), None);
+        assert_eq!(sid_epoch(sid), EPOCH_MASK as u16);
+    }
+
+    #[test]
+    fn test_exhaustion_policies() {
+        let generation = AtomicU64::new(0);
+        let limit = 3 * SID_BLOCK_SIZE + 1;
+
+        let counter = AtomicU64::new(1);
+        for _ in 0..3 {
+            assert!(allocate_block(&counter, &generation, limit, SidExhaustion::Error).is_some());
+        }
+        assert_eq!(
+            allocate_block(&counter, &generation, limit, SidExhaustion::Error),
+            None
+        );
+        assert_eq!(
+            allocate_block(&counter, &generation, limit, SidExhaustion::Error),
+            None
+        );
+
+        assert_eq!(
+            allocate_block(&counter, &generation, limit, SidExhaustion::Recycle),
+            Some(1)
+        );
+        assert_eq!(generation.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_long_run_allocation() {
+        // Many threads allocating through several generations of a small
+        // space only ever get blocks inside it.
+        let generation = AtomicU64::new(0);
+        let limit = 10 * SID_BLOCK_SIZE + 1;
+        let counter = AtomicU64::new(1);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10_000 {
+                        let base =
+                            allocate_block(&counter, &generation, limit, SidExhaustion::Recycle)
+                                .unwrap();
+                        assert!(base >= 1 && base + SID_BLOCK_SIZE <= limit);
+                        assert_eq!((base - 1) % SID_BLOCK_SIZE, 0);
+                    }
+                });
+            }
+        });
+        // 80,000 blocks from a space of ten, each handed out exactly once
+        // per generation.
+        assert_eq!(generation.load(Ordering::Relaxed), 7_999);
+    }
+}
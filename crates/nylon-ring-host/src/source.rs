@@ -0,0 +1,90 @@
+//! Plugin library sources.
+//!
+//! A plugin is either loaded from a path on disk or from an in-memory image.
+//! In-memory images are materialized into a private temporary file before
+//! being handed to the dynamic loader. Instanced plugins are copied the same
+//! way so that every instance is a distinct file with its own statics.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+#[cfg(any(test, feature = "testing"))]
+use nylon_ring::NrPluginInfo;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+
+/// Where a loaded plugin came from. Used by `reload` to recreate it.
+#[derive(Clone)]
+pub(crate) enum PluginSource {
+    /// A shared library on the filesystem.
+    Path(String),
+    /// An in-memory shared library image.
+    Bytes(Arc<[u8]>),
+    /// A private copy of a shared library on the filesystem.
+    Instanced(String),
+    /// A WebAssembly module on the filesystem.
+    #[cfg(feature = "wasm")]
+    Wasm(String),
+    /// A plugin linked into the host binary.
+    #[cfg(any(test, feature = "testing"))]
+    Static(&'static NrPluginInfo),
+}
+
+/// Write a shared library image to a temporary file readable only by the
+/// current user (`0600` on Unix).
+///
+/// The file is removed when the returned handle is dropped, so it must be
+/// kept alive for as long as the library is loaded.
+pub(crate) fn materialize(mut image: impl Read) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("nylon-ring-")
+        .suffix(std::env::consts::DLL_SUFFIX)
+        .tempfile()
+        .map_err(NylonRingHostError::FailedToMaterializeLibrary)?;
+
+    std::io::copy(&mut image, &mut file)
+        .and_then(|_| file.flush())
+        .map_err(NylonRingHostError::FailedToMaterializeLibrary)?;
+
+    Ok(file)
+}
+
+/// Copy the shared library at `path` to a fresh private file.
+///
+/// Loaders cache libraries by path (and on some platforms by file identity),
+/// so a distinct copy is what gives an instance its own statics.
+#[cfg(any(unix, windows))]
+pub(crate) fn instance_copy(path: &str) -> Result<NamedTempFile> {
+    let lib = std::fs::File::open(path).map_err(NylonRingHostError::FailedToMaterializeLibrary)?;
+    materialize(lib)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn instance_copy(path: &str) -> Result<NamedTempFile> {
+    Err(NylonRingHostError::DuplicateInstanceUnsupported(
+        path.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_copies_are_distinct() {
+        let mut original = NamedTempFile::new().unwrap();
+        original.write_all(b"\x7fELF not really").unwrap();
+        let path = original.path().to_str().unwrap();
+
+        let a = instance_copy(path).unwrap();
+        let b = instance_copy(path).unwrap();
+        assert_ne!(a.path(), b.path());
+        assert_eq!(std::fs::read(a.path()).unwrap(), b"\x7fELF not really");
+        assert_eq!(std::fs::read(b.path()).unwrap(), b"\x7fELF not really");
+
+        assert!(matches!(
+            instance_copy("/nonexistent/libplugin.so"),
+            Err(NylonRingHostError::FailedToMaterializeLibrary(_))
+        ));
+    }
+}
@@ -0,0 +1,808 @@
+//! Host-side stream channels with consumer lag tracking.
+//!
+//! Every frame the plugin sends is timestamped when it is queued, so the host
+//! can tell how far a consumer is behind: the number of buffered frames and
+//! the age of the oldest one. Only streams opened with a lag alert installed
+//! ([`NylonRingHost::on_stream_lag`](crate::NylonRingHost::on_stream_lag)) or
+//! as resumable are tracked; the others report no lag.
+//!
+//! Resumable streams outlive their receiver: frames sent while no receiver is
+//! attached are buffered, and a new receiver replays them before picking up
+//! live frames.
+//!
+//! Streams opened with [`StreamOptions`] end when the plugin goes quiet or
+//! sends too much, instead of leaving the receiver waiting on a plugin that
+//! never sends its final frame, and can drop frames the plugin sent more
+//! than once (see [`crate::dedupe`]).
+
+use crate::context::{InFlight, PluginContext};
+use crate::dedupe::{Dedupe, DedupeOptions, DedupeStats};
+use crate::metrics::DetachedCall;
+use crate::rt::{self, Instant};
+use crate::types::{self, StreamFrame};
+use crate::{LoadedPlugin, PluginHandle};
+use nylon_ring::NrStatus;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How far a stream consumer is behind its producer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamLag {
+    /// Frames sent by the plugin but not yet received.
+    pub frames: usize,
+    /// Age of the oldest buffered frame.
+    pub oldest: Duration,
+}
+
+/// When to raise a stream lag alert.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamLagAlert {
+    /// Lag is excessive when more than this many frames are buffered.
+    pub max_frames: usize,
+    /// Lag must stay excessive for this long before the hook fires.
+    pub window: Duration,
+}
+
+/// Why [`StreamReceiver::try_recv`] returned no frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TryRecvError {
+    /// No frame is buffered yet.
+    #[error("no frame is buffered")]
+    Empty,
+    /// The stream is finished and every frame has been received.
+    #[error("stream is finished")]
+    Disconnected,
+}
+
+/// Called with `(plugin, sid, lag)` once per lag episode.
+pub type StreamLagHook = Arc<dyn Fn(&str, u64, StreamLag) + Send + Sync>;
+
+/// Buffering for a resumable stream.
+#[derive(Debug, Clone, Copy)]
+pub struct ResumeOptions {
+    /// Frames kept while no receiver is attached. One more expires the
+    /// stream.
+    pub buffer: usize,
+    /// How long the stream can stay detached before it expires.
+    pub ttl: Duration,
+}
+
+impl Default for ResumeOptions {
+    fn default() -> Self {
+        Self {
+            buffer: 64,
+            ttl: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Limits on a stream opened with
+/// [`PluginHandle::call_stream_with_options`](crate::PluginHandle::call_stream_with_options).
+///
+/// A stream over a limit ends with an `Err` frame carrying
+/// [`StreamLimit::message`]. Once the receiver gets that frame, or is
+/// dropped, the stream's entry in the pending map and its state are removed,
+/// and the plugin's `stream_close`, if it has one, is called unless the
+/// plugin had ended the stream itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamOptions {
+    /// How long `recv` waits for the next frame.
+    pub idle_timeout: Option<Duration>,
+    /// Frames the plugin may send before its final one.
+    pub max_frames: Option<usize>,
+    /// Payload bytes the plugin may send before its final frame.
+    pub max_total_bytes: Option<usize>,
+    /// Drop frames the plugin sent more than once. Dropped frames do not
+    /// count toward the limits above.
+    pub dedupe: Option<DedupeOptions>,
+}
+
+impl StreamOptions {
+    fn is_limited(&self) -> bool {
+        self.idle_timeout.is_some() || self.max_frames.is_some() || self.max_total_bytes.is_some()
+    }
+
+    /// The limit `frames` frames of `bytes` bytes in total go over.
+    fn exceeded(&self, frames: usize, bytes: usize) -> Option<StreamLimit> {
+        if self.max_frames.is_some_and(|max| frames > max) {
+            Some(StreamLimit::MaxFrames)
+        } else if self.max_total_bytes.is_some_and(|max| bytes > max) {
+            Some(StreamLimit::MaxTotalBytes)
+        } else {
+            None
+        }
+    }
+}
+
+/// The [`StreamOptions`] limit a stream was ended for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamLimit {
+    IdleTimeout,
+    MaxFrames,
+    MaxTotalBytes,
+}
+
+impl StreamLimit {
+    /// The data of the `Err` frame the stream ends with.
+    pub fn message(self) -> &'static [u8] {
+        match self {
+            Self::IdleTimeout => b"stream idle timeout",
+            Self::MaxFrames => b"stream frame limit exceeded",
+            Self::MaxTotalBytes => b"stream byte limit exceeded",
+        }
+    }
+
+    fn frame(self) -> StreamFrame {
+        StreamFrame {
+            status: NrStatus::Err,
+            data: self.message().to_vec(),
+            channel: None,
+            seq: None,
+        }
+    }
+}
+
+/// The limits of a stream, and its plugin, to close the stream in.
+pub(crate) struct Limits {
+    pub(crate) options: StreamOptions,
+    pub(crate) plugin: Weak<LoadedPlugin>,
+}
+
+impl Limits {
+    pub(crate) fn new(options: StreamOptions, plugin: &Arc<LoadedPlugin>) -> Option<Self> {
+        options.is_limited().then(|| Self {
+            options,
+            plugin: Arc::downgrade(plugin),
+        })
+    }
+}
+
+/// Identifies a resumable stream for
+/// [`PluginHandle::resume_stream`](crate::PluginHandle::resume_stream).
+///
+/// Tokens are not derived from the SID in a predictable way, so they can be
+/// handed to clients (for example as an SSE event ID).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResumeToken(u64);
+
+impl ResumeToken {
+    pub(crate) fn new(sid: u64) -> Self {
+        Self(RandomState::new().hash_one(sid))
+    }
+
+    /// The token as a number, to send to a client.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for ResumeToken {
+    fn from(raw: u64) -> Self {
+        Self(raw)
+    }
+}
+
+/// A resumable stream as returned by
+/// [`PluginHandle::call_stream_resumable`](crate::PluginHandle::call_stream_resumable).
+pub struct StreamHandle {
+    pub sid: u64,
+    pub token: ResumeToken,
+    pub receiver: StreamReceiver,
+    pub(crate) plugin: PluginHandle,
+}
+
+impl StreamHandle {
+    /// Cancel the stream and forget its token, as
+    /// [`PluginHandle::cancel_stream`]. A no-op once the stream has ended.
+    pub fn cancel(&self) -> types::Result<()> {
+        self.plugin.plugin.host_ctx.resumable.remove(&self.token);
+        self.plugin.cancel_stream(self.sid)
+    }
+}
+
+impl std::fmt::Debug for StreamHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamHandle")
+            .field("sid", &self.sid)
+            .field("token", &self.token)
+            .field("receiver", &self.receiver)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Alert configuration captured by a stream when it is opened.
+pub(crate) struct LagWatch {
+    pub(crate) plugin: String,
+    pub(crate) alert: StreamLagAlert,
+    pub(crate) hook: StreamLagHook,
+}
+
+#[derive(Default)]
+struct LagState {
+    enqueued: VecDeque<Instant>,
+    max: StreamLag,
+    /// Start of the current episode of excessive lag.
+    over_since: Option<Instant>,
+    /// Whether the hook already fired for the current episode.
+    alerted: bool,
+}
+
+impl LagState {
+    fn lag(&self, now: Instant) -> StreamLag {
+        StreamLag {
+            frames: self.enqueued.len(),
+            oldest: self
+                .enqueued
+                .front()
+                .map_or(Duration::ZERO, |t| now.duration_since(*t)),
+        }
+    }
+}
+
+/// Frames held for a detached resumable stream.
+struct Replay {
+    options: ResumeOptions,
+    buffer: VecDeque<StreamFrame>,
+    /// When the last receiver went away, if none is attached.
+    detached_at: Option<Instant>,
+    /// More than `options.buffer` frames arrived while detached.
+    overflowed: bool,
+    /// The plugin sent its final frame.
+    finished: bool,
+}
+
+impl Replay {
+    fn buffer(&mut self, frame: StreamFrame) {
+        if self.overflowed {
+            return;
+        }
+        if self.buffer.len() == self.options.buffer {
+            self.overflowed = true;
+            self.buffer.clear();
+            return;
+        }
+        self.buffer.push_back(frame);
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        let Some(detached_at) = self.detached_at else {
+            return false;
+        };
+        self.overflowed
+            || (self.finished && self.buffer.is_empty())
+            || now.duration_since(detached_at) >= self.options.ttl
+    }
+}
+
+struct State {
+    lag: LagState,
+    /// Where frames go; `None` while a resumable stream is detached.
+    tx: Option<mpsc::UnboundedSender<StreamFrame>>,
+    /// Incremented each time a resumed receiver replaces the previous one.
+    generation: u64,
+    replay: Option<Replay>,
+    /// Keeps the call in flight until the stream ends or its receiver is
+    /// dropped.
+    in_flight: Option<InFlight>,
+    /// The call in the plugin's metrics, finished by the last frame.
+    call: Option<DetachedCall>,
+    /// Frames sent to the attached receiver and not received yet, counted
+    /// only with a capacity.
+    queued: usize,
+    /// The stream was closed for going over its capacity.
+    overflowed: bool,
+    /// Frames and payload bytes the plugin sent, counted only with limits.
+    frames: usize,
+    bytes: usize,
+    /// The limit the stream was ended for.
+    limited: Option<StreamLimit>,
+    /// Whether the plugin was told about `limited`.
+    limit_handled: bool,
+    dedupe: Option<Dedupe>,
+    /// Frames the plugin has sent, to index them in trace events.
+    #[cfg(feature = "tracing")]
+    sent: u64,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// The plugin the stream was opened on; empty for mux channels, which
+    /// are never registered.
+    owner: Weak<PluginContext>,
+    watch: Option<LagWatch>,
+    /// Whether frames are timestamped to measure consumer lag.
+    track_lag: bool,
+    /// Frames the receiver may fall behind before the stream is closed.
+    capacity: Option<usize>,
+    limits: Option<Limits>,
+}
+
+impl Shared {
+    /// Update the alert episode for the current lag. Returns the lag to alert
+    /// on, if the hook should fire.
+    fn check(&self, state: &mut LagState, now: Instant, lag: StreamLag) -> Option<StreamLag> {
+        let watch = self.watch.as_ref()?;
+        if lag.frames <= watch.alert.max_frames {
+            state.over_since = None;
+            state.alerted = false;
+            return None;
+        }
+        let since = *state.over_since.get_or_insert(now);
+        if !state.alerted && now.duration_since(since) >= watch.alert.window {
+            state.alerted = true;
+            return Some(lag);
+        }
+        None
+    }
+
+    fn fire(&self, sid: u64, lag: Option<StreamLag>) {
+        if let (Some(lag), Some(watch)) = (lag, &self.watch) {
+            (watch.hook)(&watch.plugin, sid, lag);
+        }
+    }
+}
+
+/// Sending half of a stream, held in the pending map.
+#[derive(Clone)]
+pub(crate) struct StreamSender {
+    sid: u64,
+    shared: Arc<Shared>,
+}
+
+impl StreamSender {
+    /// Queue a frame and return the consumer lag including it, or `None`
+    /// if the frame was dropped because no receiver will see it.
+    pub(crate) fn send(&self, mut frame: StreamFrame) -> Option<StreamLag> {
+        let now = self.shared.track_lag.then(Instant::now);
+        let (lag, alert) = {
+            let mut state = self.shared.state.lock();
+            #[cfg(feature = "tracing")]
+            {
+                crate::trace::stream_frame(self.sid, state.sent, frame.status, frame.data.len());
+                state.sent += 1;
+            }
+            let State {
+                lag,
+                tx,
+                replay,
+                in_flight,
+                call,
+                queued,
+                overflowed,
+                frames,
+                bytes,
+                limited,
+                dedupe,
+                ..
+            } = &mut *state;
+            let mut finished = frame.status != NrStatus::Ok;
+            if let (Some(dedupe), false) = (dedupe, finished) {
+                if !dedupe.admit(&mut frame.data, frame.channel.as_deref()) {
+                    // Dropped on purpose: not a frame lost to a closed stream.
+                    return Some(now.map_or_else(StreamLag::default, |now| lag.lag(now)));
+                }
+            }
+            if let (Some(limits), false, Some(_)) = (&self.shared.limits, finished, &tx) {
+                *frames += 1;
+                *bytes += frame.data.len();
+                if let Some(limit) = limits.options.exceeded(*frames, *bytes) {
+                    // The receiver ends with the limit instead of this frame.
+                    frame = limit.frame();
+                    finished = true;
+                    *limited = Some(limit);
+                    *call = None;
+                }
+            }
+            if let Some(replay) = replay {
+                replay.finished |= finished;
+            }
+
+            let (current, alert) = match now {
+                Some(now) => {
+                    lag.enqueued.push_back(now);
+                    let current = lag.lag(now);
+                    lag.max.frames = lag.max.frames.max(current.frames);
+                    lag.max.oldest = lag.max.oldest.max(current.oldest);
+                    (current, self.shared.check(lag, now, current))
+                }
+                None => (StreamLag::default(), None),
+            };
+
+            if let (Some(capacity), Some(_)) = (self.shared.capacity, &tx) {
+                if *queued >= capacity && !finished {
+                    // Too far behind: the receiver ends after what it has.
+                    *tx = None;
+                    *in_flight = None;
+                    *call = None;
+                    *overflowed = true;
+                    if let Some(replay) = replay {
+                        replay.finished = true;
+                    }
+                }
+            }
+            let undelivered = match tx {
+                Some(tx) => {
+                    let undelivered = tx.send(frame).err().map(|e| e.0);
+                    *queued += usize::from(undelivered.is_none());
+                    undelivered
+                }
+                None => Some(frame),
+            };
+            let dropped = undelivered.is_some() && replay.is_none();
+            if let Some(frame) = undelivered {
+                match replay {
+                    Some(replay) => replay.buffer(frame),
+                    // Receiver is gone; nothing is buffered any more.
+                    None => lag.enqueued.clear(),
+                }
+            }
+            if finished {
+                // Close the channel so the receiver ends after this frame.
+                *tx = None;
+                *in_flight = None;
+                if let Some(call) = call.take() {
+                    call.finish();
+                }
+            }
+            ((!dropped).then_some(current), alert)
+        };
+        self.shared.fire(self.sid, alert);
+        lag
+    }
+
+    /// Count the stream as in flight until it ends or its receiver is
+    /// dropped. `call` only records a latency if the stream ends.
+    /// Whether the stream was opened on the plugin with context `plugin`.
+    pub(crate) fn is_owned_by(&self, plugin: &Arc<PluginContext>) -> bool {
+        std::ptr::eq(self.shared.owner.as_ptr(), Arc::as_ptr(plugin))
+    }
+
+    pub(crate) fn track(&self, in_flight: InFlight, call: DetachedCall) {
+        let mut state = self.shared.state.lock();
+        state.in_flight = Some(in_flight);
+        state.call = Some(call);
+    }
+
+    /// End the stream without a final frame; the receiver sees `None` once
+    /// it has drained what was sent.
+    pub(crate) fn close(&self) {
+        let mut state = self.shared.state.lock();
+        state.tx = None;
+        state.in_flight = None;
+        state.call = None;
+        if let Some(replay) = &mut state.replay {
+            replay.finished = true;
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamSender")
+            .field("sid", &self.sid)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A receiver for streaming responses.
+///
+/// The last frame has a status other than `Ok`; the receiver yields `None`
+/// after it.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+/// # use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+/// # let mut host = NylonRingHost::new();
+/// # host.load_static("mock", testing::mock_plugin())?;
+/// let plugin = host.plugin("mock").unwrap();
+/// let (_sid, mut rx) = plugin.call_stream("words", b"a b").await?;
+///
+/// assert_eq!(rx.recv().await.unwrap().data, b"a");
+/// assert_eq!(rx.recv().await.unwrap().data, b"b");
+/// assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+/// assert!(rx.recv().await.is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub struct StreamReceiver {
+    sid: u64,
+    generation: u64,
+    rx: mpsc::UnboundedReceiver<StreamFrame>,
+    shared: Arc<Shared>,
+}
+
+impl StreamReceiver {
+    /// Receive the next frame, or `None` once the stream is finished.
+    ///
+    /// With an idle timeout in its [`StreamOptions`], waits at most that
+    /// long, then ends the stream with a [`StreamLimit::IdleTimeout`] frame.
+    pub async fn recv(&mut self) -> Option<StreamFrame> {
+        let idle_timeout = self
+            .shared
+            .limits
+            .as_ref()
+            .and_then(|l| l.options.idle_timeout);
+        let frame = match idle_timeout {
+            Some(idle_timeout) => match rt::timeout(idle_timeout, self.rx.recv()).await {
+                Some(frame) => frame?,
+                None => {
+                    let frame = self.idle_out();
+                    self.end_if_limited();
+                    return Some(frame);
+                }
+            },
+            None => self.rx.recv().await?,
+        };
+        self.received();
+        self.end_if_limited();
+        Some(frame)
+    }
+
+    /// Receive a frame if one is buffered.
+    pub fn try_recv(&mut self) -> Result<StreamFrame, TryRecvError> {
+        let frame = self.rx.try_recv().map_err(|e| match e {
+            mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
+            mpsc::error::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })?;
+        self.received();
+        self.end_if_limited();
+        Ok(frame)
+    }
+
+    /// The current consumer lag. Always zero unless the stream is tracked;
+    /// see [`NylonRingHost::on_stream_lag`](crate::NylonRingHost::on_stream_lag).
+    pub fn lag(&self) -> StreamLag {
+        self.shared.state.lock().lag.lag(Instant::now())
+    }
+
+    /// The largest lag observed over the life of the stream.
+    pub fn max_lag(&self) -> StreamLag {
+        self.shared.state.lock().lag.max
+    }
+
+    /// A handle for attaching a new receiver later, if the stream is
+    /// resumable.
+    pub(crate) fn resume_handle(&self) -> ResumeHandle {
+        ResumeHandle {
+            sid: self.sid,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Whether the stream was closed because this receiver fell behind by
+    /// the host's stream capacity (see
+    /// [`NylonRingHostBuilder::stream_capacity`](crate::NylonRingHostBuilder::stream_capacity)).
+    /// The frames sent after that are lost.
+    pub fn overflowed(&self) -> bool {
+        self.shared.state.lock().overflowed
+    }
+
+    /// The [`StreamOptions`] limit the stream was ended for, if any.
+    pub fn limited(&self) -> Option<StreamLimit> {
+        self.shared.state.lock().limited
+    }
+
+    /// Frames dropped as duplicates or replays so far. Always zero unless
+    /// the stream was opened with [`StreamOptions::dedupe`].
+    pub fn dedupe_stats(&self) -> DedupeStats {
+        let state = self.shared.state.lock();
+        state
+            .dedupe
+            .as_ref()
+            .map_or_else(DedupeStats::default, |dedupe| dedupe.stats)
+    }
+
+    /// End the stream for having been idle too long. Frames that arrive
+    /// meanwhile are dropped.
+    fn idle_out(&mut self) -> StreamFrame {
+        {
+            let mut state = self.shared.state.lock();
+            state.tx = None;
+            state.in_flight = None;
+            state.call = None;
+            state.queued = 0;
+            state.lag.enqueued.clear();
+            state.limited.get_or_insert(StreamLimit::IdleTimeout);
+        }
+        while self.rx.try_recv().is_ok() {}
+        StreamLimit::IdleTimeout.frame()
+    }
+
+    /// Once the stream is over a limit, remove it from the host and close it
+    /// in the plugin.
+    fn end_if_limited(&self) {
+        let Some(limits) = &self.shared.limits else {
+            return;
+        };
+        {
+            let mut state = self.shared.state.lock();
+            if state.limited.is_none() || state.limit_handled {
+                return;
+            }
+            state.limit_handled = true;
+        }
+        if let Some(plugin) = limits.plugin.upgrade() {
+            PluginHandle { plugin }.end_stream(self.sid);
+        }
+    }
+
+    fn received(&self) {
+        if !self.shared.track_lag && self.shared.capacity.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        let alert = {
+            let mut state = self.shared.state.lock();
+            state.queued = state.queued.saturating_sub(1);
+            if !self.shared.track_lag {
+                return;
+            }
+            state.lag.enqueued.pop_front();
+            let lag = state.lag.lag(now);
+            self.shared.check(&mut state.lag, now, lag)
+        };
+        self.shared.fire(self.sid, alert);
+    }
+}
+
+impl Drop for StreamReceiver {
+    fn drop(&mut self) {
+        self.end_if_limited();
+        let mut state = self.shared.state.lock();
+        let State {
+            tx,
+            generation,
+            replay,
+            in_flight,
+            call,
+            queued,
+            ..
+        } = &mut *state;
+        // Only detach if this receiver is the one attached; a resumed
+        // receiver replaces the previous one.
+        if *generation != self.generation {
+            return;
+        }
+        *queued = 0;
+        // Nobody is waiting for the rest of the stream.
+        *in_flight = None;
+        *call = None;
+        let Some(replay) = replay.as_mut() else {
+            return;
+        };
+        *tx = None;
+        replay.detached_at = Some(Instant::now());
+        // Frames queued but never received are replayed first.
+        while let Ok(frame) = self.rx.try_recv() {
+            replay.buffer(frame);
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamReceiver")
+            .field("sid", &self.sid)
+            .field("lag", &self.lag())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Create a stream channel, lag-tracked if `watch` or `resume` is set. With
+/// `resume`, the stream keeps buffering when its receiver is dropped. With
+/// `capacity`, it is closed once that many frames wait for the receiver.
+/// With `limits`, it ends once it goes over one. With `dedupe`, repeated
+/// frames are dropped.
+pub(crate) fn channel(
+    sid: u64,
+    owner: Weak<PluginContext>,
+    watch: Option<LagWatch>,
+    resume: Option<ResumeOptions>,
+    capacity: Option<usize>,
+    limits: Option<Limits>,
+    dedupe: Option<DedupeOptions>,
+) -> (StreamSender, StreamReceiver) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let track_lag = watch.is_some() || resume.is_some();
+    let shared = Arc::new(Shared {
+        owner,
+        state: Mutex::new(State {
+            lag: LagState::default(),
+            tx: Some(tx),
+            generation: 0,
+            replay: resume.map(|options| Replay {
+                options,
+                buffer: VecDeque::new(),
+                detached_at: None,
+                overflowed: false,
+                finished: false,
+            }),
+            in_flight: None,
+            call: None,
+            queued: 0,
+            overflowed: false,
+            frames: 0,
+            bytes: 0,
+            limited: None,
+            limit_handled: false,
+            dedupe: dedupe.map(Dedupe::new),
+            #[cfg(feature = "tracing")]
+            sent: 0,
+        }),
+        watch,
+        track_lag,
+        capacity,
+        limits,
+    });
+    (
+        StreamSender {
+            sid,
+            shared: shared.clone(),
+        },
+        StreamReceiver {
+            sid,
+            generation: 0,
+            rx,
+            shared,
+        },
+    )
+}
+
+/// A detachable resumable stream, kept by the host under its token.
+pub(crate) struct ResumeHandle {
+    sid: u64,
+    shared: Arc<Shared>,
+}
+
+impl ResumeHandle {
+    /// Attach a new receiver, replaying buffered frames into it. Returns
+    /// `None` if the stream has expired.
+    pub(crate) fn resume(&self) -> Option<StreamReceiver> {
+        let mut state = self.shared.state.lock();
+        let State {
+            tx,
+            generation,
+            replay,
+            queued,
+            ..
+        } = &mut *state;
+        let replay = replay.as_mut()?;
+        if replay.expired(Instant::now()) {
+            return None;
+        }
+
+        let (new_tx, rx) = mpsc::unbounded_channel();
+        *queued = replay.buffer.len();
+        for frame in replay.buffer.drain(..) {
+            let _ = new_tx.send(frame);
+        }
+        replay.detached_at = None;
+        // A finished stream gets no more frames; the receiver ends after the
+        // replay.
+        *tx = (!replay.finished).then_some(new_tx);
+        *generation += 1;
+        let generation = *generation;
+        drop(state);
+
+        Some(StreamReceiver {
+            sid: self.sid,
+            generation,
+            rx,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Whether the stream can no longer be resumed.
+    pub(crate) fn expired(&self, now: Instant) -> bool {
+        let state = self.shared.state.lock();
+        state
+            .replay
+            .as_ref()
+            .is_none_or(|replay| replay.expired(now))
+    }
+}
@@ -0,0 +1,94 @@
+//! Strict mode: plugin mistakes the host otherwise drops silently.
+//!
+//! Results for calls nobody waits on, frames for streams whose receiver is
+//! gone and state written under SIDs the host never issued are ignored by
+//! default, which is what production wants but hides plugin bugs. Under
+//! [`NylonRingHostBuilder::strict_mode`](crate::NylonRingHostBuilder::strict_mode)
+//! each one is logged, counted in
+//! [`HostMetricsSnapshot::strict_violations`](crate::HostMetricsSnapshot::strict_violations)
+//! and reported as [`TraceEvent::StrictViolation`], and fails the call it
+//! belongs to where that call can still fail. Plugins that echo call nonces
+//! have mismatches reported this way even without strict mode (see
+//! [`StrictViolation::NonceMismatch`]).
+
+use crate::context::PluginContext;
+use crate::trace::TraceEvent;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+
+/// Unary calls remembered as answered, to tell a second result for a call
+/// from a result for a SID that was never issued.
+const ANSWERED_CAPACITY: usize = 1024;
+
+/// Error code of the error frame a call fails with under strict mode.
+pub(crate) const STRICT_ERROR_CODE: u32 = 500;
+
+/// A plugin mistake caught under strict mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum StrictViolation {
+    /// A result for a SID the host is not waiting on. This includes
+    /// results that come after the host stopped waiting, on a timeout or a
+    /// cancelled stream.
+    #[error("result for a SID the host is not waiting on")]
+    UnknownSid,
+    /// A second result for a unary call.
+    #[error("second result for a unary call")]
+    DuplicateResult,
+    /// A frame for a stream whose receiver was dropped.
+    #[error("frame for a stream whose receiver was dropped")]
+    StreamClosed,
+    /// State written under a SID the host never issued.
+    #[error("state written under a SID the host never issued")]
+    StateForUnknownSid,
+    /// A unary result sent while the plugin was handling a different SID
+    /// on the same thread, likely under the wrong SID.
+    #[error("unary result sent while handling another SID")]
+    ForeignSid,
+    /// A unary result that does not start with the nonce of the call it
+    /// names, from a plugin that declared `ECHOES_NONCE_KEY`. Reported and
+    /// dropped whether or not strict mode is on.
+    #[error("result does not echo the nonce of its call")]
+    NonceMismatch,
+}
+
+/// The unary calls answered most recently, kept under strict mode only.
+#[derive(Default)]
+pub(crate) struct Answered(Mutex<VecDeque<u64>>);
+
+impl Answered {
+    pub(crate) fn insert(&self, sid: u64) {
+        let mut answered = self.0.lock();
+        if answered.len() == ANSWERED_CAPACITY {
+            answered.pop_front();
+        }
+        answered.push_back(sid);
+    }
+
+    pub(crate) fn contains(&self, sid: u64) -> bool {
+        self.0.lock().contains(&sid)
+    }
+
+    /// The violation a result for `sid`, which nobody waits on, amounts to.
+    pub(crate) fn classify(&self, sid: u64) -> StrictViolation {
+        if self.contains(sid) {
+            StrictViolation::DuplicateResult
+        } else {
+            StrictViolation::UnknownSid
+        }
+    }
+}
+
+/// Log, count and trace `violation` by `plugin` for `sid`.
+#[cold]
+pub(crate) fn report(plugin: &PluginContext, sid: u64, violation: StrictViolation) {
+    log::warn!(
+        "plugin {:?} misbehaved: {violation} (sid {sid})",
+        plugin.name
+    );
+    plugin.host.metrics.record_strict_violation();
+    plugin.host.tracer.emit(TraceEvent::StrictViolation {
+        plugin: &plugin.name,
+        sid,
+        violation,
+    });
+}
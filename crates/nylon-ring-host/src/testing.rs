@@ -0,0 +1,143 @@
+//! A mock plugin for exercising the host without building a shared library.
+//!
+//! [`mock_plugin`] is registered with
+//! [`NylonRingHost::load_static`](crate::NylonRingHost::load_static) and
+//! implements these entries:
+//!
+//! | Entry      | Behaviour                                                        |
+//! |------------|------------------------------------------------------------------|
+//! | `echo`     | Responds with the payload.                                       |
+//! | `words`    | Streams each space-separated word of the payload, then ends.     |
+//! | `open`     | Opens a stream without sending anything.                         |
+//! | `forward`  | Payload `target:data`; dispatches `echo` with `data` to the plugin loaded as `target` and responds with its response. |
+//! | `remember` | Stores the payload in the call's state and responds with what it reads back. |
+//!
+//! Stream data sent to an open stream is echoed back as a frame, and closing
+//! it ends the stream. Other entries are rejected with `Unsupported`.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), nylon_ring_host::NylonRingHostError> {
+//! use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+//!
+//! let mut host = NylonRingHost::new();
+//! host.load_static("mock", testing::mock_plugin())?;
+//!
+//! let plugin = host.plugin("mock").unwrap();
+//! let (status, data) = plugin.call_response("echo", b"hello").await?;
+//! assert_eq!(status, NrStatus::Ok);
+//! assert_eq!(data, b"hello");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The mock keeps the host context from its most recent `init` in a static,
+//! so load it into one host at a time.
+
+use crate::NylonRingHost;
+use nylon_ring::{
+    NrBytes, NrHostVTable, NrPluginInfo, NrPluginVTable, NrStatus, NrStr, NrVec, NR_ABI_VERSION,
+};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+static HOST_CTX: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
+
+static VTABLE: NrPluginVTable = NrPluginVTable {
+    init: Some(init),
+    handle: Some(handle),
+    shutdown: None,
+    stream_data: Some(stream_data),
+    stream_close: Some(stream_close),
+    health: None,
+    handle_req: None,
+};
+
+static INFO: NrPluginInfo = NrPluginInfo {
+    abi_version: NR_ABI_VERSION,
+    struct_size: std::mem::size_of::<NrPluginInfo>() as u32,
+    name: NrStr {
+        ptr: "mock".as_ptr(),
+        len: 4,
+    },
+    version: NrStr {
+        ptr: env!("CARGO_PKG_VERSION").as_ptr(),
+        len: env!("CARGO_PKG_VERSION").len() as u32,
+    },
+    plugin_ctx: std::ptr::null_mut(),
+    vtable: &VTABLE,
+};
+
+/// The mock plugin. See the [module docs](self) for its entries.
+pub fn mock_plugin() -> &'static NrPluginInfo {
+    &INFO
+}
+
+unsafe extern "C" fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+    HOST_CTX.store(host_ctx, Ordering::Release);
+    HOST_VTABLE.store(host_vtable as *mut _, Ordering::Release);
+    NrStatus::Ok
+}
+
+unsafe fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        status,
+        NrVec::from_slice(data),
+    );
+}
+
+unsafe extern "C" fn handle(entry: NrStr, sid: u64, payload: NrBytes) -> NrStatus {
+    let payload = payload.as_slice();
+    match entry.as_bytes() {
+        b"echo" => send(sid, NrStatus::Ok, payload),
+        b"words" => {
+            for word in payload.split(|b| *b == b' ').filter(|w| !w.is_empty()) {
+                send(sid, NrStatus::Ok, word);
+            }
+            send(sid, NrStatus::StreamEnd, &[]);
+        }
+        b"open" => {}
+        b"forward" => {
+            let Some(at) = payload.iter().position(|b| *b == b':') else {
+                return NrStatus::Invalid;
+            };
+            let Ok(target) = std::str::from_utf8(&payload[..at]) else {
+                return NrStatus::Invalid;
+            };
+            let ctx = HOST_CTX.load(Ordering::Acquire);
+            let (status, spawned) =
+                NylonRingHost::dispatch_spawn(ctx, target, "echo", &payload[at + 1..]).into_tuple();
+            if status != NrStatus::Ok {
+                return status;
+            }
+            // `echo` responds before returning from `handle`.
+            match NylonRingHost::try_take_dispatch_result(ctx, spawned) {
+                Some((status, data)) => send(sid, status, &data),
+                None => return NrStatus::Err,
+            }
+        }
+        b"remember" => {
+            let ctx = HOST_CTX.load(Ordering::Acquire);
+            let ext = &*NylonRingHost::get_host_ext(ctx);
+            let key = NrStr::new("payload");
+            (ext.set_state)(ctx, sid, key, NrBytes::from_slice(payload));
+            send(sid, NrStatus::Ok, &ext.read_state(ctx, sid, "payload"));
+        }
+        _ => return NrStatus::Unsupported,
+    }
+    NrStatus::Ok
+}
+
+unsafe extern "C" fn stream_data(sid: u64, data: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, data.as_slice());
+    NrStatus::Ok
+}
+
+unsafe extern "C" fn stream_close(sid: u64) -> NrStatus {
+    send(sid, NrStatus::StreamEnd, &[]);
+    NrStatus::Ok
+}
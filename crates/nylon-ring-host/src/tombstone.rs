@@ -0,0 +1,127 @@
+//! Entries removed by a reload.
+//!
+//! Plugins built with `define_plugin!` publish the entries they serve under
+//! `ENTRIES_KEY` during `init`. A reload compares the old and new lists, and
+//! calls to an entry the new instance dropped fail with
+//! [`NylonRingHostError::EntryRemovedInVersion`] for a grace period rather
+//! than with whatever status the plugin gives an unknown entry.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::time::{Duration, Instant};
+
+/// How long calls to a removed entry report the removal, unless configured
+/// otherwise.
+pub(crate) const DEFAULT_TOMBSTONE_GRACE: Duration = Duration::from_secs(60);
+
+/// The entries in a published list, one per line.
+pub(crate) fn parse(list: &[u8]) -> FxHashSet<String> {
+    String::from_utf8_lossy(list)
+        .lines()
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The entries `new` adds to `old` and the ones it drops, each sorted.
+pub(crate) fn diff(old: &FxHashSet<String>, new: &FxHashSet<String>) -> (Vec<String>, Vec<String>) {
+    let mut added: Vec<String> = new.difference(old).cloned().collect();
+    let mut removed: Vec<String> = old.difference(new).cloned().collect();
+    added.sort();
+    removed.sort();
+    (added, removed)
+}
+
+#[derive(Clone)]
+struct Tombstone {
+    old_version: String,
+    new_version: String,
+    until: Instant,
+}
+
+/// The removed entries of one plugin instance.
+#[derive(Default)]
+pub(crate) struct Tombstones(FxHashMap<String, Tombstone>);
+
+impl Tombstones {
+    /// The tombstones after a reload from `old_version` to `new_version`
+    /// removed `removed`. Unexpired ones from earlier reloads are kept,
+    /// unless the new instance serves their entry again.
+    pub(crate) fn after_reload(
+        &self,
+        removed: &[String],
+        provided: &FxHashSet<String>,
+        (old_version, new_version): (&str, &str),
+        grace: Duration,
+    ) -> Self {
+        let now = Instant::now();
+        let mut tombstones: FxHashMap<String, Tombstone> = self
+            .0
+            .iter()
+            .filter(|(entry, tombstone)| tombstone.until > now && !provided.contains(*entry))
+            .map(|(entry, tombstone)| (entry.clone(), tombstone.clone()))
+            .collect();
+        for entry in removed {
+            let tombstone = Tombstone {
+                old_version: old_version.to_string(),
+                new_version: new_version.to_string(),
+                until: now + grace,
+            };
+            tombstones.insert(entry.clone(), tombstone);
+        }
+        Self(tombstones)
+    }
+
+    /// Fail if `entry` was removed less than the grace period ago.
+    pub(crate) fn check(&self, entry: &str) -> Result<()> {
+        match self.0.get(entry) {
+            Some(tombstone) if tombstone.until > Instant::now() => {
+                Err(NylonRingHostError::EntryRemovedInVersion {
+                    entry: entry.to_string(),
+                    old_version: tombstone.old_version.clone(),
+                    new_version: tombstone.new_version.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(entries: &[&str]) -> FxHashSet<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tombstones_expire_and_clear_on_return() {
+        let v2 = set(&["b"]);
+        let (added, removed) = diff(&set(&["a", "b", "c"]), &v2);
+        assert!(added.is_empty());
+        assert_eq!(removed, ["a", "c"]);
+
+        let grace = Duration::from_secs(60);
+        let tombstones = Tombstones::default().after_reload(&removed, &v2, ("1", "2"), grace);
+        assert!(matches!(
+            tombstones.check("a"),
+            Err(NylonRingHostError::EntryRemovedInVersion { old_version, new_version, .. })
+                if old_version == "1" && new_version == "2"
+        ));
+        assert!(tombstones.check("b").is_ok());
+
+        // `c` comes back in version 3; `a` stays removed since version 2.
+        let v3 = set(&["b", "c"]);
+        let tombstones = tombstones.after_reload(&[], &v3, ("2", "3"), grace);
+        assert!(tombstones.check("c").is_ok());
+        assert!(matches!(
+            tombstones.check("a"),
+            Err(NylonRingHostError::EntryRemovedInVersion { old_version, .. }) if old_version == "1"
+        ));
+
+        let expired = Tombstones::default().after_reload(&removed, &v2, ("1", "2"), Duration::ZERO);
+        assert!(expired.check("a").is_ok());
+    }
+}
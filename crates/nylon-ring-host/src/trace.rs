@@ -0,0 +1,182 @@
+//! Request/response tracing hooks.
+//!
+//! A single hook can be installed per host. Call sites check an atomic flag
+//! first, so an unset hook costs one branch.
+//!
+//! With the `tracing` feature, calls also run in `tracing` spans and results
+//! and stream frames are logged as events, all carrying the SID so plugin
+//! logs can be joined with them. Without it, `CallSpan` and the event
+//! functions compile to nothing.
+
+use crate::strict::StrictViolation;
+use nylon_ring::NrStatus;
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A traced event. Borrowed fields are only valid for the duration of the
+/// hook invocation.
+#[derive(Debug, Clone, Copy)]
+pub enum TraceEvent<'a> {
+    /// A call was issued to a plugin entry point.
+    CallStart {
+        sid: u64,
+        plugin: &'a str,
+        entry: &'a str,
+    },
+    /// A call completed. `status` is the response status, or the status the
+    /// plugin's `handle` returned when it rejected the call. `bytes` is the
+    /// response payload size.
+    CallEnd {
+        sid: u64,
+        status: NrStatus,
+        bytes: usize,
+    },
+    /// A stream frame was delivered to the host.
+    StreamFrame { sid: u64, status: NrStatus },
+    /// A reload changed the entries a plugin serves. Both lists are sorted.
+    EntriesChanged {
+        plugin: &'a str,
+        added: &'a [String],
+        removed: &'a [String],
+    },
+    /// A plugin mistake caught under strict mode.
+    StrictViolation {
+        plugin: &'a str,
+        sid: u64,
+        violation: StrictViolation,
+    },
+}
+
+/// A function observing [`TraceEvent`]s.
+pub type TraceHook = Arc<dyn Fn(TraceEvent<'_>) + Send + Sync>;
+
+/// Holder for the host's trace hook.
+pub(crate) struct Tracer {
+    enabled: AtomicBool,
+    hook: RwLock<Option<TraceHook>>,
+}
+
+impl Tracer {
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            hook: RwLock::new(None),
+        }
+    }
+
+    pub(crate) fn set(&self, hook: Option<TraceHook>) {
+        let mut slot = self.hook.write();
+        self.enabled.store(hook.is_some(), Ordering::Release);
+        *slot = hook;
+    }
+
+    #[inline(always)]
+    pub(crate) fn emit(&self, event: TraceEvent<'_>) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.emit_slow(event);
+        }
+    }
+
+    #[cold]
+    fn emit_slow(&self, event: TraceEvent<'_>) {
+        // Clone out so the hook runs without holding the lock.
+        let hook = self.hook.read().clone();
+        if let Some(hook) = hook {
+            hook(event);
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod spans {
+    use nylon_ring::NrStatus;
+    use std::time::Instant;
+    use tracing::field::{debug, Empty};
+
+    /// Span around one call to a plugin, from issue to completion.
+    #[derive(Clone)]
+    pub(crate) struct CallSpan {
+        span: tracing::Span,
+        start: Instant,
+    }
+
+    impl CallSpan {
+        pub(crate) fn new(
+            call: &'static str,
+            plugin: &str,
+            entry: &str,
+            sid: u64,
+            bytes: usize,
+        ) -> Self {
+            let span = tracing::debug_span!(
+                "nylon_ring.call",
+                call,
+                plugin,
+                entry,
+                sid,
+                payload_size = bytes,
+                status = Empty,
+                response_size = Empty,
+                latency_us = Empty,
+            );
+            Self {
+                span,
+                start: Instant::now(),
+            }
+        }
+
+        /// Run `f`, typically the plugin's `handle`, inside the span.
+        pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+            self.span.in_scope(f)
+        }
+
+        /// Record how the call ended.
+        pub(crate) fn finish(&self, status: NrStatus, bytes: usize) {
+            self.span.record("status", debug(status));
+            self.span.record("response_size", bytes);
+            self.span
+                .record("latency_us", self.start.elapsed().as_micros() as u64);
+        }
+    }
+
+    /// A result delivered through `send_result`.
+    pub(crate) fn result(sid: u64, status: NrStatus, bytes: usize) {
+        tracing::debug!(sid, status = ?status, size = bytes, "nylon_ring.result");
+    }
+
+    /// The `index`th frame of a stream, counting from 0.
+    pub(crate) fn stream_frame(sid: u64, index: u64, status: NrStatus, bytes: usize) {
+        tracing::trace!(sid, index, status = ?status, size = bytes, "nylon_ring.frame");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod spans {
+    use nylon_ring::NrStatus;
+
+    #[derive(Clone)]
+    pub(crate) struct CallSpan;
+
+    impl CallSpan {
+        #[inline(always)]
+        pub(crate) fn new(_: &'static str, _: &str, _: &str, _: u64, _: usize) -> Self {
+            Self
+        }
+
+        #[inline(always)]
+        pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+
+        #[inline(always)]
+        pub(crate) fn finish(&self, _: NrStatus, _: usize) {}
+    }
+
+    #[inline(always)]
+    pub(crate) fn result(_: u64, _: NrStatus, _: usize) {}
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) use spans::stream_frame;
+pub(crate) use spans::{result, CallSpan};
@@ -0,0 +1,174 @@
+//! Typed calls.
+//!
+//! The request is encoded with a [`Codec`] and the response, or every `Ok`
+//! frame of a stream, is decoded into a value. See
+//! [`nylon_ring::codec::nr_decode_request`] and
+//! [`nylon_ring::codec::TypedSink`] for the plugin side.
+
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use crate::{PluginHandle, StreamReceiver};
+use nylon_ring::codec::{Codec, CodecError, Json, PayloadCodec};
+use nylon_ring::NrStatus;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// Why a typed stream yielded an error instead of an item.
+#[derive(Debug, thiserror::Error)]
+pub enum TypedStreamError {
+    /// A frame did not decode as an item.
+    #[error("failed to decode stream frame: {0}")]
+    Decode(#[source] CodecError),
+    /// The plugin ended the stream with an error status. `data` is the
+    /// final frame's payload, typically a message.
+    #[error("plugin ended the stream with status: {status:?}")]
+    Plugin { status: NrStatus, data: Vec<u8> },
+}
+
+/// Items decoded from a stream, as returned by
+/// [`PluginHandle::call_typed_stream`].
+pub struct TypedFrameStream<Item, C = Json> {
+    rx: StreamReceiver,
+    codec: C,
+    stop_on_decode_error: bool,
+    done: bool,
+    _item: PhantomData<fn() -> Item>,
+}
+
+impl<Item: DeserializeOwned, C: Codec> TypedFrameStream<Item, C> {
+    /// End the stream at the first frame that fails to decode, after
+    /// yielding the error. By default decoding continues with the next
+    /// frame.
+    pub fn stop_on_decode_error(mut self, stop: bool) -> Self {
+        self.stop_on_decode_error = stop;
+        self
+    }
+
+    /// The next item, or `None` once the stream has ended.
+    ///
+    /// A `StreamEnd` frame ends the stream; its payload, if any, is decoded
+    /// as a last item. Any other final status is yielded as
+    /// [`TypedStreamError::Plugin`] before the stream ends.
+    pub async fn next(&mut self) -> Option<std::result::Result<Item, TypedStreamError>> {
+        if self.done {
+            return None;
+        }
+        let Some(frame) = self.rx.recv().await else {
+            self.done = true;
+            return None;
+        };
+        match frame.status {
+            NrStatus::Ok => {}
+            NrStatus::StreamEnd => {
+                self.done = true;
+                if frame.data.is_empty() {
+                    return None;
+                }
+            }
+            status => {
+                self.done = true;
+                return Some(Err(TypedStreamError::Plugin {
+                    status,
+                    data: frame.data,
+                }));
+            }
+        }
+        let item = self.codec.decode(&frame.data).map_err(|e| {
+            self.done |= self.stop_on_decode_error;
+            TypedStreamError::Decode(e)
+        });
+        Some(item)
+    }
+
+    /// The underlying receiver, for lag tracking.
+    pub fn receiver(&self) -> &StreamReceiver {
+        &self.rx
+    }
+}
+
+impl<Item, C> std::fmt::Debug for TypedFrameStream<Item, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedFrameStream")
+            .field("rx", &self.rx)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginHandle {
+    /// Call a unary entry with a JSON request, decoding the response as
+    /// JSON. The request is prefixed with the [`PayloadCodec`] tag, for
+    /// plugins that read it with [`nylon_ring::codec::nr_decode_request`].
+    ///
+    /// Failures of the call itself are returned as usual, e.g.
+    /// [`NylonRingHostError::PluginHandleFailed`]; a response that does not
+    /// decode is [`NylonRingHostError::Decode`].
+    pub async fn call_typed<Req, Resp>(&self, entry: &str, request: &Req) -> Result<Resp>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        self.call_typed_with(entry, request, PayloadCodec::Json)
+            .await
+    }
+
+    /// Like [`call_typed`](PluginHandle::call_typed), with `codec` for both
+    /// the request and the response.
+    pub async fn call_typed_with<Req, Resp>(
+        &self,
+        entry: &str,
+        request: &Req,
+        codec: PayloadCodec,
+    ) -> Result<Resp>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let payload = codec
+            .encode_request(request)
+            .map_err(NylonRingHostError::Encode)?;
+        let (_, bytes) = self.call_response(entry, &payload).await?;
+        codec
+            .decode(&bytes)
+            .map_err(|source| NylonRingHostError::Decode { bytes, source })
+    }
+
+    /// Call a streaming entry with a JSON-encoded request, decoding every
+    /// frame as JSON.
+    pub async fn call_typed_stream<Req, Item>(
+        &self,
+        entry: &str,
+        request: &Req,
+    ) -> Result<TypedFrameStream<Item>>
+    where
+        Req: Serialize + ?Sized,
+        Item: DeserializeOwned,
+    {
+        self.call_typed_stream_with(entry, request, Json).await
+    }
+
+    /// Like [`call_typed_stream`](PluginHandle::call_typed_stream), with
+    /// `codec` for both the request and the frames.
+    pub async fn call_typed_stream_with<Req, Item, C>(
+        &self,
+        entry: &str,
+        request: &Req,
+        codec: C,
+    ) -> Result<TypedFrameStream<Item, C>>
+    where
+        Req: Serialize + ?Sized,
+        Item: DeserializeOwned,
+        C: Codec,
+    {
+        let payload = codec.encode(request).map_err(NylonRingHostError::Encode)?;
+        let (_sid, rx) = self.call_stream(entry, &payload).await?;
+        Ok(TypedFrameStream {
+            rx,
+            codec,
+            stop_on_decode_error: false,
+            done: false,
+            _item: PhantomData,
+        })
+    }
+}
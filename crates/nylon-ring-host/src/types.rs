@@ -1,11 +1,15 @@
 //! Type definitions and aliases for the nylon-ring-host crate.
 
+use crate::context::InFlight;
 use crate::error::NylonRingHostError;
+use crate::rt::Instant;
+use crate::stream::StreamSender;
 use dashmap::DashMap;
 use nylon_ring::NrStatus;
 use rustc_hash::FxBuildHasher;
 use std::collections::HashMap;
-use tokio::sync::{mpsc, oneshot};
+use std::ops::{Deref, DerefMut};
+use tokio::sync::oneshot;
 
 /// Result type alias for this crate.
 pub type Result<T> = std::result::Result<T, NylonRingHostError>;
@@ -15,27 +19,94 @@ pub type Result<T> = std::result::Result<T, NylonRingHostError>;
 pub(crate) enum Pending {
     #[allow(dead_code)]
     Unary(oneshot::Sender<(NrStatus, Vec<u8>)>),
-    Stream(mpsc::UnboundedSender<StreamFrame>),
+    /// A call started with `dispatch_spawn`, in flight until answered.
+    Dispatched(oneshot::Sender<(NrStatus, Vec<u8>)>, InFlight),
+    Stream(StreamSender),
 }
 
 /// A frame in a streaming response.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StreamFrame {
     pub status: NrStatus,
     pub data: Vec<u8>,
+    /// The sub-channel the plugin sent the frame on, if any. See
+    /// [`PluginHandle::call_stream_mux`](crate::PluginHandle::call_stream_mux).
+    pub channel: Option<String>,
+    /// The frame's sequence number, if the plugin sent it with
+    /// `send_result_seq`. See
+    /// [`PluginHandle::call_stream_ordered`](crate::PluginHandle::call_stream_ordered).
+    pub seq: Option<u64>,
+}
+
+/// A panic caught inside a plugin entry point.
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    /// Session ID of the call that panicked.
+    pub sid: u64,
+    /// Entry name the call was routed to.
+    pub entry: String,
+    /// Panic message.
+    pub message: String,
+    /// Backtrace captured by the plugin's panic hook. Empty unless
+    /// `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE` is set in the plugin's process.
+    pub backtrace: String,
 }
 
-/// A receiver for streaming responses.
-pub type StreamReceiver = mpsc::UnboundedReceiver<StreamFrame>;
+/// A pending request and when it was registered.
+pub(crate) type TimedPending = (Pending, Instant);
 
 /// Fast hash map for pending requests using FxHash.
-pub(crate) type FastPendingMap = DashMap<u64, Pending, FxBuildHasher>;
+#[cfg(not(feature = "slab-pending"))]
+pub(crate) type FastPendingMap = DashMap<u64, TimedPending, FxBuildHasher>;
+
+/// Pending requests in slots indexed by SID.
+#[cfg(feature = "slab-pending")]
+pub(crate) type FastPendingMap = crate::pending_slab::PendingSlab;
 
 /// Fast hash map for per-SID state using FxHash.
-pub(crate) type FastStateMap = DashMap<u64, HashMap<String, Vec<u8>>, FxBuildHasher>;
+pub(crate) type FastStateMap = DashMap<u64, SidState, FxBuildHasher>;
+
+/// The state stored under one SID, and when the first value was.
+#[derive(Debug)]
+pub(crate) struct SidState {
+    pub(crate) values: HashMap<String, Vec<u8>>,
+    pub(crate) since: Instant,
+}
+
+impl From<HashMap<String, Vec<u8>>> for SidState {
+    fn from(values: HashMap<String, Vec<u8>>) -> Self {
+        Self {
+            values,
+            since: Instant::now(),
+        }
+    }
+}
+
+impl Default for SidState {
+    fn default() -> Self {
+        HashMap::new().into()
+    }
+}
+
+impl Deref for SidState {
+    type Target = HashMap<String, Vec<u8>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl DerefMut for SidState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.values
+    }
+}
 
 /// Optional oneshot sender for unary responses.
 pub(crate) type UnarySender = Option<oneshot::Sender<(NrStatus, Vec<u8>)>>;
 
+/// Receiving half of a unary response.
+pub(crate) type UnaryReceiver = oneshot::Receiver<(NrStatus, Vec<u8>)>;
+
 /// Optional result slot for ultra-fast unary responses.
 pub(crate) type UnaryResultSlot = Option<(NrStatus, Vec<u8>)>;
@@ -0,0 +1,238 @@
+//! WebAssembly plugins.
+//!
+//! A module must export:
+//!
+//! - `memory`
+//! - `nr_alloc(len: i32) -> i32`, returning a buffer the host writes call
+//!   arguments into. The buffers belong to the module afterwards.
+//! - `nr_handle(entry_ptr: i32, entry_len: i32, sid: i64, payload_ptr: i32,
+//!   payload_len: i32) -> i32`, returning an `NrStatus`.
+//!
+//! and may export `nr_init() -> i32`, `nr_shutdown()`,
+//! `nr_stream_data(sid: i64, ptr: i32, len: i32) -> i32` and
+//! `nr_stream_close(sid: i64) -> i32` and `nr_health() -> i32`.
+//!
+//! Results are sent through the imported
+//! `env.nr_send_result(sid: i64, status: i32, ptr: i32, len: i32)`, which
+//! feeds the same machinery as the native `send_result`. A module only runs
+//! while the host calls into it, so results must be sent from within one of
+//! its exports. Calls into one module are serialized.
+
+use crate::callbacks::send_result_vec_callback;
+use crate::context::PluginContext;
+use crate::error::NylonRingHostError;
+use crate::types::Result;
+use nylon_ring::{NrStatus, NrVec};
+use parking_lot::Mutex;
+use std::ffi::c_void;
+use std::sync::Arc;
+use wasmtime::{Caller, Engine, Extern, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+struct WasmState {
+    ctx: Arc<PluginContext>,
+}
+
+pub(crate) struct WasmPlugin {
+    store: Mutex<Store<WasmState>>,
+    memory: Memory,
+    alloc: TypedFunc<u32, u32>,
+    handle: TypedFunc<(u32, u32, u64, u32, u32), u32>,
+    stream_data: Option<TypedFunc<(u64, u32, u32), u32>>,
+    stream_close: Option<TypedFunc<u64, u32>>,
+    shutdown: Option<TypedFunc<(), ()>>,
+    health: Option<TypedFunc<(), u32>>,
+    /// Whether the module exports `nr_init`.
+    init: bool,
+}
+
+impl WasmPlugin {
+    /// Compile and instantiate the module at `path` (binary or text format)
+    /// and run its `nr_init`.
+    pub(crate) fn load(path: &str, ctx: Arc<PluginContext>) -> Result<Self> {
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path).map_err(NylonRingHostError::Wasm)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap("env", "nr_send_result", send_result)
+            .map_err(NylonRingHostError::Wasm)?;
+
+        let mut store = Store::new(&engine, WasmState { ctx });
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(NylonRingHostError::Wasm)?;
+
+        let required = |store: &mut Store<WasmState>| -> Option<_> {
+            Some((
+                instance.get_memory(&mut *store, "memory")?,
+                instance.get_typed_func(&mut *store, "nr_alloc").ok()?,
+                instance.get_typed_func(&mut *store, "nr_handle").ok()?,
+            ))
+        };
+        let Some((memory, alloc, handle)) = required(&mut store) else {
+            return Err(NylonRingHostError::MissingRequiredFunctions);
+        };
+
+        let init = optional::<(), u32>(&instance, &mut store, "nr_init");
+        if let Some(init) = &init {
+            let status = init
+                .call(&mut store, ())
+                .map_or(NrStatus::Err, status_from_raw);
+            if status != NrStatus::Ok {
+                return Err(NylonRingHostError::PluginInitFailed {
+                    status,
+                    message: None,
+                });
+            }
+        }
+
+        Ok(Self {
+            stream_data: optional(&instance, &mut store, "nr_stream_data"),
+            stream_close: optional(&instance, &mut store, "nr_stream_close"),
+            shutdown: optional(&instance, &mut store, "nr_shutdown"),
+            health: optional(&instance, &mut store, "nr_health"),
+            init: init.is_some(),
+            store: Mutex::new(store),
+            memory,
+            alloc,
+            handle,
+        })
+    }
+
+    pub(crate) fn handle(&self, entry: &str, sid: u64, payload: &[u8]) -> NrStatus {
+        let mut store = self.store.lock();
+        let result = (|| {
+            let entry_ptr = self.write(&mut store, entry.as_bytes())?;
+            let payload_ptr = self.write(&mut store, payload)?;
+            self.handle.call(
+                &mut *store,
+                (
+                    entry_ptr,
+                    entry.len() as u32,
+                    sid,
+                    payload_ptr,
+                    payload.len() as u32,
+                ),
+            )
+        })();
+        self.status(&store, result)
+    }
+
+    /// Which of the slots in [`PLUGIN_FUNCTIONS`](crate::abi::PLUGIN_FUNCTIONS)
+    /// the module exports.
+    pub(crate) fn provided(&self) -> [bool; 7] {
+        [
+            self.init,
+            true,
+            self.shutdown.is_some(),
+            self.stream_data.is_some(),
+            self.stream_close.is_some(),
+            self.health.is_some(),
+            false,
+        ]
+    }
+
+    pub(crate) fn stream_data(&self, sid: u64, data: &[u8]) -> Option<NrStatus> {
+        let stream_data = self.stream_data.as_ref()?;
+        let mut store = self.store.lock();
+        let result = (|| {
+            let ptr = self.write(&mut store, data)?;
+            stream_data.call(&mut *store, (sid, ptr, data.len() as u32))
+        })();
+        Some(self.status(&store, result))
+    }
+
+    pub(crate) fn stream_close(&self, sid: u64) -> Option<NrStatus> {
+        let stream_close = self.stream_close.as_ref()?;
+        let mut store = self.store.lock();
+        let result = stream_close.call(&mut *store, sid);
+        Some(self.status(&store, result))
+    }
+
+    pub(crate) fn health(&self) -> Option<NrStatus> {
+        let health = self.health.as_ref()?;
+        let mut store = self.store.lock();
+        let result = health.call(&mut *store, ());
+        Some(self.status(&store, result))
+    }
+
+    pub(crate) fn shutdown(&self) {
+        if let Some(shutdown) = &self.shutdown {
+            let mut store = self.store.lock();
+            if let Err(e) = shutdown.call(&mut *store, ()) {
+                log::warn!(
+                    "nylon-ring-host: wasm plugin {:?} trapped in shutdown: {e:#}",
+                    store.data().ctx.name
+                );
+            }
+        }
+    }
+
+    /// Copy `bytes` into a buffer from the module's `nr_alloc`.
+    fn write(&self, store: &mut Store<WasmState>, bytes: &[u8]) -> wasmtime::Result<u32> {
+        let ptr = self.alloc.call(&mut *store, bytes.len() as u32)?;
+        self.memory.write(&mut *store, ptr as usize, bytes)?;
+        Ok(ptr)
+    }
+
+    /// The status an export returned. A trap counts as `Err`.
+    fn status(&self, store: &Store<WasmState>, result: wasmtime::Result<u32>) -> NrStatus {
+        result.map_or_else(
+            |e| {
+                log::warn!(
+                    "nylon-ring-host: wasm plugin {:?} trapped: {e:#}",
+                    store.data().ctx.name
+                );
+                NrStatus::Err
+            },
+            status_from_raw,
+        )
+    }
+}
+
+fn optional<P, R>(
+    instance: &Instance,
+    store: &mut Store<WasmState>,
+    name: &str,
+) -> Option<TypedFunc<P, R>>
+where
+    P: wasmtime::WasmParams,
+    R: wasmtime::WasmResults,
+{
+    instance.get_typed_func(store, name).ok()
+}
+
+/// The `env.nr_send_result` import.
+fn send_result(
+    mut caller: Caller<'_, WasmState>,
+    sid: u64,
+    status: u32,
+    ptr: u32,
+    len: u32,
+) -> wasmtime::Result<()> {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return Err(wasmtime::Error::msg("module does not export memory"));
+    };
+    let data = memory
+        .data(&caller)
+        .get(ptr as usize..)
+        .and_then(|data| data.get(..len as usize))
+        .ok_or_else(|| wasmtime::Error::msg("nr_send_result payload out of bounds"))?
+        .to_vec();
+
+    let ctx = Arc::as_ptr(&caller.data().ctx) as *mut c_void;
+    unsafe { send_result_vec_callback(ctx, sid, status_from_raw(status), NrVec::from_vec(data)) };
+    Ok(())
+}
+
+/// Statuses outside the ABI count as `Err`.
+fn status_from_raw(raw: u32) -> NrStatus {
+    match raw {
+        0 => NrStatus::Ok,
+        2 => NrStatus::Invalid,
+        3 => NrStatus::Unsupported,
+        4 => NrStatus::StreamEnd,
+        6 => NrStatus::Accepted,
+        _ => NrStatus::Err,
+    }
+}
@@ -0,0 +1,137 @@
+//! What each loaded plugin provides is recorded at load time, and calls it
+//! cannot serve name the missing function.
+
+use nylon_ring::{
+    NrBytes, NrHostVTable, NrPluginInfo, NrPluginVTable, NrStatus, NrStr, NR_ABI_VERSION,
+};
+use nylon_ring_host::{testing, NylonRingHost, NylonRingHostError};
+use std::ffi::c_void;
+
+// The mock plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe extern "C" fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe extern "C" fn handle(_entry: NrStr, _sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+/// Only the required functions, built against ABI v1.
+static MINIMAL_VTABLE: NrPluginVTable = NrPluginVTable {
+    init: Some(init),
+    handle: Some(handle),
+    shutdown: None,
+    stream_data: None,
+    stream_close: None,
+    health: None,
+    handle_req: None,
+};
+
+static MINIMAL: NrPluginInfo = NrPluginInfo {
+    abi_version: 1,
+    struct_size: std::mem::size_of::<NrPluginInfo>() as u32,
+    name: NrStr {
+        ptr: "minimal".as_ptr(),
+        len: 7,
+    },
+    version: NrStr {
+        ptr: "0.1.0".as_ptr(),
+        len: 5,
+    },
+    plugin_ctx: std::ptr::null_mut(),
+    vtable: &MINIMAL_VTABLE,
+};
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("minimal", &MINIMAL).unwrap();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_abi_details_record_each_slot() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+
+    let minimal = host.plugin("minimal").unwrap();
+    let details = minimal.abi_details();
+    assert_eq!(details.abi_version, 1);
+    assert_eq!(details.host_abi_version, NR_ABI_VERSION);
+    assert!(!details.wasm);
+    assert_eq!(
+        details.plugin_functions,
+        [
+            ("init", true),
+            ("handle", true),
+            ("shutdown", false),
+            ("stream_data", false),
+            ("stream_close", false),
+            ("health", false),
+            ("handle_req", false),
+        ]
+    );
+    assert_eq!(details.host_tables[0].functions, ["send_result"]);
+
+    let mock = host.plugin("mock").unwrap();
+    let details = mock.abi_details();
+    assert_eq!(details.abi_version, NR_ABI_VERSION);
+    assert!(!details.provides("shutdown"));
+    assert!(details.provides("stream_data"));
+    assert!(details.provides("stream_close"));
+    assert!(details.host_tables[1].functions.contains(&"buf_acquire"));
+}
+
+#[tokio::test]
+async fn test_missing_function_is_named() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let minimal = host.plugin("minimal").unwrap();
+
+    let err = minimal.send_stream_data(1, b"x").unwrap_err();
+    assert!(matches!(
+        &err,
+        NylonRingHostError::MissingFunction { plugin, function: "stream_data" }
+            if plugin == "minimal"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "plugin \"minimal\" does not provide stream_data; see abi_details"
+    );
+    assert_eq!(
+        minimal.close_stream(1).unwrap_err().to_string(),
+        "plugin \"minimal\" does not provide stream_close; see abi_details"
+    );
+    assert!(matches!(
+        minimal.open_duplex("open", b"").await,
+        Err(NylonRingHostError::MissingFunction {
+            function: "stream_data",
+            ..
+        })
+    ));
+
+    // The mock plugin has both, so only the SID is wrong.
+    let mock = host.plugin("mock").unwrap();
+    assert!(matches!(
+        mock.send_stream_data(1, b"x"),
+        Err(NylonRingHostError::UnknownStream(1))
+    ));
+}
+
+#[tokio::test]
+async fn test_describe_lists_every_plugin() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let description = host.describe();
+
+    let minimal = description.find("minimal (0.1.0)").unwrap();
+    let mock = description.find("mock (").unwrap();
+    assert!(minimal < mock);
+    assert!(description.contains(
+        "  missing: shutdown, stream_data, stream_close, health, handle_req\n  NrHostVTable: send_result\n"
+    ));
+    assert!(description.contains("  abi: v1 (native, host supports up to v"));
+    assert!(description.contains("  provides: init, handle, stream_data, stream_close\n"));
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use nylon_ring::async_reply::PANIC_ERROR_CODE;
+use nylon_ring::{define_plugin, nr_async_reply, NrBytes, NrHostVTable, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, PluginHandle};
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+common::test_plugin_host!(on_init: |_host_ctx, _host_vtable| {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap()
+    });
+});
+
+// The plugin's own runtime, separate from the test's.
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn reply_later(
+    sid: u64,
+    reply: impl std::future::Future<Output = (NrStatus, NrVec<u8>)> + Send + 'static,
+) -> NrStatus {
+    nr_async_reply(
+        RUNTIME.get().unwrap().handle(),
+        HOST_CTX.load(Ordering::Acquire),
+        HOST_VTABLE.load(Ordering::Acquire),
+        sid,
+        reply,
+    )
+}
+
+/// Echo the payload after a delay, from the plugin's runtime.
+unsafe fn handle_later(sid: u64, payload: NrBytes) -> NrStatus {
+    let data = payload.as_slice().to_vec();
+    reply_later(sid, async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        (NrStatus::Ok, NrVec::from_vec(data))
+    })
+}
+
+unsafe fn handle_panic(sid: u64, _payload: NrBytes) -> NrStatus {
+    reply_later(sid, async {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        panic!("boom")
+    })
+}
+
+/// Announce a late answer, then answer during `handle` anyway.
+unsafe fn handle_changed_mind(sid: u64, _payload: NrBytes) -> NrStatus {
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    let ext = &*(vtable.get_host_ext)(host_ctx);
+    assert_eq!((ext.complete_later)(host_ctx, sid), NrStatus::Ok);
+    (vtable.send_result)(host_ctx, sid, NrStatus::Ok, NrVec::from_slice(b"now"));
+    NrStatus::Ok
+}
+
+/// Answer from a plain thread, after `delay` ms taken from the payload,
+/// without `complete_later`.
+unsafe fn handle_accepted(sid: u64, payload: NrBytes) -> NrStatus {
+    let delay = Duration::from_millis(payload.as_slice()[0] as u64);
+    let host_ctx = HOST_CTX.load(Ordering::Acquire) as usize;
+    let vtable = HOST_VTABLE.load(Ordering::Acquire) as usize;
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        let vtable = &*(vtable as *const NrHostVTable);
+        (vtable.send_result)(host_ctx as _, sid, NrStatus::Ok, NrVec::from_slice(b"late"));
+    });
+    // Sometimes the answer is in before `handle` returns.
+    std::thread::sleep(Duration::from_millis(1));
+    NrStatus::Accepted
+}
+
+/// Claim the call is done without answering it.
+unsafe fn handle_silent(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "later" => handle_later,
+        "panic" => handle_panic,
+        "changed_mind" => handle_changed_mind,
+        "accepted" => handle_accepted,
+        "silent" => handle_silent,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("async", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("async").unwrap();
+    (host, plugin)
+}
+
+#[tokio::test]
+async fn test_fast_call_waits_for_late_answer() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+
+    let (status, data) = plugin.call_response_fast("later", b"fast").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"fast"[..]));
+
+    let (status, data) = plugin.call_response("later", b"map").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"map"[..]));
+    assert_eq!(host.metrics().errors, 0);
+}
+
+#[tokio::test]
+async fn test_panicking_reply_fails_the_call() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    for fast in [true, false] {
+        let result = if fast {
+            plugin.call_response_fast("panic", b"").await
+        } else {
+            plugin.call_response("panic", b"").await
+        };
+        match result {
+            Err(NylonRingHostError::PluginError { code, message }) => {
+                assert_eq!(code, PANIC_ERROR_CODE);
+                assert!(message.contains("boom"), "{message}");
+            }
+            other => panic!("expected a plugin error, got {other:?}"),
+        }
+    }
+    assert!(plugin
+        .panic_reports()
+        .iter()
+        .any(|report| report.message.contains("boom")));
+}
+
+#[tokio::test]
+async fn test_answer_during_handle_after_complete_later() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+
+    let (status, data) = plugin
+        .call_response_fast("changed_mind", b"")
+        .await
+        .unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"now"[..]));
+    assert_eq!(host.metrics().errors, 0);
+}
+
+#[tokio::test]
+async fn test_accepted_call_waits_for_answer() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    for delay in [0u8, 20] {
+        let (status, data) = plugin
+            .call_response_fast("accepted", &[delay])
+            .await
+            .unwrap();
+        assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"late"[..]));
+        let (status, data) = plugin.call_response("accepted", &[delay]).await.unwrap();
+        assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"late"[..]));
+    }
+}
+
+#[tokio::test]
+async fn test_ok_without_answer_does_not_wait() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let result = tokio::time::timeout(Duration::from_secs(5), plugin.call_response("silent", b""))
+        .await
+        .expect("an `Ok` call is done once `handle` returns");
+    assert!(matches!(result, Err(NylonRingHostError::OneshotClosed)));
+}
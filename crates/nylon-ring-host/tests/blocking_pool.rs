@@ -0,0 +1,131 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{ExecutionPolicy, NylonRingHost};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+common::test_plugin_host!();
+
+// The plugin keeps the host context in a static, so hosts that load it must
+// not overlap.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Block the calling thread for 200ms, then respond with its name.
+unsafe fn handle_slow(sid: u64, _payload: NrBytes) -> NrStatus {
+    std::thread::sleep(Duration::from_millis(200));
+
+    let thread = std::thread::current();
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        NrStatus::Ok,
+        NrVec::from_slice(thread.name().unwrap_or_default().as_bytes()),
+    );
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "slow" => handle_slow,
+    }
+}
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("slow", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_blocking_calls_do_not_starve_the_runtime() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("slow").unwrap();
+
+    let ticks = Arc::new(AtomicU32::new(0));
+    let ticker = tokio::spawn({
+        let ticks = ticks.clone();
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                ticks.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    // Eight calls on four threads take two rounds of 200ms, during which the
+    // only runtime thread keeps ticking.
+    let started = Instant::now();
+    let calls: Vec<_> = (0..8)
+        .map(|_| {
+            let plugin = plugin.clone();
+            tokio::spawn(async move { plugin.call_response_blocking("slow", b"").await })
+        })
+        .collect();
+    for call in calls {
+        let (status, thread) = call.await.unwrap().unwrap();
+        assert_eq!(status, NrStatus::Ok);
+        assert!(String::from_utf8(thread)
+            .unwrap()
+            .starts_with("nylon-ring-pool-slow-"));
+    }
+    let elapsed = started.elapsed();
+    ticker.abort();
+
+    assert!(elapsed < Duration::from_millis(1500), "took {elapsed:?}");
+    let ticks = ticks.load(Ordering::Relaxed);
+    assert!(ticks >= 10, "only {ticks} ticks in {elapsed:?}");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_dedicated_pool_policy() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.set_execution_policy("slow", ExecutionPolicy::DedicatedPool { threads: 2 })
+        .unwrap();
+
+    // Both unary paths run on the pool; the fast path falls back to the
+    // pending map.
+    let plugin = host.plugin("slow").unwrap();
+    let (_, thread) = plugin.call_response("slow", b"").await.unwrap();
+    assert!(thread.starts_with(b"nylon-ring-pool-slow-"));
+    let (_, thread) = plugin.call_response_fast("slow", b"").await.unwrap();
+    assert!(thread.starts_with(b"nylon-ring-pool-slow-"));
+
+    // The policy survives a reload.
+    host.reload_one("slow").unwrap();
+    let plugin = host.plugin("slow").unwrap();
+    let (_, thread) = plugin.call_response("slow", b"").await.unwrap();
+    assert!(thread.starts_with(b"nylon-ring-pool-slow-"));
+
+    host.set_execution_policy("slow", ExecutionPolicy::Inline)
+        .unwrap();
+    let plugin = host.plugin("slow").unwrap();
+    let (_, thread) = plugin.call_response("slow", b"").await.unwrap();
+    assert!(!thread.starts_with(b"nylon-ring-pool-"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_blocking_call_leaves_the_policy_inline() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    let plugin = host.plugin("slow").unwrap();
+
+    let (_, thread) = plugin.call_response_blocking("slow", b"").await.unwrap();
+    assert!(thread.starts_with(b"nylon-ring-pool-slow-"));
+
+    // Only `call_response_blocking` uses the implicit pool, and a reload
+    // does not turn it into a policy.
+    let (_, thread) = plugin.call_response("slow", b"").await.unwrap();
+    assert!(!thread.starts_with(b"nylon-ring-pool-"));
+    host.reload_one("slow").unwrap();
+    let plugin = host.plugin("slow").unwrap();
+    let (_, thread) = plugin.call_response("slow", b"").await.unwrap();
+    assert!(!thread.starts_with(b"nylon-ring-pool-"));
+}
@@ -0,0 +1,85 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `host_ctx` per loaded instance, in load order.
+static HOST_CTXS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
+/// Payloads received by the `config` entry.
+static RECEIVED: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+unsafe fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+    HOST_CTXS.lock().unwrap().push(host_ctx as usize);
+    HOST_VTABLE.store(host_vtable as *mut _, Ordering::Release);
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_config(_sid: u64, payload: NrBytes) -> NrStatus {
+    RECEIVED.lock().unwrap().push(payload.as_slice().to_vec());
+    NrStatus::Ok
+}
+
+/// Replies through the first instance's context; the host routes by SID.
+unsafe fn handle_version(sid: u64, _payload: NrBytes) -> NrStatus {
+    let ctx = HOST_CTXS.lock().unwrap()[0] as *mut c_void;
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(ctx, sid, NrStatus::Ok, NrVec::from_slice(b"1.0"));
+    NrStatus::Ok
+}
+
+/// Accepts the call and never replies.
+unsafe fn handle_hang(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Accepted
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "config" => handle_config,
+        "version" => handle_version,
+        "hang" => handle_hang,
+    }
+}
+
+#[tokio::test]
+async fn broadcast_reaches_every_plugin() {
+    let mut host = NylonRingHost::new();
+    for name in ["b", "a"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+
+    let results = host.broadcast("config", b"reloaded").await;
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["a", "b"]);
+    assert!(results.iter().all(|(_, r)| matches!(r, Ok(NrStatus::Ok))));
+    assert_eq!(*RECEIVED.lock().unwrap(), [b"reloaded", b"reloaded"]);
+
+    // Not implementing an entry is a status, not an error.
+    let results = host.broadcast("missing", b"").await;
+    assert!(results
+        .iter()
+        .all(|(_, r)| matches!(r, Ok(NrStatus::Invalid))));
+
+    let results = host
+        .broadcast_response("version", b"", Duration::from_secs(5))
+        .await;
+    for (_, result) in &results {
+        let (status, data) = result.as_ref().unwrap();
+        assert_eq!((*status, data.as_slice()), (NrStatus::Ok, &b"1.0"[..]));
+    }
+
+    let results = host
+        .broadcast_response("hang", b"", Duration::from_millis(50))
+        .await;
+    assert_eq!(results.len(), 2);
+    assert!(results
+        .iter()
+        .all(|(_, r)| matches!(r, Err(NylonRingHostError::Timeout(_)))));
+}
@@ -0,0 +1,79 @@
+/* A plugin written in C against include/nylon_ring.h, loaded by
+ * tests/c_plugin.rs.
+ *
+ * "echo" answers with the payload. "remember" stores the payload as state
+ * under the call and answers with what reading it back gives, through the
+ * host extension table.
+ */
+#include <stdlib.h>
+#include <string.h>
+
+#include "nylon_ring.h"
+
+static void *HOST_CTX;
+static const NrHostVTable *HOST;
+
+static NrStr str(const char *s) {
+    NrStr out = {(const uint8_t *)s, (uint32_t)strlen(s)};
+    return out;
+}
+
+static int is(NrStr entry, const char *name) {
+    return entry.len == strlen(name) && memcmp(entry.ptr, name, entry.len) == 0;
+}
+
+/* Answer `sid` with a copy of `len` bytes at `data`. */
+static NrStatus reply(uint64_t sid, const uint8_t *data, size_t len) {
+    NrVecU8 out = {NULL, 0, 0};
+    if (len > 0) {
+        out.ptr = malloc(len);
+        if (out.ptr == NULL) {
+            return NR_STATUS_ERR;
+        }
+        memcpy(out.ptr, data, len);
+        out.len = out.cap = len;
+    }
+    HOST->send_result(HOST_CTX, sid, NR_STATUS_OK, out);
+    return NR_STATUS_OK;
+}
+
+static NrStatus init(void *host_ctx, const NrHostVTable *host_vtable) {
+    HOST_CTX = host_ctx;
+    HOST = host_vtable;
+    return NR_STATUS_OK;
+}
+
+static NrStatus handle(NrStr entry, uint64_t sid, NrBytes payload) {
+    if (is(entry, "echo")) {
+        return reply(sid, payload.ptr, payload.len);
+    }
+    if (is(entry, "remember")) {
+        const NrHostExt *ext = HOST->get_host_ext(HOST_CTX);
+        uint8_t buf[256];
+        NrBytes error = ext->set_state(HOST_CTX, sid, str("payload"), payload);
+        if (error.len != 0) {
+            return NR_STATUS_ERR;
+        }
+        uint64_t len = ext->get_state_into(HOST_CTX, sid, str("payload"), buf, sizeof buf);
+        if (len == NR_STATE_ABSENT || len > sizeof buf) {
+            return NR_STATUS_ERR;
+        }
+        return reply(sid, buf, len);
+    }
+    return NR_STATUS_UNSUPPORTED;
+}
+
+static const NrPluginVTable VTABLE = {init, handle, NULL, NULL, NULL, NULL, NULL};
+
+static NrPluginInfo INFO = {
+    NR_ABI_VERSION,
+    sizeof(NrPluginInfo),
+    {(const uint8_t *)"c-echo", 6},
+    {(const uint8_t *)"0.1.0", 5},
+    NULL,
+    &VTABLE,
+};
+
+const NrPluginInfo *nylon_ring_get_plugin_v1(void) {
+    return &INFO;
+}
@@ -0,0 +1,162 @@
+//! A plugin written in C against the generated `nylon_ring.h`.
+
+use nylon_ring::NrPluginInfo;
+use nylon_ring_host::{plugin_file_name, LoadPolicy, NrStatus, NylonRingHost, NylonRingHostError};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// `tests/c/echo_plugin.c` and `extra`, built into the shared library
+/// `name` with `$CC`, or `cc`. The header's layout assertions are checked
+/// while compiling it.
+fn build(name: &str, extra: &[PathBuf]) -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(plugin_file_name(name));
+    let cc = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+    let status = Command::new(cc)
+        .args([
+            "-std=c11", "-Wall", "-Wextra", "-Werror", "-shared", "-fPIC", "-I",
+        ])
+        .arg(manifest_dir.join("../nylon-ring/include"))
+        .arg(manifest_dir.join("tests/c/echo_plugin.c"))
+        .args(extra)
+        .arg("-o")
+        .arg(&out)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to build the C plugin");
+    out
+}
+
+fn c_plugin() -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| build("c_echo", &[]))
+}
+
+/// The C plugin with its own `malloc`.
+fn c_plugin_with_malloc() -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| {
+        let source = Path::new(env!("CARGO_TARGET_TMPDIR")).join("own_malloc.c");
+        std::fs::write(
+            &source,
+            "#include <stddef.h>\nvoid *malloc(size_t n) { (void)n; return NULL; }\n",
+        )
+        .unwrap();
+        build("c_echo_malloc", &[source])
+    })
+}
+
+#[tokio::test]
+async fn test_c_plugin_loads_and_answers() {
+    let mut host = NylonRingHost::new();
+    host.load("c", c_plugin().to_str().unwrap()).unwrap();
+    let plugin = host.plugin("c").unwrap();
+
+    let (status, data) = plugin.call_response("echo", b"from C").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"from C"[..]));
+
+    let (_, data) = plugin.call_response("echo", b"").await.unwrap();
+    assert!(data.is_empty());
+
+    // Goes through the host extension table.
+    let (_, data) = plugin.call_response("remember", b"kept").await.unwrap();
+    assert_eq!(data, b"kept");
+
+    assert!(plugin.call_response("missing", b"").await.is_err());
+}
+
+#[tokio::test]
+async fn test_load_checked_accepts_a_matching_plugin() {
+    let path = c_plugin().to_str().unwrap();
+    let policy = LoadPolicy {
+        sha256: Some(Sha256::digest(std::fs::read(path).unwrap()).into()),
+        ..LoadPolicy::strict()
+    };
+    let mut host = NylonRingHost::new();
+    host.load_checked("c", path, policy).unwrap();
+    let (_, data) = host
+        .plugin("c")
+        .unwrap()
+        .call_response("echo", b"checked")
+        .await
+        .unwrap();
+    assert_eq!(data, b"checked");
+}
+
+#[test]
+fn test_load_checked_refuses_mismatches() {
+    let path = c_plugin().to_str().unwrap();
+    let mut host = NylonRingHost::new();
+
+    let err = host
+        .load_checked(
+            "c",
+            path,
+            LoadPolicy {
+                sha256: Some([0; 32]),
+                ..LoadPolicy::default()
+            },
+        )
+        .unwrap_err();
+    assert!(
+        matches!(&err, NylonRingHostError::ChecksumMismatch { expected, .. } if expected == &"00".repeat(32)),
+        "{err}"
+    );
+
+    let err = host
+        .load_checked(
+            "c",
+            path,
+            LoadPolicy {
+                abi_version: Some(1),
+                ..LoadPolicy::default()
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NylonRingHostError::IncompatibleAbiVersion {
+            expected: 1,
+            actual: nylon_ring::NR_ABI_VERSION
+        }
+    ));
+
+    let info_size = std::mem::size_of::<NrPluginInfo>() as u32;
+    let err = host
+        .load_checked(
+            "c",
+            path,
+            LoadPolicy {
+                struct_size: Some(info_size + 8),
+                ..LoadPolicy::default()
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NylonRingHostError::StructSizeMismatch { actual, .. } if actual == info_size
+    ));
+
+    assert!(matches!(
+        host.load_checked("c", "/nonexistent/libplugin.so", LoadPolicy::strict()),
+        Err(NylonRingHostError::ReadPluginFile { .. })
+    ));
+    assert!(host.plugin("c").is_none());
+}
+
+#[test]
+fn test_load_checked_refuses_unexpected_exports() {
+    let path = c_plugin_with_malloc().to_str().unwrap();
+    let mut host = NylonRingHost::new();
+    let err = host
+        .load_checked("c", path, LoadPolicy::strict())
+        .unwrap_err();
+    assert!(
+        matches!(&err, NylonRingHostError::UnexpectedExport { symbol, .. } if symbol == "malloc"),
+        "{err}"
+    );
+    assert!(err.to_string().contains("exports \"malloc\""));
+    assert!(host.plugin("c").is_none());
+}
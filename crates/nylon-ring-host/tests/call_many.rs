@@ -0,0 +1,85 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+common::test_plugin_host!();
+
+/// Calls held back by `slow:` payloads, answered by the next `fast:` one.
+static HELD: Mutex<Vec<(u64, Vec<u8>)>> = Mutex::new(Vec::new());
+/// The SID of every call, in the order the plugin saw them.
+static SIDS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+/// `slow:x` answers `x` later, `fast:x` answers `x` at once and then the
+/// held calls, newest first. Anything else is invalid.
+unsafe fn handle_work(sid: u64, payload: NrBytes) -> NrStatus {
+    SIDS.lock().unwrap().push(sid);
+    match payload.as_slice().split_at_checked(5) {
+        Some((b"slow:", data)) => {
+            HELD.lock().unwrap().push((sid, data.to_vec()));
+            NrStatus::Accepted
+        }
+        Some((b"fast:", data)) => {
+            send(sid, NrStatus::Ok, data);
+            while let Some((held, data)) = HELD.lock().unwrap().pop() {
+                send(held, NrStatus::Ok, &data);
+            }
+            NrStatus::Ok
+        }
+        _ => NrStatus::Invalid,
+    }
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "work" => handle_work,
+    }
+}
+
+#[tokio::test]
+async fn test_results_follow_payload_order() {
+    let mut host = NylonRingHost::new();
+    host.load_static("worker", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("worker").unwrap();
+
+    // The plugin answers `c`, then `b`, then `a`.
+    let payloads: [&[u8]; 4] = [b"slow:a", b"slow:b", b"bad", b"fast:c"];
+    let results = plugin.call_response_many("work", &payloads).await;
+
+    let data: Vec<_> = results
+        .iter()
+        .map(|result| result.as_ref().ok().map(|(_, data)| data.as_slice()))
+        .collect();
+    assert_eq!(data, [Some(&b"a"[..]), Some(b"b"), None, Some(b"c")]);
+    assert!(matches!(
+        results[2],
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        })
+    ));
+
+    let mut sids = SIDS.lock().unwrap().clone();
+    sids.sort();
+    sids.dedup();
+    assert_eq!(sids.len(), 4);
+    assert_eq!(host.diagnostics().pending_unary(), 0);
+    assert!(plugin.call_response_many("work", &[]).await.is_empty());
+}
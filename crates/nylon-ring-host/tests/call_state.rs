@@ -0,0 +1,97 @@
+//! Per-call state from the caller's side, through the example plugin's
+//! `state` entry.
+
+mod common;
+
+use common::example_plugin;
+use nylon_ring_host::{NrStatus, NylonRingHost, NylonRingHostError, TraceEvent};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// The example plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A host with the example plugin loaded, and the SID of the last call it
+/// started.
+fn load() -> (NylonRingHost, Arc<AtomicU64>) {
+    let mut host = NylonRingHost::new();
+    host.load("example", example_plugin().to_str().unwrap())
+        .unwrap();
+    let last_sid = Arc::new(AtomicU64::new(0));
+    let hook_sid = last_sid.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        if let TraceEvent::CallStart { sid, .. } = event {
+            hook_sid.store(sid, Ordering::SeqCst);
+        }
+    }));
+    (host, last_sid)
+}
+
+#[tokio::test]
+async fn test_seeded_state_is_read_updated_and_returned() {
+    let _serial = SERIAL.lock().await;
+    let (host, last_sid) = load();
+    let plugin = host.plugin("example").unwrap();
+
+    let seed: &[(&str, &[u8])] = &[("user", b"ring"), ("visits", b"2")];
+    let (status, data, state) = plugin
+        .call_response_with_state("state", b"", seed)
+        .await
+        .unwrap();
+    assert_eq!(
+        (status, data.as_slice()),
+        (NrStatus::Ok, &b"hello, ring"[..])
+    );
+    assert_eq!(state.len(), 3);
+    assert_eq!(state["user"], b"ring");
+    assert_eq!(state["greeting"], b"hello, ring");
+    assert_eq!(state["visits"], b"3");
+
+    // The state went with the response.
+    assert_eq!(host.state_for(last_sid.load(Ordering::SeqCst)), None);
+}
+
+#[tokio::test]
+async fn test_unseeded_calls_return_what_the_plugin_stored() {
+    let _serial = SERIAL.lock().await;
+    let (host, last_sid) = load();
+    let plugin = host.plugin("example").unwrap();
+
+    let (_, data, state) = plugin
+        .call_response_with_state("state", b"", &[])
+        .await
+        .unwrap();
+    assert_eq!(data, b"hello, anonymous");
+    assert_eq!(state.len(), 2);
+    assert_eq!(state["visits"], b"1");
+    assert_eq!(host.state_for(last_sid.load(Ordering::SeqCst)), None);
+}
+
+#[tokio::test]
+async fn test_state_of_plain_calls_can_be_read_afterwards() {
+    let _serial = SERIAL.lock().await;
+    let (host, last_sid) = load();
+    let plugin = host.plugin("example").unwrap();
+
+    let (_, data) = plugin.call_response("state", b"").await.unwrap();
+    assert_eq!(data, b"hello, anonymous");
+    let state = host.state_for(last_sid.load(Ordering::SeqCst)).unwrap();
+    assert_eq!(state["greeting"], b"hello, anonymous");
+    assert_eq!(state["visits"], b"1");
+    assert_eq!(host.state_for(u64::MAX), None);
+}
+
+#[tokio::test]
+async fn test_failed_calls_remove_their_state() {
+    let _serial = SERIAL.lock().await;
+    let (host, last_sid) = load();
+    let plugin = host.plugin("example").unwrap();
+
+    let seed: &[(&str, &[u8])] = &[("user", b"ring")];
+    let result = plugin.call_response_with_state("missing", b"", seed).await;
+    assert!(matches!(
+        result,
+        Err(NylonRingHostError::PluginHandleFailed { .. })
+    ));
+    assert_eq!(host.state_for(last_sid.load(Ordering::SeqCst)), None);
+}
@@ -0,0 +1,69 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate uses only some of them.
+#![allow(dead_code, unused_imports, unused_macros)]
+
+use nylon_ring_host::{plugin_artifact_in, Profile};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// The example plugin, built into a target directory of its own so the
+/// build does not wait on the lock held by the running `cargo test`.
+pub fn example_plugin() -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| {
+        let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("example-plugin");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet", "-p", "ex-nyring-plugin", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "failed to build the example plugin");
+        plugin_artifact_in(&target_dir, "ex-nyring-plugin", Profile::Debug).unwrap()
+    })
+}
+
+/// Define the `init` and `shutdown` of a test plugin, and the `HOST_CTX` and
+/// `HOST_VTABLE` statics its `init` stores the host's pointers in. Loading
+/// the plugin again overwrites them.
+///
+/// `on_init` runs at the end of `init`, which then returns `Ok`;
+/// `on_shutdown` is the body of `shutdown`.
+macro_rules! test_plugin_host {
+    () => {
+        $crate::common::test_plugin_host!(on_init: |_host_ctx, _host_vtable| {});
+    };
+    (on_init: |$host_ctx:ident, $host_vtable:ident| $on_init:block) => {
+        $crate::common::test_plugin_host!(
+            on_init: |$host_ctx, $host_vtable| $on_init,
+            on_shutdown: {}
+        );
+    };
+    (
+        on_init: |$host_ctx:ident, $host_vtable:ident| $on_init:block,
+        on_shutdown: $on_shutdown:block
+    ) => {
+        static HOST_CTX: ::std::sync::atomic::AtomicPtr<::std::ffi::c_void> =
+            ::std::sync::atomic::AtomicPtr::new(::std::ptr::null_mut());
+        static HOST_VTABLE: ::std::sync::atomic::AtomicPtr<::nylon_ring::NrHostVTable> =
+            ::std::sync::atomic::AtomicPtr::new(::std::ptr::null_mut());
+
+        unsafe fn init(
+            $host_ctx: *mut ::std::ffi::c_void,
+            $host_vtable: *const ::nylon_ring::NrHostVTable,
+        ) -> ::nylon_ring::NrStatus {
+            HOST_CTX.store($host_ctx, ::std::sync::atomic::Ordering::Release);
+            HOST_VTABLE.store(
+                $host_vtable as *mut _,
+                ::std::sync::atomic::Ordering::Release,
+            );
+            $on_init
+            ::nylon_ring::NrStatus::Ok
+        }
+
+        fn shutdown() $on_shutdown
+    };
+}
+pub(crate) use test_plugin_host;
@@ -0,0 +1,126 @@
+//! Diagnostics of what a host holds on to, and purging what went stale.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+// Tests load the plugin into hosts of their own, and it keeps one `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Store some state under the call, then never answer it.
+unsafe fn handle_ignore(sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = &*((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        (ext.set_state)(
+            host_ctx,
+            sid,
+            NrStr::new("key"),
+            NrBytes::from_slice(b"value"),
+        );
+    }
+    NrStatus::Accepted
+}
+
+/// Open a stream and never send a frame.
+unsafe fn handle_silent(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ignore" => handle_ignore,
+        "silent" => handle_silent,
+    }
+}
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_orphaned_calls_show_in_diagnostics() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let diagnostics = host.diagnostics();
+    assert_eq!(diagnostics.shards.len(), 64);
+    assert_eq!(diagnostics.pending_unary(), 0);
+    assert_eq!(diagnostics.oldest_pending, None);
+
+    let plugin = host.plugin("p").unwrap();
+    let calls: Vec<_> = (0..3)
+        .map(|_| {
+            let plugin = plugin.clone();
+            tokio::spawn(async move { plugin.call_response("ignore", b"").await })
+        })
+        .collect();
+    let (_sid, _rx) = plugin.call_stream("silent", b"").await.unwrap();
+    while host.diagnostics().pending_unary() < 3 {
+        tokio::task::yield_now().await;
+    }
+
+    let diagnostics = host.diagnostics();
+    assert_eq!(diagnostics.pending_streams(), 1);
+    assert_eq!(
+        diagnostics
+            .shards
+            .iter()
+            .map(|s| s.unary + s.streams)
+            .sum::<usize>(),
+        4
+    );
+    assert_eq!(diagnostics.sids_with_state, 3);
+    assert_eq!(diagnostics.state_bytes, 3 * "keyvalue".len());
+    assert!(diagnostics.oldest_pending.is_some());
+    for call in calls {
+        call.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_purge_stale_unblocks_callers() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    let calls: Vec<_> = (0..2)
+        .map(|_| {
+            let plugin = plugin.clone();
+            tokio::spawn(async move { plugin.call_response("ignore", b"").await })
+        })
+        .collect();
+    let (_sid, mut rx) = plugin.call_stream("silent", b"").await.unwrap();
+    while host.diagnostics().pending_unary() < 2 {
+        tokio::task::yield_now().await;
+    }
+
+    // Nothing is that old yet.
+    assert_eq!(host.purge_stale(Duration::from_secs(60)), 0);
+    assert_eq!(host.diagnostics().sids_with_state, 2);
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(host.purge_stale(Duration::from_millis(10)), 2);
+    for call in calls {
+        assert!(matches!(
+            call.await.unwrap(),
+            Err(NylonRingHostError::OneshotClosed)
+        ));
+    }
+
+    let diagnostics = host.diagnostics();
+    assert_eq!(diagnostics.pending_unary(), 0);
+    assert_eq!(diagnostics.sids_with_state, 0);
+    assert_eq!(diagnostics.state_bytes, 0);
+    // Streams are left for their own limits.
+    assert_eq!(diagnostics.pending_streams(), 1);
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec};
+use nylon_ring_host::NylonRingHost;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+common::test_plugin_host!();
+
+/// `square` holds its responses until this is set.
+static RELEASE: AtomicBool = AtomicBool::new(false);
+/// What `fanout` got back from `dispatch_spawn`.
+static SPAWNED: Mutex<Vec<(NrStatus, u64)>> = Mutex::new(Vec::new());
+
+/// Fan out to `b` without waiting on any of the calls.
+unsafe fn handle_fanout(_sid: u64, _payload: NrBytes) -> NrStatus {
+    let ctx = HOST_CTX.load(Ordering::Acquire);
+    let ext = &*((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(ctx);
+    let mut spawned = SPAWNED.lock().unwrap();
+    for (target, entry, n) in [
+        ("b", "square", 2u8),
+        ("b", "square", 3),
+        ("b", "square", 4),
+        ("missing", "square", 5),
+        ("b", "missing", 6),
+    ] {
+        let result = (ext.dispatch_spawn)(
+            ctx,
+            NrStr::new(target),
+            NrStr::new(entry),
+            NrBytes::from_slice(&[n]),
+        );
+        spawned.push(result.into_tuple());
+    }
+    NrStatus::Ok
+}
+
+unsafe fn handle_square(sid: u64, payload: NrBytes) -> NrStatus {
+    let n = payload.as_slice()[0];
+    std::thread::spawn(move || {
+        while !RELEASE.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_slice(&[n * n]),
+        );
+    });
+    NrStatus::Accepted
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "fanout" => handle_fanout,
+        "square" => handle_square,
+    }
+}
+
+#[tokio::test]
+async fn dispatched_calls_deliver_their_responses() {
+    let mut host = NylonRingHost::new();
+    for name in ["a", "b"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+    let ctx = HOST_CTX.load(Ordering::Acquire);
+
+    let a = host.plugin("a").unwrap();
+    assert_eq!(a.call("fanout", b"").await.unwrap(), NrStatus::Ok);
+
+    let spawned = SPAWNED.lock().unwrap().clone();
+    let statuses: Vec<_> = spawned.iter().map(|(status, _)| *status).collect();
+    assert_eq!(
+        statuses,
+        [
+            NrStatus::Ok,
+            NrStatus::Ok,
+            NrStatus::Ok,
+            NrStatus::Invalid,
+            NrStatus::Invalid
+        ]
+    );
+    let sids: Vec<u64> = spawned[..3].iter().map(|(_, sid)| *sid).collect();
+    assert_eq!(spawned[3].1, 0);
+
+    // All three are in flight at once.
+    for &sid in &sids {
+        assert!(unsafe { NylonRingHost::try_take_dispatch_result(ctx, sid) }.is_none());
+    }
+    assert_eq!(host.plugin("b").unwrap().metrics_snapshot().in_flight, 0);
+    let b = host.plugin("b").unwrap();
+    assert_eq!(b.in_flight(), 3);
+
+    // Answered calls stop counting against `b`'s drain before they are taken.
+    RELEASE.store(true, Ordering::Release);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while b.in_flight() > 0 {
+        assert!(Instant::now() < deadline);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    for (&sid, n) in sids.iter().zip([2u8, 3, 4]) {
+        let result = loop {
+            if let Some(result) = unsafe { NylonRingHost::try_take_dispatch_result(ctx, sid) } {
+                break result;
+            }
+            assert!(Instant::now() < deadline);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        };
+        assert_eq!(result, (NrStatus::Ok, vec![n * n]));
+
+        // Each response is taken once.
+        let again = unsafe { NylonRingHost::try_take_dispatch_result(ctx, sid) };
+        assert_eq!(again, Some((NrStatus::Invalid, Vec::new())));
+    }
+}
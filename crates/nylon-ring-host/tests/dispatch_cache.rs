@@ -0,0 +1,163 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec};
+use nylon_ring_host::{DispatchCacheRule, NylonRingHost};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+common::test_plugin_host!(on_init: |host_ctx, _host_vtable| {
+    let _ = CALLER_CTX.compare_exchange(
+        std::ptr::null_mut(),
+        host_ctx,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    );
+});
+
+/// The context of the first plugin loaded, `a`. Cache rules are per caller,
+/// so `ask` must dispatch as `a`. Reset by [`host`].
+static CALLER_CTX: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+/// Times `lookup` ran.
+static LOOKUPS: AtomicU64 = AtomicU64::new(0);
+/// What `ask` got back from `dispatch_spawn`.
+static SPAWNED: Mutex<Vec<(NrStatus, u64)>> = Mutex::new(Vec::new());
+// The statics above are shared by every host the tests load.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Dispatch the payload to `lookup` on `b`.
+unsafe fn handle_ask(_sid: u64, payload: NrBytes) -> NrStatus {
+    let ctx = CALLER_CTX.load(Ordering::Acquire);
+    let ext = &*((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(ctx);
+    let result = (ext.dispatch_spawn)(ctx, NrStr::new("b"), NrStr::new("lookup"), payload);
+    SPAWNED.lock().unwrap().push(result.into_tuple());
+    NrStatus::Ok
+}
+
+/// Answer with the payload and how many lookups came before.
+unsafe fn handle_lookup(sid: u64, payload: NrBytes) -> NrStatus {
+    let n = LOOKUPS.fetch_add(1, Ordering::Relaxed) as u8;
+    let mut data = payload.as_slice().to_vec();
+    data.push(n);
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        NrStatus::Ok,
+        NrVec::from_vec(data),
+    );
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ask" => handle_ask,
+        "lookup" => handle_lookup,
+    }
+}
+
+/// A host with `a` and `b` loaded, in that order.
+fn host() -> NylonRingHost {
+    CALLER_CTX.store(std::ptr::null_mut(), Ordering::Release);
+    LOOKUPS.store(0, Ordering::Relaxed);
+    let mut host = NylonRingHost::new();
+    for name in ["a", "b"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+    host
+}
+
+/// Have `a` dispatch `payload` to `b`, returning the SID to take.
+async fn spawn(host: &NylonRingHost, payload: &[u8]) -> u64 {
+    let a = host.plugin("a").unwrap();
+    assert_eq!(a.call("ask", payload).await.unwrap(), NrStatus::Ok);
+    let (status, sid) = SPAWNED.lock().unwrap().pop().unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    sid
+}
+
+fn take(sid: u64) -> Vec<u8> {
+    let ctx = HOST_CTX.load(Ordering::Acquire);
+    let (status, data) = unsafe { NylonRingHost::try_take_dispatch_result(ctx, sid) }.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    data
+}
+
+/// Have `a` dispatch `payload` to `b` and take the response.
+async fn ask(host: &NylonRingHost, payload: &[u8]) -> Vec<u8> {
+    take(spawn(host, payload).await)
+}
+
+#[tokio::test]
+async fn repeated_dispatches_reach_the_target_once() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+
+    // Not cached yet.
+    assert_eq!(ask(&host, b"k").await, b"k\x00");
+    assert_eq!(ask(&host, b"k").await, b"k\x01");
+
+    host.cache_dispatch(
+        "a",
+        "b",
+        "lookup",
+        DispatchCacheRule::new(Duration::from_secs(60)),
+    );
+    assert_eq!(ask(&host, b"k").await, b"k\x02");
+    for _ in 0..10 {
+        assert_eq!(ask(&host, b"k").await, b"k\x02");
+    }
+    assert_eq!(LOOKUPS.load(Ordering::Relaxed), 3);
+    // Another payload is another key.
+    assert_eq!(ask(&host, b"j").await, b"j\x03");
+    assert_eq!(ask(&host, b"j").await, b"j\x03");
+
+    let metrics = host.metrics();
+    assert_eq!(metrics.dispatch_cache_hits, 11);
+    assert_eq!(host.plugin("b").unwrap().metrics_snapshot().calls, 4);
+
+    host.invalidate_dispatch_cache("b", Some("lookup"));
+    assert_eq!(ask(&host, b"k").await, b"k\x04");
+    assert_eq!(ask(&host, b"k").await, b"k\x04");
+
+    // A short TTL runs out.
+    host.cache_dispatch(
+        "a",
+        "b",
+        "lookup",
+        DispatchCacheRule::new(Duration::from_millis(20)),
+    );
+    assert_eq!(ask(&host, b"k").await, b"k\x05");
+    assert_eq!(ask(&host, b"k").await, b"k\x05");
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(ask(&host, b"k").await, b"k\x06");
+
+    host.uncache_dispatch("a", "b", "lookup");
+    assert_eq!(ask(&host, b"k").await, b"k\x07");
+    assert_eq!(LOOKUPS.load(Ordering::Relaxed), 8);
+}
+
+#[tokio::test]
+async fn responses_from_before_a_reload_are_not_cached() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.cache_dispatch(
+        "a",
+        "b",
+        "lookup",
+        DispatchCacheRule::new(Duration::from_secs(60)),
+    );
+
+    let sid = spawn(&host, b"k").await;
+    host.reload_one("b").unwrap();
+    assert_eq!(take(sid), b"k\x00");
+
+    // The old instance's answer was not cached; the new one's is.
+    assert_eq!(ask(&host, b"k").await, b"k\x01");
+    assert_eq!(ask(&host, b"k").await, b"k\x01");
+    assert_eq!(LOOKUPS.load(Ordering::Relaxed), 2);
+}
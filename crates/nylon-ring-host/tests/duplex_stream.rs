@@ -0,0 +1,129 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, PluginHandle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+/// Number of `stream_close` calls.
+static CLOSED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+/// Echo each frame; `bye` ends the stream from the plugin side.
+unsafe fn stream_data(sid: u64, data: NrBytes) -> NrStatus {
+    match data.as_slice() {
+        b"bye" => send(sid, NrStatus::StreamEnd, b""),
+        data => send(sid, NrStatus::Ok, data),
+    }
+    NrStatus::Ok
+}
+
+unsafe fn stream_close(sid: u64) -> NrStatus {
+    CLOSED.fetch_add(1, Ordering::SeqCst);
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "open" => handle_open,
+    },
+    stream_handlers: {
+        data: stream_data,
+        close: stream_close,
+    }
+}
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("duplex", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("duplex").unwrap();
+    (host, plugin)
+}
+
+#[tokio::test]
+async fn test_host_closes_first() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let closed = CLOSED.load(Ordering::SeqCst);
+
+    let mut duplex = plugin.open_duplex("open", b"").await.unwrap();
+    assert_eq!(duplex.send(b"one").unwrap(), NrStatus::Ok);
+    assert_eq!(duplex.next().await.unwrap().data, b"one");
+
+    duplex.close().unwrap();
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed + 1);
+    assert!(matches!(
+        duplex.send(b"late"),
+        Err(NylonRingHostError::UnknownStream(sid)) if sid == duplex.sid()
+    ));
+    assert_eq!(duplex.next().await.unwrap().status, NrStatus::StreamEnd);
+    assert!(duplex.next().await.is_none());
+
+    // Closing again, and dropping, reach the plugin no more.
+    duplex.close().unwrap();
+    drop(duplex);
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed + 1);
+    assert_eq!(host.diagnostics().pending_streams(), 0);
+}
+
+#[tokio::test]
+async fn test_plugin_closes_first() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let closed = CLOSED.load(Ordering::SeqCst);
+
+    let mut duplex = plugin.open_duplex("open", b"").await.unwrap();
+    duplex.send(b"bye").unwrap();
+    // Sends fail as soon as the final frame arrived, even before it is read.
+    assert!(matches!(
+        duplex.send(b"late"),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+    assert_eq!(duplex.next().await.unwrap().status, NrStatus::StreamEnd);
+    assert!(duplex.next().await.is_none());
+    assert!(matches!(
+        duplex.send(b"later"),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+
+    duplex.close().unwrap();
+    drop(duplex);
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed);
+    assert_eq!(host.diagnostics().pending_streams(), 0);
+}
+
+#[tokio::test]
+async fn test_drop_without_close() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let closed = CLOSED.load(Ordering::SeqCst);
+
+    let duplex = plugin.open_duplex("open", b"").await.unwrap();
+    duplex.send(b"one").unwrap();
+    drop(duplex);
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed + 1);
+    assert_eq!(host.diagnostics().pending_streams(), 0);
+    assert_eq!(plugin.in_flight(), 0);
+}
@@ -0,0 +1,144 @@
+//! Reloads that change the entries a plugin serves.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec, PluginBuilder};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, TraceEvent};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+common::test_plugin_host!(on_init: |_host_ctx, _host_vtable| {
+    let mut builder = PluginBuilder::new();
+    for entry in ENTRIES.lock().unwrap().iter() {
+        builder = builder.raw_entry(*entry, handle_entry);
+    }
+    builder.install();
+});
+
+/// The entries the next `init` registers: the plugin's next version.
+static ENTRIES: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+// Tests swap `ENTRIES` and reload the plugin through it.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn handle_entry(entry: &str, sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_slice(entry.as_bytes()),
+        );
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: runtime,
+}
+
+fn load(entries: Vec<&'static str>) -> NylonRingHost {
+    *ENTRIES.lock().unwrap() = entries;
+    let mut host = NylonRingHost::new();
+    host.load_static("api", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_reload_reports_removed_entries() {
+    let _serial = SERIAL.lock().await;
+    let mut host = load(vec!["users", "orders"]);
+    host.route("users", "api").unwrap();
+    host.route("orders", "api").unwrap();
+    assert_eq!(
+        host.plugin("api").unwrap().provided_entries(),
+        Some(vec!["orders".to_string(), "users".to_string()])
+    );
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let seen = changes.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        if let TraceEvent::EntriesChanged {
+            plugin,
+            added,
+            removed,
+        } = event
+        {
+            seen.lock()
+                .unwrap()
+                .push((plugin.to_string(), added.to_vec(), removed.to_vec()));
+        }
+    }));
+
+    *ENTRIES.lock().unwrap() = vec!["orders", "carts"];
+    host.reload_one("api").unwrap();
+    assert_eq!(
+        *changes.lock().unwrap(),
+        [(
+            "api".to_string(),
+            vec!["carts".to_string()],
+            vec!["users".to_string()]
+        )]
+    );
+    assert_eq!(host.stale_routes(), ["users"]);
+
+    let version = env!("CARGO_PKG_VERSION");
+    let plugin = host.plugin("api").unwrap();
+    assert!(matches!(
+        host.call_routed("users", b"").await,
+        Err(NylonRingHostError::EntryRemovedInVersion { entry, old_version, new_version })
+            if entry == "users" && old_version == version && new_version == version
+    ));
+    assert!(matches!(
+        plugin.call_stream("users", b"").await,
+        Err(NylonRingHostError::EntryRemovedInVersion { .. })
+    ));
+    assert_eq!(
+        plugin.call_response("carts", b"").await.unwrap().1,
+        b"carts"
+    );
+
+    // A reload that changes nothing keeps the tombstone.
+    changes.lock().unwrap().clear();
+    host.reload_one("api").unwrap();
+    assert!(changes.lock().unwrap().is_empty());
+    assert!(matches!(
+        host.plugin("api").unwrap().call("users", b"").await,
+        Err(NylonRingHostError::EntryRemovedInVersion { .. })
+    ));
+
+    // Bringing the entry back clears both the tombstone and the flag.
+    *ENTRIES.lock().unwrap() = vec!["users", "orders", "carts"];
+    host.reload_one("api").unwrap();
+    assert!(host.stale_routes().is_empty());
+    assert_eq!(host.call_routed("users", b"").await.unwrap().1, b"users");
+}
+
+#[tokio::test]
+async fn test_removed_entries_fail_normally_after_the_grace_period() {
+    let _serial = SERIAL.lock().await;
+    let mut host = load(vec!["users", "orders"]);
+    host.set_entry_tombstone_grace(Duration::from_millis(50));
+
+    *ENTRIES.lock().unwrap() = vec!["orders"];
+    host.reload_one("api").unwrap();
+    let plugin = host.plugin("api").unwrap();
+    assert!(matches!(
+        plugin.call_response_fast("users", b"").await,
+        Err(NylonRingHostError::EntryRemovedInVersion { .. })
+    ));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(matches!(
+        plugin.call_response_fast("users", b"").await,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{EntryLimit, NylonRingHost, NylonRingHostError, PluginHandle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+/// Calls to `work` running now, and the most there were at once.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+// Tests read the counters and load through `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Answer after as many milliseconds as the payload says, from a thread.
+unsafe fn handle_work(sid: u64, payload: NrBytes) -> NrStatus {
+    let millis: u64 = std::str::from_utf8(payload.as_slice())
+        .unwrap()
+        .parse()
+        .unwrap();
+    let host_ctx = HOST_CTX.load(Ordering::Acquire) as usize;
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    std::thread::spawn(move || {
+        let running = RUNNING.fetch_add(1, Ordering::AcqRel) + 1;
+        MAX_RUNNING.fetch_max(running, Ordering::AcqRel);
+        std::thread::sleep(Duration::from_millis(millis));
+        RUNNING.fetch_sub(1, Ordering::AcqRel);
+        (vtable.send_result)(host_ctx as *mut _, sid, NrStatus::Ok, NrVec::default());
+    });
+    NrStatus::Accepted
+}
+
+unsafe fn handle_quick(sid: u64, _payload: NrBytes) -> NrStatus {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    (vtable.send_result)(host_ctx, sid, NrStatus::Ok, NrVec::default());
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "work" => handle_work,
+        "quick" => handle_quick,
+    }
+}
+
+fn host() -> NylonRingHost {
+    RUNNING.store(0, Ordering::Release);
+    MAX_RUNNING.store(0, Ordering::Release);
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+fn work(
+    plugin: &PluginHandle,
+    millis: u64,
+) -> tokio::task::JoinHandle<Result<(), NylonRingHostError>> {
+    let plugin = plugin.clone();
+    tokio::spawn(async move {
+        plugin
+            .call_response("work", millis.to_string().as_bytes())
+            .await
+            .map(|_| ())
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_limit_bounds_concurrent_calls() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    plugin.set_entry_limit("work", 4, 16).unwrap();
+    assert_eq!(
+        plugin.entry_limits(),
+        [(
+            "work".to_string(),
+            EntryLimit {
+                max_concurrent: 4,
+                queue_depth: 16,
+            }
+        )]
+    );
+
+    let calls: Vec<_> = (0..12).map(|_| work(&plugin, 20)).collect();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    // Other entries are not held up by the queue.
+    plugin.call_response("quick", b"").await.unwrap();
+    for call in calls {
+        call.await.unwrap().unwrap();
+    }
+    assert_eq!(MAX_RUNNING.load(Ordering::Acquire), 4);
+
+    assert!(plugin.remove_entry_limit("work").is_some());
+    assert!(plugin.entry_limits().is_empty());
+    let calls: Vec<_> = (0..6).map(|_| work(&plugin, 50)).collect();
+    for call in calls {
+        call.await.unwrap().unwrap();
+    }
+    assert!(MAX_RUNNING.load(Ordering::Acquire) > 4);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_full_queue_fails_fast() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    plugin.set_entry_limit("work", 1, 1).unwrap();
+
+    let running = work(&plugin, 100);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let queued = work(&plugin, 0);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let err = plugin.call_response("work", b"0").await.unwrap_err();
+    assert!(matches!(
+        &err,
+        NylonRingHostError::EntryOverloaded {
+            entry,
+            max_concurrent: 1,
+            queue_depth: 1,
+            ..
+        } if entry == "work"
+    ));
+    running.await.unwrap().unwrap();
+    queued.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_abandoned_call_leaves_the_queue() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    plugin.set_entry_limit("work", 1, 1).unwrap();
+
+    let running = work(&plugin, 100);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(10),
+        plugin.call_response("work", b"0"),
+    )
+    .await;
+    assert!(abandoned.is_err());
+
+    // The place it held is free again.
+    plugin.call_response("work", b"0").await.unwrap();
+    running.await.unwrap().unwrap();
+    assert_eq!(MAX_RUNNING.load(Ordering::Acquire), 1);
+}
+
+#[test]
+fn test_zero_limit_is_rejected() {
+    let _serial = SERIAL.blocking_lock();
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    assert!(matches!(
+        plugin.set_entry_limit("work", 0, 4),
+        Err(NylonRingHostError::InvalidConfig(_))
+    ));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+// Each test loads the plugin, which overwrites `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn respond(sid: u64, status: NrStatus, data: NrVec<u8>) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+
+        (vtable.send_result)(HOST_CTX.load(Ordering::Acquire), sid, status, data);
+    }
+}
+
+/// Fail with the payload as the message of a structured error.
+unsafe fn handle_reject(sid: u64, payload: NrBytes) -> NrStatus {
+    let message = String::from_utf8_lossy(payload.as_slice());
+    respond(sid, NrStatus::Err, nylon_ring::encode_error(404, &message));
+    NrStatus::Ok
+}
+
+/// Fail with the payload as free-form bytes.
+unsafe fn handle_fail(sid: u64, payload: NrBytes) -> NrStatus {
+    respond(sid, NrStatus::Err, NrVec::from_slice(payload.as_slice()));
+    NrStatus::Ok
+}
+
+/// Fail with the payload as free-form bytes, later and from another thread.
+unsafe fn handle_fail_later(sid: u64, payload: NrBytes) -> NrStatus {
+    let data = payload.as_slice().to_vec();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        respond(sid, NrStatus::Err, NrVec::from_vec(data));
+    });
+    NrStatus::Accepted
+}
+
+/// Refuse the call from `handle` itself, which cannot carry a body.
+unsafe fn handle_refuse(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Err
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "reject" => handle_reject,
+        "fail" => handle_fail,
+        "fail_later" => handle_fail_later,
+        "refuse" => handle_refuse,
+    }
+}
+
+fn is_not_found(result: Result<(NrStatus, Vec<u8>), NylonRingHostError>) -> bool {
+    matches!(
+        result,
+        Err(NylonRingHostError::PluginError { code: 404, message }) if message == "no such user"
+    )
+}
+
+#[tokio::test]
+async fn test_error_frames() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    host.load_static("errors", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("errors").unwrap();
+
+    assert!(is_not_found(
+        plugin.call_response("reject", b"no such user").await
+    ));
+    assert!(is_not_found(
+        plugin.call_response_fast("reject", b"no such user").await
+    ));
+
+    let (status, data) = plugin
+        .call_response_raw_error("reject", b"no such user")
+        .await
+        .unwrap();
+    assert_eq!(status, NrStatus::Err);
+    assert_eq!(
+        nylon_ring::decode_error(&data),
+        Some(nylon_ring::NrError {
+            code: 404,
+            message: "no such user".to_string(),
+        })
+    );
+
+    // Free-form error payloads are passed through as before.
+    let (status, data) = plugin.call_response("fail", b"oops").await.unwrap();
+    assert_eq!(status, NrStatus::Err);
+    assert_eq!(data, b"oops");
+}
+
+#[tokio::test]
+async fn test_sent_errors_keep_their_body() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    host.load_static("errors", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("errors").unwrap();
+
+    // An `Err` sent as the result is a response, wherever it comes from.
+    for result in [
+        plugin.call_response("fail_later", b"quota exceeded").await,
+        plugin.call_response_fast("fail", b"quota exceeded").await,
+        plugin
+            .call_response_fast("fail_later", b"quota exceeded")
+            .await,
+    ] {
+        let (status, data) = result.unwrap();
+        assert_eq!(status, NrStatus::Err);
+        assert_eq!(data, b"quota exceeded");
+    }
+
+    // Only an `Err` returned by `handle` fails the call.
+    for result in [
+        plugin.call_response("refuse", b"").await,
+        plugin.call_response_fast("refuse", b"").await,
+    ] {
+        assert!(matches!(
+            result,
+            Err(NylonRingHostError::PluginHandleFailed {
+                status: NrStatus::Err,
+                message: None,
+            })
+        ));
+    }
+}
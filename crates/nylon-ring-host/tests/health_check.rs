@@ -0,0 +1,74 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{testing, HealthStatus, NylonRingHost};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+const HEALTHY: u8 = 0;
+const FAILING: u8 = 1;
+const ANSWERS_ERR: u8 = 2;
+const SILENT: u8 = 3;
+
+/// How the next `__health` call answers.
+static MODE: AtomicU8 = AtomicU8::new(HEALTHY);
+
+// Tests set `MODE`, and the mock plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn handle_health(sid: u64, _payload: NrBytes) -> NrStatus {
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    match MODE.load(Ordering::Acquire) {
+        HEALTHY => (vtable.send_result)(host_ctx, sid, NrStatus::Ok, NrVec::default()),
+        FAILING => return NrStatus::Err,
+        ANSWERS_ERR => (vtable.send_result)(host_ctx, sid, NrStatus::Err, NrVec::default()),
+        // Promise an answer that never comes.
+        _ => return NrStatus::Accepted,
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "__health" => handle_health,
+    }
+}
+
+async fn check(mode: u8) -> HealthStatus {
+    MODE.store(mode, Ordering::Release);
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("p").unwrap();
+    plugin.health_check(Duration::from_millis(50)).await
+}
+
+#[tokio::test]
+async fn test_health_statuses() {
+    let _serial = SERIAL.lock().await;
+    assert_eq!(check(HEALTHY).await, HealthStatus::Healthy);
+    assert_eq!(check(FAILING).await, HealthStatus::Degraded(NrStatus::Err));
+    assert_eq!(
+        check(ANSWERS_ERR).await,
+        HealthStatus::Degraded(NrStatus::Err)
+    );
+    assert_eq!(check(SILENT).await, HealthStatus::Unreachable);
+}
+
+#[tokio::test]
+async fn test_plugins_without_health_entry_are_unknown() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    // Rejects unknown entries with `Unsupported`.
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    let plugin = host.plugin("mock").unwrap();
+    assert_eq!(
+        plugin.health_check(Duration::from_millis(50)).await,
+        HealthStatus::Unknown
+    );
+}
@@ -0,0 +1,171 @@
+//! Hosts built with `NylonRingHost::builder`.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+/// Calls `hold` has not answered yet.
+static HELD: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+// Tests share `HELD`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+/// Answer every held call with `Ok`.
+fn release() {
+    for sid in std::mem::take(&mut *HELD.lock().unwrap()) {
+        send(sid, NrStatus::Ok, b"released");
+    }
+}
+
+unsafe fn handle_hold(sid: u64, _payload: NrBytes) -> NrStatus {
+    HELD.lock().unwrap().push(sid);
+    NrStatus::Accepted
+}
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, payload.as_slice());
+    NrStatus::Ok
+}
+
+/// Send one frame per payload byte, then end the stream.
+unsafe fn handle_burst(sid: u64, payload: NrBytes) -> NrStatus {
+    for byte in payload.as_slice() {
+        send(sid, NrStatus::Ok, &[*byte]);
+    }
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "hold" => handle_hold,
+        "echo" => handle_echo,
+        "burst" => handle_burst,
+    }
+}
+
+fn load(mut host: NylonRingHost) -> NylonRingHost {
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_in_flight_limit_rejects_the_next_call() {
+    let _serial = SERIAL.lock().await;
+    let host = load(NylonRingHost::builder().max_in_flight(2).build().unwrap());
+    let plugin = host.plugin("p").unwrap();
+
+    let held: Vec<_> = (0..2)
+        .map(|_| {
+            let plugin = plugin.clone();
+            tokio::spawn(async move { plugin.call_response("hold", b"").await })
+        })
+        .collect();
+    while plugin.in_flight() < 2 {
+        tokio::task::yield_now().await;
+    }
+
+    assert!(matches!(
+        plugin.call_response("echo", b"").await,
+        Err(NylonRingHostError::Overloaded { plugin, limit: 2 }) if plugin == "p"
+    ));
+    assert!(matches!(
+        plugin.call_stream("burst", b"").await,
+        Err(NylonRingHostError::Overloaded { .. })
+    ));
+
+    release();
+    for call in held {
+        assert_eq!(call.await.unwrap().unwrap().1, b"released");
+    }
+    assert_eq!(plugin.call_response("echo", b"ok").await.unwrap().1, b"ok");
+}
+
+#[tokio::test]
+async fn test_call_timeout() {
+    let _serial = SERIAL.lock().await;
+    let timeout = Duration::from_millis(50);
+    let host = load(
+        NylonRingHost::builder()
+            .call_timeout(timeout)
+            .build()
+            .unwrap(),
+    );
+    let plugin = host.plugin("p").unwrap();
+
+    assert!(matches!(
+        plugin.call_response("hold", b"").await,
+        Err(NylonRingHostError::Timeout(t)) if t == timeout
+    ));
+    assert_eq!(plugin.in_flight(), 0);
+    // The late answer finds nobody waiting.
+    release();
+    assert_eq!(plugin.call_response("echo", b"ok").await.unwrap().1, b"ok");
+}
+
+#[tokio::test]
+async fn test_stream_capacity_closes_lagging_streams() {
+    let _serial = SERIAL.lock().await;
+    let host = load(NylonRingHost::builder().stream_capacity(2).build().unwrap());
+    let plugin = host.plugin("p").unwrap();
+
+    let (_, mut rx) = plugin.call_stream("burst", b"ab").await.unwrap();
+    assert_eq!(rx.recv().await.unwrap().data, b"a");
+    assert_eq!(rx.recv().await.unwrap().data, b"b");
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+    assert!(!rx.overflowed());
+
+    let (_, mut rx) = plugin.call_stream("burst", b"abcd").await.unwrap();
+    assert_eq!(rx.recv().await.unwrap().data, b"a");
+    assert_eq!(rx.recv().await.unwrap().data, b"b");
+    assert!(rx.recv().await.is_none());
+    assert!(rx.overflowed());
+    assert_eq!(plugin.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_fast_path_can_be_disabled() {
+    let _serial = SERIAL.lock().await;
+    let host = load(NylonRingHost::builder().fast_path(false).build().unwrap());
+    let plugin = host.plugin("p").unwrap();
+
+    let (status, data) = plugin.call_response_fast("echo", b"hi").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"hi"[..]));
+}
+
+#[test]
+fn test_builder_validates_settings() {
+    for builder in [
+        NylonRingHost::builder().pending_shards(48),
+        NylonRingHost::builder().pending_shards(0),
+        NylonRingHost::builder().max_in_flight(0),
+        NylonRingHost::builder().stream_capacity(0),
+    ] {
+        assert!(matches!(
+            builder.build(),
+            Err(NylonRingHostError::InvalidConfig(_))
+        ));
+    }
+    assert!(NylonRingHost::builder().pending_shards(1).build().is_ok());
+}
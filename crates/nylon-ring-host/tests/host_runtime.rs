@@ -0,0 +1,84 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, OrderOptions};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        (vtable.send_result)(host_ctx, sid, status, NrVec::from_slice(data));
+    }
+}
+
+/// Answer with the name of the thread `handle` runs on.
+unsafe fn handle_where(sid: u64, _payload: NrBytes) -> NrStatus {
+    let thread = std::thread::current();
+    send(
+        sid,
+        NrStatus::Ok,
+        thread.name().unwrap_or_default().as_bytes(),
+    );
+    NrStatus::Ok
+}
+
+unsafe fn handle_stream(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, b"frame");
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "where" => handle_where,
+        "stream" => handle_stream,
+    }
+}
+
+#[test]
+fn test_background_tasks_run_on_the_given_runtime() {
+    let dispatch = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("nr-dispatch")
+        .enable_time()
+        .build()
+        .unwrap();
+    let main = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    let mut host = NylonRingHost::new_with_runtime(dispatch.handle().clone());
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    main.block_on(async {
+        // Broadcast calls run in tasks, on the dispatch runtime.
+        let results = host
+            .broadcast_response("where", b"", Duration::from_secs(5))
+            .await;
+        let (status, data) = results[0].1.as_ref().unwrap();
+        assert_eq!(*status, NrStatus::Ok);
+        assert_eq!(data, b"nr-dispatch");
+
+        // Direct calls run where they are awaited.
+        let plugin = host.plugin("p").unwrap();
+        let (_, data) = plugin.call_response("where", b"").await.unwrap();
+        assert_ne!(data, b"nr-dispatch");
+
+        // A stream reordered on the dispatch runtime.
+        let (_sid, mut rx) = plugin
+            .call_stream_ordered("stream", b"", OrderOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().data, b"frame");
+        assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+    });
+    drop(host);
+    drop(dispatch);
+}
@@ -0,0 +1,143 @@
+//! Unloading and shutting down plugins while calls are in flight.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrVec};
+use nylon_ring_host::NylonRingHost;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Send `frames` from a plugin thread after `delay` each, as the plugin
+/// loaded when the call came in.
+fn reply_later(sid: u64, delay: Duration, frames: Vec<(NrStatus, Vec<u8>)>) {
+    let host_ctx = HOST_CTX.load(Ordering::Acquire) as usize;
+    let vtable = HOST_VTABLE.load(Ordering::Acquire) as usize;
+    std::thread::spawn(move || {
+        for (status, data) in frames {
+            std::thread::sleep(delay);
+            unsafe {
+                let vtable = &*(vtable as *const NrHostVTable);
+                (vtable.send_result)(host_ctx as _, sid, status, NrVec::from_vec(data));
+            }
+        }
+    });
+}
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    let data = payload.as_slice().to_vec();
+    reply_later(sid, Duration::from_micros(200), vec![(NrStatus::Ok, data)]);
+    NrStatus::Accepted
+}
+
+unsafe fn handle_slow(sid: u64, payload: NrBytes) -> NrStatus {
+    let data = payload.as_slice().to_vec();
+    reply_later(sid, Duration::from_millis(100), vec![(NrStatus::Ok, data)]);
+    NrStatus::Accepted
+}
+
+unsafe fn handle_count(sid: u64, _payload: NrBytes) -> NrStatus {
+    let mut frames: Vec<_> = (1..=3u8).map(|n| (NrStatus::Ok, vec![n])).collect();
+    frames.push((NrStatus::StreamEnd, Vec::new()));
+    reply_later(sid, Duration::from_millis(30), frames);
+    NrStatus::Accepted
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "slow" => handle_slow,
+        "count" => handle_count,
+    }
+}
+
+fn load(host: &mut NylonRingHost) {
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn hammering_echo_while_unloading() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+
+    for round in 0..50u32 {
+        load(&mut host);
+        let plugin = host.plugin("p").unwrap();
+        let tasks: Vec<_> = (0..4u32)
+            .map(|task| {
+                let plugin = plugin.clone();
+                tokio::spawn(async move {
+                    for n in 0..20u32 {
+                        let payload = [round, task, n].map(u32::to_le_bytes).concat();
+                        let (status, data) = plugin.call_response("echo", &payload).await.unwrap();
+                        assert_eq!((status, data), (NrStatus::Ok, payload));
+                    }
+                })
+            })
+            .collect();
+        drop(plugin);
+        tokio::task::yield_now().await;
+        host.unload("p").unwrap();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn unload_waits_for_streams_in_flight() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    load(&mut host);
+
+    let plugin = host.plugin("p").unwrap();
+    let (_sid, mut rx) = plugin.call_stream("count", b"").await.unwrap();
+    assert_eq!(plugin.in_flight(), 1);
+    drop(plugin);
+
+    // The plugin finishes the stream before its callbacks are revoked.
+    host.unload("p").unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        frames.push((frame.status, frame.data));
+    }
+    assert_eq!(
+        frames,
+        [
+            (NrStatus::Ok, vec![1]),
+            (NrStatus::Ok, vec![2]),
+            (NrStatus::Ok, vec![3]),
+            (NrStatus::StreamEnd, vec![]),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shutdown_all_lets_calls_finish() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    load(&mut host);
+
+    let plugin = host.plugin("p").unwrap();
+    let call = tokio::spawn({
+        let plugin = plugin.clone();
+        async move { plugin.call_response("slow", b"late").await }
+    });
+    while plugin.in_flight() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let results = tokio::task::spawn_blocking(move || host.shutdown_all(Duration::from_secs(5)))
+        .await
+        .unwrap();
+    assert_eq!(results, [("p".to_string(), true)]);
+    assert_eq!(plugin.in_flight(), 0);
+    let (status, data) = call.await.unwrap().unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"late"[..]));
+}
@@ -0,0 +1,88 @@
+//! Plugins loaded from private copies of a library: in-memory images and
+//! isolated instances.
+
+mod common;
+
+use common::example_plugin;
+use nylon_ring_host::{NrStatus, NylonRingHost, PluginHandle};
+
+// Both tests point `TMPDIR` somewhere of their own, and the example plugin
+// keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Create the private copies of the test in a fresh directory.
+fn private_temp_dir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::env::set_var("TMPDIR", dir.path());
+    dir
+}
+
+fn files_in(dir: &tempfile::TempDir) -> usize {
+    std::fs::read_dir(dir.path()).unwrap().count()
+}
+
+/// `Ok` results the host attributed to `plugin`.
+fn ok_responses(plugin: &PluginHandle) -> u64 {
+    plugin
+        .metrics_snapshot()
+        .responses_by_status
+        .iter()
+        .find(|(status, _)| *status == NrStatus::Ok)
+        .map_or(0, |(_, count)| *count)
+}
+
+#[tokio::test]
+async fn test_load_from_bytes_removes_its_copy_on_unload() {
+    let _serial = SERIAL.lock().await;
+    let image = std::fs::read(example_plugin()).unwrap();
+    let dir = private_temp_dir();
+
+    let mut host = NylonRingHost::new();
+    host.load_from_bytes("memory", &image).unwrap();
+    assert_eq!(files_in(&dir), 1);
+
+    let plugin = host.plugin("memory").unwrap();
+    let (status, data) = plugin.call_response("echo", b"Hello").await.unwrap();
+    assert_eq!(
+        (status, data.as_slice()),
+        (NrStatus::Ok, &b"Hello, Nylon Ring!"[..])
+    );
+    drop(plugin);
+
+    host.unload("memory").unwrap();
+    assert_eq!(files_in(&dir), 0);
+    std::env::remove_var("TMPDIR");
+}
+
+#[tokio::test]
+async fn test_instances_have_their_own_statics() {
+    let _serial = SERIAL.lock().await;
+    let dir = private_temp_dir();
+    let path = example_plugin().to_str().unwrap();
+
+    let mut host = NylonRingHost::new();
+    host.load_instanced("auth-a", path).unwrap();
+    host.load_instanced("auth-b", path).unwrap();
+    assert_eq!(files_in(&dir), 2);
+
+    // Each instance answers through the `host_ctx` its own `init` stored.
+    // Had `auth-b` overwritten the static, its context would get the credit.
+    let (a, b) = (
+        host.plugin("auth-a").unwrap(),
+        host.plugin("auth-b").unwrap(),
+    );
+    for _ in 0..3 {
+        let (status, _) = a.call_response("echo", b"a").await.unwrap();
+        assert_eq!(status, NrStatus::Ok);
+    }
+    assert_eq!((ok_responses(&a), ok_responses(&b)), (3, 0));
+    let (status, _) = b.call_response("echo", b"b").await.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert_eq!((ok_responses(&a), ok_responses(&b)), (3, 1));
+
+    drop((a, b));
+    host.unload("auth-a").unwrap();
+    host.unload("auth-b").unwrap();
+    assert_eq!(files_in(&dir), 0);
+    std::env::remove_var("TMPDIR");
+}
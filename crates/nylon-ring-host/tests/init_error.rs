@@ -0,0 +1,90 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrStr, INIT_ERROR_KEY, INIT_SID};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::ffi::c_void;
+use std::sync::Mutex;
+
+/// What the next `init` does: `None` succeeds, `Some(reason)` fails and
+/// stores `reason` if it is non-empty, or panics with it if it starts with
+/// `"panic: "`.
+static NEXT_INIT: Mutex<Option<&str>> = Mutex::new(None);
+
+// Tests set `NEXT_INIT` and load through it.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+unsafe fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+    let Some(reason) = *NEXT_INIT.lock().unwrap() else {
+        return NrStatus::Ok;
+    };
+    if let Some(message) = reason.strip_prefix("panic: ") {
+        panic!("{message}");
+    }
+    if !reason.is_empty() {
+        let ext = &*((*host_vtable).get_host_ext)(host_ctx);
+        (ext.set_state)(
+            host_ctx,
+            INIT_SID,
+            NrStr::new(INIT_ERROR_KEY),
+            NrBytes::from_slice(reason.as_bytes()),
+        );
+    }
+    NrStatus::Err
+}
+
+fn shutdown() {}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    }
+}
+
+fn load(host: &mut NylonRingHost, next: Option<&'static str>) -> Result<(), NylonRingHostError> {
+    *NEXT_INIT.lock().unwrap() = next;
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+}
+
+#[test]
+fn init_failure_carries_plugin_reason() {
+    let _serial = SERIAL.lock().unwrap();
+    let mut host = NylonRingHost::new();
+
+    let err = load(&mut host, Some("missing config: DATABASE_URL")).unwrap_err();
+    assert!(matches!(
+        &err,
+        NylonRingHostError::PluginInitFailed { status: NrStatus::Err, message: Some(m) }
+            if m == "missing config: DATABASE_URL"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "plugin init failed with status: Err: missing config: DATABASE_URL"
+    );
+    assert!(host.plugin("p").is_none());
+
+    // The reason is consumed, so a later bare failure does not inherit it.
+    let err = load(&mut host, Some("")).unwrap_err();
+    assert!(matches!(
+        err,
+        NylonRingHostError::PluginInitFailed { message: None, .. }
+    ));
+
+    load(&mut host, None).unwrap();
+    assert!(host.plugin("p").is_some());
+}
+
+#[test]
+fn init_panic_is_the_reason() {
+    let _serial = SERIAL.lock().unwrap();
+    let mut host = NylonRingHost::new();
+
+    let err = load(&mut host, Some("panic: no config file")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "plugin init failed with status: Err: init panicked: no config file"
+    );
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::example_plugin;
+use nylon_ring_host::{
+    plugin_file_name, LoadDirOptions, LoadOutcome, NrStatus, NylonRingHost, NylonRingHostError,
+};
+use std::env::consts::DLL_PREFIX;
+use std::path::Path;
+use tokio::sync::Mutex;
+
+// The example plugin keeps the host context in a static, so hosts that load
+// it must not overlap.
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+fn install(dir: &Path, name: &str, manifest: Option<&str>) {
+    std::fs::copy(example_plugin(), dir.join(plugin_file_name(name))).unwrap();
+    if let Some(manifest) = manifest {
+        let path = dir.join(format!("{DLL_PREFIX}{name}.plugin.toml"));
+        std::fs::write(path, manifest).unwrap();
+    }
+}
+
+fn outcomes(report: &nylon_ring_host::LoadReport) -> Vec<(&str, bool)> {
+    report
+        .outcomes
+        .iter()
+        .map(|(name, outcome)| (name.as_str(), outcome.is_loaded()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_bad_library_does_not_abort_scan() {
+    let _serial = SERIAL.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    install(dir.path(), "valid", None);
+    std::fs::write(
+        dir.path().join(plugin_file_name("garbage")),
+        b"not a library",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("README.txt"), b"ignored").unwrap();
+
+    let mut host = NylonRingHost::new();
+    let report = host
+        .load_dir(dir.path(), LoadDirOptions::default())
+        .unwrap();
+
+    assert_eq!(outcomes(&report), [("garbage", false), ("valid", true)]);
+    assert!(matches!(
+        report.outcomes[0].1,
+        LoadOutcome::Failed(NylonRingHostError::FailedToLoadLibrary(_))
+    ));
+
+    let plugin = host.plugin("valid").unwrap();
+    let (status, data) = plugin.call_response("echo", b"Hello").await.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert_eq!(data, b"Hello, Nylon Ring!");
+}
+
+#[tokio::test]
+async fn test_manifests() {
+    let _serial = SERIAL.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    install(
+        dir.path(),
+        "first",
+        Some("name = \"echo-only\"\nentries = [\"echo\"]"),
+    );
+    install(dir.path(), "second", Some("enabled = false"));
+    install(dir.path(), "third", Some("enabled = \"no\""));
+
+    let mut host = NylonRingHost::new();
+    let report = host
+        .load_dir(dir.path(), LoadDirOptions::default())
+        .unwrap();
+
+    assert_eq!(outcomes(&report), [("echo-only", true), ("third", false)]);
+    assert!(matches!(
+        report.outcomes[1].1,
+        LoadOutcome::Failed(NylonRingHostError::InvalidManifest { .. })
+    ));
+    assert!(host.plugin("first").is_none());
+    assert!(host.plugin("second").is_none());
+
+    let plugin = host.plugin("echo-only").unwrap();
+    assert!(plugin.call_response("echo", b"Hi").await.is_ok());
+    assert!(matches!(
+        plugin.call_response("uppercase", b"Hi").await,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Unsupported,
+            ..
+        })
+    ));
+
+    // The allowlist survives a reload.
+    host.reload().unwrap();
+    let plugin = host.plugin("echo-only").unwrap();
+    assert!(matches!(
+        plugin.call_response("uppercase", b"Hi").await,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Unsupported,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_name_collisions() {
+    let _serial = SERIAL.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    install(dir.path(), "one", None);
+    install(dir.path(), "two", Some("name = \"one\""));
+
+    let mut host = NylonRingHost::new();
+    let report = host
+        .load_dir(dir.path(), LoadDirOptions::default())
+        .unwrap();
+    assert_eq!(outcomes(&report), [("one", true), ("one", false)]);
+    assert!(matches!(
+        &report.outcomes[1].1,
+        LoadOutcome::Failed(NylonRingHostError::PluginAlreadyLoaded(name)) if name == "one"
+    ));
+
+    // Loading the directory again collides with what is already loaded.
+    std::fs::remove_file(dir.path().join(plugin_file_name("two"))).unwrap();
+    let report = host
+        .load_dir(dir.path(), LoadDirOptions::default())
+        .unwrap();
+    assert_eq!(outcomes(&report), [("one", false)]);
+
+    let report = host
+        .load_dir(dir.path(), LoadDirOptions { overwrite: true })
+        .unwrap();
+    assert_eq!(outcomes(&report), [("one", true)]);
+    assert!(host.plugin("one").is_some());
+
+    assert!(matches!(
+        host.load_dir(dir.path().join("missing"), LoadDirOptions::default()),
+        Err(NylonRingHostError::ReadPluginDir { .. })
+    ));
+}
@@ -0,0 +1,96 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrPluginInfo, NrStatus};
+use nylon_ring_host::{LoadOutcome, LoadStrategy, NylonRingHost, NylonRingHostError, PluginSpec};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static SERIAL: Mutex<()> = Mutex::new(());
+
+unsafe fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+    LIVE.fetch_add(1, Ordering::SeqCst);
+    NrStatus::Ok
+}
+
+fn shutdown() {
+    LIVE.fetch_sub(1, Ordering::SeqCst);
+}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    }
+}
+
+fn info() -> &'static NrPluginInfo {
+    unsafe { &*nylon_ring_get_plugin_v1() }
+}
+
+fn specs() -> Vec<PluginSpec> {
+    vec![
+        PluginSpec::from_static("a", info()),
+        PluginSpec::from_static("b", info()),
+        PluginSpec::new("broken", "/nonexistent/libbroken.so"),
+        PluginSpec::from_static("c", info()).depends_on("broken"),
+        PluginSpec::from_static("d", info()),
+    ]
+}
+
+#[test]
+fn best_effort_commits_successes() {
+    let _serial = SERIAL.lock().unwrap();
+    let mut host = NylonRingHost::new();
+
+    let report = host.load_many(&specs(), LoadStrategy::BestEffort);
+    assert!(!report.is_success());
+
+    let names: Vec<&str> = report.outcomes.iter().map(|(n, _)| n.as_str()).collect();
+    assert_eq!(names, ["a", "b", "broken", "c", "d"]);
+    assert_eq!(report.loaded().collect::<Vec<_>>(), ["a", "b", "d"]);
+    assert!(matches!(
+        report.outcomes[2].1,
+        LoadOutcome::Failed(NylonRingHostError::FailedToLoadLibrary(_))
+    ));
+    assert!(matches!(
+        &report.outcomes[3].1,
+        LoadOutcome::Skipped { dependency } if dependency == "broken"
+    ));
+
+    assert!(host.plugin("a").is_some());
+    assert!(host.plugin("c").is_none());
+    assert!(host.plugin("d").is_some());
+    assert_eq!(LIVE.load(Ordering::SeqCst), 3);
+
+    drop(host);
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn all_or_nothing_rolls_back() {
+    let _serial = SERIAL.lock().unwrap();
+    let mut host = NylonRingHost::new();
+
+    let report = host.load_many(&specs(), LoadStrategy::AllOrNothing);
+    assert!(!report.is_success());
+    assert_eq!(report.loaded().count(), 0);
+    assert!(matches!(report.outcomes[0].1, LoadOutcome::RolledBack));
+    assert!(matches!(report.outcomes[4].1, LoadOutcome::RolledBack));
+
+    // Every staged plugin was shut down again.
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+    assert!(host.plugin("a").is_none());
+
+    let ok: Vec<PluginSpec> = specs()
+        .into_iter()
+        .filter(|s| !matches!(s.name(), "broken" | "c"))
+        .collect();
+    let report = host.load_many(&ok, LoadStrategy::AllOrNothing);
+    assert!(report.is_success());
+    assert_eq!(LIVE.load(Ordering::SeqCst), 3);
+}
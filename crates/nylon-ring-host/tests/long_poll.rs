@@ -0,0 +1,163 @@
+mod common;
+
+use nylon_ring::long_poll::LongPoll;
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{LongPollOptions, LongPollOutcome, NylonRingHost, PluginHandle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+/// Worker threads still holding a `LongPoll`.
+static WORKERS: AtomicUsize = AtomicUsize::new(0);
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+/// Answer from a worker thread, as a plugin waiting on an event source would.
+fn spawn_poll<F: FnOnce(LongPoll) + Send + 'static>(sid: u64, f: F) -> NrStatus {
+    let poll = unsafe {
+        LongPoll::new(
+            HOST_CTX.load(Ordering::Acquire),
+            HOST_VTABLE.load(Ordering::Acquire),
+            sid,
+        )
+    };
+    WORKERS.fetch_add(1, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        f(poll);
+        WORKERS.fetch_sub(1, Ordering::SeqCst);
+    });
+    NrStatus::Ok
+}
+
+/// An event arrives after 20ms.
+unsafe fn handle_event(sid: u64, _payload: NrBytes) -> NrStatus {
+    spawn_poll(sid, |poll| {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            let _ = tx.send(NrVec::from_slice(b"event"));
+        });
+        poll.wait_or_empty(Duration::from_secs(5), None, |wait| {
+            rx.recv_timeout(wait).ok()
+        });
+    })
+}
+
+/// No event arrives within the plugin's 50ms wait.
+unsafe fn handle_quiet(sid: u64, _payload: NrBytes) -> NrStatus {
+    spawn_poll(sid, |poll| {
+        poll.wait_or_empty(Duration::from_millis(50), None, |wait| {
+            std::thread::sleep(wait);
+            None
+        });
+    })
+}
+
+/// Reports progress every 30ms and answers after 300ms.
+unsafe fn handle_slow(sid: u64, _payload: NrBytes) -> NrStatus {
+    spawn_poll(sid, |poll| {
+        for _ in 0..10 {
+            std::thread::sleep(Duration::from_millis(30));
+            poll.progress();
+        }
+        poll.fulfill(NrStatus::Ok, NrVec::from_slice(b"late"));
+    })
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "event" => handle_event,
+        "quiet" => handle_quiet,
+        "slow" => handle_slow,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("poll", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("poll").unwrap();
+    (host, plugin)
+}
+
+/// Wait for workers that outlived their call, so they never send to a
+/// dropped host.
+async fn settle() {
+    while WORKERS.load(Ordering::SeqCst) > 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+fn options(max_wait_ms: u64, renew_via_progress: bool) -> LongPollOptions {
+    LongPollOptions {
+        max_wait: Duration::from_millis(max_wait_ms),
+        renew_via_progress,
+    }
+}
+
+#[tokio::test]
+async fn fulfilled_before_deadline() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let outcome = plugin
+        .call_long_poll("event", b"", options(2_000, false))
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome,
+        LongPollOutcome::Fulfilled(NrStatus::Ok, b"event".to_vec())
+    );
+
+    let metrics = plugin.metrics_snapshot();
+    assert_eq!(
+        (metrics.long_poll_fulfilled, metrics.long_poll_empty),
+        (1, 0)
+    );
+    assert_eq!(metrics.latency_samples, 0);
+    settle().await;
+}
+
+#[tokio::test]
+async fn empty_at_deadline() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    // The plugin gives up first and answers empty.
+    let outcome = plugin
+        .call_long_poll("quiet", b"", options(2_000, false))
+        .await
+        .unwrap();
+    assert_eq!(outcome, LongPollOutcome::Empty);
+
+    // The host gives up first; the plugin's progress does not count.
+    let outcome = plugin
+        .call_long_poll("slow", b"", options(100, false))
+        .await
+        .unwrap();
+    assert_eq!(outcome, LongPollOutcome::Empty);
+
+    let metrics = plugin.metrics_snapshot();
+    assert_eq!(
+        (metrics.long_poll_fulfilled, metrics.long_poll_empty),
+        (0, 2)
+    );
+    settle().await;
+}
+
+#[tokio::test]
+async fn progress_renews_deadline() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let outcome = plugin
+        .call_long_poll("slow", b"", options(100, true))
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome,
+        LongPollOutcome::Fulfilled(NrStatus::Ok, b"late".to_vec())
+    );
+    settle().await;
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{
+    HostMetricsSnapshot, NrStatus as HostStatus, NylonRingHost, NylonRingHostError,
+};
+use std::sync::atomic::Ordering;
+
+common::test_plugin_host!();
+
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        status,
+        NrVec::from_slice(data),
+    );
+}
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, payload.as_slice());
+    NrStatus::Ok
+}
+
+/// Accept a stream; frames are sent by the test.
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn handle_fire(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn handle_fail(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Err
+}
+
+unsafe fn stream_data(_sid: u64, _data: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_close(_sid: u64) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "fire" => handle_fire,
+        "fail" => handle_fail,
+        "open" => handle_open,
+    },
+    stream_handlers: {
+        data: stream_data,
+        close: stream_close,
+    }
+}
+
+#[tokio::test]
+async fn counters_after_mixed_workload() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    host.load_static("metered", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("metered").unwrap();
+
+    for _ in 0..3 {
+        let (status, data) = plugin.call_response("echo", b"hi").await.unwrap();
+        assert_eq!((status, data.as_slice()), (HostStatus::Ok, &b"hi"[..]));
+    }
+    for _ in 0..2 {
+        plugin.call_response_fast("echo", b"hi").await.unwrap();
+    }
+    plugin.call("fire", b"").await.unwrap();
+    assert!(plugin.call("fail", b"").await.is_err());
+    assert!(plugin.call_response("fail", b"").await.is_err());
+    // Done without an answer.
+    assert!(matches!(
+        plugin.call_response_fast("fire", b"").await,
+        Err(NylonRingHostError::OneshotClosed)
+    ));
+
+    // A stream stays in flight until its last frame.
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+    assert_eq!(plugin.metrics_snapshot().in_flight, 1);
+    assert_eq!(plugin.metrics_snapshot().latency_samples, 6);
+    unsafe { send(sid, NrStatus::StreamEnd, b"") };
+    while rx.recv().await.is_some() {}
+
+    let snapshot = plugin.metrics_snapshot();
+    assert_eq!(snapshot.calls, 10);
+    assert_eq!(snapshot.calls_by_entry["echo"], 5);
+    assert_eq!(snapshot.calls_by_entry["fire"], 2);
+    assert_eq!(snapshot.calls_by_entry["fail"], 2);
+    assert_eq!(snapshot.calls_by_entry["open"], 1);
+    assert_eq!(snapshot.errors, 3);
+    assert_eq!(snapshot.error_rate(), 0.3);
+    assert_eq!(snapshot.responses(HostStatus::Ok), 5);
+    assert_eq!(snapshot.responses(HostStatus::Err), 0);
+    assert_eq!(snapshot.responses(HostStatus::StreamEnd), 1);
+    assert_eq!(snapshot.in_flight, 0);
+    assert_eq!(snapshot.latency_samples, 7);
+    assert!(snapshot.latency_p50 <= snapshot.latency_p99);
+}
+
+#[tokio::test]
+async fn host_counters_span_plugins_and_streams() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    for name in ["a", "b"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+    let (a, b) = (host.plugin("a").unwrap(), host.plugin("b").unwrap());
+
+    a.call_response("echo", b"hello").await.unwrap();
+    b.call_response_fast("echo", b"hi").await.unwrap();
+    assert!(b.call("fail", b"x").await.is_err());
+
+    let (ended, mut rx) = a.call_stream("open", b"").await.unwrap();
+    let (cancelled, _rx) = b.call_stream("open", b"").await.unwrap();
+    assert_eq!(host.metrics().active_streams, 2);
+
+    unsafe {
+        send(ended, NrStatus::Ok, b"frame");
+        send(ended, NrStatus::StreamEnd, b"");
+    }
+    while rx.recv().await.is_some() {}
+    assert_eq!(host.metrics().active_streams, 1);
+    b.cancel_stream(cancelled).unwrap();
+
+    assert_eq!(
+        host.metrics(),
+        HostMetricsSnapshot {
+            calls: 5,
+            errors: 1,
+            bytes_sent: 8,
+            bytes_received: 12,
+            active_streams: 0,
+            dispatch_cache_hits: 0,
+            strict_violations: 0,
+        }
+    );
+}
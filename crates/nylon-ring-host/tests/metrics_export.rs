@@ -0,0 +1,36 @@
+#![cfg(feature = "metrics-export")]
+
+use nylon_ring_host::{metrics, testing, NylonRingHost};
+
+/// Replace the values that depend on timing: latency buckets below `+Inf`
+/// and the latency sum.
+fn mask_timings(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let timed =
+                (line.contains("_bucket{") && !line.contains("+Inf")) || line.contains("_sum{");
+            match line.rsplit_once(' ') {
+                Some((series, _)) if timed => format!("{series} <timing>\n"),
+                _ => format!("{line}\n"),
+            }
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_render_prometheus_matches_golden_file() {
+    let mut host = NylonRingHost::builder().pending_shards(2).build().unwrap();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    let plugin = host.plugin("mock").unwrap();
+    plugin.call_response("echo", b"hello").await.unwrap();
+    plugin.call_response("echo", b"again").await.unwrap();
+    // Rejected with `Unsupported`, but counted under its escaped name.
+    let _ = plugin.call_response("odd\\\"entry\"\n\r", b"").await;
+
+    let text = metrics::render_prometheus(&host);
+    assert!(text.ends_with("# EOF\n"));
+    assert_eq!(
+        mask_timings(&text),
+        include_str!("golden/metrics_export.txt")
+    );
+}
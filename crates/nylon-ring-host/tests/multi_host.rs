@@ -0,0 +1,86 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrVec};
+use nylon_ring_host::{sid_epoch, NylonRingHost};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// `host_ctx` of each host the plugin was loaded into, in load order.
+static HOST_CTXS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
+/// An open stream on each host.
+static STREAM_SIDS: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+unsafe fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+    HOST_CTXS.lock().unwrap().push(host_ctx as usize);
+    HOST_VTABLE.store(host_vtable as *mut _, Ordering::Release);
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn send(host: usize, sid: u64, data: &[u8]) {
+    let ctx = HOST_CTXS.lock().unwrap()[host] as *mut c_void;
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(ctx, sid, NrStatus::Ok, NrVec::from_slice(data));
+}
+
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+/// Payload is `[host index, n]`. Before answering, emit a frame on the other
+/// host's stream from inside this call, as a worker shared between the two
+/// hosts would.
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    let data = payload.as_slice();
+    let (host, other) = (data[0] as usize, 1 - data[0] as usize);
+    send(other, STREAM_SIDS[other].load(Ordering::Acquire), b"stray");
+    send(host, sid, data);
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "open" => handle_open,
+        "echo" => handle_echo,
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn interleaved_fast_calls_never_cross_hosts() {
+    let mut hosts = [NylonRingHost::new(), NylonRingHost::new()];
+    for host in &mut hosts {
+        host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+    assert_ne!(hosts[0].epoch(), hosts[1].epoch());
+
+    let plugins = [hosts[0].plugin("p").unwrap(), hosts[1].plugin("p").unwrap()];
+    let mut streams = Vec::new();
+    for (i, plugin) in plugins.iter().enumerate() {
+        let (sid, rx) = plugin.call_stream("open", b"").await.unwrap();
+        assert_eq!(sid_epoch(sid), hosts[i].epoch());
+        STREAM_SIDS[i].store(sid, Ordering::Release);
+        streams.push(rx);
+    }
+
+    for n in 0..16u8 {
+        for (i, plugin) in plugins.iter().enumerate() {
+            let payload = [i as u8, n];
+            let (status, data) = plugin.call_response_fast("echo", &payload).await.unwrap();
+            assert_eq!((status, data.as_slice()), (NrStatus::Ok, &payload[..]));
+        }
+    }
+
+    // Every stray frame reached the stream it was addressed to.
+    for rx in &mut streams {
+        let mut frames = 0;
+        while let Ok(frame) = rx.try_recv() {
+            assert_eq!(frame.data, b"stray");
+            frames += 1;
+        }
+        assert_eq!(frames, 16);
+    }
+}
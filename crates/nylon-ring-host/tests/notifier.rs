@@ -0,0 +1,145 @@
+use nylon_ring::{batch_items, define_plugin, NrBytes, NrHostVTable, NrStatus};
+use nylon_ring_host::{NotifierOptions, NylonRingHost, NylonRingHostError, PluginHandle};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Batches the plugin received, as their events.
+static BATCHES: Mutex<Vec<Vec<Vec<u8>>>> = Mutex::new(Vec::new());
+// Whether `events` rejects its batches.
+static REJECT: AtomicBool = AtomicBool::new(false);
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_events(_sid: u64, payload: NrBytes) -> NrStatus {
+    if REJECT.load(Ordering::SeqCst) {
+        return NrStatus::Err;
+    }
+    let events = batch_items(payload.as_slice()).map(<[u8]>::to_vec);
+    BATCHES.lock().unwrap().push(events.collect());
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "events" => handle_events,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    BATCHES.lock().unwrap().clear();
+    REJECT.store(false, Ordering::SeqCst);
+    let mut host = NylonRingHost::new();
+    host.load_static("sink", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("sink").unwrap();
+    (host, plugin)
+}
+
+fn batch_sizes() -> Vec<usize> {
+    BATCHES.lock().unwrap().iter().map(Vec::len).collect()
+}
+
+const UNTIMED: NotifierOptions = NotifierOptions {
+    max_events: 3,
+    max_bytes: usize::MAX,
+    max_delay: None,
+    capacity: 5,
+};
+
+#[tokio::test]
+async fn test_flush_on_event_count() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let notifier = plugin.notifier("events", UNTIMED);
+
+    for event in [b"a", b"b", b"c", b"d"] {
+        notifier.push(event).unwrap();
+    }
+    assert_eq!(batch_sizes(), [3]);
+    assert_eq!(BATCHES.lock().unwrap()[0], [b"a", b"b", b"c"]);
+    assert_eq!(notifier.buffered(), 1);
+
+    assert_eq!(notifier.flush().unwrap(), 1);
+    assert_eq!(notifier.flush().unwrap(), 0);
+    assert_eq!(batch_sizes(), [3, 1]);
+}
+
+#[tokio::test]
+async fn test_flush_on_bytes() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let notifier = plugin.notifier(
+        "events",
+        NotifierOptions {
+            max_events: usize::MAX,
+            // Two 6-byte events with their 4-byte lengths.
+            max_bytes: 20,
+            ..UNTIMED
+        },
+    );
+
+    notifier.push(b"123456").unwrap();
+    assert!(batch_sizes().is_empty());
+    notifier.push(b"123456").unwrap();
+    assert_eq!(batch_sizes(), [2]);
+}
+
+#[tokio::test]
+async fn test_flush_on_timer_and_drop() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let notifier = plugin.notifier(
+        "events",
+        NotifierOptions {
+            max_delay: Some(Duration::from_millis(20)),
+            ..UNTIMED
+        },
+    );
+
+    notifier.push(b"late").unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(batch_sizes(), [1]);
+
+    notifier.push(b"last").unwrap();
+    drop(notifier);
+    assert_eq!(batch_sizes(), [1, 1]);
+    assert_eq!(BATCHES.lock().unwrap()[1], [b"last"]);
+}
+
+#[tokio::test]
+async fn test_rejected_batches_drop_oldest() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let notifier = plugin.notifier("events", UNTIMED);
+
+    REJECT.store(true, Ordering::SeqCst);
+    for event in [b"1", b"2"] {
+        notifier.push(event).unwrap();
+    }
+    assert!(matches!(
+        notifier.push(b"3"),
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Err,
+            ..
+        })
+    ));
+    for event in [b"4", b"5", b"6", b"7"] {
+        let _ = notifier.push(event);
+    }
+    // Five fit; the two oldest are gone.
+    assert_eq!(notifier.buffered(), 5);
+    assert_eq!(notifier.lost(), 2);
+
+    REJECT.store(false, Ordering::SeqCst);
+    assert_eq!(notifier.flush().unwrap(), 5);
+    assert_eq!(BATCHES.lock().unwrap()[0], [b"3", b"4", b"5", b"6", b"7"]);
+}
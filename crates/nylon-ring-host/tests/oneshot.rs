@@ -0,0 +1,131 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::oneshot::{self, OneshotOptions};
+use nylon_ring_host::{NylonRingHostError, PluginSpec};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!(
+    on_init: |_host_ctx, _host_vtable| {
+        LIVE.fetch_add(1, Ordering::SeqCst);
+    },
+    on_shutdown: {
+        LIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+);
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+unsafe fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        status,
+        NrVec::from_slice(data),
+    );
+}
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, payload.as_slice());
+    NrStatus::Ok
+}
+
+unsafe fn handle_count(sid: u64, _payload: NrBytes) -> NrStatus {
+    for n in [b"1", b"2"] {
+        send(sid, NrStatus::Ok, n);
+    }
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+/// Accepts the call and never answers.
+unsafe fn handle_hang(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Accepted
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "count" => handle_count,
+        "hang" => handle_hang,
+    }
+}
+
+fn spec() -> PluginSpec {
+    PluginSpec::from_static("tool", unsafe { &*nylon_ring_get_plugin_v1() })
+}
+
+#[tokio::test]
+async fn call_returns_response_and_unloads() {
+    let _serial = SERIAL.lock().await;
+    let (status, data) = oneshot::call_spec(&spec(), "echo", b"hi", OneshotOptions::default())
+        .await
+        .unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"hi"[..]));
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+
+    let frames = oneshot::call_stream_spec(&spec(), "count", b"", OneshotOptions::default())
+        .await
+        .unwrap();
+    let frames: Vec<(NrStatus, &[u8])> = frames
+        .iter()
+        .map(|f| (f.status, f.data.as_slice()))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            (NrStatus::Ok, &b"1"[..]),
+            (NrStatus::Ok, &b"2"[..]),
+            (NrStatus::StreamEnd, &b""[..])
+        ]
+    );
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn missing_entry_unloads() {
+    let _serial = SERIAL.lock().await;
+    let err = oneshot::call_spec(&spec(), "nope", b"", OneshotOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        }
+    ));
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn timeout_unloads() {
+    let _serial = SERIAL.lock().await;
+    let options = OneshotOptions {
+        timeout: Some(Duration::from_secs(5)),
+    };
+    let err = oneshot::call_spec(&spec(), "hang", b"", options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, NylonRingHostError::Timeout(t) if t == Duration::from_secs(5)));
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn missing_library_fails_cleanly() {
+    let err = oneshot::call(
+        "/nonexistent/libtool.so",
+        "echo",
+        b"",
+        OneshotOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, NylonRingHostError::FailedToLoadLibrary(_)));
+}
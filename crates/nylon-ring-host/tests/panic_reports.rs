@@ -0,0 +1,84 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus};
+use nylon_ring_host::{NrStatus as HostStatus, NylonRingHost, NylonRingHostError};
+use std::ffi::c_void;
+
+unsafe fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn handle_panic(_sid: u64, payload: NrBytes) -> NrStatus {
+    panic!("boom: {}", String::from_utf8_lossy(payload.as_slice()));
+}
+
+unsafe fn handle_panic_any(_sid: u64, _payload: NrBytes) -> NrStatus {
+    std::panic::panic_any(404);
+}
+
+unsafe fn handle_panic_opaque(_sid: u64, _payload: NrBytes) -> NrStatus {
+    std::panic::panic_any(vec![1u8]);
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+        "panic" => handle_panic,
+        "panic_any" => handle_panic_any,
+        "panic_opaque" => handle_panic_opaque,
+    }
+}
+
+#[tokio::test]
+async fn panic_is_reported_to_host() {
+    // Backtraces are only captured on request.
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let mut host = NylonRingHost::new();
+    host.load_static("panicky", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("panicky").unwrap();
+
+    assert_eq!(plugin.call("ok", b"").await.unwrap(), HostStatus::Ok);
+    assert!(plugin.panic_reports().is_empty());
+
+    let err = plugin.call("panic", b"payload-42").await.unwrap_err();
+    assert!(matches!(
+        &err,
+        NylonRingHostError::PluginHandleFailed {
+            status: HostStatus::Err,
+            message: Some(m),
+        } if m == "boom: payload-42"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "plugin handle failed immediately with status: Err: boom: payload-42"
+    );
+
+    let reports = plugin.panic_reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].entry, "panic");
+    assert_eq!(reports[0].message, "boom: payload-42");
+    assert!(!reports[0].backtrace.is_empty());
+}
+
+#[tokio::test]
+async fn non_string_panics_are_described() {
+    let mut host = NylonRingHost::new();
+    host.load_static("panicky", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("panicky").unwrap();
+
+    let err = plugin.call("panic_any", b"").await.unwrap_err();
+    assert!(err.to_string().ends_with("status: Err: 404"), "{err}");
+    let err = plugin.call("panic_opaque", b"").await.unwrap_err();
+    assert!(
+        err.to_string().ends_with("status: Err: Box<dyn Any>"),
+        "{err}"
+    );
+}
@@ -0,0 +1,110 @@
+//! Records plugins send with `nr_log!` reach the host's logger.
+
+mod common;
+
+use log::kv::Key;
+use nylon_ring::{define_plugin, nr_log, NrBytes, NrLogLevel, NrStatus, NrStr};
+use nylon_ring_host::NylonRingHost;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+common::test_plugin_host!();
+
+unsafe fn handle_work(_sid: u64, payload: NrBytes) -> NrStatus {
+    let job = String::from_utf8_lossy(payload.as_slice());
+    nr_log!(NrLogLevel::Info, "working on {job}");
+    nr_log!(NrLogLevel::Error, "{job} failed");
+    nr_log!(NrLogLevel::Trace, "filtered out by the host");
+    NrStatus::Ok
+}
+
+/// Log through the extension table with a level no `NrLogLevel` stands for.
+unsafe fn handle_bad_level(_sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = ((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        ((*ext).log)(host_ctx, 42, NrStr::new("raw"), NrStr::new("odd level"));
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "work" => handle_work,
+        "bad_level" => handle_bad_level,
+    }
+}
+
+/// `(level, target, plugin, message)`.
+type Record = (log::Level, String, Option<String>, String);
+
+/// Every record logged.
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Debug
+    }
+
+    fn log(&self, record: &log::Record) {
+        let plugin = record
+            .key_values()
+            .get(Key::from("plugin"))
+            .map(|plugin| plugin.to_string());
+        RECORDS.lock().unwrap().push((
+            record.level(),
+            record.target().to_string(),
+            plugin,
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+#[tokio::test]
+async fn test_plugin_records_reach_the_host_logger() {
+    log::set_logger(&Capture).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let mut host = NylonRingHost::new();
+    host.load_static("worker", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("worker").unwrap();
+    plugin.call("work", b"job-7").await.unwrap();
+    plugin.call("bad_level", b"").await.unwrap();
+
+    let records: Vec<_> = std::mem::take(&mut *RECORDS.lock().unwrap())
+        .into_iter()
+        .filter(|(_, _, plugin, _)| plugin.is_some())
+        .collect();
+    let worker = Some("worker".to_string());
+    assert_eq!(
+        records,
+        [
+            (
+                log::Level::Info,
+                module_path!().to_string(),
+                worker.clone(),
+                "working on job-7".to_string()
+            ),
+            (
+                log::Level::Error,
+                module_path!().to_string(),
+                worker.clone(),
+                "job-7 failed".to_string()
+            ),
+            // Unknown levels are logged at `Info`.
+            (
+                log::Level::Info,
+                "raw".to_string(),
+                worker,
+                "odd level".to_string()
+            ),
+        ]
+    );
+}
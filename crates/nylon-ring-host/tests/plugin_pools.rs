@@ -0,0 +1,71 @@
+//! Calls spread over a pool of plugin instances.
+
+use nylon_ring_host::{testing, NylonRingHost, NylonRingHostError};
+
+/// A host with the mock plugin loaded as `worker-0`, `worker-1` and
+/// `worker-2`, pooled as `workers`.
+fn workers() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    for name in ["worker-0", "worker-1", "worker-2"] {
+        host.load_static(name, testing::mock_plugin()).unwrap();
+    }
+    host.register_pool("workers", &["worker-0", "worker-1", "worker-2"])
+        .unwrap();
+    host
+}
+
+fn calls(host: &NylonRingHost, name: &str) -> u64 {
+    host.plugin(name).unwrap().metrics_snapshot().calls
+}
+
+#[tokio::test]
+async fn test_calls_rotate_over_members() {
+    let host = workers();
+    for _ in 0..6 {
+        let (_, data) = host
+            .call_pool_response("workers", "echo", b"hi")
+            .await
+            .unwrap();
+        assert_eq!(data, b"hi");
+    }
+    for name in ["worker-0", "worker-1", "worker-2"] {
+        assert_eq!(calls(&host, name), 2);
+    }
+}
+
+#[tokio::test]
+async fn test_unloaded_members_are_skipped() {
+    let mut host = workers();
+    host.unload("worker-1").unwrap();
+    for _ in 0..4 {
+        host.call_pool_response("workers", "echo", b"")
+            .await
+            .unwrap();
+    }
+    assert_eq!(calls(&host, "worker-0") + calls(&host, "worker-2"), 4);
+
+    host.unload("worker-0").unwrap();
+    host.unload("worker-2").unwrap();
+    assert!(matches!(
+        host.call_pool_response("workers", "echo", b"").await,
+        Err(NylonRingHostError::EmptyPool(pool)) if pool == "workers"
+    ));
+}
+
+#[tokio::test]
+async fn test_empty_and_unknown_pools_fail() {
+    let mut host = workers();
+    host.register_pool("idle", &[]).unwrap();
+    assert!(matches!(
+        host.call_pool_response("idle", "echo", b"").await,
+        Err(NylonRingHostError::EmptyPool(_))
+    ));
+    assert!(matches!(
+        host.call_pool_response("missing", "echo", b"").await,
+        Err(NylonRingHostError::PoolNotFound(pool)) if pool == "missing"
+    ));
+    assert!(matches!(
+        host.register_pool("workers", &["worker-0", "worker-9"]),
+        Err(NylonRingHostError::PluginNotFound(name)) if name == "worker-9"
+    ));
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{
+    testing, HealthPolicy, HealthState, NylonRingHost, NylonRingHostError, PluginHealth,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+/// Whether `work` fails, and whether the health check does.
+static WORK_FAILS: AtomicBool = AtomicBool::new(false);
+static CHECK_FAILS: AtomicBool = AtomicBool::new(false);
+
+// Tests toggle the flags and load through `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+unsafe fn handle_work(sid: u64, _payload: NrBytes) -> NrStatus {
+    if WORK_FAILS.load(Ordering::Acquire) {
+        return NrStatus::Err;
+    }
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    (vtable.send_result)(host_ctx, sid, NrStatus::Ok, NrVec::default());
+    NrStatus::Ok
+}
+
+unsafe fn check() -> NrStatus {
+    if CHECK_FAILS.load(Ordering::Acquire) {
+        NrStatus::Err
+    } else {
+        NrStatus::Ok
+    }
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "work" => handle_work,
+    },
+    health: check
+}
+
+const POLICY: HealthPolicy = HealthPolicy {
+    check_interval: Duration::from_millis(20),
+    failure_threshold: 3,
+    quarantine_duration: Duration::from_millis(100),
+};
+
+fn host() -> NylonRingHost {
+    WORK_FAILS.store(false, Ordering::Release);
+    CHECK_FAILS.store(false, Ordering::Release);
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+fn state(host: &NylonRingHost) -> HealthState {
+    host.health_report()[0].1.state
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_failing_plugin_is_quarantined_until_a_check_passes() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.set_health_policy("p", Some(POLICY)).unwrap();
+    let plugin = host.plugin("p").unwrap();
+    assert!(plugin.abi_details().provides("health"));
+
+    WORK_FAILS.store(true, Ordering::Release);
+    CHECK_FAILS.store(true, Ordering::Release);
+    for _ in 0..3 {
+        assert!(matches!(
+            plugin.call_response("work", b"").await,
+            Err(NylonRingHostError::PluginHandleFailed { .. })
+        ));
+    }
+    assert!(matches!(
+        plugin.call_response("work", b"").await,
+        Err(NylonRingHostError::Quarantined { plugin }) if plugin == "p"
+    ));
+    assert!(matches!(state(&host), HealthState::Quarantined { .. }));
+
+    // The plugin recovers, but its health check still fails.
+    WORK_FAILS.store(false, Ordering::Release);
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(state(&host), HealthState::AwaitingCheck);
+    assert!(plugin.call_response("work", b"").await.is_err());
+
+    CHECK_FAILS.store(false, Ordering::Release);
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(
+        host.health_report(),
+        [(
+            "p".to_string(),
+            PluginHealth {
+                state: HealthState::Healthy,
+                consecutive_failures: 0,
+                last_check: Some(NrStatus::Ok),
+                policy: Some(POLICY),
+            }
+        )]
+    );
+    plugin.call_response("work", b"").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_successes_reset_the_count() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.set_health_policy("p", Some(POLICY)).unwrap();
+    let plugin = host.plugin("p").unwrap();
+
+    for _ in 0..5 {
+        WORK_FAILS.store(true, Ordering::Release);
+        plugin.call_response("work", b"").await.unwrap_err();
+        plugin.call_response("work", b"").await.unwrap_err();
+        WORK_FAILS.store(false, Ordering::Release);
+        plugin.call_response("work", b"").await.unwrap();
+    }
+    assert_eq!(state(&host), HealthState::Healthy);
+
+    // Kept across a reload, with a clean record.
+    WORK_FAILS.store(true, Ordering::Release);
+    plugin.call_response("work", b"").await.unwrap_err();
+    host.reload_one("p").unwrap();
+    let report = &host.health_report()[0].1;
+    assert_eq!(report.policy, Some(POLICY));
+    assert_eq!(report.consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn test_policies_are_per_plugin() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    assert!(matches!(
+        host.set_health_policy(
+            "p",
+            Some(HealthPolicy {
+                failure_threshold: 0,
+                ..POLICY
+            })
+        ),
+        Err(NylonRingHostError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        host.set_health_policy("missing", Some(POLICY)),
+        Err(NylonRingHostError::PluginNotFound(_))
+    ));
+
+    host.set_health_policy("mock", Some(POLICY)).unwrap();
+    let report = host.health_report();
+    assert_eq!(report.len(), 2);
+    assert_eq!(report[0].0, "mock");
+    assert_eq!(report[0].1.policy, Some(POLICY));
+    assert_eq!(report[1].1.policy, None);
+    assert_eq!(report[1].1.state, HealthState::Healthy);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{testing, NylonRingHost, NylonRingHostError};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+common::test_plugin_host!(
+    on_init: |_host_ctx, _host_vtable| {
+        INITS.fetch_add(1, Ordering::SeqCst);
+    },
+    on_shutdown: {
+        SHUTDOWNS.fetch_add(1, Ordering::SeqCst);
+    }
+);
+
+static INITS: AtomicU32 = AtomicU32::new(0);
+static SHUTDOWNS: AtomicU32 = AtomicU32::new(0);
+
+/// Respond with how many times `init` has run.
+unsafe fn handle_generation(sid: u64, _payload: NrBytes) -> NrStatus {
+    let generation = INITS.load(Ordering::SeqCst).to_string();
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_string(generation),
+        );
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "generation" => handle_generation,
+    }
+}
+
+#[tokio::test]
+async fn test_reload_one() {
+    let mut host = NylonRingHost::new();
+    host.load_static("counter", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    let mock = host.plugin("mock").unwrap();
+    mock.call_response("echo", b"hi").await.unwrap();
+    let old = host.plugin("counter").unwrap();
+    assert_eq!(old.call_response("generation", b"").await.unwrap().1, b"1");
+
+    host.reload_one("counter").unwrap();
+    assert_eq!(INITS.load(Ordering::SeqCst), 2);
+    let new = host.plugin("counter").unwrap();
+    assert_eq!(new.call_response("generation", b"").await.unwrap().1, b"2");
+
+    // The old instance lives on until its last handle is dropped.
+    assert_eq!(SHUTDOWNS.load(Ordering::SeqCst), 0);
+    assert!(old.call_response("generation", b"").await.is_ok());
+    drop(old);
+    assert_eq!(SHUTDOWNS.load(Ordering::SeqCst), 1);
+
+    // Other plugins are untouched, so their metrics carry on.
+    let mock = host.plugin("mock").unwrap();
+    assert_eq!(mock.metrics_snapshot().calls, 1);
+
+    assert!(matches!(
+        host.reload_one("missing"),
+        Err(NylonRingHostError::PluginNotFound(name)) if name == "missing"
+    ));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrRequest, NrStatus, NrVec};
+use nylon_ring_host::{testing, HighLevelRequest, NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+// Tests load through `HOST_CTX`.
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        (vtable.send_result)(host_ctx, sid, status, NrVec::from_slice(data));
+    }
+}
+
+unsafe fn handle_plain(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, b"plain");
+    NrStatus::Ok
+}
+
+/// Answer with everything the plugin saw of the request.
+unsafe fn handle_route(sid: u64, req: &NrRequest, body: NrBytes) -> NrStatus {
+    let line = format!(
+        "{} {}?{} trace={} headers={} body={}",
+        req.method.as_str(),
+        req.path.as_str(),
+        req.query.as_str(),
+        req.header("x-trace").unwrap_or("-"),
+        req.headers().len(),
+        String::from_utf8_lossy(body.as_slice()),
+    );
+    send(sid, NrStatus::Ok, line.as_bytes());
+    NrStatus::Ok
+}
+
+/// One frame per header, then the end of the stream.
+unsafe fn handle_headers(sid: u64, req: &NrRequest, _body: NrBytes) -> NrStatus {
+    for kv in req.headers() {
+        send(sid, NrStatus::Ok, kv.key.as_str().as_bytes());
+    }
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "plain" => handle_plain,
+    },
+    req_entries: {
+        "route" => handle_route,
+        "headers" => handle_headers,
+    }
+}
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+fn request() -> HighLevelRequest {
+    let mut req = HighLevelRequest::new("POST", "/orders");
+    req.query = "page=2".to_string();
+    req.headers = vec![
+        ("X-Trace".to_string(), "abc".to_string()),
+        ("Accept".to_string(), "*/*".to_string()),
+    ];
+    req.body = b"{}".to_vec();
+    req.extensions.insert(7u32);
+    req
+}
+
+#[tokio::test]
+async fn test_plugin_sees_the_request() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    assert!(plugin.abi_details().provides("handle_req"));
+
+    let (status, data) = plugin.call_request("route", &request()).await.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert_eq!(data, b"POST /orders?page=2 trace=abc headers=2 body={}");
+
+    // Plain entries are not reachable through `handle_req`, nor the
+    // other way round.
+    assert!(matches!(
+        plugin.call_request("plain", &request()).await,
+        Err(NylonRingHostError::PluginHandleFailed { .. })
+    ));
+    assert!(plugin.call_response("route", b"").await.is_err());
+    let (_, data) = plugin.call_response("plain", b"").await.unwrap();
+    assert_eq!(data, b"plain");
+}
+
+#[tokio::test]
+async fn test_request_streams() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+
+    let (_sid, mut rx) = plugin
+        .call_request_stream("headers", &request())
+        .await
+        .unwrap();
+    let mut names = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        if frame.status != NrStatus::Ok {
+            break;
+        }
+        names.push(String::from_utf8(frame.data).unwrap());
+    }
+    assert_eq!(names, ["X-Trace", "Accept"]);
+}
+
+#[tokio::test]
+async fn test_plugins_without_handle_req() {
+    let mut host = NylonRingHost::new();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    let plugin = host.plugin("mock").unwrap();
+    assert!(matches!(
+        plugin
+            .call_request("echo", &HighLevelRequest::new("GET", "/"))
+            .await,
+        Err(NylonRingHostError::MissingFunction {
+            function: "handle_req",
+            ..
+        })
+    ));
+}
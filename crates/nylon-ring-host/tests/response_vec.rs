@@ -0,0 +1,74 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::NylonRingHost;
+use std::sync::atomic::Ordering;
+
+common::test_plugin_host!();
+
+// Loading the plugin overwrites `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Echo the payload in a buffer with spare capacity.
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(payload.as_slice());
+    send(sid, NrVec::from_vec(data));
+    NrStatus::Ok
+}
+
+/// Send a buffer claiming more bytes than it has room for.
+unsafe fn handle_malformed(sid: u64, _payload: NrBytes) -> NrStatus {
+    static BYTES: [u8; 4] = *b"oops";
+    send(
+        sid,
+        NrVec {
+            ptr: BYTES.as_ptr() as *mut u8,
+            len: 8,
+            cap: 4,
+        },
+    );
+    NrStatus::Ok
+}
+
+unsafe fn send(sid: u64, payload: NrVec<u8>) {
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(host_ctx, sid, NrStatus::Ok, payload);
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "malformed" => handle_malformed,
+    }
+}
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_response_keeps_the_plugins_buffer() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    let (status, data) = plugin.call_response_vec("echo", b"hello").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"hello"[..]));
+    // The capacity the plugin allocated survives, so nothing was copied.
+    assert_eq!(data.cap, 64);
+}
+
+#[tokio::test]
+async fn test_malformed_buffer_fails_the_call() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let plugin = host.plugin("p").unwrap();
+    let (status, data) = plugin.call_response("malformed", b"").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Invalid, &b""[..]));
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+// Plugin statics point at the most recently initialized instance, so tests
+// must not interleave.
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        NrStatus::Ok,
+        NrVec::from_slice(payload.as_slice()),
+    );
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "image.resize" => handle_echo,
+        "image.crop" => handle_echo,
+        "text.upper" => handle_echo,
+    }
+}
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    for name in ["images", "resizer", "text"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+    host.route("image.*", "images").unwrap();
+    host.route("image.resize", "resizer").unwrap();
+    host.route("text.*", "text").unwrap();
+    host
+}
+
+/// Calls the named plugin has seen for `entry`.
+fn calls(host: &NylonRingHost, plugin: &str, entry: &str) -> u64 {
+    let snapshot = host.plugin(plugin).unwrap().metrics_snapshot();
+    snapshot.calls_by_entry.get(entry).copied().unwrap_or(0)
+}
+
+#[tokio::test]
+async fn exact_routes_win_over_wildcards() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+
+    let (status, data) = host.call_routed("image.resize", b"big").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"big"[..]));
+    host.call_routed("image.crop", b"").await.unwrap();
+
+    assert_eq!(calls(&host, "resizer", "image.resize"), 1);
+    assert_eq!(calls(&host, "images", "image.resize"), 0);
+    assert_eq!(calls(&host, "images", "image.crop"), 1);
+
+    assert!(matches!(
+        host.call_routed("audio.play", b"").await,
+        Err(NylonRingHostError::NoRoute(entry)) if entry == "audio.play"
+    ));
+}
+
+#[tokio::test]
+async fn ambiguous_and_dangling_routes_are_rejected() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    assert!(matches!(
+        host.route("image.*", "text"),
+        Err(NylonRingHostError::AmbiguousRoute { .. })
+    ));
+    assert!(matches!(
+        host.route("video.*", "missing"),
+        Err(NylonRingHostError::PluginNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn unload_invalidates_routes() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.unload("resizer").unwrap();
+
+    // The exact route is gone; the wildcard picks the entry up.
+    host.call_routed("image.resize", b"").await.unwrap();
+    assert_eq!(calls(&host, "images", "image.resize"), 1);
+
+    host.unload("text").unwrap();
+    assert!(matches!(
+        host.call_routed("text.upper", b"").await,
+        Err(NylonRingHostError::NoRoute(_))
+    ));
+}
+
+#[tokio::test]
+async fn routes_follow_reload() {
+    let _serial = SERIAL.lock().await;
+    let mut host = host();
+    host.call_routed("text.upper", b"").await.unwrap();
+    assert_eq!(calls(&host, "text", "text.upper"), 1);
+
+    host.reload().unwrap();
+
+    // Reloaded instances start with fresh metrics, and routes resolve to them.
+    assert_eq!(calls(&host, "text", "text.upper"), 0);
+    host.call_routed("text.upper", b"").await.unwrap();
+    assert_eq!(calls(&host, "text", "text.upper"), 1);
+}
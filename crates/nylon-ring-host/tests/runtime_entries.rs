@@ -0,0 +1,83 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec, PluginBuilder};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+common::test_plugin_host!(on_init: |_host_ctx, _host_vtable| {
+    let mut builder = PluginBuilder::new().entry("ping", handle_ping);
+    for route in ROUTES.lock().unwrap().iter() {
+        builder = builder.raw_entry(*route, handle_route);
+    }
+    builder.install();
+});
+
+/// The routes the next `init` registers, standing in for a config file.
+static ROUTES: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+fn respond(sid: u64, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+unsafe fn handle_ping(sid: u64, _payload: NrBytes) -> NrStatus {
+    respond(sid, b"pong");
+    NrStatus::Ok
+}
+
+/// Respond with the entry that was called and the payload.
+unsafe fn handle_route(entry: &str, sid: u64, payload: NrBytes) -> NrStatus {
+    respond(sid, &[entry.as_bytes(), b":", payload.as_slice()].concat());
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: runtime,
+}
+
+#[tokio::test]
+async fn test_entries_registered_in_init() {
+    *ROUTES.lock().unwrap() = vec!["users", "orders"];
+    let mut host = NylonRingHost::new();
+    host.load_static("router", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("router").unwrap();
+
+    let call = |entry: &'static str| {
+        let plugin = plugin.clone();
+        async move { plugin.call_response(entry, b"42").await }
+    };
+    assert_eq!(call("ping").await.unwrap().1, b"pong");
+    assert_eq!(call("users").await.unwrap().1, b"users:42");
+    assert_eq!(call("orders").await.unwrap().1, b"orders:42");
+    assert!(matches!(
+        call("carts").await,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        })
+    ));
+
+    // A reload runs `init` again, which replaces the table.
+    *ROUTES.lock().unwrap() = vec!["carts"];
+    host.reload().unwrap();
+    let plugin = host.plugin("router").unwrap();
+    assert_eq!(
+        plugin.call_response("carts", b"1").await.unwrap().1,
+        b"carts:1"
+    );
+    assert!(matches!(
+        plugin.call_response("users", b"1").await,
+        Err(NylonRingHostError::EntryRemovedInVersion { entry, .. }) if entry == "users"
+    ));
+}
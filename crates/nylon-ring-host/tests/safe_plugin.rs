@@ -0,0 +1,113 @@
+//! A plugin written with `nylon_ring::Plugin` and exported by `impl_plugin!`.
+
+use nylon_ring::{impl_plugin, CallContext, HostApi, NrStatus, Plugin, Response};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+
+// Each host that loads the plugin replaces its one instance.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Default)]
+struct Greeter {
+    greeting: String,
+    calls: AtomicU64,
+}
+
+impl Plugin for Greeter {
+    fn init(&mut self, _host: &HostApi) -> NrStatus {
+        self.greeting = "hello".to_string();
+        SHUT_DOWN.store(false, Ordering::SeqCst);
+        NrStatus::Ok
+    }
+
+    fn entries(&self) -> &[&'static str] {
+        &["greet", "fail", "later", "count"]
+    }
+
+    fn handle(&self, ctx: &CallContext<'_>) -> Response {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match ctx.entry() {
+            "greet" => {
+                let name = String::from_utf8_lossy(ctx.payload());
+                Response::ok(format!("{} {name}", self.greeting))
+            }
+            "fail" => Response::error(404, "no such greeting"),
+            "later" => {
+                let host = *ctx.host();
+                let sid = ctx.sid();
+                let payload = ctx.payload().to_vec();
+                std::thread::spawn(move || host.send(sid, NrStatus::Ok, &payload));
+                Response::accepted()
+            }
+            "count" => Response::ok(self.calls.load(Ordering::Relaxed).to_string()),
+            _ => Response::reject(NrStatus::Invalid),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        SHUT_DOWN.store(true, Ordering::SeqCst);
+    }
+}
+
+impl_plugin!(Greeter);
+
+fn load() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("greeter", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_safe_plugin_answers() {
+    let _serial = SERIAL.lock().await;
+    let host = load();
+    let plugin = host.plugin("greeter").unwrap();
+
+    let (status, data) = plugin.call_response("greet", b"ring").await.unwrap();
+    assert_eq!(
+        (status, data.as_slice()),
+        (NrStatus::Ok, &b"hello ring"[..])
+    );
+
+    assert!(matches!(
+        plugin.call_response("fail", b"").await,
+        Err(NylonRingHostError::PluginError { code: 404, message })
+            if message == "no such greeting"
+    ));
+
+    let (_, data) = plugin.call_response("later", b"async").await.unwrap();
+    assert_eq!(data, b"async");
+
+    let (_, data) = plugin.call_response("count", b"").await.unwrap();
+    assert_eq!(data, b"4");
+
+    assert_eq!(
+        plugin.provided_entries().unwrap(),
+        ["count", "fail", "greet", "later"]
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_entries_are_rejected() {
+    let _serial = SERIAL.lock().await;
+    let host = load();
+    let plugin = host.plugin("greeter").unwrap();
+    assert!(matches!(
+        plugin.call_response("missing", b"").await,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_unload_shuts_the_plugin_down() {
+    let _serial = SERIAL.lock().await;
+    let mut host = load();
+    host.unload("greeter").unwrap();
+    assert!(SHUT_DOWN.load(Ordering::SeqCst));
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use nylon_ring::{
+    define_plugin, NrBytes, NrStatus, NrStr, NrVec, INIT_SID, REQUEST_SCHEMA_KEY_PREFIX,
+    RESPONSE_SCHEMA_KEY_PREFIX,
+};
+use nylon_ring_host::{BytesSchema, NylonRingHost, NylonRingHostError, PluginHandle, SchemaRule};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Mutex;
+
+common::test_plugin_host!(on_init: |host_ctx, host_vtable| {
+    let ext = &*((*host_vtable).get_host_ext)(host_ctx);
+    let publish = |prefix: &str, entry: &str, schema: &str| {
+        let key = format!("{prefix}{entry}");
+        (ext.set_state)(
+            host_ctx,
+            INIT_SID,
+            NrStr::new(&key),
+            NrBytes::from_slice(schema.as_bytes()),
+        );
+    };
+    publish(REQUEST_SCHEMA_KEY_PREFIX, "user", USER_SCHEMA);
+    publish(RESPONSE_SCHEMA_KEY_PREFIX, "user", r#"{"type": "object"}"#);
+});
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+/// Number of calls that reached the plugin.
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+const USER_SCHEMA: &str = r#"{
+    "type": "object",
+    "properties": {"id": {"type": "integer", "minimum": 1}},
+    "required": ["id"]
+}"#;
+
+fn respond(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+/// Respond with the payload.
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    HANDLED.fetch_add(1, Ordering::SeqCst);
+    respond(sid, NrStatus::Ok, payload.as_slice());
+    NrStatus::Ok
+}
+
+/// Respond with an error status and the payload as its message.
+unsafe fn handle_fail(sid: u64, payload: NrBytes) -> NrStatus {
+    HANDLED.fetch_add(1, Ordering::SeqCst);
+    respond(sid, NrStatus::Err, payload.as_slice());
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "user" => handle_echo,
+        "fail" => handle_fail,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("users", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("users").unwrap();
+    (host, plugin)
+}
+
+fn violation(result: Result<(NrStatus, Vec<u8>), NylonRingHostError>) -> Option<String> {
+    match result {
+        Err(NylonRingHostError::SchemaViolation { path, .. }) => Some(path),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn test_request_rejected_before_plugin() {
+    let _serial = SERIAL.lock().await;
+    let (mut host, plugin) = plugin();
+    host.register_schema("users", "echo", BytesSchema::new().prefix(b"v1:"))
+        .unwrap();
+
+    let handled = HANDLED.load(Ordering::SeqCst);
+    assert!(violation(plugin.call_response("echo", b"v2:x").await).is_some());
+    assert!(violation(plugin.call_response_fast("echo", b"v2:x").await).is_some());
+    assert_eq!(HANDLED.load(Ordering::SeqCst), handled);
+
+    assert!(plugin.call_response("echo", b"v1:x").await.is_ok());
+    assert!(plugin.call_response_fast("echo", b"v1:x").await.is_ok());
+    assert_eq!(HANDLED.load(Ordering::SeqCst), handled + 2);
+
+    // Entries without a rule are not checked.
+    assert!(plugin.call_response("fail", b"v2:x").await.is_ok());
+
+    assert!(matches!(
+        host.register_schema("missing", "echo", BytesSchema::new()),
+        Err(NylonRingHostError::PluginNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_response_validation() {
+    let _serial = SERIAL.lock().await;
+    let (mut host, plugin) = plugin();
+    let ok_prefix = || SchemaRule::new().response(BytesSchema::new().prefix(b"ok:"));
+    host.register_schema("users", "echo", ok_prefix()).unwrap();
+    host.register_schema("users", "fail", ok_prefix()).unwrap();
+
+    assert!(plugin.call_response("echo", b"ok:1").await.is_ok());
+    let handled = HANDLED.load(Ordering::SeqCst);
+    assert!(violation(plugin.call_response("echo", b"bad").await).is_some());
+    assert!(violation(plugin.call_response_fast("echo", b"bad").await).is_some());
+    // The plugin was called; only its response was rejected.
+    assert_eq!(HANDLED.load(Ordering::SeqCst), handled + 2);
+
+    // Error responses carry a message, not a payload, and are not checked.
+    let (status, data) = plugin.call_response("fail", b"bad").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Err, &b"bad"[..]));
+}
+
+#[tokio::test]
+async fn test_published_schemas() {
+    let _serial = SERIAL.lock().await;
+    let (mut host, plugin) = plugin();
+
+    assert!(plugin.call_response("user", br#"{"id": 7}"#).await.is_ok());
+    assert_eq!(
+        violation(plugin.call_response("user", br#"{"id": 0}"#).await).as_deref(),
+        Some("/id")
+    );
+    assert_eq!(
+        violation(plugin.call_response("user", b"{}").await).as_deref(),
+        Some("")
+    );
+    // The published response schema wants an object back.
+    assert!(violation(plugin.call_response("user", b"[]").await).is_some());
+
+    // A registered rule replaces the published one and survives a reload.
+    host.register_schema("users", "user", BytesSchema::new().max_len(2))
+        .unwrap();
+    host.reload().unwrap();
+    let plugin = host.plugin("users").unwrap();
+    assert!(plugin.call_response("user", b"{}").await.is_ok());
+    assert!(violation(plugin.call_response("user", br#"{"id": 7}"#).await).is_some());
+}
@@ -0,0 +1,207 @@
+//! Large payloads through the shared buffer arena.
+
+mod common;
+
+use nylon_ring::shared::{decode_shared_request, encode_shared_reply};
+use nylon_ring::{define_plugin, NrBytes, NrHostExt, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, SharedBufferStats};
+use std::ffi::c_void;
+use std::sync::atomic::Ordering;
+
+common::test_plugin_host!();
+
+// Tests load the plugin into hosts of their own, and it keeps one `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn host() -> (*mut c_void, &'static NrHostExt) {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = ((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        (host_ctx, &*ext)
+    }
+}
+
+fn send(sid: u64, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+fn upper(out: &mut [u8], body: &[u8]) -> usize {
+    for (out, byte) in out.iter_mut().zip(body) {
+        *out = byte.to_ascii_uppercase();
+    }
+    body.len()
+}
+
+/// Answer with the body uppercased, in the buffer of a shared request or
+/// in one acquired for a plain request.
+unsafe fn handle_upper(sid: u64, payload: NrBytes) -> NrStatus {
+    let (host_ctx, ext) = host();
+    let handle = match unsafe { decode_shared_request(payload.as_slice()) } {
+        Some(request) => {
+            let len = upper(unsafe { request.response_buf() }, request.body);
+            let status = unsafe { (ext.buf_commit)(host_ctx, request.handle, len as u64) };
+            (status == NrStatus::Ok).then_some(request.handle)
+        }
+        None => unsafe {
+            ext.write_shared(host_ctx, payload.as_slice().len(), |out| {
+                upper(out, payload.as_slice())
+            })
+        },
+    };
+    match handle {
+        Some(handle) => {
+            send(sid, &encode_shared_reply(handle));
+            NrStatus::Ok
+        }
+        None => NrStatus::Err,
+    }
+}
+
+/// Answer with whether the request came in a shared buffer.
+unsafe fn handle_kind(sid: u64, payload: NrBytes) -> NrStatus {
+    match unsafe { decode_shared_request(payload.as_slice()) } {
+        Some(_) => send(sid, b"shared"),
+        None => send(sid, b"plain"),
+    }
+    NrStatus::Ok
+}
+
+/// Commit more than acquired, then acquire without committing.
+unsafe fn handle_misuse(sid: u64, _payload: NrBytes) -> NrStatus {
+    let (host_ctx, ext) = host();
+    unsafe {
+        let (overrun, _) = (ext.buf_acquire)(host_ctx, 4).into_tuple();
+        assert_eq!((ext.buf_commit)(host_ctx, overrun, 5), NrStatus::Invalid);
+        assert_eq!((ext.buf_commit)(host_ctx, overrun, 4), NrStatus::Invalid);
+        assert_eq!((ext.buf_commit)(host_ctx, 0, 0), NrStatus::Invalid);
+        let (abandoned, _) = (ext.buf_acquire)(host_ctx, 4).into_tuple();
+        send(sid, &encode_shared_reply(abandoned));
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "upper" => handle_upper,
+        "kind" => handle_kind,
+        "misuse" => handle_misuse,
+    }
+}
+
+fn load(threshold: usize) -> NylonRingHost {
+    let mut host = NylonRingHost::builder()
+        .shared_threshold(threshold)
+        .build()
+        .unwrap();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_requests_above_the_threshold_are_shared() {
+    let _serial = SERIAL.lock().await;
+    let host = load(8);
+    let plugin = host.plugin("p").unwrap();
+
+    let (_, kind) = plugin.call_response_shared("kind", b"short").await.unwrap();
+    assert_eq!((&*kind, kind.is_shared()), (&b"plain"[..], false));
+    let (_, kind) = plugin
+        .call_response_shared("kind", b"long enough")
+        .await
+        .unwrap();
+    assert_eq!(&*kind, b"shared");
+    let (_, kind) = plugin.call_response("kind", b"long enough").await.unwrap();
+    assert_eq!(kind, b"plain");
+
+    // The response buffer the shared request came with went back to the
+    // pool, unused.
+    assert_eq!(
+        host.shared_buffer_stats(),
+        SharedBufferStats { live: 0, pooled: 1 }
+    );
+}
+
+#[tokio::test]
+async fn test_shared_replies_reuse_buffers() {
+    let _serial = SERIAL.lock().await;
+    let host = load(1024);
+    let plugin = host.plugin("p").unwrap();
+    let payload = vec![b'a'; 4096];
+
+    let (status, first) = plugin
+        .call_response_shared("upper", &payload)
+        .await
+        .unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert!(first.is_shared());
+    assert!(first.iter().all(|&byte| byte == b'A'));
+    assert_eq!(first.len(), 4096);
+    // The buffer is out with the payload.
+    assert_eq!(host.shared_buffer_stats(), SharedBufferStats::default());
+    let address = first.as_ptr();
+    drop(first);
+    assert_eq!(
+        host.shared_buffer_stats(),
+        SharedBufferStats { live: 0, pooled: 1 }
+    );
+
+    for _ in 0..10 {
+        let (_, again) = plugin
+            .call_response_shared("upper", &payload)
+            .await
+            .unwrap();
+        assert_eq!(again.as_ptr(), address);
+    }
+    assert_eq!(
+        host.shared_buffer_stats(),
+        SharedBufferStats { live: 0, pooled: 1 }
+    );
+
+    // A payload taken out of the pool does not come back.
+    let (_, kept) = plugin
+        .call_response_shared("upper", b"small")
+        .await
+        .unwrap();
+    assert_eq!(kept.into_vec(), b"SMALL");
+    assert_eq!(host.shared_buffer_stats(), SharedBufferStats::default());
+}
+
+#[tokio::test]
+async fn test_misused_buffers_are_not_handed_out() {
+    let _serial = SERIAL.lock().await;
+    let host = load(1024);
+    let plugin = host.plugin("p").unwrap();
+
+    // The uncommitted buffer is not taken; the reply comes back as it is.
+    let (_, data) = plugin.call_response_shared("misuse", b"").await.unwrap();
+    assert!(!data.is_shared());
+    assert_eq!(data.len(), 16);
+    // The overrun buffer was released and reused for the abandoned one,
+    // which stays live.
+    assert_eq!(
+        host.shared_buffer_stats(),
+        SharedBufferStats { live: 1, pooled: 0 }
+    );
+}
+
+#[tokio::test]
+async fn test_payloads_outlive_their_host() {
+    let _serial = SERIAL.lock().await;
+    let host = load(1024);
+    let plugin = host.plugin("p").unwrap();
+    let (_, data) = plugin.call_response_shared("upper", b"bye").await.unwrap();
+    drop(plugin);
+    drop(host);
+    assert_eq!(&*data, b"BYE");
+}
@@ -0,0 +1,64 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus};
+use nylon_ring_host::NylonRingHost;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// The next `shutdown` blocks until `RELEASE` is set.
+static BLOCK_NEXT: AtomicBool = AtomicBool::new(false);
+static RELEASE: AtomicBool = AtomicBool::new(false);
+static SHUTDOWNS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {
+    if BLOCK_NEXT.swap(false, Ordering::SeqCst) {
+        while !RELEASE.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+    SHUTDOWNS.fetch_add(1, Ordering::SeqCst);
+}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    }
+}
+
+#[test]
+fn stuck_plugin_is_detached_at_the_deadline() {
+    let mut host = NylonRingHost::new();
+    for name in ["b", "a", "c"] {
+        host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+            .unwrap();
+    }
+
+    BLOCK_NEXT.store(true, Ordering::SeqCst);
+    let started = Instant::now();
+    let results = host.shutdown_all(Duration::from_millis(200));
+    let elapsed = started.elapsed();
+
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["a", "b", "c"]);
+    assert_eq!(results.iter().filter(|(_, done)| !done).count(), 1);
+    assert_eq!(SHUTDOWNS.load(Ordering::SeqCst), 2);
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_secs(5));
+
+    // The detached shutdown still finishes once unblocked.
+    RELEASE.store(true, Ordering::SeqCst);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while SHUTDOWNS.load(Ordering::SeqCst) < 3 {
+        assert!(Instant::now() < deadline);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
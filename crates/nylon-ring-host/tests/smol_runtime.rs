@@ -0,0 +1,42 @@
+//! The host driven by smol instead of Tokio.
+
+use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+
+#[test]
+fn test_calls_under_smol() {
+    smol::block_on(async {
+        let mut host = NylonRingHost::new();
+        host.load_static("mock", testing::mock_plugin()).unwrap();
+        let plugin = host.plugin("mock").unwrap();
+
+        let (status, data) = plugin.call_response("echo", b"hello").await.unwrap();
+        assert_eq!(status, NrStatus::Ok);
+        assert_eq!(data, b"hello");
+
+        let (_sid, mut rx) = plugin.call_stream("words", b"a b c").await.unwrap();
+        let mut words = Vec::new();
+        while let Some(frame) = rx.recv().await {
+            words.push((frame.status, frame.data));
+        }
+        assert_eq!(
+            words,
+            vec![
+                (NrStatus::Ok, b"a".to_vec()),
+                (NrStatus::Ok, b"b".to_vec()),
+                (NrStatus::Ok, b"c".to_vec()),
+                (NrStatus::StreamEnd, Vec::new()),
+            ]
+        );
+
+        // Background tasks need Tokio unless it is compiled out.
+        #[cfg(not(feature = "tokio-rt"))]
+        {
+            let mux = plugin.call_stream_mux("words", b"x y").await.unwrap();
+            let mut default = mux.subscribe_default();
+            assert_eq!(default.recv().await.unwrap().data, b"x");
+            assert_eq!(default.recv().await.unwrap().data, b"y");
+            assert_eq!(default.recv().await.unwrap().status, NrStatus::StreamEnd);
+            assert!(default.recv().await.is_none());
+        }
+    });
+}
@@ -0,0 +1,155 @@
+//! State kept per plugin and per host, across calls.
+
+mod common;
+
+use nylon_ring::{
+    define_plugin, NrBytes, NrHostExt, NrStatus, NrStr, NrVec, NR_SCOPE_GLOBAL, NR_SCOPE_PLUGIN,
+    NR_SCOPE_SID,
+};
+use nylon_ring_host::NylonRingHost;
+use std::ffi::c_void;
+use std::sync::atomic::Ordering;
+
+common::test_plugin_host!();
+
+// Tests load the plugin into hosts of their own, and it keeps one `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn host() -> (*mut c_void, &'static NrHostExt) {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = ((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        (host_ctx, &*ext)
+    }
+}
+
+fn set(scope: u32, owner: u64, key: &str, value: &[u8]) -> Vec<u8> {
+    let (host_ctx, ext) = host();
+    let error = unsafe {
+        (ext.set_state_scoped)(
+            host_ctx,
+            scope,
+            owner,
+            NrStr::new(key),
+            NrBytes::from_slice(value),
+        )
+    };
+    error.as_slice().to_vec()
+}
+
+fn get(scope: u32, owner: u64, key: &str) -> Option<Vec<u8>> {
+    let (host_ctx, ext) = host();
+    unsafe { ext.state_scoped(host_ctx, scope, owner, key) }
+}
+
+fn send(sid: u64, data: Vec<u8>) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            NrStatus::Ok,
+            NrVec::from_vec(data),
+        );
+    }
+}
+
+/// Count the calls to this entry in plugin state, and answer with the
+/// count.
+unsafe fn handle_count(sid: u64, _payload: NrBytes) -> NrStatus {
+    let count = get(NR_SCOPE_PLUGIN, 0, "count")
+        .map(|count| String::from_utf8(count).unwrap().parse::<u32>().unwrap())
+        .unwrap_or(0)
+        + 1;
+    set(NR_SCOPE_PLUGIN, 0, "count", count.to_string().as_bytes());
+    send(sid, count.to_string().into_bytes());
+    NrStatus::Ok
+}
+
+/// Store the payload as the host-wide `config`.
+unsafe fn handle_publish(sid: u64, payload: NrBytes) -> NrStatus {
+    send(sid, set(NR_SCOPE_GLOBAL, 0, "config", payload.as_slice()));
+    NrStatus::Ok
+}
+
+/// Answer with the host-wide `config`.
+unsafe fn handle_config(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, get(NR_SCOPE_GLOBAL, 0, "config").unwrap_or_default());
+    NrStatus::Ok
+}
+
+/// Store under the call's SID through the scoped callback, then try an
+/// unknown scope and answer with its error.
+unsafe fn handle_scopes(sid: u64, _payload: NrBytes) -> NrStatus {
+    assert!(set(NR_SCOPE_SID, sid, "seen", b"yes").is_empty());
+    assert_eq!(get(NR_SCOPE_SID, sid, "seen").unwrap(), b"yes");
+    assert_eq!(get(7, 0, "seen"), None);
+    send(sid, set(7, 0, "seen", b"yes"));
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "count" => handle_count,
+        "publish" => handle_publish,
+        "config" => handle_config,
+        "scopes" => handle_scopes,
+    }
+}
+
+fn load(host: &mut NylonRingHost, name: &str) {
+    host.load_static(name, unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_plugin_state_outlives_calls_until_reload() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    load(&mut host, "p");
+
+    let plugin = host.plugin("p").unwrap();
+    for expected in ["1", "2", "3"] {
+        let (_, data) = plugin.call_response("count", b"").await.unwrap();
+        assert_eq!(data, expected.as_bytes());
+    }
+    assert_eq!(host.plugin_state("p").unwrap()["count"], b"3");
+    assert_eq!(host.plugin_state("missing"), None);
+    assert!(host.global_state().is_empty());
+
+    host.reload_one("p").unwrap();
+    assert!(host.plugin_state("p").unwrap().is_empty());
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(plugin.call_response("count", b"").await.unwrap().1, b"1");
+}
+
+#[tokio::test]
+async fn test_global_state_outlives_plugins() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    load(&mut host, "writer");
+    let writer = host.plugin("writer").unwrap();
+    let (_, error) = writer.call_response("publish", b"v1").await.unwrap();
+    assert!(error.is_empty());
+    drop(writer);
+    host.unload("writer").unwrap();
+
+    load(&mut host, "reader");
+    let reader = host.plugin("reader").unwrap();
+    assert_eq!(reader.call_response("config", b"").await.unwrap().1, b"v1");
+    assert_eq!(host.global_state()["config"], b"v1");
+    assert!(host.plugin_state("reader").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sid_scope_and_unknown_scopes() {
+    let _serial = SERIAL.lock().await;
+    let mut host = NylonRingHost::new();
+    load(&mut host, "p");
+    let plugin = host.plugin("p").unwrap();
+    let (_, error) = plugin.call_response("scopes", b"").await.unwrap();
+    assert_eq!(error, b"unknown state scope");
+    assert!(host.plugin_state("p").unwrap().is_empty());
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, PluginHandle, ResumeOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+/// Number of `stream_close` calls.
+static CLOSED: AtomicUsize = AtomicUsize::new(0);
+
+/// Accept the stream; frames are produced by the test through `send`.
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_data(_sid: u64, _data: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_close(_sid: u64) -> NrStatus {
+    CLOSED.fetch_add(1, Ordering::SeqCst);
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "open" => handle_open,
+    },
+    stream_handlers: {
+        data: stream_data,
+        close: stream_close,
+    }
+}
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("producer", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("producer").unwrap();
+    (host, plugin)
+}
+
+#[tokio::test]
+async fn test_cancel_ends_receiver() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let closed = CLOSED.load(Ordering::SeqCst);
+
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+    send(sid, NrStatus::Ok, b"before");
+    plugin.cancel_stream(sid).unwrap();
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed + 1);
+
+    // Late frames from the plugin are dropped.
+    send(sid, NrStatus::Ok, b"after");
+    assert_eq!(rx.recv().await.unwrap().data, b"before");
+    assert!(rx.recv().await.is_none());
+
+    // Cancelling again is a no-op.
+    plugin.cancel_stream(sid).unwrap();
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed + 1);
+}
+
+#[tokio::test]
+async fn test_cancel_after_natural_end() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let closed = CLOSED.load(Ordering::SeqCst);
+
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+    send(sid, NrStatus::StreamEnd, b"");
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+
+    plugin.cancel_stream(sid).unwrap();
+    assert_eq!(CLOSED.load(Ordering::SeqCst), closed);
+}
+
+#[tokio::test]
+async fn test_cancel_resumable() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let stream = plugin
+        .call_stream_resumable("open", b"", ResumeOptions::default())
+        .await
+        .unwrap();
+    let token = stream.token;
+    send(stream.sid, NrStatus::Ok, b"kept");
+    stream.cancel().unwrap();
+
+    let mut rx = stream.receiver;
+    assert_eq!(rx.recv().await.unwrap().data, b"kept");
+    assert!(rx.recv().await.is_none());
+    assert!(matches!(
+        plugin.resume_stream(token),
+        Err(NylonRingHostError::StreamExpired)
+    ));
+}
+
+#[tokio::test]
+async fn test_checked_send_after_end() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+    assert_eq!(
+        plugin.send_stream_data_checked(sid, b"in").unwrap(),
+        NrStatus::Ok
+    );
+
+    send(sid, NrStatus::StreamEnd, b"");
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+    assert!(matches!(
+        plugin.send_stream_data_checked(sid, b"late"),
+        Err(NylonRingHostError::StreamClosed(s)) if s == sid
+    ));
+
+    let (sid, _rx) = plugin.call_stream("open", b"").await.unwrap();
+    plugin.cancel_stream(sid).unwrap();
+    assert!(matches!(
+        plugin.send_stream_data_checked(sid, b"late"),
+        Err(NylonRingHostError::StreamClosed(_))
+    ));
+}
@@ -0,0 +1,143 @@
+//! Streams that drop frames the plugin sent more than once.
+
+mod common;
+
+use nylon_ring::{define_plugin, encode_seq_frame, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{
+    DedupeOptions, DedupeStats, NylonRingHost, PluginHandle, StreamOptions, StreamReceiver,
+};
+use std::sync::atomic::Ordering;
+
+common::test_plugin_host!();
+
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn send(sid: u64, status: NrStatus, data: NrVec<u8>) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(HOST_CTX.load(Ordering::Acquire), sid, status, data);
+    }
+}
+
+/// Send frames 0 to 4, each twice, then frame 1 again once 4 is out, then
+/// a replay from long before, then end the stream.
+unsafe fn handle_numbered(sid: u64, _payload: NrBytes) -> NrStatus {
+    for seq in 0..5u64 {
+        let data = format!("frame {seq}");
+        send(sid, NrStatus::Ok, encode_seq_frame(seq, data.as_bytes()));
+        send(sid, NrStatus::Ok, encode_seq_frame(seq, data.as_bytes()));
+    }
+    send(sid, NrStatus::Ok, encode_seq_frame(1, b"frame 1"));
+    send(sid, NrStatus::Ok, encode_seq_frame(100, b"frame 100"));
+    send(sid, NrStatus::Ok, encode_seq_frame(3, b"frame 3"));
+    send(sid, NrStatus::StreamEnd, NrVec::from_slice(b""));
+    NrStatus::Ok
+}
+
+/// Send "a", "a", "b", "a" without numbers, then end the stream.
+unsafe fn handle_plain(sid: u64, _payload: NrBytes) -> NrStatus {
+    for data in [b"a", b"a", b"b", b"a"] {
+        send(sid, NrStatus::Ok, NrVec::from_slice(data));
+    }
+    send(sid, NrStatus::StreamEnd, NrVec::from_slice(b""));
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "numbered" => handle_numbered,
+        "plain" => handle_plain,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("p").unwrap();
+    (host, plugin)
+}
+
+async fn open(plugin: &PluginHandle, entry: &str, dedupe: Option<DedupeOptions>) -> StreamReceiver {
+    let options = StreamOptions {
+        dedupe,
+        ..StreamOptions::default()
+    };
+    let (_, rx) = plugin
+        .call_stream_with_options(entry, b"", options)
+        .await
+        .unwrap();
+    rx
+}
+
+/// The data of every `Ok` frame, checking the stream ends normally.
+async fn drain(rx: &mut StreamReceiver) -> Vec<String> {
+    let mut data = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        match frame.status {
+            NrStatus::Ok => data.push(String::from_utf8(frame.data).unwrap()),
+            status => assert_eq!(status, NrStatus::StreamEnd),
+        }
+    }
+    data
+}
+
+#[tokio::test]
+async fn test_numbered_frames_are_delivered_once() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let dedupe = DedupeOptions {
+        window: 8,
+        hash_window: 0,
+    };
+    let mut rx = open(&plugin, "numbered", Some(dedupe)).await;
+
+    assert_eq!(
+        drain(&mut rx).await,
+        [
+            "frame 0",
+            "frame 1",
+            "frame 2",
+            "frame 3",
+            "frame 4",
+            "frame 100"
+        ]
+    );
+    assert_eq!(
+        rx.dedupe_stats(),
+        DedupeStats {
+            duplicates: 6,
+            replays: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_streams_without_dedupe_deliver_every_frame() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let mut rx = open(&plugin, "numbered", None).await;
+    assert_eq!(drain(&mut rx).await.len(), 13);
+    assert_eq!(rx.dedupe_stats(), DedupeStats::default());
+
+    // Unnumbered frames are only compared with a hash window.
+    let mut rx = open(&plugin, "plain", Some(DedupeOptions::default())).await;
+    assert_eq!(drain(&mut rx).await, ["a", "a", "b", "a"]);
+}
+
+#[tokio::test]
+async fn test_unnumbered_frames_are_hashed_on_request() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let dedupe = DedupeOptions {
+        hash_window: 4,
+        ..DedupeOptions::default()
+    };
+
+    let mut rx = open(&plugin, "plain", Some(dedupe)).await;
+    assert_eq!(drain(&mut rx).await, ["a", "b"]);
+    assert_eq!(rx.dedupe_stats().duplicates, 2);
+}
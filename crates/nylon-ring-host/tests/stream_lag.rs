@@ -0,0 +1,122 @@
+// Lag is measured against the Tokio clock, which these tests pause.
+#![cfg(feature = "tokio-rt")]
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, StreamLag, StreamLagAlert};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+common::test_plugin_host!();
+
+/// Accept the stream; frames are produced by the test through `produce`.
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "open" => handle_open,
+    }
+}
+
+fn produce(sid: u64, frames: usize) {
+    for _ in 0..frames {
+        unsafe {
+            let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+
+            (vtable.send_result)(
+                HOST_CTX.load(Ordering::Acquire),
+                sid,
+                NrStatus::Ok,
+                NrVec::from_slice(b"tick"),
+            );
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn lag_alert_fires_once_per_sustained_episode() {
+    let mut host = NylonRingHost::new();
+    host.load_static("producer", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+
+    let alerts: Arc<Mutex<Vec<(String, u64, StreamLag)>>> = Arc::default();
+    let sink = alerts.clone();
+    host.on_stream_lag(
+        StreamLagAlert {
+            max_frames: 2,
+            window: Duration::from_secs(1),
+        },
+        Arc::new(move |plugin, sid, lag| {
+            sink.lock().unwrap().push((plugin.to_string(), sid, lag));
+        }),
+    );
+
+    let plugin = host.plugin("producer").unwrap();
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+
+    // A short burst over the threshold does not alert.
+    produce(sid, 3);
+    tokio::time::advance(Duration::from_millis(500)).await;
+    while rx.try_recv().is_ok() {}
+    assert_eq!(rx.lag(), StreamLag::default());
+    produce(sid, 3);
+    tokio::time::advance(Duration::from_millis(600)).await;
+    produce(sid, 1);
+    assert!(alerts.lock().unwrap().is_empty());
+
+    // The consumer stalls: lag stays over the threshold past the window.
+    tokio::time::advance(Duration::from_millis(500)).await;
+    produce(sid, 1);
+    assert_eq!(rx.lag().frames, 5);
+    assert_eq!(rx.lag().oldest, Duration::from_millis(1100));
+    {
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].0, "producer");
+        assert_eq!(alerts[0].1, sid);
+        assert_eq!(alerts[0].2.frames, 5);
+    }
+
+    // Still lagging: same episode, no new alert.
+    tokio::time::advance(Duration::from_secs(2)).await;
+    produce(sid, 1);
+    assert_eq!(alerts.lock().unwrap().len(), 1);
+
+    // Catching up ends the episode; the next one alerts again.
+    while rx.try_recv().is_ok() {}
+    produce(sid, 3);
+    tokio::time::advance(Duration::from_secs(1)).await;
+    produce(sid, 1);
+    assert_eq!(alerts.lock().unwrap().len(), 2);
+
+    assert_eq!(rx.max_lag().frames, 6);
+    assert_eq!(rx.max_lag().oldest, Duration::from_millis(3100));
+    let stats = plugin.metrics_snapshot();
+    assert_eq!(stats.max_stream_lag, rx.max_lag());
+}
+
+#[tokio::test(start_paused = true)]
+async fn streams_without_alert_are_not_tracked() {
+    let mut host = NylonRingHost::new();
+    host.load_static("producer", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+
+    let plugin = host.plugin("producer").unwrap();
+    let (sid, mut rx) = plugin.call_stream("open", b"").await.unwrap();
+    produce(sid, 3);
+    tokio::time::advance(Duration::from_secs(1)).await;
+
+    assert_eq!(rx.lag(), StreamLag::default());
+    assert_eq!(rx.max_lag(), StreamLag::default());
+    assert_eq!(
+        plugin.metrics_snapshot().max_stream_lag,
+        StreamLag::default()
+    );
+    assert_eq!(rx.try_recv().unwrap().data, b"tick");
+}
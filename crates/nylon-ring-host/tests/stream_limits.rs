@@ -0,0 +1,231 @@
+//! Streams ended by their `StreamOptions` limits.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec};
+use nylon_ring_host::{NylonRingHost, PluginHandle, StreamLimit, StreamOptions};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+/// SID of the last `stream_close` call.
+static CLOSED: AtomicU64 = AtomicU64::new(0);
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+fn set_state(sid: u64) {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = &*((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        (ext.set_state)(
+            host_ctx,
+            sid,
+            NrStr::new("cursor"),
+            NrBytes::from_slice(b"1"),
+        );
+    }
+}
+
+/// Send two frames, then go quiet without ending the stream.
+unsafe fn handle_stall(sid: u64, _payload: NrBytes) -> NrStatus {
+    set_state(sid);
+    send(sid, NrStatus::Ok, b"one");
+    send(sid, NrStatus::Ok, b"two");
+    NrStatus::Ok
+}
+
+/// Send as many 10-byte frames as the payload says, without ending the
+/// stream.
+unsafe fn handle_flood(sid: u64, payload: NrBytes) -> NrStatus {
+    set_state(sid);
+    let count: usize = std::str::from_utf8(payload.as_slice())
+        .unwrap()
+        .parse()
+        .unwrap();
+    for _ in 0..count {
+        send(sid, NrStatus::Ok, b"0123456789");
+    }
+    NrStatus::Ok
+}
+
+/// Like `flood`, then end the stream.
+unsafe fn handle_burst(sid: u64, payload: NrBytes) -> NrStatus {
+    unsafe { handle_flood(sid, payload) };
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+unsafe fn stream_data(_sid: u64, _data: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_close(sid: u64) -> NrStatus {
+    CLOSED.store(sid, Ordering::SeqCst);
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "stall" => handle_stall,
+        "flood" => handle_flood,
+        "burst" => handle_burst,
+    },
+    stream_handlers: {
+        data: stream_data,
+        close: stream_close,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("producer", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("producer").unwrap();
+    (host, plugin)
+}
+
+/// The data of every `Ok` frame, and the final frame.
+async fn drain(rx: &mut nylon_ring_host::StreamReceiver) -> (Vec<Vec<u8>>, (NrStatus, Vec<u8>)) {
+    let mut data = Vec::new();
+    loop {
+        let frame = rx.recv().await.unwrap();
+        if frame.status != NrStatus::Ok {
+            assert!(rx.recv().await.is_none());
+            return (data, (frame.status, frame.data));
+        }
+        data.push(frame.data);
+    }
+}
+
+/// Assert the stream `sid` is gone from the host and was closed in the
+/// plugin.
+fn assert_torn_down(host: &NylonRingHost, plugin: &PluginHandle, sid: u64) {
+    assert_eq!(CLOSED.load(Ordering::SeqCst), sid);
+    assert_eq!(host.state_for(sid), None);
+    assert_eq!(plugin.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_idle_stream_times_out() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let options = StreamOptions {
+        idle_timeout: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+
+    let (sid, mut rx) = plugin
+        .call_stream_with_options("stall", b"", options)
+        .await
+        .unwrap();
+    let (data, last) = drain(&mut rx).await;
+    assert_eq!(data, [b"one", b"two"]);
+    assert_eq!(last, (NrStatus::Err, b"stream idle timeout".to_vec()));
+    assert_eq!(rx.limited(), Some(StreamLimit::IdleTimeout));
+    assert_torn_down(&host, &plugin, sid);
+
+    // Frames the plugin sends afterwards go nowhere.
+    send(sid, NrStatus::Ok, b"late");
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_frame_limit_ends_the_stream() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let options = StreamOptions {
+        max_frames: Some(3),
+        ..Default::default()
+    };
+
+    let (sid, mut rx) = plugin
+        .call_stream_with_options("flood", b"10", options)
+        .await
+        .unwrap();
+    let (data, last) = drain(&mut rx).await;
+    assert_eq!(data.len(), 3);
+    assert_eq!(
+        last,
+        (NrStatus::Err, StreamLimit::MaxFrames.message().to_vec())
+    );
+    assert_eq!(rx.limited(), Some(StreamLimit::MaxFrames));
+    assert_torn_down(&host, &plugin, sid);
+}
+
+#[tokio::test]
+async fn test_byte_limit_ends_the_stream() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let options = StreamOptions {
+        max_total_bytes: Some(25),
+        ..Default::default()
+    };
+
+    let (sid, mut rx) = plugin
+        .call_stream_with_options("flood", b"10", options)
+        .await
+        .unwrap();
+    let (data, last) = drain(&mut rx).await;
+    assert_eq!(data.len(), 2);
+    assert_eq!(
+        last,
+        (NrStatus::Err, b"stream byte limit exceeded".to_vec())
+    );
+    assert_eq!(rx.limited(), Some(StreamLimit::MaxTotalBytes));
+    assert_torn_down(&host, &plugin, sid);
+}
+
+#[tokio::test]
+async fn test_streams_within_limits_end_normally() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    CLOSED.store(0, Ordering::SeqCst);
+    let options = StreamOptions {
+        idle_timeout: Some(Duration::from_secs(5)),
+        max_frames: Some(3),
+        max_total_bytes: Some(30),
+        ..StreamOptions::default()
+    };
+
+    let (_, mut rx) = plugin
+        .call_stream_with_options("burst", b"3", options)
+        .await
+        .unwrap();
+    let (data, last) = drain(&mut rx).await;
+    assert_eq!(data.len(), 3);
+    assert_eq!(last.0, NrStatus::StreamEnd);
+    assert_eq!(rx.limited(), None);
+    assert_eq!(CLOSED.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_dropped_receiver_still_closes_the_plugin() {
+    let _serial = SERIAL.lock().await;
+    let (host, plugin) = plugin();
+    let options = StreamOptions {
+        max_frames: Some(1),
+        ..Default::default()
+    };
+
+    let (sid, rx) = plugin
+        .call_stream_with_options("flood", b"5", options)
+        .await
+        .unwrap();
+    drop(rx);
+    assert_torn_down(&host, &plugin, sid);
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec};
+use nylon_ring_host::{NylonRingHost, PluginHandle, StreamReceiver};
+use std::sync::atomic::Ordering;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+/// Interleave two channels and the default one, then end the stream.
+unsafe fn handle_chat(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, Some("events"), b"e1");
+    send(sid, NrStatus::Ok, Some("presence"), b"p1");
+    send(sid, NrStatus::Ok, None, b"d1");
+    send(sid, NrStatus::Ok, Some("events"), b"e2");
+    send(sid, NrStatus::Ok, Some("presence"), b"p2");
+    send(sid, NrStatus::StreamEnd, None, b"");
+    NrStatus::Ok
+}
+
+/// Accept the stream; frames are produced by the test through `send`.
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "chat" => handle_chat,
+        "open" => handle_open,
+    }
+}
+
+fn send(sid: u64, status: NrStatus, channel: Option<&str>, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        let ctx = HOST_CTX.load(Ordering::Acquire);
+        let data = NrVec::from_slice(data);
+        match channel {
+            Some(channel) => {
+                (vtable.send_result_channel)(ctx, sid, status, NrStr::new(channel), data)
+            }
+            None => (vtable.send_result)(ctx, sid, status, data),
+        }
+    }
+}
+
+async fn collect(mut rx: StreamReceiver) -> Vec<(NrStatus, String)> {
+    let mut frames = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        frames.push((frame.status, String::from_utf8(frame.data).unwrap()));
+    }
+    frames
+}
+
+fn frames(expected: &[(NrStatus, &str)]) -> Vec<(NrStatus, String)> {
+    expected
+        .iter()
+        .map(|(status, data)| (*status, data.to_string()))
+        .collect()
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("chat", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("chat").unwrap();
+    (host, plugin)
+}
+
+#[tokio::test]
+async fn test_interleaved_channels() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    // Everything was sent before anyone subscribed.
+    let mux = plugin.call_stream_mux("chat", b"").await.unwrap();
+    let events = mux.subscribe("events");
+    let presence = mux.subscribe("presence");
+    let default = mux.subscribe_default();
+
+    use NrStatus::{Ok, StreamEnd};
+    assert_eq!(
+        collect(events).await,
+        frames(&[(Ok, "e1"), (Ok, "e2"), (StreamEnd, "")])
+    );
+    assert_eq!(
+        collect(presence).await,
+        frames(&[(Ok, "p1"), (Ok, "p2"), (StreamEnd, "")])
+    );
+    assert_eq!(
+        collect(default).await,
+        frames(&[(Ok, "d1"), (StreamEnd, "")])
+    );
+
+    // Channels first seen after the stream ended only get the final frame.
+    assert_eq!(
+        collect(mux.subscribe("typing")).await,
+        frames(&[(StreamEnd, "")])
+    );
+}
+
+#[tokio::test]
+async fn test_live_routing_and_teardown() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let mux = plugin.call_stream_mux("open", b"").await.unwrap();
+    let sid = mux.sid();
+    let mut events = mux.subscribe("events");
+
+    send(sid, NrStatus::Ok, Some("events"), b"live");
+    let frame = events.recv().await.unwrap();
+    assert_eq!(frame.data, b"live");
+    assert_eq!(frame.channel.as_deref(), Some("events"));
+
+    // A second subscription replaces the first.
+    let mut replacement = mux.subscribe("events");
+    assert!(events.recv().await.is_none());
+    send(sid, NrStatus::Ok, Some("events"), b"next");
+    assert_eq!(replacement.recv().await.unwrap().data, b"next");
+
+    // Dropping the mux ends its receivers while the stream is still open.
+    let mut presence = mux.subscribe("presence");
+    drop(mux);
+    assert!(replacement.recv().await.is_none());
+    assert!(presence.recv().await.is_none());
+    send(sid, NrStatus::StreamEnd, None, b"");
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, OrderOptions, PluginHandle, StreamReceiver};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+/// Send frame `seq` with its number as data.
+fn send_seq(sid: u64, seq: u64, status: NrStatus) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        let ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = &*(vtable.get_host_ext)(ctx);
+        let data = NrVec::from_slice(seq.to_string().as_bytes());
+        (ext.send_result_seq)(ctx, sid, seq, status, data);
+    }
+}
+
+unsafe fn handle_shuffled(sid: u64, _payload: NrBytes) -> NrStatus {
+    for seq in [3, 1, 2, 5, 4] {
+        send_seq(sid, seq, NrStatus::Ok);
+    }
+    send_seq(sid, 6, NrStatus::StreamEnd);
+    NrStatus::Ok
+}
+
+/// Frame 2 never comes.
+unsafe fn handle_gap(sid: u64, _payload: NrBytes) -> NrStatus {
+    for seq in [1, 3, 4, 5] {
+        send_seq(sid, seq, NrStatus::Ok);
+    }
+    NrStatus::Ok
+}
+
+/// Frames through the callback without numbers.
+unsafe fn handle_plain(sid: u64, _payload: NrBytes) -> NrStatus {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    let ctx = HOST_CTX.load(Ordering::Acquire);
+    (vtable.send_result)(ctx, sid, NrStatus::Ok, NrVec::from_slice(b"a"));
+    (vtable.send_result)(ctx, sid, NrStatus::StreamEnd, NrVec::default());
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "shuffled" => handle_shuffled,
+        "gap" => handle_gap,
+        "plain" => handle_plain,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("p").unwrap();
+    (host, plugin)
+}
+
+async fn collect(mut rx: StreamReceiver) -> Vec<(NrStatus, Option<u64>, String)> {
+    let mut frames = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        let data = String::from_utf8(frame.data).unwrap();
+        frames.push((frame.status, frame.seq, data));
+    }
+    frames
+}
+
+#[tokio::test]
+async fn test_frames_are_delivered_in_order() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let (_sid, rx) = plugin
+        .call_stream_ordered("shuffled", b"", OrderOptions::default())
+        .await
+        .unwrap();
+    let frames = collect(rx).await;
+    let seqs: Vec<_> = frames.iter().map(|(_, seq, _)| seq.unwrap()).collect();
+    assert_eq!(seqs, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(frames[0].2, "1");
+    assert_eq!(frames[5].0, NrStatus::StreamEnd);
+
+    // Without ordering, frames come as they were sent.
+    let (_sid, rx) = plugin.call_stream("shuffled", b"").await.unwrap();
+    let seqs: Vec<_> = collect(rx).await.iter().map(|f| f.1.unwrap()).collect();
+    assert_eq!(seqs, [3, 1, 2, 5, 4, 6]);
+}
+
+#[tokio::test]
+async fn test_gap_ends_the_stream_after_the_timeout() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let options = OrderOptions {
+        gap_timeout: Duration::from_millis(50),
+        ..OrderOptions::default()
+    };
+    let started = Instant::now();
+    let (_sid, rx) = plugin
+        .call_stream_ordered("gap", b"", options)
+        .await
+        .unwrap();
+    let frames = collect(rx).await;
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(
+        frames,
+        [
+            (NrStatus::Ok, Some(1), "1".to_string()),
+            (
+                NrStatus::Err,
+                None,
+                "stream gap: frame 2 missing".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_full_buffer_does_not_wait() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    // Frames 3 and 4 fill the buffer; 5 overflows it.
+    let options = OrderOptions {
+        gap_timeout: Duration::from_secs(60),
+        max_buffered: 2,
+    };
+    let (_sid, rx) = plugin
+        .call_stream_ordered("gap", b"", options)
+        .await
+        .unwrap();
+    let frames = tokio::time::timeout(Duration::from_secs(5), collect(rx))
+        .await
+        .unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].0, NrStatus::Err);
+}
+
+#[tokio::test]
+async fn test_unnumbered_frames_pass_through() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let (_sid, rx) = plugin
+        .call_stream_ordered("plain", b"", OrderOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        collect(rx).await,
+        [
+            (NrStatus::Ok, None, "a".to_string()),
+            (NrStatus::StreamEnd, None, String::new()),
+        ]
+    );
+}
@@ -0,0 +1,81 @@
+//! Stream input only reaches the plugin a stream was opened on, while the
+//! stream is open.
+
+use nylon_ring_host::{testing, NrStatus, NylonRingHost, NylonRingHostError};
+
+// The mock plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn host() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load_static("a", testing::mock_plugin()).unwrap();
+    host.load_static("b", testing::mock_plugin()).unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_other_plugins_handle_is_rejected() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let (a, b) = (host.plugin("a").unwrap(), host.plugin("b").unwrap());
+    let (sid, mut rx) = a.call_stream("open", b"").await.unwrap();
+
+    assert!(matches!(
+        b.send_stream_data(sid, b"stray"),
+        Err(NylonRingHostError::UnknownStream(s)) if s == sid
+    ));
+    assert!(matches!(
+        b.close_stream(sid),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+    // Cancelling through the wrong handle leaves the stream alone.
+    b.cancel_stream(sid).unwrap();
+
+    assert_eq!(a.send_stream_data(sid, b"ping").unwrap(), NrStatus::Ok);
+    assert_eq!(rx.recv().await.unwrap().data, b"ping");
+    assert_eq!(a.close_stream(sid).unwrap(), NrStatus::Ok);
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+}
+
+#[tokio::test]
+async fn test_ended_and_unknown_streams_are_rejected() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let a = host.plugin("a").unwrap();
+    let (sid, mut rx) = a.call_stream("open", b"").await.unwrap();
+    a.close_stream(sid).unwrap();
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::StreamEnd);
+
+    assert!(matches!(
+        a.send_stream_data(sid, b"late"),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+    assert!(matches!(
+        a.close_stream(sid),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+
+    // Nor is a SID that was never issued.
+    let (sid, _) = a.call_stream("open", b"").await.unwrap();
+    assert!(matches!(
+        a.send_stream_data(sid + 1, b"x"),
+        Err(NylonRingHostError::UnknownStream(_))
+    ));
+    a.cancel_stream(sid).unwrap();
+}
+
+#[tokio::test]
+async fn test_short_streams_leave_nothing_registered() {
+    let _serial = SERIAL.lock().await;
+    let host = host();
+    let a = host.plugin("a").unwrap();
+    for _ in 0..10_000 {
+        let (sid, mut rx) = a.call_stream("open", b"").await.unwrap();
+        a.send_stream_data(sid, b"x").unwrap();
+        a.close_stream(sid).unwrap();
+        while rx.recv().await.is_some() {}
+    }
+    let diagnostics = host.diagnostics();
+    assert_eq!(diagnostics.pending_streams(), 0);
+    assert_eq!(diagnostics.pending_unary(), 0);
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{
+    NylonRingHost, NylonRingHostError, PluginHandle, ResumeOptions, ResumeToken, StreamReceiver,
+};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+/// Accept the stream; frames are produced by the test through `send`.
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "open" => handle_open,
+    }
+}
+
+fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+fn drain(rx: &mut StreamReceiver) -> Vec<(NrStatus, Vec<u8>)> {
+    std::iter::from_fn(|| rx.try_recv().ok())
+        .map(|frame| (frame.status, frame.data))
+        .collect()
+}
+
+fn frames(expected: &[(NrStatus, &[u8])]) -> Vec<(NrStatus, Vec<u8>)> {
+    expected
+        .iter()
+        .map(|(status, data)| (*status, data.to_vec()))
+        .collect()
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("producer", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("producer").unwrap();
+    (host, plugin)
+}
+
+fn options(buffer: usize, ttl_secs: u64) -> ResumeOptions {
+    ResumeOptions {
+        buffer,
+        ttl: Duration::from_secs(ttl_secs),
+    }
+}
+
+fn assert_expired(plugin: &PluginHandle, token: ResumeToken) {
+    assert!(matches!(
+        plugin.resume_stream(token),
+        Err(NylonRingHostError::StreamExpired)
+    ));
+}
+
+#[tokio::test]
+async fn resume_replays_and_reattaches() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+    let stream = plugin
+        .call_stream_resumable("open", b"", options(8, 30))
+        .await
+        .unwrap();
+    let (sid, token, mut rx) = (stream.sid, stream.token, stream.receiver);
+
+    send(sid, NrStatus::Ok, b"1");
+    assert_eq!(drain(&mut rx), frames(&[(NrStatus::Ok, b"1")]));
+
+    // "2" is queued but never read; "3" arrives while detached.
+    send(sid, NrStatus::Ok, b"2");
+    drop(rx);
+    send(sid, NrStatus::Ok, b"3");
+
+    let mut rx = plugin.resume_stream(token).unwrap();
+    assert_eq!(
+        drain(&mut rx),
+        frames(&[(NrStatus::Ok, b"2"), (NrStatus::Ok, b"3")])
+    );
+
+    // Live frames follow the replay.
+    send(sid, NrStatus::Ok, b"4");
+    send(sid, NrStatus::StreamEnd, b"");
+    assert_eq!(
+        drain(&mut rx),
+        frames(&[(NrStatus::Ok, b"4"), (NrStatus::StreamEnd, b"")])
+    );
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn resume_after_termination() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    // Everything was delivered before the consumer went away.
+    let stream = plugin
+        .call_stream_resumable("open", b"", options(8, 30))
+        .await
+        .unwrap();
+    let mut rx = stream.receiver;
+    send(stream.sid, NrStatus::Ok, b"1");
+    send(stream.sid, NrStatus::StreamEnd, b"");
+    assert_eq!(drain(&mut rx).len(), 2);
+    drop(rx);
+    assert_expired(&plugin, stream.token);
+
+    // The stream ended while detached: the tail is replayed once.
+    let stream = plugin
+        .call_stream_resumable("open", b"", options(8, 30))
+        .await
+        .unwrap();
+    drop(stream.receiver);
+    send(stream.sid, NrStatus::Ok, b"1");
+    send(stream.sid, NrStatus::StreamEnd, b"");
+    let mut rx = plugin.resume_stream(stream.token).unwrap();
+    assert_eq!(
+        drain(&mut rx),
+        frames(&[(NrStatus::Ok, b"1"), (NrStatus::StreamEnd, b"")])
+    );
+    assert!(rx.recv().await.is_none());
+    drop(rx);
+    assert_expired(&plugin, stream.token);
+}
+
+// The TTL is measured against the Tokio clock, which this test pauses.
+#[cfg(feature = "tokio-rt")]
+#[tokio::test(start_paused = true)]
+async fn detached_streams_expire() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    // Overflowing the buffer.
+    let stream = plugin
+        .call_stream_resumable("open", b"", options(2, 30))
+        .await
+        .unwrap();
+    drop(stream.receiver);
+    for _ in 0..3 {
+        send(stream.sid, NrStatus::Ok, b"x");
+    }
+    assert_expired(&plugin, stream.token);
+
+    // Staying detached past the TTL.
+    let stream = plugin
+        .call_stream_resumable("open", b"", options(8, 30))
+        .await
+        .unwrap();
+    drop(stream.receiver);
+    send(stream.sid, NrStatus::Ok, b"x");
+    tokio::time::advance(Duration::from_secs(29)).await;
+    let rx = plugin.resume_stream(stream.token).unwrap();
+    drop(rx);
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert_expired(&plugin, stream.token);
+
+    assert_expired(&plugin, ResumeToken::from(42));
+}
@@ -0,0 +1,192 @@
+//! Plugin mistakes under strict mode, and ignored without it.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, StrictViolation, TraceEvent};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+common::test_plugin_host!();
+
+unsafe fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(
+            HOST_CTX.load(Ordering::Acquire),
+            sid,
+            status,
+            NrVec::from_slice(data),
+        );
+    }
+}
+
+unsafe fn handle_twice(sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        send(sid, NrStatus::Ok, b"first");
+        send(sid, NrStatus::Ok, b"second");
+    }
+    NrStatus::Ok
+}
+
+/// Answer a SID that was never issued, then the call.
+unsafe fn handle_stray(sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        send(sid + (1 << 40), NrStatus::Ok, b"stray");
+        send(sid, NrStatus::Ok, b"answered");
+    }
+    NrStatus::Ok
+}
+
+unsafe fn handle_open(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+/// Write state under a SID the host never issued and answer with what
+/// `set_state` returned.
+unsafe fn handle_state(sid: u64, _payload: NrBytes) -> NrStatus {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = ((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        let error = ((*ext).set_state)(host_ctx, 12345, NrStr::new("k"), NrBytes::from_slice(b"v"));
+        send(sid, NrStatus::Ok, error.as_slice());
+    }
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "twice" => handle_twice,
+        "stray" => handle_stray,
+        "open" => handle_open,
+        "state" => handle_state,
+    }
+}
+
+// Tests load the plugin into hosts of their own, and it keeps one `HOST_CTX`.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A host with the plugin loaded as `p`, and the violations it reports.
+fn load(strict: bool) -> (NylonRingHost, Arc<Mutex<Vec<StrictViolation>>>) {
+    let mut host = NylonRingHost::builder()
+        .strict_mode(strict)
+        .build()
+        .unwrap();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let seen = violations.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        if let TraceEvent::StrictViolation {
+            plugin, violation, ..
+        } = event
+        {
+            assert_eq!(plugin, "p");
+            seen.lock().unwrap().push(violation);
+        }
+    }));
+    (host, violations)
+}
+
+#[tokio::test]
+async fn test_duplicate_results() {
+    let _serial = SERIAL.lock().await;
+    let (host, violations) = load(false);
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(
+        plugin.call_response_fast("twice", b"").await.unwrap().1,
+        b"second"
+    );
+    assert_eq!(
+        plugin.call_response("twice", b"").await.unwrap().1,
+        b"first"
+    );
+    assert!(violations.lock().unwrap().is_empty());
+    assert_eq!(host.metrics().strict_violations, 0);
+
+    let (host, violations) = load(true);
+    let plugin = host.plugin("p").unwrap();
+    // A fast call is still in progress, so it fails.
+    assert!(matches!(
+        plugin.call_response_fast("twice", b"").await,
+        Err(NylonRingHostError::PluginError { code: 500, message })
+            if message == "second result for a unary call"
+    ));
+    // The first result already went to the caller.
+    assert_eq!(
+        plugin.call_response("twice", b"").await.unwrap().1,
+        b"first"
+    );
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [StrictViolation::DuplicateResult; 2]
+    );
+    assert_eq!(host.metrics().strict_violations, 2);
+}
+
+#[tokio::test]
+async fn test_unknown_sid_results() {
+    let _serial = SERIAL.lock().await;
+    let (host, violations) = load(false);
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(
+        plugin.call_response("stray", b"").await.unwrap().1,
+        b"answered"
+    );
+    assert!(violations.lock().unwrap().is_empty());
+
+    let (host, violations) = load(true);
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(
+        plugin.call_response("stray", b"").await.unwrap().1,
+        b"answered"
+    );
+    assert_eq!(*violations.lock().unwrap(), [StrictViolation::UnknownSid]);
+    assert_eq!(host.metrics().strict_violations, 1);
+}
+
+#[tokio::test]
+async fn test_frames_for_dropped_receivers() {
+    let _serial = SERIAL.lock().await;
+    for strict in [false, true] {
+        let (host, violations) = load(strict);
+        let plugin = host.plugin("p").unwrap();
+        let (sid, rx) = plugin.call_stream("open", b"").await.unwrap();
+        drop(rx);
+        unsafe {
+            send(sid, NrStatus::Ok, b"frame");
+            send(sid, NrStatus::StreamEnd, b"");
+        }
+
+        let expected: &[StrictViolation] = if strict {
+            &[StrictViolation::StreamClosed; 2]
+        } else {
+            &[]
+        };
+        assert_eq!(*violations.lock().unwrap(), expected);
+        assert_eq!(host.metrics().strict_violations, expected.len() as u64);
+    }
+}
+
+#[tokio::test]
+async fn test_state_for_unknown_sids() {
+    let _serial = SERIAL.lock().await;
+    let (host, violations) = load(false);
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(plugin.call_response("state", b"").await.unwrap().1, b"");
+    assert!(violations.lock().unwrap().is_empty());
+
+    let (host, violations) = load(true);
+    let plugin = host.plugin("p").unwrap();
+    assert_eq!(
+        plugin.call_response("state", b"").await.unwrap().1,
+        b"state written under a SID the host never issued"
+    );
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [StrictViolation::StateForUnknownSid]
+    );
+    assert_eq!(host.metrics().strict_violations, 1);
+}
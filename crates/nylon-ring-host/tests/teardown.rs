@@ -0,0 +1,85 @@
+//! Unloading a plugin whose own runtime is still calling the host.
+
+mod common;
+
+use common::example_plugin;
+use nylon_ring_host::{NrStatus, NylonRingHost, TraceEvent};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// The example plugin keeps the host context in a static, so hosts that load
+// it must not overlap.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A host running the example plugin, whose `shutdown` waits for its
+/// `ticker` tasks to stop.
+fn host_with_example(dir: &tempfile::TempDir) -> NylonRingHost {
+    let path = dir.path().join(example_plugin().file_name().unwrap());
+    std::fs::copy(example_plugin(), &path).unwrap();
+    let mut host = NylonRingHost::new();
+    host.load("example", path.to_str().unwrap()).unwrap();
+    host
+}
+
+#[tokio::test]
+async fn test_unload_revokes_callbacks_first() {
+    let _serial = SERIAL.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut host = host_with_example(&dir);
+    host.set_shutdown_watchdog(Duration::from_secs(30));
+    // The ticker never ends its stream, so it stays in flight.
+    host.set_drain_timeout(Duration::from_millis(100));
+
+    let plugin = host.plugin("example").unwrap();
+    let (_sid, mut rx) = plugin.call_stream("ticker", b"").await.unwrap();
+    assert_eq!(rx.recv().await.unwrap().data, b"tick 1");
+    drop(plugin);
+
+    // The ticker sees the revocation and stops, so `shutdown` returns long
+    // before the watchdog would fire.
+    let started = Instant::now();
+    host.unload("example").unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_watchdog_breaks_circular_wait() {
+    let _serial = SERIAL.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut host = host_with_example(&dir);
+    let watchdog = Duration::from_millis(300);
+    host.set_shutdown_watchdog(watchdog);
+    host.set_drain_timeout(Duration::ZERO);
+
+    // Stands in for host code that needs what the unloading thread holds:
+    // while `gate` is locked, a ticker delivering a frame blocks inside
+    // `send_result`.
+    let gate = Arc::new(Mutex::new(()));
+    let hook_gate = gate.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        if let TraceEvent::StreamFrame { .. } = event {
+            drop(hook_gate.lock().unwrap());
+        }
+    }));
+
+    let plugin = host.plugin("example").unwrap();
+    let (_sid, mut rx) = plugin.call_stream("ticker", b"").await.unwrap();
+    assert_eq!(rx.recv().await.unwrap().status, NrStatus::Ok);
+    drop(plugin);
+
+    let held = gate.lock().unwrap();
+    // Give the ticker time to block on the gate.
+    std::thread::sleep(Duration::from_millis(50));
+
+    // `shutdown` waits for the ticker, which waits for this thread: without
+    // the watchdog, `unload` would never return.
+    let started = Instant::now();
+    host.unload("example").unwrap();
+    let elapsed = started.elapsed();
+    assert!(elapsed >= watchdog, "unload returned after {elapsed:?}");
+    assert!(elapsed < watchdog + Duration::from_secs(5));
+
+    // The detached ticker finishes its frame and stops on its own.
+    drop(held);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NrStatus as HostStatus, NylonRingHost, TraceEvent};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+common::test_plugin_host!();
+
+unsafe fn send(sid: u64, status: NrStatus, data: &[u8]) {
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        status,
+        NrVec::from_slice(data),
+    );
+}
+
+unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, payload.as_slice());
+    NrStatus::Ok
+}
+
+unsafe fn handle_stream(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, NrStatus::Ok, b"frame");
+    send(sid, NrStatus::StreamEnd, b"");
+    NrStatus::Ok
+}
+
+unsafe fn handle_fail(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Invalid
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+        "stream" => handle_stream,
+        "fail" => handle_fail,
+    }
+}
+
+fn describe(event: TraceEvent<'_>) -> String {
+    match event {
+        TraceEvent::CallStart { plugin, entry, .. } => format!("start {plugin}/{entry}"),
+        TraceEvent::CallEnd { status, bytes, .. } => format!("end {status:?} {bytes}"),
+        TraceEvent::StreamFrame { status, .. } => format!("frame {status:?}"),
+        TraceEvent::EntriesChanged { plugin, .. } => format!("entries {plugin}"),
+        TraceEvent::StrictViolation { violation, .. } => format!("strict {violation:?}"),
+    }
+}
+
+#[tokio::test]
+async fn hook_observes_calls_and_frames() {
+    let mut host = NylonRingHost::new();
+    host.load_static("traced", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("traced").unwrap();
+
+    // Nothing is recorded before a hook is installed.
+    plugin.call_response("echo", b"abc").await.unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        sink.lock().unwrap().push(describe(event));
+    }));
+
+    plugin.call_response("echo", b"abc").await.unwrap();
+    plugin.call("fail", b"").await.unwrap_err();
+    let (_sid, mut rx) = plugin.call_stream("stream", b"").await.unwrap();
+    while let Some(frame) = rx.recv().await {
+        if frame.status == HostStatus::StreamEnd {
+            break;
+        }
+    }
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            "start traced/echo",
+            "end Ok 3",
+            "start traced/fail",
+            "end Invalid 0",
+            "start traced/stream",
+            "frame Ok",
+            "frame StreamEnd",
+        ]
+    );
+
+    host.clear_trace_hook();
+    plugin.call_response("echo", b"abc").await.unwrap();
+    assert_eq!(events.lock().unwrap().len(), 7);
+}
@@ -0,0 +1,97 @@
+use nylon_ring_host::{testing, NrStatus, NylonRingHost};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Collects formatted output so the test can read it back.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<String> {
+        let output = self.0.lock().unwrap();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Capture;
+
+    fn make_writer(&'a self) -> Capture {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn test_call_spans_carry_call_fields() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(capture.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::TRACE)
+        .with_span_events(FmtSpan::CLOSE)
+        .finish();
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let mut host = NylonRingHost::new();
+    host.load_static("mock", testing::mock_plugin()).unwrap();
+    let plugin = host.plugin("mock").unwrap();
+
+    let (status, data) = plugin.call_response("echo", b"hello").await.unwrap();
+    assert_eq!((status, data.as_slice()), (NrStatus::Ok, &b"hello"[..]));
+
+    let (sid, mut rx) = plugin.call_stream("words", b"a bc").await.unwrap();
+    while rx.recv().await.is_some() {}
+
+    let lines = capture.lines();
+    let close = lines
+        .iter()
+        .find(|l| l.contains("call=\"call_response\"") && l.contains("close"))
+        .expect("call_response span closed");
+    for field in [
+        "plugin=\"mock\"",
+        "entry=\"echo\"",
+        "payload_size=5",
+        "status=Ok",
+        "response_size=5",
+        "latency_us=",
+    ] {
+        assert!(close.contains(field), "{field} missing from {close}");
+    }
+    let sid_field = close
+        .split_whitespace()
+        .find(|f| f.starts_with("sid="))
+        .expect("sid recorded");
+
+    // The result was delivered inside the span and carries the same SID.
+    assert!(lines
+        .iter()
+        .any(|l| l.contains("nylon_ring.result") && l.contains(sid_field)));
+
+    // Stream frames are indexed in order.
+    let frames: Vec<_> = lines
+        .iter()
+        .filter(|l| l.contains("nylon_ring.frame") && l.contains(&format!("sid={sid}")))
+        .collect();
+    assert_eq!(frames.len(), 3);
+    for (index, (frame, size)) in frames.iter().zip([1, 2, 0]).enumerate() {
+        assert!(frame.contains(&format!("index={index}")), "{frame}");
+        assert!(frame.contains(&format!("size={size}")), "{frame}");
+    }
+    assert!(frames[2].contains("status=StreamEnd"));
+}
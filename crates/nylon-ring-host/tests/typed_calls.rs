@@ -0,0 +1,92 @@
+//! Typed unary calls, through the example plugin's `order` entry.
+
+mod common;
+
+use common::example_plugin;
+use nylon_ring_host::{NrStatus, NylonRingHost, NylonRingHostError, PayloadCodec};
+use serde::{Deserialize, Serialize};
+
+// The example plugin keeps the host context in a static.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Serialize)]
+struct Order {
+    id: u32,
+    items: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Receipt {
+    id: u32,
+    count: usize,
+    items: Vec<String>,
+}
+
+fn load() -> NylonRingHost {
+    let mut host = NylonRingHost::new();
+    host.load("example", example_plugin().to_str().unwrap())
+        .unwrap();
+    host
+}
+
+fn order() -> Order {
+    Order {
+        id: 7,
+        items: vec!["tea".into(), "bread".into()],
+    }
+}
+
+#[tokio::test]
+async fn test_structs_round_trip_in_each_codec() {
+    let _serial = SERIAL.lock().await;
+    let host = load();
+    let plugin = host.plugin("example").unwrap();
+    let expected = Receipt {
+        id: 7,
+        count: 2,
+        items: vec!["bread".into(), "tea".into()],
+    };
+
+    let receipt: Receipt = plugin.call_typed("order", &order()).await.unwrap();
+    assert_eq!(receipt, expected);
+    for codec in [PayloadCodec::Json, PayloadCodec::Cbor] {
+        let receipt: Receipt = plugin
+            .call_typed_with("order", &order(), codec)
+            .await
+            .unwrap();
+        assert_eq!(receipt, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_decode_failures_keep_the_response() {
+    let _serial = SERIAL.lock().await;
+    let host = load();
+    let plugin = host.plugin("example").unwrap();
+
+    // `echo` answers with plain text, which is not JSON.
+    let result = plugin.call_typed::<_, Receipt>("echo", &order()).await;
+    let Err(NylonRingHostError::Decode { bytes, .. }) = result else {
+        panic!("expected a decode error, got {result:?}");
+    };
+    assert!(bytes.ends_with(b", Nylon Ring!"));
+
+    // The plugin rejects requests it cannot decode.
+    let result = plugin
+        .call_typed::<_, Receipt>("order", &"not an order")
+        .await;
+    assert!(matches!(
+        result,
+        Err(NylonRingHostError::PluginError { code: 400, .. })
+    ));
+
+    // Transport failures are not decode failures.
+    let result = plugin.call_typed::<_, Receipt>("missing", &order()).await;
+    assert!(matches!(
+        result,
+        Err(NylonRingHostError::PluginHandleFailed {
+            status: NrStatus::Invalid,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use nylon_ring::codec::{Codec, Json, TypedSink};
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrVec};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, PluginHandle, TypedStreamError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use tokio::sync::Mutex;
+
+common::test_plugin_host!();
+
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Range {
+    count: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Point {
+    index: u32,
+    label: String,
+}
+
+unsafe fn sink(sid: u64) -> TypedSink<Point> {
+    TypedSink::new(
+        HOST_CTX.load(Ordering::Acquire),
+        HOST_VTABLE.load(Ordering::Acquire),
+        sid,
+        Json,
+    )
+}
+
+/// Stream `count` points.
+unsafe fn handle_points(sid: u64, payload: NrBytes) -> NrStatus {
+    let Ok(range) = Json.decode::<Range>(payload.as_slice()) else {
+        return NrStatus::Invalid;
+    };
+    let sink = sink(sid);
+    for index in 0..range.count {
+        let label = format!("point-{index}");
+        sink.send(&Point { index, label }).unwrap();
+    }
+    sink.end();
+    NrStatus::Ok
+}
+
+/// A frame that is not a point between two that are, then an error.
+unsafe fn handle_garbled(sid: u64, _payload: NrBytes) -> NrStatus {
+    let sink = sink(sid);
+    let point = |index| Point {
+        index,
+        label: String::new(),
+    };
+    sink.send(&point(0)).unwrap();
+    let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+    (vtable.send_result)(
+        HOST_CTX.load(Ordering::Acquire),
+        sid,
+        NrStatus::Ok,
+        NrVec::from_slice(b"not json"),
+    );
+    sink.send(&point(1)).unwrap();
+    sink.fail(NrStatus::Err, "boom");
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "points" => handle_points,
+        "garbled" => handle_garbled,
+    }
+}
+
+fn plugin() -> (NylonRingHost, PluginHandle) {
+    let mut host = NylonRingHost::new();
+    host.load_static("typed", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let plugin = host.plugin("typed").unwrap();
+    (host, plugin)
+}
+
+#[tokio::test]
+async fn test_typed_stream_round_trip() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let mut stream = plugin
+        .call_typed_stream::<_, Point>("points", &Range { count: 100 })
+        .await
+        .unwrap();
+    let mut points = Vec::new();
+    while let Some(point) = stream.next().await {
+        points.push(point.unwrap());
+    }
+
+    assert_eq!(points.len(), 100);
+    for (i, point) in points.iter().enumerate() {
+        assert_eq!(point.index, i as u32);
+        assert_eq!(point.label, format!("point-{i}"));
+    }
+    assert!(stream.next().await.is_none());
+
+    // A request the codec cannot encode never reaches the plugin.
+    let mut unencodable = std::collections::HashMap::new();
+    unencodable.insert(vec![1u8], 1u8);
+    assert!(matches!(
+        plugin
+            .call_typed_stream::<_, Point>("points", &unencodable)
+            .await,
+        Err(NylonRingHostError::Encode(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_decode_errors_per_frame() {
+    let _serial = SERIAL.lock().await;
+    let (_host, plugin) = plugin();
+
+    let mut stream = plugin
+        .call_typed_stream::<_, Point>("garbled", &())
+        .await
+        .unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().index, 0);
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(TypedStreamError::Decode(_)))
+    ));
+    assert_eq!(stream.next().await.unwrap().unwrap().index, 1);
+    match stream.next().await {
+        Some(Err(TypedStreamError::Plugin { status, data })) => {
+            assert_eq!(status, NrStatus::Err);
+            assert_eq!(data, b"boom");
+        }
+        other => panic!("expected plugin error, got {other:?}"),
+    }
+    assert!(stream.next().await.is_none());
+
+    let mut stream = plugin
+        .call_typed_stream::<_, Point>("garbled", &())
+        .await
+        .unwrap()
+        .stop_on_decode_error(true);
+    assert!(stream.next().await.unwrap().is_ok());
+    assert!(stream.next().await.unwrap().is_err());
+    assert!(stream.next().await.is_none());
+}
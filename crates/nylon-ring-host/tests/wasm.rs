@@ -0,0 +1,92 @@
+#![cfg(feature = "wasm")]
+
+use nylon_ring_host::{NrStatus, NylonRingHost, NylonRingHostError};
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// Echoes the payload. `stream` (the only six-byte entry used here) ends the
+/// stream after the echo.
+const ECHO: &str = r#"
+(module
+  (import "env" "nr_send_result" (func $send (param i64 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "nr_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "nr_handle")
+    (param $entry i32) (param $entry_len i32) (param $sid i64)
+    (param $payload i32) (param $payload_len i32) (result i32)
+    (call $send (local.get $sid) (i32.const 0) (local.get $payload) (local.get $payload_len))
+    (if (i32.eq (local.get $entry_len) (i32.const 6))
+      (then (call $send (local.get $sid) (i32.const 4) (i32.const 0) (i32.const 0))))
+    (global.set $next (i32.const 1024))
+    (i32.const 0)))
+"#;
+
+fn module(source: &str) -> NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(".wat").tempfile().unwrap();
+    file.write_all(source.as_bytes()).unwrap();
+    file
+}
+
+#[tokio::test]
+async fn test_wasm_echo() {
+    let file = module(ECHO);
+    let mut host = NylonRingHost::new();
+    host.load_wasm("echo", file.path().to_str().unwrap())
+        .unwrap();
+    let plugin = host.plugin("echo").unwrap();
+
+    let (status, data) = plugin.call_response("echo", b"hello").await.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert_eq!(data, b"hello");
+
+    let (status, data) = plugin.call_response_fast("echo", b"fast").await.unwrap();
+    assert_eq!(status, NrStatus::Ok);
+    assert_eq!(data, b"fast");
+
+    let (_sid, mut rx) = plugin.call_stream("stream", b"frame").await.unwrap();
+    let frame = rx.recv().await.unwrap();
+    assert_eq!(
+        (frame.status, frame.data.as_slice()),
+        (NrStatus::Ok, &b"frame"[..])
+    );
+    let frame = rx.recv().await.unwrap();
+    assert_eq!(frame.status, NrStatus::StreamEnd);
+    assert!(rx.recv().await.is_none());
+
+    // No `nr_stream_data` export.
+    assert!(matches!(
+        plugin.send_stream_data(1, b"x"),
+        Err(NylonRingHostError::MissingFunction {
+            function: "stream_data",
+            ..
+        })
+    ));
+    assert!(plugin.abi_details().wasm);
+    assert!(!plugin.abi_details().provides("stream_data"));
+
+    host.reload().unwrap();
+    let plugin = host.plugin("echo").unwrap();
+    let (_, data) = plugin.call_response("echo", b"again").await.unwrap();
+    assert_eq!(data, b"again");
+}
+
+#[tokio::test]
+async fn test_wasm_rejects_incomplete_module() {
+    let file = module(r#"(module (memory (export "memory") 1))"#);
+    let mut host = NylonRingHost::new();
+    assert!(matches!(
+        host.load_wasm("empty", file.path().to_str().unwrap()),
+        Err(NylonRingHostError::MissingRequiredFunctions)
+    ));
+
+    let file = module("(module");
+    assert!(matches!(
+        host.load_wasm("broken", file.path().to_str().unwrap()),
+        Err(NylonRingHostError::Wasm(_))
+    ));
+}
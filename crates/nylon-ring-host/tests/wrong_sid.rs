@@ -0,0 +1,192 @@
+//! Results a plugin sends under the SID of another call.
+
+mod common;
+
+use nylon_ring::{define_plugin, NrBytes, NrStatus, NrStr, NrVec, ECHOES_NONCE_KEY, INIT_SID};
+use nylon_ring_host::{NylonRingHost, NylonRingHostError, StrictViolation, TraceEvent};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+common::test_plugin_host!(on_init: |host_ctx, host_vtable| {
+    if ECHO.load(Ordering::SeqCst) {
+        let ext = &*((*host_vtable).get_host_ext)(host_ctx);
+        (ext.set_state)(
+            host_ctx,
+            INIT_SID,
+            NrStr::new(ECHOES_NONCE_KEY),
+            NrBytes::empty(),
+        );
+    }
+});
+
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+/// Whether the next load declares that the plugin echoes nonces.
+static ECHO: AtomicBool = AtomicBool::new(false);
+/// SID of the last call to `park`.
+static PARKED: AtomicU64 = AtomicU64::new(0);
+
+fn send(sid: u64, data: NrVec<u8>) {
+    unsafe {
+        let vtable = &*HOST_VTABLE.load(Ordering::Acquire);
+        (vtable.send_result)(HOST_CTX.load(Ordering::Acquire), sid, NrStatus::Ok, data);
+    }
+}
+
+/// `data` with the nonce of `sid`, as a plugin that echoes nonces sends it.
+fn echo(sid: u64, data: &[u8]) -> NrVec<u8> {
+    unsafe {
+        let host_ctx = HOST_CTX.load(Ordering::Acquire);
+        let ext = &*((*HOST_VTABLE.load(Ordering::Acquire)).get_host_ext)(host_ctx);
+        ext.echo_nonce(host_ctx, sid, data)
+    }
+}
+
+/// Leave the answer for later.
+unsafe fn handle_park(sid: u64, _payload: NrBytes) -> NrStatus {
+    PARKED.store(sid, Ordering::SeqCst);
+    NrStatus::Accepted
+}
+
+/// The bug: answer with the parked call's SID instead of this one.
+unsafe fn handle_buggy(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(PARKED.load(Ordering::SeqCst), echo(sid, b"secret"));
+    NrStatus::Accepted
+}
+
+/// Answer the parked call properly, then this one.
+unsafe fn handle_release(sid: u64, _payload: NrBytes) -> NrStatus {
+    let parked = PARKED.load(Ordering::SeqCst);
+    send(parked, echo(parked, b"parked"));
+    send(sid, echo(sid, b"released"));
+    NrStatus::Ok
+}
+
+/// Answer without the nonce.
+unsafe fn handle_forgetful(sid: u64, _payload: NrBytes) -> NrStatus {
+    send(sid, NrVec::from_slice(b"plain"));
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "park" => handle_park,
+        "buggy" => handle_buggy,
+        "release" => handle_release,
+        "forgetful" => handle_forgetful,
+    }
+}
+
+/// A host with the plugin loaded, echoing nonces or not, and the
+/// violations it reports.
+fn load(echo: bool, strict: bool) -> (NylonRingHost, Arc<Mutex<Vec<StrictViolation>>>) {
+    ECHO.store(echo, Ordering::SeqCst);
+    let mut host = NylonRingHost::builder()
+        .call_timeout(Duration::from_millis(100))
+        .strict_mode(strict)
+        .build()
+        .unwrap();
+    host.load_static("p", unsafe { &*nylon_ring_get_plugin_v1() })
+        .unwrap();
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let seen = violations.clone();
+    host.set_trace_hook(Arc::new(move |event| {
+        if let TraceEvent::StrictViolation { violation, .. } = event {
+            seen.lock().unwrap().push(violation);
+        }
+    }));
+    (host, violations)
+}
+
+fn is_timeout<T>(result: &Result<T, NylonRingHostError>) -> bool {
+    matches!(result, Err(NylonRingHostError::Timeout(_)))
+}
+
+#[tokio::test]
+async fn test_results_under_the_wrong_sid_are_dropped() {
+    let _serial = SERIAL.lock().await;
+    let (host, violations) = load(true, false);
+    let plugin = host.plugin("p").unwrap();
+
+    let parked = tokio::spawn({
+        let plugin = plugin.clone();
+        async move { plugin.call_response("park", b"").await }
+    });
+    while PARKED.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // Neither caller gets the data: the parked one times out instead.
+    assert!(is_timeout(&plugin.call_response("buggy", b"").await));
+    assert!(is_timeout(&parked.await.unwrap()));
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [StrictViolation::NonceMismatch]
+    );
+    assert_eq!(host.metrics().strict_violations, 1);
+    PARKED.store(0, Ordering::SeqCst);
+}
+
+#[tokio::test]
+async fn test_echoed_nonces_are_stripped() {
+    let _serial = SERIAL.lock().await;
+    let (host, violations) = load(true, false);
+    let plugin = host.plugin("p").unwrap();
+
+    let parked = tokio::spawn({
+        let plugin = plugin.clone();
+        async move { plugin.call_response("park", b"").await }
+    });
+    while PARKED.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+    let parked_sid = PARKED.load(Ordering::SeqCst);
+
+    // Answering another call from `handle` is fine with the right nonce.
+    let (_, data) = plugin.call_response("release", b"").await.unwrap();
+    assert_eq!(data, b"released");
+    assert_eq!(parked.await.unwrap().unwrap().1, b"parked");
+    assert_eq!(host.state_for(parked_sid), None);
+    PARKED.store(0, Ordering::SeqCst);
+
+    let (_, data) = plugin.call_response_fast("release", b"").await.unwrap();
+    assert_eq!(data, b"released");
+
+    // A result without the nonce is dropped like one under a wrong SID,
+    // and the call is left without an answer.
+    assert!(plugin.call_response("forgetful", b"").await.is_err());
+    assert_eq!(
+        *violations.lock().unwrap(),
+        [StrictViolation::NonceMismatch]
+    );
+}
+
+#[tokio::test]
+async fn test_plugins_without_nonces_have_foreign_results_noticed() {
+    let _serial = SERIAL.lock().await;
+    for strict in [false, true] {
+        let (host, violations) = load(false, strict);
+        let plugin = host.plugin("p").unwrap();
+
+        let parked = tokio::spawn({
+            let plugin = plugin.clone();
+            async move { plugin.call_response("park", b"").await }
+        });
+        while PARKED.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Without nonces the data cannot be told apart, and is delivered.
+        assert!(is_timeout(&plugin.call_response("buggy", b"").await));
+        assert_eq!(parked.await.unwrap().unwrap().1, b"secret");
+        let expected: &[StrictViolation] = if strict {
+            &[StrictViolation::ForeignSid]
+        } else {
+            &[]
+        };
+        assert_eq!(*violations.lock().unwrap(), expected);
+        PARKED.store(0, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,227 @@
+/* nylon_ring.h - the nylon-ring plugin ABI, version 8, for C.
+ *
+ * Generated by `cargo run -p nylon-ring --bin nylon-ring-abigen`; do not edit.
+ * See the nylon-ring crate's documentation for what each callback does.
+ *
+ * A plugin exports `nylon_ring_get_plugin_v1`, returning an NrPluginInfo
+ * with `abi_version` NR_ABI_VERSION and `struct_size` sizeof(NrPluginInfo).
+ *
+ * The host frees the NrVecU8 payloads a plugin sends with its global
+ * allocator: allocate them with malloc() for hosts on the system allocator,
+ * Rust's default.
+ */
+#ifndef NYLON_RING_H
+#define NYLON_RING_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NR_ABI_VERSION 8u
+#define NR_INIT_SID 0u
+#define NR_STATE_ABSENT 0xFFFFFFFFFFFFFFFFu
+#define NR_SCOPE_SID 0u
+#define NR_SCOPE_PLUGIN 1u
+#define NR_SCOPE_GLOBAL 2u
+
+/* Status codes. */
+typedef uint32_t NrStatus;
+#define NR_STATUS_OK 0u
+#define NR_STATUS_ERR 1u
+#define NR_STATUS_INVALID 2u
+#define NR_STATUS_UNSUPPORTED 3u
+#define NR_STATUS_STREAM_END 4u
+#define NR_STATUS_REVOKED 5u
+#define NR_STATUS_ACCEPTED 6u
+
+/* Levels for `NrHostExt.log`. */
+#define NR_LOG_ERROR 1u
+#define NR_LOG_WARN 2u
+#define NR_LOG_INFO 3u
+#define NR_LOG_DEBUG 4u
+#define NR_LOG_TRACE 5u
+
+/* A UTF-8 string slice. Not NUL-terminated. */
+typedef struct NrStr {
+    const uint8_t *ptr;
+    uint32_t len;
+} NrStr;
+
+/* A byte slice. */
+typedef struct NrBytes {
+    const uint8_t *ptr;
+    uint64_t len;
+} NrBytes;
+
+/* A key-value pair of strings. */
+typedef struct NrKV {
+    NrStr key;
+    NrStr value;
+} NrKV;
+
+/* Method, path, query and headers of a call to `handle_req`. */
+typedef struct NrRequest {
+    NrStr method;
+    NrStr path;
+    NrStr query;
+    const NrKV *headers;
+    uint32_t headers_len;
+} NrRequest;
+
+/* An owned byte buffer, `NrVec<u8>` on the Rust side. */
+typedef struct NrVecU8 {
+    uint8_t *ptr;
+    size_t len;
+    size_t cap;
+} NrVecU8;
+
+/* `NrTuple<NrStatus, u64>`: what `dispatch_spawn` returns. */
+typedef struct NrTupleStatusU64 {
+    NrStatus a;
+    uint64_t b;
+} NrTupleStatusU64;
+
+/* `NrTuple<u64, *mut u8>`: what `buf_acquire` returns. */
+typedef struct NrTupleU64Ptr {
+    uint64_t a;
+    uint8_t *b;
+} NrTupleU64Ptr;
+
+/* Host callback table, passed to `init`. */
+typedef struct NrHostVTable {
+    void (*send_result)(void *host_ctx, uint64_t sid, NrStatus status, NrVecU8 payload);
+    const struct NrHostExt *(*get_host_ext)(void *host_ctx);
+    void (*send_result_channel)(void *host_ctx, uint64_t sid, NrStatus status, NrStr channel, NrVecU8 payload);
+} NrHostVTable;
+
+/* Host extension table, from `get_host_ext`. */
+typedef struct NrHostExt {
+    NrBytes (*set_state)(void *host_ctx, uint64_t sid, NrStr key, NrBytes value);
+    NrBytes (*get_state)(void *host_ctx, uint64_t sid, NrStr key);
+    void (*report_panic)(void *host_ctx, uint64_t sid, NrStr entry, NrStr message, NrStr backtrace);
+    NrTupleStatusU64 (*dispatch_spawn)(void *host_ctx, NrStr target, NrStr entry, NrBytes payload);
+    bool (*take_dispatch_result)(void *host_ctx, uint64_t sid, NrStatus *status, NrVecU8 *payload);
+    uint64_t (*get_state_into)(void *host_ctx, uint64_t sid, NrStr key, uint8_t *out_buf, uint64_t out_cap);
+    bool (*is_revoked)(void *host_ctx);
+    NrStatus (*complete_later)(void *host_ctx, uint64_t sid);
+    void (*log)(void *host_ctx, uint32_t level, NrStr target, NrStr message);
+    NrTupleU64Ptr (*buf_acquire)(void *host_ctx, uint64_t size);
+    NrStatus (*buf_commit)(void *host_ctx, uint64_t handle, uint64_t len);
+    NrBytes (*set_state_scoped)(void *host_ctx, uint32_t scope, uint64_t owner, NrStr key, NrBytes value);
+    uint64_t (*get_state_scoped_into)(void *host_ctx, uint32_t scope, uint64_t owner, NrStr key, uint8_t *out_buf, uint64_t out_cap);
+    void (*send_result_seq)(void *host_ctx, uint64_t sid, uint64_t seq, NrStatus status, NrVecU8 payload);
+} NrHostExt;
+
+/* Plugin function table. `handle` is required; the rest may be NULL. */
+typedef struct NrPluginVTable {
+    NrStatus (*init)(void *host_ctx, const NrHostVTable *host_vtable);
+    NrStatus (*handle)(NrStr entry, uint64_t sid, NrBytes payload);
+    void (*shutdown)(void);
+    NrStatus (*stream_data)(uint64_t sid, NrBytes data);
+    NrStatus (*stream_close)(uint64_t sid);
+    NrStatus (*health)(void);
+    NrStatus (*handle_req)(NrStr entry, uint64_t sid, const NrRequest *req, NrBytes payload);
+} NrPluginVTable;
+
+/* What `nylon_ring_get_plugin_v1` returns. */
+typedef struct NrPluginInfo {
+    uint32_t abi_version;
+    uint32_t struct_size;
+    NrStr name;
+    NrStr version;
+    void *plugin_ctx;
+    const NrPluginVTable *vtable;
+} NrPluginInfo;
+
+/* Defined by the plugin. */
+const NrPluginInfo *nylon_ring_get_plugin_v1(void);
+
+#ifdef __cplusplus
+}
+#define NR_STATIC_ASSERT(cond, msg) static_assert(cond, msg)
+#define NR_ALIGNOF(type) alignof(type)
+#else
+#define NR_STATIC_ASSERT(cond, msg) _Static_assert(cond, msg)
+#define NR_ALIGNOF(type) _Alignof(type)
+#endif
+
+/* The layouts of the Rust types, on 64-bit targets. */
+#if UINTPTR_MAX == 0xFFFFFFFFFFFFFFFFu
+NR_STATIC_ASSERT(sizeof(NrStatus) == 4, "NrStatus size");
+NR_STATIC_ASSERT(sizeof(NrStr) == 16, "NrStr size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrStr) == 8, "NrStr alignment");
+NR_STATIC_ASSERT(offsetof(NrStr, ptr) == 0, "NrStr.ptr offset");
+NR_STATIC_ASSERT(offsetof(NrStr, len) == 8, "NrStr.len offset");
+NR_STATIC_ASSERT(sizeof(NrBytes) == 16, "NrBytes size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrBytes) == 8, "NrBytes alignment");
+NR_STATIC_ASSERT(offsetof(NrBytes, ptr) == 0, "NrBytes.ptr offset");
+NR_STATIC_ASSERT(offsetof(NrBytes, len) == 8, "NrBytes.len offset");
+NR_STATIC_ASSERT(sizeof(NrKV) == 32, "NrKV size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrKV) == 8, "NrKV alignment");
+NR_STATIC_ASSERT(offsetof(NrKV, key) == 0, "NrKV.key offset");
+NR_STATIC_ASSERT(offsetof(NrKV, value) == 16, "NrKV.value offset");
+NR_STATIC_ASSERT(sizeof(NrRequest) == 64, "NrRequest size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrRequest) == 8, "NrRequest alignment");
+NR_STATIC_ASSERT(offsetof(NrRequest, method) == 0, "NrRequest.method offset");
+NR_STATIC_ASSERT(offsetof(NrRequest, path) == 16, "NrRequest.path offset");
+NR_STATIC_ASSERT(offsetof(NrRequest, query) == 32, "NrRequest.query offset");
+NR_STATIC_ASSERT(offsetof(NrRequest, headers) == 48, "NrRequest.headers offset");
+NR_STATIC_ASSERT(offsetof(NrRequest, headers_len) == 56, "NrRequest.headers_len offset");
+NR_STATIC_ASSERT(sizeof(NrVecU8) == 24, "NrVecU8 size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrVecU8) == 8, "NrVecU8 alignment");
+NR_STATIC_ASSERT(offsetof(NrVecU8, ptr) == 0, "NrVecU8.ptr offset");
+NR_STATIC_ASSERT(offsetof(NrVecU8, len) == 8, "NrVecU8.len offset");
+NR_STATIC_ASSERT(offsetof(NrVecU8, cap) == 16, "NrVecU8.cap offset");
+NR_STATIC_ASSERT(sizeof(NrTupleStatusU64) == 16, "NrTupleStatusU64 size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrTupleStatusU64) == 8, "NrTupleStatusU64 alignment");
+NR_STATIC_ASSERT(offsetof(NrTupleStatusU64, a) == 0, "NrTupleStatusU64.a offset");
+NR_STATIC_ASSERT(offsetof(NrTupleStatusU64, b) == 8, "NrTupleStatusU64.b offset");
+NR_STATIC_ASSERT(sizeof(NrTupleU64Ptr) == 16, "NrTupleU64Ptr size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrTupleU64Ptr) == 8, "NrTupleU64Ptr alignment");
+NR_STATIC_ASSERT(offsetof(NrTupleU64Ptr, a) == 0, "NrTupleU64Ptr.a offset");
+NR_STATIC_ASSERT(offsetof(NrTupleU64Ptr, b) == 8, "NrTupleU64Ptr.b offset");
+NR_STATIC_ASSERT(sizeof(NrHostVTable) == 24, "NrHostVTable size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrHostVTable) == 8, "NrHostVTable alignment");
+NR_STATIC_ASSERT(offsetof(NrHostVTable, send_result) == 0, "NrHostVTable.send_result offset");
+NR_STATIC_ASSERT(offsetof(NrHostVTable, get_host_ext) == 8, "NrHostVTable.get_host_ext offset");
+NR_STATIC_ASSERT(offsetof(NrHostVTable, send_result_channel) == 16, "NrHostVTable.send_result_channel offset");
+NR_STATIC_ASSERT(sizeof(NrHostExt) == 112, "NrHostExt size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrHostExt) == 8, "NrHostExt alignment");
+NR_STATIC_ASSERT(offsetof(NrHostExt, set_state) == 0, "NrHostExt.set_state offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, get_state) == 8, "NrHostExt.get_state offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, report_panic) == 16, "NrHostExt.report_panic offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, dispatch_spawn) == 24, "NrHostExt.dispatch_spawn offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, take_dispatch_result) == 32, "NrHostExt.take_dispatch_result offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, get_state_into) == 40, "NrHostExt.get_state_into offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, is_revoked) == 48, "NrHostExt.is_revoked offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, complete_later) == 56, "NrHostExt.complete_later offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, log) == 64, "NrHostExt.log offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, buf_acquire) == 72, "NrHostExt.buf_acquire offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, buf_commit) == 80, "NrHostExt.buf_commit offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, set_state_scoped) == 88, "NrHostExt.set_state_scoped offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, get_state_scoped_into) == 96, "NrHostExt.get_state_scoped_into offset");
+NR_STATIC_ASSERT(offsetof(NrHostExt, send_result_seq) == 104, "NrHostExt.send_result_seq offset");
+NR_STATIC_ASSERT(sizeof(NrPluginVTable) == 56, "NrPluginVTable size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrPluginVTable) == 8, "NrPluginVTable alignment");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, init) == 0, "NrPluginVTable.init offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, handle) == 8, "NrPluginVTable.handle offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, shutdown) == 16, "NrPluginVTable.shutdown offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, stream_data) == 24, "NrPluginVTable.stream_data offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, stream_close) == 32, "NrPluginVTable.stream_close offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, health) == 40, "NrPluginVTable.health offset");
+NR_STATIC_ASSERT(offsetof(NrPluginVTable, handle_req) == 48, "NrPluginVTable.handle_req offset");
+NR_STATIC_ASSERT(sizeof(NrPluginInfo) == 56, "NrPluginInfo size");
+NR_STATIC_ASSERT(NR_ALIGNOF(NrPluginInfo) == 8, "NrPluginInfo alignment");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, abi_version) == 0, "NrPluginInfo.abi_version offset");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, struct_size) == 4, "NrPluginInfo.struct_size offset");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, name) == 8, "NrPluginInfo.name offset");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, version) == 24, "NrPluginInfo.version offset");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, plugin_ctx) == 40, "NrPluginInfo.plugin_ctx offset");
+NR_STATIC_ASSERT(offsetof(NrPluginInfo, vtable) == 48, "NrPluginInfo.vtable offset");
+#endif
+
+#endif /* NYLON_RING_H */
@@ -0,0 +1,110 @@
+//! Answering calls from async tasks.
+//!
+//! A handler that has to await something should not block `handle` on it.
+//! [`nr_async_reply`] tells the host the answer comes later, runs the reply
+//! future on the plugin's own Tokio runtime and sends its result once it
+//! resolves:
+//!
+//! ```
+//! use nylon_ring::{nr_async_reply, NrBytes, NrHostVTable, NrStatus, NrVec};
+//! use std::ffi::c_void;
+//! use std::sync::OnceLock;
+//! use std::sync::atomic::{AtomicPtr, Ordering};
+//!
+//! static HOST_CTX: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+//! static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
+//! // Built in `init`.
+//! static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+//!
+//! unsafe fn handle_lookup(sid: u64, payload: NrBytes) -> NrStatus {
+//!     // `payload` is only valid during `handle`; the future owns a copy.
+//!     let key = payload.as_slice().to_vec();
+//!     let runtime = RUNTIME.get().expect("runtime is built in init");
+//!     unsafe {
+//!         nr_async_reply(
+//!             runtime.handle(),
+//!             HOST_CTX.load(Ordering::Acquire),
+//!             HOST_VTABLE.load(Ordering::Acquire),
+//!             sid,
+//!             async move { (NrStatus::Ok, NrVec::from_vec(key)) },
+//!         )
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use crate::error_frame::encode_error;
+use crate::panic_report::{describe_panic, report};
+use crate::{NrHostVTable, NrStatus, NrVec};
+use std::ffi::c_void;
+use std::future::Future;
+
+/// Error code sent when the reply future panicked.
+pub const PANIC_ERROR_CODE: u32 = 500;
+
+/// The host context, carried into the reply task.
+#[derive(Clone, Copy)]
+struct HostCtx(*mut c_void);
+
+// Safety: the host's callbacks may be called from any thread.
+unsafe impl Send for HostCtx {}
+
+impl HostCtx {
+    // A method, so closures capture the whole `Send` wrapper rather than
+    // its raw pointer field.
+    fn get(self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Answer the call `sid` with what `reply` resolves to, from a task on
+/// `runtime`. Returns the status `handle` should return: `Accepted` once
+/// the reply is scheduled, or the host's refusal.
+///
+/// The host is told with `NrHostExt::complete_later` first, so callers that
+/// expected the answer during `handle` wait for it. If `reply` panics, the
+/// call fails with `Err` and an error frame (code [`PANIC_ERROR_CODE`])
+/// carrying the panic message, and the panic is reported to the host.
+///
+/// # Safety
+///
+/// `host_ctx` and `host_vtable` must be the values the host passed to the
+/// plugin's `init`, and `sid` the SID `handle` was called with.
+pub unsafe fn nr_async_reply<F>(
+    runtime: &tokio::runtime::Handle,
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    sid: u64,
+    reply: F,
+) -> NrStatus
+where
+    F: Future<Output = (NrStatus, NrVec<u8>)> + Send + 'static,
+{
+    let vtable = unsafe { *host_vtable };
+    let ext = unsafe { (vtable.get_host_ext)(host_ctx) };
+    if !ext.is_null() {
+        let status = unsafe { ((*ext).complete_later)(host_ctx, sid) };
+        if status != NrStatus::Ok {
+            return status;
+        }
+    }
+
+    let host = HostCtx(host_ctx);
+    let task = runtime.spawn(reply);
+    runtime.spawn(async move {
+        let (status, payload) = match task.await {
+            Ok(reply) => reply,
+            Err(e) => match e.try_into_panic() {
+                Ok(panic) => {
+                    unsafe { report(host.get(), &vtable, sid, "<async reply>", &*panic) };
+                    let message = format!("reply panicked: {}", describe_panic(&*panic));
+                    (NrStatus::Err, encode_error(PANIC_ERROR_CODE, &message))
+                }
+                // Cancelled with the runtime, which takes this task too.
+                Err(_) => return,
+            },
+        };
+        unsafe { (vtable.send_result)(host.get(), sid, status, payload) };
+    });
+    NrStatus::Accepted
+}
@@ -0,0 +1,82 @@
+//! Several payloads in one call.
+//!
+//! A batch is a sequence of items, each its length as a little-endian `u32`
+//! followed by its bytes. The host's `Notifier` sends fire-and-forget events
+//! this way, one call per batch, so an entry fed by a notifier reads its
+//! payload with [`batch_items`].
+
+use alloc::vec::Vec;
+
+/// Bytes a batch spends on an item of `len` bytes.
+pub const fn batch_item_len(len: usize) -> usize {
+    4 + len
+}
+
+/// Append `item` to `batch`.
+///
+/// # Panics
+///
+/// If `item` is longer than `u32::MAX` bytes.
+pub fn push_batch_item(batch: &mut Vec<u8>, item: &[u8]) {
+    let len = u32::try_from(item.len()).expect("batch item longer than u32::MAX bytes");
+    batch.extend_from_slice(&len.to_le_bytes());
+    batch.extend_from_slice(item);
+}
+
+/// Encode `items` as a batch.
+pub fn encode_batch<'a>(items: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut batch = Vec::new();
+    for item in items {
+        push_batch_item(&mut batch, item);
+    }
+    batch
+}
+
+/// The items of a batch, in order. A truncated last item is not returned.
+pub fn batch_items(batch: &[u8]) -> BatchItems<'_> {
+    BatchItems { rest: batch }
+}
+
+/// Iterator returned by [`batch_items`].
+#[derive(Debug, Clone)]
+pub struct BatchItems<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for BatchItems<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let (len, rest) = self.rest.split_first_chunk::<4>()?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            self.rest = &[];
+            return None;
+        }
+        let (item, rest) = rest.split_at(len);
+        self.rest = rest;
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_round_trip() {
+        let items: [&[u8]; 3] = [b"one", b"", b"three"];
+        let batch = encode_batch(items);
+        assert_eq!(
+            batch.len(),
+            items.iter().map(|i| batch_item_len(i.len())).sum::<usize>()
+        );
+        assert_eq!(batch_items(&batch).collect::<Vec<_>>(), items);
+
+        assert_eq!(batch_items(&[]).count(), 0);
+        // The length says five bytes, only two follow.
+        assert_eq!(batch_items(b"\x05\x00\x00\x00ab").count(), 0);
+        let truncated = &batch[..batch.len() - 1];
+        assert_eq!(batch_items(truncated).count(), 2);
+    }
+}
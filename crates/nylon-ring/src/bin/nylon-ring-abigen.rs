@@ -0,0 +1,344 @@
+//! Writes `include/nylon_ring.h`, the C declarations of the plugin ABI.
+//!
+//! ```text
+//! cargo run -p nylon-ring --bin nylon-ring-abigen            # regenerate
+//! cargo run -p nylon-ring --bin nylon-ring-abigen -- --check # fail if stale
+//! ```
+//!
+//! An optional path argument writes, or checks, another file.
+//!
+//! Offsets and sizes come from the Rust types, so the header's layout
+//! assertions always match them. The field lists are written here by hand;
+//! a field added on the Rust side and missing here leaves the struct
+//! uncovered, which fails generation.
+
+use nylon_ring::{
+    INIT_SID, NR_ABI_VERSION, NR_SCOPE_GLOBAL, NR_SCOPE_PLUGIN, NR_SCOPE_SID, NR_STATE_ABSENT,
+    NrBytes, NrHostExt, NrHostVTable, NrKV, NrLogLevel, NrPluginInfo, NrPluginVTable, NrRequest,
+    NrStatus, NrStr, NrTuple, NrVec,
+};
+use std::fmt::Write;
+use std::mem::{align_of, offset_of, size_of};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const DEFAULT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/include/nylon_ring.h");
+
+struct Field {
+    name: &'static str,
+    /// The C declaration, with `@` for the field's name.
+    decl: &'static str,
+    offset: usize,
+    size: usize,
+}
+
+struct CStruct {
+    name: &'static str,
+    doc: &'static str,
+    size: usize,
+    align: usize,
+    fields: Vec<Field>,
+}
+
+fn pointee_size<T>(_: *const T) -> usize {
+    size_of::<T>()
+}
+
+/// A [`CStruct`] for `$rust`, its fields listed in declaration order.
+macro_rules! c_struct {
+    ($rust:ty as $name:literal, $doc:literal { $($field:ident: $decl:literal,)* }) => {
+        CStruct {
+            name: $name,
+            doc: $doc,
+            size: size_of::<$rust>(),
+            align: align_of::<$rust>(),
+            fields: vec![$(Field {
+                name: stringify!($field),
+                decl: $decl,
+                offset: offset_of!($rust, $field),
+                size: {
+                    let value = std::mem::MaybeUninit::<$rust>::uninit();
+                    // Safety: only the field's address is taken, nothing is read.
+                    pointee_size(unsafe { &raw const (*value.as_ptr()).$field })
+                },
+            }),*],
+        }
+    };
+}
+
+fn structs() -> Vec<CStruct> {
+    vec![
+        c_struct!(NrStr as "NrStr", "A UTF-8 string slice. Not NUL-terminated." {
+            ptr: "const uint8_t *@",
+            len: "uint32_t @",
+        }),
+        c_struct!(NrBytes as "NrBytes", "A byte slice." {
+            ptr: "const uint8_t *@",
+            len: "uint64_t @",
+        }),
+        c_struct!(NrKV as "NrKV", "A key-value pair of strings." {
+            key: "NrStr @",
+            value: "NrStr @",
+        }),
+        c_struct!(NrRequest as "NrRequest", "Method, path, query and headers of a call to `handle_req`." {
+            method: "NrStr @",
+            path: "NrStr @",
+            query: "NrStr @",
+            headers: "const NrKV *@",
+            headers_len: "uint32_t @",
+        }),
+        c_struct!(NrVec<u8> as "NrVecU8", "An owned byte buffer, `NrVec<u8>` on the Rust side." {
+            ptr: "uint8_t *@",
+            len: "size_t @",
+            cap: "size_t @",
+        }),
+        c_struct!(NrTuple<NrStatus, u64> as "NrTupleStatusU64", "`NrTuple<NrStatus, u64>`: what `dispatch_spawn` returns." {
+            a: "NrStatus @",
+            b: "uint64_t @",
+        }),
+        c_struct!(NrTuple<u64, *mut u8> as "NrTupleU64Ptr", "`NrTuple<u64, *mut u8>`: what `buf_acquire` returns." {
+            a: "uint64_t @",
+            b: "uint8_t *@",
+        }),
+        c_struct!(NrHostVTable as "NrHostVTable", "Host callback table, passed to `init`." {
+            send_result: "void (*@)(void *host_ctx, uint64_t sid, NrStatus status, NrVecU8 payload)",
+            get_host_ext: "const struct NrHostExt *(*@)(void *host_ctx)",
+            send_result_channel: "void (*@)(void *host_ctx, uint64_t sid, NrStatus status, NrStr channel, NrVecU8 payload)",
+        }),
+        c_struct!(NrHostExt as "NrHostExt", "Host extension table, from `get_host_ext`." {
+            set_state: "NrBytes (*@)(void *host_ctx, uint64_t sid, NrStr key, NrBytes value)",
+            get_state: "NrBytes (*@)(void *host_ctx, uint64_t sid, NrStr key)",
+            report_panic: "void (*@)(void *host_ctx, uint64_t sid, NrStr entry, NrStr message, NrStr backtrace)",
+            dispatch_spawn: "NrTupleStatusU64 (*@)(void *host_ctx, NrStr target, NrStr entry, NrBytes payload)",
+            take_dispatch_result: "bool (*@)(void *host_ctx, uint64_t sid, NrStatus *status, NrVecU8 *payload)",
+            get_state_into: "uint64_t (*@)(void *host_ctx, uint64_t sid, NrStr key, uint8_t *out_buf, uint64_t out_cap)",
+            is_revoked: "bool (*@)(void *host_ctx)",
+            complete_later: "NrStatus (*@)(void *host_ctx, uint64_t sid)",
+            log: "void (*@)(void *host_ctx, uint32_t level, NrStr target, NrStr message)",
+            buf_acquire: "NrTupleU64Ptr (*@)(void *host_ctx, uint64_t size)",
+            buf_commit: "NrStatus (*@)(void *host_ctx, uint64_t handle, uint64_t len)",
+            set_state_scoped: "NrBytes (*@)(void *host_ctx, uint32_t scope, uint64_t owner, NrStr key, NrBytes value)",
+            get_state_scoped_into: "uint64_t (*@)(void *host_ctx, uint32_t scope, uint64_t owner, NrStr key, uint8_t *out_buf, uint64_t out_cap)",
+            send_result_seq: "void (*@)(void *host_ctx, uint64_t sid, uint64_t seq, NrStatus status, NrVecU8 payload)",
+        }),
+        c_struct!(NrPluginVTable as "NrPluginVTable", "Plugin function table. `handle` is required; the rest may be NULL." {
+            init: "NrStatus (*@)(void *host_ctx, const NrHostVTable *host_vtable)",
+            handle: "NrStatus (*@)(NrStr entry, uint64_t sid, NrBytes payload)",
+            shutdown: "void (*@)(void)",
+            stream_data: "NrStatus (*@)(uint64_t sid, NrBytes data)",
+            stream_close: "NrStatus (*@)(uint64_t sid)",
+            health: "NrStatus (*@)(void)",
+            handle_req: "NrStatus (*@)(NrStr entry, uint64_t sid, const NrRequest *req, NrBytes payload)",
+        }),
+        c_struct!(NrPluginInfo as "NrPluginInfo", "What `nylon_ring_get_plugin_v1` returns." {
+            abi_version: "uint32_t @",
+            struct_size: "uint32_t @",
+            name: "NrStr @",
+            version: "NrStr @",
+            plugin_ctx: "void *@",
+            vtable: "const NrPluginVTable *@",
+        }),
+    ]
+}
+
+/// Fails unless the fields of `s` are in order and cover it.
+fn check_coverage(s: &CStruct) -> Result<(), String> {
+    let mut end = 0;
+    for field in &s.fields {
+        if field.offset < end {
+            return Err(format!("{}.{} is out of order", s.name, field.name));
+        }
+        end = field.offset + field.size;
+    }
+    if end.next_multiple_of(s.align) != s.size {
+        return Err(format!(
+            "the fields listed for {} end at {end} of its {} bytes; \
+             list the fields added to it in nylon-ring-abigen",
+            s.name, s.size
+        ));
+    }
+    Ok(())
+}
+
+fn statuses() -> Vec<(&'static str, u32)> {
+    // Fails to compile when a status is added, so it is not left out below.
+    let _exhaustive = |status: NrStatus| match status {
+        NrStatus::Ok
+        | NrStatus::Err
+        | NrStatus::Invalid
+        | NrStatus::Unsupported
+        | NrStatus::StreamEnd
+        | NrStatus::Revoked
+        | NrStatus::Accepted => {}
+    };
+    vec![
+        ("OK", NrStatus::Ok as u32),
+        ("ERR", NrStatus::Err as u32),
+        ("INVALID", NrStatus::Invalid as u32),
+        ("UNSUPPORTED", NrStatus::Unsupported as u32),
+        ("STREAM_END", NrStatus::StreamEnd as u32),
+        ("REVOKED", NrStatus::Revoked as u32),
+        ("ACCEPTED", NrStatus::Accepted as u32),
+    ]
+}
+
+fn log_levels() -> Vec<(&'static str, u32)> {
+    let _exhaustive = |level: NrLogLevel| match level {
+        NrLogLevel::Error
+        | NrLogLevel::Warn
+        | NrLogLevel::Info
+        | NrLogLevel::Debug
+        | NrLogLevel::Trace => {}
+    };
+    vec![
+        ("ERROR", NrLogLevel::Error as u32),
+        ("WARN", NrLogLevel::Warn as u32),
+        ("INFO", NrLogLevel::Info as u32),
+        ("DEBUG", NrLogLevel::Debug as u32),
+        ("TRACE", NrLogLevel::Trace as u32),
+    ]
+}
+
+fn render() -> Result<String, String> {
+    let structs = structs();
+    for s in &structs {
+        check_coverage(s)?;
+    }
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        r#"/* nylon_ring.h - the nylon-ring plugin ABI, version {NR_ABI_VERSION}, for C.
+ *
+ * Generated by `cargo run -p nylon-ring --bin nylon-ring-abigen`; do not edit.
+ * See the nylon-ring crate's documentation for what each callback does.
+ *
+ * A plugin exports `nylon_ring_get_plugin_v1`, returning an NrPluginInfo
+ * with `abi_version` NR_ABI_VERSION and `struct_size` sizeof(NrPluginInfo).
+ *
+ * The host frees the NrVecU8 payloads a plugin sends with its global
+ * allocator: allocate them with malloc() for hosts on the system allocator,
+ * Rust's default.
+ */
+#ifndef NYLON_RING_H
+#define NYLON_RING_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {{
+#endif
+
+#define NR_ABI_VERSION {NR_ABI_VERSION}u
+#define NR_INIT_SID {INIT_SID}u
+#define NR_STATE_ABSENT {NR_STATE_ABSENT:#X}u
+#define NR_SCOPE_SID {NR_SCOPE_SID}u
+#define NR_SCOPE_PLUGIN {NR_SCOPE_PLUGIN}u
+#define NR_SCOPE_GLOBAL {NR_SCOPE_GLOBAL}u
+
+/* Status codes. */
+typedef uint32_t NrStatus;
+"#
+    );
+    for (name, value) in statuses() {
+        let _ = writeln!(out, "#define NR_STATUS_{name} {value}u");
+    }
+    let _ = writeln!(out, "\n/* Levels for `NrHostExt.log`. */");
+    for (name, value) in log_levels() {
+        let _ = writeln!(out, "#define NR_LOG_{name} {value}u");
+    }
+
+    for s in &structs {
+        let _ = writeln!(out, "\n/* {} */", s.doc);
+        let _ = writeln!(out, "typedef struct {} {{", s.name);
+        for field in &s.fields {
+            let _ = writeln!(out, "    {};", field.decl.replace('@', field.name));
+        }
+        let _ = writeln!(out, "}} {};", s.name);
+    }
+
+    let _ = write!(
+        out,
+        r#"
+/* Defined by the plugin. */
+const NrPluginInfo *nylon_ring_get_plugin_v1(void);
+
+#ifdef __cplusplus
+}}
+#define NR_STATIC_ASSERT(cond, msg) static_assert(cond, msg)
+#define NR_ALIGNOF(type) alignof(type)
+#else
+#define NR_STATIC_ASSERT(cond, msg) _Static_assert(cond, msg)
+#define NR_ALIGNOF(type) _Alignof(type)
+#endif
+
+/* The layouts of the Rust types, on 64-bit targets. */
+#if UINTPTR_MAX == 0xFFFFFFFFFFFFFFFFu
+NR_STATIC_ASSERT(sizeof(NrStatus) == {status}, "NrStatus size");
+"#,
+        status = size_of::<NrStatus>(),
+    );
+    for s in &structs {
+        let name = s.name;
+        let _ = writeln!(
+            out,
+            "NR_STATIC_ASSERT(sizeof({name}) == {}, \"{name} size\");",
+            s.size
+        );
+        let _ = writeln!(
+            out,
+            "NR_STATIC_ASSERT(NR_ALIGNOF({name}) == {}, \"{name} alignment\");",
+            s.align
+        );
+        for field in &s.fields {
+            let _ = writeln!(
+                out,
+                "NR_STATIC_ASSERT(offsetof({name}, {field}) == {offset}, \"{name}.{field} offset\");",
+                field = field.name,
+                offset = field.offset,
+            );
+        }
+    }
+    out.push_str("#endif\n\n#endif /* NYLON_RING_H */\n");
+    Ok(out)
+}
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut path = PathBuf::from(DEFAULT_PATH);
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if arg.starts_with('-') => {
+                eprintln!("usage: nylon-ring-abigen [--check] [PATH]");
+                return ExitCode::FAILURE;
+            }
+            _ => path = PathBuf::from(arg),
+        }
+    }
+
+    let header = match render() {
+        Ok(header) => header,
+        Err(e) => {
+            eprintln!("nylon-ring-abigen: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if check {
+        if std::fs::read_to_string(&path).is_ok_and(|existing| existing == header) {
+            return ExitCode::SUCCESS;
+        }
+        eprintln!(
+            "{} is stale; run `cargo run -p nylon-ring --bin nylon-ring-abigen`",
+            path.display()
+        );
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = std::fs::write(&path, header) {
+        eprintln!("nylon-ring-abigen: failed to write {}: {e}", path.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
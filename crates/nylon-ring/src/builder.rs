@@ -0,0 +1,113 @@
+//! Entries registered at runtime.
+//!
+//! `define_plugin!` with `entries: runtime` dispatches through the table a
+//! [`PluginBuilder`] installs, typically from `init` once the plugin knows
+//! which routes it serves:
+//!
+//! ```
+//! use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, PluginBuilder};
+//! use std::ffi::c_void;
+//!
+//! unsafe fn init(_host_ctx: *mut c_void, _host_vtable: *const NrHostVTable) -> NrStatus {
+//!     let mut builder = PluginBuilder::new().entry("health", handle_health);
+//!     for route in ["users", "orders"] {
+//!         builder = builder.raw_entry(route, handle_route);
+//!     }
+//!     builder.install();
+//!     NrStatus::Ok
+//! }
+//!
+//! fn shutdown() {}
+//!
+//! unsafe fn handle_health(_sid: u64, _payload: NrBytes) -> NrStatus {
+//!     NrStatus::Ok
+//! }
+//!
+//! unsafe fn handle_route(entry: &str, _sid: u64, _payload: NrBytes) -> NrStatus {
+//!     println!("called {entry}");
+//!     NrStatus::Ok
+//! }
+//!
+//! define_plugin! {
+//!     init: init,
+//!     shutdown: shutdown,
+//!     entries: runtime,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! The table is global to the shared library, which holds one plugin.
+
+use crate::{NrBytes, NrStatus, PluginEntryFn, PluginRawEntryFn};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy)]
+enum Handler {
+    Entry(PluginEntryFn),
+    Raw(PluginRawEntryFn),
+}
+
+static ENTRIES: RwLock<Option<HashMap<Box<str>, Handler>>> = RwLock::new(None);
+
+/// Collects entries and installs them as the plugin's entry table.
+#[derive(Default)]
+pub struct PluginBuilder {
+    entries: HashMap<Box<str>, Handler>,
+}
+
+impl PluginBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `name` with `handler`, replacing an earlier handler for it.
+    pub fn entry(mut self, name: impl Into<Box<str>>, handler: PluginEntryFn) -> Self {
+        self.entries.insert(name.into(), Handler::Entry(handler));
+        self
+    }
+
+    /// Like [`entry`](Self::entry), for a handler that is also passed the
+    /// entry name, so one function can serve several entries.
+    pub fn raw_entry(mut self, name: impl Into<Box<str>>, handler: PluginRawEntryFn) -> Self {
+        self.entries.insert(name.into(), Handler::Raw(handler));
+        self
+    }
+
+    /// Make these the plugin's entries, replacing any installed before.
+    pub fn install(self) {
+        *ENTRIES.write().unwrap_or_else(|e| e.into_inner()) = Some(self.entries);
+    }
+}
+
+/// Call the installed handler for `entry`. `Invalid` if there is none.
+///
+/// # Safety
+///
+/// Same contract as calling the handler directly.
+#[doc(hidden)]
+pub unsafe fn dispatch(entry: &str, sid: u64, payload: NrBytes) -> NrStatus {
+    let handler = ENTRIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|entries| entries.get(entry).copied());
+    match handler {
+        Some(Handler::Entry(handler)) => unsafe { handler(sid, payload) },
+        Some(Handler::Raw(handler)) => unsafe { handler(entry, sid, payload) },
+        None => NrStatus::Invalid,
+    }
+}
+
+/// The names of the installed entries, sorted.
+#[doc(hidden)]
+pub fn entry_names() -> Vec<Box<str>> {
+    let mut names: Vec<_> = ENTRIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .flat_map(|entries| entries.keys().cloned())
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,208 @@
+//! Typed payloads.
+//!
+//! A [`Codec`] turns values into payload bytes and back. Host and plugin
+//! must agree on the codec for an entry; [`Json`] is the default.
+//! [`PayloadCodec`] instead names the codec in a one-byte prefix of the
+//! request, which the plugin reads with [`nr_decode_request`] and answers
+//! in kind with [`nr_encode_response`].
+//! [`TypedSink`] is the plugin side of a typed stream: each item becomes one
+//! `Ok` frame.
+
+use crate::{NrHostVTable, NrStatus, NrVec};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::ffi::c_void;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Failure to encode or decode a payload.
+#[derive(Debug)]
+pub struct CodecError(Box<dyn std::error::Error + Send + Sync>);
+
+impl CodecError {
+    pub fn new(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+/// A payload encoding.
+pub trait Codec: Send + Sync + 'static {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// JSON via `serde_json`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(value).map_err(CodecError::new)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        serde_json::from_slice(bytes).map_err(CodecError::new)
+    }
+}
+
+/// CBOR via `ciborium`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl Codec for Cbor {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(CodecError::new)?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        ciborium::from_reader(bytes).map_err(CodecError::new)
+    }
+}
+
+/// A codec chosen per call. The request starts with the codec's
+/// [`tag`](PayloadCodec::tag); the response is encoded with the same codec,
+/// without a tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadCodec {
+    #[default]
+    Json = 1,
+    Cbor = 2,
+}
+
+impl PayloadCodec {
+    /// The prefix byte naming this codec.
+    pub fn tag(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::Json),
+            2 => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+
+    /// `value` encoded as a request: the tag, then the encoded bytes.
+    pub fn encode_request<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = vec![self.tag()];
+        match self {
+            Self::Json => serde_json::to_writer(&mut bytes, value).map_err(CodecError::new)?,
+            Self::Cbor => ciborium::into_writer(value, &mut bytes).map_err(CodecError::new)?,
+        }
+        Ok(bytes)
+    }
+}
+
+impl Codec for PayloadCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Self::Json => Json.encode(value),
+            Self::Cbor => Cbor.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        match self {
+            Self::Json => Json.decode(bytes),
+            Self::Cbor => Cbor.decode(bytes),
+        }
+    }
+}
+
+/// Decode a request sent with a [`PayloadCodec`] prefix, returning the
+/// value and the codec to answer with.
+pub fn nr_decode_request<T: DeserializeOwned>(
+    payload: &[u8],
+) -> Result<(T, PayloadCodec), CodecError> {
+    let (&tag, bytes) = payload
+        .split_first()
+        .ok_or_else(|| CodecError::new("empty request"))?;
+    let codec = PayloadCodec::from_tag(tag)
+        .ok_or_else(|| CodecError::new(format!("unknown codec tag {tag}")))?;
+    Ok((codec.decode(bytes)?, codec))
+}
+
+/// Encode the response to a request decoded with [`nr_decode_request`].
+pub fn nr_encode_response<T: Serialize + ?Sized>(
+    codec: PayloadCodec,
+    value: &T,
+) -> Result<NrVec<u8>, CodecError> {
+    codec.encode(value).map(NrVec::from_vec)
+}
+
+/// The sending side of one typed stream.
+pub struct TypedSink<Item, C = Json> {
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    sid: u64,
+    codec: C,
+    _item: PhantomData<fn(&Item)>,
+}
+
+// Safety: the host's `send_result` may be called from any thread.
+unsafe impl<Item, C: Send> Send for TypedSink<Item, C> {}
+
+impl<Item: Serialize, C: Codec> TypedSink<Item, C> {
+    /// # Safety
+    ///
+    /// `host_ctx` and `host_vtable` must be the values the host passed to the
+    /// plugin's `init`, and `sid` the SID of a streaming call.
+    pub unsafe fn new(
+        host_ctx: *mut c_void,
+        host_vtable: *const NrHostVTable,
+        sid: u64,
+        codec: C,
+    ) -> Self {
+        Self {
+            host_ctx,
+            host_vtable,
+            sid,
+            codec,
+            _item: PhantomData,
+        }
+    }
+
+    /// Send one item. Nothing is sent if it fails to encode.
+    pub fn send(&self, item: &Item) -> Result<(), CodecError> {
+        let bytes = self.codec.encode(item)?;
+        self.frame(NrStatus::Ok, bytes);
+        Ok(())
+    }
+
+    /// End the stream normally.
+    pub fn end(self) {
+        self.frame(NrStatus::StreamEnd, Vec::new());
+    }
+
+    /// End the stream with an error `status` and a message for the caller.
+    pub fn fail(self, status: NrStatus, message: &str) {
+        self.frame(status, message.as_bytes().to_vec());
+    }
+
+    fn frame(&self, status: NrStatus, bytes: Vec<u8>) {
+        unsafe {
+            ((*self.host_vtable).send_result)(
+                self.host_ctx,
+                self.sid,
+                status,
+                NrVec::from_vec(bytes),
+            );
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Machine-readable error payloads.
+//!
+//! A plugin that fails a call with `NrStatus::Err` can send an error frame
+//! as the payload instead of free-form bytes, so the host can tell failures
+//! apart by code. The frame is [`ERROR_FRAME_MAGIC`], the code as a
+//! little-endian `u32`, then the UTF-8 message.
+
+use crate::NrVec;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// First bytes of an error frame.
+pub const ERROR_FRAME_MAGIC: [u8; 4] = *b"NRE1";
+
+const HEADER_LEN: usize = ERROR_FRAME_MAGIC.len() + 4;
+
+/// A decoded error frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NrError {
+    pub code: u32,
+    pub message: String,
+}
+
+/// Encode an error frame, to be sent with `NrStatus::Err`.
+pub fn encode_error(code: u32, message: &str) -> NrVec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + message.len());
+    frame.extend_from_slice(&ERROR_FRAME_MAGIC);
+    frame.extend_from_slice(&code.to_le_bytes());
+    frame.extend_from_slice(message.as_bytes());
+    NrVec::from_vec(frame)
+}
+
+/// Decode an error frame. `None` if `payload` is not one.
+///
+/// Invalid UTF-8 in the message is replaced rather than rejected.
+pub fn decode_error(payload: &[u8]) -> Option<NrError> {
+    let rest = payload.strip_prefix(&ERROR_FRAME_MAGIC)?;
+    let (code, message) = rest.split_first_chunk::<4>()?;
+    Some(NrError {
+        code: u32::from_le_bytes(*code),
+        message: String::from_utf8_lossy(message).into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_frame_round_trip() {
+        let frame = encode_error(404, "no such user");
+        assert_eq!(
+            decode_error(frame.as_slice()),
+            Some(NrError {
+                code: 404,
+                message: "no such user".to_string(),
+            })
+        );
+
+        let empty = encode_error(u32::MAX, "");
+        assert_eq!(decode_error(empty.as_slice()).unwrap().code, u32::MAX);
+
+        assert_eq!(decode_error(b"plain message"), None);
+        assert_eq!(decode_error(b"NRE1\x01\x00"), None);
+        assert_eq!(decode_error(b""), None);
+    }
+}
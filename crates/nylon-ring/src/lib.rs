@@ -1,4 +1,47 @@
-use std::ffi::c_void;
+//! The Nylon Ring ABI: the `repr(C)` types hosts and plugins exchange.
+//!
+//! Without the default `std` feature the crate is `no_std` (it still needs
+//! `alloc`), for embedded and sandboxed plugin targets: the ABI types and
+//! the pointer-free helpers stay, while `define_plugin!`, the safe
+//! [`Plugin`] API, panic reports, long polls, `HashMap` conversions and
+//! `NrAny`'s boxing constructors need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+#[cfg(feature = "tokio")]
+pub mod async_reply;
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "serde")]
+pub mod codec;
+pub mod error_frame;
+pub mod logging;
+#[cfg(feature = "std")]
+pub mod long_poll;
+#[cfg(feature = "std")]
+pub mod panic_report;
+#[cfg(feature = "std")]
+pub mod plugin;
+pub mod seq_frame;
+pub mod shared;
+
+#[cfg(feature = "tokio")]
+pub use async_reply::nr_async_reply;
+pub use batch::{batch_items, encode_batch, push_batch_item};
+#[cfg(feature = "std")]
+pub use builder::PluginBuilder;
+pub use error_frame::{NrError, decode_error, encode_error};
+pub use logging::NrLogLevel;
+#[cfg(feature = "std")]
+pub use plugin::{CallContext, HostApi, Plugin, Response};
+pub use seq_frame::{decode_seq_frame, encode_seq_frame};
 
 /// Status codes for the Nylon Ring ABI.
 #[repr(u32)]
@@ -10,8 +53,40 @@ pub enum NrStatus {
     Unsupported = 3,
     /// Streaming completed normally.
     StreamEnd = 4,
+    /// The host no longer serves this plugin's callbacks because the plugin
+    /// is being shut down.
+    Revoked = 5,
+    /// Returned by `handle`: the plugin took the call and answers it later
+    /// with `send_result`. Since ABI v2, `Ok` from `handle` means the
+    /// answer was already sent.
+    Accepted = 6,
 }
 
+impl NrStatus {
+    /// Whether `handle` took the call: `Ok` or `Accepted`.
+    pub fn is_success(self) -> bool {
+        matches!(self, NrStatus::Ok | NrStatus::Accepted)
+    }
+}
+
+/// The ABI version plugins built against this crate report.
+///
+/// Version 2 added [`NrStatus::Accepted`], and everything in
+/// [`NrHostVTable`] after `send_result` and in [`NrHostExt`] after
+/// `get_state`. Version 3 added [`NrHostExt::log`], version 4
+/// [`NrHostExt::buf_acquire`] and [`NrHostExt::buf_commit`], version 5
+/// [`NrHostExt::set_state_scoped`] and [`NrHostExt::get_state_scoped_into`],
+/// version 6 [`NrPluginVTable::health`], which hosts only read from
+/// plugins of version 6 or later, version 7
+/// [`NrHostExt::send_result_seq`], and version 8
+/// [`NrPluginVTable::handle_req`], likewise read only from plugins of
+/// version 8 or later. A host only loads
+/// plugins of the versions it knows, so a plugin can use every field of its
+/// version's tables; new fields are only ever appended, with a new version.
+/// Hosts still load version 1 plugins, for which `Ok` from `handle` may
+/// mean either.
+pub const NR_ABI_VERSION: u32 = 8;
+
 /// A UTF-8 string slice with a pointer and length.
 /// This struct is `#[repr(C)]` and ABI-stable.
 #[repr(C)]
@@ -38,12 +113,46 @@ pub struct NrKV {
     pub value: NrStr,
 }
 
-/// A key-value pair with any type as value.
+/// The method, path, query and headers of an HTTP-shaped call, passed to
+/// [`NrPluginVTable::handle_req`] next to the body. Everything it points to
+/// is the host's and only lives for the call.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct NrRequest {
+    pub method: NrStr,
+    pub path: NrStr,
+    /// The query string, without the `?`. Empty if there is none.
+    pub query: NrStr,
+    pub headers: *const NrKV,
+    pub headers_len: u32,
+}
+
+impl NrRequest {
+    /// The headers, in the order the host sent them.
+    pub fn headers(&self) -> &[NrKV] {
+        if self.headers.is_null() || self.headers_len == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.headers, self.headers_len as usize) }
+    }
+
+    /// The value of the first header named `name`, compared ASCII
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .iter()
+            .find(|kv| kv.key.as_bytes().eq_ignore_ascii_case(name.as_bytes()))
+            .map(|kv| kv.value.as_str())
+    }
+}
+
+/// A key-value pair with any type as value. The key is owned, so the pair
+/// releases both key and value when dropped.
 /// This struct is `#[repr(C)]` and ABI-stable.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct NrKVAny {
-    pub key: NrStr,
+    pub key: NrString,
     pub value: NrAny,
 }
 
@@ -59,9 +168,11 @@ pub struct NrIndexSlot {
 }
 
 /// A map/dictionary type implemented as a vector of key-value pairs with hash index.
+/// The map owns copies of its keys; removing an entry hands its key and
+/// value to the caller, and `clear` or dropping the map releases the rest.
 /// This struct is `#[repr(C)]` and ABI-stable.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct NrMap {
     pub entries: NrVec<NrKVAny>,
     pub index: NrVec<NrIndexSlot>, // hash index table
@@ -69,6 +180,12 @@ pub struct NrMap {
     pub tomb: u32,                 // number of tombstones
 }
 
+/// `NrAny` type tag reserved for byte blobs built with [`NrAny::from_bytes`].
+pub const NR_ANY_BYTES_TAG: u32 = u32::MAX;
+
+/// `NrAny` type tag of bytes borrowed with [`NrAny::from_static_bytes`].
+pub const NR_ANY_STATIC_BYTES_TAG: u32 = u32::MAX - 1;
+
 /// A type-erased value that can hold any data type.
 /// This struct is `#[repr(C)]` and ABI-stable.
 #[repr(C)]
@@ -86,6 +203,22 @@ pub struct NrAny {
 
 /// A vector with a pointer, length, and capacity.
 /// This struct is `#[repr(C)]` and ABI-stable.
+///
+/// # Allocation
+///
+/// Whoever ends up owning an `NrVec` grows, frees and converts it with
+/// its own global allocator: `Drop`, `reserve` and [`into_vec`] all assume
+/// the buffer came from there. An `NrVec` passed across the ABI, such as a
+/// payload sent with `send_result`, must therefore be allocated with the
+/// receiver's allocator. Rust plugins on the default allocator and C
+/// plugins using `malloc` meet this for hosts on the default allocator;
+/// a plugin or host with a custom `#[global_allocator]` does not, and must
+/// copy into a buffer from the other side's allocator instead. Nothing can
+/// check this at runtime; [`is_well_formed`] only checks that the parts
+/// are consistent.
+///
+/// [`into_vec`]: NrVec::into_vec
+/// [`is_well_formed`]: NrVec::is_well_formed
 #[repr(C)]
 #[derive(Debug)]
 pub struct NrVec<T> {
@@ -94,40 +227,28 @@ pub struct NrVec<T> {
     pub cap: usize,
 }
 
+/// An owned, growable UTF-8 string.
+/// This struct is `#[repr(C)]` and ABI-stable, with the layout of `NrVec<u8>`.
+#[repr(C)]
+#[derive(Default, Clone)]
+pub struct NrString {
+    buf: NrVec<u8>,
+}
+
 impl<T> Default for NrVec<T> {
     fn default() -> Self {
         Self {
-            ptr: std::ptr::null_mut(),
+            ptr: core::ptr::null_mut(),
             len: 0,
             cap: 0,
         }
     }
 }
 
-impl Default for NrKVAny {
-    fn default() -> Self {
-        Self {
-            key: NrStr::default(),
-            value: NrAny::default(),
-        }
-    }
-}
-
-impl Default for NrMap {
-    fn default() -> Self {
-        Self {
-            entries: NrVec::default(),
-            index: NrVec::default(),
-            used: 0,
-            tomb: 0,
-        }
-    }
-}
-
 impl Default for NrAny {
     fn default() -> Self {
         Self {
-            data: std::ptr::null_mut(),
+            data: core::ptr::null_mut(),
             size: 0,
             type_tag: 0,
             drop_fn: None,
@@ -144,12 +265,67 @@ pub struct NrTuple<A, B> {
     pub b: B,
 }
 
+impl<A, B> NrTuple<A, B> {
+    /// `(a, b)`.
+    #[inline]
+    pub fn into_tuple(self) -> (A, B) {
+        (self.a, self.b)
+    }
+
+    /// `(&a, &b)`.
+    #[inline]
+    pub fn as_tuple(&self) -> (&A, &B) {
+        (&self.a, &self.b)
+    }
+}
+
+impl<A, B> From<(A, B)> for NrTuple<A, B> {
+    #[inline]
+    fn from((a, b): (A, B)) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<A, B> From<NrTuple<A, B>> for (A, B) {
+    #[inline]
+    fn from(tuple: NrTuple<A, B>) -> Self {
+        tuple.into_tuple()
+    }
+}
+
 /// Host callback table.
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct NrHostVTable {
     pub send_result:
         unsafe extern "C" fn(host_ctx: *mut c_void, sid: u64, status: NrStatus, payload: NrVec<u8>),
+
+    /// Get the host extension table for this `host_ctx`.
+    /// Returns null if the host does not provide extensions.
+    pub get_host_ext: unsafe extern "C" fn(host_ctx: *mut c_void) -> *const NrHostExt,
+
+    /// Like `send_result`, tagging a stream frame with a named sub-channel
+    /// of the stream. Unary results ignore the channel.
+    pub send_result_channel: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        sid: u64,
+        status: NrStatus,
+        channel: NrStr,
+        payload: NrVec<u8>,
+    ),
+}
+
+impl NrHostVTable {
+    /// Whether the host has revoked the plugin's callbacks; see
+    /// [`NrHostExt::is_revoked`]. `false` if the host has no extension table.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`.
+    pub unsafe fn is_revoked(&self, host_ctx: *mut c_void) -> bool {
+        let ext = unsafe { (self.get_host_ext)(host_ctx) };
+        !ext.is_null() && unsafe { ((*ext).is_revoked)(host_ctx) }
+    }
 }
 
 /// Host extension table for state management.
@@ -168,13 +344,321 @@ pub struct NrHostExt {
 
     /// Get state for a given sid and key.
     /// Returns empty NrBytes if not found.
+    ///
+    /// The returned view points into a per-thread host buffer that the next
+    /// `get_state` call on the same thread overwrites or reallocates, so only
+    /// one view can be used at a time: copy it out before calling again.
+    /// Reading a view after that is undefined behavior. Prefer
+    /// `get_state_into`, or [`NrHostExt::read_state`].
     pub get_state: unsafe extern "C" fn(host_ctx: *mut c_void, sid: u64, key: NrStr) -> NrBytes,
+
+    /// Report a panic caught inside a plugin entry point.
+    /// The host copies the strings; they only need to live for the call.
+    pub report_panic: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        sid: u64,
+        entry: NrStr,
+        message: NrStr,
+        backtrace: NrStr,
+    ),
+
+    /// Call `entry` on the plugin loaded as `target` without waiting for its
+    /// response. Returns `(Ok, sid)` once the target accepted the call, to be
+    /// polled with `take_dispatch_result`; otherwise the status the target
+    /// returned, or `Invalid` if no such plugin is loaded, and SID 0.
+    pub dispatch_spawn: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        target: NrStr,
+        entry: NrStr,
+        payload: NrBytes,
+    ) -> NrTuple<NrStatus, u64>,
+
+    /// Take the response to a call started with `dispatch_spawn`. Returns
+    /// `false` while it has not arrived; otherwise writes it to `status` and
+    /// `payload` (whose previous contents are overwritten, not dropped) and
+    /// returns `true`. Each response can be taken once.
+    pub take_dispatch_result: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        sid: u64,
+        status: *mut NrStatus,
+        payload: *mut NrVec<u8>,
+    ) -> bool,
+
+    /// Copy the state for a given sid and key into `out_buf`, which has
+    /// room for `out_cap` bytes. Returns the length of the value, or
+    /// [`NR_STATE_ABSENT`] if there is none, so an empty value can be told
+    /// apart from a missing one. A value longer than `out_cap` is not copied;
+    /// call again with a buffer of at least the returned length.
+    pub get_state_into: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        sid: u64,
+        key: NrStr,
+        out_buf: *mut u8,
+        out_cap: u64,
+    ) -> u64,
+
+    /// Whether the host has revoked this plugin's callbacks ahead of its
+    /// `shutdown`. Once it has, callbacks return at once without doing
+    /// anything, so background work should stop instead of calling the host.
+    pub is_revoked: unsafe extern "C" fn(host_ctx: *mut c_void) -> bool,
+
+    /// Tell the host the call `sid` will be answered after `handle` returns,
+    /// from another thread or task. Call it from within `handle`, before
+    /// returning `Ok`. Without it, a caller expecting the answer during
+    /// `handle` may give up on the call once `handle` returns.
+    pub complete_later: unsafe extern "C" fn(host_ctx: *mut c_void, sid: u64) -> NrStatus,
+
+    /// Log `message` through the host's logger, with the plugin's name
+    /// attached. `level` is an [`NrLogLevel`]; other values log at `Info`.
+    /// The host copies the strings; they only need to live for the call.
+    pub log: unsafe extern "C" fn(host_ctx: *mut c_void, level: u32, target: NrStr, message: NrStr),
+
+    /// Take a host-owned buffer with room for `size` bytes, for a response
+    /// written in place (see [`shared`]). Returns its handle and where to
+    /// write, or handle 0 and a null pointer if the host cannot provide one.
+    /// The bytes are initialized, to unspecified values. The buffer is the
+    /// plugin's until committed.
+    pub buf_acquire:
+        unsafe extern "C" fn(host_ctx: *mut c_void, size: u64) -> NrTuple<u64, *mut u8>,
+
+    /// Mark the first `len` bytes of the buffer `handle` as written, so it
+    /// can be sent with [`shared::encode_shared_reply`]. No more writes may
+    /// follow. Returns `Invalid` for an unknown handle or a `len` past the
+    /// size it was acquired with.
+    pub buf_commit: unsafe extern "C" fn(host_ctx: *mut c_void, handle: u64, len: u64) -> NrStatus,
+
+    /// Like `set_state`, in `scope`: [`NR_SCOPE_SID`] with the SID as
+    /// `owner`, [`NR_SCOPE_PLUGIN`] or [`NR_SCOPE_GLOBAL`], where `owner` is
+    /// ignored. Returns error bytes for any other scope.
+    pub set_state_scoped: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        scope: u32,
+        owner: u64,
+        key: NrStr,
+        value: NrBytes,
+    ) -> NrBytes,
+
+    /// Like `get_state_into`, in `scope` (see `set_state_scoped`). Returns
+    /// [`NR_STATE_ABSENT`] for an unknown scope.
+    pub get_state_scoped_into: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        scope: u32,
+        owner: u64,
+        key: NrStr,
+        out_buf: *mut u8,
+        out_cap: u64,
+    ) -> u64,
+
+    /// Like `send_result`, numbering a stream frame with `seq` so the host
+    /// can put frames sent from several threads back in order. Unary
+    /// results ignore the number.
+    pub send_result_seq: unsafe extern "C" fn(
+        host_ctx: *mut c_void,
+        sid: u64,
+        seq: u64,
+        status: NrStatus,
+        payload: NrVec<u8>,
+    ),
+}
+
+/// What [`NrHostExt::get_state_into`] returns when there is no value.
+pub const NR_STATE_ABSENT: u64 = u64::MAX;
+
+/// State scope of one call, keyed by its SID: what `set_state` writes to.
+/// The host may drop it once the call is done.
+pub const NR_SCOPE_SID: u32 = 0;
+
+/// State scope of the calling plugin, kept across calls. Cleared when the
+/// plugin is unloaded or reloaded.
+pub const NR_SCOPE_PLUGIN: u32 = 1;
+
+/// State scope shared by every plugin of a host, kept until the host is
+/// dropped.
+pub const NR_SCOPE_GLOBAL: u32 = 2;
+
+/// Copy a state value out with `get`, a `get_state_into`-like call taking
+/// the buffer and its capacity.
+fn copy_state(mut get: impl FnMut(*mut u8, u64) -> u64) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    loop {
+        let len = get(buf.as_mut_ptr(), buf.capacity() as u64);
+        if len == NR_STATE_ABSENT {
+            return None;
+        }
+        let len = len as usize;
+        if len <= buf.capacity() {
+            // The host copied `len` bytes.
+            unsafe { buf.set_len(len) };
+            return Some(buf);
+        }
+        // The value grew since the last attempt, or this is the first.
+        buf.reserve(len);
+    }
+}
+
+impl NrHostExt {
+    /// Copy the state for `sid` and `key` out of the host. Empty if not found.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`.
+    pub unsafe fn read_state(&self, host_ctx: *mut c_void, sid: u64, key: &str) -> Vec<u8> {
+        unsafe { self.state(host_ctx, sid, key) }.unwrap_or_default()
+    }
+
+    /// Copy the state for `sid` and `key` out of the host, or `None` if
+    /// there is no value.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`.
+    pub unsafe fn state(&self, host_ctx: *mut c_void, sid: u64, key: &str) -> Option<Vec<u8>> {
+        copy_state(|buf, cap| unsafe {
+            (self.get_state_into)(host_ctx, sid, NrStr::new(key), buf, cap)
+        })
+    }
+
+    /// Copy the state for `key` in `scope` out of the host (see
+    /// [`NrHostExt::set_state_scoped`]), or `None` if there is no value.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`, and the
+    /// host must support ABI version 5.
+    pub unsafe fn state_scoped(
+        &self,
+        host_ctx: *mut c_void,
+        scope: u32,
+        owner: u64,
+        key: &str,
+    ) -> Option<Vec<u8>> {
+        copy_state(|buf, cap| unsafe {
+            (self.get_state_scoped_into)(host_ctx, scope, owner, NrStr::new(key), buf, cap)
+        })
+    }
+
+    /// `data` prefixed with the nonce of the call `sid`, to send as its
+    /// result. `data` as it is if the call has no nonce: the plugin did not
+    /// declare [`ECHOES_NONCE_KEY`], or `sid` is not a unary call.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`.
+    pub unsafe fn echo_nonce(&self, host_ctx: *mut c_void, sid: u64, data: &[u8]) -> NrVec<u8> {
+        let mut frame = unsafe { self.read_state(host_ctx, sid, CALL_NONCE_KEY) };
+        frame.extend_from_slice(data);
+        NrVec::from_vec(frame)
+    }
+
+    /// Acquire a host buffer of `size` bytes, let `write` fill it and
+    /// return how many bytes it wrote, and commit that many. The buffer
+    /// holds unspecified bytes before `write`. Returns the
+    /// handle to reply with, or `None` if the host has no buffer or `write`
+    /// claims more than `size` bytes.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` must be the `host_ctx` the host passed to `init`.
+    pub unsafe fn write_shared(
+        &self,
+        host_ctx: *mut c_void,
+        size: usize,
+        write: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Option<u64> {
+        let (handle, ptr) = unsafe { (self.buf_acquire)(host_ctx, size as u64) }.into_tuple();
+        if ptr.is_null() {
+            return None;
+        }
+        let len = write(unsafe { core::slice::from_raw_parts_mut(ptr, size) });
+        match unsafe { (self.buf_commit)(host_ctx, handle, len as u64) } {
+            NrStatus::Ok => Some(handle),
+            _ => None,
+        }
+    }
 }
 
 // Safety: NrHostExt is ABI-stable data carrier.
 unsafe impl Send for NrHostExt {}
 unsafe impl Sync for NrHostExt {}
 
+/// Reserved SID for state set during `init`.
+pub const INIT_SID: u64 = 0;
+
+/// State key under [`INIT_SID`] carrying the reason `init` failed.
+///
+/// A plugin whose `init` is about to return a non-`Ok` status can
+/// `set_state(host_ctx, INIT_SID, INIT_ERROR_KEY, reason)` first; the host
+/// reports the UTF-8 reason with the load error.
+pub const INIT_ERROR_KEY: &str = "__init_error";
+
+/// Prefix of state keys under [`INIT_SID`] that publish a JSON Schema for an
+/// entry's requests: `set_state(host_ctx, INIT_SID, "__request_schema:get",
+/// schema)`. Hosts that validate payloads reject requests to `get` that do
+/// not conform.
+pub const REQUEST_SCHEMA_KEY_PREFIX: &str = "__request_schema:";
+
+/// Like [`REQUEST_SCHEMA_KEY_PREFIX`], for an entry's `Ok` responses.
+pub const RESPONSE_SCHEMA_KEY_PREFIX: &str = "__response_schema:";
+
+/// State key under [`INIT_SID`] listing the entries a plugin serves, one
+/// per line. `define_plugin!` publishes it once `init` returns `Ok`, so a
+/// host can tell which entries a reload added or removed.
+pub const ENTRIES_KEY: &str = "__entries";
+
+/// Entry hosts call to ask a plugin whether it is healthy. A plugin that
+/// serves it answers `Ok` while healthy and any other status when it is
+/// not; the payload is ignored.
+pub const HEALTH_ENTRY: &str = "__health";
+
+/// State key under [`INIT_SID`] declaring that the plugin echoes call
+/// nonces. The value is ignored.
+///
+/// The host then stores a random nonce under [`CALL_NONCE_KEY`] for every
+/// unary call before `handle`, and expects the call's result to start with
+/// it (see [`NrHostExt::echo_nonce`]). A result that does not is dropped,
+/// so one sent under the wrong SID never reaches the caller of that SID.
+pub const ECHOES_NONCE_KEY: &str = "__echoes_nonce";
+
+/// State key under a unary call's SID holding the call's 8-byte nonce, for
+/// plugins that declared [`ECHOES_NONCE_KEY`].
+pub const CALL_NONCE_KEY: &str = "__call_nonce";
+
+/// Publish `entries` under [`ENTRIES_KEY`]. Called by `define_plugin!`.
+///
+/// # Safety
+///
+/// `host_ctx` and `host_vtable` must be the pointers the host passed to
+/// `init`.
+#[doc(hidden)]
+pub unsafe fn publish_entries<I>(
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    entries: I,
+) where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut list = String::new();
+    for entry in entries {
+        if !list.is_empty() {
+            list.push('\n');
+        }
+        list.push_str(entry.as_ref());
+    }
+    unsafe {
+        let ext = ((*host_vtable).get_host_ext)(host_ctx);
+        if ext.is_null() {
+            return;
+        }
+        ((*ext).set_state)(
+            host_ctx,
+            INIT_SID,
+            NrStr::new(ENTRIES_KEY),
+            NrBytes::from_slice(list.as_bytes()),
+        );
+    }
+}
+
 /// Plugin function table.
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -190,8 +674,105 @@ pub struct NrPluginVTable {
     pub stream_data: Option<unsafe extern "C" fn(sid: u64, data: NrBytes) -> NrStatus>,
 
     pub stream_close: Option<unsafe extern "C" fn(sid: u64) -> NrStatus>,
+
+    /// Whether the plugin can serve calls: `Ok` if so, anything else if
+    /// not. Hosts may call it periodically, from any thread, so it should
+    /// be quick. Read only from plugins of ABI version 6 or later.
+    pub health: Option<unsafe extern "C" fn() -> NrStatus>,
+
+    /// Like `handle`, for a call that comes with an [`NrRequest`]; the
+    /// payload is the request's body. Read only from plugins of ABI version
+    /// 8 or later.
+    pub handle_req: Option<
+        unsafe extern "C" fn(
+            entry: NrStr,
+            sid: u64,
+            req: *const NrRequest,
+            payload: NrBytes,
+        ) -> NrStatus,
+    >,
 }
 
+/// Signature `define_plugin!` expects for `init`. Safe functions coerce.
+pub type PluginInitFn = unsafe fn(*mut c_void, *const NrHostVTable) -> NrStatus;
+
+/// Signature `define_plugin!` expects for `shutdown`.
+pub type PluginShutdownFn = unsafe fn();
+
+/// Signature `define_plugin!` expects for each `entries` handler.
+pub type PluginEntryFn = unsafe fn(u64, NrBytes) -> NrStatus;
+
+/// Signature of handlers registered with [`PluginBuilder::raw_entry`].
+pub type PluginRawEntryFn = unsafe fn(&str, u64, NrBytes) -> NrStatus;
+
+/// Signature `define_plugin!` expects for `stream_handlers.data`.
+pub type PluginStreamDataFn = unsafe fn(u64, NrBytes) -> NrStatus;
+
+/// Signature `define_plugin!` expects for `stream_handlers.close`.
+pub type PluginStreamCloseFn = unsafe fn(u64) -> NrStatus;
+
+/// Signature `define_plugin!` expects for `req_entries`.
+pub type PluginReqEntryFn = unsafe fn(u64, &NrRequest, NrBytes) -> NrStatus;
+
+/// Signature `define_plugin!` expects for `health`.
+pub type PluginHealthFn = unsafe fn() -> NrStatus;
+
+/// Export a plugin: its `init`, `shutdown`, entry handlers and, optionally,
+/// stream handlers and a `health` check.
+///
+/// Handlers that answer through the host need the `host_ctx` and
+/// `host_vtable` passed to `init`. Keep them in atomics (or a `OnceLock`),
+/// not in `static mut`: handlers run on any thread, and reading a
+/// `static mut` while `init` may write it is undefined behavior.
+///
+/// ```
+/// use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrVec};
+/// use std::ffi::c_void;
+/// use std::sync::atomic::{AtomicPtr, Ordering};
+///
+/// static HOST_CTX: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+/// static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
+///
+/// unsafe fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+///     HOST_CTX.store(host_ctx, Ordering::Release);
+///     HOST_VTABLE.store(host_vtable.cast_mut(), Ordering::Release);
+///     NrStatus::Ok
+/// }
+///
+/// fn shutdown() {}
+///
+/// fn send_result(sid: u64, status: NrStatus, data: NrVec<u8>) {
+///     // Only called from handlers, which the host runs after `init`.
+///     let vtable = unsafe { &*HOST_VTABLE.load(Ordering::Acquire) };
+///     unsafe { (vtable.send_result)(HOST_CTX.load(Ordering::Acquire), sid, status, data) };
+/// }
+///
+/// unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
+///     send_result(sid, NrStatus::Ok, NrVec::from_nr_bytes(payload));
+///     NrStatus::Ok
+/// }
+///
+/// define_plugin! {
+///     init: init,
+///     shutdown: shutdown,
+///     entries: {
+///         "echo" => handle_echo,
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Handlers are checked against [`PluginEntryFn`] and friends where they are
+/// named, so a mismatched signature fails to compile at its path. With
+/// `entries: runtime`, entries come from a [`PluginBuilder`] instead.
+///
+/// `req_entries: { "name" => handler, ... }` right after `entries` serves
+/// calls that come with an [`NrRequest`], through
+/// [`NrPluginVTable::handle_req`]; handlers are [`PluginReqEntryFn`]s.
+///
+/// `health: check` after the entries (and stream handlers, if any) fills
+/// [`NrPluginVTable::health`]; a panic in `check` reports `Err`.
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! define_plugin {
     (
@@ -200,10 +781,87 @@ macro_rules! define_plugin {
         entries: {
             $($entry_name:literal => $handler_fn:path),* $(,)?
         }
+        $(, req_entries: {
+            $($req_name:literal => $req_handler_fn:path),* $(,)?
+        })?
+        $(, stream_handlers: {
+            data: $stream_data_fn:path,
+            close: $stream_close_fn:path $(,)?
+        })?
+        $(, health: $health_fn:path)?
+        $(,)?
+    ) => {
+        $crate::define_plugin! {
+            @plugin
+            init: $init_fn,
+            shutdown: $shutdown_fn,
+            entry_list: {
+                let list: &[&str] = &[$($entry_name,)* $($($req_name,)*)?];
+                list
+            },
+            dispatch: |entry, sid, payload| match entry {
+                $(
+                    $entry_name => {
+                        const HANDLER: $crate::PluginEntryFn = $handler_fn;
+                        unsafe { HANDLER(sid, payload) }
+                    }
+                )*
+                _ => $crate::NrStatus::Invalid,
+            }
+            $(, req_dispatch: |entry, sid, req, payload| match entry {
+                $(
+                    $req_name => {
+                        const HANDLER: $crate::PluginReqEntryFn = $req_handler_fn;
+                        unsafe { HANDLER(sid, req, payload) }
+                    }
+                )*
+                _ => $crate::NrStatus::Invalid,
+            })?
+            $(, stream_handlers: {
+                data: $stream_data_fn,
+                close: $stream_close_fn,
+            })?
+            $(, health: $health_fn)?
+        }
+    };
+    (
+        init: $init_fn:path,
+        shutdown: $shutdown_fn:path,
+        entries: runtime
         $(, stream_handlers: {
             data: $stream_data_fn:path,
             close: $stream_close_fn:path $(,)?
         })?
+        $(, health: $health_fn:path)?
+        $(,)?
+    ) => {
+        $crate::define_plugin! {
+            @plugin
+            init: $init_fn,
+            shutdown: $shutdown_fn,
+            entry_list: $crate::builder::entry_names(),
+            dispatch: |entry, sid, payload| unsafe {
+                $crate::builder::dispatch(entry, sid, payload)
+            }
+            $(, stream_handlers: {
+                data: $stream_data_fn,
+                close: $stream_close_fn,
+            })?
+            $(, health: $health_fn)?
+        }
+    };
+    (
+        @plugin
+        init: $init_fn:path,
+        shutdown: $shutdown_fn:path,
+        entry_list: $entry_list:expr,
+        dispatch: |$entry:ident, $sid:ident, $payload:ident| $dispatch:expr
+        $(, req_dispatch: |$req_entry:ident, $req_sid:ident, $req:ident, $req_payload:ident| $req_dispatch:expr)?
+        $(, stream_handlers: {
+            data: $stream_data_fn:path,
+            close: $stream_close_fn:path,
+        })?
+        $(, health: $health_fn:path)?
     ) => {
         // Static VTable
         static PLUGIN_VTABLE: $crate::NrPluginVTable = $crate::NrPluginVTable {
@@ -212,11 +870,47 @@ macro_rules! define_plugin {
             shutdown: Some(plugin_shutdown_wrapper),
             stream_data: Some(plugin_stream_data_wrapper),
             stream_close: Some(plugin_stream_close_wrapper),
+            health: $crate::define_plugin!(@health $($health_fn)?),
+            handle_req: $crate::define_plugin!(@handle_req $($req_dispatch)?),
         };
 
+        $(
+            unsafe extern "C" fn plugin_handle_req_wrapper(
+                entry: $crate::NrStr,
+                sid: u64,
+                req: *const $crate::NrRequest,
+                payload: $crate::NrBytes,
+            ) -> $crate::NrStatus {
+                let entry_str = match entry.try_as_str() {
+                    Ok(s) if !req.is_null() => s,
+                    _ => return $crate::NrStatus::Invalid,
+                };
+                let req = unsafe { &*req };
+                let result = std::panic::catch_unwind(|| {
+                    let ($req_entry, $req_sid, $req, $req_payload) = (entry_str, sid, req, payload);
+                    $req_dispatch
+                });
+                match result {
+                    Ok(status) => status,
+                    Err(panic) => {
+                        plugin_report_panic(sid, entry_str, &*panic);
+                        $crate::NrStatus::Err
+                    }
+                }
+            }
+        )?
+
+        $(
+            unsafe extern "C" fn plugin_health_wrapper() -> $crate::NrStatus {
+                const HEALTH: $crate::PluginHealthFn = $health_fn;
+                std::panic::catch_unwind(|| unsafe { HEALTH() })
+                    .unwrap_or($crate::NrStatus::Err)
+            }
+        )?
+
         // Static Plugin Info
         static PLUGIN_INFO: $crate::NrPluginInfo = $crate::NrPluginInfo {
-            abi_version: 1,
+            abi_version: $crate::NR_ABI_VERSION,
             struct_size: std::mem::size_of::<$crate::NrPluginInfo>() as u32,
             name: $crate::NrStr {
                 ptr: env!("CARGO_PKG_NAME").as_ptr(),
@@ -230,22 +924,62 @@ macro_rules! define_plugin {
             vtable: &PLUGIN_VTABLE,
         };
 
-        // Exported Entry Point
+        /// Entry point the host loads this plugin through.
         #[unsafe(no_mangle)]
         pub extern "C" fn nylon_ring_get_plugin_v1() -> *const $crate::NrPluginInfo {
             &PLUGIN_INFO
         }
 
+        // Host pointers captured in init, used to report panics.
+        static PLUGIN_HOST_CTX: std::sync::atomic::AtomicPtr<std::ffi::c_void> =
+            std::sync::atomic::AtomicPtr::new(std::ptr::null_mut());
+        static PLUGIN_HOST_VTABLE: std::sync::atomic::AtomicPtr<$crate::NrHostVTable> =
+            std::sync::atomic::AtomicPtr::new(std::ptr::null_mut());
+
+        fn plugin_report_panic(sid: u64, entry: &str, payload: &(dyn std::any::Any + Send)) {
+            unsafe {
+                $crate::panic_report::report(
+                    PLUGIN_HOST_CTX.load(std::sync::atomic::Ordering::Acquire),
+                    PLUGIN_HOST_VTABLE.load(std::sync::atomic::Ordering::Acquire),
+                    sid,
+                    entry,
+                    payload,
+                );
+            }
+        }
+
         // Wrappers
         unsafe extern "C" fn plugin_init_wrapper(
             host_ctx: *mut std::ffi::c_void,
             host_vtable: *const $crate::NrHostVTable,
         ) -> $crate::NrStatus {
-            $init_fn(host_ctx, host_vtable)
+            PLUGIN_HOST_CTX.store(host_ctx, std::sync::atomic::Ordering::Release);
+            PLUGIN_HOST_VTABLE.store(
+                host_vtable as *mut $crate::NrHostVTable,
+                std::sync::atomic::Ordering::Release,
+            );
+            $crate::panic_report::install_hook();
+            $crate::logging::install(host_ctx, host_vtable);
+
+            // Typed consts make a mismatched handler fail right at its path.
+            const INIT: $crate::PluginInitFn = $init_fn;
+            let status = std::panic::catch_unwind(|| unsafe { INIT(host_ctx, host_vtable) })
+                .unwrap_or_else(|panic| {
+                    unsafe { $crate::panic_report::report_init(host_ctx, host_vtable, &*panic) };
+                    $crate::NrStatus::Err
+                });
+            if status == $crate::NrStatus::Ok {
+                let entries = $entry_list;
+                unsafe { $crate::publish_entries(host_ctx, host_vtable, entries) };
+            }
+            status
         }
 
         unsafe extern "C" fn plugin_shutdown_wrapper() {
-            $shutdown_fn();
+            const SHUTDOWN: $crate::PluginShutdownFn = $shutdown_fn;
+            if let Err(panic) = std::panic::catch_unwind(|| unsafe { SHUTDOWN() }) {
+                $crate::panic_report::report_shutdown(&*panic);
+            }
         }
 
         unsafe extern "C" fn plugin_handle_wrapper(
@@ -253,38 +987,68 @@ macro_rules! define_plugin {
             sid: u64,
             payload: $crate::NrBytes,
         ) -> $crate::NrStatus {
-            let entry_str = entry.as_str();
-            match entry_str {
-                $(
-                    $entry_name => {
-                        $handler_fn(sid, payload)
-                    }
-                )*
-                _ => $crate::NrStatus::Invalid,
+            let entry_str = match entry.try_as_str() {
+                Ok(s) => s,
+                Err(_) => return $crate::NrStatus::Invalid,
+            };
+            let result = std::panic::catch_unwind(|| {
+                let ($entry, $sid, $payload) = (entry_str, sid, payload);
+                $dispatch
+            });
+            match result {
+                Ok(status) => status,
+                Err(panic) => {
+                    plugin_report_panic(sid, entry_str, &*panic);
+                    $crate::NrStatus::Err
+                }
             }
         }
 
+        #[allow(unused_variables)]
         unsafe extern "C" fn plugin_stream_data_wrapper(
             sid: u64,
             data: $crate::NrBytes,
         ) -> $crate::NrStatus {
             $(
-                return $stream_data_fn(sid, data);
+                const STREAM_DATA: $crate::PluginStreamDataFn = $stream_data_fn;
+                return std::panic::catch_unwind(|| unsafe { STREAM_DATA(sid, data) })
+                .unwrap_or_else(|panic| {
+                    plugin_report_panic(sid, "<stream_data>", &*panic);
+                    $crate::NrStatus::Err
+                });
             )?
             #[allow(unreachable_code)]
             $crate::NrStatus::Unsupported
         }
 
+        #[allow(unused_variables)]
         unsafe extern "C" fn plugin_stream_close_wrapper(
             sid: u64,
         ) -> $crate::NrStatus {
             $(
-                return $stream_close_fn(sid);
+                const STREAM_CLOSE: $crate::PluginStreamCloseFn = $stream_close_fn;
+                return std::panic::catch_unwind(|| unsafe { STREAM_CLOSE(sid) })
+                .unwrap_or_else(|panic| {
+                    plugin_report_panic(sid, "<stream_close>", &*panic);
+                    $crate::NrStatus::Err
+                });
             )?
             #[allow(unreachable_code)]
             $crate::NrStatus::Unsupported
         }
     };
+    (@health) => {
+        None
+    };
+    (@health $health_fn:path) => {
+        Some(plugin_health_wrapper)
+    };
+    (@handle_req) => {
+        None
+    };
+    (@handle_req $req_dispatch:expr) => {
+        Some(plugin_handle_req_wrapper)
+    };
 }
 
 /// Metadata exported by the plugin.
@@ -309,56 +1073,44 @@ impl NrStr {
         }
     }
 
+    /// View the string without validating it.
+    ///
+    /// Only use this for strings produced by Rust code. Bytes that come from
+    /// a foreign plugin should go through [`NrStr::try_as_str`] or
+    /// [`NrStr::as_str_lossy`] instead, since invalid UTF-8 here is UB.
     pub fn as_str(&self) -> &str {
-        unsafe {
-            let slice = std::slice::from_raw_parts(self.ptr, self.len as usize);
-            std::str::from_utf8_unchecked(slice)
-        }
+        unsafe { core::str::from_utf8_unchecked(self.as_bytes()) }
     }
 
-    // push_str
-    pub fn push_str(&mut self, s: &str) {
+    /// View the raw bytes of the string.
+    pub fn as_bytes(&self) -> &[u8] {
         if self.ptr.is_null() {
-            let v = s.as_bytes().to_vec();
-            // Leak memory to keep it alive (ABI)
-            let mut v = std::mem::ManuallyDrop::new(v);
-            self.ptr = v.as_mut_ptr();
-            self.len = v.len() as u32;
-            return;
+            return &[];
         }
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len as usize) }
+    }
 
-        // We cannot just write to self.ptr because we don't know the capacity!
-        // It might be a string literal (read-only) or a short buffer.
-        // SAFE WAY: Always re-allocate.
-
-        let current_slice = unsafe { std::slice::from_raw_parts(self.ptr, self.len as usize) };
-        let mut new_vec = Vec::with_capacity(self.len as usize + s.len());
-        new_vec.extend_from_slice(current_slice);
-        new_vec.extend_from_slice(s.as_bytes());
-
-        // We must assume ownership of the old pointer?
-        // NO. In ABI, string ownership is ambiguous.
-        // But for `push_str`, we are mutating, so we become the owner of the new buffer.
-
-        let mut v = std::mem::ManuallyDrop::new(new_vec);
-        self.ptr = v.as_mut_ptr();
-        self.len = v.len() as u32;
+    /// View the string, validating that it is UTF-8.
+    pub fn try_as_str(&self) -> Result<&str, core::str::Utf8Error> {
+        core::str::from_utf8(self.as_bytes())
     }
 
-    pub fn clear(&mut self) {
-        self.ptr = std::ptr::null();
-        self.len = 0;
+    /// View the string, replacing invalid UTF-8 sequences with `U+FFFD`.
+    pub fn as_str_lossy(&self) -> alloc::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
     }
 }
 
+// Deep copy: `Copy` duplicates the pointer, `Clone` duplicates the buffer.
+#[allow(clippy::non_canonical_clone_impl)]
 impl Clone for NrStr {
     fn clone(&self) -> Self {
         if self.ptr.is_null() {
             return Self::default();
         }
-        let slice = unsafe { std::slice::from_raw_parts(self.ptr, self.len as usize) };
+        let slice = unsafe { core::slice::from_raw_parts(self.ptr, self.len as usize) };
         let v = slice.to_vec();
-        let mut v = std::mem::ManuallyDrop::new(v);
+        let mut v = core::mem::ManuallyDrop::new(v);
         Self {
             ptr: v.as_mut_ptr(),
             len: v.len() as u32,
@@ -366,14 +1118,15 @@ impl Clone for NrStr {
     }
 }
 
+#[allow(clippy::non_canonical_clone_impl)]
 impl Clone for NrBytes {
     fn clone(&self) -> Self {
         if self.ptr.is_null() {
             return Self::default();
         }
-        let slice = unsafe { std::slice::from_raw_parts(self.ptr, self.len as usize) };
+        let slice = unsafe { core::slice::from_raw_parts(self.ptr, self.len as usize) };
         let v = slice.to_vec();
-        let mut v = std::mem::ManuallyDrop::new(v);
+        let mut v = core::mem::ManuallyDrop::new(v);
         Self {
             ptr: v.as_mut_ptr(),
             len: v.len() as u64,
@@ -381,6 +1134,7 @@ impl Clone for NrBytes {
     }
 }
 
+#[allow(clippy::non_canonical_clone_impl, clippy::clone_on_copy)]
 impl Clone for NrKV {
     fn clone(&self) -> Self {
         Self {
@@ -416,6 +1170,15 @@ impl Clone for NrAny {
         if self.data.is_null() {
             return Self::default();
         }
+        if self.drop_fn.is_none() {
+            // Borrowed, not owned: the copy borrows the same data.
+            return Self {
+                data: self.data,
+                size: self.size,
+                type_tag: self.type_tag,
+                drop_fn: None,
+            };
+        }
 
         // We can only deep copy if we know how to copy the underlying type.
         // But NrAny is type-erased.
@@ -428,10 +1191,10 @@ impl Clone for NrAny {
         // we must panic or return empty for unknown types to be safe?
         // OR we just perform new allocation and memcpy (Shallow copy of content, deep copy of container).
 
-        let layout = std::alloc::Layout::from_size_align(self.size as usize, 1).unwrap();
+        let layout = alloc::alloc::Layout::from_size_align(self.size as usize, 1).unwrap();
         unsafe {
-            let new_data = std::alloc::alloc(layout) as *mut c_void;
-            std::ptr::copy_nonoverlapping(self.data, new_data, self.size as usize);
+            let new_data = alloc::alloc::alloc(layout) as *mut c_void;
+            core::ptr::copy_nonoverlapping(self.data, new_data, self.size as usize);
             Self {
                 data: new_data,
                 size: self.size,
@@ -447,13 +1210,22 @@ impl<T: Clone> Clone for NrVec<T> {
         if self.ptr.is_null() {
             return Self::default();
         }
-        let slice = unsafe { std::slice::from_raw_parts(self.ptr, self.len) };
+        let slice = unsafe { core::slice::from_raw_parts(self.ptr, self.len) };
         let v = slice.to_vec(); // Deep copy elements via T::clone()
         Self::from_vec(v)
     }
 }
 
 impl NrBytes {
+    /// An empty slice with a dangling, aligned pointer, for callbacks that
+    /// have nothing to return.
+    pub const fn empty() -> Self {
+        Self {
+            ptr: core::ptr::NonNull::<u8>::dangling().as_ptr(),
+            len: 0,
+        }
+    }
+
     pub fn from_slice(s: &[u8]) -> Self {
         Self {
             ptr: s.as_ptr(),
@@ -465,7 +1237,7 @@ impl NrBytes {
         if self.ptr.is_null() {
             return &[];
         }
-        unsafe { std::slice::from_raw_parts(self.ptr, self.len as usize) }
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len as usize) }
     }
 }
 
@@ -483,15 +1255,17 @@ impl NrKV {
 }
 
 impl NrKVAny {
+    /// A pair holding a copy of `key`.
     pub fn new(key: &str, value: NrAny) -> Self {
         Self {
-            key: NrStr::new(key),
+            key: NrString::from(key),
             value,
         }
     }
 
+    /// A pair holding a copy of the string `key` points to.
     pub fn from_nr_str(key: NrStr, value: NrAny) -> Self {
-        Self { key, value }
+        Self::new(key.as_str(), value)
     }
 }
 
@@ -513,16 +1287,52 @@ impl NrMap {
         Self::default()
     }
 
+    /// An empty map with room for `capacity` entries, indexed from the
+    /// first insert. The index is sized so that `capacity` inserts neither
+    /// search linearly nor rehash.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut map = Self::new();
+        if capacity > 0 {
+            map.entries.reserve(capacity);
+            // Stay below the 0.7 load factor `should_grow` checks.
+            map.rehash((capacity * 10).div_ceil(7));
+        }
+        map
+    }
+
+    /// How many entries the map holds without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// The number of slots in the hash index, 0 until the map has one.
+    pub fn index_capacity(&self) -> usize {
+        self.index_len()
+    }
+
     #[inline]
     fn index_len(&self) -> usize {
         self.index.len
     }
 
-    fn ensure_index(&mut self) {
-        // Create index when we have enough entries (threshold = 8)
-        if self.index.ptr.is_null() && self.entries.len >= 8 {
-            self.rehash(16);
+    /// Index the entry just pushed at `entry_idx`.
+    ///
+    /// Creating or growing the index rehashes every entry, the new one
+    /// included, so it is only inserted by hand when neither happens.
+    fn index_pushed(&mut self, hash: impl FnOnce() -> u64, entry_idx: u32) {
+        if self.index.ptr.is_null() {
+            // Create index when we have enough entries (threshold = 8)
+            if self.entries.len >= 8 {
+                self.rehash(16);
+            }
+            return;
         }
+        if self.should_grow() {
+            let cap = self.index_len();
+            self.rehash(cap * 2);
+            return;
+        }
+        self.index_insert(hash(), entry_idx);
     }
 
     fn rehash(&mut self, mut new_cap: usize) {
@@ -555,13 +1365,6 @@ impl NrMap {
         (self.used + self.tomb) * 10 >= cap * 7
     }
 
-    fn maybe_grow(&mut self) {
-        if self.should_grow() {
-            let cap = self.index_len();
-            self.rehash(cap * 2);
-        }
-    }
-
     fn index_insert(&mut self, hash: u64, entry_idx: u32) {
         let cap = self.index_len();
         if cap == 0 {
@@ -586,10 +1389,8 @@ impl NrMap {
                     self.used += 1;
                     return;
                 }
-                2 => {
-                    if first_tomb.is_none() {
-                        first_tomb = Some(pos);
-                    }
+                2 if first_tomb.is_none() => {
+                    first_tomb = Some(pos);
                 }
                 _ => {}
             }
@@ -602,6 +1403,8 @@ impl NrMap {
         self.index_insert(hash, entry_idx);
     }
 
+    /// Insert `value` under a copy of `key`. A value already there is
+    /// dropped and replaced; its key is kept.
     pub fn insert(&mut self, key: &str, value: NrAny) {
         // If key exists, replace the value (set behavior)
         if let Some(v) = self.get_mut(key) {
@@ -611,15 +1414,10 @@ impl NrMap {
 
         let kv = NrKVAny::new(key, value);
         self.entries.push(kv);
-
-        self.ensure_index();
-        if !self.index.ptr.is_null() {
-            self.maybe_grow();
-            let idx = (self.entries.len - 1) as u32;
-            self.index_insert(hash_str(key), idx);
-        }
+        self.index_pushed(|| hash_str(key), (self.entries.len - 1) as u32);
     }
 
+    /// Like [`insert`](Self::insert), with the key as an `NrStr`.
     pub fn insert_nr(&mut self, key: NrStr, value: NrAny) {
         let key_str = key.as_str();
         // If key exists, replace the value (set behavior)
@@ -630,13 +1428,7 @@ impl NrMap {
 
         let kv = NrKVAny::from_nr_str(key, value);
         self.entries.push(kv);
-
-        self.ensure_index();
-        if !self.index.ptr.is_null() {
-            self.maybe_grow();
-            let idx = (self.entries.len - 1) as u32;
-            self.index_insert(hash_str(key_str), idx);
-        }
+        self.index_pushed(|| hash_str(key_str), (self.entries.len - 1) as u32);
     }
 
     pub fn get(&self, key: &str) -> Option<&NrAny> {
@@ -704,49 +1496,88 @@ impl NrMap {
         None
     }
 
-    pub fn remove(&mut self, key: &str) -> Option<NrKVAny> {
-        // Find the index of the entry to remove
+    /// Get the value for `key`, inserting `f()` first if it is absent.
+    ///
+    /// The key is hashed and probed once. On a miss the entry is appended and
+    /// the index may grow; the returned reference is taken after that, and a
+    /// rehash only rebuilds `index` (it never moves `entries`), so it is
+    /// always valid.
+    pub fn get_or_insert_with<F: FnOnce() -> NrAny>(&mut self, key: &str, f: F) -> &mut NrAny {
         let idx = if self.index.ptr.is_null() {
-            // Fallback to linear search
-            self.entries.iter().position(|kv| kv.key.as_str() == key)?
+            match self.entries.iter().position(|kv| kv.key.as_str() == key) {
+                Some(idx) => idx,
+                None => self.push_entry(key, None, f()),
+            }
         } else {
-            // Use hash lookup
             let h = hash_str(key);
-            let cap = self.index.len;
-            let mask = cap - 1;
-            let mut pos = (h as usize) & mask;
-            let mut found_idx: Option<usize> = None;
+            match self.probe(key, h) {
+                Some(idx) => idx,
+                None => self.push_entry(key, Some(h), f()),
+            }
+        };
+        unsafe { &mut (*self.entries.ptr.add(idx)).value }
+    }
 
-            for _ in 0..cap {
-                let slot = unsafe { &*self.index.ptr.add(pos) };
-                match slot.state {
-                    0 => break, // Empty slot found, key doesn't exist
-                    1 if slot.hash == h => {
-                        let entry_idx = slot.entry_idx as usize;
-                        let kv = unsafe { &*self.entries.ptr.add(entry_idx) };
-                        if kv.key.as_str() == key {
-                            found_idx = Some(entry_idx);
-                            break;
-                        }
+    /// Find the entry index for `key` through the hash index.
+    #[inline]
+    fn probe(&self, key: &str, h: u64) -> Option<usize> {
+        let cap = self.index.len;
+        let mask = cap - 1;
+        let mut pos = (h as usize) & mask;
+
+        for _ in 0..cap {
+            let slot = unsafe { &*self.index.ptr.add(pos) };
+            match slot.state {
+                0 => return None,
+                1 if slot.hash == h => {
+                    let idx = slot.entry_idx as usize;
+                    let kv = unsafe { &*self.entries.ptr.add(idx) };
+                    if kv.key.as_str() == key {
+                        return Some(idx);
                     }
-                    _ => {}
                 }
-                pos = (pos + 1) & mask;
+                _ => {}
             }
+            pos = (pos + 1) & mask;
+        }
+        None
+    }
 
-            found_idx?
-        };
+    /// Append a new entry and index it, reusing `hash` if already computed.
+    fn push_entry(&mut self, key: &str, hash: Option<u64>, value: NrAny) -> usize {
+        self.entries.push(NrKVAny::new(key, value));
+        let idx = self.entries.len - 1;
+        self.index_pushed(|| hash.unwrap_or_else(|| hash_str(key)), idx as u32);
+        idx
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<NrKVAny> {
+        let idx = self.find(key)?;
+        Some(self.remove_at(idx))
+    }
 
+    /// The position of `key` in `entries`.
+    fn find(&self, key: &str) -> Option<usize> {
+        if self.index.ptr.is_null() {
+            // Fallback to linear search
+            self.entries.iter().position(|kv| kv.key.as_str() == key)
+        } else {
+            self.probe(key, hash_str(key))
+        }
+    }
+
+    /// Remove the entry at `idx`, moving the last entry into its place.
+    fn remove_at(&mut self, idx: usize) -> NrKVAny {
         let last = self.entries.len - 1;
 
         // take removed
-        let removed = unsafe { std::ptr::read(self.entries.ptr.add(idx)) };
+        let removed = unsafe { core::ptr::read(self.entries.ptr.add(idx)) };
 
         if idx != last {
             // Move last into idx (swap_remove)
             unsafe {
-                let last_val = std::ptr::read(self.entries.ptr.add(last));
-                std::ptr::write(self.entries.ptr.add(idx), last_val);
+                let last_val = core::ptr::read(self.entries.ptr.add(last));
+                core::ptr::write(self.entries.ptr.add(idx), last_val);
             }
 
             // Update index for the moved entry (last -> idx)
@@ -774,7 +1605,7 @@ impl NrMap {
 
         // Remove slot from index (mark as tombstone or rehash)
         if !self.index.ptr.is_null() {
-            let h = hash_str(key);
+            let h = hash_str(removed.key.as_str());
             let cap = self.index.len;
             let mask = cap - 1;
             let mut pos = (h as usize) & mask;
@@ -803,7 +1634,7 @@ impl NrMap {
             }
         }
 
-        Some(removed)
+        removed
     }
 
     pub fn len(&self) -> usize {
@@ -816,17 +1647,231 @@ impl NrMap {
 
     pub fn clear(&mut self) {
         self.entries.clear();
-        if !self.index.ptr.is_null() {
-            self.index.clear();
-        }
+        // Drop the index entirely; an allocated but empty index would make
+        // lookups mask with `0 - 1`.
+        self.index = NrVec::default();
         self.used = 0;
         self.tomb = 0;
     }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over the entries in storage order.
+    ///
+    /// Entries are kept in insertion order, except that `remove` moves the
+    /// last entry into the removed slot.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NrAny)> {
+        self.entries.iter().map(|kv| (kv.key.as_str(), &kv.value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut NrAny)> {
+        self.entries
+            .iter_mut()
+            .map(|kv| (kv.key.as_str(), &mut kv.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &NrAny> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// The entry for `key`, occupied or vacant, found with a single probe.
+    /// Modeled after `std::collections::hash_map::Entry`.
+    ///
+    /// A vacant entry remembers the key's hash, so inserting through it
+    /// does not probe again. The key is copied when it is inserted.
+    pub fn entry<'a>(&'a mut self, key: &'a str) -> NrMapEntry<'a> {
+        let (idx, hash) = if self.index.ptr.is_null() {
+            (self.find(key), None)
+        } else {
+            let h = hash_str(key);
+            (self.probe(key, h), Some(h))
+        };
+        match idx {
+            Some(idx) => NrMapEntry::Occupied(NrOccupiedEntry { map: self, idx }),
+            None => NrMapEntry::Vacant(NrVacantEntry {
+                map: self,
+                key,
+                hash,
+            }),
+        }
+    }
+}
+
+/// A view into a single `NrMap` key. See [`NrMap::entry`].
+pub enum NrMapEntry<'a> {
+    Occupied(NrOccupiedEntry<'a>),
+    Vacant(NrVacantEntry<'a>),
+}
+
+impl<'a> NrMapEntry<'a> {
+    pub fn key(&self) -> &str {
+        match self {
+            NrMapEntry::Occupied(entry) => entry.key(),
+            NrMapEntry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, value: NrAny) -> &'a mut NrAny {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> NrAny>(self, f: F) -> &'a mut NrAny {
+        match self {
+            NrMapEntry::Occupied(entry) => entry.into_mut(),
+            NrMapEntry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Run `f` on the value if the key is present.
+    pub fn and_modify<F: FnOnce(&mut NrAny)>(mut self, f: F) -> Self {
+        if let NrMapEntry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// A key present in an `NrMap`. See [`NrMap::entry`].
+pub struct NrOccupiedEntry<'a> {
+    map: &'a mut NrMap,
+    /// Position of the entry in `map.entries`.
+    idx: usize,
+}
+
+impl<'a> NrOccupiedEntry<'a> {
+    fn kv(&self) -> &NrKVAny {
+        unsafe { &*self.map.entries.ptr.add(self.idx) }
+    }
+
+    fn kv_mut(&mut self) -> &mut NrKVAny {
+        unsafe { &mut *self.map.entries.ptr.add(self.idx) }
+    }
+
+    pub fn key(&self) -> &str {
+        self.kv().key.as_str()
+    }
+
+    pub fn get(&self) -> &NrAny {
+        &self.kv().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut NrAny {
+        &mut self.kv_mut().value
+    }
+
+    /// The value, borrowed for as long as the map was.
+    pub fn into_mut(self) -> &'a mut NrAny {
+        unsafe { &mut (*self.map.entries.ptr.add(self.idx)).value }
+    }
+
+    /// Replace the value, returning the old one. The key is kept.
+    pub fn insert(&mut self, value: NrAny) -> NrAny {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove the entry, returning its value. The last entry moves into
+    /// its place, as with [`NrMap::remove`].
+    pub fn remove(self) -> NrAny {
+        self.remove_entry().value
+    }
+
+    pub fn remove_entry(self) -> NrKVAny {
+        self.map.remove_at(self.idx)
+    }
+}
+
+/// A key absent from an `NrMap`. See [`NrMap::entry`].
+pub struct NrVacantEntry<'a> {
+    map: &'a mut NrMap,
+    key: &'a str,
+    /// The key's hash, if the map was indexed when it was probed.
+    hash: Option<u64>,
+}
+
+impl<'a> NrVacantEntry<'a> {
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    /// Insert `value` under the key. The push may move `entries` and grow
+    /// the index; the returned reference is taken after both, so it is
+    /// valid.
+    pub fn insert(self, value: NrAny) -> &'a mut NrAny {
+        let idx = self.map.push_entry(self.key, self.hash, value);
+        unsafe { &mut (*self.map.entries.ptr.add(idx)).value }
+    }
+}
+
+/// Error converting an `NrMap` into a byte map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotBytesError {
+    /// The first key whose value is not a byte blob.
+    pub key: String,
+}
+
+impl core::fmt::Display for NotBytesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "value for key {:?} is not a byte blob", self.key)
+    }
+}
+
+impl core::error::Error for NotBytesError {}
+
+/// Builds a map of byte blobs (tagged [`NR_ANY_BYTES_TAG`]).
+///
+/// Keys and values are copied.
+#[cfg(feature = "std")]
+impl From<&std::collections::HashMap<String, Vec<u8>>> for NrMap {
+    fn from(map: &std::collections::HashMap<String, Vec<u8>>) -> Self {
+        let mut out = NrMap::with_capacity(map.len());
+        for (key, value) in map {
+            out.insert(
+                key,
+                NrAny::from_bytes(NrBytes::from_slice(value), NR_ANY_BYTES_TAG),
+            );
+        }
+        out
+    }
+}
+
+/// Copies a map of byte blobs out. Fails if any value is not tagged
+/// [`NR_ANY_BYTES_TAG`].
+#[cfg(feature = "std")]
+impl TryFrom<&NrMap> for std::collections::HashMap<String, Vec<u8>> {
+    type Error = NotBytesError;
+
+    fn try_from(map: &NrMap) -> Result<Self, Self::Error> {
+        map.iter()
+            .map(|(key, value)| match value.as_bytes() {
+                Some(bytes) => Ok((key.to_string(), bytes.to_vec())),
+                None => Err(NotBytesError {
+                    key: key.to_string(),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a NrMap {
+    type Item = (&'a str, &'a NrAny);
+    type IntoIter =
+        core::iter::Map<core::slice::Iter<'a, NrKVAny>, fn(&'a NrKVAny) -> (&'a str, &'a NrAny)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|kv| (kv.key.as_str(), &kv.value))
+    }
 }
 
 impl NrAny {
+    #[cfg(feature = "std")]
     pub fn new<T>(value: T, type_tag: u32) -> Self {
-        let size = std::mem::size_of::<T>() as u64;
+        let size = core::mem::size_of::<T>() as u64;
         let data = Box::into_raw(Box::new(value)) as *mut c_void;
         Self {
             data,
@@ -836,13 +1881,14 @@ impl NrAny {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn from_bytes(bytes: NrBytes, type_tag: u32) -> Self {
         let size = bytes.len;
         let data = if size > 0 {
             let v = bytes.as_slice().to_vec();
             Box::into_raw(Box::new(v)) as *mut c_void
         } else {
-            std::ptr::null_mut()
+            core::ptr::null_mut()
         };
         Self {
             data,
@@ -852,11 +1898,47 @@ impl NrAny {
         }
     }
 
+    /// Borrow `bytes` without copying: `data` points at them, the tag is
+    /// [`NR_ANY_STATIC_BYTES_TAG`] and there is no `drop_fn`, so nothing is
+    /// allocated or freed. Clones borrow the same bytes.
+    ///
+    /// The bytes must stay valid for as long as any copy of the value may
+    /// be read, which `'static` guarantees within one binary. A value passed
+    /// to another plugin must not borrow from a library that can be
+    /// unloaded first. Never build a value with this tag over owned heap
+    /// data: without a `drop_fn` it is never freed.
+    pub fn from_static_bytes(bytes: &'static [u8]) -> Self {
+        Self {
+            data: bytes.as_ptr() as *mut c_void,
+            size: bytes.len() as u64,
+            type_tag: NR_ANY_STATIC_BYTES_TAG,
+            drop_fn: None,
+        }
+    }
+
+    /// View a byte blob: one created by [`NrAny::from_static_bytes`], or by
+    /// [`NrAny::from_bytes`] with [`NR_ANY_BYTES_TAG`]. Returns `None` for
+    /// any other value.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        let bytes = matches!(self.type_tag, NR_ANY_BYTES_TAG | NR_ANY_STATIC_BYTES_TAG);
+        if self.data.is_null() {
+            return bytes.then_some(&[]);
+        }
+        match self.type_tag {
+            // Borrowed: `data` is the bytes themselves.
+            NR_ANY_STATIC_BYTES_TAG if self.drop_fn.is_none() => Some(unsafe {
+                core::slice::from_raw_parts(self.data as *const u8, self.size as usize)
+            }),
+            NR_ANY_BYTES_TAG => Some(unsafe { (*(self.data as *const Vec<u8>)).as_slice() }),
+            _ => None,
+        }
+    }
+
     pub fn as_ptr<T>(&self) -> Result<*const T, NrStatus> {
         if self.data.is_null() {
             return Err(NrStatus::Invalid);
         }
-        let expected_size = std::mem::size_of::<T>() as u64;
+        let expected_size = core::mem::size_of::<T>() as u64;
         if self.size != expected_size {
             return Err(NrStatus::Err);
         }
@@ -867,7 +1949,7 @@ impl NrAny {
         if self.data.is_null() {
             return Err(NrStatus::Invalid);
         }
-        let expected_size = std::mem::size_of::<T>() as u64;
+        let expected_size = core::mem::size_of::<T>() as u64;
         if self.size != expected_size {
             return Err(NrStatus::Err);
         }
@@ -887,6 +1969,7 @@ impl NrAny {
     }
 }
 
+#[cfg(feature = "std")]
 unsafe extern "C" fn drop_any<T>(ptr: *mut c_void) {
     if !ptr.is_null() {
         unsafe {
@@ -895,6 +1978,7 @@ unsafe extern "C" fn drop_any<T>(ptr: *mut c_void) {
     }
 }
 
+#[cfg(feature = "std")]
 unsafe extern "C" fn drop_bytes(ptr: *mut c_void) {
     if !ptr.is_null() {
         unsafe {
@@ -905,11 +1989,11 @@ unsafe extern "C" fn drop_bytes(ptr: *mut c_void) {
 
 impl Drop for NrAny {
     fn drop(&mut self) {
-        if let Some(drop_fn) = self.drop_fn {
-            if !self.data.is_null() {
-                unsafe {
-                    drop_fn(self.data);
-                }
+        if let Some(drop_fn) = self.drop_fn
+            && !self.data.is_null()
+        {
+            unsafe {
+                drop_fn(self.data);
             }
         }
     }
@@ -923,84 +2007,266 @@ impl NrPluginInfo {
 
 impl NrVec<u8> {
     pub fn from_nr_bytes(bytes: NrBytes) -> Self {
-        let v = bytes.as_slice().to_vec();
-        Self::from_vec(v)
+        Self::from_slice(bytes.as_slice())
     }
     pub fn from_string(s: String) -> Self {
         Self::from_vec(s.into_bytes())
     }
+    /// Copy `s` into a new vector with a single allocation.
+    pub fn from_slice(s: &[u8]) -> Self {
+        let mut v = Self::default();
+        v.extend_from_slice(s);
+        v
+    }
+}
+
+impl<T: Copy> NrVec<T> {
+    /// Append all of `s`, reserving once and copying in bulk.
+    pub fn extend_from_slice(&mut self, s: &[T]) {
+        if s.is_empty() {
+            return;
+        }
+        self.reserve(s.len());
+        unsafe {
+            core::ptr::copy_nonoverlapping(s.as_ptr(), self.ptr.add(self.len), s.len());
+        }
+        self.len += s.len();
+    }
 }
 
 impl<T> NrVec<T> {
     pub fn from_vec(v: Vec<T>) -> Self {
-        let mut v = std::mem::ManuallyDrop::new(v);
+        let mut v = core::mem::ManuallyDrop::new(v);
         let ptr = v.as_mut_ptr();
         let len = v.len();
         let cap = v.capacity();
         Self { ptr, len, cap }
     }
 
+    /// Take the buffer as a `Vec`, without copying. The buffer must come
+    /// from this process's global allocator (see
+    /// [Allocation](NrVec#allocation)); a buffer from another allocator is
+    /// freed with the wrong one when the `Vec` is dropped.
     pub fn into_vec(self) -> Vec<T> {
-        let this = std::mem::ManuallyDrop::new(self);
+        let this = core::mem::ManuallyDrop::new(self);
+        // A default (unallocated) vector has a null pointer.
+        if this.ptr.is_null() {
+            return Vec::new();
+        }
         unsafe { Vec::from_raw_parts(this.ptr, this.len, this.cap) }
     }
 
+    /// Whether `ptr`, `len` and `cap` describe a buffer [`into_vec`] and
+    /// `Drop` can take: no longer than its capacity, and allocated if it
+    /// has any. Says nothing about which allocator it came from.
+    ///
+    /// [`into_vec`]: NrVec::into_vec
+    pub fn is_well_formed(&self) -> bool {
+        self.len <= self.cap && (self.cap == 0 || !self.ptr.is_null())
+    }
+
     pub fn push(&mut self, value: T) {
         if self.len == self.cap {
             self.reserve(1);
         }
         unsafe {
-            std::ptr::write(self.ptr.add(self.len), value);
+            core::ptr::write(self.ptr.add(self.len), value);
         }
         self.len += 1;
     }
 
+    /// Remove and return the last element, if any.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(core::ptr::read(self.ptr.add(self.len))) }
+    }
+
     pub fn clear(&mut self) {
         while self.len > 0 {
             self.len -= 1;
             unsafe {
-                std::ptr::drop_in_place(self.ptr.add(self.len));
+                core::ptr::drop_in_place(self.ptr.add(self.len));
             }
         }
     }
 
+    /// Keep the first `len` elements and drop the rest. Does nothing if
+    /// the vector is no longer than `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail = self.len - len;
+        // Shorten first, so a panicking `drop` cannot drop an element twice.
+        self.len = len;
+        unsafe {
+            core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.ptr.add(len), tail));
+        }
+    }
+
+    /// Remove and return the element at `index`, shifting the ones after it
+    /// down.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(
+            index < len,
+            "removal index (is {index}) should be < len (is {len})"
+        );
+        unsafe {
+            let at = self.ptr.add(index);
+            let value = core::ptr::read(at);
+            core::ptr::copy(at.add(1), at, len - index - 1);
+            self.len = len - 1;
+            value
+        }
+    }
+
+    /// Remove and return the element at `index`, moving the last element
+    /// into its place. Faster than [`remove`](Self::remove), but does not
+    /// keep the order.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(
+            index < len,
+            "swap_remove index (is {index}) should be < len (is {len})"
+        );
+        unsafe {
+            let value = core::ptr::read(self.ptr.add(index));
+            core::ptr::copy(self.ptr.add(len - 1), self.ptr.add(index), 1);
+            self.len = len - 1;
+            value
+        }
+    }
+
+    /// Remove the elements in `range`, returning them as an iterator. The
+    /// elements after the range move down when the iterator is dropped;
+    /// the ones it did not yield are dropped with it. The buffer is kept.
+    ///
+    /// # Panics
+    ///
+    /// If the range starts after it ends, or ends past the length.
+    pub fn drain<R: core::ops::RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        use core::ops::Bound;
+        let len = self.len;
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end,
+            "drain range starts at {start} but ends at {end}"
+        );
+        assert!(
+            end <= len,
+            "drain range end (is {end}) should be <= len (is {len})"
+        );
+        // Shortened for as long as the drain lives, so forgetting it leaks
+        // the tail rather than exposing moved-out elements.
+        self.len = start;
+        Drain {
+            vec: self,
+            next: start,
+            end,
+            tail_start: end,
+            tail_len: len - end,
+        }
+    }
+
+    /// Insert `value` at `index`, shifting the elements from there up.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is greater than the length.
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len;
+        assert!(
+            index <= len,
+            "insertion index (is {index}) should be <= len (is {len})"
+        );
+        self.reserve(1);
+        unsafe {
+            let at = self.ptr.add(index);
+            core::ptr::copy(at, at.add(1), len - index);
+            core::ptr::write(at, value);
+        }
+        self.len = len + 1;
+    }
+
+    /// An empty vector with room for `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut v = Self::default();
+        if capacity > 0 {
+            v.reserve(capacity);
+        }
+        v
+    }
+
     pub fn reserve(&mut self, additional: usize) {
+        if core::mem::size_of::<T>() == 0 {
+            // Zero-sized elements need no memory: any aligned pointer holds
+            // as many as fit in `usize`, as with `Vec`.
+            if self.ptr.is_null() {
+                self.ptr = core::ptr::NonNull::dangling().as_ptr();
+                self.cap = usize::MAX;
+            }
+            if self.len.checked_add(additional).is_none() {
+                panic!("capacity overflow");
+            }
+            return;
+        }
         let available = self.cap - self.len;
         if available < additional {
             let required = self.len + additional;
             let new_cap = if self.cap == 0 {
-                std::cmp::max(1, required)
+                core::cmp::max(1, required)
             } else {
-                std::cmp::max(self.cap * 2, required)
+                core::cmp::max(self.cap * 2, required)
             };
 
-            let new_layout = match std::alloc::Layout::array::<T>(new_cap) {
+            let new_layout = match alloc::alloc::Layout::array::<T>(new_cap) {
                 Ok(layout) => layout,
                 Err(_) => {
                     // Layout calculation overflow - trigger allocation error
-                    std::alloc::handle_alloc_error(
-                        std::alloc::Layout::from_size_align(usize::MAX, 1)
-                            .unwrap_or_else(|_| std::alloc::Layout::new::<u8>()),
+                    alloc::alloc::handle_alloc_error(
+                        alloc::alloc::Layout::from_size_align(usize::MAX, 1)
+                            .unwrap_or_else(|_| alloc::alloc::Layout::new::<u8>()),
                     )
                 }
             };
 
             let new_ptr = if self.cap == 0 {
-                unsafe { std::alloc::alloc(new_layout) }
+                unsafe { alloc::alloc::alloc(new_layout) }
             } else {
-                let old_layout = match std::alloc::Layout::array::<T>(self.cap) {
+                let old_layout = match alloc::alloc::Layout::array::<T>(self.cap) {
                     Ok(layout) => layout,
                     Err(_) => {
                         // This should never happen since we successfully allocated before
                         // But handle it defensively
-                        std::alloc::handle_alloc_error(new_layout)
+                        alloc::alloc::handle_alloc_error(new_layout)
                     }
                 };
-                unsafe { std::alloc::realloc(self.ptr as *mut u8, old_layout, new_layout.size()) }
+                unsafe { alloc::alloc::realloc(self.ptr as *mut u8, old_layout, new_layout.size()) }
             };
 
             if new_ptr.is_null() {
-                std::alloc::handle_alloc_error(new_layout);
+                alloc::alloc::handle_alloc_error(new_layout);
             }
 
             self.ptr = new_ptr as *mut T;
@@ -1011,6 +2277,38 @@ impl<T> NrVec<T> {
     pub fn capacity(&self) -> usize {
         self.cap
     }
+
+    /// Give back the capacity beyond the length, freeing the buffer of an
+    /// empty vector.
+    pub fn shrink_to_fit(&mut self) {
+        if self.cap <= self.len || self.ptr.is_null() {
+            return;
+        }
+        let Ok(old_layout) = alloc::alloc::Layout::array::<T>(self.cap) else {
+            return;
+        };
+        if old_layout.size() == 0 {
+            // Zero-sized elements take no memory to give back.
+            return;
+        }
+        if self.len == 0 {
+            unsafe { alloc::alloc::dealloc(self.ptr as *mut u8, old_layout) };
+            self.ptr = core::ptr::null_mut();
+            self.cap = 0;
+            return;
+        }
+        // No larger than the old layout, which was valid.
+        let new_size = core::mem::size_of::<T>() * self.len;
+        let new_ptr = unsafe { alloc::alloc::realloc(self.ptr as *mut u8, old_layout, new_size) };
+        if new_ptr.is_null() {
+            alloc::alloc::handle_alloc_error(
+                alloc::alloc::Layout::from_size_align(new_size, old_layout.align())
+                    .unwrap_or(old_layout),
+            );
+        }
+        self.ptr = new_ptr as *mut T;
+        self.cap = self.len;
+    }
 }
 
 impl<T> Drop for NrVec<T> {
@@ -1021,12 +2319,15 @@ impl<T> Drop for NrVec<T> {
             }
             unsafe {
                 // Drop elements
-                let s = std::slice::from_raw_parts_mut(self.ptr, self.len);
-                std::ptr::drop_in_place(s);
-
-                // Deallocate
-                if let Ok(layout) = std::alloc::Layout::array::<T>(self.cap) {
-                    std::alloc::dealloc(self.ptr as *mut u8, layout);
+                let s = core::slice::from_raw_parts_mut(self.ptr, self.len);
+                core::ptr::drop_in_place(s);
+
+                // Deallocate, unless nothing was allocated for zero-sized
+                // elements
+                if let Ok(layout) = alloc::alloc::Layout::array::<T>(self.cap)
+                    && layout.size() != 0
+                {
+                    alloc::alloc::dealloc(self.ptr as *mut u8, layout);
                 }
             }
         }
@@ -1034,11 +2335,11 @@ impl<T> Drop for NrVec<T> {
 }
 
 impl<T> NrVec<T> {
-    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
         self.as_slice().iter()
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
         self.as_mut_slice().iter_mut()
     }
 
@@ -1046,7 +2347,7 @@ impl<T> NrVec<T> {
         if self.ptr.is_null() {
             &[]
         } else {
-            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+            unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
         }
     }
 
@@ -1054,14 +2355,45 @@ impl<T> NrVec<T> {
         if self.ptr.is_null() {
             &mut []
         } else {
-            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+            unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) }
         }
     }
 }
 
+/// Slice methods such as `first`, `windows` and `binary_search` work on an
+/// `NrVec` directly. An unallocated vector derefs to an empty slice.
+impl<T> core::ops::Deref for NrVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> core::ops::DerefMut for NrVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+/// Indexing panics when out of bounds, as for `Vec`.
+impl<T, I: core::slice::SliceIndex<[T]>> core::ops::Index<I> for NrVec<T> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, I: core::slice::SliceIndex<[T]>> core::ops::IndexMut<I> for NrVec<T> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
 impl<'a, T> IntoIterator for &'a NrVec<T> {
     type Item = &'a T;
-    type IntoIter = std::slice::Iter<'a, T>;
+    type IntoIter = core::slice::Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -1070,7 +2402,7 @@ impl<'a, T> IntoIterator for &'a NrVec<T> {
 
 impl<'a, T> IntoIterator for &'a mut NrVec<T> {
     type Item = &'a mut T;
-    type IntoIter = std::slice::IterMut<'a, T>;
+    type IntoIter = core::slice::IterMut<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter_mut()
@@ -1081,27 +2413,29 @@ impl<'a, T> IntoIterator for &'a mut NrVec<T> {
 pub struct IntoIter<T> {
     buf: *mut T,
     cap: usize,
-    ptr: *const T,
-    end: *const T,
+    // Indices rather than pointers, which do not advance over zero-sized
+    // elements.
+    next: usize,
+    end: usize,
 }
 
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.ptr == self.end {
+        if self.next == self.end {
             None
         } else {
             unsafe {
-                let result = std::ptr::read(self.ptr);
-                self.ptr = self.ptr.add(1);
+                let result = core::ptr::read(self.buf.add(self.next));
+                self.next += 1;
                 Some(result)
             }
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = (self.end as usize - self.ptr as usize) / std::mem::size_of::<T>();
+        let len = self.end - self.next;
         (len, Some(len))
     }
 }
@@ -1109,47 +2443,144 @@ impl<T> Iterator for IntoIter<T> {
 impl<T> Drop for IntoIter<T> {
     fn drop(&mut self) {
         // Drop remaining elements
-        if self.ptr != self.end {
+        if self.next != self.end {
             unsafe {
-                let len = (self.end as usize - self.ptr as usize) / std::mem::size_of::<T>();
-                let s = std::slice::from_raw_parts_mut(self.ptr as *mut T, len);
-                std::ptr::drop_in_place(s);
+                let s =
+                    core::slice::from_raw_parts_mut(self.buf.add(self.next), self.end - self.next);
+                core::ptr::drop_in_place(s);
             }
         }
         // Deallocate buffer
         if self.cap != 0 {
             unsafe {
-                if let Ok(layout) = std::alloc::Layout::array::<T>(self.cap) {
-                    std::alloc::dealloc(self.buf as *mut u8, layout);
+                if let Ok(layout) = alloc::alloc::Layout::array::<T>(self.cap)
+                    && layout.size() != 0
+                {
+                    alloc::alloc::dealloc(self.buf as *mut u8, layout);
                 }
             }
         }
     }
 }
 
+/// The iterator returned by [`NrVec::drain`].
+pub struct Drain<'a, T> {
+    vec: &'a mut NrVec<T>,
+    /// The range of elements still to yield.
+    next: usize,
+    end: usize,
+    /// Where the elements after the drained range start, and how many
+    /// there are.
+    tail_start: usize,
+    tail_len: usize,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        let value = unsafe { core::ptr::read(self.vec.ptr.add(self.next)) };
+        self.next += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.next;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        self.end -= 1;
+        unsafe { Some(core::ptr::read(self.vec.ptr.add(self.end))) }
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        /// Moves the tail down even if dropping an element panics.
+        struct MoveTail<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for MoveTail<'_, '_, T> {
+            fn drop(&mut self) {
+                let drain = &mut *self.0;
+                let vec = &mut *drain.vec;
+                if drain.tail_len > 0 {
+                    unsafe {
+                        core::ptr::copy(
+                            vec.ptr.add(drain.tail_start),
+                            vec.ptr.add(vec.len),
+                            drain.tail_len,
+                        );
+                    }
+                }
+                vec.len += drain.tail_len;
+            }
+        }
+
+        let remaining = self.end - self.next;
+        let first = self.next;
+        self.next = self.end;
+        let guard = MoveTail(self);
+        if remaining > 0 {
+            unsafe {
+                core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(
+                    guard.0.vec.ptr.add(first),
+                    remaining,
+                ));
+            }
+        }
+    }
+}
+
 impl<T> IntoIterator for NrVec<T> {
     type Item = T;
     type IntoIter = IntoIter<T>;
 
     fn into_iter(self) -> Self::IntoIter {
         // Prevent NrVec drop from deallocating
-        let this = std::mem::ManuallyDrop::new(self);
+        let this = core::mem::ManuallyDrop::new(self);
 
         let ptr = this.ptr;
         let cap = this.cap;
         let len = this.len;
 
-        unsafe {
-            IntoIter {
-                buf: ptr,
-                cap,
-                ptr,
-                end: if ptr.is_null() { ptr } else { ptr.add(len) },
-            }
+        IntoIter {
+            buf: ptr,
+            cap,
+            next: 0,
+            end: len,
         }
     }
 }
 
+impl<T> Extend<T> for NrVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for NrVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Self::default();
+        v.extend(iter);
+        v
+    }
+}
+
 // Safety: These types are ABI-stable data carriers.
 // Users must ensure that the pointers they contain are valid and accessed safely.
 unsafe impl Send for NrStr {}
@@ -1161,6 +2592,9 @@ unsafe impl Sync for NrBytes {}
 unsafe impl Send for NrKV {}
 unsafe impl Sync for NrKV {}
 
+unsafe impl Send for NrRequest {}
+unsafe impl Sync for NrRequest {}
+
 unsafe impl Send for NrKVAny {}
 unsafe impl Sync for NrKVAny {}
 
@@ -1182,9 +2616,116 @@ unsafe impl Sync for NrPluginInfo {}
 unsafe impl<T: Send> Send for NrVec<T> {}
 unsafe impl<T: Sync> Sync for NrVec<T> {}
 
+impl NrString {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty string with room for at least `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut buf = NrVec::default();
+        buf.reserve(capacity);
+        Self { buf }
+    }
+
+    /// Take ownership of `s` without copying.
+    pub fn from_string(s: String) -> Self {
+        Self {
+            buf: NrVec::from_vec(s.into_bytes()),
+        }
+    }
+
+    /// Append `s`, growing the buffer as needed.
+    pub fn push_str(&mut self, s: &str) {
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    /// Truncate to empty, keeping the allocation.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ever extended with `&str`, so the contents are UTF-8.
+        unsafe { core::str::from_utf8_unchecked(self.buf.as_slice()) }
+    }
+
+    /// Borrow the string as an `NrStr` to pass across the ABI. The view is
+    /// valid until `self` is mutated or dropped.
+    pub fn as_nr_str(&self) -> NrStr {
+        NrStr::new(self.as_str())
+    }
+
+    pub fn into_string(self) -> String {
+        unsafe { String::from_utf8_unchecked(self.buf.into_vec()) }
+    }
+
+    /// Convert into the byte vector `send_result` takes, without copying.
+    pub fn into_bytes(self) -> NrVec<u8> {
+        self.buf
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+}
+
+impl From<String> for NrString {
+    fn from(s: String) -> Self {
+        Self::from_string(s)
+    }
+}
+
+impl From<&str> for NrString {
+    fn from(s: &str) -> Self {
+        Self {
+            buf: NrVec::from_slice(s.as_bytes()),
+        }
+    }
+}
+
+impl core::fmt::Debug for NrString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl core::fmt::Display for NrString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::fmt::Write for NrString {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
 unsafe impl<A: Send, B: Send> Send for NrTuple<A, B> {}
 unsafe impl<A: Sync, B: Sync> Sync for NrTuple<A, B> {}
 
+// The layout `test_layout` checks, asserted at compile time so every
+// feature configuration, `no_std` included, is held to it.
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(size_of::<NrStatus>() == 4);
+    assert!(size_of::<NrStr>() == 16 && align_of::<NrStr>() == 8);
+    assert!(size_of::<NrBytes>() == 16 && align_of::<NrBytes>() == 8);
+    assert!(size_of::<NrVec<u8>>() == 24 && align_of::<NrVec<u8>>() == 8);
+    assert!(size_of::<NrTuple<u64, u64>>() == 16);
+    assert!(size_of::<NrKV>() == 32 && align_of::<NrKV>() == 8);
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1218,6 +2759,28 @@ mod tests {
         assert_eq!(align_of::<NrKV>(), 8);
     }
 
+    #[test]
+    fn test_nr_tuple_conversions_keep_field_order() {
+        let tuple = NrTuple::from((1u8, "two"));
+        assert_eq!((tuple.a, tuple.b), (1, "two"));
+        assert_eq!(tuple.as_tuple(), (&1, &"two"));
+        assert_eq!(tuple.into_tuple(), (1, "two"));
+        let (a, b): (u8, &str) = NrTuple { a: 3, b: "four" }.into();
+        assert_eq!((a, b), (3, "four"));
+    }
+
+    #[test]
+    fn test_nr_vec_is_well_formed() {
+        assert!(NrVec::<u8>::default().is_well_formed());
+        assert!(NrVec::from_slice(b"abc").is_well_formed());
+        let mut byte = 0u8;
+        let parts = [(&mut byte as *mut u8, 2, 1), (std::ptr::null_mut(), 0, 4)];
+        for (ptr, len, cap) in parts {
+            let v = core::mem::ManuallyDrop::new(NrVec { ptr, len, cap });
+            assert!(!v.is_well_formed());
+        }
+    }
+
     #[test]
     fn test_nr_vec() {
         let mut v = NrVec::<u32>::default();
@@ -1244,6 +2807,352 @@ mod tests {
         assert_eq!(v.len, 0);
         assert!(v.cap >= 12);
     }
+
+    /// Counts its drops in the shared counter.
+    struct Tracked(u32, std::rc::Rc<std::cell::Cell<usize>>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_nr_vec_truncate_remove_insert() {
+        let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+        let ids = |v: &NrVec<Tracked>| v.iter().map(|t| t.0).collect::<Vec<_>>();
+
+        let mut v = NrVec::default();
+        v.insert(0, Tracked(2, drops.clone()));
+        v.insert(0, Tracked(0, drops.clone()));
+        v.insert(1, Tracked(1, drops.clone()));
+        for i in 3..6 {
+            v.push(Tracked(i, drops.clone()));
+        }
+        v.insert(6, Tracked(6, drops.clone()));
+        assert_eq!(ids(&v), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(drops.get(), 0);
+
+        let removed = v.remove(3);
+        assert_eq!(removed.0, 3);
+        assert_eq!(drops.get(), 0);
+        drop(removed);
+        assert_eq!(drops.get(), 1);
+        assert_eq!(ids(&v), [0, 1, 2, 4, 5, 6]);
+
+        assert_eq!(v.remove(5).0, 6);
+        assert_eq!(v.remove(0).0, 0);
+        assert_eq!(drops.get(), 3);
+        assert_eq!(ids(&v), [1, 2, 4, 5]);
+
+        v.truncate(10);
+        assert_eq!(v.len, 4);
+        v.truncate(1);
+        assert_eq!(drops.get(), 6);
+        assert_eq!(ids(&v), [1]);
+
+        drop(v);
+        assert_eq!(drops.get(), 7);
+    }
+
+    #[test]
+    fn test_nr_vec_shrink_to_fit() {
+        let mut v = NrVec::default();
+        for i in 0..100u64 {
+            v.push(i);
+        }
+        v.reserve(1000);
+        v.truncate(40);
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 40);
+        assert_eq!(v.as_slice(), (0..40).collect::<Vec<_>>());
+        // Still grows after shrinking.
+        v.push(40);
+        assert_eq!(v.as_slice(), (0..41).collect::<Vec<_>>());
+
+        v.clear();
+        v.shrink_to_fit();
+        assert!(v.ptr.is_null());
+        assert_eq!(v.capacity(), 0);
+        v.push(7);
+        assert_eq!(v.as_slice(), [7]);
+
+        let mut strings = NrVec::default();
+        strings.reserve(16);
+        strings.push(String::from("kept"));
+        strings.shrink_to_fit();
+        assert_eq!(strings.capacity(), 1);
+        assert_eq!(strings.as_slice(), ["kept"]);
+    }
+
+    std::thread_local! {
+        static UNIT_DROPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// A zero-sized element counting its drops in `UNIT_DROPS`.
+    struct Unit;
+
+    impl Drop for Unit {
+        fn drop(&mut self) {
+            UNIT_DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+
+    fn unit_drops() -> usize {
+        UNIT_DROPS.with(|drops| drops.get())
+    }
+
+    #[test]
+    fn test_nr_vec_pop_swap_remove_with_capacity() {
+        let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+        let ids = |v: &NrVec<Tracked>| v.iter().map(|t| t.0).collect::<Vec<_>>();
+
+        let mut v = NrVec::with_capacity(4);
+        assert_eq!(v.capacity(), 4);
+        assert!(v.pop().is_none());
+        for i in 0..5 {
+            v.push(Tracked(i, drops.clone()));
+        }
+        assert_eq!(v.pop().unwrap().0, 4);
+        assert_eq!(drops.get(), 1);
+        assert_eq!(v.swap_remove(0).0, 0);
+        assert_eq!(ids(&v), [3, 1, 2]);
+        // The last element swaps with itself.
+        assert_eq!(v.swap_remove(2).0, 2);
+        assert_eq!(ids(&v), [3, 1]);
+        assert_eq!(drops.get(), 3);
+        drop(v);
+        assert_eq!(drops.get(), 5);
+
+        let empty = NrVec::<u64>::with_capacity(0);
+        assert!(empty.ptr.is_null());
+    }
+
+    #[test]
+    fn test_nr_vec_drain() {
+        let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+        let ids = |v: &NrVec<Tracked>| v.iter().map(|t| t.0).collect::<Vec<_>>();
+        let mut v: NrVec<Tracked> = (0..8).map(|i| Tracked(i, drops.clone())).collect();
+        let buffer = v.ptr;
+
+        let drained: Vec<_> = v.drain(2..5).map(|t| t.0).collect();
+        assert_eq!(drained, [2, 3, 4]);
+        assert_eq!(drops.get(), 3);
+        assert_eq!(ids(&v), [0, 1, 5, 6, 7]);
+        assert_eq!(v.ptr, buffer);
+
+        // Elements left in the drain are dropped with it, from both ends.
+        let mut drain = v.drain(1..=3);
+        assert_eq!(drain.len(), 3);
+        assert_eq!(drain.next_back().unwrap().0, 6);
+        drop(drain);
+        assert_eq!(drops.get(), 6);
+        assert_eq!(ids(&v), [0, 7]);
+
+        v.drain(..0);
+        assert_eq!(ids(&v), [0, 7]);
+        assert_eq!(v.drain(..).count(), 2);
+        assert_eq!(v.len, 0);
+        assert_eq!(drops.get(), 8);
+
+        // A forgotten drain leaks the tail instead of exposing it.
+        let mut v: NrVec<Tracked> = (0..3).map(|i| Tracked(i, drops.clone())).collect();
+        core::mem::forget(v.drain(1..2));
+        assert_eq!(ids(&v), [0]);
+        drop(v);
+        assert_eq!(drops.get(), 9);
+
+        let mut bytes = NrVec::from_slice(b"hello world");
+        bytes.drain(..6);
+        bytes.extend_from_slice(b"!");
+        assert_eq!(bytes.as_slice(), b"world!");
+    }
+
+    #[test]
+    #[should_panic(expected = "drain range end (is 3) should be <= len (is 2)")]
+    fn test_nr_vec_drain_out_of_bounds() {
+        let mut v = NrVec::from_slice(b"ab");
+        v.drain(1..3);
+    }
+
+    #[test]
+    fn test_nr_vec_zero_sized_elements() {
+        let before = unit_drops();
+        let mut v = NrVec::<Unit>::with_capacity(3);
+        assert!(!v.ptr.is_null());
+        for _ in 0..10 {
+            v.push(Unit);
+        }
+        v.insert(4, Unit);
+        assert_eq!(v.len, 11);
+        drop(v.remove(0));
+        drop(v.swap_remove(0));
+        drop(v.pop());
+        assert_eq!(unit_drops() - before, 3);
+        v.truncate(6);
+        assert_eq!(unit_drops() - before, 5);
+        assert_eq!(v.drain(1..4).count(), 3);
+        assert_eq!(v.len, 3);
+        assert_eq!(unit_drops() - before, 8);
+        v.shrink_to_fit();
+        assert_eq!(v.into_iter().count(), 3);
+        assert_eq!(unit_drops() - before, 11);
+
+        let mut units = NrVec::from_vec(vec![(); 4]);
+        units.extend_from_slice(&[(), ()]);
+        assert_eq!(units.len, 6);
+        assert_eq!(units.into_vec().len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index (is 1) should be < len (is 1)")]
+    fn test_nr_vec_remove_out_of_bounds() {
+        let mut v = NrVec::default();
+        v.push(1u32);
+        v.remove(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 2) should be <= len (is 1)")]
+    fn test_nr_vec_insert_out_of_bounds() {
+        let mut v = NrVec::default();
+        v.push(1u32);
+        v.insert(2, 2);
+    }
+
+    #[test]
+    fn test_nr_vec_extend_from_slice() {
+        let mut v = NrVec::<u32>::default();
+        v.extend_from_slice(&[]);
+        assert_eq!(v.cap, 0);
+
+        v.push(1);
+        v.extend_from_slice(&[2, 3, 4]);
+        v.extend_from_slice(&[5]);
+        assert_eq!(v.as_slice(), &[1, 2, 3, 4, 5]);
+
+        let src: Vec<u8> = (0..=255).collect();
+        let bytes = NrVec::<u8>::from_slice(&src);
+        assert_eq!(bytes.as_slice(), src.as_slice());
+        assert_eq!(bytes.capacity(), src.len());
+    }
+
+    #[test]
+    fn test_nr_string_growth() {
+        let mut s = NrString::new();
+        assert!(s.is_empty());
+        assert_eq!(s.as_str(), "");
+        assert_eq!(s.as_nr_str().as_bytes(), b"");
+
+        let mut expected = String::new();
+        for i in 0..100 {
+            let part = format!("{i}-ü,");
+            s.push_str(&part);
+            expected.push_str(&part);
+            assert!(s.capacity() >= s.len());
+        }
+        assert_eq!(s.as_str(), expected);
+        assert_eq!(s.as_nr_str().try_as_str(), Ok(expected.as_str()));
+
+        // Clones own their buffer.
+        let copy = s.clone();
+        s.push_str("tail");
+        assert_eq!(copy.as_str(), expected);
+        assert_eq!(s.into_string(), expected + "tail");
+
+        use std::fmt::Write;
+        let mut s = NrString::from("a");
+        write!(s, "{}{}", 1, 2.5).unwrap();
+        assert_eq!(s.to_string(), "a12.5");
+    }
+
+    #[test]
+    fn test_nr_string_past_capacity() {
+        let mut s = NrString::with_capacity(4);
+        assert!(s.capacity() >= 4);
+        let initial = s.capacity();
+
+        s.push_str("abc");
+        s.push_str("defghijklmnop");
+        assert!(s.capacity() > initial);
+        assert_eq!(s.as_str(), "abcdefghijklmnop");
+
+        let grown = s.capacity();
+        s.clear();
+        assert!(s.is_empty());
+        assert_eq!(s.capacity(), grown);
+        s.push_str("again");
+        assert_eq!(s.as_str(), "again");
+    }
+
+    #[test]
+    fn test_nr_string_ownership() {
+        // Unallocated strings convert and drop without touching the heap.
+        assert_eq!(NrString::new().into_string(), "");
+        drop(NrString::new());
+        assert_eq!(NrString::new().into_bytes().as_slice(), b"");
+
+        let s = NrString::from_string(String::from("héllo"));
+        let ptr = s.as_str().as_ptr();
+        let bytes = s.into_bytes();
+        assert_eq!(bytes.as_slice().as_ptr(), ptr);
+        assert_eq!(bytes.into_vec(), "héllo".as_bytes());
+
+        drop(NrString::from("dropped"));
+    }
+
+    #[test]
+    fn test_nr_vec_from_iter() {
+        let empty: NrVec<u8> = std::iter::empty().collect();
+        assert_eq!(empty.len, 0);
+        assert_eq!(empty.capacity(), 0);
+        assert!(empty.ptr.is_null());
+
+        let small: NrVec<u8> = (0..4).map(|i| i * 2).collect();
+        assert_eq!(small.as_slice(), &[0, 2, 4, 6]);
+        // Exact size hints allocate once.
+        assert_eq!(small.capacity(), 4);
+
+        let large: NrVec<String> = (0..10_000).map(|i| i.to_string()).collect();
+        assert_eq!(large.len, 10_000);
+        assert_eq!(large.as_slice()[9_999], "9999");
+
+        // No lower bound from `filter`; growth still works.
+        let mut v: NrVec<u32> = (0..100).filter(|i| i % 3 == 0).collect();
+        assert_eq!(v.len, 34);
+        v.extend([1000, 1001]);
+        v.extend(Vec::new());
+        assert_eq!(&v.as_slice()[33..], &[99, 1000, 1001]);
+    }
+
+    #[test]
+    fn test_nr_vec_index_and_deref() {
+        let mut v = NrVec::from_vec(vec![3u32, 1, 2]);
+        assert_eq!(v[0], 3);
+        v[0] = 0;
+        assert_eq!(&v[1..], &[1, 2]);
+        v[1..].copy_from_slice(&[4, 5]);
+        assert_eq!(v.first(), Some(&0));
+        assert_eq!(v.last(), Some(&5));
+        assert_eq!(v.binary_search(&4), Ok(1));
+        assert_eq!(v.windows(2).count(), 2);
+
+        // An unallocated vector is an empty slice, not a null one.
+        let mut empty = NrVec::<u32>::default();
+        assert!(empty.ptr.is_null());
+        assert!(empty.is_empty());
+        assert_eq!(empty.first(), None);
+        empty.sort();
+        assert_eq!(&empty[..], &[] as &[u32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 2 but the index is 2")]
+    fn test_nr_vec_index_out_of_bounds() {
+        let v = NrVec::from_slice(b"ab");
+        let _ = v[2];
+    }
+
     #[test]
     fn test_nr_vec_iter() {
         let mut v = NrVec::<u32>::default();
@@ -1352,6 +3261,329 @@ mod tests {
         assert!(map.is_empty());
     }
 
+    #[test]
+    fn test_nr_map_owns_its_keys() {
+        let mut map = NrMap::new();
+        for i in 0..20 {
+            let key = format!("key-{i}");
+            map.insert(&key, NrAny::new(i as u64, 1));
+            let nr_key = format!("nr-{i}");
+            map.insert_nr(NrStr::new(&nr_key), NrAny::new(i as u64, 1));
+            map.entry(&format!("entry-{i}"))
+                .or_insert(NrAny::new(i as u64, 1));
+            // The strings are gone; the map's copies are not.
+        }
+        for i in 0..20 {
+            for prefix in ["key", "nr", "entry"] {
+                let value = map.get(&format!("{prefix}-{i}")).unwrap();
+                assert_eq!(read_u64(value), i as u64);
+            }
+        }
+        let removed = map.remove(&String::from("key-3")).unwrap();
+        assert_eq!(removed.key.as_str(), "key-3");
+        assert_eq!(map.keys().filter(|key| key.starts_with("nr-")).count(), 20);
+    }
+
+    #[test]
+    fn test_nr_map_drops_values_once() {
+        let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+        let tracked = |i| NrAny::new(Tracked(i, drops.clone()), 1);
+        let mut map = NrMap::new();
+        for i in 0..12 {
+            map.insert(&format!("k{i}"), tracked(i));
+        }
+        assert_eq!(drops.get(), 0);
+
+        // Replacing drops the old value.
+        map.insert("k0", tracked(100));
+        assert_eq!(drops.get(), 1);
+
+        let removed = map.remove("k5").unwrap();
+        assert_eq!(drops.get(), 1);
+        drop(removed);
+        assert_eq!(drops.get(), 2);
+        assert!(map.remove("k5").is_none());
+
+        map.clear();
+        assert_eq!(drops.get(), 13);
+        map.insert("again", tracked(0));
+        drop(map);
+        assert_eq!(drops.get(), 14);
+    }
+
+    #[test]
+    fn test_nr_map_get_or_insert_with() {
+        // Enough keys to build the index and grow it at least once.
+        let keys: Vec<String> = (0..40).map(|i| format!("k{i}")).collect();
+        let mut map = NrMap::new();
+        let mut inits = 0;
+
+        for round in 0..3 {
+            for key in &keys {
+                let v = map.get_or_insert_with(key, || {
+                    inits += 1;
+                    NrAny::new(0u64, 1)
+                });
+                unsafe { *v.as_mut_ptr::<u64>().unwrap() += 1 };
+                assert_eq!(unsafe { *v.as_ptr::<u64>().unwrap() }, round + 1);
+            }
+        }
+
+        assert_eq!(inits, 40);
+        assert_eq!(map.len(), 40);
+        for key in &keys {
+            let v = map.get(key).unwrap();
+            assert_eq!(unsafe { *v.as_ptr::<u64>().unwrap() }, 3);
+        }
+    }
+
+    #[test]
+    fn test_nr_map_indexes_new_entries_once() {
+        // Each insert path, across the index creation (8 entries) and the
+        // first growths, must leave exactly one slot per entry.
+        let keys: Vec<String> = (0..64).map(|i| format!("k{i}")).collect();
+        for path in 0..3 {
+            let mut map = NrMap::new();
+            for (n, key) in keys.iter().enumerate() {
+                match path {
+                    0 => map.insert(key, NrAny::new(n as u64, 1)),
+                    1 => map.insert_nr(NrStr::new(key), NrAny::new(n as u64, 1)),
+                    _ => {
+                        map.get_or_insert_with(key, || NrAny::new(n as u64, 1));
+                    }
+                }
+                if !map.index.ptr.is_null() {
+                    assert_eq!(
+                        map.used as usize,
+                        map.len(),
+                        "path {path}, {} entries",
+                        n + 1
+                    );
+                }
+            }
+
+            // Removing every entry must not leave a slot that still finds it.
+            for key in keys.iter().rev() {
+                assert!(map.remove(key).is_some());
+                assert!(map.get(key).is_none());
+                assert_eq!(map.used as usize, map.len());
+            }
+            assert!(map.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_nr_map_with_capacity_indexes_from_the_start() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("k{i}")).collect();
+        let mut map = NrMap::with_capacity(keys.len());
+        assert!(!map.index.ptr.is_null());
+        let (index, slots) = (map.index.ptr, map.index.len);
+
+        for (n, key) in keys.iter().enumerate() {
+            map.insert(key, NrAny::new(n as u64, 1));
+            assert_eq!(map.used as usize, n + 1);
+        }
+        // Never rehashed, so never grown.
+        assert_eq!((map.index.ptr, map.index.len), (index, slots));
+        for (n, key) in keys.iter().enumerate() {
+            assert_eq!(read_u64(map.get(key).unwrap()), n as u64);
+        }
+
+        assert!(map.capacity() >= keys.len());
+        assert_eq!(map.index_capacity(), slots);
+
+        assert!(NrMap::with_capacity(0).index.ptr.is_null());
+        assert_eq!(NrMap::with_capacity(0).index_capacity(), 0);
+        let mut small = NrMap::with_capacity(1);
+        small.insert("only", NrAny::new(1u64, 1));
+        assert_eq!(small.used, 1);
+    }
+
+    fn read_u64(v: &NrAny) -> u64 {
+        unsafe { *v.as_ptr::<u64>().unwrap() }
+    }
+
+    #[test]
+    fn test_nr_map_entry() {
+        let keys: Vec<String> = (0..20).map(|i| format!("key-{i}")).collect();
+        let mut map = NrMap::new();
+        // Vacant inserts, across the switch to an index and two rehashes.
+        for (i, key) in keys.iter().enumerate() {
+            match map.entry(key) {
+                NrMapEntry::Vacant(entry) => {
+                    assert_eq!(entry.key(), key);
+                    let value = entry.insert(NrAny::new(i as u64, 1));
+                    assert_eq!(read_u64(value), i as u64);
+                }
+                NrMapEntry::Occupied(_) => panic!("{key} is not in the map yet"),
+            }
+        }
+        assert!(!map.index.ptr.is_null());
+
+        // Occupied: modify in place.
+        let NrMapEntry::Occupied(mut entry) = map.entry("key-3") else {
+            panic!("key-3 is in the map");
+        };
+        assert_eq!(entry.key(), "key-3");
+        assert_eq!(read_u64(entry.get()), 3);
+        *entry.get_mut() = NrAny::new(30u64, 1);
+        assert_eq!(read_u64(&entry.insert(NrAny::new(300u64, 1))), 30);
+        assert_eq!(read_u64(map.get("key-3").unwrap()), 300);
+
+        map.entry("key-4").and_modify(|v| *v = NrAny::new(40u64, 1));
+        map.entry("absent")
+            .and_modify(|_| panic!("absent is not in the map"));
+        assert_eq!(read_u64(map.get("key-4").unwrap()), 40);
+        assert!(!map.contains_key("absent"));
+
+        // Occupied: remove, which moves the last entry into its place.
+        let NrMapEntry::Occupied(entry) = map.entry("key-0") else {
+            panic!("key-0 is in the map");
+        };
+        assert_eq!(read_u64(&entry.remove()), 0);
+        assert!(!map.contains_key("key-0"));
+        assert_eq!(map.len(), keys.len() - 1);
+        assert_eq!(read_u64(map.get("key-19").unwrap()), 19);
+        assert_eq!(map.keys().next(), Some("key-19"));
+    }
+
+    #[test]
+    fn test_nr_map_matches_model() {
+        // Reference model: a Vec with swap_remove, which also pins down the
+        // iteration order NrMap promises.
+        let keys: Vec<String> = (0..48).map(|i| format!("key-{i}")).collect();
+        // Never inserted, so probes must walk past live and removed slots.
+        let absent: Vec<String> = (0..16).map(|i| format!("absent-{i}")).collect();
+
+        for seed in 1..=100u64 {
+            let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            let mut next = move || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            };
+            let mut map = NrMap::new();
+            let mut model: Vec<(&str, u64)> = Vec::new();
+
+            for step in 0..400u64 {
+                let key = keys[(next() % keys.len() as u64) as usize].as_str();
+                let pos = model.iter().position(|(k, _)| *k == key);
+                match next() % 14 {
+                    0..=3 => {
+                        map.insert(key, NrAny::new(step, 1));
+                        match pos {
+                            Some(i) => model[i].1 = step,
+                            None => model.push((key, step)),
+                        }
+                    }
+                    4..=5 => {
+                        let removed = map.remove(key);
+                        assert_eq!(removed.is_some(), pos.is_some());
+                        if let Some(i) = pos {
+                            model.swap_remove(i);
+                        }
+                    }
+                    6..=7 => {
+                        let v = map.entry(key).or_insert_with(|| NrAny::new(step, 1));
+                        let expected = match pos {
+                            Some(i) => model[i].1,
+                            None => {
+                                model.push((key, step));
+                                step
+                            }
+                        };
+                        assert_eq!(read_u64(v), expected);
+                    }
+                    8..=9 => {
+                        let v = map.get_or_insert_with(key, || NrAny::new(step, 1));
+                        let expected = match pos {
+                            Some(i) => model[i].1,
+                            None => {
+                                model.push((key, step));
+                                step
+                            }
+                        };
+                        assert_eq!(read_u64(v), expected);
+                    }
+                    10 => assert_eq!(map.contains_key(key), pos.is_some()),
+                    11 => match map.entry(key) {
+                        NrMapEntry::Occupied(mut entry) => {
+                            let old = entry.insert(NrAny::new(step, 1));
+                            assert_eq!(read_u64(&old), model[pos.unwrap()].1);
+                            model[pos.unwrap()].1 = step;
+                        }
+                        NrMapEntry::Vacant(entry) => {
+                            assert!(pos.is_none());
+                            assert_eq!(read_u64(entry.insert(NrAny::new(step, 1))), step);
+                            model.push((key, step));
+                        }
+                    },
+                    12 => match map.entry(key) {
+                        NrMapEntry::Occupied(entry) => {
+                            let i = pos.unwrap();
+                            assert_eq!(read_u64(&entry.remove()), model[i].1);
+                            model.swap_remove(i);
+                        }
+                        NrMapEntry::Vacant(_) => assert!(pos.is_none()),
+                    },
+                    _ if step % 97 == 0 => {
+                        map.clear();
+                        model.clear();
+                    }
+                    _ => assert_eq!(map.get(key).map(read_u64), pos.map(|i| model[i].1)),
+                }
+
+                assert_eq!(map.len(), model.len());
+                for key in &keys {
+                    let expected = model.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+                    assert_eq!(
+                        map.get(key).map(read_u64),
+                        expected,
+                        "seed {seed}, step {step}"
+                    );
+                }
+                for key in &absent {
+                    assert!(map.get(key).is_none(), "seed {seed}, step {step}");
+                }
+            }
+
+            let items: Vec<(&str, u64)> = map.iter().map(|(k, v)| (k, read_u64(v))).collect();
+            assert_eq!(items, model);
+            assert!(map.keys().eq(model.iter().map(|(k, _)| *k)));
+            assert!(map.values().map(read_u64).eq(model.iter().map(|(_, v)| *v)));
+            for (k, v) in &model {
+                assert_eq!(map.get(k).map(read_u64), Some(*v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nr_map_byte_conversions() {
+        let mut source = std::collections::HashMap::new();
+        source.insert("content-type".to_string(), b"text/plain".to_vec());
+        source.insert("empty".to_string(), Vec::new());
+
+        let map = NrMap::from(&source);
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.get("content-type").and_then(NrAny::as_bytes),
+            Some(&b"text/plain"[..])
+        );
+
+        let back = std::collections::HashMap::<String, Vec<u8>>::try_from(&map).unwrap();
+        assert_eq!(back, source);
+
+        let mut mixed = NrMap::new();
+        mixed.insert("n", NrAny::new(1u64, 1));
+        assert_eq!(
+            std::collections::HashMap::<String, Vec<u8>>::try_from(&mixed),
+            Err(NotBytesError {
+                key: "n".to_string()
+            })
+        );
+    }
+
     #[test]
     fn test_nr_any() {
         let any_int = NrAny::new(42i32, 1);
@@ -1376,8 +3608,34 @@ mod tests {
         assert_eq!(any_bytes.type_tag(), 3);
         assert_eq!(any_bytes.size(), 4);
 
+        static STATIC_BYTES: &[u8] = b"static";
+        let any_static = NrAny::from_static_bytes(STATIC_BYTES);
+        assert_eq!(any_static.type_tag(), NR_ANY_STATIC_BYTES_TAG);
+        assert_eq!(any_static.size(), 6);
+        assert_eq!(any_static.as_bytes(), Some(STATIC_BYTES));
+        assert_eq!(
+            any_static.as_bytes().unwrap().as_ptr(),
+            STATIC_BYTES.as_ptr()
+        );
+        assert!(any_static.drop_fn.is_none());
+        // Clones borrow the same bytes rather than copying them.
+        let cloned = any_static.clone();
+        assert_eq!(cloned.as_bytes().unwrap().as_ptr(), STATIC_BYTES.as_ptr());
+        assert!(cloned.drop_fn.is_none());
+        assert_eq!(NrAny::from_static_bytes(b"").as_bytes(), Some(&[][..]));
+        assert_eq!(any_bytes.as_bytes(), None);
+        // Only the tag marks a value as borrowed bytes.
+        let mut untagged = NrAny::from_static_bytes(STATIC_BYTES);
+        untagged.type_tag = 4;
+        assert_eq!(untagged.as_bytes(), None);
+        assert_eq!(
+            NrAny::from_bytes(NrBytes::from_slice(b"heap"), NR_ANY_BYTES_TAG).as_bytes(),
+            Some(&b"heap"[..])
+        );
+
         let default_any = NrAny::default();
         assert!(default_any.is_null());
+        assert_eq!(default_any.as_bytes(), None);
         assert_eq!(default_any.type_tag(), 0);
         assert_eq!(default_any.size(), 0);
 
@@ -1392,4 +3650,79 @@ mod tests {
         let mut any_int_mut = NrAny::new(42i32, 1);
         assert_eq!(any_int_mut.as_mut_ptr::<u64>(), Err(NrStatus::Err));
     }
+
+    #[test]
+    fn test_nr_str_checked() {
+        let valid = NrStr::new("hello");
+        assert_eq!(valid.try_as_str(), Ok("hello"));
+        assert_eq!(valid.as_str_lossy(), "hello");
+
+        let bytes = [b'o', b'k', 0xff, 0xfe];
+        let invalid = NrStr {
+            ptr: bytes.as_ptr(),
+            len: bytes.len() as u32,
+        };
+        assert!(invalid.try_as_str().is_err());
+        assert_eq!(invalid.as_str_lossy(), "ok\u{fffd}\u{fffd}");
+
+        let null = NrStr::default();
+        assert_eq!(null.try_as_str(), Ok(""));
+    }
+
+    #[test]
+    fn test_null_views_are_empty() {
+        let null = NrStr::default();
+        assert_eq!(null.as_str(), "");
+        // A null pointer with a bogus length is still empty.
+        let bogus = NrBytes {
+            ptr: std::ptr::null(),
+            len: 16,
+        };
+        assert_eq!(bogus.as_slice(), &[] as &[u8]);
+        assert_eq!(NrBytes::default().as_slice(), &[] as &[u8]);
+
+        let empty = NrBytes::empty();
+        assert!(!empty.ptr.is_null());
+        assert_eq!(empty.as_slice(), &[] as &[u8]);
+    }
+
+    mod invalid_entry_plugin {
+        use crate::{NrBytes, NrHostVTable, NrStatus, NrStr};
+        use std::ffi::c_void;
+
+        unsafe fn init(_: *mut c_void, _: *const NrHostVTable) -> NrStatus {
+            NrStatus::Ok
+        }
+
+        fn shutdown() {}
+
+        unsafe fn handle_echo(_sid: u64, _payload: NrBytes) -> NrStatus {
+            NrStatus::Ok
+        }
+
+        define_plugin! {
+            init: init,
+            shutdown: shutdown,
+            entries: {
+                "echo" => handle_echo,
+            }
+        }
+
+        #[test]
+        fn test_invalid_utf8_entry_is_rejected() {
+            let handle = PLUGIN_VTABLE.handle.unwrap();
+
+            let status = unsafe { handle(NrStr::new("echo"), 1, NrBytes::default()) };
+            assert_eq!(status, NrStatus::Ok);
+
+            // "echo" followed by a lone continuation byte
+            let bytes = [b'e', b'c', b'h', b'o', 0x80];
+            let entry = NrStr {
+                ptr: bytes.as_ptr(),
+                len: bytes.len() as u32,
+            };
+            let status = unsafe { handle(entry, 2, NrBytes::default()) };
+            assert_eq!(status, NrStatus::Invalid);
+        }
+    }
 }
@@ -0,0 +1,100 @@
+//! Logging into the host's logger.
+//!
+//! `define_plugin!` keeps the host pointers it gets in `init`, and
+//! [`nr_log!`](crate::nr_log) sends records through [`NrHostExt::log`]. The
+//! host logs them with the plugin's name attached, so a plugin in production
+//! needs no `println!`:
+//!
+//! ```
+//! use nylon_ring::{NrLogLevel, nr_log};
+//!
+//! fn handle_order(id: u64) {
+//!     nr_log!(NrLogLevel::Info, "processing order {id}");
+//! }
+//! # handle_order(7);
+//! ```
+//!
+//! Records are dropped before `init` and when the host has no extension
+//! table. Each binary keeps one set of pointers, that of the plugin it
+//! initialized last.
+//!
+//! [`NrHostExt::log`]: crate::NrHostExt::log
+
+use crate::{NrHostVTable, NrStr};
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use core::ffi::c_void;
+use core::fmt;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Severity of a record sent through [`NrHostExt::log`](crate::NrHostExt::log).
+/// Hosts read other values as `Info`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NrLogLevel {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl NrLogLevel {
+    /// The level `level` stands for on the wire; `Info` if it is none.
+    pub fn from_u32(level: u32) -> Self {
+        match level {
+            1 => Self::Error,
+            2 => Self::Warn,
+            4 => Self::Debug,
+            5 => Self::Trace,
+            _ => Self::Info,
+        }
+    }
+}
+
+static HOST_CTX: AtomicPtr<c_void> = AtomicPtr::new(core::ptr::null_mut());
+static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Remember the host pointers records are sent with. Called by
+/// `define_plugin!` in `init`.
+#[doc(hidden)]
+pub fn install(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) {
+    HOST_CTX.store(host_ctx, Ordering::Release);
+    HOST_VTABLE.store(host_vtable as *mut _, Ordering::Release);
+}
+
+/// Send a record to the host. Used by [`nr_log!`](crate::nr_log).
+#[doc(hidden)]
+pub fn log(level: NrLogLevel, target: &str, message: fmt::Arguments<'_>) {
+    let host_vtable = HOST_VTABLE.load(Ordering::Acquire);
+    if host_vtable.is_null() {
+        return;
+    }
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    unsafe {
+        let ext = ((*host_vtable).get_host_ext)(host_ctx);
+        if ext.is_null() {
+            return;
+        }
+        let message = match message.as_str() {
+            Some(message) => Cow::Borrowed(message),
+            None => Cow::Owned(message.to_string()),
+        };
+        ((*ext).log)(
+            host_ctx,
+            level as u32,
+            NrStr::new(target),
+            NrStr::new(&message),
+        );
+    }
+}
+
+/// Log a message through the host, like `log::log!`:
+/// `nr_log!(NrLogLevel::Warn, "retrying {entry}")`. The target is the
+/// calling module's path.
+#[macro_export]
+macro_rules! nr_log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::logging::log($level, module_path!(), format_args!($($arg)+))
+    };
+}
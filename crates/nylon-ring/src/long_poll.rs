@@ -0,0 +1,101 @@
+//! Plugin-side helper for long-poll entries.
+//!
+//! A long-poll call answers with a single result frame, or with nothing once
+//! its wait is over. Frames are encoded as:
+//!
+//! - `Ok` with an empty payload: progress. Renews the host's deadline when the
+//!   caller asked for renewal, and is otherwise ignored.
+//! - `StreamEnd` with an empty payload: the wait ended without an event.
+//! - anything else: the result.
+
+use crate::{NrHostVTable, NrStatus, NrVec};
+use std::ffi::c_void;
+use std::time::{Duration, Instant};
+
+/// The reply side of one long-poll call.
+pub struct LongPoll {
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    sid: u64,
+}
+
+// Safety: the host's `send_result` may be called from any thread.
+unsafe impl Send for LongPoll {}
+
+impl LongPoll {
+    /// # Safety
+    ///
+    /// `host_ctx` and `host_vtable` must be the values the host passed to the
+    /// plugin's `init`, and `sid` the SID of a long-poll call.
+    pub unsafe fn new(host_ctx: *mut c_void, host_vtable: *const NrHostVTable, sid: u64) -> Self {
+        Self {
+            host_ctx,
+            host_vtable,
+            sid,
+        }
+    }
+
+    /// Tell the host the call is still alive.
+    pub fn progress(&self) {
+        self.send(NrStatus::Ok, NrVec::default());
+    }
+
+    /// Answer with a result. An empty `Ok` payload would read as progress, so
+    /// it is sent as an empty answer instead.
+    pub fn fulfill(self, status: NrStatus, data: NrVec<u8>) {
+        if status == NrStatus::Ok && data.len == 0 {
+            return self.empty();
+        }
+        self.send(status, data);
+    }
+
+    /// Answer that no event arrived.
+    pub fn empty(self) {
+        self.send(NrStatus::StreamEnd, NrVec::default());
+    }
+
+    /// Wait up to `max_wait` for an event and answer with it, or answer empty.
+    ///
+    /// `wait` is called with the longest it may block and returns the event
+    /// payload if one arrived. With `renew_every`, waits are capped at that
+    /// interval and a progress frame is sent between them. Returns whether the
+    /// call was fulfilled.
+    ///
+    /// Gives up between waits, without answering, once the host has revoked
+    /// the plugin's callbacks for shutdown.
+    pub fn wait_or_empty<F>(
+        self,
+        max_wait: Duration,
+        renew_every: Option<Duration>,
+        mut wait: F,
+    ) -> bool
+    where
+        F: FnMut(Duration) -> Option<NrVec<u8>>,
+    {
+        let deadline = Instant::now() + max_wait;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let slice = renew_every.map_or(remaining, |every| every.min(remaining));
+            if let Some(data) = wait(slice) {
+                self.fulfill(NrStatus::Ok, data);
+                return true;
+            }
+            if self.is_revoked() {
+                return false;
+            }
+            if Instant::now() >= deadline {
+                self.empty();
+                return false;
+            }
+            self.progress();
+        }
+    }
+
+    fn is_revoked(&self) -> bool {
+        unsafe { (*self.host_vtable).is_revoked(self.host_ctx) }
+    }
+
+    fn send(&self, status: NrStatus, data: NrVec<u8>) {
+        unsafe { ((*self.host_vtable).send_result)(self.host_ctx, self.sid, status, data) }
+    }
+}
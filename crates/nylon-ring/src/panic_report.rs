@@ -0,0 +1,143 @@
+//! Panic capture for plugin entry points.
+//!
+//! `define_plugin!` installs a panic hook on init and wraps every entry point
+//! in `catch_unwind`. The hook records the panic message and a backtrace in a
+//! thread-local, which the wrapper forwards to the host through
+//! `NrHostExt::report_panic` before returning `NrStatus::Err`. The host puts
+//! the message in the call's error. A panic in `init` becomes the reason
+//! under `INIT_ERROR_KEY`, and one in `shutdown` is logged.
+//!
+//! Backtraces are opt-in: like `std`'s own, they are only captured when
+//! `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE` is set, and are empty otherwise.
+//!
+//! Nothing is caught in a plugin built with `panic = "abort"` (as this
+//! workspace's release profile is): a panic then ends the host process.
+
+use crate::{NrBytes, NrHostVTable, NrStr};
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::sync::Once;
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+/// Install the plugin panic hook. Safe to call more than once.
+///
+/// The previously installed hook is still invoked, so the default stderr
+/// output is preserved. The hook is process-wide when the plugin shares its
+/// `std` with the host, as statically linked plugins do.
+pub fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let message = describe_panic(info.payload());
+            let backtrace = Backtrace::capture();
+            let backtrace = match backtrace.status() {
+                BacktraceStatus::Captured => backtrace.to_string(),
+                _ => String::new(),
+            };
+            LAST_PANIC.with(|cell| *cell.borrow_mut() = Some((message, backtrace)));
+            previous(info);
+        }));
+    });
+}
+
+/// Take the message and backtrace of the last panic on this thread.
+pub fn take_last() -> Option<(String, String)> {
+    LAST_PANIC.with(|cell| cell.borrow_mut().take())
+}
+
+/// Extract a human-readable message from a panic payload: the text of
+/// `&str` and `String` payloads, the value of integer ones (as from
+/// `panic_any(404)`), and `Box<dyn Any>`, as `std` prints it, for others.
+pub fn describe_panic(payload: &(dyn Any + Send)) -> String {
+    macro_rules! display {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(value) = payload.downcast_ref::<$ty>() {
+                    return value.to_string();
+                }
+            )*
+        };
+    }
+    display!(&str, String, i32, i64, u32, u64, usize);
+    "Box<dyn Any>".to_string()
+}
+
+/// Forward a caught panic to the host.
+///
+/// # Safety
+///
+/// `host_ctx` and `host_vtable` must be the values the host passed to the
+/// plugin's `init`, or `host_vtable` must be null.
+pub unsafe fn report(
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    sid: u64,
+    entry: &str,
+    payload: &(dyn Any + Send),
+) {
+    let (message, backtrace) =
+        take_last().unwrap_or_else(|| (describe_panic(payload), String::new()));
+
+    if host_vtable.is_null() {
+        return;
+    }
+    unsafe {
+        let ext = ((*host_vtable).get_host_ext)(host_ctx);
+        if ext.is_null() {
+            return;
+        }
+        ((*ext).report_panic)(
+            host_ctx,
+            sid,
+            NrStr::new(entry),
+            NrStr::new(&message),
+            NrStr::new(&backtrace),
+        );
+    }
+}
+
+/// Report a panic in `init` as the reason it failed, under
+/// [`INIT_ERROR_KEY`](crate::INIT_ERROR_KEY).
+///
+/// # Safety
+///
+/// Same as [`report`].
+pub unsafe fn report_init(
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+    payload: &(dyn Any + Send),
+) {
+    let message = take_last().map_or_else(|| describe_panic(payload), |(message, _)| message);
+    if host_vtable.is_null() {
+        return;
+    }
+    unsafe {
+        let ext = ((*host_vtable).get_host_ext)(host_ctx);
+        if ext.is_null() {
+            return;
+        }
+        ((*ext).set_state)(
+            host_ctx,
+            crate::INIT_SID,
+            NrStr::new(crate::INIT_ERROR_KEY),
+            NrBytes::from_slice(format!("init panicked: {message}").as_bytes()),
+        );
+    }
+}
+
+/// Log a panic in `shutdown` through the host, which has revoked every
+/// other callback by then.
+pub fn report_shutdown(payload: &(dyn Any + Send)) {
+    let message = take_last().map_or_else(|| describe_panic(payload), |(message, _)| message);
+    crate::logging::log(
+        crate::NrLogLevel::Error,
+        module_path!(),
+        format_args!("shutdown panicked: {message}"),
+    );
+}
@@ -0,0 +1,378 @@
+//! Plugins without `unsafe`.
+//!
+//! Implement [`Plugin`] and export it with [`impl_plugin!`](crate::impl_plugin).
+//! The macro generates the `extern "C"` entry points, keeps one instance of
+//! the plugin, and turns each [`Response`] into a `send_result`:
+//!
+//! ```
+//! use nylon_ring::{impl_plugin, CallContext, NrStatus, Plugin, Response};
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! #[derive(Default)]
+//! struct Counter {
+//!     hits: AtomicU64,
+//! }
+//!
+//! impl Plugin for Counter {
+//!     fn entries(&self) -> &[&'static str] {
+//!         &["hit", "echo"]
+//!     }
+//!
+//!     fn handle(&self, ctx: &CallContext<'_>) -> Response {
+//!         match ctx.entry() {
+//!             "hit" => {
+//!                 let hits = self.hits.fetch_add(1, Ordering::Relaxed) + 1;
+//!                 Response::ok(hits.to_string())
+//!             }
+//!             "echo" => Response::ok(ctx.payload()),
+//!             _ => Response::reject(NrStatus::Invalid),
+//!         }
+//!     }
+//! }
+//!
+//! impl_plugin!(Counter);
+//! # fn main() {}
+//! ```
+//!
+//! A call the plugin answers later, from another thread or as a stream of
+//! frames, returns [`Response::accepted`] and sends through [`HostApi`].
+
+use crate::{NrBytes, NrHostVTable, NrStatus, NrStr, NrVec};
+use std::ffi::c_void;
+use std::sync::RwLock;
+
+/// A plugin written against safe types. Exported with
+/// [`impl_plugin!`](crate::impl_plugin), which creates it with `Default`.
+///
+/// `handle` may run on several threads at once; `init` and `shutdown` run
+/// alone.
+pub trait Plugin: Default + Send + Sync + 'static {
+    /// Prepare the plugin once it is loaded. Any status but `Ok` fails the
+    /// load.
+    fn init(&mut self, host: &HostApi) -> NrStatus {
+        let _ = host;
+        NrStatus::Ok
+    }
+
+    /// The entries the plugin serves, listed to the host after `init`.
+    fn entries(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Answer one call.
+    fn handle(&self, ctx: &CallContext<'_>) -> Response;
+
+    /// Release resources before the plugin is unloaded.
+    fn shutdown(&mut self) {}
+}
+
+/// The host's callbacks, for a plugin that sends results itself.
+#[derive(Debug, Clone, Copy)]
+pub struct HostApi {
+    host_ctx: *mut c_void,
+    host_vtable: *const NrHostVTable,
+}
+
+// Safety: the host's callbacks may be called from any thread.
+unsafe impl Send for HostApi {}
+unsafe impl Sync for HostApi {}
+
+impl HostApi {
+    /// Send a result for `sid`: the answer to a unary call, or one frame of
+    /// a stream, the last of which has a status other than `Ok`.
+    pub fn send(&self, sid: u64, status: NrStatus, data: &[u8]) {
+        unsafe {
+            ((*self.host_vtable).send_result)(self.host_ctx, sid, status, NrVec::from_slice(data));
+        }
+    }
+
+    /// Store `value` under `key` for `sid`. Fails with the host's reason, or
+    /// if the host has no extension table.
+    pub fn set_state(&self, sid: u64, key: &str, value: &[u8]) -> Result<(), String> {
+        let ext = unsafe { ((*self.host_vtable).get_host_ext)(self.host_ctx) };
+        if ext.is_null() {
+            return Err("host has no extension table".to_string());
+        }
+        let error = unsafe {
+            ((*ext).set_state)(
+                self.host_ctx,
+                sid,
+                NrStr::new(key),
+                NrBytes::from_slice(value),
+            )
+        };
+        match error.as_slice() {
+            [] => Ok(()),
+            reason => Err(String::from_utf8_lossy(reason).into_owned()),
+        }
+    }
+
+    /// The value stored under `key` for `sid`, if any.
+    pub fn state(&self, sid: u64, key: &str) -> Option<Vec<u8>> {
+        let ext = unsafe { ((*self.host_vtable).get_host_ext)(self.host_ctx) };
+        if ext.is_null() {
+            return None;
+        }
+        unsafe { (*ext).state(self.host_ctx, sid, key) }
+    }
+
+    /// Store `value` under `key` in `scope`, one of the `NR_SCOPE_*`
+    /// constants; `owner` is the SID for [`NR_SCOPE_SID`](crate::NR_SCOPE_SID)
+    /// and ignored otherwise.
+    pub fn set_state_scoped(
+        &self,
+        scope: u32,
+        owner: u64,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), String> {
+        let ext = unsafe { ((*self.host_vtable).get_host_ext)(self.host_ctx) };
+        if ext.is_null() {
+            return Err("host has no extension table".to_string());
+        }
+        let error = unsafe {
+            ((*ext).set_state_scoped)(
+                self.host_ctx,
+                scope,
+                owner,
+                NrStr::new(key),
+                NrBytes::from_slice(value),
+            )
+        };
+        match error.as_slice() {
+            [] => Ok(()),
+            reason => Err(String::from_utf8_lossy(reason).into_owned()),
+        }
+    }
+
+    /// The value stored under `key` in `scope`, if any.
+    pub fn state_scoped(&self, scope: u32, owner: u64, key: &str) -> Option<Vec<u8>> {
+        let ext = unsafe { ((*self.host_vtable).get_host_ext)(self.host_ctx) };
+        if ext.is_null() {
+            return None;
+        }
+        unsafe { (*ext).state_scoped(self.host_ctx, scope, owner, key) }
+    }
+
+    /// Write a response into a host-owned buffer of `size` bytes, as
+    /// [`NrHostExt::write_shared`](crate::NrHostExt::write_shared) does.
+    /// Answer with `Response::ok(shared::encode_shared_reply(handle))`.
+    pub fn write_shared(&self, size: usize, write: impl FnOnce(&mut [u8]) -> usize) -> Option<u64> {
+        let ext = unsafe { ((*self.host_vtable).get_host_ext)(self.host_ctx) };
+        if ext.is_null() {
+            return None;
+        }
+        unsafe { (*ext).write_shared(self.host_ctx, size, write) }
+    }
+
+    /// Whether the host has revoked the plugin's callbacks ahead of
+    /// `shutdown`. Background work should stop once it has.
+    pub fn is_revoked(&self) -> bool {
+        unsafe { (*self.host_vtable).is_revoked(self.host_ctx) }
+    }
+}
+
+/// One call to the plugin.
+#[derive(Debug)]
+pub struct CallContext<'a> {
+    entry: &'a str,
+    sid: u64,
+    payload: &'a [u8],
+    host: &'a HostApi,
+}
+
+impl<'a> CallContext<'a> {
+    /// The entry called.
+    pub fn entry(&self) -> &'a str {
+        self.entry
+    }
+
+    /// The call's SID, for answering it later through [`HostApi`].
+    pub fn sid(&self) -> u64 {
+        self.sid
+    }
+
+    /// The request payload. Only valid during `handle`; copy it to keep it.
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// The payload decoded as JSON.
+    #[cfg(feature = "serde")]
+    pub fn request<T: serde::de::DeserializeOwned>(&self) -> Result<T, crate::codec::CodecError> {
+        use crate::codec::Codec;
+        crate::codec::Json.decode(self.payload)
+    }
+
+    /// The host, for answering later.
+    pub fn host(&self) -> &'a HostApi {
+        self.host
+    }
+}
+
+/// What [`Plugin::handle`] answers a call with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    status: NrStatus,
+    data: Vec<u8>,
+    /// Whether the response is sent as the call's result, or `status` is
+    /// only returned from `handle`.
+    send: bool,
+}
+
+impl Response {
+    /// A `status` answer carrying `data`.
+    pub fn new(status: NrStatus, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            data: data.into(),
+            send: true,
+        }
+    }
+
+    /// An `Ok` answer.
+    pub fn ok(data: impl Into<Vec<u8>>) -> Self {
+        Self::new(NrStatus::Ok, data)
+    }
+
+    /// An `Err` answer carrying an error frame, which hosts surface as an
+    /// error with `code` and `message`.
+    pub fn error(code: u32, message: &str) -> Self {
+        Self::new(NrStatus::Err, crate::encode_error(code, message).into_vec())
+    }
+
+    /// An `Ok` answer with `value` encoded as JSON, or an error answer
+    /// (code 500) if it cannot be encoded.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::Serialize + ?Sized>(value: &T) -> Self {
+        use crate::codec::Codec;
+        match crate::codec::Json.encode(value) {
+            Ok(data) => Self::ok(data),
+            Err(error) => Self::error(500, &error.to_string()),
+        }
+    }
+
+    /// Refuse the call: `handle` returns `status` and nothing is sent, as
+    /// `define_plugin!` does with `Invalid` for an unknown entry.
+    pub fn reject(status: NrStatus) -> Self {
+        Self {
+            status,
+            data: Vec::new(),
+            send: false,
+        }
+    }
+
+    /// Nothing is sent now: the plugin answers through [`HostApi::send`],
+    /// from another thread or as the frames of a stream.
+    pub fn accepted() -> Self {
+        Self::reject(NrStatus::Accepted)
+    }
+
+    pub fn status(&self) -> NrStatus {
+        self.status
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// The instance of a [`Plugin`] a shared library exports, with its host.
+/// Declared by `impl_plugin!`.
+#[doc(hidden)]
+pub struct Slot<P>(RwLock<Option<(P, HostApi)>>);
+
+impl<P: Plugin> Slot<P> {
+    pub const fn new() -> Self {
+        Self(RwLock::new(None))
+    }
+
+    /// Create and initialize the plugin, keeping it if `init` succeeds.
+    ///
+    /// # Safety
+    ///
+    /// `host_ctx` and `host_vtable` must be the pointers the host passed to
+    /// `init`.
+    pub unsafe fn init(&self, host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
+        let host = HostApi {
+            host_ctx,
+            host_vtable,
+        };
+        let mut plugin = P::default();
+        let status = plugin.init(&host);
+        if status == NrStatus::Ok {
+            *self.0.write().unwrap_or_else(|e| e.into_inner()) = Some((plugin, host));
+        }
+        status
+    }
+
+    pub fn entries(&self) -> Vec<&'static str> {
+        let slot = self.0.read().unwrap_or_else(|e| e.into_inner());
+        slot.as_ref()
+            .map(|(plugin, _)| plugin.entries().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Run `handle` and send its response, unless it was not meant to be.
+    pub fn handle(&self, entry: &str, sid: u64, payload: NrBytes) -> NrStatus {
+        let slot = self.0.read().unwrap_or_else(|e| e.into_inner());
+        let Some((plugin, host)) = slot.as_ref() else {
+            return NrStatus::Invalid;
+        };
+        let ctx = CallContext {
+            entry,
+            sid,
+            payload: payload.as_slice(),
+            host,
+        };
+        let response = plugin.handle(&ctx);
+        if !response.send {
+            return response.status;
+        }
+        host.send(sid, response.status, &response.data);
+        NrStatus::Ok
+    }
+
+    /// Shut the plugin down and drop it.
+    pub fn shutdown(&self) {
+        let plugin = self.0.write().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some((mut plugin, _)) = plugin {
+            plugin.shutdown();
+        }
+    }
+}
+
+impl<P: Plugin> Default for Slot<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Export a [`Plugin`] as this library's plugin. Generates
+/// `nylon_ring_get_plugin_v1` and the entry points, as `define_plugin!`
+/// does, around one instance created with `Default`.
+#[macro_export]
+macro_rules! impl_plugin {
+    ($plugin:ty) => {
+        static NR_PLUGIN: $crate::plugin::Slot<$plugin> = $crate::plugin::Slot::new();
+
+        unsafe fn nr_plugin_init(
+            host_ctx: *mut std::ffi::c_void,
+            host_vtable: *const $crate::NrHostVTable,
+        ) -> $crate::NrStatus {
+            unsafe { NR_PLUGIN.init(host_ctx, host_vtable) }
+        }
+
+        fn nr_plugin_shutdown() {
+            NR_PLUGIN.shutdown()
+        }
+
+        $crate::define_plugin! {
+            @plugin
+            init: nr_plugin_init,
+            shutdown: nr_plugin_shutdown,
+            entry_list: NR_PLUGIN.entries(),
+            dispatch: |entry, sid, payload| NR_PLUGIN.handle(entry, sid, payload)
+        }
+    };
+}
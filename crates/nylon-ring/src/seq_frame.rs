@@ -0,0 +1,53 @@
+//! Sequence-numbered stream frames.
+//!
+//! A plugin that may send a stream frame more than once, for example when it
+//! retries after a transport hiccup, can number its frames so a host reading
+//! the stream with deduplication delivers each one once. The frame is
+//! [`SEQ_FRAME_MAGIC`], the sequence number as a little-endian `u64`, then
+//! the data. Hosts that do not deduplicate pass the frame on as is.
+
+use crate::NrVec;
+use alloc::vec::Vec;
+
+/// First bytes of a sequence-numbered frame.
+pub const SEQ_FRAME_MAGIC: [u8; 4] = *b"NRQ1";
+
+const HEADER_LEN: usize = SEQ_FRAME_MAGIC.len() + 8;
+
+/// Encode `data` as frame `seq` of a stream.
+pub fn encode_seq_frame(seq: u64, data: &[u8]) -> NrVec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + data.len());
+    frame.extend_from_slice(&SEQ_FRAME_MAGIC);
+    frame.extend_from_slice(&seq.to_le_bytes());
+    frame.extend_from_slice(data);
+    NrVec::from_vec(frame)
+}
+
+/// The sequence number and data of a frame. `None` if `payload` is not a
+/// sequence-numbered frame.
+pub fn decode_seq_frame(payload: &[u8]) -> Option<(u64, &[u8])> {
+    let rest = payload.strip_prefix(&SEQ_FRAME_MAGIC)?;
+    let (seq, data) = rest.split_first_chunk::<8>()?;
+    Some((u64::from_le_bytes(*seq), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_frame_round_trip() {
+        let frame = encode_seq_frame(42, b"tick");
+        assert_eq!(decode_seq_frame(frame.as_slice()), Some((42, &b"tick"[..])));
+
+        let empty = encode_seq_frame(u64::MAX, b"");
+        assert_eq!(
+            decode_seq_frame(empty.as_slice()),
+            Some((u64::MAX, &b""[..]))
+        );
+
+        assert_eq!(decode_seq_frame(b"plain frame"), None);
+        assert_eq!(decode_seq_frame(b"NRQ1\x01\x00"), None);
+        assert_eq!(decode_seq_frame(b""), None);
+    }
+}
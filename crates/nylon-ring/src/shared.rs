@@ -0,0 +1,156 @@
+//! Large payloads without copies.
+//!
+//! A host calling with `call_response_shared` passes a request above its
+//! threshold as a shared request: instead of the body, the payload is a
+//! header pointing at the body, with a host-owned response buffer of the
+//! same size already acquired for the plugin. The plugin writes its response
+//! into that buffer, or into one from [`NrHostExt::buf_acquire`] when it
+//! needs another size, commits it with [`NrHostExt::buf_commit`], and sends
+//! only [`encode_shared_reply`] of the buffer's handle. Neither side copies
+//! the body or allocates for the response.
+//!
+//! ```
+//! use nylon_ring::{NrBytes, NrHostExt, NrStatus, shared};
+//! use std::ffi::c_void;
+//!
+//! /// The reply to send for a shared request, with the body uppercased.
+//! unsafe fn reply_upper(
+//!     ext: &NrHostExt,
+//!     host_ctx: *mut c_void,
+//!     payload: NrBytes,
+//! ) -> Option<[u8; shared::NR_SHARED_REPLY_LEN]> {
+//!     let request = unsafe { shared::decode_shared_request(payload.as_slice()) }?;
+//!     let out = unsafe { request.response_buf() };
+//!     for (out, byte) in out.iter_mut().zip(request.body) {
+//!         *out = byte.to_ascii_uppercase();
+//!     }
+//!     let len = request.body.len() as u64;
+//!     match unsafe { (ext.buf_commit)(host_ctx, request.handle, len) } {
+//!         NrStatus::Ok => Some(shared::encode_shared_reply(request.handle)),
+//!         _ => None,
+//!     }
+//! }
+//! ```
+//!
+//! The body and the response buffer of a shared request are only valid
+//! during `handle`, like any payload. A plugin that answers later acquires
+//! a buffer of its own.
+//!
+//! [`NrHostExt::buf_acquire`]: crate::NrHostExt::buf_acquire
+//! [`NrHostExt::buf_commit`]: crate::NrHostExt::buf_commit
+
+/// First bytes of a shared request or reply.
+pub const NR_SHARED_MAGIC: [u8; 8] = *b"NRSHARED";
+
+/// Length of a shared reply: the magic bytes and a little-endian `u64`
+/// handle.
+pub const NR_SHARED_REPLY_LEN: usize = 16;
+
+/// Length of a shared request: the magic bytes, then the response buffer's
+/// handle, the body's address and length, and the response buffer's
+/// address, each a little-endian `u64`.
+pub const NR_SHARED_REQUEST_LEN: usize = 40;
+
+/// A request passed by reference, from [`decode_shared_request`].
+#[derive(Debug, Clone, Copy)]
+pub struct SharedRequest<'a> {
+    /// The response buffer, to commit and reply with.
+    pub handle: u64,
+    /// The request body.
+    pub body: &'a [u8],
+    response: *mut u8,
+}
+
+impl<'a> SharedRequest<'a> {
+    /// The response buffer, with room for as many bytes as the body. Its
+    /// contents are unspecified.
+    ///
+    /// # Safety
+    ///
+    /// Only valid during `handle`, and until the buffer is committed. Take
+    /// it once: two slices would alias.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn response_buf(&self) -> &'a mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.response, self.body.len()) }
+    }
+}
+
+fn field(data: &[u8], index: usize) -> u64 {
+    let start = 8 + index * 8;
+    u64::from_le_bytes(data[start..start + 8].try_into().expect("8 bytes"))
+}
+
+/// Build the payload of a shared request. For hosts.
+pub fn encode_shared_request(
+    handle: u64,
+    body: &[u8],
+    response: *mut u8,
+) -> [u8; NR_SHARED_REQUEST_LEN] {
+    let mut request = [0; NR_SHARED_REQUEST_LEN];
+    request[..8].copy_from_slice(&NR_SHARED_MAGIC);
+    request[8..16].copy_from_slice(&handle.to_le_bytes());
+    request[16..24].copy_from_slice(&(body.as_ptr() as u64).to_le_bytes());
+    request[24..32].copy_from_slice(&(body.len() as u64).to_le_bytes());
+    request[32..].copy_from_slice(&(response as u64).to_le_bytes());
+    request
+}
+
+/// The shared request `payload` holds, or `None` if it is an ordinary one.
+///
+/// # Safety
+///
+/// A payload with the magic bytes must be a shared request from the host,
+/// for the call being handled.
+pub unsafe fn decode_shared_request(payload: &[u8]) -> Option<SharedRequest<'_>> {
+    if payload.len() != NR_SHARED_REQUEST_LEN || payload[..8] != NR_SHARED_MAGIC {
+        return None;
+    }
+    let (body, len) = (field(payload, 1) as *const u8, field(payload, 2) as usize);
+    Some(SharedRequest {
+        handle: field(payload, 0),
+        body: unsafe { core::slice::from_raw_parts(body, len) },
+        response: field(payload, 3) as *mut u8,
+    })
+}
+
+/// A reply that hands the host the committed buffer `handle`.
+pub fn encode_shared_reply(handle: u64) -> [u8; NR_SHARED_REPLY_LEN] {
+    let mut reply = [0; NR_SHARED_REPLY_LEN];
+    reply[..8].copy_from_slice(&NR_SHARED_MAGIC);
+    reply[8..].copy_from_slice(&handle.to_le_bytes());
+    reply
+}
+
+/// The handle a shared reply carries, or `None` if `data` is an ordinary
+/// response.
+pub fn decode_shared_reply(data: &[u8]) -> Option<u64> {
+    if data.len() != NR_SHARED_REPLY_LEN || data[..8] != NR_SHARED_MAGIC {
+        return None;
+    }
+    Some(field(data, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_frames() {
+        let reply = encode_shared_reply(0x0102_0304_0506_0708);
+        assert_eq!(decode_shared_reply(&reply), Some(0x0102_0304_0506_0708));
+        assert_eq!(decode_shared_reply(b"NRSHARED"), None);
+        assert_eq!(decode_shared_reply(b"plain response!!"), None);
+
+        let body = b"body".to_vec();
+        let mut response = [0u8; 4];
+        let request = encode_shared_request(7, &body, response.as_mut_ptr());
+        assert_eq!(decode_shared_reply(&request), None);
+        let decoded = unsafe { decode_shared_request(&request) }.unwrap();
+        assert_eq!((decoded.handle, decoded.body), (7, &b"body"[..]));
+        unsafe { decoded.response_buf() }.copy_from_slice(b"BODY");
+        assert_eq!(&response, b"BODY");
+
+        assert!(unsafe { decode_shared_request(&reply) }.is_none());
+        assert!(unsafe { decode_shared_request(&[0; NR_SHARED_REQUEST_LEN]) }.is_none());
+    }
+}
@@ -0,0 +1,33 @@
+//! The checked-in `include/nylon_ring.h` is what `nylon-ring-abigen`
+//! generates. `nylon-ring-host`'s `c_plugin` test compiles a plugin with it.
+
+use std::path::Path;
+use std::process::Command;
+
+fn abigen(args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_nylon-ring-abigen"))
+        .args(args)
+        .status()
+        .expect("failed to run nylon-ring-abigen")
+        .success()
+}
+
+#[test]
+fn test_checked_in_header_is_current() {
+    assert!(
+        abigen(&["--check"]),
+        "include/nylon_ring.h is stale; run `cargo run -p nylon-ring --bin nylon-ring-abigen`"
+    );
+}
+
+#[test]
+fn test_check_fails_on_a_stale_header() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("nylon_ring.h");
+    let path = path.to_str().unwrap();
+    assert!(abigen(&[path]));
+    assert!(abigen(&["--check", path]));
+
+    let header = std::fs::read_to_string(path).unwrap();
+    std::fs::write(path, header.replace("uint32_t len;", "uint64_t len;")).unwrap();
+    assert!(!abigen(&["--check", path]));
+}
@@ -0,0 +1,5 @@
+#[test]
+fn define_plugin_signature_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
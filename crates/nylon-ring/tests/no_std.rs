@@ -0,0 +1,48 @@
+//! The crate builds without `std`.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Targets without `std` the crate is checked for when their standard
+/// library is installed.
+const NO_STD_TARGETS: &[&str] = &["thumbv7em-none-eabihf", "wasm32-unknown-unknown"];
+
+/// `cargo check` the crate without default features, for `target` or the
+/// host. On the host, `#![no_std]` still rejects any use of `std`.
+fn check(target: Option<&str>) {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-std");
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args(["check", "--quiet", "-p", "nylon-ring", "--lib"])
+        .arg("--no-default-features")
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(env!("CARGO_MANIFEST_DIR"));
+    if let Some(target) = target {
+        cargo.args(["--target", target]);
+    }
+    let status = cargo.status().expect("failed to run cargo");
+    assert!(
+        status.success(),
+        "nylon-ring does not build without std for {target:?}"
+    );
+}
+
+fn installed(target: &str) -> bool {
+    let Ok(output) = Command::new("rustc").args(["--print", "sysroot"]).output() else {
+        return false;
+    };
+    let sysroot = String::from_utf8_lossy(&output.stdout);
+    Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(target)
+        .is_dir()
+}
+
+#[test]
+fn test_builds_without_std() {
+    check(None);
+    for target in NO_STD_TARGETS.iter().filter(|target| installed(target)) {
+        check(Some(target));
+    }
+}
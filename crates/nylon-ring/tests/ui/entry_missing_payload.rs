@@ -0,0 +1,22 @@
+use nylon_ring::{define_plugin, NrHostVTable, NrStatus};
+use std::ffi::c_void;
+
+unsafe fn init(_ctx: *mut c_void, _vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_echo(_sid: u64) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "echo" => handle_echo,
+    }
+}
+
+fn main() {}
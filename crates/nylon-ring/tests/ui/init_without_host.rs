@@ -0,0 +1,24 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus};
+use std::ffi::c_void;
+
+fn init() -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    }
+}
+
+fn main() {
+    let _ = (std::ptr::null_mut::<c_void>(), std::ptr::null::<NrHostVTable>());
+}
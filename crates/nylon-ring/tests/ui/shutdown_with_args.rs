@@ -0,0 +1,22 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus};
+use std::ffi::c_void;
+
+unsafe fn init(_ctx: *mut c_void, _vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown(_reason: u32) {}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus};
+use std::ffi::c_void;
+
+unsafe fn init(_ctx: *mut c_void, _vtable: *const NrHostVTable) -> NrStatus {
+    NrStatus::Ok
+}
+
+fn shutdown() {}
+
+unsafe fn handle_ok(_sid: u64, _payload: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_data(_sid: u64, _data: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+unsafe fn stream_close(_sid: u64, _data: NrBytes) -> NrStatus {
+    NrStatus::Ok
+}
+
+define_plugin! {
+    init: init,
+    shutdown: shutdown,
+    entries: {
+        "ok" => handle_ok,
+    },
+    stream_handlers: {
+        data: stream_data,
+        close: stream_close,
+    }
+}
+
+fn main() {}
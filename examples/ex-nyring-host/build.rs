@@ -0,0 +1,12 @@
+//! Generates the typed client for the example plugin from `plugin.json`.
+
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=plugin.json");
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let out = Path::new(&out_dir).join("example_plugin.rs");
+    if let Err(e) = nylon_ring_codegen::generate_file("plugin.json", out) {
+        panic!("failed to generate the example plugin client: {e}");
+    }
+}
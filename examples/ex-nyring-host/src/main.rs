@@ -1,9 +1,41 @@
 mod benchmark;
+mod orders;
 
-use nylon_ring_host::NylonRingHost;
+/// The typed client `build.rs` generates from `plugin.json`.
+mod example_plugin {
+    include!(concat!(env!("OUT_DIR"), "/example_plugin.rs"));
+}
+
+use log::kv::Key;
+use nylon_ring_host::{plugin_artifact_path, NylonRingHost, Profile};
+
+/// Prints log records, including those plugins send with `nr_log!`, which
+/// carry the plugin's name.
+struct StdoutLogger;
+
+impl log::Log for StdoutLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.key_values().get(Key::from("plugin")) {
+            Some(plugin) => println!("[{plugin}] {}", record.args()),
+            None => println!("[host] {}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    log::set_logger(&StdoutLogger).expect("no other logger is set");
+    log::set_max_level(log::LevelFilter::Info);
+
     println!("=== Nylon Ring Demo ===\n");
 
     // Build the plugin first
@@ -23,23 +55,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Load the plugin
-    #[cfg(not(debug_assertions))]
-    let plugin_path = if cfg!(target_os = "macos") {
-        "target/release/libex_nyring_plugin.dylib"
-    } else if cfg!(target_os = "windows") {
-        "target/release/ex_nyring_plugin.dll"
-    } else {
-        "target/release/libex_nyring_plugin.so"
-    };
-    #[cfg(debug_assertions)]
-    let plugin_path = if cfg!(target_os = "macos") {
-        "target/debug/libex_nyring_plugin.dylib"
-    } else if cfg!(target_os = "windows") {
-        "target/debug/ex_nyring_plugin.dll"
-    } else {
-        "target/debug/libex_nyring_plugin.so"
-    };
-
+    let plugin_path = plugin_artifact_path("ex-nyring-plugin", Profile::Release)?;
+    let plugin_path = plugin_path.to_str().ok_or("plugin path is not UTF-8")?;
     println!("Loading plugin from: {}\n", plugin_path);
     let mut host = NylonRingHost::new();
     host.load("default", plugin_path)
@@ -147,16 +164,61 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Demo 6: Multiple rapid calls (showing Robustness)
     println!("--- Demo 6: Multiple Rapid Calls ---");
     println!("  Path: Testing Sharded DashMap under load");
-    println!("  → Running 10 sequential async calls");
+    println!("  → Running 10 concurrent async calls with call_response_many()");
     println!("  → Verifies map insertion/removal consistency");
     let now = std::time::Instant::now();
-    for i in 1..=10 {
-        let message = format!("Message #{}", i);
-        let (status, _) = plugin.call_response("echo", message.as_bytes()).await?;
-        println!("  Call {}: {:?}", i, status);
+    let messages: Vec<String> = (1..=10).map(|i| format!("Message #{}", i)).collect();
+    let payloads: Vec<&[u8]> = messages.iter().map(|m| m.as_bytes()).collect();
+    for (i, result) in plugin
+        .call_response_many("echo", &payloads)
+        .await
+        .into_iter()
+        .enumerate()
+    {
+        let (status, _) = result?;
+        println!("  Call {}: {:?}", i + 1, status);
     }
     println!("  10 calls completed in {:?}\n", now.elapsed());
 
+    // Demo 7: Request-scoped state, both ways
+    println!("--- Demo 7: call_response_with_state() ---");
+    println!("  Path: STANDARD ASYNC PATH with per-SID state");
+    println!("  → Host seeds state under the call's SID before handle runs");
+    println!("  → Plugin reads it and stores its own with get_state/set_state");
+    println!("  → Host gets the final state back; it is removed afterwards");
+    let seed: &[(&str, &[u8])] = &[("user", b"ring"), ("visits", b"41")];
+    let (status, response, state) = plugin.call_response_with_state("state", b"", seed).await?;
+    println!("  Status: {:?}", status);
+    println!("  Response: {}", String::from_utf8_lossy(&response));
+    let mut keys: Vec<_> = state.iter().collect();
+    keys.sort();
+    for (key, value) in keys {
+        println!("  State {key} = {}", String::from_utf8_lossy(value));
+    }
+    println!();
+
+    // Demo 8: A generated typed client
+    println!("--- Demo 8: generated ExamplePlugin client ---");
+    println!("  Path: call_typed() / call_typed_stream() behind typed methods");
+    println!("  → build.rs generates the client from plugin.json");
+    println!("  → The client keeps the plugin's handle; no lookup per call");
+    let client =
+        example_plugin::ExamplePlugin::from_host(&host, "default").expect("Plugin not found");
+    let order = orders::Order {
+        id: 7,
+        items: vec!["tea".into(), "bread".into(), "jam".into()],
+    };
+    let receipt = client.order(&order).await?;
+    println!(
+        "  Receipt #{}: {} items, {:?}",
+        receipt.id, receipt.count, receipt.items
+    );
+    let mut items = client.order_items(&order).await?;
+    while let Some(item) = items.next().await {
+        println!("  Item: {}", item?);
+    }
+    println!();
+
     // Fire-and-Forget Benchmark
     benchmark::run_fire_and_forget_benchmark(plugin.clone()).await;
 
@@ -173,5 +235,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  3. call()               → FIRE-AND-FORGET (No Map)");
     println!("  4. async handler        → Verified Async Correctness");
     println!("  5. call_stream()        → STREAMING (mpsc + Map)");
+    println!("  7. with_state()         → STANDARD ASYNC + per-SID state");
+    println!("  8. generated client     → TYPED (call_typed + call_typed_stream)");
     Ok(())
 }
@@ -0,0 +1,16 @@
+//! Requests and responses of the example plugin's `order` entries.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct Order {
+    pub id: u32,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Receipt {
+    pub id: u32,
+    pub count: usize,
+    pub items: Vec<String>,
+}
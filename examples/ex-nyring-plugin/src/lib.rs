@@ -1,18 +1,31 @@
-use nylon_ring::{define_plugin, NrBytes, NrHostVTable, NrStatus, NrVec};
+use nylon_ring::codec::{nr_decode_request, nr_encode_response, Codec, Json, TypedSink};
+use nylon_ring::{
+    define_plugin, nr_async_reply, nr_log, shared, NrBytes, NrHostVTable, NrLogLevel, NrRequest,
+    NrStatus, NrString, NrVec,
+};
+use serde::{Deserialize, Serialize};
 use std::ffi::c_void;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use std::sync::OnceLock;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
-// Global state to store host context and vtable
-static mut HOST_CTX: *mut c_void = std::ptr::null_mut();
-static mut HOST_VTABLE: *const NrHostVTable = std::ptr::null();
+// Host context and vtable, set once in `init` and read from any thread
+static HOST_CTX: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static HOST_VTABLE: AtomicPtr<NrHostVTable> = AtomicPtr::new(std::ptr::null_mut());
 
 // Tokio runtime for async operations
 static TOKIO_RT: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 
+// Runtime tasks that still talk to the host; shutdown waits for them
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// Events received through `benchmark_batch`
+static BATCHED_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
 thread_local! {
     static ASYNC_Q_BENCHMARK: once_cell::sync::OnceCell<mpsc::UnboundedSender<(u64, NrBytes)>> = const { once_cell::sync::OnceCell::new() };
-    static ASYNC_Q: once_cell::sync::OnceCell<mpsc::UnboundedSender<(u64, NrBytes)>> = const { once_cell::sync::OnceCell::new() };
 }
 
 fn get_runtime() -> &'static tokio::runtime::Runtime {
@@ -28,49 +41,66 @@ fn get_runtime() -> &'static tokio::runtime::Runtime {
     })
 }
 
+// The host vtable. Only valid once `init` has run, which the host
+// guarantees before calling any handler.
+fn host_vtable() -> &'static NrHostVTable {
+    unsafe { &*HOST_VTABLE.load(Ordering::Acquire) }
+}
+
 #[inline(always)]
 pub fn send_result(sid: u64, status: NrStatus, data: nylon_ring::NrVec<u8>) {
-    unsafe {
-        let f = (*HOST_VTABLE).send_result;
-        f(HOST_CTX, sid, status, data);
-    }
+    unsafe { (host_vtable().send_result)(HOST_CTX.load(Ordering::Acquire), sid, status, data) }
+}
+
+// Whether the host has revoked our callbacks because we are being shut down
+fn is_revoked() -> bool {
+    unsafe { host_vtable().is_revoked(HOST_CTX.load(Ordering::Acquire)) }
 }
 
 // Initialize the plugin
 unsafe fn init(host_ctx: *mut c_void, host_vtable: *const NrHostVTable) -> NrStatus {
-    println!("[Plugin] Initialized!");
+    nr_log!(NrLogLevel::Info, "initialized");
     // Initialize Tokio runtime
     let _ = get_runtime();
-    println!("[Plugin] Tokio runtime initialized with 4 worker threads");
-    HOST_CTX = host_ctx;
-    HOST_VTABLE = host_vtable;
+    nr_log!(NrLogLevel::Debug, "Tokio runtime initialized");
+    HOST_CTX.store(host_ctx, Ordering::Release);
+    HOST_VTABLE.store(host_vtable as *mut _, Ordering::Release);
 
     async_worker_benchmark();
-    async_worker();
     NrStatus::Ok
 }
 
 // Shutdown the plugin
 fn shutdown() {
-    println!("[Plugin] Shutting down!");
+    nr_log!(NrLogLevel::Info, "shutting down");
+
+    // Let runtime tasks finish talking to the host. They stop on their own
+    // once the host has revoked our callbacks.
+    while IN_FLIGHT.load(Ordering::Acquire) > 0 {
+        std::thread::sleep(Duration::from_millis(1));
+    }
 }
 
 // Echo handler - simply returns the input data
 unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
     let data = payload.as_slice();
     let text_str = String::from_utf8_lossy(data);
-    println!("[Plugin] Echo received: {}", text_str);
+    nr_log!(NrLogLevel::Debug, "echo received: {text_str}");
 
     // Modify the text
-    let new_text = format!("{}, Nylon Ring!", text_str);
+    let mut new_text = NrString::from(text_str.as_ref());
+    new_text.push_str(", Nylon Ring!");
 
-    // Convert to NrVec (Zero Copy transfer)
-    let vec_bytes = new_text.into_bytes();
-    let nr_vec = NrVec::from_vec(vec_bytes);
+    // Send response back to host (transfer ownership, no copy)
+    send_result(sid, NrStatus::Ok, new_text.into_bytes());
 
-    // Send response back to host (transfer ownership)
-    send_result(sid, NrStatus::Ok, nr_vec);
+    NrStatus::Ok
+}
 
+// Route handler - answers with the method and path of the request
+unsafe fn handle_route(sid: u64, req: &NrRequest, _body: NrBytes) -> NrStatus {
+    let line = format!("{} {}", req.method.as_str(), req.path.as_str());
+    send_result(sid, NrStatus::Ok, NrVec::from_slice(line.as_bytes()));
     NrStatus::Ok
 }
 
@@ -78,7 +108,10 @@ unsafe fn handle_echo(sid: u64, payload: NrBytes) -> NrStatus {
 unsafe fn handle_uppercase(sid: u64, payload: NrBytes) -> NrStatus {
     let data = payload.as_slice();
     let text = String::from_utf8_lossy(data).to_uppercase();
-    println!("[Plugin] Uppercase received, sending back: {}", text);
+    nr_log!(
+        NrLogLevel::Debug,
+        "uppercase received, sending back: {text}"
+    );
 
     // Send response back to host
     let nr_vec = NrVec::from_string(text);
@@ -87,15 +120,153 @@ unsafe fn handle_uppercase(sid: u64, payload: NrBytes) -> NrStatus {
     NrStatus::Ok
 }
 
+// Fail handler - rejects the call with a structured error
+unsafe fn handle_fail(sid: u64, payload: NrBytes) -> NrStatus {
+    let reason = String::from_utf8_lossy(payload.as_slice());
+    nr_log!(NrLogLevel::Warn, "failing on request: {reason}");
+
+    send_result(sid, NrStatus::Err, nylon_ring::encode_error(422, &reason));
+
+    NrStatus::Ok
+}
+
+#[derive(Deserialize)]
+struct Order {
+    id: u32,
+    items: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Receipt {
+    id: u32,
+    count: usize,
+    items: Vec<String>,
+}
+
+// Order handler - answers a typed order with a receipt, in the codec the
+// request was sent with
+unsafe fn handle_order(sid: u64, payload: NrBytes) -> NrStatus {
+    let (order, codec) = match nr_decode_request::<Order>(payload.as_slice()) {
+        Ok(request) => request,
+        Err(e) => {
+            send_result(
+                sid,
+                NrStatus::Err,
+                nylon_ring::encode_error(400, &e.to_string()),
+            );
+            return NrStatus::Ok;
+        }
+    };
+    let mut items = order.items;
+    items.sort();
+    let receipt = Receipt {
+        id: order.id,
+        count: items.len(),
+        items,
+    };
+    match nr_encode_response(codec, &receipt) {
+        Ok(bytes) => send_result(sid, NrStatus::Ok, bytes),
+        Err(e) => send_result(
+            sid,
+            NrStatus::Err,
+            nylon_ring::encode_error(500, &e.to_string()),
+        ),
+    }
+    NrStatus::Ok
+}
+
+// Order items handler - streams the items of an order, sorted, one JSON
+// string per frame
+unsafe fn handle_order_items(sid: u64, payload: NrBytes) -> NrStatus {
+    let Ok(order) = Json.decode::<Order>(payload.as_slice()) else {
+        return NrStatus::Invalid;
+    };
+    let sink = TypedSink::<String>::new(
+        HOST_CTX.load(Ordering::Acquire),
+        HOST_VTABLE.load(Ordering::Acquire),
+        sid,
+        Json,
+    );
+    let mut items = order.items;
+    items.sort();
+    for item in &items {
+        if sink.send(item).is_err() {
+            break;
+        }
+    }
+    sink.end();
+    NrStatus::Ok
+}
+
+// Mirror handler - answers with the request body reversed. A shared request
+// is answered in the host-owned buffer that comes with it, without
+// allocating an NrVec
+unsafe fn handle_mirror(sid: u64, payload: NrBytes) -> NrStatus {
+    let Some(request) = (unsafe { shared::decode_shared_request(payload.as_slice()) }) else {
+        let reversed = payload.as_slice().iter().rev().copied().collect();
+        send_result(sid, NrStatus::Ok, NrVec::from_vec(reversed));
+        return NrStatus::Ok;
+    };
+    let out = unsafe { request.response_buf() };
+    for (out, byte) in out.iter_mut().zip(request.body.iter().rev()) {
+        *out = *byte;
+    }
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    let ext = unsafe { &*(host_vtable().get_host_ext)(host_ctx) };
+    let len = request.body.len() as u64;
+    if unsafe { (ext.buf_commit)(host_ctx, request.handle, len) } != NrStatus::Ok {
+        return NrStatus::Err;
+    }
+    let reply = shared::encode_shared_reply(request.handle);
+    send_result(sid, NrStatus::Ok, NrVec::from_slice(&reply));
+    NrStatus::Ok
+}
+
+// State handler - greets the `user` the host stored for the call, and
+// leaves the greeting and a count of visits in the call's state for the host
+// to read back
+unsafe fn handle_state(sid: u64, _payload: NrBytes) -> NrStatus {
+    let host_ctx = HOST_CTX.load(Ordering::Acquire);
+    let ext = unsafe { &*(host_vtable().get_host_ext)(host_ctx) };
+    let user = unsafe { ext.state(host_ctx, sid, "user") };
+    let user = user
+        .as_deref()
+        .map_or("anonymous".into(), String::from_utf8_lossy);
+    let visits = unsafe { ext.state(host_ctx, sid, "visits") }
+        .and_then(|visits| String::from_utf8(visits).ok()?.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let greeting = format!("hello, {user}");
+    let visits = (visits + 1).to_string();
+    for (key, value) in [
+        ("greeting", greeting.as_bytes()),
+        ("visits", visits.as_bytes()),
+    ] {
+        let error = unsafe {
+            (ext.set_state)(
+                host_ctx,
+                sid,
+                nylon_ring::NrStr::new(key),
+                NrBytes::from_slice(value),
+            )
+        };
+        if !error.as_slice().is_empty() {
+            return NrStatus::Err;
+        }
+    }
+    send_result(sid, NrStatus::Ok, NrVec::from_string(greeting));
+    NrStatus::Ok
+}
+
 // Stream handler - sends multiple responses
 unsafe fn handle_stream(sid: u64, _payload: NrBytes) -> NrStatus {
-    println!("[Plugin] Stream handler started for SID: {}", sid);
+    nr_log!(NrLogLevel::Debug, "stream handler started for SID {sid}");
 
     // Send 5 frames
     for i in 1..=5 {
-        let message = format!("Frame {}/5", i);
-        let nr_vec = NrVec::from_string(message);
-        send_result(sid, NrStatus::Ok, nr_vec);
+        let mut message = NrString::new();
+        let _ = write!(message, "Frame {}/5", i);
+        send_result(sid, NrStatus::Ok, message.into_bytes());
     }
 
     // Send final frame with StreamEnd status
@@ -120,40 +291,53 @@ pub fn async_worker_benchmark() {
     });
 }
 
-pub fn async_worker() {
-    let (tx, mut rx) = mpsc::unbounded_channel::<(u64, NrBytes)>();
-    ASYNC_Q.with(|cell| {
-        cell.set(tx).ok();
-    });
+// Async handler - answers from a task on the Tokio runtime. `nr_async_reply`
+// tells the host the answer comes later, so even `call_response_fast`
+// waits for it, and turns a panicking task into an `Err` answer.
+unsafe fn handle_async(sid: u64, payload: NrBytes) -> NrStatus {
+    // The payload is only valid during `handle`; the task owns a copy
+    let text = String::from_utf8_lossy(payload.as_slice()).into_owned();
+    nr_log!(
+        NrLogLevel::Debug,
+        "async handler started for SID {sid} with: {text}"
+    );
+    nr_async_reply(
+        get_runtime().handle(),
+        HOST_CTX.load(Ordering::Acquire),
+        HOST_VTABLE.load(Ordering::Acquire),
+        sid,
+        async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            nr_log!(NrLogLevel::Debug, "async task completed");
+            let mut result = NrString::new();
+            let _ = write!(result, "Async result: {} (processed after 100ms)", text);
+            (NrStatus::Ok, result.into_bytes())
+        },
+    )
+}
 
+// Ticker handler - streams a tick every 10ms from the Tokio runtime until the
+// host revokes our callbacks
+unsafe fn handle_ticker(sid: u64, _payload: NrBytes) -> NrStatus {
+    IN_FLIGHT.fetch_add(1, Ordering::AcqRel);
     get_runtime().spawn(async move {
-        while let Some((sid, payload)) = rx.recv().await {
-            let data = payload.as_slice();
-            let text = String::from_utf8_lossy(data).to_string();
-            println!(
-                "[Plugin] Async handler started for SID: {} with: {}",
-                sid, text
+        let mut tick = 0u64;
+        while !is_revoked() {
+            tick += 1;
+            send_result(
+                sid,
+                NrStatus::Ok,
+                NrVec::from_string(format!("tick {tick}")),
             );
-            println!("[Plugin] Spawning async task...");
-            println!("[Plugin] Async task running on Tokio runtime...");
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            println!("[Plugin] Async task completed!");
-            let result = format!("Async result: {} (processed after 100ms)", text);
-            let nr_vec = NrVec::from_string(result);
-            send_result(sid, NrStatus::Ok, nr_vec);
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        nr_log!(
+            NrLogLevel::Info,
+            "ticker for SID {sid} stopped after {tick} ticks"
+        );
+        IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
     });
-}
-
-// Async handler - demonstrates async operations using Tokio runtime
-unsafe fn handle_async(sid: u64, payload: NrBytes) -> NrStatus {
-    ASYNC_Q.with(|cell| {
-        if let Some(tx) = cell.get() {
-            let _ = tx.send((sid, payload));
-            return NrStatus::Ok;
-        }
-        NrStatus::Err
-    })
+    NrStatus::Accepted
 }
 
 // benchmark - fast handler for benchmarking
@@ -161,7 +345,7 @@ unsafe fn handle_benchmark(sid: u64, payload: NrBytes) -> NrStatus {
     ASYNC_Q_BENCHMARK.with(|cell| {
         if let Some(tx) = cell.get() {
             let _ = tx.send((sid, payload));
-            return NrStatus::Ok;
+            return NrStatus::Accepted;
         }
         NrStatus::Err
     })
@@ -172,6 +356,13 @@ unsafe fn handle_benchmark_without_response(_sid: u64, _payload: NrBytes) -> NrS
     NrStatus::Ok
 }
 
+// benchmark - counts the events in a batch sent by a host `Notifier`
+unsafe fn handle_benchmark_batch(_sid: u64, payload: NrBytes) -> NrStatus {
+    let events = nylon_ring::batch_items(payload.as_slice()).count();
+    BATCHED_EVENTS.fetch_add(events, Ordering::Relaxed);
+    NrStatus::Ok
+}
+
 // Define the plugin with its entry points
 define_plugin! {
     init: init,
@@ -179,9 +370,19 @@ define_plugin! {
     entries: {
         "echo" => handle_echo,
         "uppercase" => handle_uppercase,
+        "fail" => handle_fail,
+        "mirror" => handle_mirror,
+        "order" => handle_order,
+        "order_items" => handle_order_items,
+        "state" => handle_state,
         "stream" => handle_stream,
         "async" => handle_async,
+        "ticker" => handle_ticker,
         "benchmark" => handle_benchmark,
         "benchmark_without_response" => handle_benchmark_without_response,
+        "benchmark_batch" => handle_benchmark_batch,
+    },
+    req_entries: {
+        "route" => handle_route,
     }
 }